@@ -0,0 +1,51 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::path::PathBuf;
+
+/// The crates that analyze packages during publishing. Their exact versions
+/// are exposed to the API, so that publish failures can be reproduced with the
+/// same versions.
+const ANALYSIS_CRATES: &[(&str, &str)] = &[
+  ("deno_ast", "DENO_AST_VERSION"),
+  ("deno_graph", "DENO_GRAPH_VERSION"),
+  ("deno_doc", "DENO_DOC_VERSION"),
+];
+
+fn main() {
+  let manifest_dir =
+    PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+  // The lockfile is next to the manifest when the API is built on its own, and
+  // in the workspace root otherwise.
+  let lockfile = [
+    manifest_dir.join("Cargo.lock"),
+    manifest_dir.join("../Cargo.lock"),
+  ]
+  .into_iter()
+  .find(|path| path.exists());
+
+  let lockfile = match lockfile {
+    Some(path) => {
+      println!("cargo:rerun-if-changed={}", path.display());
+      std::fs::read_to_string(path).unwrap()
+    }
+    None => String::new(),
+  };
+
+  for (name, env) in ANALYSIS_CRATES {
+    let version = locked_version(&lockfile, name).unwrap_or("unknown");
+    println!("cargo:rustc-env={env}={version}");
+  }
+}
+
+fn locked_version<'a>(lockfile: &'a str, name: &str) -> Option<&'a str> {
+  let name_line = format!("name = \"{name}\"");
+  let mut lines = lockfile.lines();
+  while let Some(line) = lines.next() {
+    if line == name_line {
+      return lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"');
+    }
+  }
+  None
+}
@@ -0,0 +1,24 @@
+CREATE TABLE npm_tarball_build_requests (
+  scope text NOT NULL,
+  name text NOT NULL,
+  request_count integer NOT NULL DEFAULT 0 CHECK (request_count >= 0),
+  last_requested_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  created_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, name),
+  FOREIGN KEY (scope, name) REFERENCES packages (scope, name) ON DELETE CASCADE
+);
+SELECT manage_updated_at('npm_tarball_build_requests');
+
+CREATE TABLE npm_tarball_build_claims (
+  scope text NOT NULL,
+  name text NOT NULL,
+  version text NOT NULL,
+  revision integer NOT NULL,
+  claimed_until timestamptz NOT NULL,
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  created_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, name, version, revision),
+  FOREIGN KEY (scope, name, version) REFERENCES package_versions (scope, name, version) ON DELETE CASCADE
+);
+SELECT manage_updated_at('npm_tarball_build_claims');
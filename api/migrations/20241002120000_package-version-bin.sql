@@ -0,0 +1 @@
+ALTER TABLE package_versions ADD bin jsonb NOT NULL DEFAULT '{}';
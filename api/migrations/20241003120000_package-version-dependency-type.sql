@@ -0,0 +1,2 @@
+CREATE TYPE dependency_type AS ENUM ('regular', 'peer', 'optional');
+ALTER TABLE package_version_dependencies ADD dependency_type dependency_type NOT NULL DEFAULT 'regular';
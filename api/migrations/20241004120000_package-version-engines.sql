@@ -0,0 +1 @@
+ALTER TABLE package_versions ADD engines jsonb NOT NULL DEFAULT '{}';
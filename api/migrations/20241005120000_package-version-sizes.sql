@@ -0,0 +1,13 @@
+ALTER TABLE package_versions ADD COLUMN unpacked_size bigint NOT NULL DEFAULT 0 CHECK (unpacked_size >= 0);
+ALTER TABLE package_versions ADD COLUMN file_count integer NOT NULL DEFAULT 0 CHECK (file_count >= 0);
+
+UPDATE package_versions
+SET unpacked_size = files.unpacked_size, file_count = files.file_count
+FROM (
+  SELECT scope, name, version, SUM(size) AS unpacked_size, COUNT(*) AS file_count
+  FROM package_files
+  GROUP BY scope, name, version
+) AS files
+WHERE package_versions.scope = files.scope AND package_versions.name = files.name AND package_versions.version = files.version;
+
+ALTER TABLE publishing_tasks ADD COLUMN size_report jsonb;
@@ -0,0 +1,3 @@
+CREATE TYPE validation_level AS ENUM ('full', 'lint_only', 'none');
+ALTER TABLE packages ADD COLUMN validation_level validation_level NOT NULL DEFAULT 'full';
+ALTER TABLE scopes ADD COLUMN validation_level validation_level;
@@ -0,0 +1 @@
+ALTER TABLE publishing_tasks ADD COLUMN warnings jsonb NOT NULL DEFAULT '[]';
@@ -0,0 +1 @@
+ALTER TABLE scopes ADD COLUMN outdated_dependencies_email boolean NOT NULL DEFAULT false;
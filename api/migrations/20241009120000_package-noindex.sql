@@ -0,0 +1 @@
+ALTER TABLE packages ADD COLUMN noindex boolean NOT NULL DEFAULT false;
@@ -0,0 +1,3 @@
+ALTER TABLE package_versions ADD COLUMN keywords text[] NOT NULL DEFAULT '{}';
+ALTER TABLE packages ADD COLUMN keywords text[] NOT NULL DEFAULT '{}';
+CREATE INDEX packages_keywords_idx ON packages USING gin (keywords);
@@ -0,0 +1 @@
+ALTER TABLE packages ADD COLUMN docs_landing_page text;
@@ -0,0 +1,15 @@
+CREATE TYPE user_export_status AS ENUM ('pending', 'processing', 'success', 'failure');
+
+CREATE TABLE user_exports (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  status user_export_status NOT NULL DEFAULT 'pending',
+  -- SHA256 hash of the secret in the download link that is emailed to the user.
+  download_token_hash text,
+  expires_at timestamptz,
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+SELECT manage_updated_at('user_exports');
+CREATE INDEX user_exports_user_id_idx ON user_exports (user_id, created_at);
+CREATE INDEX user_exports_status_idx ON user_exports (status);
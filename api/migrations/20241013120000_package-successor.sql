@@ -0,0 +1,5 @@
+ALTER TABLE packages ADD COLUMN successor_scope text;
+ALTER TABLE packages ADD COLUMN successor_name text;
+ALTER TABLE packages ADD CONSTRAINT packages_successor_fkey FOREIGN KEY (successor_scope, successor_name) REFERENCES packages (scope, name) ON DELETE SET NULL;
+ALTER TABLE packages ADD CONSTRAINT packages_successor_check CHECK ((successor_scope IS NULL) = (successor_name IS NULL));
+ALTER TABLE packages ADD CONSTRAINT packages_successor_not_self CHECK (successor_scope != scope OR successor_name != name);
@@ -0,0 +1,5 @@
+CREATE TYPE yank_reason_category AS ENUM ('security', 'broken', 'accidental');
+
+ALTER TABLE package_versions ADD COLUMN yank_reason_category yank_reason_category;
+ALTER TABLE package_versions ADD COLUMN yank_reason text;
+ALTER TABLE package_versions ADD CONSTRAINT package_versions_yank_reason_check CHECK (is_yanked OR (yank_reason_category IS NULL AND yank_reason IS NULL));
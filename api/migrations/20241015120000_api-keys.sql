@@ -0,0 +1,15 @@
+CREATE TABLE api_keys (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  -- SHA256 hash of the key.
+  hash text NOT NULL UNIQUE,
+  name text NOT NULL,
+  request_count bigint NOT NULL DEFAULT 0,
+  last_used_at timestamptz,
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
+
+SELECT manage_updated_at('api_keys');
@@ -0,0 +1,7 @@
+CREATE TYPE maintenance_status AS ENUM ('actively_maintained', 'maintenance_only', 'seeking_maintainers', 'unmaintained');
+
+ALTER TABLE packages ADD COLUMN maintenance_status maintenance_status;
+ALTER TABLE packages ADD COLUMN maintenance_status_updated_at timestamptz;
+-- Set by the background task when a package looks unmaintained, until a scope
+-- admin confirms or dismisses the suggestion by setting the status.
+ALTER TABLE packages ADD COLUMN unmaintained_suggested_at timestamptz;
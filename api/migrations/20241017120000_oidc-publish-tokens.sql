@@ -0,0 +1,18 @@
+CREATE TABLE oidc_publish_tokens (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  -- SHA256 hash of the token.
+  hash text NOT NULL UNIQUE,
+  -- The GitHub repository that the OIDC token was issued to.
+  repository_id bigint NOT NULL,
+  -- The user that triggered the workflow, if they have a JSR account.
+  user_id uuid REFERENCES users (id) ON DELETE CASCADE,
+  scope text NOT NULL,
+  name text NOT NULL,
+  version text NOT NULL,
+  tarball_hash text NOT NULL,
+  expires_at timestamptz NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  FOREIGN KEY (scope, name) REFERENCES packages (scope, name) ON DELETE CASCADE
+);
+
+CREATE INDEX oidc_publish_tokens_expires_at_idx ON oidc_publish_tokens (expires_at);
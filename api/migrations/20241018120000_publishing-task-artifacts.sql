@@ -0,0 +1,6 @@
+-- When a publish fails, the uploaded tarball and a diagnostic log are retained
+-- until this time, so the submitter can download them for debugging.
+ALTER TABLE publishing_tasks ADD COLUMN artifacts_expire_at timestamptz;
+ALTER TABLE publishing_tasks ADD COLUMN diagnostic_log text;
+
+CREATE INDEX publishing_tasks_artifacts_expire_at_idx ON publishing_tasks (artifacts_expire_at) WHERE artifacts_expire_at IS NOT NULL;
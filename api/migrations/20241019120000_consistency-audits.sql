@@ -0,0 +1,18 @@
+CREATE TYPE audit_finding_severity AS ENUM ('warning', 'error');
+
+CREATE TABLE consistency_audits (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE TABLE consistency_audit_findings (
+  audit_id uuid NOT NULL REFERENCES consistency_audits (id) ON DELETE CASCADE,
+  -- The invariant that was violated, like `orphaned_npm_tarball`.
+  kind text NOT NULL,
+  severity audit_finding_severity NOT NULL,
+  -- The row that violates the invariant, like `@scope/name@1.0.0`.
+  subject text NOT NULL,
+  message text NOT NULL
+);
+
+CREATE INDEX consistency_audit_findings_audit_id_idx ON consistency_audit_findings (audit_id);
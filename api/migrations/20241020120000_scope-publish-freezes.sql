@@ -0,0 +1,16 @@
+CREATE TABLE scope_publish_freezes (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  scope text NOT NULL REFERENCES scopes (scope) ON DELETE CASCADE,
+  starts_at timestamptz NOT NULL,
+  ends_at timestamptz NOT NULL,
+  -- If true, the window repeats every week after starts_at, for example to
+  -- freeze publishing on weekends.
+  recurs_weekly boolean NOT NULL DEFAULT false,
+  reason text NOT NULL,
+  created_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT scope_publish_freezes_valid_window CHECK (starts_at < ends_at),
+  CONSTRAINT scope_publish_freezes_valid_weekly_window CHECK (NOT recurs_weekly OR ends_at - starts_at < interval '7 days')
+);
+
+CREATE INDEX scope_publish_freezes_scope_idx ON scope_publish_freezes (scope);
@@ -0,0 +1,8 @@
+-- Publishing tasks with a publish_at in the future are validated immediately,
+-- and then wait in the 'scheduled' status until the scheduled_publishes task
+-- finalizes them.
+ALTER TYPE task_status ADD VALUE 'scheduled' AFTER 'pending';
+
+ALTER TABLE publishing_tasks ADD COLUMN publish_at timestamptz;
+
+CREATE INDEX publishing_tasks_publish_at_idx ON publishing_tasks (publish_at) WHERE publish_at IS NOT NULL;
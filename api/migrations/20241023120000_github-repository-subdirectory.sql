@@ -0,0 +1 @@
+ALTER TABLE packages ADD COLUMN github_repository_subdirectory text;
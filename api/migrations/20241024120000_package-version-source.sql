@@ -0,0 +1,2 @@
+ALTER TABLE package_versions ADD COLUMN source_repository text;
+ALTER TABLE package_versions ADD COLUMN source_commit text;
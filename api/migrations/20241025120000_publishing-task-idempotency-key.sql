@@ -0,0 +1 @@
+ALTER TABLE publishing_tasks ADD COLUMN idempotency_key text;
@@ -0,0 +1 @@
+ALTER TABLE publishing_tasks ADD COLUMN tarball_sha256 text;
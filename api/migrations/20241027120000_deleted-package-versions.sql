@@ -0,0 +1,17 @@
+-- Versions that were deleted by staff. The original tarball of the version is
+-- retained in the publishing bucket until restorable_until, so that the
+-- version can be restored by replaying its publishing task.
+CREATE TABLE deleted_package_versions (
+  scope text NOT NULL,
+  name text NOT NULL,
+  version text NOT NULL collate en_natural,
+  publishing_task_id uuid NOT NULL REFERENCES publishing_tasks (id) ON DELETE CASCADE,
+  reason text NOT NULL,
+  deleted_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  restorable_until timestamptz NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, name, version),
+  FOREIGN KEY (scope, name) REFERENCES packages (scope, name) ON DELETE CASCADE
+);
+
+CREATE INDEX deleted_package_versions_restorable_until_idx ON deleted_package_versions (restorable_until);
@@ -0,0 +1 @@
+ALTER TYPE download_kind ADD VALUE 'web';
@@ -0,0 +1,11 @@
+-- If a scope has any IP ranges, new versions may only be published to its
+-- packages from clients within one of them.
+CREATE TABLE scope_publish_ip_ranges (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  scope text NOT NULL REFERENCES scopes (scope) ON DELETE CASCADE,
+  cidr cidr NOT NULL,
+  description text NOT NULL,
+  created_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (scope, cidr)
+);
@@ -0,0 +1,13 @@
+-- Tasks of queues that are backed by Postgres instead of Cloud Tasks. A worker
+-- claims a task by pushing its `run_after` into the future, so that the task
+-- becomes available again if the worker dies while processing it.
+CREATE TABLE queued_tasks (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  queue text NOT NULL,
+  body bytea NOT NULL,
+  attempts integer NOT NULL DEFAULT 0,
+  run_after timestamptz NOT NULL DEFAULT now(),
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX queued_tasks_queue_run_after_idx ON queued_tasks (queue, run_after);
@@ -0,0 +1,11 @@
+-- One row per 4 hour window of the request logs that download counts were
+-- ingested from. A window is complete once `window_end` is 4 hours after
+-- `window_start`; the current window is ingested again until then.
+CREATE TABLE download_ingestion_checkpoints (
+  window_start timestamptz NOT NULL PRIMARY KEY,
+  window_end timestamptz NOT NULL,
+  downloads bigint NOT NULL,
+  -- The staff member that replayed the window, if it was replayed.
+  replayed_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  ingested_at timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,3 @@
+-- The repository whose GitHub Actions workflow requested the publish, if it
+-- was authenticated with an OIDC token.
+ALTER TABLE publishing_tasks ADD COLUMN github_repository_id bigint REFERENCES github_repositories (id) ON DELETE SET NULL;
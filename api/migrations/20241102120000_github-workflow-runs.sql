@@ -0,0 +1,4 @@
+-- The GitHub Actions workflow run that requested a publish, from the claims of
+-- its OIDC token: the repository, workflow path, git ref and run ID.
+ALTER TABLE publishing_tasks ADD COLUMN github_workflow_run jsonb;
+ALTER TABLE oidc_publish_tokens ADD COLUMN github_workflow_run jsonb;
@@ -0,0 +1,12 @@
+-- Packages that users starred, which shows community interest in a package
+-- better than download counts alone.
+CREATE TABLE package_stars (
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  scope text NOT NULL,
+  name text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (user_id, scope, name),
+  FOREIGN KEY (scope, name) REFERENCES packages (scope, name) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX package_stars_scope_name_idx ON package_stars (scope, name);
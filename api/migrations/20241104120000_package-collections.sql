@@ -0,0 +1,23 @@
+-- Public, user curated lists of packages, like "best testing tools".
+CREATE TABLE package_collections (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  name text NOT NULL,
+  description text NOT NULL DEFAULT '',
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX package_collections_user_id_idx ON package_collections (user_id);
+
+SELECT manage_updated_at('package_collections');
+
+CREATE TABLE package_collection_items (
+  collection_id uuid NOT NULL REFERENCES package_collections (id) ON DELETE CASCADE,
+  scope text NOT NULL,
+  name text NOT NULL,
+  -- The position of the package in the collection, starting at 1.
+  position integer NOT NULL,
+  PRIMARY KEY (collection_id, scope, name),
+  FOREIGN KEY (scope, name) REFERENCES packages (scope, name) ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,36 @@
+-- Whether the package has at least one version that is not yanked. Packages
+-- without one, because all versions were yanked or no publish completed yet,
+-- are left out of package listings, stats and the sitemap.
+ALTER TABLE packages ADD COLUMN has_usable_version boolean NOT NULL DEFAULT false;
+
+UPDATE packages SET has_usable_version = EXISTS (
+  SELECT 1 FROM package_versions
+  WHERE package_versions.scope = packages.scope AND package_versions.name = packages.name AND NOT package_versions.is_yanked
+);
+
+CREATE OR REPLACE FUNCTION set_package_has_usable_version() RETURNS trigger AS $$
+DECLARE
+    _scope text;
+    _name text;
+    _usable boolean;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        _scope := OLD.scope;
+        _name := OLD.name;
+    ELSE
+        _scope := NEW.scope;
+        _name := NEW.name;
+    END IF;
+    _usable := EXISTS (
+        SELECT 1 FROM package_versions
+        WHERE scope = _scope AND name = _name AND NOT is_yanked
+    );
+    UPDATE packages SET has_usable_version = _usable
+    WHERE scope = _scope AND name = _name AND has_usable_version <> _usable;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER set_has_usable_version
+AFTER INSERT OR DELETE OR UPDATE OF is_yanked ON package_versions
+FOR EACH ROW EXECUTE PROCEDURE set_package_has_usable_version();
@@ -0,0 +1,3 @@
+-- The imports of the published modules that point outside of the package, and
+-- what they resolved to, as found by the analysis of the publish.
+ALTER TABLE publishing_tasks ADD COLUMN specifiers jsonb;
@@ -0,0 +1,11 @@
+-- A markdown profile that is shown on the landing page of the scope.
+ALTER TABLE scopes ADD COLUMN readme text;
+
+CREATE TABLE scope_pinned_packages (
+  scope text NOT NULL,
+  name text NOT NULL,
+  -- The position of the package on the landing page, starting at 1.
+  position integer NOT NULL,
+  PRIMARY KEY (scope, name),
+  FOREIGN KEY (scope, name) REFERENCES packages (scope, name) ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,23 @@
+-- Supplementary files attached to a package version after it was published,
+-- like prebuilt binaries or a checksums file.
+CREATE TABLE package_version_artifacts (
+  scope text NOT NULL,
+  name text NOT NULL,
+  version text NOT NULL,
+  filename text NOT NULL,
+  -- The size and SHA-256 digest that the uploader declared before uploading.
+  size bigint NOT NULL,
+  sha256 text NOT NULL,
+  user_id uuid REFERENCES users (id) ON DELETE SET NULL,
+  -- SHA256 hash of the secret in the upload URL. Cleared once the upload
+  -- completed.
+  upload_token_hash text,
+  upload_expires_at timestamptz,
+  uploaded_at timestamptz,
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  created_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, name, version, filename),
+  FOREIGN KEY (scope, name, version) REFERENCES package_versions (scope, name, version) ON DELETE CASCADE
+);
+
+SELECT manage_updated_at('package_version_artifacts');
@@ -0,0 +1,2 @@
+ALTER TABLE scopes ADD COLUMN min_docs_coverage real CHECK (min_docs_coverage >= 0 AND min_docs_coverage <= 1);
+ALTER TABLE scopes ADD COLUMN enforce_min_docs_coverage boolean NOT NULL DEFAULT false;
@@ -0,0 +1,13 @@
+-- Flags that staff raised on an account. While an account has a flag, it is
+-- held to the limits of new accounts.
+CREATE TABLE user_moderation_flags (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  reason text NOT NULL,
+  created_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX user_moderation_flags_user_id_idx ON user_moderation_flags (user_id);
+
+CREATE INDEX publishing_tasks_user_id_created_at_idx ON publishing_tasks (user_id, created_at);
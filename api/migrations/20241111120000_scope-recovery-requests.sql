@@ -0,0 +1,18 @@
+-- Requests by scope members to become the creator of a scope whose creator
+-- was blocked. Requests that no other admin of the scope could object to are
+-- approved right away, the rest are reviewed by staff.
+CREATE TYPE scope_recovery_status AS ENUM ('pending', 'approved', 'rejected');
+
+CREATE TABLE scope_recovery_requests (
+  id uuid NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+  scope text NOT NULL REFERENCES scopes (scope) ON DELETE CASCADE,
+  requester_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  previous_creator uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  reason text NOT NULL,
+  status scope_recovery_status NOT NULL DEFAULT 'pending',
+  reviewed_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  reviewed_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX scope_recovery_requests_pending_idx ON scope_recovery_requests (scope) WHERE status = 'pending';
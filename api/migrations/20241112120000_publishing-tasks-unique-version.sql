@@ -0,0 +1,13 @@
+-- Concurrent publishes of the same version could both pass the duplicate check
+-- in `create_publishing_task`. Fail all but the oldest in-flight task of each
+-- version before enforcing that there is at most one.
+UPDATE publishing_tasks SET status = 'failure', error = '{"code": "duplicateVersionPublish", "message": "Another publishing task for this version was created at the same time."}'::jsonb
+WHERE status != 'failure' AND id IN (
+  SELECT id FROM (
+    SELECT id, row_number() OVER (PARTITION BY package_scope, package_name, package_version ORDER BY created_at, id) AS n
+    FROM publishing_tasks
+    WHERE status != 'failure'
+  ) t WHERE n > 1
+);
+
+CREATE UNIQUE INDEX publishing_tasks_unique_version_idx ON publishing_tasks (package_scope, package_name, package_version) WHERE status != 'failure';
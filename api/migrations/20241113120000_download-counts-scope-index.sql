@@ -0,0 +1,3 @@
+-- Scope wide download counts are aggregated over all packages of a scope, which
+-- the package index can only serve by reading every day of every package.
+CREATE INDEX version_download_counts_24h_scope_idx ON version_download_counts_24h (scope, time_bucket DESC);
@@ -0,0 +1,46 @@
+-- Packages whose search index entry is out of date. Entries are written by
+-- triggers in the same transaction as the change to the package, and removed
+-- by the search index task once the package has been indexed, so that the
+-- search index eventually reflects every change even if indexing fails.
+CREATE TABLE search_index_outbox (
+  scope text NOT NULL,
+  name text NOT NULL,
+  -- Bumped on every change, so that a change made while the package is being
+  -- indexed is not lost when the entry is removed.
+  revision integer NOT NULL DEFAULT 0,
+  attempts integer NOT NULL DEFAULT 0,
+  last_error text,
+  next_attempt_at timestamptz NOT NULL DEFAULT now(),
+  enqueued_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, name)
+);
+
+CREATE INDEX search_index_outbox_next_attempt_at_idx ON search_index_outbox (next_attempt_at);
+
+CREATE OR REPLACE FUNCTION enqueue_search_index_update() RETURNS trigger AS $$
+DECLARE
+    _scope text;
+    _name text;
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        _scope := OLD.scope;
+        _name := OLD.name;
+    ELSE
+        _scope := NEW.scope;
+        _name := NEW.name;
+    END IF;
+    INSERT INTO search_index_outbox (scope, name) VALUES (_scope, _name)
+    ON CONFLICT (scope, name) DO UPDATE
+    SET revision = search_index_outbox.revision + 1, attempts = 0, last_error = NULL, next_attempt_at = now();
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER enqueue_package_search_index_update
+AFTER INSERT OR DELETE OR UPDATE ON packages
+FOR EACH ROW EXECUTE PROCEDURE enqueue_search_index_update();
+
+-- The score of a package depends on its latest version, and its provenance.
+CREATE TRIGGER enqueue_package_version_search_index_update
+AFTER INSERT OR DELETE OR UPDATE ON package_versions
+FOR EACH ROW EXECUTE PROCEDURE enqueue_search_index_update();
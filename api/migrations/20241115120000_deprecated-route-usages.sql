@@ -0,0 +1,15 @@
+-- Requests made to deprecated API routes, counted per token and user agent,
+-- so that the remaining consumers of a route can be contacted before the route
+-- is removed.
+CREATE TABLE deprecated_route_usages (
+  route text NOT NULL,
+  -- The token the requests were authenticated with. NULL for anonymous
+  -- requests and requests authenticated with GitHub Actions OIDC tokens.
+  token_id uuid REFERENCES tokens (id) ON DELETE CASCADE,
+  user_id uuid REFERENCES users (id) ON DELETE CASCADE,
+  user_agent text NOT NULL,
+  request_count bigint NOT NULL DEFAULT 0,
+  first_used_at timestamptz NOT NULL DEFAULT now(),
+  last_used_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE NULLS NOT DISTINCT (route, token_id, user_id, user_agent)
+);
@@ -0,0 +1,56 @@
+-- The number of distinct packages that depend on a JSR or npm package in any
+-- of their versions. Maintained by triggers on package_version_dependencies,
+-- so that listing dependents does not have to count them on every request.
+CREATE TABLE package_dependent_counts (
+  dependency_kind dependency_kind NOT NULL,
+  dependency_name text NOT NULL,
+  dependent_count bigint NOT NULL,
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (dependency_kind, dependency_name)
+);
+
+SELECT manage_updated_at('package_dependent_counts');
+
+INSERT INTO package_dependent_counts (dependency_kind, dependency_name, dependent_count)
+SELECT dependency_kind, dependency_name, COUNT(DISTINCT (package_scope, package_name))
+FROM package_version_dependencies
+GROUP BY dependency_kind, dependency_name;
+
+CREATE OR REPLACE FUNCTION count_package_dependents(_kind dependency_kind, _name text) RETURNS bigint AS $$
+    SELECT COUNT(DISTINCT (package_scope, package_name))
+    FROM package_version_dependencies
+    WHERE dependency_kind = _kind AND dependency_name = _name;
+$$ LANGUAGE sql STABLE;
+
+-- Recounts the dependents of every dependency that was added or removed by
+-- the statement. Concurrent publishes of two dependents of the same package
+-- can each miss the rows of the other, so the counts are also reconciled
+-- periodically.
+CREATE OR REPLACE FUNCTION refresh_package_dependent_counts() RETURNS trigger AS $$
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        INSERT INTO package_dependent_counts (dependency_kind, dependency_name, dependent_count)
+        SELECT dependency_kind, dependency_name, count_package_dependents(dependency_kind, dependency_name)
+        FROM (SELECT DISTINCT dependency_kind, dependency_name FROM old_rows) changed
+        ON CONFLICT (dependency_kind, dependency_name)
+        DO UPDATE SET dependent_count = EXCLUDED.dependent_count;
+    ELSE
+        INSERT INTO package_dependent_counts (dependency_kind, dependency_name, dependent_count)
+        SELECT dependency_kind, dependency_name, count_package_dependents(dependency_kind, dependency_name)
+        FROM (SELECT DISTINCT dependency_kind, dependency_name FROM new_rows) changed
+        ON CONFLICT (dependency_kind, dependency_name)
+        DO UPDATE SET dependent_count = EXCLUDED.dependent_count;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER refresh_package_dependent_counts_on_insert
+AFTER INSERT ON package_version_dependencies
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT EXECUTE PROCEDURE refresh_package_dependent_counts();
+
+CREATE TRIGGER refresh_package_dependent_counts_on_delete
+AFTER DELETE ON package_version_dependencies
+REFERENCING OLD TABLE AS old_rows
+FOR EACH STATEMENT EXECUTE PROCEDURE refresh_package_dependent_counts();
@@ -0,0 +1,21 @@
+ALTER TABLE scopes ADD COLUMN download_anomaly_email boolean NOT NULL DEFAULT false;
+
+CREATE TYPE download_anomaly_kind AS ENUM ('spike', 'cliff');
+
+-- Days on which the downloads of a package deviated strongly from the days
+-- before, as found by the daily download anomaly task.
+CREATE TABLE download_anomalies (
+  scope text NOT NULL,
+  package text NOT NULL,
+  day timestamptz NOT NULL,
+  kind download_anomaly_kind NOT NULL,
+  count bigint NOT NULL,
+  -- The mean and standard deviation of the daily downloads in the baseline
+  -- period before the day.
+  mean double precision NOT NULL,
+  stddev double precision NOT NULL,
+  z_score double precision NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, package, day),
+  FOREIGN KEY (scope, package) REFERENCES packages (scope, name) ON UPDATE CASCADE ON DELETE CASCADE
+);
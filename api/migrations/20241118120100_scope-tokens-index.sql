@@ -0,0 +1,2 @@
+-- Scope tokens always have exactly one permission, which names their scope.
+CREATE INDEX tokens_scope_idx ON tokens ((permissions->0->>'scope')) WHERE type = 'scope';
@@ -0,0 +1,14 @@
+-- Old names of packages that were renamed or moved to another scope. Requests
+-- for the old name are redirected to the new one until the redirect expires.
+CREATE TABLE package_redirects (
+  scope text NOT NULL,
+  name text NOT NULL,
+  target_scope text NOT NULL,
+  target_name text NOT NULL,
+  expires_at timestamptz NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, name),
+  FOREIGN KEY (target_scope, target_name) REFERENCES packages (scope, name) ON UPDATE CASCADE ON DELETE CASCADE
+);
+
+CREATE INDEX package_redirects_target_idx ON package_redirects (target_scope, target_name);
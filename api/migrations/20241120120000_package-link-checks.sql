@@ -0,0 +1,26 @@
+-- The last check of the external links in the README and docs of a package,
+-- as done by the link check task for popular packages.
+CREATE TABLE package_link_checks (
+  scope text NOT NULL,
+  package text NOT NULL,
+  -- The version whose README and docs were checked.
+  version text NOT NULL,
+  checked_link_count integer NOT NULL,
+  checked_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, package),
+  FOREIGN KEY (scope, package) REFERENCES packages (scope, name) ON UPDATE CASCADE ON DELETE CASCADE
+);
+
+-- The links that were found to be broken in the last check of a package.
+CREATE TABLE package_broken_links (
+  scope text NOT NULL,
+  package text NOT NULL,
+  url text NOT NULL,
+  -- Where the link was found, either the path of the README or 'docs'.
+  source text NOT NULL,
+  -- The HTTP status of the response, or null if no response was received.
+  status integer,
+  error text,
+  PRIMARY KEY (scope, package, url),
+  FOREIGN KEY (scope, package) REFERENCES package_link_checks (scope, package) ON UPDATE CASCADE ON DELETE CASCADE
+);
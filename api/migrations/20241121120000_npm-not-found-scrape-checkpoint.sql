@@ -0,0 +1,10 @@
+-- The end of the time range of the request logs that npm requests resulting
+-- in a 404 were last counted from, so that every request is counted once.
+-- There is only ever one row.
+CREATE TABLE npm_not_found_scrape_checkpoint (
+  id boolean PRIMARY KEY DEFAULT true CHECK (id),
+  scraped_until timestamptz NOT NULL,
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+SELECT manage_updated_at('npm_not_found_scrape_checkpoint');
@@ -0,0 +1,50 @@
+-- Lets the dependent count triggers check whether a package still depends on
+-- a dependency in any of its versions. The old index is a prefix of this one.
+CREATE INDEX package_version_dependencies_dependency_package_idx ON package_version_dependencies (dependency_kind, dependency_name, package_scope, package_name);
+DROP INDEX package_version_dependencies_dependency_kind_dependency_name_idx;
+
+-- Adjusts the dependent counts by the packages that gained their first or lost
+-- their last dependency on a package in the statement, instead of recounting
+-- all dependents. Concurrent publishes of the first versions of one package
+-- that depend on the same package can both count it as a new dependent, so the
+-- counts are still reconciled periodically.
+CREATE OR REPLACE FUNCTION refresh_package_dependent_counts() RETURNS trigger AS $$
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        UPDATE package_dependent_counts
+        SET dependent_count = GREATEST(package_dependent_counts.dependent_count - removed.count, 0)
+        FROM (
+            SELECT dependency_kind, dependency_name, COUNT(*) AS count
+            FROM (SELECT DISTINCT dependency_kind, dependency_name, package_scope, package_name FROM old_rows) r
+            WHERE NOT EXISTS (
+                SELECT 1 FROM package_version_dependencies d
+                WHERE d.dependency_kind = r.dependency_kind AND d.dependency_name = r.dependency_name
+                AND d.package_scope = r.package_scope AND d.package_name = r.package_name
+            )
+            GROUP BY dependency_kind, dependency_name
+        ) removed
+        WHERE package_dependent_counts.dependency_kind = removed.dependency_kind
+        AND package_dependent_counts.dependency_name = removed.dependency_name;
+    ELSE
+        INSERT INTO package_dependent_counts (dependency_kind, dependency_name, dependent_count)
+        SELECT dependency_kind, dependency_name, COUNT(*)
+        FROM (
+            SELECT dependency_kind, dependency_name, package_scope, package_name, array_agg(package_version) AS versions
+            FROM new_rows
+            GROUP BY dependency_kind, dependency_name, package_scope, package_name
+        ) a
+        WHERE NOT EXISTS (
+            SELECT 1 FROM package_version_dependencies d
+            WHERE d.dependency_kind = a.dependency_kind AND d.dependency_name = a.dependency_name
+            AND d.package_scope = a.package_scope AND d.package_name = a.package_name
+            AND d.package_version <> ALL (a.versions)
+        )
+        GROUP BY dependency_kind, dependency_name
+        ON CONFLICT (dependency_kind, dependency_name)
+        DO UPDATE SET dependent_count = package_dependent_counts.dependent_count + EXCLUDED.dependent_count;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP FUNCTION count_package_dependents(dependency_kind, text);
@@ -1,8 +1,12 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 use std::cell::RefCell;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
 
 use bytes::Bytes;
 use deno_ast::swc::common::comments::CommentKind;
@@ -13,6 +17,7 @@ use deno_ast::ModuleSpecifier;
 use deno_ast::ParsedSource;
 use deno_ast::SourceRange;
 use deno_ast::SourceRangedForSpanned;
+use deno_doc::DocNode;
 use deno_doc::DocNodeKind;
 use deno_error::JsErrorBox;
 use deno_graph::source::load_data_url;
@@ -36,6 +41,7 @@ use deno_semver::package::PackageNv;
 use deno_semver::package::PackageReqReference;
 use deno_semver::StackString;
 use futures::FutureExt;
+use indexmap::IndexMap;
 use once_cell::sync::Lazy;
 use regex::bytes::Regex as BytesRegex;
 use regex::Regex;
@@ -44,9 +50,18 @@ use tracing::Instrument;
 use url::Url;
 
 use crate::buckets::BucketWithQueue;
+use crate::db::BinMap;
 use crate::db::DependencyKind;
+use crate::db::DependencyType;
+use crate::db::DeprecatedSymbol;
+use crate::db::EntrypointStats;
 use crate::db::ExportsMap;
+use crate::db::PackageVersionEngines;
 use crate::db::PackageVersionMeta;
+use crate::db::PublishedSpecifier;
+use crate::db::PublishedSpecifierKind;
+use crate::db::RuntimeCompat;
+use crate::db::ValidationLevel;
 use crate::docs::DocNodesByUrl;
 use crate::gcs_paths;
 use crate::ids::PackageName;
@@ -58,9 +73,154 @@ use crate::npm::NpmTarball;
 use crate::npm::NpmTarballFiles;
 use crate::npm::NpmTarballOptions;
 use crate::tarball::PublishError;
+use crate::typescript::collect_declaration_features;
+
+/// The exact versions of the crates that analyze packages, as resolved in the
+/// lockfile by the build script.
+pub const DENO_AST_VERSION: &str = env!("DENO_AST_VERSION");
+pub const DENO_GRAPH_VERSION: &str = env!("DENO_GRAPH_VERSION");
+pub const DENO_DOC_VERSION: &str = env!("DENO_DOC_VERSION");
+
+/// Resource limits that are applied to the analysis of a package during
+/// publishing, so that a pathological package can not wedge the worker.
+#[derive(Debug, Clone, Copy)]
+pub struct AnalysisLimits {
+  /// The maximum wall clock time that a single analysis stage may take.
+  pub stage_timeout: Duration,
+  /// The maximum number of bytes that analysis may have allocated at once.
+  pub memory_limit: u64,
+}
+
+impl Default for AnalysisLimits {
+  fn default() -> Self {
+    Self {
+      stage_timeout: Duration::from_secs(60),
+      memory_limit: 2 * 1024 * 1024 * 1024,
+    }
+  }
+}
+
+impl AnalysisLimits {
+  /// Analysis runs on a blocking thread that can not be interrupted, so stage
+  /// limits are checked between units of work. This is the point at which the
+  /// publishing task stops waiting for an analysis that does not get to such a
+  /// check anymore.
+  pub fn total_timeout(&self) -> Duration {
+    self.stage_timeout * AnalysisStage::ALL.len() as u32
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalysisStage {
+  ModuleGraph,
+  FastCheck,
+  Docs,
+  NpmTarball,
+}
+
+impl AnalysisStage {
+  const ALL: [AnalysisStage; 4] = [
+    AnalysisStage::ModuleGraph,
+    AnalysisStage::FastCheck,
+    AnalysisStage::Docs,
+    AnalysisStage::NpmTarball,
+  ];
+}
+
+impl std::fmt::Display for AnalysisStage {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AnalysisStage::ModuleGraph => write!(f, "module graph"),
+      AnalysisStage::FastCheck => write!(f, "fast check"),
+      AnalysisStage::Docs => write!(f, "documentation"),
+      AnalysisStage::NpmTarball => write!(f, "npm tarball"),
+    }
+  }
+}
+
+/// Tracks the time and memory used by the stages of a package analysis
+/// against its [AnalysisLimits]. It is shared between the analysis thread and
+/// the publishing task that waits for it.
+pub struct AnalysisBudget {
+  limits: AnalysisLimits,
+  state: Mutex<AnalysisBudgetState>,
+}
+
+struct AnalysisBudgetState {
+  stage: AnalysisStage,
+  stage_started_at: Instant,
+  memory_baseline: i64,
+}
+
+impl AnalysisBudget {
+  pub fn new(limits: AnalysisLimits) -> Self {
+    Self {
+      limits,
+      state: Mutex::new(AnalysisBudgetState {
+        stage: AnalysisStage::ModuleGraph,
+        stage_started_at: Instant::now(),
+        memory_baseline: 0,
+      }),
+    }
+  }
+
+  pub fn limits(&self) -> AnalysisLimits {
+    self.limits
+  }
+
+  /// The stage that analysis is currently in.
+  pub fn stage(&self) -> AnalysisStage {
+    self.state.lock().unwrap().stage
+  }
+
+  /// Starts the first stage. Must be called on the thread that performs the
+  /// analysis, as memory is tracked per thread.
+  fn start(&self) {
+    let mut state = self.state.lock().unwrap();
+    state.stage = AnalysisStage::ModuleGraph;
+    state.stage_started_at = Instant::now();
+    state.memory_baseline = crate::memory::thread_allocated_bytes();
+  }
+
+  /// Checks the limits for the current stage and moves on to the next one.
+  fn enter(&self, stage: AnalysisStage) -> Result<(), PublishError> {
+    self.check()?;
+    let mut state = self.state.lock().unwrap();
+    state.stage = stage;
+    state.stage_started_at = Instant::now();
+    Ok(())
+  }
+
+  /// Fails if the current stage has exceeded its time or memory limit.
+  fn check(&self) -> Result<(), PublishError> {
+    let state = self.state.lock().unwrap();
+    if state.stage_started_at.elapsed() > self.limits.stage_timeout {
+      return Err(PublishError::AnalysisTimeout {
+        stage: state.stage,
+        timeout: self.limits.stage_timeout,
+      });
+    }
+    let used = crate::memory::thread_allocated_bytes()
+      .saturating_sub(state.memory_baseline)
+      .max(0) as u64;
+    if used > self.limits.memory_limit {
+      return Err(PublishError::AnalysisMemoryExceeded {
+        stage: state.stage,
+        limit: self.limits.memory_limit,
+        used,
+      });
+    }
+    Ok(())
+  }
+}
 
 pub struct PackageAnalysisData {
   pub exports: ExportsMap,
+  pub bin: BinMap,
+  pub engines: PackageVersionEngines,
+  pub runtime_compat: RuntimeCompat,
+  pub validation_level: ValidationLevel,
+  pub dependency_types: HashMap<(DependencyKind, String), DependencyType>,
   pub files: HashMap<PackagePath, Vec<u8>>,
 }
 
@@ -69,14 +229,17 @@ pub struct PackageAnalysisOutput {
   pub module_graph_2: HashMap<String, ModuleInfo>,
   pub doc_nodes_json: Bytes,
   pub doc_search_json: serde_json::Value,
-  pub dependencies: HashSet<(DependencyKind, PackageReqReference)>,
+  pub dependencies:
+    HashSet<(DependencyKind, DependencyType, PackageReqReference)>,
   pub npm_tarball: NpmTarball,
   pub readme_path: Option<PackagePath>,
   pub meta: PackageVersionMeta,
+  pub specifiers: Vec<PublishedSpecifier>,
 }
 
 // We have to spawn another tokio runtime, because
 // `deno_graph::ModuleGraph::build` is not thread-safe.
+#[allow(clippy::too_many_arguments)]
 #[tokio::main(flavor = "current_thread")]
 pub async fn analyze_package(
   span: tracing::Span,
@@ -86,13 +249,22 @@ pub async fn analyze_package(
   version: Version,
   config_file: PackagePath,
   data: PackageAnalysisData,
+  budget: Arc<AnalysisBudget>,
 ) -> Result<PackageAnalysisOutput, PublishError> {
-  analyze_package_inner(registry_url, scope, name, version, config_file, data)
-    .instrument(span)
-    .await
+  analyze_package_inner(
+    registry_url,
+    scope,
+    name,
+    version,
+    config_file,
+    data,
+    &budget,
+  )
+  .instrument(span)
+  .await
 }
 
-#[instrument(name = "analyze_package", skip(registry_url, data), err)]
+#[instrument(name = "analyze_package", skip(registry_url, data, budget), err)]
 async fn analyze_package_inner(
   registry_url: Url,
   scope: ScopeName,
@@ -100,8 +272,18 @@ async fn analyze_package_inner(
   version: Version,
   config_file: PackagePath,
   data: PackageAnalysisData,
+  budget: &AnalysisBudget,
 ) -> Result<PackageAnalysisOutput, PublishError> {
-  let PackageAnalysisData { exports, files } = data;
+  budget.start();
+  let PackageAnalysisData {
+    exports,
+    bin,
+    engines,
+    runtime_compat,
+    validation_level,
+    dependency_types,
+    files,
+  } = data;
   let mut roots = vec![];
   let mut main_entrypoint = None;
 
@@ -132,6 +314,31 @@ async fn analyze_package_inner(
     roots.push(url);
   }
 
+  // Executables are not part of the public API of the package, so they are
+  // only added to the module graph, not documented.
+  let mut bin_roots = vec![];
+  for (name, path) in bin.iter() {
+    let path = path.strip_prefix('.').unwrap();
+    let path = PackagePath::new(path.to_string()).map_err(|error| {
+      PublishError::InvalidPath {
+        path: path.to_string(),
+        error,
+      }
+    })?;
+    if !files.contains_key(&path) {
+      return Err(PublishError::ConfigFileBinInvalid {
+        path: Box::new(config_file.clone()),
+        invalid_bin: format!(
+          "executable '{name}' references entrypoint '{path}' which does not exist",
+        ),
+      });
+    }
+    let url = Url::parse(&format!("file://{}", path)).unwrap();
+    if !roots.contains(&url) {
+      bin_roots.push(url);
+    }
+  }
+
   let module_analyzer = ModuleAnalyzer::default();
 
   let workspace_member = WorkspaceMember {
@@ -144,7 +351,7 @@ async fn analyze_package_inner(
   let mut graph = deno_graph::ModuleGraph::new(GraphKind::All);
   graph
     .build(
-      roots.clone(),
+      roots.iter().chain(bin_roots.iter()).cloned().collect(),
       &SyncLoader { files: &files },
       BuildOptions {
         is_dynamic: false,
@@ -167,17 +374,31 @@ async fn analyze_package_inner(
   graph
     .valid()
     .map_err(|e| PublishError::GraphError(Box::new(e)))?;
-  graph.build_fast_check_type_graph(BuildFastCheckTypeGraphOptions {
-    fast_check_cache: None,
-    fast_check_dts: true,
-    jsr_url_provider: &PassthroughJsrUrlProvider,
-    es_parser: Some(&module_analyzer.analyzer),
-    resolver: Default::default(),
-    npm_resolver: Default::default(),
-    workspace_fast_check: WorkspaceFastCheckOption::Enabled(&workspace_members),
-  });
+  budget.enter(AnalysisStage::FastCheck)?;
+  if validation_level != ValidationLevel::None {
+    graph.build_fast_check_type_graph(BuildFastCheckTypeGraphOptions {
+      fast_check_cache: None,
+      fast_check_dts: true,
+      jsr_url_provider: &PassthroughJsrUrlProvider,
+      es_parser: Some(&module_analyzer.analyzer),
+      resolver: Default::default(),
+      npm_resolver: Default::default(),
+      workspace_fast_check: WorkspaceFastCheckOption::Enabled(
+        &workspace_members,
+      ),
+    });
+  }
 
   let dependencies = collect_dependencies(&graph)?;
+  let dependencies = assign_dependency_types(dependencies, &dependency_types)
+    .map_err(|invalid_dependencies| {
+    PublishError::ConfigFileDependenciesInvalid {
+      path: Box::new(config_file.clone()),
+      invalid_dependencies,
+    }
+  })?;
+
+  budget.check()?;
 
   for module in graph.modules() {
     // Check for global type augementation.
@@ -193,6 +414,7 @@ async fn analyze_package_inner(
       check_for_banned_syntax(&parsed_source)?;
       check_for_banned_triple_slash_directives(&parsed_source)?;
     }
+    budget.check()?;
   }
 
   let all_fast_check = graph
@@ -206,10 +428,36 @@ async fn analyze_package_inner(
     })
     .all(|js| js.fast_check_module().is_some());
 
+  // Declarations are only emitted from the fast check output when the public
+  // API is fully validated.
+  let typescript_features = if validation_level == ValidationLevel::Full {
+    collect_declaration_features(&graph)
+      .map(|features| features.into_iter().collect())
+  } else {
+    None
+  };
+
+  let specifiers =
+    collect_published_specifiers(&graph, &module_analyzer.analyzer);
+
+  let entrypoints = exports
+    .iter()
+    .map(|(key, path)| {
+      let url =
+        Url::parse(&format!("file://{}", path.strip_prefix('.').unwrap()))
+          .unwrap();
+      let stats =
+        collect_entrypoint_stats(&graph, &module_analyzer.analyzer, &url);
+      (key.clone(), stats)
+    })
+    .collect::<IndexMap<_, _>>();
+
+  budget.enter(AnalysisStage::Docs)?;
   let doc_nodes =
     crate::docs::generate_docs(roots, &graph, &module_analyzer.analyzer)
       .map_err(PublishError::DocError)?;
 
+  budget.enter(AnalysisStage::NpmTarball)?;
   let module_graph_2 = module_analyzer.take_module_graph_2();
   let npm_tarball = create_npm_tarball(NpmTarballOptions {
     graph: &graph,
@@ -219,13 +467,18 @@ async fn analyze_package_inner(
     package: &name,
     version: &version,
     exports: &exports,
+    bin: &bin,
+    engines: &engines,
+    runtime_compat: &runtime_compat,
+    validation_level,
     files: NpmTarballFiles::WithBytes(&files),
     dependencies: dependencies.iter(),
   })
   .await
   .map_err(PublishError::NpmTarballError)?;
+  budget.check()?;
 
-  let (meta, readme_path) = {
+  let (mut meta, readme_path) = {
     let readme = files
       .iter()
       .find(|file| file.0.case_insensitive().is_readme());
@@ -240,6 +493,11 @@ async fn analyze_package_inner(
       readme.map(|readme| readme.0.clone()),
     )
   };
+  meta.entrypoints = entrypoints;
+  meta.typescript_features = typescript_features;
+  meta.deprecated_symbols = collect_deprecated_symbols(&exports, &doc_nodes);
+  meta.docs_coverage = collect_docs_coverage(&exports, &doc_nodes);
+  meta.runtime_compat = Some(runtime_compat.clone());
 
   let doc_nodes_json = serde_json::to_vec(&doc_nodes).unwrap().into();
 
@@ -254,6 +512,7 @@ async fn analyze_package_inner(
     version,
     true,
     None,
+    None,
     false,
     crate::db::RuntimeCompat {
       browser: None,
@@ -273,17 +532,290 @@ async fn analyze_package_inner(
   };
 
   Ok(PackageAnalysisOutput {
-    data: PackageAnalysisData { exports, files },
+    data: PackageAnalysisData {
+      exports,
+      bin,
+      engines,
+      runtime_compat,
+      validation_level,
+      dependency_types,
+      files,
+    },
     module_graph_2,
     doc_nodes_json,
     doc_search_json,
     dependencies,
     npm_tarball,
+    specifiers,
     readme_path,
     meta,
   })
 }
 
+/// Collects the imports of the modules of the package that point to a package
+/// on JSR or npm, or to a runtime built-in module, together with what they
+/// resolved to, so that authors can check that the registry interpreted their
+/// `jsr:` and `npm:` imports the way they intended.
+fn collect_published_specifiers(
+  graph: &ModuleGraph,
+  analyzer: &CapturingModuleAnalyzer,
+) -> Vec<PublishedSpecifier> {
+  let mut specifiers = BTreeSet::new();
+  for module in graph.modules() {
+    if module.specifier().scheme() != "file" {
+      continue;
+    }
+    let Some(js) = module.js() else {
+      continue;
+    };
+    let imported_names = analyzer
+      .get_parsed_source(module.specifier())
+      .map(|parsed_source| collect_imported_names(&parsed_source))
+      .unwrap_or_default();
+    for (specifier, dep) in &js.dependencies {
+      let resolved = [
+        dep.maybe_code.maybe_specifier(),
+        dep.maybe_type.maybe_specifier(),
+      ];
+      for resolved in resolved.into_iter().flatten() {
+        let kind = match resolved.scheme() {
+          "jsr" => PublishedSpecifierKind::Jsr,
+          "npm" => PublishedSpecifierKind::Npm,
+          "node" => PublishedSpecifierKind::Node,
+          "bun" => PublishedSpecifierKind::Bun,
+          _ => continue,
+        };
+        specifiers.insert(PublishedSpecifier {
+          referrer: module.specifier().path().to_string(),
+          specifier: specifier.clone(),
+          resolved: resolved.to_string(),
+          kind,
+          imported_names: imported_names
+            .get(specifier)
+            .map(|names| names.iter().cloned().collect())
+            .unwrap_or_default(),
+        });
+      }
+    }
+  }
+  specifiers.into_iter().collect()
+}
+
+/// Collects the names that the static imports and re-exports of a module take
+/// from each specifier.
+fn collect_imported_names(
+  parsed_source: &ParsedSource,
+) -> HashMap<String, BTreeSet<String>> {
+  use deno_ast::swc::ast;
+
+  let export_name = |name: &ast::ModuleExportName| match name {
+    ast::ModuleExportName::Ident(ident) => ident.sym.to_string(),
+    ast::ModuleExportName::Str(str) => str.value.to_string(),
+  };
+
+  let mut names: HashMap<String, BTreeSet<String>> = HashMap::new();
+  for item in parsed_source.program_ref().body() {
+    let deno_ast::ModuleItemRef::ModuleDecl(decl) = item else {
+      continue;
+    };
+    match decl {
+      ast::ModuleDecl::Import(n) => {
+        let entry = names.entry(n.src.value.to_string()).or_default();
+        for specifier in &n.specifiers {
+          entry.insert(match specifier {
+            ast::ImportSpecifier::Named(s) => s
+              .imported
+              .as_ref()
+              .map(export_name)
+              .unwrap_or_else(|| s.local.sym.to_string()),
+            ast::ImportSpecifier::Default(_) => "default".to_string(),
+            ast::ImportSpecifier::Namespace(_) => "*".to_string(),
+          });
+        }
+      }
+      ast::ModuleDecl::ExportNamed(n) => {
+        let Some(src) = &n.src else {
+          continue;
+        };
+        let entry = names.entry(src.value.to_string()).or_default();
+        for specifier in &n.specifiers {
+          entry.insert(match specifier {
+            ast::ExportSpecifier::Named(s) => export_name(&s.orig),
+            ast::ExportSpecifier::Default(_) => "default".to_string(),
+            ast::ExportSpecifier::Namespace(_) => "*".to_string(),
+          });
+        }
+      }
+      ast::ModuleDecl::ExportAll(n) => {
+        names
+          .entry(n.src.value.to_string())
+          .or_default()
+          .insert("*".to_string());
+      }
+      _ => {}
+    }
+  }
+  names
+}
+
+/// Computes the share of exported symbols of each entrypoint that have JSDoc,
+/// keyed by export name.
+fn collect_docs_coverage(
+  exports: &ExportsMap,
+  doc_nodes_by_url: &DocNodesByUrl,
+) -> IndexMap<String, f32> {
+  exports
+    .iter()
+    .map(|(key, path)| {
+      let url =
+        Url::parse(&format!("file://{}", path.strip_prefix('.').unwrap()))
+          .unwrap();
+      let nodes = doc_nodes_by_url.get(&url).into_iter().flatten();
+      (key.clone(), percentage_of_symbols_with_docs(nodes))
+    })
+    .collect()
+}
+
+/// Collects the exported symbols of each entrypoint that are marked
+/// `@deprecated` in their JSDoc.
+fn collect_deprecated_symbols(
+  exports: &ExportsMap,
+  doc_nodes_by_url: &DocNodesByUrl,
+) -> IndexMap<String, Vec<DeprecatedSymbol>> {
+  exports
+    .iter()
+    .filter_map(|(key, path)| {
+      let url =
+        Url::parse(&format!("file://{}", path.strip_prefix('.').unwrap()))
+          .unwrap();
+      let mut seen = HashSet::new();
+      let symbols = doc_nodes_by_url
+        .get(&url)?
+        .iter()
+        .filter(|node| {
+          node.kind() != DocNodeKind::ModuleDoc
+            && node.kind() != DocNodeKind::Import
+            && node.declaration_kind != deno_doc::node::DeclarationKind::Private
+        })
+        .filter_map(|node| {
+          let message = node.js_doc.tags.iter().find_map(|tag| match tag {
+            deno_doc::js_doc::JsDocTag::Deprecated { doc } => Some(doc),
+            _ => None,
+          })?;
+          // Overloads of a function share a name.
+          seen
+            .insert(node.get_name().to_string())
+            .then(|| DeprecatedSymbol {
+              name: node.get_name().to_string(),
+              message: message.as_ref().map(|doc| doc.to_string()),
+            })
+        })
+        .collect::<Vec<_>>();
+      (!symbols.is_empty()).then(|| (key.clone(), symbols))
+    })
+    .collect()
+}
+
+/// Collects stats about the modules of the package that are statically
+/// reachable from the given entrypoint. Dynamic imports are not followed, as
+/// they are not loaded when the entrypoint is imported.
+fn collect_entrypoint_stats(
+  graph: &ModuleGraph,
+  analyzer: &CapturingModuleAnalyzer,
+  entrypoint: &ModuleSpecifier,
+) -> EntrypointStats {
+  let mut stats = EntrypointStats::default();
+  let mut external_dependencies = HashSet::new();
+  let mut seen = HashSet::new();
+  let mut pending = vec![entrypoint.clone()];
+  while let Some(specifier) = pending.pop() {
+    if !seen.insert(specifier.clone()) {
+      continue;
+    }
+    match specifier.scheme() {
+      "jsr" => {
+        if let Ok(req) = JsrPackageReqReference::from_str(specifier.as_str()) {
+          external_dependencies.insert(format!("jsr:{}", req.req().name));
+        }
+        continue;
+      }
+      "npm" => {
+        if let Ok(req) = NpmPackageReqReference::from_str(specifier.as_str()) {
+          external_dependencies.insert(format!("npm:{}", req.req().name));
+        }
+        continue;
+      }
+      "file" => {}
+      _ => continue,
+    }
+    match graph.get(&specifier) {
+      Some(deno_graph::Module::Js(js)) => {
+        stats.module_count += 1;
+        stats.total_bytes += js.source.len() as u64;
+        if !stats.top_level_await {
+          stats.top_level_await = analyzer
+            .get_parsed_source(&specifier)
+            .is_some_and(|parsed_source| has_top_level_await(&parsed_source));
+        }
+        for dep in js.dependencies.values() {
+          if dep.is_dynamic {
+            continue;
+          }
+          if let Some(specifier) = dep.maybe_code.maybe_specifier() {
+            pending.push(specifier.clone());
+          }
+        }
+      }
+      Some(deno_graph::Module::Json(json)) => {
+        stats.module_count += 1;
+        stats.total_bytes += json.source.len() as u64;
+      }
+      _ => {}
+    }
+  }
+  stats.external_dependency_count = external_dependencies.len() as u32;
+  stats
+}
+
+fn has_top_level_await(parsed_source: &ParsedSource) -> bool {
+  use deno_ast::swc::ast;
+  use deno_ast::swc::visit::Visit;
+  use deno_ast::swc::visit::VisitWith;
+
+  /// Finds `await` expressions and `for await` loops that are not nested in a
+  /// function.
+  struct TopLevelAwaitVisitor {
+    found: bool,
+  }
+
+  impl Visit for TopLevelAwaitVisitor {
+    fn visit_await_expr(&mut self, _: &ast::AwaitExpr) {
+      self.found = true;
+    }
+
+    fn visit_for_of_stmt(&mut self, n: &ast::ForOfStmt) {
+      if n.is_await {
+        self.found = true;
+      } else {
+        n.visit_children_with(self);
+      }
+    }
+
+    fn visit_function(&mut self, _: &ast::Function) {}
+    fn visit_arrow_expr(&mut self, _: &ast::ArrowExpr) {}
+    fn visit_constructor(&mut self, _: &ast::Constructor) {}
+    fn visit_getter_prop(&mut self, _: &ast::GetterProp) {}
+    fn visit_setter_prop(&mut self, _: &ast::SetterProp) {}
+  }
+
+  let mut visitor = TopLevelAwaitVisitor { found: false };
+  match parsed_source.program_ref() {
+    deno_ast::ProgramRef::Module(module) => module.visit_with(&mut visitor),
+    deno_ast::ProgramRef::Script(script) => script.visit_with(&mut visitor),
+  }
+  visitor.found
+}
+
 static INDENTED_CODE_BLOCK_RE: Lazy<BytesRegex> =
   Lazy::new(|| BytesRegex::new(r#"\n\s*?\n( {4}|\t)[^\S\n]*\S"#).unwrap());
 
@@ -330,10 +862,11 @@ fn generate_score(
       readme.is_some(),
     ),
     percentage_documented_symbols: percentage_of_symbols_with_docs(
-      doc_nodes_by_url,
+      doc_nodes_by_url.values().flatten(),
     ),
     all_fast_check,
     has_provenance: false, // Provenance score is updated after version publish
+    ..Default::default()
   }
 }
 
@@ -363,24 +896,24 @@ fn all_entrypoints_have_module_doc(
   true
 }
 
-fn percentage_of_symbols_with_docs(doc_nodes_by_url: &DocNodesByUrl) -> f32 {
+fn percentage_of_symbols_with_docs<'a>(
+  nodes: impl Iterator<Item = &'a DocNode>,
+) -> f32 {
   let mut total_symbols = 0;
   let mut documented_symbols = 0;
 
-  for (_specifier, nodes) in doc_nodes_by_url {
-    for node in nodes {
-      if node.kind() == DocNodeKind::ModuleDoc
-        || node.kind() == DocNodeKind::Import
-        || node.declaration_kind == deno_doc::node::DeclarationKind::Private
-      {
-        continue;
-      }
+  for node in nodes {
+    if node.kind() == DocNodeKind::ModuleDoc
+      || node.kind() == DocNodeKind::Import
+      || node.declaration_kind == deno_doc::node::DeclarationKind::Private
+    {
+      continue;
+    }
 
-      total_symbols += 1;
+    total_symbols += 1;
 
-      if !node.js_doc.is_empty() {
-        documented_symbols += 1;
-      }
+    if !node.js_doc.is_empty() {
+      documented_symbols += 1;
     }
   }
 
@@ -503,8 +1036,12 @@ pub struct RebuildNpmTarballData {
   pub name: PackageName,
   pub version: Version,
   pub exports: ExportsMap,
+  pub bin: BinMap,
+  pub engines: PackageVersionEngines,
+  pub runtime_compat: RuntimeCompat,
+  pub validation_level: ValidationLevel,
   pub files: HashSet<PackagePath>,
-  pub dependencies: Vec<(DependencyKind, PackageReqReference)>,
+  pub dependencies: Vec<(DependencyKind, DependencyType, PackageReqReference)>,
 }
 
 // We have to spawn another tokio runtime, because
@@ -536,6 +1073,10 @@ async fn rebuild_npm_tarball_inner(
     name,
     version,
     exports,
+    bin,
+    engines,
+    runtime_compat,
+    validation_level,
     files,
     dependencies,
   } = data;
@@ -554,6 +1095,19 @@ async fn rebuild_npm_tarball_inner(
     let url = Url::parse(&format!("file://{}", path)).unwrap();
     roots.push(url);
   }
+  for (_, path) in bin.iter() {
+    let path = path.strip_prefix('.').unwrap();
+    let path = PackagePath::new(path.to_string()).map_err(|error| {
+      PublishError::InvalidPath {
+        path: path.to_string(),
+        error,
+      }
+    })?;
+    let url = Url::parse(&format!("file://{}", path)).unwrap();
+    if !roots.contains(&url) {
+      roots.push(url);
+    }
+  }
 
   let module_analyzer = ModuleAnalyzer::default();
 
@@ -594,15 +1148,21 @@ async fn rebuild_npm_tarball_inner(
     )
     .await;
   graph.valid()?;
-  graph.build_fast_check_type_graph(BuildFastCheckTypeGraphOptions {
-    fast_check_cache: Default::default(),
-    fast_check_dts: true,
-    jsr_url_provider: &PassthroughJsrUrlProvider,
-    es_parser: Some(&module_analyzer.analyzer),
-    resolver: None,
-    npm_resolver: None,
-    workspace_fast_check: WorkspaceFastCheckOption::Enabled(&workspace_members),
-  });
+  // The fast check output is only used for declarations when the public API
+  // is fully validated.
+  if validation_level == ValidationLevel::Full {
+    graph.build_fast_check_type_graph(BuildFastCheckTypeGraphOptions {
+      fast_check_cache: Default::default(),
+      fast_check_dts: true,
+      jsr_url_provider: &PassthroughJsrUrlProvider,
+      es_parser: Some(&module_analyzer.analyzer),
+      resolver: None,
+      npm_resolver: None,
+      workspace_fast_check: WorkspaceFastCheckOption::Enabled(
+        &workspace_members,
+      ),
+    });
+  }
 
   let npm_tarball = create_npm_tarball(NpmTarballOptions {
     graph: &graph,
@@ -612,6 +1172,10 @@ async fn rebuild_npm_tarball_inner(
     package: &name,
     version: &version,
     exports: &exports,
+    bin: &bin,
+    engines: &engines,
+    runtime_compat: &runtime_compat,
+    validation_level,
     files: NpmTarballFiles::FromBucket {
       files: &files,
       modules_bucket: &modules_bucket,
@@ -623,6 +1187,129 @@ async fn rebuild_npm_tarball_inner(
   Ok(npm_tarball)
 }
 
+pub struct RebuildVersionAnalysisData {
+  pub scope: ScopeName,
+  pub name: PackageName,
+  pub version: Version,
+  pub exports: ExportsMap,
+  pub bin: BinMap,
+  pub files: HashSet<PackagePath>,
+}
+
+pub struct RebuildVersionAnalysisOutput {
+  pub module_graph_2: HashMap<String, ModuleInfo>,
+  pub doc_nodes_json: Bytes,
+}
+
+/// Analyzes an already published package version again, from the files in the
+/// modules bucket, to regenerate its docs and module graph.
+// We have to spawn another tokio runtime, because
+// `deno_graph::ModuleGraph::build` is not thread-safe.
+#[tokio::main(flavor = "current_thread")]
+pub async fn rebuild_version_analysis(
+  span: tracing::Span,
+  modules_bucket: BucketWithQueue,
+  data: RebuildVersionAnalysisData,
+) -> Result<RebuildVersionAnalysisOutput, anyhow::Error> {
+  rebuild_version_analysis_inner(modules_bucket, data)
+    .instrument(span)
+    .await
+}
+
+#[instrument(
+  name = "rebuild_version_analysis",
+  skip(modules_bucket, data),
+  err
+)]
+async fn rebuild_version_analysis_inner(
+  modules_bucket: BucketWithQueue,
+  data: RebuildVersionAnalysisData,
+) -> Result<RebuildVersionAnalysisOutput, anyhow::Error> {
+  let RebuildVersionAnalysisData {
+    scope,
+    name,
+    version,
+    exports,
+    bin,
+    files,
+  } = data;
+
+  let mut roots = vec![];
+  for (_, path) in exports.iter() {
+    let path = path.strip_prefix('.').unwrap();
+    let path = PackagePath::new(path.to_string()).map_err(|error| {
+      PublishError::InvalidPath {
+        path: path.to_string(),
+        error,
+      }
+    })?;
+    roots.push(Url::parse(&format!("file://{}", path)).unwrap());
+  }
+  // Executables are part of the module graph, but are not documented.
+  let mut bin_roots = vec![];
+  for (_, path) in bin.iter() {
+    let path = path.strip_prefix('.').unwrap();
+    let path = PackagePath::new(path.to_string()).map_err(|error| {
+      PublishError::InvalidPath {
+        path: path.to_string(),
+        error,
+      }
+    })?;
+    let url = Url::parse(&format!("file://{}", path)).unwrap();
+    if !roots.contains(&url) {
+      bin_roots.push(url);
+    }
+  }
+
+  let module_analyzer = ModuleAnalyzer::default();
+
+  let mut graph = deno_graph::ModuleGraph::new(GraphKind::All);
+  let workspace_member = WorkspaceMember {
+    base: Url::parse("file:///").unwrap(),
+    name: StackString::from_string(format!("@{}/{}", scope, name)),
+    version: Some(version.0.clone()),
+    exports: exports.clone().into_inner(),
+  };
+  graph
+    .build(
+      roots.iter().chain(bin_roots.iter()).cloned().collect(),
+      &GcsLoader {
+        files: &files,
+        bucket: &modules_bucket,
+        scope: &scope,
+        name: &name,
+        version: &version,
+      },
+      BuildOptions {
+        is_dynamic: false,
+        module_analyzer: &module_analyzer,
+        imports: Default::default(),
+        file_system: &NullFileSystem,
+        jsr_url_provider: &PassthroughJsrUrlProvider,
+        passthrough_jsr_specifiers: true,
+        resolver: Some(&JsrResolver {
+          member: workspace_member,
+        }),
+        npm_resolver: Default::default(),
+        reporter: Default::default(),
+        executor: Default::default(),
+        locker: None,
+      },
+    )
+    .await;
+  graph.valid()?;
+
+  let doc_nodes =
+    crate::docs::generate_docs(roots, &graph, &module_analyzer.analyzer)?;
+  let doc_nodes_json = serde_json::to_vec(&doc_nodes).unwrap().into();
+  let module_graph_2 = module_analyzer.take_module_graph_2();
+
+  Ok(RebuildVersionAnalysisOutput {
+    module_graph_2,
+    doc_nodes_json,
+  })
+}
+
 struct GcsLoader<'a> {
   files: &'a HashSet<PackagePath>,
   bucket: &'a BucketWithQueue,
@@ -813,6 +1500,45 @@ fn collect_dependencies(
   Ok(dependencies)
 }
 
+/// Marks the imported dependencies that are listed in the `peerDependencies`
+/// or `optionalDependencies` field of the config file with the respective
+/// type. Every listed dependency must be imported by the package.
+fn assign_dependency_types(
+  dependencies: HashSet<(DependencyKind, PackageReqReference)>,
+  dependency_types: &HashMap<(DependencyKind, String), DependencyType>,
+) -> Result<
+  HashSet<(DependencyKind, DependencyType, PackageReqReference)>,
+  String,
+> {
+  let mut unused = dependency_types.keys().collect::<HashSet<_>>();
+  let dependencies = dependencies
+    .into_iter()
+    .map(|(kind, req)| {
+      let key = (kind, req.req.name.to_string());
+      let dependency_type = match dependency_types.get_key_value(&key) {
+        Some((key, dependency_type)) => {
+          unused.remove(key);
+          *dependency_type
+        }
+        None => DependencyType::Regular,
+      };
+      (kind, dependency_type, req)
+    })
+    .collect();
+
+  if let Some((kind, name)) = unused.into_iter().next() {
+    let scheme = match kind {
+      DependencyKind::Jsr => "jsr",
+      DependencyKind::Npm => "npm",
+    };
+    return Err(format!(
+      "'{scheme}:{name}' is listed as a peer or optional dependency, but is not imported by the package"
+    ));
+  }
+
+  Ok(dependencies)
+}
+
 fn check_for_banned_extensions(
   parsed_source: &ParsedSource,
 ) -> Result<(), PublishError> {
@@ -1,49 +1,166 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::analysis::rebuild_version_analysis;
+use crate::analysis::RebuildVersionAnalysisData;
+use crate::analysis::RebuildVersionAnalysisOutput;
+use crate::api::package::check_not_latest_version;
 use crate::buckets::Buckets;
-use crate::orama::OramaClient;
+use crate::buckets::UploadTaskBody;
+use crate::gcp::GcsUploadOptions;
+use crate::gcp::CACHE_CONTROL_IMMUTABLE;
+use crate::gcs_paths;
+use crate::signing::MetadataSigner;
+use crate::tarball::gcs_tarball_path;
+use crate::NpmIncludeYankedVersions;
 use crate::NpmUrl;
 use crate::RegistryUrl;
+use chrono::Utc;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use hyper::Body;
 use hyper::Request;
+use hyper::Response;
+use hyper::StatusCode;
 use routerify::prelude::RequestExt;
 use routerify::Router;
 use tracing::field;
 use tracing::instrument;
-use tracing::Instrument;
 use tracing::Span;
 
 use crate::db::*;
+use crate::iam::IamHandler;
+use crate::iam::IamInfo;
+use crate::iam::Principal;
 use crate::iam::ReqIamExt;
-use crate::publish::publish_task;
+use crate::npm::NPM_TARBALL_REVISION;
+use crate::publish::enqueue_publishing_task;
+use crate::publish::publish_queue;
+use crate::publish::upload_package_manifest;
+use crate::publish::upload_version_manifest;
+use crate::tarball::FileInfo;
+use crate::tasks::build_npm_tarball;
+use crate::tasks::download_ingestion_window_start;
+use crate::tasks::enqueue_npm_tarball_build;
+use crate::tasks::ingest_download_counts;
+use crate::tasks::upload_npm_version_manifest;
+use crate::tasks::LogsBigQueryTable;
+use crate::tasks::NpmTarballBuildQueue;
+use crate::tasks::DOWNLOAD_INGESTION_WINDOW;
+use crate::tasks::MAX_DOWNLOAD_INGESTION_RANGE;
 use crate::util;
 use crate::util::decode_json;
 use crate::util::pagination;
 use crate::util::search;
 use crate::util::ApiResult;
+use crate::util::DeprecatedRouteUsages;
 use crate::util::RequestIdExt;
 
 use super::map_unique_violation;
 use super::types::*;
 use super::ApiError;
-use super::PublishQueue;
+
+/// How long the tarball of a version deleted by staff is retained, and the
+/// version can be restored.
+pub const DELETED_VERSION_RETENTION: chrono::Duration =
+  chrono::Duration::days(30);
+
+/// How long the old name of a renamed or moved package is redirected to the
+/// new name by default.
+pub const PACKAGE_REDIRECT_GRACE_PERIOD: chrono::Duration =
+  chrono::Duration::days(180);
 
 pub fn admin_router() -> Router<Body, ApiError> {
   Router::builder()
     .get("/aliases", util::auth(util::json(list_aliases)))
     .post("/aliases", util::auth(util::json(create_alias)))
-    .get("/users", util::auth(util::json(list_users)))
+    .get(
+      "/package_redirects",
+      util::auth(util::json(list_package_redirects)),
+    )
+    .post(
+      "/package_redirects",
+      util::auth(util::json(create_package_redirect)),
+    )
+    .delete(
+      "/package_redirects/:scope/:package",
+      util::auth(delete_package_redirect),
+    )
+    .get("/users", util::auth(util::paginated_json(list_users)))
     .patch("/users/:user_id", util::auth(util::json(update_user)))
-    .get("/scopes", util::auth(util::json(list_scopes)))
+    .get(
+      "/users/:user_id/moderation_flags",
+      util::auth(util::json(list_user_moderation_flags)),
+    )
+    .post(
+      "/users/:user_id/moderation_flags",
+      util::auth(util::json(create_user_moderation_flag)),
+    )
+    .delete(
+      "/users/:user_id/moderation_flags/:flag_id",
+      util::auth(delete_user_moderation_flag),
+    )
+    .get("/scopes", util::auth(util::paginated_json(list_scopes)))
     .post("/scopes", util::auth(util::json(assign_scope)))
     .patch("/scopes/:scope", util::auth(util::json(patch_scopes)))
+    .get(
+      "/scope_recovery_requests",
+      util::auth(util::paginated_json(list_scope_recovery_requests)),
+    )
+    .post(
+      "/scope_recovery_requests/:request_id/approve",
+      util::auth(util::json(approve_scope_recovery_request)),
+    )
+    .post(
+      "/scope_recovery_requests/:request_id/reject",
+      util::auth(util::json(reject_scope_recovery_request)),
+    )
     .get(
       "/publishing_tasks",
-      util::auth(util::json(list_publishing_tasks)),
+      util::auth(util::paginated_json(list_publishing_tasks)),
     )
     .post(
       "/publishing_tasks/:publishing_task/requeue",
       util::auth(util::json(requeue_publishing_tasks)),
     )
+    .get(
+      "/npm_tarball_build_progress",
+      util::auth(util::json(get_npm_tarball_build_progress)),
+    )
+    .get(
+      "/download_ingestion",
+      util::auth(util::paginated_json(list_download_ingestion_checkpoints)),
+    )
+    .post(
+      "/download_ingestion/replay",
+      util::auth(util::json(replay_download_ingestion)),
+    )
+    .get(
+      "/database_pool",
+      util::auth(util::json(get_database_pool_stats)),
+    )
+    .get(
+      "/deprecated_routes",
+      util::auth(util::json(list_deprecated_route_usages)),
+    )
+    .post("/iam/simulate", util::auth(util::json(simulate_iam)))
+    .get(
+      "/consistency_audit",
+      util::auth(util::json(get_consistency_audit)),
+    )
+    .post(
+      "/packages/:scope/:package/versions/:version/rebuild",
+      util::auth(util::json(rebuild_package_version)),
+    )
+    .delete(
+      "/packages/:scope/:package/versions/:version",
+      util::auth(util::json(delete_package_version)),
+    )
+    .post(
+      "/packages/:scope/:package/versions/:version/restore",
+      util::auth(util::json(restore_package_version)),
+    )
     .build()
     .unwrap()
 }
@@ -87,6 +204,112 @@ pub async fn create_alias(mut req: Request<Body>) -> ApiResult<ApiAlias> {
   Ok(alias.into())
 }
 
+#[instrument(name = "GET /api/admin/package_redirects", skip(req), err)]
+pub async fn list_package_redirects(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiPackageRedirect>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  let redirects = db.list_package_redirects().await?;
+
+  Ok(redirects.into_iter().map(Into::into).collect())
+}
+
+/// Redirects the old name of a package that was renamed or moved to another
+/// scope to its new name, so that the registry metadata, the npm
+/// compatibility layer, and the API keep working for the old name until the
+/// redirect expires.
+#[instrument(
+  name = "POST /api/admin/package_redirects",
+  skip(req),
+  err,
+  fields(scope, package, target_scope, target_package)
+)]
+pub async fn create_package_redirect(
+  mut req: Request<Body>,
+) -> ApiResult<ApiPackageRedirect> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let ApiCreatePackageRedirectRequest {
+    scope,
+    package,
+    target_scope,
+    target_package,
+    expires_at,
+  } = decode_json(&mut req).await?;
+
+  let span = Span::current();
+  span.record("scope", field::display(&scope));
+  span.record("package", field::display(&package));
+  span.record("target_scope", field::display(&target_scope));
+  span.record("target_package", field::display(&target_package));
+
+  if scope == target_scope && package == target_package {
+    return Err(ApiError::MalformedRequest {
+      msg: "a package can not be redirected to itself".into(),
+    });
+  }
+  let expires_at =
+    expires_at.unwrap_or_else(|| Utc::now() + PACKAGE_REDIRECT_GRACE_PERIOD);
+  if expires_at <= Utc::now() {
+    return Err(ApiError::MalformedRequest {
+      msg: "expiresAt must be in the future".into(),
+    });
+  }
+
+  let db = req.data::<Database>().unwrap();
+  if db.get_package(&scope, &package).await?.is_some() {
+    return Err(ApiError::PackageRedirectSourceExists);
+  }
+  db.get_package(&target_scope, &target_package)
+    .await?
+    .ok_or(ApiError::PackageNotFound)?;
+
+  let redirect = db
+    .upsert_package_redirect(
+      &scope,
+      &package,
+      &target_scope,
+      &target_package,
+      expires_at,
+    )
+    .await?;
+
+  Ok(redirect.into())
+}
+
+#[instrument(
+  name = "DELETE /api/admin/package_redirects/:scope/:package",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn delete_package_redirect(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let db = req.data::<Database>().unwrap();
+  if !db.delete_package_redirect(&scope, &package).await? {
+    return Err(ApiError::PackageRedirectNotFound);
+  }
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
 #[instrument(name = "GET /api/admin/users", skip(req), err)]
 pub async fn list_users(req: Request<Body>) -> ApiResult<ApiList<ApiFullUser>> {
   let iam = req.iam();
@@ -143,6 +366,89 @@ pub async fn update_user(mut req: Request<Body>) -> ApiResult<ApiFullUser> {
   }
 }
 
+#[instrument(
+  name = "GET /api/admin/users/:user_id/moderation_flags",
+  skip(req),
+  err,
+  fields(user_id)
+)]
+pub async fn list_user_moderation_flags(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiUserModerationFlag>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let user_id = req.param_uuid("user_id")?;
+  Span::current().record("user_id", field::display(&user_id));
+
+  let db = req.data::<Database>().unwrap();
+  db.get_user(user_id).await?.ok_or(ApiError::UserNotFound)?;
+  let flags = db.list_user_moderation_flags(user_id).await?;
+  Ok(flags.into_iter().map(Into::into).collect())
+}
+
+/// Flags an account, which holds it to the limits of new accounts until the
+/// flag is removed.
+#[instrument(
+  name = "POST /api/admin/users/:user_id/moderation_flags",
+  skip(req),
+  err,
+  fields(user_id)
+)]
+pub async fn create_user_moderation_flag(
+  mut req: Request<Body>,
+) -> ApiResult<ApiUserModerationFlag> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+  let staff_id = iam.check_current_user_access()?.id;
+
+  let user_id = req.param_uuid("user_id")?;
+  Span::current().record("user_id", field::display(&user_id));
+  let ApiCreateUserModerationFlagRequest { reason } =
+    decode_json(&mut req).await?;
+  if reason.trim().is_empty() || reason.len() > 1000 {
+    return Err(ApiError::MalformedRequest {
+      msg: "reason must be between 1 and 1000 characters long".into(),
+    });
+  }
+
+  let db = req.data::<Database>().unwrap();
+  db.get_user(user_id).await?.ok_or(ApiError::UserNotFound)?;
+  let flag = db
+    .create_user_moderation_flag(user_id, reason.trim(), staff_id)
+    .await?;
+  Ok(flag.into())
+}
+
+#[instrument(
+  name = "DELETE /api/admin/users/:user_id/moderation_flags/:flag_id",
+  skip(req),
+  err,
+  fields(user_id, flag_id)
+)]
+pub async fn delete_user_moderation_flag(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let user_id = req.param_uuid("user_id")?;
+  let flag_id = req.param_uuid("flag_id")?;
+  Span::current().record("user_id", field::display(&user_id));
+  Span::current().record("flag_id", field::display(&flag_id));
+
+  let db = req.data::<Database>().unwrap();
+  if !db.delete_user_moderation_flag(user_id, flag_id).await? {
+    return Err(ApiError::UserModerationFlagNotFound);
+  }
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
 #[instrument(name = "GET /api/admin/scopes", skip(req), err)]
 pub async fn list_scopes(
   req: Request<Body>,
@@ -203,6 +509,71 @@ pub async fn patch_scopes(mut req: Request<Body>) -> ApiResult<ApiFullScope> {
   Ok(scope.into())
 }
 
+#[instrument(name = "GET /api/admin/scope_recovery_requests", skip(req), err)]
+pub async fn list_scope_recovery_requests(
+  req: Request<Body>,
+) -> ApiResult<ApiList<ApiScopeRecoveryRequest>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  let (start, limit) = pagination(&req);
+
+  let (total, requests) =
+    db.list_pending_scope_recovery_requests(start, limit).await?;
+  Ok(ApiList {
+    items: requests.into_iter().map(Into::into).collect(),
+    total,
+  })
+}
+
+#[instrument(
+  name = "POST /api/admin/scope_recovery_requests/:request_id/approve",
+  skip(req),
+  err,
+  fields(request_id)
+)]
+pub async fn approve_scope_recovery_request(
+  req: Request<Body>,
+) -> ApiResult<ApiScopeRecoveryRequest> {
+  resolve_scope_recovery_request(req, ScopeRecoveryStatus::Approved).await
+}
+
+#[instrument(
+  name = "POST /api/admin/scope_recovery_requests/:request_id/reject",
+  skip(req),
+  err,
+  fields(request_id)
+)]
+pub async fn reject_scope_recovery_request(
+  req: Request<Body>,
+) -> ApiResult<ApiScopeRecoveryRequest> {
+  resolve_scope_recovery_request(req, ScopeRecoveryStatus::Rejected).await
+}
+
+async fn resolve_scope_recovery_request(
+  req: Request<Body>,
+  status: ScopeRecoveryStatus,
+) -> ApiResult<ApiScopeRecoveryRequest> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+  let staff_id = iam.check_current_user_access()?.id;
+
+  let request_id = req.param_uuid("request_id")?;
+  Span::current().record("request_id", field::display(&request_id));
+
+  let db = req.data::<Database>().unwrap();
+  db.get_scope_recovery_request(request_id)
+    .await?
+    .ok_or(ApiError::ScopeRecoveryRequestNotFound)?;
+
+  let request = db
+    .resolve_scope_recovery_request(request_id, status, Some(staff_id))
+    .await?
+    .ok_or(ApiError::ScopeRecoveryRequestNotPending)?;
+  Ok(request.into())
+}
+
 #[instrument(
   name = "POST /api/admin/scopes",
   skip(req),
@@ -286,90 +657,1056 @@ pub async fn requeue_publishing_tasks(req: Request<Body>) -> ApiResult<()> {
     .await?;
   }
 
-  let publish_queue = req.data::<PublishQueue>().unwrap().0.clone();
-  let orama_client = req.data::<Option<OramaClient>>().unwrap().clone();
+  let queue = publish_queue(&req);
+  enqueue_publishing_task(&*queue, publishing_task_id).await?;
 
-  if let Some(queue) = publish_queue {
-    let body = serde_json::to_vec(&publishing_task_id).unwrap();
-    queue.task_buffer(None, Some(body.into())).await?;
-  } else {
-    let buckets = req.data::<Buckets>().unwrap().clone();
-    let registry = req.data::<RegistryUrl>().unwrap().0.clone();
-    let npm_url = req.data::<NpmUrl>().unwrap().0.clone();
+  Ok(())
+}
 
-    let span = Span::current();
-    let fut = publish_task(
-      publishing_task_id,
-      buckets,
-      registry,
-      npm_url,
-      db,
-      orama_client,
-    )
-    .instrument(span);
-    tokio::spawn(fut);
-  }
+#[instrument(
+  name = "GET /api/admin/npm_tarball_build_progress",
+  skip(req),
+  err
+)]
+pub async fn get_npm_tarball_build_progress(
+  req: Request<Body>,
+) -> ApiResult<ApiNpmTarballBuildProgress> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
 
-  Ok(())
+  let db = req.data::<Database>().unwrap();
+  let progress = db
+    .get_npm_tarball_build_progress(NPM_TARBALL_REVISION as i32)
+    .await?;
+
+  Ok(progress.into())
 }
 
-#[cfg(test)]
-mod tests {
-  use crate::api::ApiFullScope;
-  use crate::api::ApiFullUser;
-  use crate::api::ApiList;
-  use crate::api::ApiScope;
-  use crate::util::test::ApiResultExt;
-  use crate::util::test::TestSetup;
-  use hyper::StatusCode;
-  use serde_json::json;
+#[instrument(name = "GET /api/admin/database_pool", skip(req), err)]
+pub async fn get_database_pool_stats(
+  req: Request<Body>,
+) -> ApiResult<ApiDatabasePoolStats> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
 
-  #[tokio::test]
-  async fn list_users() {
-    let mut t = TestSetup::new().await;
+  let db = req.data::<Database>().unwrap();
+  Ok(db.pool_stats().into())
+}
 
-    let token = t.staff_user.token.clone();
-    let users = t
-      .http()
-      .get("/api/admin/users")
-      .token(Some(&token))
-      .call()
-      .await
-      .unwrap()
-      .expect_ok::<ApiList<ApiFullUser>>()
-      .await;
-    assert_eq!(users.items.len(), 5);
+/// Returns the findings of the latest nightly database consistency audit, or
+/// `null` if no audit has run yet.
+#[instrument(name = "GET /api/admin/consistency_audit", skip(req), err)]
+pub async fn get_consistency_audit(
+  req: Request<Body>,
+) -> ApiResult<Option<ApiConsistencyAudit>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
 
-    let path = format!("/api/admin/users?query={}", t.user2.user.id);
-    let users = t
-      .http()
-      .get(path)
-      .token(Some(&token))
-      .call()
-      .await
-      .unwrap()
-      .expect_ok::<ApiList<ApiFullUser>>()
-      .await;
-    assert_eq!(users.items.len(), 1);
-    assert_eq!(users.items[0].id, t.user2.user.id);
-  }
+  let db = req.data::<Database>().unwrap();
+  let audit = db.get_latest_consistency_audit().await?;
+  Ok(audit.map(Into::into))
+}
 
-  #[tokio::test]
-  async fn scope_management() {
-    let mut t = TestSetup::new().await;
+/// Lists who is still calling deprecated routes, per token and user agent.
+/// The counts buffered by other instances are included once they are flushed.
+#[instrument(name = "GET /api/admin/deprecated_routes", skip(req), err)]
+pub async fn list_deprecated_route_usages(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiDeprecatedRouteUsage>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
 
-    assert_eq!(t.scope.package_limit, 250);
-    assert_eq!(t.scope.new_package_per_week_limit, 200);
-    assert_eq!(t.scope.publish_attempts_per_week_limit, 1000);
+  let db = req.data::<Database>().unwrap();
+  let buffered = req.data::<DeprecatedRouteUsages>().unwrap();
+  buffered.flush(db).await?;
+  let usages = db.list_deprecated_route_usages().await?;
+  Ok(usages.into_iter().map(Into::into).collect())
+}
 
-    let path = format!("/api/admin/scopes/{}", t.scope.scope);
-    let token = t.staff_user.token.clone();
-    let res_scope = t
-      .http()
-      .patch(path)
-      .body_json(json!({
-        "packageLimit": 101,
-        "newPackagePerWeekLimit": 101,
+#[instrument(name = "POST /api/admin/iam/simulate", skip(req), err)]
+pub async fn simulate_iam(
+  mut req: Request<Body>,
+) -> ApiResult<ApiIamSimulateResponse> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let ApiIamSimulateRequest { principal, action } =
+    decode_json(&mut req).await?;
+
+  let db = req.data::<Database>().unwrap();
+  let info = match principal {
+    ApiIamSimulatePrincipal::User { user_id, sudo } => {
+      let user = db.get_user(user_id).await?.ok_or(ApiError::UserNotFound)?;
+      if user.is_blocked {
+        return Ok(denied(ApiError::Blocked));
+      }
+      IamInfo {
+        principal: Principal::User(user),
+        permissions: None,
+        interactive: true,
+        sudo,
+        token_id: None,
+      }
+    }
+    ApiIamSimulatePrincipal::Token { token_id, sudo } => {
+      let token = db
+        .get_token(token_id)
+        .await?
+        .ok_or(ApiError::TokenNotFound)?;
+      if token
+        .expires_at
+        .is_some_and(|expires_at| expires_at < chrono::Utc::now())
+      {
+        return Ok(denied(ApiError::InvalidBearerToken));
+      }
+      let user = db
+        .get_user(token.user_id)
+        .await?
+        .ok_or(ApiError::UserNotFound)?;
+      if user.is_blocked {
+        return Ok(denied(ApiError::Blocked));
+      }
+      IamInfo::from((token, user, sudo))
+    }
+  };
+
+  match IamHandler::new(db, info).simulate(&action.into()).await {
+    Ok(decision) => Ok(decision.into()),
+    Err(ApiError::InternalServerError) => Err(ApiError::InternalServerError),
+    Err(err) => Ok(denied(err)),
+  }
+}
+
+/// Regenerates the artifacts of a single package version, so that a broken
+/// version can be fixed without a bulk backfill. Each requested artifact is
+/// rebuilt independently, and failures are reported per artifact.
+#[instrument(
+  name = "POST /api/admin/packages/:scope/:package/versions/:version/rebuild",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn rebuild_package_version(
+  mut req: Request<Body>,
+) -> ApiResult<ApiRebuildPackageVersionResult> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let body: ApiRebuildPackageVersionRequest = decode_json(&mut req).await?;
+
+  let db = req.data::<Database>().unwrap().clone();
+  let buckets = req.data::<Buckets>().unwrap().clone();
+  let registry_url = req.data::<RegistryUrl>().unwrap().0.clone();
+  let npm_url = req.data::<NpmUrl>().unwrap().0.clone();
+  let npm_include_yanked_versions =
+    req.data::<NpmIncludeYankedVersions>().unwrap().0;
+  let npm_tarball_build_queue =
+    req.data::<NpmTarballBuildQueue>().unwrap().0.clone();
+  let metadata_signer = req.data::<MetadataSigner>().unwrap().clone();
+
+  let package_version = db
+    .get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let mut result = ApiRebuildPackageVersionResult {
+    docs: ApiRebuildStatus::Skipped,
+    metadata: ApiRebuildStatus::Skipped,
+    npm_tarball: ApiRebuildStatus::Skipped,
+  };
+
+  // Docs and metadata both need the package to be analyzed again.
+  if body.docs || body.metadata {
+    let files = db.list_package_files(&scope, &package, &version).await?;
+    let data = RebuildVersionAnalysisData {
+      scope: scope.clone(),
+      name: package.clone(),
+      version: version.clone(),
+      exports: package_version.exports.clone(),
+      bin: package_version.bin.clone(),
+      files: files.iter().map(|file| file.path.clone()).collect(),
+    };
+    let span = Span::current();
+    let modules_bucket = buckets.modules_bucket.clone();
+    let analysis = tokio::task::spawn_blocking(|| {
+      rebuild_version_analysis(span, modules_bucket, data)
+    })
+    .await
+    .unwrap();
+
+    match analysis {
+      Ok(RebuildVersionAnalysisOutput {
+        module_graph_2,
+        doc_nodes_json,
+      }) => {
+        if body.docs {
+          let docs_path = gcs_paths::docs_v1_path(&scope, &package, &version);
+          let res = buckets
+            .docs_bucket
+            .upload(
+              docs_path.into(),
+              UploadTaskBody::Bytes(doc_nodes_json),
+              GcsUploadOptions {
+                content_type: Some("application/json".into()),
+                cache_control: Some(CACHE_CONTROL_IMMUTABLE.into()),
+                gzip_encoded: false,
+              },
+            )
+            .await
+            .map_err(anyhow::Error::from);
+          result.docs = rebuild_status(res);
+        }
+        if body.metadata {
+          let res = rebuild_metadata(
+            &db,
+            &buckets,
+            &metadata_signer,
+            &package_version,
+            files,
+            module_graph_2,
+          )
+          .await;
+          result.metadata = rebuild_status(res);
+        }
+      }
+      Err(err) => {
+        let failed = || ApiRebuildStatus::Failed {
+          message: format!("failed to analyze package version: {err}"),
+        };
+        if body.docs {
+          result.docs = failed();
+        }
+        if body.metadata {
+          result.metadata = failed();
+        }
+      }
+    }
+  }
+
+  if body.npm_tarball {
+    db.delete_npm_tarball(
+      &scope,
+      &package,
+      &version,
+      NPM_TARBALL_REVISION as i32,
+    )
+    .await?;
+    result.npm_tarball = if let Some(queue) = &npm_tarball_build_queue {
+      match enqueue_npm_tarball_build(
+        queue.as_ref(),
+        &scope,
+        &package,
+        &version,
+      )
+      .await
+      {
+        Ok(()) => ApiRebuildStatus::Queued,
+        Err(err) => ApiRebuildStatus::Failed {
+          message: err.message().to_string(),
+        },
+      }
+    } else {
+      let res = async {
+        build_npm_tarball(
+          &db,
+          &buckets,
+          &registry_url,
+          &scope,
+          &package,
+          &version,
+        )
+        .await?;
+        upload_npm_version_manifest(
+          &db,
+          &buckets,
+          &npm_url,
+          &scope,
+          &package,
+          npm_include_yanked_versions,
+        )
+        .await
+      }
+      .await;
+      match res {
+        Ok(()) => ApiRebuildStatus::Rebuilt,
+        Err(err) => ApiRebuildStatus::Failed {
+          message: err.message().to_string(),
+        },
+      }
+    };
+  }
+
+  Ok(result)
+}
+
+/// Deletes a package version, for example because of a takedown notice. The
+/// original tarball is moved to the `deleted_versions/` prefix of the
+/// publishing bucket, where it is retained for
+/// [`DELETED_VERSION_RETENTION`], so that the version can be restored. All
+/// other artifacts are derived from the tarball and are deleted.
+#[instrument(
+  name = "DELETE /api/admin/packages/:scope/:package/versions/:version",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn delete_package_version(
+  mut req: Request<Body>,
+) -> ApiResult<ApiDeletedPackageVersion> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+  let staff_id = iam.check_current_user_access()?.id;
+
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let body: ApiDeletePackageVersionRequest = decode_json(&mut req).await?;
+  let reason = body.reason.trim();
+  if reason.is_empty() || reason.chars().count() > 1000 {
+    return Err(ApiError::DeletionReasonInvalid);
+  }
+
+  let db = req.data::<Database>().unwrap().clone();
+  let buckets = req.data::<Buckets>().unwrap().clone();
+  let npm_url = req.data::<NpmUrl>().unwrap().0.clone();
+  let npm_include_yanked_versions =
+    req.data::<NpmIncludeYankedVersions>().unwrap().0;
+  let metadata_signer = req.data::<MetadataSigner>().unwrap().clone();
+
+  db.get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+  if !body.force {
+    check_not_latest_version(&db, &scope, &package, &[version.clone()]).await?;
+  }
+  let publishing_task = db
+    .list_publishing_tasks_for_package(&scope, &package)
+    .await?
+    .into_iter()
+    .find(|task| {
+      task.package_version == version
+        && task.status == PublishingTaskStatus::Success
+    })
+    .ok_or(ApiError::InternalServerError)?;
+  let files = db.list_package_files(&scope, &package, &version).await?;
+
+  copy_tarball(
+    &buckets,
+    &gcs_tarball_path(publishing_task.id),
+    &gcs_paths::deleted_version_tarball_path(publishing_task.id),
+  )
+  .await?;
+
+  let deleted = db
+    .delete_package_version(
+      &scope,
+      &package,
+      &version,
+      publishing_task.id,
+      reason,
+      staff_id,
+      Utc::now() + DELETED_VERSION_RETENTION,
+    )
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  buckets
+    .publishing_bucket
+    .bucket
+    .delete(&gcs_tarball_path(publishing_task.id))
+    .await?;
+  for file in &files {
+    let path = gcs_paths::file_path(&scope, &package, &version, &file.path);
+    buckets.modules_bucket.bucket.delete(&path).await?;
+  }
+  let version_metadata_path =
+    gcs_paths::version_metadata(&scope, &package, &version);
+  buckets
+    .modules_bucket
+    .bucket
+    .delete(&gcs_paths::metadata_signature(&version_metadata_path))
+    .await?;
+  buckets
+    .modules_bucket
+    .bucket
+    .delete(&version_metadata_path)
+    .await?;
+  buckets
+    .docs_bucket
+    .bucket
+    .delete(&gcs_paths::docs_v1_path(&scope, &package, &version))
+    .await?;
+  buckets
+    .npm_bucket
+    .bucket
+    .delete(&gcs_paths::npm_tarball_path(
+      &scope,
+      &package,
+      &version,
+      NPM_TARBALL_REVISION,
+    ))
+    .await?;
+
+  upload_package_manifest(&db, &buckets, &metadata_signer, &scope, &package)
+    .await?;
+  upload_npm_version_manifest(
+    &db,
+    &buckets,
+    &npm_url,
+    &scope,
+    &package,
+    npm_include_yanked_versions,
+  )
+  .await?;
+
+  Ok(deleted.into())
+}
+
+/// Restores a package version that was deleted by staff, by moving its
+/// retained tarball back and processing its original publishing task again.
+/// The processing happens in the background, so the version is available once
+/// the returned publishing task succeeds.
+#[instrument(
+  name = "POST /api/admin/packages/:scope/:package/versions/:version/restore",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn restore_package_version(
+  req: Request<Body>,
+) -> ApiResult<ApiPublishingTask> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap().clone();
+  let buckets = req.data::<Buckets>().unwrap().clone();
+
+  let deleted = db
+    .get_deleted_package_version(&scope, &package, &version)
+    .await?
+    .filter(|deleted| deleted.restorable_until > Utc::now())
+    .ok_or(ApiError::DeletedPackageVersionNotFound)?;
+  let retained_path =
+    gcs_paths::deleted_version_tarball_path(deleted.publishing_task_id);
+
+  copy_tarball(
+    &buckets,
+    &retained_path,
+    &gcs_tarball_path(deleted.publishing_task_id),
+  )
+  .await?;
+  db.restore_deleted_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::DeletedPackageVersionNotFound)?;
+  buckets
+    .publishing_bucket
+    .bucket
+    .delete(&retained_path)
+    .await?;
+
+  let queue = publish_queue(&req);
+  enqueue_publishing_task(&*queue, deleted.publishing_task_id).await?;
+
+  let publishing_task = db
+    .get_publishing_task(deleted.publishing_task_id)
+    .await?
+    .ok_or(ApiError::InternalServerError)?;
+
+  Ok(publishing_task.into())
+}
+
+/// Copies a tarball within the publishing bucket. Tarballs are stored gzip
+/// encoded, but are downloaded decoded, so they are compressed again before
+/// the upload.
+async fn copy_tarball(
+  buckets: &Buckets,
+  from: &str,
+  to: &str,
+) -> Result<(), ApiError> {
+  let tarball = buckets
+    .publishing_bucket
+    .download(from.into())
+    .await?
+    .ok_or(ApiError::InternalServerError)?;
+  let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+  encoder.write_all(&tarball)?;
+  let gz_bytes = encoder.finish()?;
+  buckets
+    .publishing_bucket
+    .upload(
+      to.into(),
+      UploadTaskBody::Bytes(gz_bytes.into()),
+      GcsUploadOptions {
+        content_type: Some("application/x-tar".into()),
+        cache_control: None,
+        gzip_encoded: true,
+      },
+    )
+    .await?;
+  Ok(())
+}
+
+/// Uploads the version metadata file, built from the files and exports in the
+/// database, and the package metadata file.
+async fn rebuild_metadata(
+  db: &Database,
+  buckets: &Buckets,
+  metadata_signer: &MetadataSigner,
+  package_version: &PackageVersion,
+  files: Vec<PackageFile>,
+  module_graph_2: HashMap<String, deno_graph::ModuleInfo>,
+) -> Result<(), anyhow::Error> {
+  let file_infos = files
+    .into_iter()
+    .map(|file| {
+      let hash = file.checksum.ok_or_else(|| {
+        anyhow::anyhow!("file {} has no recorded checksum", file.path)
+      })?;
+      Ok(FileInfo {
+        path: file.path,
+        size: file.size as u64,
+        hash,
+      })
+    })
+    .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+  upload_version_manifest(
+    buckets,
+    metadata_signer,
+    &package_version.scope,
+    &package_version.name,
+    &package_version.version,
+    &file_infos,
+    package_version.exports.clone().into_inner(),
+    module_graph_2,
+  )
+  .await?;
+  upload_package_manifest(
+    db,
+    buckets,
+    metadata_signer,
+    &package_version.scope,
+    &package_version.name,
+  )
+  .await?;
+
+  Ok(())
+}
+
+fn rebuild_status(res: Result<(), anyhow::Error>) -> ApiRebuildStatus {
+  match res {
+    Ok(()) => ApiRebuildStatus::Rebuilt,
+    Err(err) => ApiRebuildStatus::Failed {
+      message: format!("{err:#}"),
+    },
+  }
+}
+
+#[instrument(name = "GET /api/admin/download_ingestion", skip(req), err)]
+pub async fn list_download_ingestion_checkpoints(
+  req: Request<Body>,
+) -> ApiResult<ApiList<ApiDownloadIngestionCheckpoint>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  let (start, limit) = pagination(&req);
+
+  let (total, checkpoints) =
+    db.list_download_ingestion_checkpoints(start, limit).await?;
+
+  Ok(ApiList {
+    items: checkpoints.into_iter().map(|c| c.into()).collect(),
+    total,
+  })
+}
+
+/// Ingests the download counts of a time range from the request logs again,
+/// for example after a bug in the ingestion was fixed. The counts of every
+/// window in the range are replaced, so replaying a range more than once does
+/// not count downloads twice.
+#[instrument(
+  name = "POST /api/admin/download_ingestion/replay",
+  skip(req),
+  err,
+  fields(start, end)
+)]
+pub async fn replay_download_ingestion(
+  mut req: Request<Body>,
+) -> ApiResult<Vec<ApiDownloadIngestionCheckpoint>> {
+  let iam = req.iam();
+  iam.check_admin_access()?;
+  let staff_id = iam.check_current_user_access()?.id;
+
+  let ApiReplayDownloadIngestionRequest { start, end } =
+    decode_json(&mut req).await?;
+
+  let now = Utc::now();
+  let start = download_ingestion_window_start(start);
+  let end = if download_ingestion_window_start(end) == end {
+    end
+  } else {
+    download_ingestion_window_start(end) + DOWNLOAD_INGESTION_WINDOW
+  }
+  .min(now);
+  Span::current().record("start", field::display(&start));
+  Span::current().record("end", field::display(&end));
+
+  if start >= end {
+    return Err(ApiError::MalformedRequest {
+      msg: "start must be before end and in the past".into(),
+    });
+  }
+  if end - start > MAX_DOWNLOAD_INGESTION_RANGE {
+    return Err(ApiError::MalformedRequest {
+      msg: format!(
+        "can not replay more than {} days at once",
+        MAX_DOWNLOAD_INGESTION_RANGE.num_days()
+      )
+      .into(),
+    });
+  }
+
+  let db = req.data::<Database>().unwrap();
+  let bigquery = req.data::<LogsBigQueryTable>().unwrap();
+  let Some((bigquery, logs_table_id)) = bigquery.0.as_ref() else {
+    return Err(ApiError::InternalServerError);
+  };
+  let registry_root = req.data::<RegistryUrl>().unwrap().0.to_string();
+  let npm_root = req.data::<NpmUrl>().unwrap().0.to_string();
+
+  let checkpoints = ingest_download_counts(
+    db,
+    bigquery,
+    logs_table_id,
+    &registry_root,
+    &npm_root,
+    start,
+    end,
+    Some(staff_id),
+  )
+  .await?;
+
+  Ok(checkpoints.into_iter().map(|c| c.into()).collect())
+}
+
+fn denied(err: ApiError) -> ApiIamSimulateResponse {
+  ApiIamSimulateResponse {
+    allowed: false,
+    rule: None,
+    permission: None,
+    code: Some(err.code().to_string()),
+    message: Some(err.message().to_string()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::api::ApiAuditFindingSeverity;
+  use crate::api::ApiConsistencyAudit;
+  use crate::api::ApiDatabasePoolStats;
+  use crate::api::ApiDeletedPackageVersion;
+  use crate::api::ApiDeprecatedRouteUsage;
+  use crate::api::ApiDownloadIngestionCheckpoint;
+  use crate::api::ApiFullScope;
+  use crate::api::ApiFullUser;
+  use crate::api::ApiIamSimulateResponse;
+  use crate::api::ApiList;
+  use crate::api::ApiPackageRedirect;
+  use crate::api::ApiPublishingTask;
+  use crate::api::ApiRebuildPackageVersionResult;
+  use crate::api::ApiRebuildStatus;
+  use crate::api::ApiScope;
+  use crate::api::ApiUserModerationFlag;
+  use crate::api::ApiUserTrust;
+  use crate::db::CreatePackageResult;
+  use crate::db::CreatePublishingTaskResult;
+  use crate::db::NewPublishingTask;
+  use crate::db::PublishingTaskStatus;
+  use crate::gcs_paths::deleted_version_tarball_path;
+  use crate::iam::IamRule;
+  use crate::ids::PackageName;
+  use crate::ids::PackagePath;
+  use crate::ids::Version;
+  use crate::npm::NPM_TARBALL_REVISION;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+  use crate::trust::TrustLevel;
+  use chrono::DateTime;
+  use chrono::Utc;
+  use hyper::header::HeaderValue;
+  use hyper::header::USER_AGENT;
+  use hyper::StatusCode;
+  use serde_json::json;
+
+  #[tokio::test]
+  async fn list_users() {
+    let mut t = TestSetup::new().await;
+
+    let token = t.staff_user.token.clone();
+    let users = t
+      .http()
+      .get("/api/admin/users")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiList<ApiFullUser>>()
+      .await;
+    assert_eq!(users.items.len(), 5);
+
+    let path = format!("/api/admin/users?query={}", t.user2.user.id);
+    let users = t
+      .http()
+      .get(path)
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiList<ApiFullUser>>()
+      .await;
+    assert_eq!(users.items.len(), 1);
+    assert_eq!(users.items[0].id, t.user2.user.id);
+  }
+
+  #[tokio::test]
+  async fn user_moderation_flags() {
+    let mut t = TestSetup::new().await;
+
+    let token = t.staff_user.token.clone();
+    let path = format!("/api/admin/users/{}/moderation_flags", t.user1.user.id);
+    let flag = t
+      .http()
+      .post(&path)
+      .body_json(json!({ "reason": "spam campaign" }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiUserModerationFlag>()
+      .await;
+    assert_eq!(flag.reason, "spam campaign");
+    assert_eq!(flag.created_by, Some(t.staff_user.user.id));
+
+    let flags = t
+      .http()
+      .get(&path)
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Vec<ApiUserModerationFlag>>()
+      .await;
+    assert_eq!(flags.len(), 1);
+
+    let signals = t
+      .db()
+      .get_user_trust_signals(t.user1.user.id)
+      .await
+      .unwrap();
+    assert_eq!(signals.moderation_flags, 1);
+    let trust = t
+      .http()
+      .get("/api/user/trust")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiUserTrust>()
+      .await;
+    assert_eq!(trust.level, TrustLevel::New);
+
+    // only staff can flag accounts
+    t.http()
+      .post(&path)
+      .body_json(json!({ "reason": "spam campaign" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+
+    t.http()
+      .delete(format!("{path}/{}", flag.id))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    t.http()
+      .delete(format!("{path}/{}", flag.id))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "userModerationFlagNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn database_pool_stats() {
+    let mut t = TestSetup::new().await;
+
+    let token = t.staff_user.token.clone();
+    let stats = t
+      .http()
+      .get("/api/admin/database_pool")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiDatabasePoolStats>()
+      .await;
+    assert_eq!(stats.limit, 1);
+    assert!(stats.size >= 1);
+
+    let token = t.user1.token.clone();
+    t.http()
+      .get("/api/admin/database_pool")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn consistency_audit() {
+    let mut t = TestSetup::new().await;
+
+    let token = t.staff_user.token.clone();
+    let audit = t
+      .http()
+      .get("/api/admin/consistency_audit")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Option<ApiConsistencyAudit>>()
+      .await;
+    assert!(audit.is_none());
+
+    let scope = t.scope.scope.clone();
+    let name = PackageName::new("foo".to_owned()).unwrap();
+    let CreatePackageResult::Ok(package) =
+      t.db().create_package(&scope, &name).await.unwrap()
+    else {
+      unreachable!();
+    };
+    let res = t
+      .db()
+      .create_publishing_task(NewPublishingTask {
+        package_scope: &scope,
+        package_name: &package.name,
+        package_version: &Version::new("1.0.0").unwrap(),
+        config_file: &PackagePath::try_from("/jsr.json").unwrap(),
+        user_id: None,
+        github_repository_id: None,
+        github_workflow_run: None,
+        bypass_publish_freezes: false,
+        publish_at: None,
+        idempotency_key: None,
+      })
+      .await
+      .unwrap();
+    let CreatePublishingTaskResult::Created(task) = res else {
+      unreachable!();
+    };
+
+    // The publishing task is still pending, so it is reported as stuck once
+    // the threshold has passed.
+    t.db()
+      .run_consistency_audit(chrono::Utc::now() + chrono::Duration::minutes(1))
+      .await
+      .unwrap();
+
+    let audit = t
+      .http()
+      .get("/api/admin/consistency_audit")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Option<ApiConsistencyAudit>>()
+      .await
+      .unwrap();
+    assert_eq!(audit.findings.len(), 1, "{:?}", audit.findings);
+    assert_eq!(audit.findings[0].kind, "stuck_publishing_task");
+    assert_eq!(audit.findings[0].severity, ApiAuditFindingSeverity::Warning);
+    assert_eq!(audit.findings[0].subject, task.id.to_string());
+
+    let token = t.user1.token.clone();
+    t.http()
+      .get("/api/admin/consistency_audit")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn deprecated_routes() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    let path = format!("/api/publish_status/{}", task.id);
+
+    let token = t.user1.token.clone();
+    for _ in 0..2 {
+      let mut resp = t
+        .http()
+        .get(&path)
+        .header(USER_AGENT, HeaderValue::from_static("jsr-cli/0.1.0"))
+        .token(Some(&token))
+        .call()
+        .await
+        .unwrap();
+      assert_eq!(resp.headers().get("deprecation").unwrap(), "@1730419200");
+      assert_eq!(
+        resp.headers().get("sunset").unwrap(),
+        "Tue, 01 Jun 2027 00:00:00 GMT"
+      );
+      let publishing_task = resp.expect_ok::<ApiPublishingTask>().await;
+      assert_eq!(publishing_task.id, task.id);
+    }
+
+    // The replacement route is not counted.
+    t.http()
+      .get(format!("/api/publishing_tasks/{}", task.id))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPublishingTask>()
+      .await;
+
+    let staff_token = t.staff_user.token.clone();
+    let usages = t
+      .http()
+      .get("/api/admin/deprecated_routes")
+      .token(Some(&staff_token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Vec<ApiDeprecatedRouteUsage>>()
+      .await;
+    assert_eq!(usages.len(), 1, "{usages:?}");
+    assert_eq!(usages[0].route, "/api/publish_status/:publishing_task_id");
+    assert_eq!(usages[0].user_id, Some(t.user1.user.id));
+    assert!(usages[0].token_id.is_some());
+    assert_eq!(usages[0].user_agent, "jsr-cli/0.1.0");
+    assert_eq!(usages[0].request_count, 2);
+
+    t.http()
+      .get("/api/admin/deprecated_routes")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn simulate_iam() {
+    let mut t = TestSetup::new().await;
+
+    let token = t.staff_user.token.clone();
+    let user1_id = t.user1.user.id;
+    let res = t
+      .http()
+      .post("/api/admin/iam/simulate")
+      .body_json(json!({
+        "principal": { "type": "user", "userId": user1_id },
+        "action": { "type": "scope_admin", "scope": "scope" },
+      }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiIamSimulateResponse>()
+      .await;
+    assert!(res.allowed);
+    assert_eq!(res.rule, Some(IamRule::ScopeAdmin));
+    assert!(res.code.is_none());
+
+    let user2_id = t.user2.user.id;
+    let res = t
+      .http()
+      .post("/api/admin/iam/simulate")
+      .body_json(json!({
+        "principal": { "type": "user", "userId": user2_id },
+        "action": {
+          "type": "publish",
+          "scope": "scope",
+          "package": "foo",
+          "version": "1.0.0",
+        },
+      }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiIamSimulateResponse>()
+      .await;
+    assert!(!res.allowed);
+    assert!(res.rule.is_none());
+    assert_eq!(res.code.as_deref(), Some("actorNotScopeMember"));
+
+    let token = t.user1.token.clone();
+    t.http()
+      .post("/api/admin/iam/simulate")
+      .body_json(json!({
+        "principal": { "type": "user", "userId": user1_id },
+        "action": { "type": "scope_write", "scope": "scope" },
+      }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn scope_management() {
+    let mut t = TestSetup::new().await;
+
+    assert_eq!(t.scope.package_limit, 250);
+    assert_eq!(t.scope.new_package_per_week_limit, 200);
+    assert_eq!(t.scope.publish_attempts_per_week_limit, 1000);
+
+    let path = format!("/api/admin/scopes/{}", t.scope.scope);
+    let token = t.staff_user.token.clone();
+    let res_scope = t
+      .http()
+      .patch(path)
+      .body_json(json!({
+        "packageLimit": 101,
+        "newPackagePerWeekLimit": 101,
         "publishAttemptsPerWeekLimit": 101,
       }))
       .token(Some(&token))
@@ -436,4 +1773,413 @@ mod tests {
       .expect_err_code(StatusCode::CONFLICT, "scopeAlreadyExists")
       .await;
   }
+
+  #[tokio::test]
+  async fn rebuild_package_version() {
+    let mut t = TestSetup::new().await;
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success);
+
+    let path = "/api/admin/packages/scope/foo/versions/1.2.3/rebuild";
+
+    t.http()
+      .post(path)
+      .body_json(json!({ "docs": true }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+
+    let token = t.staff_user.token.clone();
+    let result = t
+      .http()
+      .post(path)
+      .token(Some(&token))
+      .body_json(json!({ "docs": true, "metadata": true, "npmTarball": true }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiRebuildPackageVersionResult>()
+      .await;
+    assert_eq!(result.docs, ApiRebuildStatus::Rebuilt);
+    assert_eq!(result.metadata, ApiRebuildStatus::Rebuilt);
+    assert_eq!(result.npm_tarball, ApiRebuildStatus::Rebuilt);
+
+    let npm_tarball = t
+      .db()
+      .get_npm_tarball(
+        &t.scope.scope,
+        &PackageName::new("foo".to_owned()).unwrap(),
+        &Version::new("1.2.3").unwrap(),
+        NPM_TARBALL_REVISION as i32,
+      )
+      .await
+      .unwrap();
+    assert!(npm_tarball.is_some());
+
+    let result = t
+      .http()
+      .post(path)
+      .token(Some(&token))
+      .body_json(json!({}))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiRebuildPackageVersionResult>()
+      .await;
+    assert_eq!(result.docs, ApiRebuildStatus::Skipped);
+    assert_eq!(result.metadata, ApiRebuildStatus::Skipped);
+    assert_eq!(result.npm_tarball, ApiRebuildStatus::Skipped);
+  }
+
+  #[tokio::test]
+  async fn delete_and_restore_package_version() {
+    let mut t = TestSetup::new().await;
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success);
+
+    let path = "/api/admin/packages/scope/foo/versions/1.2.3";
+    let restore_path = "/api/admin/packages/scope/foo/versions/1.2.3/restore";
+    let name = PackageName::new("foo".to_owned()).unwrap();
+    let version = Version::new("1.2.3").unwrap();
+
+    t.http()
+      .delete(path)
+      .body_json(json!({ "reason": "takedown" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+
+    let token = t.staff_user.token.clone();
+    t.http()
+      .delete(path)
+      .token(Some(&token))
+      .body_json(json!({ "reason": " " }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "deletionReasonInvalid")
+      .await;
+
+    t.http()
+      .post(restore_path)
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "deletedPackageVersionNotFound")
+      .await;
+
+    // 1.2.3 is the latest version, so deleting it has to be forced.
+    t.http()
+      .delete(path)
+      .token(Some(&token))
+      .body_json(json!({ "reason": "takedown" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::CONFLICT, "packageVersionIsLatest")
+      .await;
+
+    let deleted = t
+      .http()
+      .delete(path)
+      .token(Some(&token))
+      .body_json(json!({ "reason": "takedown", "force": true }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiDeletedPackageVersion>()
+      .await;
+    assert_eq!(deleted.publishing_task_id, task.id);
+    assert_eq!(deleted.reason, "takedown");
+    assert_eq!(deleted.deleted_by, Some(t.staff_user.user.id));
+
+    assert!(t
+      .db()
+      .get_package_version(&t.scope.scope, &name, &version)
+      .await
+      .unwrap()
+      .is_none());
+    let retained = t
+      .buckets
+      .publishing_bucket
+      .download(deleted_version_tarball_path(task.id).into())
+      .await
+      .unwrap();
+    assert!(retained.is_some());
+
+    t.http()
+      .delete(path)
+      .token(Some(&token))
+      .body_json(json!({ "reason": "takedown" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+
+    let restored = t
+      .http()
+      .post(restore_path)
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPublishingTask>()
+      .await;
+    assert_eq!(restored.id, task.id);
+
+    // The restored publishing task is processed in the background.
+    let mut restored_version = None;
+    for _ in 0..50 {
+      restored_version = t
+        .db()
+        .get_package_version(&t.scope.scope, &name, &version)
+        .await
+        .unwrap();
+      if restored_version.is_some() {
+        break;
+      }
+      tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(restored_version.is_some());
+    assert!(t
+      .db()
+      .get_deleted_package_version(&t.scope.scope, &name, &version)
+      .await
+      .unwrap()
+      .is_none());
+  }
+
+  #[tokio::test]
+  async fn download_ingestion() {
+    let mut t = TestSetup::new().await;
+
+    let start = "2024-07-16T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+    let window = crate::tasks::DOWNLOAD_INGESTION_WINDOW;
+    t.db()
+      .upsert_download_ingestion_checkpoint(start, start + window, 154, None)
+      .await
+      .unwrap();
+    t.db()
+      .upsert_download_ingestion_checkpoint(
+        start + window,
+        start + window + chrono::Duration::hours(1),
+        3,
+        None,
+      )
+      .await
+      .unwrap();
+    assert_eq!(
+      t.db()
+        .get_download_ingestion_complete_until()
+        .await
+        .unwrap(),
+      Some(start + window)
+    );
+
+    let token = t.staff_user.token.clone();
+    let checkpoints = t
+      .http()
+      .get("/api/admin/download_ingestion")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiList<ApiDownloadIngestionCheckpoint>>()
+      .await;
+    assert_eq!(checkpoints.total, 2);
+    assert_eq!(checkpoints.items[0].window_start, start + window);
+    assert!(!checkpoints.items[0].complete);
+    assert_eq!(checkpoints.items[1].window_start, start);
+    assert!(checkpoints.items[1].complete);
+    assert_eq!(checkpoints.items[1].downloads, 154);
+
+    t.http()
+      .post("/api/admin/download_ingestion/replay")
+      .token(Some(&token))
+      .body_json(json!({
+        "start": "2024-07-16T04:00:00Z",
+        "end": "2024-07-16T01:00:00Z",
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    t.http()
+      .post("/api/admin/download_ingestion/replay")
+      .token(Some(&token))
+      .body_json(json!({
+        "start": "2024-07-01T00:00:00Z",
+        "end": "2024-07-16T00:00:00Z",
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    let token = t.user1.token.clone();
+    t.http()
+      .post("/api/admin/download_ingestion/replay")
+      .token(Some(&token))
+      .body_json(json!({
+        "start": "2024-07-16T00:00:00Z",
+        "end": "2024-07-16T04:00:00Z",
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn package_redirects() {
+    let mut t = TestSetup::new().await;
+
+    let token = t.staff_user.token.clone();
+    let scope = t.scope.scope.clone();
+    let name = PackageName::try_from("foo").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package(&scope, &name)
+      .await
+      .unwrap();
+    assert!(matches!(res, CreatePackageResult::Ok(_)));
+
+    // only staff can create redirects
+    t.http()
+      .post("/api/admin/package_redirects")
+      .body_json(json!({
+        "scope": "scope",
+        "package": "old",
+        "targetScope": "scope",
+        "targetPackage": "foo",
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+
+    // the old name must not belong to an existing package
+    t.http()
+      .post("/api/admin/package_redirects")
+      .body_json(json!({
+        "scope": "scope",
+        "package": "foo",
+        "targetScope": "scope",
+        "targetPackage": "bar",
+      }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::CONFLICT, "packageRedirectSourceExists")
+      .await;
+
+    let redirect = t
+      .http()
+      .post("/api/admin/package_redirects")
+      .body_json(json!({
+        "scope": "scope",
+        "package": "old",
+        "targetScope": "scope",
+        "targetPackage": "foo",
+      }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPackageRedirect>()
+      .await;
+    assert_eq!(redirect.target_package, name);
+    assert!(redirect.expires_at > Utc::now() + chrono::Duration::days(179));
+
+    let redirects = t
+      .http()
+      .get("/api/admin/package_redirects")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Vec<ApiPackageRedirect>>()
+      .await;
+    assert_eq!(redirects.len(), 1);
+
+    let resp = t
+      .unauthed_http()
+      .get("/api/scopes/scope/packages/old")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(
+      resp.headers().get("location").unwrap(),
+      "/api/scopes/scope/packages/foo"
+    );
+
+    let resp = t
+      .unauthed_http()
+      .get("/@scope/old/1.0.0_meta.json")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+    let location = resp.headers().get("location").unwrap().to_str().unwrap();
+    assert!(
+      location.ends_with("/@scope/foo/1.0.0_meta.json"),
+      "{location}"
+    );
+    let resp = t
+      .unauthed_http()
+      .get("/@scope/old/meta.json")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+    let location = resp.headers().get("location").unwrap().to_str().unwrap();
+    assert!(location.ends_with("/@scope/foo/meta.json"), "{location}");
+
+    let resp = t
+      .unauthed_http()
+      .get("/api/npm/@jsr/scope__old")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+    let location = resp.headers().get("location").unwrap().to_str().unwrap();
+    assert!(location.ends_with("/@jsr/scope__foo"), "{location}");
+
+    t.http()
+      .delete("/api/admin/package_redirects/scope/old")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    t.http()
+      .get("/api/scopes/scope/packages/old")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+    t.http()
+      .delete("/api/admin/package_redirects/scope/old")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageRedirectNotFound")
+      .await;
+  }
 }
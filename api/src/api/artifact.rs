@@ -0,0 +1,563 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::io;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use chrono::Utc;
+use futures::StreamExt;
+use hyper::body::HttpBody;
+use hyper::header::CACHE_CONTROL;
+use hyper::header::CONTENT_DISPOSITION;
+use hyper::header::CONTENT_TYPE;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use hyper::StatusCode;
+use routerify::prelude::RequestExt;
+use routerify_query::RequestQueryExt;
+use sha2::Digest;
+use tracing::field;
+use tracing::instrument;
+use tracing::Span;
+
+use crate::buckets::Buckets;
+use crate::buckets::UploadTaskBody;
+use crate::db::Database;
+use crate::db::NewPackageVersionArtifact;
+use crate::gcp::GcsUploadOptions;
+use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
+use crate::gcs_paths;
+use crate::iam::ReqIamExt;
+use crate::util::decode_json;
+use crate::util::ApiResult;
+use crate::util::RequestIdExt;
+use crate::RegistryUrl;
+
+use super::ApiCreatePackageVersionArtifactRequest;
+use super::ApiCreatedPackageVersionArtifact;
+use super::ApiError;
+use super::ApiPackageVersionArtifact;
+
+/// The most artifacts that can be attached to a single package version.
+const MAX_ARTIFACTS_PER_VERSION: usize = 10;
+/// The most bytes that the artifacts of a single package version may take up
+/// together.
+const MAX_ARTIFACTS_SIZE_PER_VERSION: u64 = 100 * 1024 * 1024; // 100mb
+/// How long an upload URL can be used after it was requested.
+const ARTIFACT_UPLOAD_EXPIRY: chrono::Duration = chrono::Duration::hours(1);
+
+fn validate_artifact_filename(filename: &str) -> Result<(), ApiError> {
+  if filename.is_empty()
+    || filename.len() > 100
+    || filename.starts_with('.')
+    || !filename
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+  {
+    return Err(ApiError::MalformedRequest {
+      msg: "filename must be at most 100 characters long, only contain ASCII letters, digits, '.', '-' and '_', and not start with '.'".into(),
+    });
+  }
+  Ok(())
+}
+
+/// Reads the `:filename` path parameter. Invalid filenames can not belong to
+/// an artifact, so they are reported as not found.
+fn param_filename(req: &Request<Body>) -> Result<String, ApiError> {
+  let filename = req.param("filename").unwrap();
+  validate_artifact_filename(filename)
+    .map_err(|_| ApiError::PackageVersionArtifactNotFound)?;
+  Ok(filename.to_owned())
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/artifacts",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn list_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiPackageVersionArtifact>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap();
+  db.get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let artifacts = db
+    .list_package_version_artifacts(&scope, &package, &version)
+    .await?
+    .into_iter()
+    .filter(|artifact| artifact.uploaded_at.is_some())
+    .map(Into::into)
+    .collect();
+
+  Ok(artifacts)
+}
+
+/// Reserves space for an artifact of a published package version, and returns
+/// a short-lived URL that the file can be uploaded to. This lets CI workflows
+/// hand the upload to a tool that does not hold the credentials of the
+/// workflow.
+#[instrument(
+  name = "POST /api/scopes/:scope/packages/:package/versions/:version/artifacts",
+  skip(req),
+  err,
+  fields(scope, package, version, filename)
+)]
+pub async fn create_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiCreatedPackageVersionArtifact> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let ApiCreatePackageVersionArtifactRequest {
+    filename,
+    size,
+    sha256,
+  } = decode_json(&mut req).await?;
+  Span::current().record("filename", field::display(&filename));
+
+  validate_artifact_filename(&filename)?;
+  if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(ApiError::MalformedRequest {
+      msg: "sha256 must be a hex encoded SHA-256 digest".into(),
+    });
+  }
+  let sha256 = sha256.to_ascii_lowercase();
+
+  let iam = req.iam();
+  let (_, user_id) =
+    iam.check_publish_access(&scope, &package, &version).await?;
+
+  let db = req.data::<Database>().unwrap();
+  db.get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  // Pending uploads count towards the limits until their upload URL expires.
+  // An artifact with the same filename is replaced by this one.
+  let now = Utc::now();
+  let artifacts = db
+    .list_package_version_artifacts(&scope, &package, &version)
+    .await?
+    .into_iter()
+    .filter(|artifact| artifact.filename != filename)
+    .filter(|artifact| {
+      artifact.uploaded_at.is_some()
+        || artifact
+          .upload_expires_at
+          .is_some_and(|expires_at| expires_at > now)
+    })
+    .collect::<Vec<_>>();
+  if artifacts.len() >= MAX_ARTIFACTS_PER_VERSION {
+    return Err(ApiError::PackageVersionArtifactLimitExceeded {
+      limit: MAX_ARTIFACTS_PER_VERSION,
+    });
+  }
+  let used_size = artifacts
+    .iter()
+    .map(|artifact| artifact.size as u64)
+    .sum::<u64>();
+  let max_size = MAX_ARTIFACTS_SIZE_PER_VERSION.saturating_sub(used_size);
+  if size > max_size {
+    return Err(ApiError::PackageVersionArtifactSizeLimitExceeded {
+      size,
+      max_size,
+    });
+  }
+
+  let secret = format!(
+    "{:032x}{:032x}",
+    rand::random::<u128>(),
+    rand::random::<u128>()
+  );
+  let upload_expires_at = now + ARTIFACT_UPLOAD_EXPIRY;
+  let artifact = db
+    .create_package_version_artifact(NewPackageVersionArtifact {
+      scope: &scope,
+      name: &package,
+      version: &version,
+      filename: &filename,
+      size: size as i64,
+      sha256: &sha256,
+      user_id,
+      upload_token_hash: &crate::token::hash(&secret),
+      upload_expires_at,
+    })
+    .await?
+    .ok_or(ApiError::PackageVersionArtifactAlreadyExists)?;
+
+  let registry_url = &req.data::<RegistryUrl>().unwrap().0;
+  let mut upload_url = registry_url
+    .join(&format!(
+      "api/scopes/{scope}/packages/{package}/versions/{version}/artifacts/{filename}"
+    ))
+    .map_err(|_| ApiError::InternalServerError)?;
+  upload_url.query_pairs_mut().append_pair("token", &secret);
+
+  Ok(ApiCreatedPackageVersionArtifact {
+    artifact: artifact.into(),
+    upload_url: upload_url.into(),
+    upload_expires_at,
+  })
+}
+
+/// Receives the file of an artifact. The request is authenticated by the token
+/// in the upload URL, and the file must match the size and digest that were
+/// declared when the upload URL was requested.
+#[instrument(
+  name = "PUT /api/scopes/:scope/packages/:package/versions/:version/artifacts/:filename",
+  skip(req),
+  err,
+  fields(scope, package, version, filename)
+)]
+pub async fn upload_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPackageVersionArtifact> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  let filename = param_filename(&req)?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+  Span::current().record("filename", field::display(&filename));
+  let token = req
+    .query("token")
+    .ok_or(ApiError::PackageVersionArtifactNotFound)?
+    .to_owned();
+
+  let db = req.data::<Database>().unwrap().clone();
+  let buckets = req.data::<Buckets>().unwrap().clone();
+
+  let artifact = db
+    .get_package_version_artifact(&scope, &package, &version, &filename)
+    .await?
+    .ok_or(ApiError::PackageVersionArtifactNotFound)?;
+  if artifact.uploaded_at.is_some() {
+    return Err(ApiError::PackageVersionArtifactAlreadyExists);
+  }
+  if artifact.upload_token_hash.as_deref()
+    != Some(crate::token::hash(&token).as_str())
+    || artifact
+      .upload_expires_at
+      .map_or(true, |expires_at| expires_at < Utc::now())
+  {
+    return Err(ApiError::PackageVersionArtifactNotFound);
+  }
+
+  let expected_size = artifact.size as u64;
+  if let Some(size) = req.body().size_hint().upper() {
+    if size > expected_size {
+      return Err(ApiError::PackageVersionArtifactMismatch);
+    }
+  }
+
+  let body = req.into_body();
+  let total_size = Arc::new(AtomicU64::new(0));
+  let total_size_ = total_size.clone();
+
+  let hash = Arc::new(Mutex::new(Some(sha2::Sha256::new())));
+
+  let hash_ = hash.clone();
+  let stream = body.map(move |res| match res {
+    Ok(bytes) => {
+      hash_.lock().unwrap().as_mut().unwrap().update(&bytes);
+      total_size_.fetch_add(bytes.len() as u64, Ordering::SeqCst);
+      if total_size_.load(Ordering::SeqCst) > expected_size {
+        Err(io::Error::new(io::ErrorKind::Other, "Payload too large"))
+      } else {
+        Ok(bytes)
+      }
+    }
+    Err(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
+  });
+
+  let gcs_path = gcs_paths::package_version_artifact_path(
+    &scope, &package, &version, &filename,
+  );
+  let upload_result = buckets
+    .artifacts_bucket
+    .upload(
+      gcs_path.clone().into(),
+      UploadTaskBody::Stream(Box::new(stream)),
+      GcsUploadOptions {
+        content_type: Some("application/octet-stream".into()),
+        cache_control: None,
+        gzip_encoded: false,
+      },
+    )
+    .await;
+
+  let sha256 =
+    format!("{:02x}", hash.lock().unwrap().take().unwrap().finalize());
+  if total_size.load(Ordering::SeqCst) != expected_size
+    || sha256 != artifact.sha256
+  {
+    if upload_result.is_ok() {
+      buckets.artifacts_bucket.bucket.delete(&gcs_path).await?;
+    }
+    return Err(ApiError::PackageVersionArtifactMismatch);
+  }
+  upload_result?;
+
+  let artifact = db
+    .complete_package_version_artifact_upload(
+      &scope, &package, &version, &filename,
+    )
+    .await?;
+
+  Ok(artifact.into())
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/artifacts/:filename",
+  skip(req),
+  err,
+  fields(scope, package, version, filename)
+)]
+pub async fn download_handler(req: Request<Body>) -> ApiResult<Response<Body>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  let filename = param_filename(&req)?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+  Span::current().record("filename", field::display(&filename));
+
+  let db = req.data::<Database>().unwrap();
+  db.get_package_version_artifact(&scope, &package, &version, &filename)
+    .await?
+    .filter(|artifact| artifact.uploaded_at.is_some())
+    .ok_or(ApiError::PackageVersionArtifactNotFound)?;
+
+  let buckets = req.data::<Buckets>().unwrap();
+  let stream = buckets
+    .artifacts_bucket
+    .bucket
+    .download_stream(
+      &gcs_paths::package_version_artifact_path(
+        &scope, &package, &version, &filename,
+      ),
+      None,
+    )
+    .await?
+    .ok_or(ApiError::PackageVersionArtifactNotFound)?;
+
+  let resp = Response::builder()
+    .status(StatusCode::OK)
+    .header(CONTENT_TYPE, "application/octet-stream")
+    .header(
+      CONTENT_DISPOSITION,
+      format!("attachment; filename=\"{filename}\""),
+    )
+    .header(CACHE_CONTROL, CACHE_CONTROL_DO_NOT_CACHE)
+    .body(Body::wrap_stream(stream))
+    .unwrap();
+  Ok(resp)
+}
+
+#[instrument(
+  name = "DELETE /api/scopes/:scope/packages/:package/versions/:version/artifacts/:filename",
+  skip(req),
+  err,
+  fields(scope, package, version, filename)
+)]
+pub async fn delete_handler(req: Request<Body>) -> ApiResult<Response<Body>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  let filename = param_filename(&req)?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+  Span::current().record("filename", field::display(&filename));
+
+  let iam = req.iam();
+  iam.check_publish_access(&scope, &package, &version).await?;
+
+  let db = req.data::<Database>().unwrap();
+  if !db
+    .delete_package_version_artifact(&scope, &package, &version, &filename)
+    .await?
+  {
+    return Err(ApiError::PackageVersionArtifactNotFound);
+  }
+
+  let buckets = req.data::<Buckets>().unwrap();
+  buckets
+    .artifacts_bucket
+    .bucket
+    .delete(&gcs_paths::package_version_artifact_path(
+      &scope, &package, &version, &filename,
+    ))
+    .await?;
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+  use hyper::Body;
+  use hyper::StatusCode;
+  use serde_json::json;
+  use sha2::Digest;
+
+  use crate::api::ApiCreatedPackageVersionArtifact;
+  use crate::api::ApiPackageVersionArtifact;
+  use crate::db::PublishingTaskStatus;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  #[tokio::test]
+  async fn artifacts() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let path = "/api/scopes/scope/packages/foo/versions/1.2.3/artifacts";
+    let content = b"abc  foo.tar.gz\n";
+    let sha256 = format!("{:02x}", sha2::Sha256::digest(content));
+
+    let created: ApiCreatedPackageVersionArtifact = t
+      .http()
+      .post(path)
+      .body_json(json!({
+        "filename": "checksums.txt",
+        "size": content.len(),
+        "sha256": sha256,
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(created.artifact.filename, "checksums.txt");
+    assert!(created.artifact.uploaded_at.is_none());
+    let upload_url = url::Url::parse(&created.upload_url).unwrap();
+    let upload_path =
+      format!("{}?{}", upload_url.path(), upload_url.query().unwrap());
+
+    // pending uploads are not listed
+    let artifacts: Vec<ApiPackageVersionArtifact> =
+      t.http().get(path).call().await.unwrap().expect_ok().await;
+    assert!(artifacts.is_empty());
+
+    t.unauthed_http()
+      .put(&upload_path)
+      .body(Body::from("something else"))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(
+        StatusCode::BAD_REQUEST,
+        "packageVersionArtifactMismatch",
+      )
+      .await;
+
+    t.unauthed_http()
+      .put(format!("{path}/checksums.txt?token=invalid"))
+      .body(Body::from(&content[..]))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionArtifactNotFound")
+      .await;
+
+    let artifact: ApiPackageVersionArtifact = t
+      .unauthed_http()
+      .put(&upload_path)
+      .body(Body::from(&content[..]))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert!(artifact.uploaded_at.is_some());
+    assert_eq!(artifact.size, content.len() as u64);
+
+    t.unauthed_http()
+      .put(&upload_path)
+      .body(Body::from(&content[..]))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(
+        StatusCode::CONFLICT,
+        "packageVersionArtifactAlreadyExists",
+      )
+      .await;
+
+    let artifacts: Vec<ApiPackageVersionArtifact> = t
+      .unauthed_http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(artifacts.len(), 1);
+    assert_eq!(artifacts[0].sha256, sha256);
+
+    let resp = t
+      .unauthed_http()
+      .get(format!("{path}/checksums.txt"))
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &content[..]);
+
+    t.http()
+      .post(path)
+      .body_json(json!({
+        "filename": "../secret",
+        "size": 1,
+        "sha256": sha256,
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    t.http()
+      .delete(format!("{path}/checksums.txt"))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+
+    t.unauthed_http()
+      .get(format!("{path}/checksums.txt"))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionArtifactNotFound")
+      .await;
+  }
+}
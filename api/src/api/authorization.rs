@@ -46,6 +46,8 @@ async fn create_authorization(
     permissions,
   } = decode_json(&mut req).await?;
 
+  crate::token::check_user_token_permissions(permissions.as_ref())?;
+
   let db = req.data::<Database>().unwrap();
   let registry_url = req.data::<RegistryUrl>().unwrap().0.clone();
 
@@ -258,8 +260,8 @@ mod tests {
   use crate::db::PackagePublishPermission;
   use crate::db::Permission;
   use crate::db::Permissions;
-  use crate::util::test::ApiResultExt;
-  use crate::util::test::TestSetup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
 
   fn new_verifier_and_challenge() -> (String, String) {
     let verifier = Uuid::new_v4().to_string();
@@ -425,6 +427,19 @@ mod tests {
     resp
       .expect_err_code(StatusCode::FORBIDDEN, "missingPermission")
       .await;
+
+    // package lists are only supported for scope tokens
+    let permissions = Permissions(vec![Permission::PackagePublish(
+      PackagePublishPermission::Packages {
+        scope: t.scope.scope.clone(),
+        packages: vec!["test".try_into().unwrap()],
+      },
+    )]);
+    let mut resp =
+      create_authorization(&mut t, &challenge, Some(permissions)).await;
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
   }
 
   #[tokio::test]
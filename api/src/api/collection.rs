@@ -0,0 +1,39 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use hyper::Body;
+use hyper::Request;
+use routerify::prelude::RequestExt;
+use routerify::Router;
+use tracing::field;
+use tracing::instrument;
+use tracing::Span;
+
+use crate::db::Database;
+use crate::util;
+use crate::util::ApiResult;
+use crate::util::RequestIdExt;
+
+use super::ApiError;
+use super::ApiPackageCollection;
+
+pub fn collection_router() -> Router<Body, ApiError> {
+  Router::builder()
+    .get("/:id", util::json(get_handler))
+    .build()
+    .unwrap()
+}
+
+#[instrument(name = "GET /api/collections/:id", skip(req), err, fields(id))]
+pub async fn get_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPackageCollection> {
+  let id = req.param_uuid("id")?;
+  Span::current().record("id", field::display(id));
+
+  let db = req.data::<Database>().unwrap();
+  let collection = db
+    .get_package_collection(id)
+    .await?
+    .ok_or(ApiError::PackageCollectionNotFound)?;
+
+  Ok(collection.into())
+}
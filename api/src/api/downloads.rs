@@ -0,0 +1,324 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Utc;
+use hyper::Body;
+use hyper::Request;
+use routerify::prelude::RequestExt;
+use routerify::Router;
+use tracing::field;
+use tracing::instrument;
+use tracing::Span;
+
+use crate::db::Database;
+use crate::db::DownloadKind;
+use crate::ids::PackageName;
+use crate::ids::ScopeName;
+use crate::util;
+use crate::util::ApiResult;
+use crate::util::CacheDuration;
+use crate::util::RequestIdExt;
+
+use super::ApiDownloadsDay;
+use super::ApiDownloadsPoint;
+use super::ApiDownloadsRange;
+use super::ApiError;
+
+/// The longest period that can be queried, like on the npm downloads API.
+const MAX_PERIOD_DAYS: i64 = 549;
+
+/// Endpoints with the same paths and response shapes as the npm downloads API
+/// (https://github.com/npm/registry/blob/main/docs/download-counts.md), so
+/// that tools built against it work with JSR packages.
+pub fn downloads_router() -> Router<Body, ApiError> {
+  Router::builder()
+    .get(
+      "/point/:period/@:scope/:package",
+      util::cache(CacheDuration::ONE_MINUTE, util::json(point_handler)),
+    )
+    .get(
+      "/range/:period/@:scope/:package",
+      util::cache(CacheDuration::ONE_MINUTE, util::json(range_handler)),
+    )
+    .build()
+    .unwrap()
+}
+
+/// Parses a period in the format of the npm downloads API into the first and
+/// last day it covers. Named periods end on the last complete day.
+fn parse_period(
+  period: &str,
+  today: NaiveDate,
+) -> Result<(NaiveDate, NaiveDate), ApiError> {
+  let yesterday = today - Duration::days(1);
+  let named_days = match period {
+    "last-day" => Some(1),
+    "last-week" => Some(7),
+    "last-month" => Some(30),
+    "last-year" => Some(365),
+    _ => None,
+  };
+  if let Some(days) = named_days {
+    return Ok((yesterday - Duration::days(days - 1), yesterday));
+  }
+
+  let parse_date = |date: &str| {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+      ApiError::MalformedRequest {
+        msg: format!("invalid period '{period}': expected last-day, last-week, last-month, last-year, a date or a date range").into(),
+      }
+    })
+  };
+  let (start, end) = match period.split_once(':') {
+    Some((start, end)) => (parse_date(start)?, parse_date(end)?),
+    None => {
+      let date = parse_date(period)?;
+      (date, date)
+    }
+  };
+  if start > end {
+    return Err(ApiError::MalformedRequest {
+      msg: format!("invalid period '{period}': start is after end").into(),
+    });
+  }
+  if (end - start).num_days() >= MAX_PERIOD_DAYS {
+    return Err(ApiError::MalformedRequest {
+      msg: format!(
+        "invalid period '{period}': at most {MAX_PERIOD_DAYS} days can be queried"
+      )
+      .into(),
+    });
+  }
+  Ok((start, end))
+}
+
+/// Returns the downloads of each day of the period, including days without
+/// downloads. Requests from web browsers are not counted.
+async fn daily_downloads(
+  db: &Database,
+  scope: &ScopeName,
+  package: &PackageName,
+  start: NaiveDate,
+  end: NaiveDate,
+) -> ApiResult<Vec<ApiDownloadsDay>> {
+  db.get_package(scope, package)
+    .await?
+    .ok_or(ApiError::PackageNotFound)?;
+
+  let points = db
+    .get_package_downloads_24h(
+      scope,
+      package,
+      start.and_time(NaiveTime::MIN).and_utc(),
+      (end + Duration::days(1)).and_time(NaiveTime::MIN).and_utc(),
+    )
+    .await?;
+
+  let mut days = start
+    .iter_days()
+    .take_while(|day| *day <= end)
+    .map(|day| ApiDownloadsDay { downloads: 0, day })
+    .collect::<Vec<_>>();
+  for point in points {
+    if point.kind == DownloadKind::Web {
+      continue;
+    }
+    let offset = (point.time_bucket.date_naive() - start).num_days();
+    if let Some(day) = days.get_mut(offset as usize) {
+      day.downloads += point.count as u64;
+    }
+  }
+  Ok(days)
+}
+
+#[instrument(
+  name = "GET /api/downloads/point/:period/@:scope/:package",
+  skip(req),
+  err,
+  fields(period, scope, package)
+)]
+pub async fn point_handler(req: Request<Body>) -> ApiResult<ApiDownloadsPoint> {
+  let period = req.param("period").unwrap();
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("period", field::display(period));
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let (start, end) = parse_period(period, Utc::now().date_naive())?;
+
+  let db = req.data::<Database>().unwrap();
+  let days = daily_downloads(db, &scope, &package, start, end).await?;
+
+  Ok(ApiDownloadsPoint {
+    downloads: days.iter().map(|day| day.downloads).sum(),
+    start,
+    end,
+    package: format!("@{scope}/{package}"),
+  })
+}
+
+#[instrument(
+  name = "GET /api/downloads/range/:period/@:scope/:package",
+  skip(req),
+  err,
+  fields(period, scope, package)
+)]
+pub async fn range_handler(req: Request<Body>) -> ApiResult<ApiDownloadsRange> {
+  let period = req.param("period").unwrap();
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("period", field::display(period));
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let (start, end) = parse_period(period, Utc::now().date_naive())?;
+
+  let db = req.data::<Database>().unwrap();
+  let downloads = daily_downloads(db, &scope, &package, start, end).await?;
+
+  Ok(ApiDownloadsRange {
+    downloads,
+    start,
+    end,
+    package: format!("@{scope}/{package}"),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Duration;
+  use chrono::NaiveDate;
+  use chrono::NaiveTime;
+  use chrono::Utc;
+  use hyper::StatusCode;
+
+  use super::parse_period;
+  use crate::api::ApiDownloadsPoint;
+  use crate::api::ApiDownloadsRange;
+  use crate::db::DownloadKind;
+  use crate::db::PublishingTaskStatus;
+  use crate::db::VersionDownloadCount;
+  use crate::ids::PackageName;
+  use crate::ids::ScopeName;
+  use crate::ids::Version;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+  }
+
+  #[test]
+  fn periods() {
+    let today = date("2024-03-10");
+    assert_eq!(
+      parse_period("last-day", today).unwrap(),
+      (date("2024-03-09"), date("2024-03-09"))
+    );
+    assert_eq!(
+      parse_period("last-week", today).unwrap(),
+      (date("2024-03-03"), date("2024-03-09"))
+    );
+    assert_eq!(
+      parse_period("last-month", today).unwrap(),
+      (date("2024-02-09"), date("2024-03-09"))
+    );
+    assert_eq!(
+      parse_period("2024-01-01", today).unwrap(),
+      (date("2024-01-01"), date("2024-01-01"))
+    );
+    assert_eq!(
+      parse_period("2024-01-01:2024-01-31", today).unwrap(),
+      (date("2024-01-01"), date("2024-01-31"))
+    );
+    assert!(parse_period("last-decade", today).is_err());
+    assert!(parse_period("2024-01-31:2024-01-01", today).is_err());
+    assert!(parse_period("2020-01-01:2024-01-01", today).is_err());
+  }
+
+  #[tokio::test]
+  async fn point_and_range() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let yesterday = Utc::now().date_naive() - Duration::days(1);
+    let two_days_ago = yesterday - Duration::days(1);
+    let entry = |day: NaiveDate, kind, count| VersionDownloadCount {
+      scope: ScopeName::new("scope".to_owned()).unwrap(),
+      package: PackageName::new("foo".to_owned()).unwrap(),
+      version: Version::new("1.2.3").unwrap(),
+      time_bucket: day.and_time(NaiveTime::MIN).and_utc(),
+      kind,
+      count,
+    };
+    t.db()
+      .insert_download_entries(vec![
+        entry(two_days_ago, DownloadKind::JsrMeta, 3),
+        entry(yesterday, DownloadKind::JsrMeta, 5),
+        entry(yesterday, DownloadKind::NpmTgz, 2),
+        entry(yesterday, DownloadKind::Web, 100),
+      ])
+      .await
+      .unwrap();
+
+    let point = t
+      .http()
+      .get("/api/downloads/point/last-day/@scope/foo")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiDownloadsPoint>()
+      .await;
+    assert_eq!(point.downloads, 7);
+    assert_eq!(point.start, yesterday);
+    assert_eq!(point.end, yesterday);
+    assert_eq!(point.package, "@scope/foo");
+
+    let point = t
+      .http()
+      .get("/api/downloads/point/last-week/@scope/foo")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiDownloadsPoint>()
+      .await;
+    assert_eq!(point.downloads, 10);
+
+    let range = t
+      .http()
+      .get("/api/downloads/range/last-week/@scope/foo")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiDownloadsRange>()
+      .await;
+    assert_eq!(range.downloads.len(), 7);
+    assert_eq!(range.downloads[0].downloads, 0);
+    assert_eq!(range.downloads[5].day, two_days_ago);
+    assert_eq!(range.downloads[5].downloads, 3);
+    assert_eq!(range.downloads[6].day, yesterday);
+    assert_eq!(range.downloads[6].downloads, 7);
+
+    t.http()
+      .get("/api/downloads/point/last-day/@scope/bar")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+
+    t.http()
+      .get("/api/downloads/range/yesterday/@scope/foo")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+  }
+}
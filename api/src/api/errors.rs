@@ -28,6 +28,22 @@ errors!(
     status: NOT_FOUND,
     "The requested publish was not found.",
   },
+  PublishArtifactsNotFound {
+    status: NOT_FOUND,
+    "The artifacts of the requested publish were not found. Artifacts are only retained for 7 days after a publish fails.",
+  },
+  PublishNotFailed {
+    status: BAD_REQUEST,
+    "The requested publish has not failed, so there is no failure to explain.",
+  },
+  PublishNotCancellable {
+    status: BAD_REQUEST,
+    "The requested publish can not be cancelled, because it has already been processed or failed.",
+  },
+  PublishNotRetryable {
+    status: BAD_REQUEST,
+    "The requested publish can not be retried. Only publishes that have been pending for at least 10 minutes can be retried.",
+  },
   UserNotFound {
     status: NOT_FOUND,
     "The requested user was not found. Only users who have logged in to JSR at least once are visible.",
@@ -40,10 +56,38 @@ errors!(
     status: NOT_FOUND,
     "The requested package was not found.",
   },
+  TopicNotFound {
+    status: NOT_FOUND,
+    "The requested topic was not found.",
+  },
+  PackageRedirectNotFound {
+    status: NOT_FOUND,
+    "The requested package redirect was not found.",
+  },
+  PackageLinkCheckNotFound {
+    status: NOT_FOUND,
+    "The links of the requested package have not been checked yet.",
+  },
+  PackageRedirectSourceExists {
+    status: CONFLICT,
+    "A package with the old name still exists. Only the old names of renamed or moved packages can be redirected.",
+  },
   PackageVersionNotFound {
     status: NOT_FOUND,
     "The requested package version was not found.",
   },
+  DeletedPackageVersionNotFound {
+    status: NOT_FOUND,
+    "The requested package version was not deleted, or can no longer be restored.",
+  },
+  PackageVersionSpecifiersNotFound {
+    status: NOT_FOUND,
+    "The specifiers of the requested package version were not recorded, because it was published before specifiers were recorded.",
+  },
+  NpmTarballNotFound {
+    status: NOT_FOUND,
+    "The requested npm tarball was not found.",
+  },
   EntrypointOrSymbolNotFound {
     status: NOT_FOUND,
     "The requested entrypoint or symbol was not found.",
@@ -132,6 +176,20 @@ errors!(
     status: BAD_REQUEST,
     "This account has reached the maximum number of created scopes.",
   },
+  TrustLevelPackageLimitExceeded {
+    status: BAD_REQUEST,
+    fields: { limit: u32 },
+    ({ limit }) => "Exceeded daily limit of {limit} new packages for scopes created by a new account. The limit is lifted as the account that created the scope gains trust.",
+  },
+  TrustLevelPublishAttemptsLimitExceeded {
+    status: BAD_REQUEST,
+    fields: { limit: u32 },
+    ({ limit }) => "Exceeded daily limit of {limit} publish attempts for a new account. The limit is lifted as the account gains trust.",
+  },
+  UserModerationFlagNotFound {
+    status: NOT_FOUND,
+    "The requested moderation flag was not found.",
+  },
   ScopeInviteNotFound {
     status: NOT_FOUND,
     "The requested scope invite was not found.",
@@ -152,6 +210,15 @@ errors!(
     status: BAD_REQUEST,
     "To link a GitHub repository, you must have at least push permissions for it.",
   },
+  GithubRepositorySubdirectoryInvalid {
+    status: BAD_REQUEST,
+    "The subdirectory must be a relative path within the repository, without '.' or '..' segments.",
+  },
+  GithubRepositorySubdirectoryMismatch {
+    status: BAD_REQUEST,
+    fields: { msg: Cow<'static, str> },
+    ({ msg }) => "The subdirectory of the GitHub repository does not contain this package: {msg}.",
+  },
   MissingPermission {
     status: FORBIDDEN,
     "The credential this request was authenticated with does not have the necessary permissions to perform this action.",
@@ -168,6 +235,10 @@ errors!(
     status: FORBIDDEN,
     "The actor that this request was authenticated for is not a user.",
   },
+  ActorNotGitHubActions {
+    status: FORBIDDEN,
+    "This request must be authenticated with a GitHub Actions OIDC token.",
+  },
   ActorNotScopeAdmin {
     status: FORBIDDEN,
     "The actor that this request was authenticated for is not authorized as a scope admin for this scope.",
@@ -180,6 +251,32 @@ errors!(
     status: FORBIDDEN,
     "This scope requires that all packages must be published from CI.",
   },
+  TarballDigestMismatch {
+    status: BAD_REQUEST,
+    fields: { expected: String, actual: String },
+    ({ expected, actual }) => "The SHA-256 digest of the uploaded tarball ({actual}) does not match the expected digest ({expected}).",
+  },
+  ScopePublishFreezeActive {
+    status: FORBIDDEN,
+    fields: { reason: String, ends_at: chrono::DateTime<chrono::Utc> },
+    ({ reason, ends_at }) => "Publishing to this scope is frozen until {ends_at}: {reason}",
+  },
+  PublishFreezeNotFound {
+    status: NOT_FOUND,
+    "The requested publish freeze was not found.",
+  },
+  PublishIpNotAllowed {
+    status: FORBIDDEN,
+    "This scope only allows publishing from the IP ranges in its allowlist, and the request came from outside of them.",
+  },
+  PublishIpRangeNotFound {
+    status: NOT_FOUND,
+    "The requested publish IP range was not found.",
+  },
+  PublishIpRangeAlreadyExists {
+    status: CONFLICT,
+    "The IP range is already in the allowlist of this scope.",
+  },
   InvalidBearerToken {
     status: UNAUTHORIZED,
     "The provided bearer token is invalid.",
@@ -213,6 +310,22 @@ errors!(
     status: BAD_REQUEST,
     "The requested authorization is still pending. Try again later.",
   },
+  ScopeNotOrphaned {
+    status: BAD_REQUEST,
+    "The creator of the requested scope is not blocked, so the scope can not be recovered.",
+  },
+  ScopeRecoveryAlreadyRequested {
+    status: CONFLICT,
+    "A recovery request for the requested scope is already pending review.",
+  },
+  ScopeRecoveryRequestNotFound {
+    status: NOT_FOUND,
+    "The requested scope recovery request was not found.",
+  },
+  ScopeRecoveryRequestNotPending {
+    status: BAD_REQUEST,
+    "The requested scope recovery request has already been reviewed, or the creator of the scope has changed since it was made.",
+  },
   ScopeNotEmpty {
     status: CONFLICT,
     "The requested scope contains packages. Only empty scopes may be deleted.",
@@ -237,6 +350,79 @@ errors!(
     status: BAD_REQUEST,
     "The requested package is archived. Unarchive it to modify settings or publish to it.",
   },
+  InvalidPackageSuccessor {
+    status: BAD_REQUEST,
+    fields: { msg: Cow<'static, str> },
+    ({ msg }) => "Invalid package successor: {msg}.",
+  },
+  DeletionReasonInvalid {
+    status: BAD_REQUEST,
+    "The deletion reason must not be empty, and may be at most 1000 characters.",
+  },
+  PackageVersionIsLatest {
+    status: CONFLICT,
+    "The requested version is the latest version of the package. Publish a newer version first, or set force to remove it anyway.",
+  },
+  YankReasonTooLong {
+    status: BAD_REQUEST,
+    "The yank reason is too long. It may be at most 1000 characters.",
+  },
+  UserEmailRequired {
+    status: BAD_REQUEST,
+    "An email address is required to receive a data export. Add a public or primary email address to your GitHub account and log in again.",
+  },
+  UserExportNotFound {
+    status: NOT_FOUND,
+    "The requested data export was not found, or its download link has expired.",
+  },
+  PackageVersionArtifactNotFound {
+    status: NOT_FOUND,
+    "The requested artifact was not found, or its upload link has expired.",
+  },
+  PackageVersionArtifactAlreadyExists {
+    status: CONFLICT,
+    "An artifact with this filename was already uploaded to the package version. Delete it before uploading it again.",
+  },
+  PackageVersionArtifactLimitExceeded {
+    status: BAD_REQUEST,
+    fields: { limit: usize },
+    ({ limit }) => "Exceeded limit of {limit} artifacts per package version.",
+  },
+  PackageVersionArtifactSizeLimitExceeded {
+    status: PAYLOAD_TOO_LARGE,
+    fields: { size: u64, max_size: u64 },
+    ({ size, max_size }) => "The artifact ({size} bytes) exceeds the space left for artifacts of the package version ({max_size} bytes).",
+  },
+  PackageVersionArtifactMismatch {
+    status: BAD_REQUEST,
+    "The uploaded artifact does not match the size or SHA-256 digest that were declared when the upload was requested.",
+  },
+  ApiKeyNotFound {
+    status: NOT_FOUND,
+    "The requested API key was not found.",
+  },
+  ApiKeyLimitExceeded {
+    status: BAD_REQUEST,
+    fields: { limit: usize },
+    ({ limit }) => "Exceeded limit of {limit} API keys per user.",
+  },
+  PackageCollectionNotFound {
+    status: NOT_FOUND,
+    "The requested collection was not found.",
+  },
+  PackageCollectionLimitExceeded {
+    status: BAD_REQUEST,
+    fields: { limit: usize },
+    ({ limit }) => "Exceeded limit of {limit} collections per user.",
+  },
+  InvalidApiKey {
+    status: UNAUTHORIZED,
+    "The provided API key is invalid.",
+  },
+  RateLimitExceeded {
+    status: TOO_MANY_REQUESTS,
+    "Too many requests. Try again in a minute, or use an API key for a higher rate limit.",
+  },
 );
 
 pub fn map_unique_violation(err: sqlx::Error, new_err: ApiError) -> ApiError {
@@ -1,37 +1,70 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 mod admin;
+mod artifact;
 mod authorization;
+mod collection;
+mod downloads;
 mod errors;
+mod npm;
+mod oidc;
 mod package;
 mod publishing_task;
+mod rate_limits;
+mod registry;
 mod scope;
 mod self_user;
+mod status;
+mod tools;
 mod types;
 mod users;
+mod watch;
 
+use chrono::TimeZone;
+use chrono::Utc;
 use hyper::Body;
 use hyper::Response;
 use package::global_list_handler;
 use package::global_metrics_handler;
 use package::global_stats_handler;
+use package::topic_list_handler;
 use routerify::Middleware;
 use routerify::Router;
 
 pub use self::errors::*;
+use self::npm::npm_router;
+pub use self::npm::NpmTarballBuildDemand;
+pub use self::npm::NpmTarballOnDemandBuilds;
+use self::oidc::oidc_router;
+pub use self::package::dependency_freshness;
 pub use self::package::PublishQueue;
 use self::publishing_task::publishing_task_router;
+use self::rate_limits::rate_limits_handler;
+pub use self::registry::registry_metadata_handler;
 use self::self_user::self_user_router;
+use self::status::status_handler;
+use self::tools::tools_router;
 pub use self::types::*;
 
 use self::admin::admin_router;
 use self::authorization::authorization_router;
+use self::collection::collection_router;
+use self::downloads::downloads_router;
 use self::scope::scope_router;
 use self::users::users_router;
+use self::watch::watch_handler;
 
 use crate::util;
 use crate::util::CacheDuration;
+use crate::util::Deprecation;
 
 pub fn api_router() -> Router<Body, ApiError> {
+  // Superseded by `GET /api/publishing_tasks/:publishing_task_id`.
+  let publish_status_deprecation = Deprecation {
+    route: "/api/publish_status/:publishing_task_id",
+    deprecated_at: Utc.with_ymd_and_hms(2024, 11, 1, 0, 0, 0).unwrap(),
+    sunset: Utc.with_ymd_and_hms(2027, 6, 1, 0, 0, 0).unwrap(),
+  };
+
   Router::builder()
     .get(
       "/metrics",
@@ -41,21 +74,43 @@ pub fn api_router() -> Router<Body, ApiError> {
       ),
     )
     .middleware(Middleware::pre(util::auth_middleware))
+    .middleware(Middleware::pre(util::rate_limit_middleware))
+    .middleware(Middleware::post_with_info(util::quota_headers_middleware))
     .scope("/admin", admin_router())
     .scope("/scopes", scope_router())
     .scope("/user", self_user_router())
     .scope("/users", users_router())
+    .scope("/collections", collection_router())
     .scope("/authorizations", authorization_router())
+    .scope("/oidc", oidc_router())
     .scope("/publishing_tasks", publishing_task_router())
-    .get("/packages", util::json(global_list_handler))
+    .scope("/npm", npm_router())
+    .scope("/tools", tools_router())
+    .scope("/downloads", downloads_router())
+    .get("/packages", util::paginated_json(global_list_handler))
+    .post("/watch", util::json(watch_handler))
+    .get(
+      "/topics/:topic/packages",
+      util::cache(
+        CacheDuration::ONE_MINUTE,
+        util::paginated_json(topic_list_handler),
+      ),
+    )
     .get(
       "/stats",
       util::cache(CacheDuration::ONE_MINUTE, util::json(global_stats_handler)),
     )
     .get(
-      // todo: remove once CLI uses the new endpoint
       "/publish_status/:publishing_task_id",
-      util::json(publishing_task::get_handler),
+      util::deprecated(
+        publish_status_deprecation,
+        util::json(publishing_task::get_handler),
+      ),
+    )
+    .get("/rate_limits", util::json(rate_limits_handler))
+    .get(
+      "/status",
+      util::cache(CacheDuration::ONE_MINUTE, util::json(status_handler)),
     )
     .get("/.well-known/openapi", openapi_handler)
     .build()
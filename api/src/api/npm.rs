@@ -0,0 +1,802 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::Utc;
+use hyper::header;
+use hyper::header::HeaderValue;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use hyper::StatusCode;
+use routerify::prelude::RequestExt;
+use routerify::Router;
+use tokio::sync::Semaphore;
+use tracing::error;
+use tracing::field;
+use tracing::instrument;
+use tracing::Instrument;
+use tracing::Span;
+use url::Url;
+
+use crate::buckets::Buckets;
+use crate::db::Database;
+use crate::db::NpmTarballBuildRequest;
+use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
+use crate::gcp::CACHE_CONTROL_IMMUTABLE;
+use crate::gcs_paths;
+use crate::ids::PackageName;
+use crate::ids::ScopeName;
+use crate::ids::Version;
+use crate::npm::generate_npm_version_manifest;
+use crate::npm::NpmJsrVersionInfo;
+use crate::npm::NpmMappedJsrPackageName;
+use crate::npm::NPM_TARBALL_REVISION;
+use crate::tasks::build_npm_tarball;
+use crate::tasks::upload_npm_version_manifest;
+use crate::util;
+use crate::util::ApiResult;
+use crate::NpmIncludeYankedVersions;
+use crate::NpmUrl;
+use crate::RegistryUrl;
+
+use super::ApiError;
+use super::ApiPackageScore;
+
+/// How long a request waits for an on-demand npm tarball build before falling
+/// back to the tarball of a previous revision.
+const NPM_TARBALL_ON_DEMAND_BUILD_TIMEOUT: Duration = Duration::from_secs(20);
+/// How often a request that is waiting for a build checks whether the tarball
+/// has been built.
+const NPM_TARBALL_ON_DEMAND_POLL_INTERVAL: Duration =
+  Duration::from_millis(500);
+const NPM_TARBALL_ON_DEMAND_CLAIM_DURATION: chrono::Duration =
+  chrono::Duration::minutes(2);
+/// How many npm tarballs an instance builds on demand at the same time.
+/// Requests that find no free slot wait for builds by others, or fall back to
+/// the previous revision, and leave the build to the background workers.
+const NPM_TARBALL_ON_DEMAND_MAX_BUILDS: usize = 4;
+
+/// The slots for on-demand npm tarball builds in this instance.
+#[derive(Clone)]
+pub struct NpmTarballOnDemandBuilds(Arc<Semaphore>);
+
+impl Default for NpmTarballOnDemandBuilds {
+  fn default() -> Self {
+    Self(Arc::new(Semaphore::new(NPM_TARBALL_ON_DEMAND_MAX_BUILDS)))
+  }
+}
+
+/// How long repeated requests by one client for a tarball that is not built
+/// yet are counted only once towards the build priority of its package.
+const NPM_TARBALL_DEMAND_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// How many clients and versions an instance keeps track of. Requests are not
+/// counted while all of them were seen within the window.
+const NPM_TARBALL_DEMAND_MAX_TRACKED: usize = 100_000;
+
+/// The clients that requested tarballs of the current revision that were not
+/// built yet, so that a client can not raise the build priority of a package
+/// by requesting its tarballs over and over.
+#[derive(Clone, Default)]
+#[allow(clippy::type_complexity)]
+pub struct NpmTarballBuildDemand(
+  Arc<Mutex<HashMap<(IpAddr, ScopeName, PackageName, Version), Instant>>>,
+);
+
+impl NpmTarballBuildDemand {
+  /// Returns whether the request should be counted, which is the case if the
+  /// client did not request the same version within the window.
+  fn count(
+    &self,
+    client: IpAddr,
+    scope: &ScopeName,
+    package: &PackageName,
+    version: &Version,
+  ) -> bool {
+    self.count_at(client, scope, package, version, Instant::now())
+  }
+
+  fn count_at(
+    &self,
+    client: IpAddr,
+    scope: &ScopeName,
+    package: &PackageName,
+    version: &Version,
+    now: Instant,
+  ) -> bool {
+    let mut seen = self.0.lock().unwrap();
+    let key = (client, scope.clone(), package.clone(), version.clone());
+    if let Some(seen_at) = seen.get(&key) {
+      if now - *seen_at < NPM_TARBALL_DEMAND_WINDOW {
+        return false;
+      }
+    }
+    if seen.len() >= NPM_TARBALL_DEMAND_MAX_TRACKED {
+      seen.retain(|_, seen_at| now - *seen_at < NPM_TARBALL_DEMAND_WINDOW);
+      if seen.len() >= NPM_TARBALL_DEMAND_MAX_TRACKED {
+        return false;
+      }
+    }
+    seen.insert(key, now);
+    true
+  }
+}
+
+/// Routes for the npm compatibility endpoint that are served by the API instead
+/// of the npm bucket. The load balancer routes version manifest and tarball
+/// requests on the npm host here. Tarballs of the current revision that were
+/// not built yet are built on demand. Built tarballs are served with immutable
+/// cache headers, so repeated downloads are served by the CDN.
+pub fn npm_router() -> Router<Body, ApiError> {
+  Router::builder()
+    .get("/@jsr/:npm_package", version_manifest_handler)
+    .get("/~/:revision/@jsr/:npm_package/:tarball", tarball_handler)
+    .build()
+    .unwrap()
+}
+
+/// Whether the `Accept` header of the request asks for the `_jsr` extension in
+/// version manifests, with a `jsr` parameter, like
+/// `Accept: application/json; jsr=1`.
+fn accepts_jsr_extension(req: &Request<Body>) -> bool {
+  req
+    .headers()
+    .get_all(header::ACCEPT)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .flat_map(|media_range| media_range.split(';').skip(1))
+    .filter_map(|param| param.split_once('='))
+    .any(|(name, value)| {
+      name.trim().eq_ignore_ascii_case("jsr")
+        && matches!(value.trim().trim_matches('"'), "1" | "true")
+    })
+}
+
+/// Manifests generated from the database are cached briefly, so that the CDN
+/// absorbs repeated requests. The manifests differ by the `Accept` header.
+const GENERATED_MANIFEST_CACHE_CONTROL: &str = "public, max-age=60";
+
+/// Serves the version manifest (packument) of a package. The load balancer
+/// routes manifest requests on the npm host here, so that the old names of
+/// renamed or moved packages are redirected to their new names. Plain manifests
+/// are served from the npm bucket. Manifests with the `_jsr` extension in each
+/// version (see [`accepts_jsr_extension`]) are generated from the database.
+#[instrument(
+  name = "GET /api/npm/@jsr/:npm_package",
+  skip(req),
+  err,
+  fields(scope, package, jsr_extension)
+)]
+pub async fn version_manifest_handler(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let (scope, package) = req
+    .param("npm_package")
+    .unwrap()
+    .split_once("__")
+    .ok_or(ApiError::PackageNotFound)?;
+  let scope =
+    ScopeName::try_from(scope).map_err(|_| ApiError::PackageNotFound)?;
+  let package =
+    PackageName::try_from(package).map_err(|_| ApiError::PackageNotFound)?;
+  let jsr_extension = accepts_jsr_extension(&req);
+
+  let span = Span::current();
+  span.record("scope", field::display(&scope));
+  span.record("package", field::display(&package));
+  span.record("jsr_extension", jsr_extension);
+
+  if !jsr_extension {
+    let buckets = req.data::<Buckets>().unwrap();
+    let path = gcs_paths::npm_version_manifest_path(&scope, &package);
+    if let Some(manifest) = buckets.npm_bucket.download(path.into()).await? {
+      return Ok(
+        Response::builder()
+          .header(header::CONTENT_TYPE, "application/json")
+          .header(header::VARY, "Accept")
+          .header(header::CACHE_CONTROL, CACHE_CONTROL_DO_NOT_CACHE)
+          .body(Body::from(manifest))
+          .unwrap(),
+      );
+    }
+  }
+
+  let db = req.data::<Database>().unwrap();
+  let npm_url = &req.data::<NpmUrl>().unwrap().0;
+  let npm_include_yanked = req.data::<NpmIncludeYankedVersions>().unwrap().0;
+
+  let Some((pkg, _, meta)) = db.get_package(&scope, &package).await? else {
+    // The package may have been renamed or moved to another scope.
+    let redirect = db
+      .get_package_redirect(&scope, &package)
+      .await?
+      .ok_or(ApiError::PackageNotFound)?;
+    let location = Url::options()
+      .base_url(Some(npm_url))
+      .parse(&format!(
+        "./{}",
+        NpmMappedJsrPackageName {
+          scope: &redirect.target_scope,
+          package: &redirect.target_name,
+        }
+      ))
+      .unwrap();
+    return Ok(util::moved_permanently(location.as_str()));
+  };
+
+  let mut manifest = generate_npm_version_manifest(
+    db,
+    npm_url,
+    &scope,
+    &package,
+    npm_include_yanked,
+  )
+  .await?;
+
+  if jsr_extension {
+    let scope_info = db
+      .get_scope(&scope)
+      .await?
+      .ok_or(ApiError::PackageNotFound)?;
+    let score = ApiPackageScore::from((&meta, &pkg)).score_percentage();
+    let versions = db.list_package_versions(&scope, &package).await?;
+    for (version, _) in versions {
+      let Some(info) = manifest.versions.get_mut(&version.version) else {
+        continue;
+      };
+      info.jsr = Some(NpmJsrVersionInfo {
+        has_provenance: version.rekor_log_id.is_some(),
+        rekor_log_id: version.rekor_log_id,
+        provenance_required: scope_info.require_publishing_from_ci,
+        score,
+        runtime_compat: pkg.runtime_compat.clone(),
+      });
+    }
+  }
+
+  let mut resp = util::respond_json(&manifest, StatusCode::OK);
+  let headers = resp.headers_mut();
+  headers.insert(header::VARY, HeaderValue::from_static("Accept"));
+  headers.insert(
+    header::CACHE_CONTROL,
+    HeaderValue::from_static(GENERATED_MANIFEST_CACHE_CONTROL),
+  );
+  Ok(resp)
+}
+
+#[instrument(
+  name = "GET /api/npm/~/:revision/@jsr/:npm_package/:tarball",
+  skip(req),
+  err,
+  fields(scope, package, version, revision)
+)]
+pub async fn tarball_handler(req: Request<Body>) -> ApiResult<Response<Body>> {
+  let revision: u32 = req
+    .param("revision")
+    .unwrap()
+    .parse()
+    .map_err(|_| ApiError::NpmTarballNotFound)?;
+  let (scope, package) = req
+    .param("npm_package")
+    .unwrap()
+    .split_once("__")
+    .ok_or(ApiError::NpmTarballNotFound)?;
+  let scope =
+    ScopeName::try_from(scope).map_err(|_| ApiError::NpmTarballNotFound)?;
+  let package =
+    PackageName::try_from(package).map_err(|_| ApiError::NpmTarballNotFound)?;
+  let version = req
+    .param("tarball")
+    .unwrap()
+    .strip_suffix(".tgz")
+    .and_then(|version| Version::try_from(version).ok())
+    .ok_or(ApiError::NpmTarballNotFound)?;
+
+  let span = Span::current();
+  span.record("scope", field::display(&scope));
+  span.record("package", field::display(&package));
+  span.record("version", field::display(&version));
+  span.record("revision", revision);
+
+  let db = req.data::<Database>().unwrap().clone();
+  let buckets = req.data::<Buckets>().unwrap().clone();
+  let registry_url = req.data::<RegistryUrl>().unwrap().0.clone();
+  let npm_url = req.data::<NpmUrl>().unwrap().0.clone();
+  let npm_include_yanked = req.data::<NpmIncludeYankedVersions>().unwrap().0;
+
+  db.get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let is_built = db
+    .get_npm_tarball(&scope, &package, &version, revision as i32)
+    .await?
+    .is_some();
+
+  if is_built {
+    return serve_npm_tarball(&buckets, &scope, &package, &version, revision)
+      .await;
+  }
+
+  if revision == NPM_TARBALL_REVISION {
+    // Make sure the background workers pick up the rest of this package soon.
+    // Each client counts once per version, so that retries, or a single
+    // client requesting the same tarball over and over, do not raise the
+    // priority of the package.
+    let demand = req.data::<NpmTarballBuildDemand>().unwrap();
+    let counted = util::client_ip(&req)
+      .is_some_and(|client| demand.count(client, &scope, &package, &version));
+    if counted {
+      db.record_npm_tarball_build_requests(vec![NpmTarballBuildRequest {
+        scope: scope.clone(),
+        name: package.clone(),
+        count: 1,
+        last_requested_at: Utc::now(),
+      }])
+      .await?;
+    }
+
+    // Only one build runs per version: the request that claims the version
+    // builds it, and all other requests wait for that build. The build is
+    // spawned so that it runs to completion even if the request stops waiting
+    // for it. If this instance is already building as many tarballs as it
+    // may, the request falls back right away.
+    let builds = req.data::<NpmTarballOnDemandBuilds>().unwrap();
+    let is_building = if let Ok(permit) = builds.0.clone().try_acquire_owned() {
+      let claimed = db
+        .claim_npm_tarball_build(
+          &scope,
+          &package,
+          &version,
+          NPM_TARBALL_REVISION as i32,
+          NPM_TARBALL_ON_DEMAND_CLAIM_DURATION,
+        )
+        .await?;
+      if claimed {
+        let db = db.clone();
+        let buckets = buckets.clone();
+        let scope = scope.clone();
+        let package = package.clone();
+        let version = version.clone();
+        let npm_url = npm_url.clone();
+        tokio::spawn(
+          async move {
+            let _permit = permit;
+            let res = build_npm_tarball_and_manifest(
+              &db,
+              &buckets,
+              &registry_url,
+              &npm_url,
+              npm_include_yanked,
+              &scope,
+              &package,
+              &version,
+            )
+            .await;
+            if let Err(err) = res {
+              error!("failed to build npm tarball on demand: {err}");
+            }
+          }
+          .instrument(span.clone()),
+        );
+      }
+      // If the claim is held by someone else, they are building it.
+      true
+    } else {
+      false
+    };
+
+    if is_building
+      && wait_for_npm_tarball(&db, &scope, &package, &version).await?
+    {
+      return serve_npm_tarball(
+        &buckets,
+        &scope,
+        &package,
+        &version,
+        NPM_TARBALL_REVISION,
+      )
+      .await;
+    }
+  }
+
+  // Fall back to the most recent revision that has been built.
+  let fallback = db
+    .get_latest_npm_tarball_for_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::NpmTarballNotFound)?;
+  if fallback.revision as u32 == revision {
+    return Err(ApiError::NpmTarballNotFound);
+  }
+
+  let location = npm_tarball_url(
+    &npm_url,
+    &scope,
+    &package,
+    &version,
+    fallback.revision as u32,
+  );
+  Ok(
+    Response::builder()
+      .status(StatusCode::FOUND)
+      .header(header::LOCATION, location.as_str())
+      .header(header::CACHE_CONTROL, CACHE_CONTROL_DO_NOT_CACHE)
+      .body(Body::empty())
+      .unwrap(),
+  )
+}
+
+/// Builds the npm tarball of the current revision of a version, and updates
+/// the version manifest of the package to point to it.
+#[allow(clippy::too_many_arguments)]
+async fn build_npm_tarball_and_manifest(
+  db: &Database,
+  buckets: &Buckets,
+  registry_url: &Url,
+  npm_url: &Url,
+  npm_include_yanked: bool,
+  scope: &ScopeName,
+  package: &PackageName,
+  version: &Version,
+) -> Result<(), ApiError> {
+  build_npm_tarball(db, buckets, registry_url, scope, package, version).await?;
+  upload_npm_version_manifest(
+    db,
+    buckets,
+    npm_url,
+    scope,
+    package,
+    npm_include_yanked,
+  )
+  .await?;
+  Ok(())
+}
+
+/// Waits until the npm tarball of the current revision of a version has been
+/// built, by this or another instance. Returns `false` if it was not built
+/// within [`NPM_TARBALL_ON_DEMAND_BUILD_TIMEOUT`].
+async fn wait_for_npm_tarball(
+  db: &Database,
+  scope: &ScopeName,
+  package: &PackageName,
+  version: &Version,
+) -> Result<bool, ApiError> {
+  let deadline =
+    tokio::time::Instant::now() + NPM_TARBALL_ON_DEMAND_BUILD_TIMEOUT;
+  loop {
+    let is_built = db
+      .get_npm_tarball(scope, package, version, NPM_TARBALL_REVISION as i32)
+      .await?
+      .is_some();
+    if is_built {
+      return Ok(true);
+    }
+    if tokio::time::Instant::now() >= deadline {
+      return Ok(false);
+    }
+    tokio::time::sleep(NPM_TARBALL_ON_DEMAND_POLL_INTERVAL).await;
+  }
+}
+
+async fn serve_npm_tarball(
+  buckets: &Buckets,
+  scope: &ScopeName,
+  package: &PackageName,
+  version: &Version,
+  revision: u32,
+) -> ApiResult<Response<Body>> {
+  let path = gcs_paths::npm_tarball_path(scope, package, version, revision);
+  let tarball = buckets
+    .npm_bucket
+    .download(path.into())
+    .await?
+    .ok_or(ApiError::NpmTarballNotFound)?;
+
+  Ok(
+    Response::builder()
+      .header(header::CONTENT_TYPE, "application/octet-stream")
+      .header(header::CACHE_CONTROL, CACHE_CONTROL_IMMUTABLE)
+      .body(Body::from(tarball))
+      .unwrap(),
+  )
+}
+
+fn npm_tarball_url(
+  npm_url: &Url,
+  scope: &ScopeName,
+  package: &PackageName,
+  version: &Version,
+  revision: u32,
+) -> Url {
+  Url::options()
+    .base_url(Some(npm_url))
+    .parse(&format!(
+      "./~/{}/{}/{}.tgz",
+      revision,
+      NpmMappedJsrPackageName { scope, package },
+      version,
+    ))
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::IpAddr;
+  use std::time::Duration;
+  use std::time::Instant;
+
+  use hyper::StatusCode;
+
+  use super::NpmTarballBuildDemand;
+  use super::NPM_TARBALL_DEMAND_WINDOW;
+
+  use crate::db::NewNpmTarball;
+  use crate::db::PublishingTaskStatus;
+  use crate::gcp::CACHE_CONTROL_IMMUTABLE;
+  use crate::ids::PackageName;
+  use crate::ids::ScopeName;
+  use crate::ids::Version;
+  use crate::npm::NPM_TARBALL_REVISION;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  #[tokio::test]
+  async fn version_manifest_jsr_extension() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    let name = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.0.0").unwrap();
+    t.db().create_package(&scope, &name).await.unwrap();
+    t.db()
+      .create_package_version_for_test(crate::db::NewPackageVersion {
+        scope: &scope,
+        name: &name,
+        version: &version,
+        exports: &crate::db::ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
+        user_id: None,
+        readme_path: None,
+        uses_npm: false,
+        meta: Default::default(),
+      })
+      .await
+      .unwrap();
+    t.db()
+      .create_npm_tarball(NewNpmTarball {
+        scope: &scope,
+        name: &name,
+        version: &version,
+        revision: NPM_TARBALL_REVISION as i32,
+        sha1: "",
+        sha512: "",
+        size: 0,
+      })
+      .await
+      .unwrap();
+
+    let path = format!("/api/npm/@jsr/{}__{}", scope, name);
+
+    // Strict packuments by default.
+    let manifest: serde_json::Value = t
+      .unauthed_http()
+      .get(&path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(manifest["dist-tags"]["latest"], "1.0.0");
+    assert!(manifest["versions"]["1.0.0"].get("_jsr").is_none());
+
+    let mut resp = t
+      .unauthed_http()
+      .get(&path)
+      .header(
+        hyper::header::ACCEPT,
+        hyper::header::HeaderValue::from_static("application/json; jsr=1"),
+      )
+      .call()
+      .await
+      .unwrap();
+    // Generated manifests are cached briefly, separately per `Accept` header.
+    assert_eq!(
+      resp.headers().get("cache-control").unwrap(),
+      super::GENERATED_MANIFEST_CACHE_CONTROL
+    );
+    assert_eq!(resp.headers().get("vary").unwrap(), "Accept");
+    let manifest: serde_json::Value = resp.expect_ok().await;
+    let jsr = &manifest["versions"]["1.0.0"]["_jsr"];
+    assert_eq!(jsr["hasProvenance"], false);
+    assert_eq!(jsr["provenanceRequired"], false);
+    assert!(jsr["score"].is_u64());
+    assert!(jsr["runtimeCompat"].is_object());
+
+    t.unauthed_http()
+      .get(format!("/api/npm/@jsr/{}__bar", scope))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn tarball_built_on_demand() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    // Simulate a revision bump: only the previous revision was built.
+    let scope = ScopeName::try_from("scope").unwrap();
+    let name = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.2.3").unwrap();
+    let current = NPM_TARBALL_REVISION as i32;
+    let previous = t
+      .db()
+      .get_npm_tarball(&scope, &name, &version, current)
+      .await
+      .unwrap()
+      .unwrap();
+    t.db()
+      .delete_npm_tarball(&scope, &name, &version, current)
+      .await
+      .unwrap();
+    t.db()
+      .create_npm_tarball(NewNpmTarball {
+        scope: &scope,
+        name: &name,
+        version: &version,
+        revision: current - 1,
+        sha1: &previous.sha1,
+        sha512: &previous.sha512,
+        size: previous.size,
+      })
+      .await
+      .unwrap();
+
+    let path = format!(
+      "/api/npm/~/{}/@jsr/{}__{}/{}.tgz",
+      NPM_TARBALL_REVISION, scope, name, version
+    );
+    for _ in 0..2 {
+      let resp = t.unauthed_http().get(&path).call().await.unwrap();
+      assert_eq!(resp.status(), StatusCode::OK);
+      assert_eq!(
+        resp.headers().get("cache-control").unwrap(),
+        CACHE_CONTROL_IMMUTABLE
+      );
+      let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+      // A gzipped tarball.
+      assert_eq!(&body[..2], &[0x1f, 0x8b]);
+
+      let built = t
+        .db()
+        .get_npm_tarball(&scope, &name, &version, current)
+        .await
+        .unwrap()
+        .unwrap();
+      assert_eq!(built.size as usize, body.len());
+    }
+
+    // The build released its claim.
+    assert!(t
+      .db()
+      .claim_npm_tarball_build(
+        &scope,
+        &name,
+        &version,
+        current,
+        chrono::Duration::minutes(1),
+      )
+      .await
+      .unwrap());
+  }
+
+  #[tokio::test]
+  async fn tarball_fallback_to_previous_revision() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    let name = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.0.0").unwrap();
+    t.db().create_package(&scope, &name).await.unwrap();
+    t.db()
+      .create_package_version_for_test(crate::db::NewPackageVersion {
+        scope: &scope,
+        name: &name,
+        version: &version,
+        exports: &crate::db::ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
+        user_id: None,
+        readme_path: None,
+        uses_npm: false,
+        meta: Default::default(),
+      })
+      .await
+      .unwrap();
+    t.db()
+      .create_npm_tarball(NewNpmTarball {
+        scope: &scope,
+        name: &name,
+        version: &version,
+        revision: NPM_TARBALL_REVISION as i32 - 1,
+        sha1: "",
+        sha512: "",
+        size: 0,
+      })
+      .await
+      .unwrap();
+
+    // A revision that was never built falls back to the latest revision that
+    // was.
+    let path = format!(
+      "/api/npm/~/{}/@jsr/{}__{}/{}.tgz",
+      NPM_TARBALL_REVISION - 2,
+      scope,
+      name,
+      version
+    );
+    let resp = t.unauthed_http().get(path).call().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    let location = resp.headers().get("location").unwrap().to_str().unwrap();
+    assert!(
+      location.ends_with(&format!(
+        "/~/{}/@jsr/{}__{}/{}.tgz",
+        NPM_TARBALL_REVISION - 1,
+        scope,
+        name,
+        version
+      )),
+      "{location}"
+    );
+
+    // Unknown versions are not found.
+    let path = format!(
+      "/api/npm/~/{}/@jsr/{}__{}/2.0.0.tgz",
+      NPM_TARBALL_REVISION, scope, name
+    );
+    t.unauthed_http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+  }
+
+  #[test]
+  fn tarball_build_demand_counts_clients_once() {
+    let demand = NpmTarballBuildDemand::default();
+    let scope = ScopeName::try_from("scope").unwrap();
+    let name = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.0.0").unwrap();
+    let other_version = Version::try_from("1.0.1").unwrap();
+    let client: IpAddr = "203.0.113.1".parse().unwrap();
+    let other_client: IpAddr = "203.0.113.2".parse().unwrap();
+    let now = Instant::now();
+
+    assert!(demand.count_at(client, &scope, &name, &version, now));
+    let soon = now + Duration::from_secs(60);
+    assert!(!demand.count_at(client, &scope, &name, &version, soon));
+    assert!(demand.count_at(client, &scope, &name, &other_version, soon));
+    assert!(demand.count_at(other_client, &scope, &name, &version, soon));
+
+    let later = now + NPM_TARBALL_DEMAND_WINDOW;
+    assert!(demand.count_at(client, &scope, &name, &version, later));
+  }
+}
@@ -0,0 +1,234 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use chrono::Utc;
+use hyper::header;
+use hyper::Body;
+use hyper::Request;
+use routerify::prelude::RequestExt;
+use routerify::Router;
+use tracing::field;
+use tracing::instrument;
+use tracing::Span;
+
+use crate::db::Database;
+use crate::db::NewOidcPublishToken;
+use crate::iam::IamInfo;
+use crate::iam::Principal;
+use crate::iam::ReqIamExt;
+use crate::token::generate_oidc_publish_token;
+use crate::util;
+use crate::util::decode_json;
+use crate::util::ApiResult;
+
+use super::ApiError;
+use super::ApiOidcExchangeRequest;
+use super::ApiOidcExchangeResponse;
+
+/// How long a publish token obtained through an OIDC token exchange is valid.
+pub const OIDC_PUBLISH_TOKEN_EXPIRY: chrono::Duration =
+  chrono::Duration::minutes(15);
+
+pub fn oidc_router() -> Router<Body, ApiError> {
+  Router::builder()
+    .post("/exchange", util::auth(util::json(exchange_handler)))
+    .build()
+    .unwrap()
+}
+
+/// Exchanges a GitHub Actions OIDC token for a short-lived token that can only
+/// publish the requested package version. The same rules apply as when
+/// publishing with the OIDC token directly: the package must be linked to the
+/// repository of the workflow, and the scope policy must allow the actor.
+#[instrument(
+  name = "POST /api/oidc/exchange",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+async fn exchange_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiOidcExchangeResponse> {
+  let ApiOidcExchangeRequest {
+    scope,
+    package,
+    version,
+    tarball_hash,
+  } = decode_json(&mut req).await?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  // Only OIDC tokens can be exchanged, not tokens that were obtained through
+  // an earlier exchange, so that the lifetime of a token can not be extended.
+  let is_oidc_token = req
+    .headers()
+    .get(header::AUTHORIZATION)
+    .and_then(|value| value.to_str().ok())
+    .is_some_and(|value| value.starts_with("githuboidc "));
+  let iam_info = req.context::<IamInfo>().unwrap();
+  let (repo_id, workflow_run) = match &iam_info.principal {
+    Principal::GitHubActions {
+      repo_id,
+      workflow_run,
+      ..
+    } if is_oidc_token => (*repo_id, workflow_run.clone()),
+    _ => return Err(ApiError::ActorNotGitHubActions),
+  };
+
+  let db = req.data::<Database>().unwrap();
+  let iam = req.iam();
+  let (restriction, user_id) =
+    iam.check_publish_access(&scope, &package, &version).await?;
+  if let Some(allowed_tarball_hash) = restriction.tarball_hash {
+    if allowed_tarball_hash != tarball_hash {
+      return Err(ApiError::MissingPermission);
+    }
+  }
+
+  let token = generate_oidc_publish_token();
+  let expires_at = Utc::now() + OIDC_PUBLISH_TOKEN_EXPIRY;
+  db.insert_oidc_publish_token(NewOidcPublishToken {
+    hash: &crate::token::hash(&token),
+    repository_id: repo_id,
+    github_workflow_run: workflow_run,
+    user_id,
+    scope: &scope,
+    name: &package,
+    version: &version,
+    tarball_hash: &tarball_hash,
+    expires_at,
+  })
+  .await?;
+
+  Ok(ApiOidcExchangeResponse { token, expires_at })
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Utc;
+  use hyper::StatusCode;
+  use serde_json::json;
+
+  use crate::db::CreatePackageResult;
+  use crate::db::GithubWorkflowRun;
+  use crate::db::NewOidcPublishToken;
+  use crate::ids::PackageName;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+  use crate::token::generate_oidc_publish_token;
+
+  #[tokio::test]
+  async fn exchanged_tokens() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    let name = PackageName::try_from("foo").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package(&scope, &name)
+      .await
+      .unwrap();
+    assert!(matches!(res, CreatePackageResult::Ok(_)));
+
+    let workflow_run = GithubWorkflowRun {
+      repository: "denoland/deno".into(),
+      workflow_path: ".github/workflows/publish.yml".into(),
+      git_ref: "refs/heads/main".into(),
+      run_id: 1234,
+    };
+    let token = generate_oidc_publish_token();
+    t.ephemeral_database
+      .insert_oidc_publish_token(NewOidcPublishToken {
+        hash: &crate::token::hash(&token),
+        repository_id: 1,
+        github_workflow_run: Some(workflow_run.clone()),
+        user_id: None,
+        scope: &scope,
+        name: &name,
+        version: &"1.0.0".try_into().unwrap(),
+        tarball_hash: "sha256-abc",
+        expires_at: Utc::now() + chrono::Duration::minutes(15),
+      })
+      .await
+      .unwrap();
+
+    // the workflow run is kept, so that it can be recorded on the publish
+    let stored = t
+      .ephemeral_database
+      .get_oidc_publish_token_by_hash(&crate::token::hash(&token))
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(stored.github_workflow_run, Some(workflow_run));
+
+    // an exchanged token can not be exchanged again
+    let body = json!({
+      "scope": "scope",
+      "package": "foo",
+      "version": "1.0.0",
+      "tarballHash": "sha256-abc",
+    });
+    let mut resp = t
+      .http()
+      .post("/api/oidc/exchange")
+      .token(Some(&token))
+      .body_json(body.clone())
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotGitHubActions")
+      .await;
+
+    // an exchanged token does not allow anything but publishing
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .token(Some(&token))
+      .body_json(json!({ "description": "foo" }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "missingPermission")
+      .await;
+
+    // users can not exchange their tokens
+    let mut resp = t
+      .http()
+      .post("/api/oidc/exchange")
+      .body_json(body)
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotGitHubActions")
+      .await;
+
+    // expired tokens are rejected
+    let expired_token = generate_oidc_publish_token();
+    t.ephemeral_database
+      .insert_oidc_publish_token(NewOidcPublishToken {
+        hash: &crate::token::hash(&expired_token),
+        repository_id: 1,
+        github_workflow_run: None,
+        user_id: None,
+        scope: &scope,
+        name: &name,
+        version: &"1.0.0".try_into().unwrap(),
+        tarball_hash: "sha256-abc",
+        expires_at: Utc::now() - chrono::Duration::minutes(1),
+      })
+      .await
+      .unwrap();
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo")
+      .token(Some(&expired_token))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::UNAUTHORIZED, "invalidBearerToken")
+      .await;
+  }
+}
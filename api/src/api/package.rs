@@ -17,10 +17,12 @@ use deno_graph::Module;
 use deno_graph::ModuleInfo;
 use deno_graph::Resolution;
 use deno_graph::WorkspaceMember;
+use deno_semver::jsr::JsrPackageReqReference;
 use deno_semver::StackString;
-use futures::future::Either;
+use deno_semver::VersionReq;
 use futures::StreamExt;
 use hyper::body::HttpBody;
+use hyper::header::HeaderName;
 use hyper::Body;
 use hyper::Request;
 use hyper::Response;
@@ -35,6 +37,8 @@ use serde::Deserialize;
 use serde::Serialize;
 use sha2::Digest;
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::io;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
@@ -42,8 +46,8 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use tracing::error;
 use tracing::field;
+use tracing::info;
 use tracing::instrument;
-use tracing::Instrument;
 use tracing::Span;
 use url::Url;
 
@@ -56,28 +60,44 @@ use crate::buckets::UploadTaskBody;
 use crate::db::CreatePackageResult;
 use crate::db::CreatePublishingTaskResult;
 use crate::db::Database;
+use crate::db::GithubRepository;
+use crate::db::MaintenanceStatus;
 use crate::db::NewGithubRepository;
 use crate::db::NewPublishingTask;
 use crate::db::Package;
+use crate::db::PublishedSpecifierKind;
+use crate::db::PublishedSpecifiers;
+use crate::db::PublishingTaskError;
+use crate::db::PublishingTaskStatus;
 use crate::db::RuntimeCompat;
 use crate::db::User;
 use crate::docs::DocNodesByUrl;
+use crate::docs::DocsLandingPage;
 use crate::docs::DocsRequest;
 use crate::docs::GeneratedDocsOutput;
-use crate::gcp;
 use crate::gcp::GcsUploadOptions;
 use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
+use crate::iam::IamInfo;
+use crate::iam::Principal;
 use crate::iam::ReqIamExt;
 use crate::ids::PackageName;
 use crate::ids::PackagePath;
 use crate::ids::ScopeName;
+use crate::ids::ScopedPackageName;
+use crate::ids::Version;
 use crate::metadata::PackageMetadata;
 use crate::metadata::VersionMetadata;
 use crate::npm::generate_npm_version_manifest;
-use crate::orama::OramaClient;
 use crate::provenance;
-use crate::publish::publish_task;
+use crate::provenance::ProvenancePolicies;
+use crate::publish::enqueue_publishing_task;
+use crate::publish::publish_queue;
+use crate::queue::TaskQueue;
+use crate::signing::upload_metadata_signature;
+use crate::signing::MetadataSigner;
 use crate::tarball::gcs_tarball_path;
+use crate::trust::TrustPolicy;
+use crate::typescript::TypeScriptVersions;
 use crate::util;
 use crate::util::decode_json;
 use crate::util::pagination;
@@ -86,56 +106,103 @@ use crate::util::ApiResult;
 use crate::util::CacheDuration;
 use crate::util::RequestIdExt;
 use crate::util::VersionOrLatest;
+use crate::NpmIncludeYankedVersions;
 use crate::NpmUrl;
 use crate::RegistryUrl;
 
+use super::artifact;
+use super::ApiBulkYankPackageVersionsRequest;
+use super::ApiBulkYankPackageVersionsResponse;
 use super::ApiCreatePackageRequest;
 use super::ApiDependency;
+use super::ApiDependencyFreshness;
 use super::ApiDependencyGraphItem;
 use super::ApiDependent;
+use super::ApiDeprecatedImport;
+use super::ApiDownloadAnomaly;
 use super::ApiDownloadDataPoint;
+use super::ApiEntrypoint;
 use super::ApiError;
+use super::ApiLicenseReport;
+use super::ApiLicenseReportPackage;
+use super::ApiLicenseReportUnresolved;
 use super::ApiList;
 use super::ApiMetrics;
 use super::ApiPackage;
 use super::ApiPackageDownloads;
 use super::ApiPackageDownloadsRecentVersion;
+use super::ApiPackageLinkCheck;
 use super::ApiPackageScore;
+use super::ApiPackageSuccessor;
 use super::ApiPackageVersion;
 use super::ApiPackageVersionDocs;
+use super::ApiPackageVersionIntegrity;
+use super::ApiPackageVersionListItem;
+use super::ApiPackageVersionPublishInfo;
 use super::ApiPackageVersionSource;
-use super::ApiPackageVersionWithUser;
+use super::ApiPackageVersionSymbolDocs;
 use super::ApiProvenanceStatementRequest;
+use super::ApiPublishProvenance;
+use super::ApiPublishedSpecifier;
 use super::ApiPublishingTask;
 use super::ApiSource;
 use super::ApiSourceDirEntry;
 use super::ApiSourceDirEntryKind;
 use super::ApiStats;
+use super::ApiTypeScriptCompatibility;
 use super::ApiUpdatePackageGithubRepositoryRequest;
 use super::ApiUpdatePackageRequest;
 use super::ApiUpdatePackageVersionRequest;
 
 const MAX_PUBLISH_TARBALL_SIZE: u64 = 20 * 1024 * 1024; // 20mb
 
-pub struct PublishQueue(pub Option<gcp::Queue>);
+/// How far in the future a publish can be scheduled with `publishAt`.
+const MAX_PUBLISH_SCHEDULE_AHEAD: chrono::Duration = chrono::Duration::days(30);
+
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// The request header that pins the SHA-256 digest of the uploaded tarball, as
+/// a hex string.
+static X_TARBALL_SHA256: HeaderName =
+  HeaderName::from_static("x-tarball-sha256");
+
+pub struct PublishQueue(pub Option<Arc<dyn TaskQueue>>);
 
 pub fn package_router() -> Router<Body, ApiError> {
   Router::builder()
-    .get("/", util::json(list_handler))
+    .get("/", util::paginated_json(list_handler))
     .post("/", util::json(create_handler))
-    .get("/:package", util::json(get_handler))
+    .get("/:package", get_handler)
     .patch("/:package", util::auth(util::json(update_handler)))
     .delete("/:package", util::auth(delete_handler))
     .get(
       "/:package/versions",
-      util::cache(CacheDuration::ONE_MINUTE, util::json(list_versions_handler)),
+      util::cache(
+        CacheDuration::ONE_MINUTE,
+        util::paginated_json(list_versions_handler),
+      ),
+    )
+    .get(
+      "/:package/dependents",
+      util::paginated_json(list_dependents_handler),
     )
-    .get("/:package/dependents", util::json(list_dependents_handler))
     .get("/:package/downloads", util::json(get_downloads_handler))
+    .get(
+      "/:package/download_anomalies",
+      util::json(list_download_anomalies_handler),
+    )
+    .get(
+      "/:package/broken_links",
+      util::auth(util::json(get_broken_links_handler)),
+    )
     .get(
       "/:package/versions/:version",
       util::cache(CacheDuration::ONE_MINUTE, util::json(get_version_handler)),
     )
+    .post(
+      "/:package/versions/bulk_yank",
+      util::auth(util::json(version_bulk_yank_handler)),
+    )
     .post(
       "/:package/versions/:version",
       util::auth(util::json(version_publish_handler)),
@@ -166,6 +233,10 @@ pub fn package_router() -> Router<Body, ApiError> {
         util::json(get_docs_search_html_handler),
       ),
     )
+    .get(
+      "/:package/versions/:version/docs/symbol/:symbol",
+      util::cache(CacheDuration::ONE_MINUTE, get_docs_symbol_handler),
+    )
     .get(
       "/:package/versions/:version/source",
       util::cache(CacheDuration::ONE_MINUTE, util::json(get_source_handler)),
@@ -174,6 +245,70 @@ pub fn package_router() -> Router<Body, ApiError> {
       "/:package/versions/:version/dependencies",
       util::json(list_dependencies_handler),
     )
+    .get(
+      "/:package/versions/:version/specifiers",
+      util::cache(CacheDuration::ONE_DAY, util::json(list_specifiers_handler)),
+    )
+    .get(
+      "/:package/versions/:version/artifacts",
+      util::json(artifact::list_handler),
+    )
+    .post(
+      "/:package/versions/:version/artifacts",
+      util::auth(util::json(artifact::create_handler)),
+    )
+    .get(
+      "/:package/versions/:version/artifacts/:filename",
+      artifact::download_handler,
+    )
+    .put(
+      "/:package/versions/:version/artifacts/:filename",
+      util::json(artifact::upload_handler),
+    )
+    .delete(
+      "/:package/versions/:version/artifacts/:filename",
+      util::auth(artifact::delete_handler),
+    )
+    .get(
+      "/:package/versions/:version/deprecated_imports",
+      util::cache(
+        CacheDuration::ONE_MINUTE,
+        util::json(list_deprecated_imports_handler),
+      ),
+    )
+    .get(
+      "/:package/versions/:version/dependencies/freshness",
+      util::cache(
+        CacheDuration::ONE_MINUTE,
+        util::json(list_dependencies_freshness_handler),
+      ),
+    )
+    .get(
+      "/:package/versions/:version/licenses",
+      util::cache(
+        CacheDuration::ONE_MINUTE,
+        util::json(get_license_report_handler),
+      ),
+    )
+    .get(
+      "/:package/versions/:version/entrypoints",
+      util::json(list_entrypoints_handler),
+    )
+    .get(
+      "/:package/versions/:version/typescript",
+      util::json(list_typescript_compatibility_handler),
+    )
+    .get(
+      "/:package/versions/:version/integrity",
+      util::cache(CacheDuration::ONE_DAY, util::json(get_integrity_handler)),
+    )
+    .get(
+      "/:package/versions/:version/publish_info",
+      util::cache(
+        CacheDuration::ONE_MINUTE,
+        util::json(get_publish_info_handler),
+      ),
+    )
     .get(
       "/:package/versions/:version/dependencies/graph",
       util::cache(
@@ -213,8 +348,80 @@ pub async fn global_list_handler(
     })
     .transpose()?;
 
+  // Filters packages by the minimum version of a runtime they require, e.g.
+  // `engine=node@18.19.0` only returns packages whose latest version can run
+  // on Node.js 18.19.0.
+  let engine = req
+    .query("engine")
+    .map(|engine| {
+      parse_engine_filter(engine).ok_or_else(|| ApiError::MalformedRequest {
+        msg: "'engine' query must be of the form 'deno@1.2.3', 'node@1.2.3', or 'bun@1.2.3'".into(),
+      })
+    })
+    .transpose()?;
+
+  let maintenance_status = req
+    .query("maintenanceStatus")
+    .map(|status| match status.as_str() {
+      "actively_maintained" => Ok(MaintenanceStatus::ActivelyMaintained),
+      "maintenance_only" => Ok(MaintenanceStatus::MaintenanceOnly),
+      "seeking_maintainers" => Ok(MaintenanceStatus::SeekingMaintainers),
+      "unmaintained" => Ok(MaintenanceStatus::Unmaintained),
+      _ => Err(ApiError::MalformedRequest {
+        msg: "'maintenanceStatus' query must be one of 'actively_maintained', 'maintenance_only', 'seeking_maintainers', or 'unmaintained'".into(),
+      }),
+    })
+    .transpose()?;
+
+  // `sort=stars` lists the most starred packages first.
+  let sort_by_stars = match req.query("sort").map(String::as_str) {
+    None => false,
+    Some("stars") => true,
+    Some(_) => {
+      return Err(ApiError::MalformedRequest {
+        msg: "'sort' query must be 'stars'".into(),
+      })
+    }
+  };
+
+  let (total, packages) = db
+    .list_packages(
+      start,
+      limit,
+      maybe_search,
+      github_repo_id,
+      engine,
+      None,
+      maintenance_status,
+      sort_by_stars,
+    )
+    .await?;
+  Ok(ApiList {
+    items: packages.into_iter().map(ApiPackage::from).collect(),
+    total,
+  })
+}
+
+#[instrument(
+  name = "GET /api/topics/:topic/packages",
+  skip(req),
+  err,
+  fields(topic)
+)]
+pub async fn topic_list_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiList<ApiPackage>> {
+  let topic = req.param("topic").unwrap();
+  Span::current().record("topic", field::display(topic));
+  if !crate::topics::is_topic(topic) {
+    return Err(ApiError::TopicNotFound);
+  }
+
+  let db = req.data::<Database>().unwrap();
+  let (start, limit) = pagination(&req);
+
   let (total, packages) = db
-    .list_packages(start, limit, maybe_search, github_repo_id)
+    .list_packages(start, limit, None, None, None, Some(topic), None, false)
     .await?;
   Ok(ApiList {
     items: packages.into_iter().map(ApiPackage::from).collect(),
@@ -222,6 +429,22 @@ pub async fn global_list_handler(
   })
 }
 
+fn parse_engine_filter(engine: &str) -> Option<(&str, Vec<i32>)> {
+  let (name, version) = engine.split_once('@')?;
+  if !matches!(name, "deno" | "node" | "bun") {
+    return None;
+  }
+  let mut parts = version
+    .split('.')
+    .map(|part| part.parse::<i32>().ok().filter(|part| *part >= 0))
+    .collect::<Option<Vec<_>>>()?;
+  if parts.len() > 3 {
+    return None;
+  }
+  parts.resize(3, 0);
+  Some((name, parts))
+}
+
 #[instrument(name = "GET /api/stats", skip(req), err)]
 pub async fn global_stats_handler(req: Request<Body>) -> ApiResult<ApiStats> {
   let db = req.data::<Database>().unwrap();
@@ -297,6 +520,24 @@ pub async fn create_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
     return Err(ApiError::PackageNameNotAllowed);
   }
 
+  // Scopes that a new account created get few new packages per day, no matter
+  // which member creates them.
+  let scope_creator = db
+    .get_scope(&scope)
+    .await?
+    .ok_or(ApiError::ScopeNotFound)?
+    .creator;
+  let trust_policy = req.data::<TrustPolicy>().unwrap();
+  let trust = db.get_user_trust_signals(scope_creator).await?;
+  if let Some(limit) = trust_policy
+    .limits(trust_policy.level(&trust))
+    .new_packages_per_day
+  {
+    if trust.new_packages_per_day >= limit as i64 {
+      return Err(ApiError::TrustLevelPackageLimitExceeded { limit });
+    }
+  }
+
   let res = db.create_package(&scope, &package_name).await?;
   let package = match res {
     CreatePackageResult::Ok(package) => package,
@@ -311,11 +552,6 @@ pub async fn create_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
     }
   };
 
-  let orama_client = req.data::<Option<OramaClient>>().unwrap();
-  if let Some(orama_client) = orama_client {
-    orama_client.upsert_package(&package, &Default::default());
-  }
-
   Ok(ApiPackage::from((package, None, Default::default())))
 }
 
@@ -325,7 +561,7 @@ pub async fn create_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
   err,
   fields(scope, package)
 )]
-pub async fn get_handler(req: Request<Body>) -> ApiResult<ApiPackage> {
+pub async fn get_handler(req: Request<Body>) -> ApiResult<Response<Body>> {
   let scope = req.param_scope()?;
   let package = req.param_package()?;
 
@@ -333,12 +569,20 @@ pub async fn get_handler(req: Request<Body>) -> ApiResult<ApiPackage> {
   Span::current().record("package", field::display(&package));
 
   let db = req.data::<Database>().unwrap();
-  let package = db
-    .get_package(&scope, &package)
-    .await?
-    .ok_or(ApiError::PackageNotFound)?;
+  let Some(package) = db.get_package(&scope, &package).await? else {
+    // The package may have been renamed or moved to another scope.
+    let redirect = db
+      .get_package_redirect(&scope, &package)
+      .await?
+      .ok_or(ApiError::PackageNotFound)?;
+    return Ok(util::moved_permanently(&format!(
+      "/api/scopes/{}/packages/{}",
+      redirect.target_scope, redirect.target_name
+    )));
+  };
 
-  Ok(ApiPackage::from(package))
+  let package = ApiPackage::from(package);
+  Ok(util::respond_json(&package, StatusCode::OK))
 }
 
 #[instrument(
@@ -354,7 +598,6 @@ pub async fn update_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
   let body: ApiUpdatePackageRequest = decode_json(&mut req).await?;
 
   let db: &Database = req.data::<Database>().unwrap();
-  let orama_client = req.data::<Option<OramaClient>>().unwrap();
   let github_oauth2_client = req.data::<GithubOauth2Client>().unwrap();
 
   let (package, repo, meta) = db
@@ -363,11 +606,15 @@ pub async fn update_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
     .ok_or(ApiError::PackageNotFound)?;
 
   let iam = req.iam();
-  // Updating if a package is featured is allowed for admins, update package
-  // description is allowed for all members, updating the repo
+  // Updating if a package is featured or indexed is allowed for staff, update
+  // package description is allowed for all members, updating the repo
   // requires admin permissions because it extends who can publish new
   // versions (anyone with write access to the repo).
-  if matches!(body, ApiUpdatePackageRequest::IsFeatured(_)) {
+  if matches!(
+    body,
+    ApiUpdatePackageRequest::IsFeatured(_)
+      | ApiUpdatePackageRequest::Noindex(_)
+  ) {
     iam.check_admin_access()?;
   } else if matches!(body, ApiUpdatePackageRequest::Description(_)) {
     iam.check_scope_write_access(&scope).await?;
@@ -375,8 +622,15 @@ pub async fn update_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
     iam.check_scope_admin_access(&scope).await?;
   }
 
+  // Pointing an archived package to its successor is a common final step
+  // after moving a package, so it is allowed.
   if package.is_archived
-    && !matches!(body, ApiUpdatePackageRequest::IsArchived(_))
+    && !matches!(
+      body,
+      ApiUpdatePackageRequest::IsArchived(_)
+        | ApiUpdatePackageRequest::Noindex(_)
+        | ApiUpdatePackageRequest::Successor(_)
+    )
   {
     return Err(ApiError::PackageArchived);
   }
@@ -384,12 +638,14 @@ pub async fn update_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
   match body {
     ApiUpdatePackageRequest::Description(description) => {
       let npm_url = &req.data::<NpmUrl>().unwrap().0;
+      let npm_include_yanked =
+        req.data::<NpmIncludeYankedVersions>().unwrap().0;
       let buckets = req.data::<Buckets>().unwrap().clone();
       let package = update_description(
         db,
         npm_url,
+        npm_include_yanked,
         &buckets,
-        orama_client,
         &scope,
         &package_name,
         description,
@@ -420,9 +676,16 @@ pub async fn update_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
       let package = db
         .update_package_runtime_compat(&scope, &package_name, &runtime_compat)
         .await?;
-      if let Some(orama_client) = orama_client {
-        orama_client.upsert_package(&package, &meta);
-      }
+      Ok(ApiPackage::from((package, repo, meta)))
+    }
+    ApiUpdatePackageRequest::ValidationLevel(validation_level) => {
+      let package = db
+        .update_package_validation_level(
+          &scope,
+          &package_name,
+          validation_level.into(),
+        )
+        .await?;
       Ok(ApiPackage::from((package, repo, meta)))
     }
     ApiUpdatePackageRequest::IsFeatured(is_featured) => {
@@ -435,30 +698,74 @@ pub async fn update_handler(mut req: Request<Body>) -> ApiResult<ApiPackage> {
       let package = db
         .update_package_is_archived(&scope, &package_name, is_archived)
         .await?;
-
-      if let Some(orama_client) = orama_client {
-        if package.is_archived {
-          orama_client.delete_package(&scope, &package.name);
-        } else {
-          orama_client.upsert_package(&package, &meta);
-        }
+      Ok(ApiPackage::from((package, repo, meta)))
+    }
+    ApiUpdatePackageRequest::Noindex(noindex) => {
+      let package = db
+        .update_package_noindex(&scope, &package_name, noindex)
+        .await?;
+      Ok(ApiPackage::from((package, repo, meta)))
+    }
+    ApiUpdatePackageRequest::DocsLandingPage(docs_landing_page) => {
+      let docs_landing_page = docs_landing_page
+        .map(|page| page.trim().to_string())
+        .filter(|page| !page.is_empty());
+      if let Some(page) = &docs_landing_page {
+        DocsLandingPage::parse(page).map_err(|msg| {
+          ApiError::MalformedRequest {
+            msg: format!("invalid docs landing page: {msg}").into(),
+          }
+        })?;
       }
-
+      let package = db
+        .update_package_docs_landing_page(
+          &scope,
+          &package_name,
+          docs_landing_page.as_deref(),
+        )
+        .await?;
+      Ok(ApiPackage::from((package, repo, meta)))
+    }
+    ApiUpdatePackageRequest::MaintenanceStatus(maintenance_status) => {
+      let package = db
+        .update_package_maintenance_status(
+          &scope,
+          &package_name,
+          maintenance_status.map(Into::into),
+        )
+        .await?;
+      Ok(ApiPackage::from((package, repo, meta)))
+    }
+    ApiUpdatePackageRequest::Successor(successor) => {
+      let npm_url = &req.data::<NpmUrl>().unwrap().0;
+      let npm_include_yanked =
+        req.data::<NpmIncludeYankedVersions>().unwrap().0;
+      let buckets = req.data::<Buckets>().unwrap().clone();
+      let package = update_successor(
+        db,
+        npm_url,
+        npm_include_yanked,
+        &buckets,
+        &scope,
+        &package_name,
+        successor,
+      )
+      .await?;
       Ok(ApiPackage::from((package, repo, meta)))
     }
   }
 }
 
 #[instrument(
-  skip(db, npm_url, buckets, orama_client, scope, package_name),
+  skip(db, npm_url, buckets, scope, package_name),
   err,
   fields(description)
 )]
 async fn update_description(
   db: &Database,
   npm_url: &Url,
+  npm_include_yanked: bool,
   buckets: &Buckets,
-  orama_client: &Option<OramaClient>,
   scope: &ScopeName,
   package_name: &PackageName,
   description: String,
@@ -477,18 +784,43 @@ async fn update_description(
     });
   }
 
-  let (package, _, meta) = db
+  let (package, _, _) = db
     .update_package_description(scope, package_name, &description)
     .await?;
 
-  if let Some(orama_client) = orama_client {
-    orama_client.upsert_package(&package, &meta);
-  }
+  upload_npm_version_manifest(
+    db,
+    npm_url,
+    npm_include_yanked,
+    buckets,
+    scope,
+    &package.name,
+  )
+  .await?;
 
+  Ok(package)
+}
+
+/// Regenerates the npm version manifest of the package, after a change to
+/// the package that is reflected in it.
+async fn upload_npm_version_manifest(
+  db: &Database,
+  npm_url: &Url,
+  include_yanked: bool,
+  buckets: &Buckets,
+  scope: &ScopeName,
+  package_name: &PackageName,
+) -> Result<(), ApiError> {
   let npm_version_manifest_path =
-    crate::gcs_paths::npm_version_manifest_path(scope, &package.name);
-  let npm_version_manifest =
-    generate_npm_version_manifest(db, npm_url, scope, &package.name).await?;
+    crate::gcs_paths::npm_version_manifest_path(scope, package_name);
+  let npm_version_manifest = generate_npm_version_manifest(
+    db,
+    npm_url,
+    scope,
+    package_name,
+    include_yanked,
+  )
+  .await?;
   let content = serde_json::to_vec_pretty(&npm_version_manifest)?;
   buckets
     .npm_bucket
@@ -502,6 +834,77 @@ async fn update_description(
       },
     )
     .await?;
+  Ok(())
+}
+
+/// The maximum number of successors that are followed when checking that
+/// the successors of packages do not form a cycle.
+const MAX_SUCCESSOR_CHAIN: usize = 16;
+
+#[instrument(skip(db, npm_url, buckets), err)]
+async fn update_successor(
+  db: &Database,
+  npm_url: &Url,
+  npm_include_yanked: bool,
+  buckets: &Buckets,
+  scope: &ScopeName,
+  package_name: &PackageName,
+  successor: Option<ApiPackageSuccessor>,
+) -> Result<Package, ApiError> {
+  if let Some(successor) = &successor {
+    if &successor.scope == scope && &successor.name == package_name {
+      return Err(ApiError::InvalidPackageSuccessor {
+        msg: "a package can not be its own successor".into(),
+      });
+    }
+
+    let (mut next, _, _) = db
+      .get_package(&successor.scope, &successor.name)
+      .await?
+      .ok_or_else(|| ApiError::InvalidPackageSuccessor {
+        msg: format!("@{}/{} does not exist", successor.scope, successor.name)
+          .into(),
+      })?;
+    for _ in 0..MAX_SUCCESSOR_CHAIN {
+      let (Some(next_scope), Some(next_name)) =
+        (&next.successor_scope, &next.successor_name)
+      else {
+        break;
+      };
+      if next_scope == scope && next_name == package_name {
+        return Err(ApiError::InvalidPackageSuccessor {
+          msg: format!(
+            "@{}/{} has this package as its successor",
+            next.scope, next.name
+          )
+          .into(),
+        });
+      }
+      let Some((package, _, _)) = db.get_package(next_scope, next_name).await?
+      else {
+        break;
+      };
+      next = package;
+    }
+  }
+
+  let package = db
+    .update_package_successor(
+      scope,
+      package_name,
+      successor.as_ref().map(|s| (&s.scope, &s.name)),
+    )
+    .await?;
+
+  upload_npm_version_manifest(
+    db,
+    npm_url,
+    npm_include_yanked,
+    buckets,
+    scope,
+    package_name,
+  )
+  .await?;
 
   Ok(package)
 }
@@ -546,10 +949,29 @@ async fn update_github_repository(
     return Err(ApiError::GithubRepositoryNotAuthorized);
   }
 
+  let subdirectory = req
+    .subdirectory
+    .as_deref()
+    .map(normalize_github_subdirectory)
+    .transpose()?
+    .flatten();
+  if let Some(subdirectory) = &subdirectory {
+    verify_github_subdirectory(
+      &github_u2s_client,
+      &repo.owner.login,
+      &repo.name,
+      subdirectory,
+      &scope,
+      &package,
+    )
+    .await?;
+  }
+
   let new_repo = NewGithubRepository {
     id: repo.id,
     owner: &repo.owner.login,
     name: &repo.name,
+    subdirectory: subdirectory.as_deref(),
   };
 
   let (package, repo, score) = db
@@ -559,6 +981,84 @@ async fn update_github_repository(
   Ok(ApiPackage::from((package, Some(repo), score)))
 }
 
+/// Normalizes the subdirectory of a linked GitHub repository to a relative
+/// path without leading or trailing slashes. An empty path refers to the root
+/// of the repository, and is returned as `None`.
+fn normalize_github_subdirectory(
+  subdirectory: &str,
+) -> Result<Option<String>, ApiError> {
+  let subdirectory = subdirectory.trim().trim_matches('/');
+  if subdirectory.is_empty() {
+    return Ok(None);
+  }
+  let valid = subdirectory.len() <= 255
+    && !subdirectory.contains('\\')
+    && subdirectory
+      .split('/')
+      .all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+  if !valid {
+    return Err(ApiError::GithubRepositorySubdirectoryInvalid);
+  }
+  Ok(Some(subdirectory.to_owned()))
+}
+
+/// The config files that are looked up in the subdirectory of a linked
+/// repository, in the order that they are checked.
+const CONFIG_FILE_NAMES: [&str; 4] =
+  ["jsr.json", "jsr.jsonc", "deno.json", "deno.jsonc"];
+
+/// Verifies that the subdirectory of a repository contains the package, by
+/// checking that a config file in it has the name of the package. This is the
+/// same config file that `jsr publish` would pick up when run from that
+/// directory.
+async fn verify_github_subdirectory(
+  client: &crate::github::GitHubUserClient,
+  owner: &str,
+  name: &str,
+  subdirectory: &str,
+  scope: &ScopeName,
+  package: &PackageName,
+) -> Result<(), ApiError> {
+  let expected = format!("@{scope}/{package}");
+  for file_name in CONFIG_FILE_NAMES {
+    let path = format!("{subdirectory}/{file_name}");
+    let Some(contents) = client.get_file_contents(owner, name, &path).await?
+    else {
+      continue;
+    };
+    let config_name = std::str::from_utf8(&contents)
+      .ok()
+      .and_then(|contents| {
+        jsonc_parser::parse_to_serde_value(
+          contents,
+          &jsonc_parser::ParseOptions::default(),
+        )
+        .ok()
+        .flatten()
+      })
+      .and_then(|config| config.get("name")?.as_str().map(ToOwned::to_owned));
+    match config_name {
+      Some(config_name) if config_name == expected => return Ok(()),
+      Some(config_name) => {
+        return Err(ApiError::GithubRepositorySubdirectoryMismatch {
+          msg: format!(
+            "'{path}' is the config file of '{config_name}', not '{expected}'"
+          )
+          .into(),
+        })
+      }
+      // A deno.json without a name may sit next to a jsr.json that has one.
+      None => continue,
+    }
+  }
+  Err(ApiError::GithubRepositorySubdirectoryMismatch {
+    msg: format!(
+      "no config file with the name '{expected}' was found in '{subdirectory}'"
+    )
+    .into(),
+  })
+}
+
 #[instrument(
   name = "GET /api/scopes/:scope/packages/:package/versions",
   skip(req),
@@ -567,27 +1067,60 @@ async fn update_github_repository(
 )]
 pub async fn list_versions_handler(
   req: Request<Body>,
-) -> ApiResult<Vec<ApiPackageVersionWithUser>> {
+) -> ApiResult<ApiList<ApiPackageVersionListItem>> {
   let scope = req.param_scope()?;
   let package = req.param_package()?;
 
   Span::current().record("scope", field::display(&scope));
   Span::current().record("package", field::display(&package));
 
+  let (start, limit) = pagination(&req);
+  let include_prereleases =
+    req.query("prerelease").map(String::as_str) != Some("false");
+  let compact = req.query("compact").map(String::as_str) == Some("true");
+  let since = req
+    .query("since")
+    .map(|since| {
+      chrono::DateTime::parse_from_rfc3339(since)
+        .map(|since| since.with_timezone(&Utc))
+        .map_err(|err| {
+          let msg = format!(
+            "failed to parse query parameter 'since' with value '{since}': {err}"
+          )
+          .into();
+          ApiError::MalformedRequest { msg }
+        })
+    })
+    .transpose()?;
+
   let db = req.data::<Database>().unwrap();
 
   db.get_package(&scope, &package)
     .await?
     .ok_or(ApiError::PackageNotFound)?;
 
-  let versions = db
-    .list_package_versions(&scope, &package)
-    .await?
+  let (total, versions) = db
+    .list_package_versions_page(
+      &scope,
+      &package,
+      include_prereleases,
+      since,
+      start,
+      limit,
+    )
+    .await?;
+  let items = versions
     .into_iter()
-    .map(ApiPackageVersionWithUser::from)
-    .collect::<Vec<_>>();
+    .map(|(version, user)| {
+      if compact {
+        ApiPackageVersionListItem::Compact(version.into())
+      } else {
+        ApiPackageVersionListItem::Full((version, user).into())
+      }
+    })
+    .collect();
 
-  Ok(versions)
+  Ok(ApiList { items, total })
 }
 
 #[instrument(
@@ -615,11 +1148,6 @@ pub async fn delete_handler(req: Request<Body>) -> ApiResult<Response<Body>> {
     return Err(ApiError::PackageNotEmpty);
   }
 
-  let orama_client = req.data::<Option<OramaClient>>().unwrap();
-  if let Some(orama_client) = orama_client {
-    orama_client.delete_package(&scope, &package);
-  }
-
   let res = Response::builder()
     .status(StatusCode::NO_CONTENT)
     .body(Body::empty())
@@ -692,6 +1220,76 @@ pub async fn version_publish_handler(
       .into();
       ApiError::MalformedRequest { msg }
     })?;
+  let publish_at = req
+    .query("publishAt")
+    .map(|publish_at| {
+      chrono::DateTime::parse_from_rfc3339(publish_at)
+        .map(|publish_at| publish_at.with_timezone(&Utc))
+        .map_err(|err| {
+          let msg = format!(
+            "failed to parse query parameter 'publishAt' with value '{publish_at}': {err}"
+          )
+          .into();
+          ApiError::MalformedRequest { msg }
+        })
+    })
+    .transpose()?;
+  if let Some(publish_at) = publish_at {
+    let now = Utc::now();
+    if publish_at <= now || publish_at > now + MAX_PUBLISH_SCHEDULE_AHEAD {
+      let msg = format!(
+        "query parameter 'publishAt' must be in the future, and at most {} days from now",
+        MAX_PUBLISH_SCHEDULE_AHEAD.num_days()
+      )
+      .into();
+      return Err(ApiError::MalformedRequest { msg });
+    }
+  }
+
+  // Retries of a publish (for example by CI on a flaky network) send the same
+  // key, and get the task of the first attempt back.
+  let idempotency_key = req
+    .headers()
+    .get("idempotency-key")
+    .map(|value| {
+      value
+        .to_str()
+        .ok()
+        .filter(|key| {
+          !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH
+        })
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| {
+          let msg = format!(
+            "header 'Idempotency-Key' must be between 1 and {MAX_IDEMPOTENCY_KEY_LENGTH} visible ASCII characters"
+          )
+          .into();
+          ApiError::MalformedRequest { msg }
+        })
+    })
+    .transpose()?;
+
+  // The caller can pin the digest of the tarball it meant to upload, so that a
+  // tarball that was changed or corrupted on the way is never processed.
+  let expected_tarball_sha256 = req
+    .headers()
+    .get(&X_TARBALL_SHA256)
+    .map(|value| {
+      value
+        .to_str()
+        .ok()
+        .filter(|digest| {
+          digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())
+        })
+        .map(|digest| digest.to_ascii_lowercase())
+        .ok_or_else(|| {
+          let msg =
+            "header 'X-Tarball-Sha256' must be a hex encoded SHA-256 digest"
+              .into();
+          ApiError::MalformedRequest { msg }
+        })
+    })
+    .transpose()?;
 
   // If there is a content-length header, check it isn't too big.
   // We don't rely on this, we will also check MAX_PAYLOAD_SIZE later.
@@ -712,10 +1310,7 @@ pub async fn version_publish_handler(
 
   let db = req.data::<Database>().unwrap().clone();
   let buckets = req.data::<Buckets>().unwrap().clone();
-  let registry_url = req.data::<RegistryUrl>().unwrap().0.clone();
-  let npm_url = req.data::<NpmUrl>().unwrap().0.clone();
-  let publish_queue = req.data::<PublishQueue>().unwrap().0.clone();
-  let orama_client = req.data::<Option<OramaClient>>().unwrap().clone();
+  let queue = publish_queue(&req);
 
   let iam = req.iam();
   let (access_restriction, user_id) = iam
@@ -731,17 +1326,53 @@ pub async fn version_publish_handler(
     return Err(ApiError::PackageArchived);
   }
 
-  let res = db
-    .create_publishing_task(NewPublishingTask {
+  if let Some(user_id) = user_id {
+    let trust_policy = req.data::<TrustPolicy>().unwrap();
+    let trust = db.get_user_trust_signals(user_id).await?;
+    if let Some(limit) = trust_policy
+      .limits(trust_policy.level(&trust))
+      .publish_attempts_per_day
+    {
+      if trust.publish_attempts_per_day >= limit as i64 {
+        return Err(ApiError::TrustLevelPublishAttemptsLimitExceeded { limit });
+      }
+    }
+  }
+
+  let bypass_publish_freezes =
+    iam.can_bypass_publish_freezes(&package.scope).await?;
+  let (github_repository_id, github_workflow_run) =
+    match &req.context::<IamInfo>().unwrap().principal {
+      Principal::GitHubActions {
+        repo_id,
+        workflow_run,
+        ..
+      } => (Some(*repo_id), workflow_run.clone()),
+      _ => (None, None),
+    };
+
+  let res = db
+    .create_publishing_task(NewPublishingTask {
       user_id,
+      github_repository_id,
+      github_workflow_run,
       package_scope: &package.scope,
       package_name: &package.name,
       package_version: &package_version,
       config_file: &config_file,
+      bypass_publish_freezes,
+      publish_at,
+      idempotency_key: idempotency_key.as_deref(),
     })
     .await?;
   let publishing_task = match res {
     CreatePublishingTaskResult::Created(publishing_task) => publishing_task,
+    // The tarball of the first attempt is already uploaded (or still being
+    // uploaded), so the body of the retry is ignored.
+    CreatePublishingTaskResult::Retried(task) => return Ok(task.into()),
+    // Parallel jobs publishing the same version attach to the task that was
+    // created first, rather than failing depending on which one was faster.
+    CreatePublishingTaskResult::Raced(task) => return Ok(task.into()),
     CreatePublishingTaskResult::Exists(task) => {
       return Err(ApiError::DuplicateVersionPublish {
         task: Box::new(task.into()),
@@ -750,6 +1381,12 @@ pub async fn version_publish_handler(
     CreatePublishingTaskResult::WeeklyPublishAttemptsLimitExceeded(limit) => {
       return Err(ApiError::WeeklyPublishAttemptsLimitExceeded { limit })
     }
+    CreatePublishingTaskResult::PublishFreezeActive(freeze) => {
+      return Err(ApiError::ScopePublishFreezeActive {
+        ends_at: freeze.occurrence_ends_at(Utc::now()),
+        reason: freeze.reason,
+      })
+    }
   };
 
   let gcs_path = gcs_tarball_path(publishing_task.id);
@@ -787,8 +1424,9 @@ pub async fn version_publish_handler(
     )
     .await;
 
-  let hash = hash.lock().unwrap().take().unwrap().finalize();
-  let hash = format!("sha256-{:02x}", hash);
+  let tarball_sha256 =
+    format!("{:02x}", hash.lock().unwrap().take().unwrap().finalize());
+  let hash = format!("sha256-{tarball_sha256}");
   if let Some(tarball_hash) = access_restriction.tarball_hash {
     if tarball_hash != hash {
       error!(
@@ -811,22 +1449,31 @@ pub async fn version_publish_handler(
   // Otherwise, we can just propagate the error.
   upload_result?;
 
-  if let Some(queue) = publish_queue {
-    let body = serde_json::to_vec(&publishing_task.id).unwrap();
-    queue.task_buffer(None, Some(body.into())).await?;
-  } else {
-    let span = Span::current();
-    let fut = publish_task(
-      publishing_task.id,
-      buckets.clone(),
-      registry_url,
-      npm_url,
-      db,
-      orama_client,
-    )
-    .instrument(span);
-    tokio::spawn(fut);
+  if let Some(expected) = expected_tarball_sha256 {
+    if expected != tarball_sha256 {
+      db.update_publishing_task_status(
+        publishing_task.id,
+        PublishingTaskStatus::Pending,
+        PublishingTaskStatus::Failure,
+        Some(PublishingTaskError {
+          code: "tarballDigestMismatch".to_owned(),
+          message: format!(
+            "The SHA-256 digest of the uploaded tarball ({tarball_sha256}) does not match the expected digest ({expected})."
+          ),
+          ..Default::default()
+        }),
+      )
+      .await?;
+      return Err(ApiError::TarballDigestMismatch {
+        expected,
+        actual: tarball_sha256,
+      });
+    }
   }
+  db.set_publishing_task_tarball_sha256(publishing_task.id, &tarball_sha256)
+    .await?;
+
+  enqueue_publishing_task(&*queue, publishing_task.id).await?;
 
   Ok(publishing_task.into())
 }
@@ -835,7 +1482,7 @@ pub async fn version_publish_handler(
   name = "POST /api/scopes/:scope/packages/:package/versions/:version/provenance",
   skip(req),
   err,
-  fields(scope, package, version)
+  fields(scope, package, version, provider)
 )]
 pub async fn version_provenance_statements_handler(
   mut req: Request<Body>,
@@ -851,24 +1498,23 @@ pub async fn version_provenance_statements_handler(
   let body: ApiProvenanceStatementRequest = decode_json(&mut req).await?;
 
   let db = req.data::<Database>().unwrap();
-  let orama_client = req.data::<Option<OramaClient>>().unwrap().clone();
+  let provenance_policies = req.data::<ProvenancePolicies>().unwrap();
 
   let iam = req.iam();
   iam.check_publish_access(&scope, &package, &version).await?;
 
   let name = format!("pkg:jsr/@{}/{}@{}", scope, package, version);
-  let rekor_log_id = provenance::verify(name, body.bundle)?;
-
-  db.insert_provenance_statement(&scope, &package, &version, &rekor_log_id)
-    .await?;
-
-  if let Some(orama_client) = orama_client {
-    let (package, _, meta) = db
-      .get_package(&scope, &package)
-      .await?
-      .ok_or(ApiError::InternalServerError)?;
-    orama_client.upsert_package(&package, &meta);
-  }
+  let verified = provenance::verify(name, body.bundle, provenance_policies)?;
+  Span::current().record("provider", field::display(&verified.provider));
+
+  db.insert_provenance_statement(
+    &scope,
+    &package,
+    &version,
+    &verified.rekor_log_id,
+    verified.source.as_ref(),
+  )
+  .await?;
 
   Ok(
     Response::builder()
@@ -900,23 +1546,205 @@ pub async fn version_update_handler(
   let db = req.data::<Database>().unwrap();
   let buckets = req.data::<Buckets>().unwrap().clone();
   let npm_url = &req.data::<NpmUrl>().unwrap().0;
+  let npm_include_yanked = req.data::<NpmIncludeYankedVersions>().unwrap().0;
+  let metadata_signer = req.data::<MetadataSigner>().unwrap();
+
+  let yank_reason = body
+    .yank_reason
+    .as_deref()
+    .map(str::trim)
+    .filter(|reason| !reason.is_empty());
+  if yank_reason.is_some_and(|reason| reason.chars().count() > 1000) {
+    return Err(ApiError::YankReasonTooLong);
+  }
+
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+
+  if body.yanked && !body.force {
+    check_not_latest_version(db, &scope, &package, &[version.clone()]).await?;
+  }
+
+  db.yank_package_version(
+    &scope,
+    &package,
+    &version,
+    body.yanked,
+    body.yank_reason_category.map(Into::into),
+    yank_reason,
+  )
+  .await?;
+
+  upload_yanked_package_metadata(
+    db,
+    &buckets,
+    metadata_signer,
+    npm_url,
+    npm_include_yanked,
+    &scope,
+    &package,
+  )
+  .await?;
+
+  Ok(
+    Response::builder()
+      .status(StatusCode::NO_CONTENT)
+      .body(Body::empty())
+      .unwrap(),
+  )
+}
+
+/// Yanks or unyanks many versions of a package at once, selected by a semver
+/// range or an explicit list. All versions are updated in one transaction, and
+/// the package metadata and npm manifest are only regenerated once.
+#[instrument(
+  name = "POST /api/scopes/:scope/packages/:package/versions/bulk_yank",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn version_bulk_yank_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiBulkYankPackageVersionsResponse> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let body: ApiBulkYankPackageVersionsRequest = decode_json(&mut req).await?;
+
+  let db = req.data::<Database>().unwrap();
+  let buckets = req.data::<Buckets>().unwrap().clone();
+  let npm_url = &req.data::<NpmUrl>().unwrap().0;
+  let npm_include_yanked = req.data::<NpmIncludeYankedVersions>().unwrap().0;
+  let metadata_signer = req.data::<MetadataSigner>().unwrap();
+
+  let yank_reason = body
+    .yank_reason
+    .as_deref()
+    .map(str::trim)
+    .filter(|reason| !reason.is_empty());
+  if yank_reason.is_some_and(|reason| reason.chars().count() > 1000) {
+    return Err(ApiError::YankReasonTooLong);
+  }
 
   let iam = req.iam();
   iam.check_scope_admin_access(&scope).await?;
 
-  db.yank_package_version(&scope, &package, &version, body.yanked)
+  let existing = db
+    .list_package_versions(&scope, &package)
+    .await?
+    .into_iter()
+    .map(|(version, _)| version.version)
+    .collect::<Vec<_>>();
+
+  let versions = match (body.range, body.versions) {
+    (Some(range), None) => {
+      let version_req = VersionReq::parse_from_npm(&range).map_err(|_| {
+        ApiError::MalformedRequest {
+          msg: "range is not a valid semver range".into(),
+        }
+      })?;
+      existing
+        .into_iter()
+        .filter(|version| version_req.matches(&version.0))
+        .collect::<Vec<_>>()
+    }
+    (None, Some(versions)) => {
+      if versions.iter().any(|version| !existing.contains(version)) {
+        return Err(ApiError::PackageVersionNotFound);
+      }
+      existing
+        .into_iter()
+        .filter(|version| versions.contains(version))
+        .collect::<Vec<_>>()
+    }
+    _ => {
+      return Err(ApiError::MalformedRequest {
+        msg: "exactly one of range and versions must be specified".into(),
+      });
+    }
+  };
+
+  if body.yanked && !body.force {
+    check_not_latest_version(db, &scope, &package, &versions).await?;
+  }
+
+  if !versions.is_empty() {
+    db.yank_package_versions(
+      &scope,
+      &package,
+      &versions,
+      body.yanked,
+      body.yank_reason_category.map(Into::into),
+      yank_reason,
+    )
+    .await?;
+
+    upload_yanked_package_metadata(
+      db,
+      &buckets,
+      metadata_signer,
+      npm_url,
+      npm_include_yanked,
+      &scope,
+      &package,
+    )
+    .await?;
+
+    let versions_list = versions
+      .iter()
+      .map(ToString::to_string)
+      .collect::<Vec<_>>()
+      .join(", ");
+    info!(
+      "{} @{scope}/{package} versions {versions_list}",
+      if body.yanked { "yanked" } else { "unyanked" },
+    );
+  }
+
+  Ok(ApiBulkYankPackageVersionsResponse { versions })
+}
+
+/// Refuses to yank or delete the latest version of a package. The `latest`
+/// dist-tag of the npm manifest points at it, so removing it would leave the
+/// tag dangling, or silently move it to an older version.
+pub async fn check_not_latest_version(
+  db: &Database,
+  scope: &ScopeName,
+  package: &PackageName,
+  versions: &[Version],
+) -> Result<(), ApiError> {
+  let latest = db
+    .get_latest_unyanked_version_for_package(scope, package)
     .await?;
+  if latest.is_some_and(|latest| versions.contains(&latest.version)) {
+    return Err(ApiError::PackageVersionIsLatest);
+  }
+  Ok(())
+}
 
+/// Regenerates the package metadata file and the npm version manifest after
+/// the yank status of versions of the package changed.
+async fn upload_yanked_package_metadata(
+  db: &Database,
+  buckets: &Buckets,
+  metadata_signer: &MetadataSigner,
+  npm_url: &Url,
+  npm_include_yanked: bool,
+  scope: &ScopeName,
+  package: &PackageName,
+) -> Result<(), ApiError> {
   let package_metadata_path =
-    crate::gcs_paths::package_metadata(&scope, &package);
-  let package_metadata = PackageMetadata::create(db, &scope, &package).await?;
+    crate::gcs_paths::package_metadata(scope, package);
+  let package_metadata = PackageMetadata::create(db, scope, package).await?;
 
   let content = serde_json::to_vec_pretty(&package_metadata)?;
   buckets
     .modules_bucket
     .upload(
-      package_metadata_path.into(),
-      UploadTaskBody::Bytes(content.into()),
+      package_metadata_path.as_str().into(),
+      UploadTaskBody::Bytes(content.clone().into()),
       GcsUploadOptions {
         content_type: Some("application/json".into()),
         cache_control: Some(CACHE_CONTROL_DO_NOT_CACHE.into()),
@@ -925,31 +1753,24 @@ pub async fn version_update_handler(
     )
     .await
     .unwrap();
+  upload_metadata_signature(
+    buckets,
+    metadata_signer,
+    &package_metadata_path,
+    &content,
+    CACHE_CONTROL_DO_NOT_CACHE,
+  )
+  .await?;
 
-  let npm_version_manifest_path =
-    crate::gcs_paths::npm_version_manifest_path(&scope, &package);
-  let npm_version_manifest =
-    generate_npm_version_manifest(db, npm_url, &scope, &package).await?;
-  let content = serde_json::to_vec_pretty(&npm_version_manifest)?;
-  buckets
-    .npm_bucket
-    .upload(
-      npm_version_manifest_path.into(),
-      UploadTaskBody::Bytes(content.into()),
-      GcsUploadOptions {
-        content_type: Some("application/json".into()),
-        cache_control: Some(CACHE_CONTROL_DO_NOT_CACHE.into()),
-        gzip_encoded: false,
-      },
-    )
-    .await?;
-
-  Ok(
-    Response::builder()
-      .status(StatusCode::NO_CONTENT)
-      .body(Body::empty())
-      .unwrap(),
+  upload_npm_version_manifest(
+    db,
+    npm_url,
+    npm_include_yanked,
+    buckets,
+    scope,
+    package,
   )
+  .await
 }
 
 #[instrument(
@@ -1011,24 +1832,49 @@ pub async fn get_docs_handler(
   };
   let version = maybe_version.ok_or(ApiError::PackageVersionNotFound)?;
 
+  // The landing page replaces the README on the index page. If it refers to
+  // an export or file that this version does not have, the README is shown.
+  let is_index = !all_symbols && entrypoint.is_none() && symbol.is_none();
+  let landing_page = package
+    .docs_landing_page
+    .as_deref()
+    .filter(|_| is_index)
+    .and_then(|page| DocsLandingPage::parse(page).ok());
+  let entrypoint = match &landing_page {
+    Some(page @ DocsLandingPage::Export(name))
+      if version.exports.contains_key(name) =>
+    {
+      page.entrypoint()
+    }
+    _ => entrypoint,
+  };
+  let readme_paths = match &landing_page {
+    Some(DocsLandingPage::File(path)) => {
+      vec![Some(path.clone()), version.readme_path.clone()]
+    }
+    _ => vec![version.readme_path.clone()],
+  };
+
   let docs_path =
     crate::gcs_paths::docs_v1_path(&scope, &package_name, &version.version);
   let doc_nodes_fut = buckets.docs_bucket.download(docs_path.into());
-  let readme_fut = if !all_symbols && entrypoint.is_none() && symbol.is_none() {
-    if let Some(readme_path) = &version.readme_path {
+  let readme_fut = async {
+    if !is_index || entrypoint.is_some() {
+      return Ok::<_, crate::gcp::GcsError>(None);
+    }
+    for readme_path in readme_paths.into_iter().flatten() {
       let gcs_path = crate::gcs_paths::file_path(
         &scope,
         &package_name,
         &version.version,
-        readme_path,
+        &readme_path,
       )
       .into();
-      Either::Left(buckets.modules_bucket.download(gcs_path))
-    } else {
-      Either::Right(futures::future::ready(Ok(None)))
+      if let Some(readme) = buckets.modules_bucket.download(gcs_path).await? {
+        return Ok(Some(readme));
+      }
     }
-  } else {
-    Either::Right(futures::future::ready(Ok(None)))
+    Ok(None)
   };
 
   let (docs, readme) =
@@ -1040,6 +1886,16 @@ pub async fn get_docs_handler(
     );
     ApiError::InternalServerError
   })?;
+
+  let source_url_base = get_source_url_base(
+    db,
+    repo.as_ref(),
+    &scope,
+    &package_name,
+    &version.version,
+  )
+  .await?;
+
   let doc_nodes: DocNodesByUrl =
     serde_json::from_slice(&docs).context("failed to parse doc nodes")?;
   let readme = readme.and_then(|readme| {
@@ -1080,6 +1936,7 @@ pub async fn get_docs_handler(
     version.version.clone(),
     version_or_latest == VersionOrLatest::Latest,
     repo,
+    source_url_base,
     readme,
     package.runtime_compat,
     registry_url,
@@ -1151,6 +2008,16 @@ pub async fn get_docs_search_handler(
     );
     ApiError::InternalServerError
   })?;
+
+  let source_url_base = get_source_url_base(
+    db,
+    repo.as_ref(),
+    &scope,
+    &package_name,
+    &version.version,
+  )
+  .await?;
+
   let doc_nodes: DocNodesByUrl =
     serde_json::from_slice(&docs).context("failed to parse doc nodes")?;
 
@@ -1167,6 +2034,7 @@ pub async fn get_docs_search_handler(
     version.version.clone(),
     version_or_latest == VersionOrLatest::Latest,
     repo,
+    source_url_base,
     false,
     package.runtime_compat,
     registry_url,
@@ -1222,6 +2090,16 @@ pub async fn get_docs_search_html_handler(
     );
     ApiError::InternalServerError
   })?;
+
+  let source_url_base = get_source_url_base(
+    db,
+    repo.as_ref(),
+    &scope,
+    &package_name,
+    &version.version,
+  )
+  .await?;
+
   let doc_nodes: DocNodesByUrl =
     serde_json::from_slice(&docs).context("failed to parse doc nodes")?;
 
@@ -1239,6 +2117,7 @@ pub async fn get_docs_search_html_handler(
     version.version.clone(),
     version_or_latest == VersionOrLatest::Latest,
     repo,
+    source_url_base,
     None,
     package.runtime_compat,
     registry_url,
@@ -1257,111 +2136,318 @@ pub async fn get_docs_search_html_handler(
   Ok(search)
 }
 
+/// Renders the documentation of a single symbol, for embedding in editors and
+/// other doc sites. By default the rendered HTML is returned as is. With
+/// `format=json`, the HTML is returned together with the doc nodes of the
+/// symbol, which describe its parameters, types and examples.
 #[instrument(
-  name = "GET /api/scopes/:scope/packages/:package/versions/:version/source",
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/docs/symbol/:symbol",
   skip(req),
   err,
-  fields(scope, package, version, path)
+  fields(scope, package, version, entrypoint, symbol, format)
 )]
-pub async fn get_source_handler(
+pub async fn get_docs_symbol_handler(
   req: Request<Body>,
-) -> ApiResult<ApiPackageVersionSource> {
+) -> ApiResult<Response<Body>> {
   let scope = req.param_scope()?;
-  let package = req.param_package()?;
+  let package_name = req.param_package()?;
   let version_or_latest = req.param_version_or_latest()?;
-  let path = req.query("path").cloned().unwrap_or("/".to_string());
-
   Span::current().record("scope", field::display(&scope));
-  Span::current().record("package", field::display(&package));
+  Span::current().record("package", field::display(&package_name));
   Span::current().record("version", field::display(&version_or_latest));
-  Span::current().record("path", field::display(&path));
+  let symbol = urlencoding::decode(req.param("symbol").unwrap())?.into_owned();
+  Span::current().record("symbol", field::display(&symbol));
+  let entrypoint = req.query("entrypoint").and_then(|s| match s.as_str() {
+    "" => None,
+    s => Some(s),
+  });
+  Span::current()
+    .record("entrypoint", field::display(&entrypoint.unwrap_or("")));
+  let as_json = match req.query("format").map(|s| s.as_str()) {
+    None | Some("html") => false,
+    Some("json") => true,
+    Some(_) => {
+      return Err(ApiError::MalformedRequest {
+        msg: "format must be 'html' or 'json'".into(),
+      })
+    }
+  };
+  Span::current().record("format", if as_json { "json" } else { "html" });
 
   let db = req.data::<Database>().unwrap();
   let buckets = req.data::<Buckets>().unwrap();
-  let _ = db
-    .get_package(&scope, &package)
+  let (package, repo, _) = db
+    .get_package(&scope, &package_name)
     .await?
     .ok_or(ApiError::PackageNotFound)?;
 
   let maybe_version = match &version_or_latest {
     VersionOrLatest::Version(version) => {
-      db.get_package_version(&scope, &package, version).await?
+      db.get_package_version(&scope, &package_name, version)
+        .await?
     }
     VersionOrLatest::Latest => {
-      db.get_latest_unyanked_version_for_package(&scope, &package)
+      db.get_latest_unyanked_version_for_package(&scope, &package_name)
         .await?
     }
   };
   let version = maybe_version.ok_or(ApiError::PackageVersionNotFound)?;
 
-  let file = if path == "meta.json" {
-    let source_file_path = crate::gcs_paths::package_metadata(&scope, &package);
-    buckets
-      .modules_bucket
-      .download(source_file_path.into())
-      .await?
-  } else if path == format!("{}_meta.json", version.version) {
-    let source_file_path =
-      crate::gcs_paths::version_metadata(&scope, &package, &version.version);
-    buckets
-      .modules_bucket
-      .download(source_file_path.into())
-      .await?
-  } else if path != "/" {
-    let package_path = PackagePath::try_from(path.as_str()).map_err(|err| {
-      let msg = format!("failed to parse path parameter 'path': {err}").into();
-      ApiError::MalformedRequest { msg }
-    })?;
-
-    let source_file_path = crate::gcs_paths::file_path(
-      &scope,
-      &package,
-      &version.version,
-      &package_path,
+  let docs_path =
+    crate::gcs_paths::docs_v1_path(&scope, &package_name, &version.version);
+  let docs = buckets.docs_bucket.download(docs_path.into()).await?;
+  let docs = docs.ok_or_else(|| {
+    error!(
+      "docs not found for {}/{}/{}",
+      scope, package_name, version.version
     );
-    buckets
-      .modules_bucket
-      .download(source_file_path.into())
-      .await?
-  } else {
-    None
-  };
-
-  let path_buf = std::path::PathBuf::from(path);
-
-  let source = if let Some(file) = file {
-    let size = file.len();
+    ApiError::InternalServerError
+  })?;
 
-    let highlighter = deno_doc::html::comrak::ComrakHighlightWrapperAdapter(
-      Some(Arc::new(crate::tree_sitter::ComrakAdapter {
-        show_line_numbers: true,
-      })),
-    );
+  let source_url_base = get_source_url_base(
+    db,
+    repo.as_ref(),
+    &scope,
+    &package_name,
+    &version.version,
+  )
+  .await?;
 
-    let view = if let Ok(file) = String::from_utf8(file.to_vec()) {
-      let mut out = vec![];
-      highlighter.write_pre_tag(&mut out, Default::default())?;
-      highlighter.write_code_tag(&mut out, Default::default())?;
-      highlighter.write_highlighted(
-        &mut out,
-        path_buf
-          .extension()
-          .map(|ext| ext.to_string_lossy())
-          .as_deref(),
-        &file,
-      )?;
-      out.extend(b"</code></pre>");
+  let doc_nodes: DocNodesByUrl =
+    serde_json::from_slice(&docs).context("failed to parse doc nodes")?;
 
-      Some(String::from_utf8(out).context("File is not valid utf8")?)
-    } else {
-      None
-    };
+  let docs_info = crate::docs::get_docs_info(&version.exports, entrypoint);
+  let entrypoint_url = match entrypoint {
+    Some(_) => docs_info.entrypoint_url,
+    None => docs_info.main_entrypoint.clone(),
+  }
+  .ok_or(ApiError::EntrypointOrSymbolNotFound)?;
 
-    ApiSource::File { size, view }
+  let nodes = if as_json {
+    let nodes = doc_nodes
+      .get(&entrypoint_url)
+      .map(serde_json::to_value)
+      .transpose()
+      .context("failed to serialize doc nodes")?
+      .unwrap_or_default();
+    find_symbol_doc_nodes(nodes, &symbol)
   } else {
-    let files = db
-      .list_package_files(&scope, &package, &version.version)
-      .await?;
+    vec![]
+  };
+
+  let registry_url = req.data::<RegistryUrl>().unwrap().0.to_string();
+
+  let docs = crate::docs::generate_docs_html(
+    doc_nodes,
+    docs_info.main_entrypoint,
+    docs_info.rewrite_map,
+    DocsRequest::Symbol(entrypoint_url, symbol.clone()),
+    scope.clone(),
+    package_name.clone(),
+    version.version.clone(),
+    version_or_latest == VersionOrLatest::Latest,
+    repo,
+    source_url_base,
+    None,
+    package.runtime_compat,
+    registry_url,
+  )
+  .map_err(|e| {
+    error!("failed to generate docs: {}", e);
+    ApiError::InternalServerError
+  })?;
+  let Some(GeneratedDocsOutput::Docs(docs)) = docs else {
+    return Err(ApiError::EntrypointOrSymbolNotFound);
+  };
+
+  let (content_type, body) = if as_json {
+    let body = serde_json::to_vec(&ApiPackageVersionSymbolDocs {
+      symbol,
+      css: Cow::Borrowed(deno_doc::html::STYLESHEET),
+      comrak_css: Cow::Borrowed(deno_doc::html::comrak::COMRAK_STYLESHEET),
+      html: docs.main,
+      nodes,
+    })
+    .context("failed to serialize symbol docs")?;
+    ("application/json", Body::from(body))
+  } else {
+    ("text/html; charset=utf-8", Body::from(docs.main))
+  };
+
+  let resp = Response::builder()
+    .status(StatusCode::OK)
+    .header(hyper::header::CONTENT_TYPE, content_type)
+    .body(body)
+    .unwrap();
+  Ok(resp)
+}
+
+/// Finds the doc nodes of a symbol in the serialized doc nodes of a module.
+/// Members of namespaces are referred to with a dotted path, like `Foo.bar`.
+/// A symbol can have multiple nodes, for example a function with overloads.
+fn find_symbol_doc_nodes(
+  nodes: serde_json::Value,
+  symbol: &str,
+) -> Vec<serde_json::Value> {
+  let mut nodes = match nodes {
+    serde_json::Value::Array(nodes) => nodes,
+    _ => return vec![],
+  };
+  let mut path = symbol.split('.').peekable();
+  while let Some(name) = path.next() {
+    nodes.retain(|node| node["name"] == name);
+    if path.peek().is_none() {
+      break;
+    }
+    nodes = nodes
+      .into_iter()
+      .filter_map(|mut node| match node["namespaceDef"]["elements"].take() {
+        serde_json::Value::Array(elements) => Some(elements),
+        _ => None,
+      })
+      .flatten()
+      .collect();
+  }
+  nodes
+}
+
+/// Resolves where the files of a package version can be viewed in the linked
+/// GitHub repository, if the provenance of the version ties it to a commit of
+/// that repository.
+async fn get_source_url_base(
+  db: &Database,
+  repo: Option<&GithubRepository>,
+  scope: &ScopeName,
+  package: &PackageName,
+  version: &Version,
+) -> Result<Option<String>, ApiError> {
+  let Some(repo) = repo else {
+    return Ok(None);
+  };
+  let source = db
+    .get_package_version_source(scope, package, version)
+    .await?;
+  Ok(
+    source
+      .and_then(|source| crate::docs::github_source_url_base(repo, &source)),
+  )
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/source",
+  skip(req),
+  err,
+  fields(scope, package, version, path)
+)]
+pub async fn get_source_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPackageVersionSource> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version_or_latest = req.param_version_or_latest()?;
+  let path = req.query("path").cloned().unwrap_or("/".to_string());
+
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version_or_latest));
+  Span::current().record("path", field::display(&path));
+
+  let db = req.data::<Database>().unwrap();
+  let buckets = req.data::<Buckets>().unwrap();
+  let (_, repo, _) = db
+    .get_package(&scope, &package)
+    .await?
+    .ok_or(ApiError::PackageNotFound)?;
+
+  let maybe_version = match &version_or_latest {
+    VersionOrLatest::Version(version) => {
+      db.get_package_version(&scope, &package, version).await?
+    }
+    VersionOrLatest::Latest => {
+      db.get_latest_unyanked_version_for_package(&scope, &package)
+        .await?
+    }
+  };
+  let version = maybe_version.ok_or(ApiError::PackageVersionNotFound)?;
+
+  let file = if path == "meta.json" {
+    let source_file_path = crate::gcs_paths::package_metadata(&scope, &package);
+    buckets
+      .modules_bucket
+      .download(source_file_path.into())
+      .await?
+  } else if path == format!("{}_meta.json", version.version) {
+    let source_file_path =
+      crate::gcs_paths::version_metadata(&scope, &package, &version.version);
+    buckets
+      .modules_bucket
+      .download(source_file_path.into())
+      .await?
+  } else if path != "/" {
+    let package_path = PackagePath::try_from(path.as_str()).map_err(|err| {
+      let msg = format!("failed to parse path parameter 'path': {err}").into();
+      ApiError::MalformedRequest { msg }
+    })?;
+
+    let source_file_path = crate::gcs_paths::file_path(
+      &scope,
+      &package,
+      &version.version,
+      &package_path,
+    );
+    buckets
+      .modules_bucket
+      .download(source_file_path.into())
+      .await?
+  } else {
+    None
+  };
+
+  // The metadata files are generated by the registry, so they do not exist in
+  // the repository.
+  let repository_url = if file.is_some() && !path.starts_with('/') {
+    None
+  } else {
+    get_source_url_base(db, repo.as_ref(), &scope, &package, &version.version)
+      .await?
+      .map(|base| format!("{base}{}", path.trim_end_matches('/')))
+  };
+
+  let path_buf = std::path::PathBuf::from(path);
+
+  let source = if let Some(file) = file {
+    let size = file.len();
+
+    let highlighter = deno_doc::html::comrak::ComrakHighlightWrapperAdapter(
+      Some(Arc::new(crate::tree_sitter::ComrakAdapter {
+        show_line_numbers: true,
+      })),
+    );
+
+    let view = if let Ok(file) = String::from_utf8(file.to_vec()) {
+      let mut out = vec![];
+      highlighter.write_pre_tag(&mut out, Default::default())?;
+      highlighter.write_code_tag(&mut out, Default::default())?;
+      highlighter.write_highlighted(
+        &mut out,
+        path_buf
+          .extension()
+          .map(|ext| ext.to_string_lossy())
+          .as_deref(),
+        &file,
+      )?;
+      out.extend(b"</code></pre>");
+
+      Some(String::from_utf8(out).context("File is not valid utf8")?)
+    } else {
+      None
+    };
+
+    ApiSource::File { size, view }
+  } else {
+    let files = db
+      .list_package_files(&scope, &package, &version.version)
+      .await?;
 
     let mut entries = indexmap::IndexMap::new();
 
@@ -1414,6 +2500,7 @@ pub async fn get_source_handler(
     comrak_css: Cow::Borrowed(deno_doc::html::comrak::COMRAK_STYLESHEET),
     script: Cow::Borrowed(deno_doc::html::SCRIPT_JS),
     source,
+    repository_url,
   })
 }
 
@@ -1460,6 +2547,58 @@ pub async fn list_dependents_handler(
   })
 }
 
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/download_anomalies",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn list_download_anomalies_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiDownloadAnomaly>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let db = req.data::<Database>().unwrap();
+  db.get_package(&scope, &package)
+    .await?
+    .ok_or(ApiError::PackageNotFound)?;
+
+  let anomalies = db.list_package_download_anomalies(&scope, &package).await?;
+  Ok(anomalies.into_iter().map(ApiDownloadAnomaly::from).collect())
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/broken_links",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn get_broken_links_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPackageLinkCheck> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let iam = req.iam();
+  iam.check_scope_write_access(&scope).await?;
+
+  let db = req.data::<Database>().unwrap();
+  db.get_package(&scope, &package)
+    .await?
+    .ok_or(ApiError::PackageNotFound)?;
+
+  let check = db
+    .get_package_link_check(&scope, &package)
+    .await?
+    .ok_or(ApiError::PackageLinkCheckNotFound)?;
+  Ok(check.into())
+}
+
 #[instrument(
   name = "GET /api/scopes/:scope/packages/:package/downloads",
   skip(req),
@@ -1543,57 +2682,665 @@ pub async fn list_dependencies_handler(
   let deps = db
     .list_package_version_dependencies(&scope, &package, &version)
     .await?;
+  let archived = db
+    .list_archived_package_version_dependencies(&scope, &package, &version)
+    .await?
+    .into_iter()
+    .collect::<HashSet<_>>();
   let deps = deps
     .into_iter()
-    .map(ApiDependency::from)
+    .map(|dep| {
+      let is_archived = dep.dependency_kind == crate::db::DependencyKind::Jsr
+        && archived.contains(&dep.dependency_name);
+      ApiDependency::from((dep, is_archived))
+    })
     .collect::<Vec<_>>();
 
   Ok(deps)
 }
 
-struct DepTreeLoader {
-  scope: ScopeName,
-  package: PackageName,
-  version: crate::ids::Version,
-  bucket: crate::buckets::BucketWithQueue,
-  exports: Arc<tokio::sync::Mutex<IndexMap<String, IndexMap<String, String>>>>,
-}
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/dependencies/freshness",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn list_dependencies_freshness_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiDependencyFreshness>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
 
-impl DepTreeLoader {
-  fn load_inner(
-    &self,
-    specifier: &ModuleSpecifier,
-  ) -> deno_graph::source::LoadFuture {
-    use futures::FutureExt;
-    let specifier = specifier.clone();
+  let db = req.data::<Database>().unwrap();
 
-    match specifier.scheme() {
-      "file" => {
-        let Ok(path) = PackagePath::new(specifier.path().to_string()) else {
-          return async move { Ok(None) }.boxed();
-        };
+  db.get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
 
-        let scope = self.scope.clone();
-        let package = self.package.clone();
-        let version = self.version.clone();
-        let bucket = self.bucket.clone();
+  let deps = dependency_freshness(db, &scope, &package, &version).await?;
 
-        async move {
-          let Some(bytes) = bucket
-            .download(
-              crate::gcs_paths::file_path(&scope, &package, &version, &path)
-                .into(),
-            )
-            .await
-            .map_err(|e| LoadError::Other(Arc::new(JsErrorBox::from_err(e))))?
-          else {
-            return Ok(None);
-          };
+  Ok(deps)
+}
 
-          Ok(Some(deno_graph::source::LoadResponse::Module {
-            content: bytes.to_vec().into(),
-            specifier: specifier.clone(),
-            maybe_headers: None,
+/// Annotates each dependency of a package version with the latest version of
+/// the dependency, and how far behind it the constraint of the dependency is.
+/// The latest version is only known for JSR dependencies.
+pub async fn dependency_freshness(
+  db: &Database,
+  scope: &ScopeName,
+  package: &PackageName,
+  version: &Version,
+) -> Result<Vec<ApiDependencyFreshness>, sqlx::Error> {
+  let deps = db
+    .list_package_version_dependencies(scope, package, version)
+    .await?;
+
+  let mut available_versions: HashMap<
+    String,
+    Vec<(deno_semver::Version, bool)>,
+  > = HashMap::new();
+  for (name, version, is_yanked) in db
+    .list_package_version_jsr_dependency_versions(scope, package, version)
+    .await?
+  {
+    available_versions
+      .entry(name)
+      .or_default()
+      .push((version.0, is_yanked));
+  }
+
+  let deps = deps
+    .into_iter()
+    .map(|dep| {
+      let versions = if dep.dependency_kind == crate::db::DependencyKind::Jsr {
+        available_versions
+          .get(&dep.dependency_name)
+          .map(|versions| versions.as_slice())
+          .unwrap_or_default()
+      } else {
+        &[]
+      };
+      let version_req =
+        VersionReq::parse_from_specifier(&dep.dependency_constraint).ok();
+      let matching = |version: &&(deno_semver::Version, bool)| {
+        version_req
+          .as_ref()
+          .is_some_and(|version_req| version_req.matches(&version.0))
+      };
+
+      let latest = versions
+        .iter()
+        .filter(|(version, is_yanked)| !is_yanked && version.pre.is_empty())
+        .map(|(version, _)| version)
+        .max();
+      let resolved = versions
+        .iter()
+        .filter(|(_, is_yanked)| !is_yanked)
+        .filter(matching)
+        .map(|(version, _)| version)
+        .max();
+      // A yanked version is still used by everyone who locked it before it
+      // was yanked, so the dependency is flagged if the highest version that
+      // ever satisfied the constraint has been yanked.
+      let yanked = versions
+        .iter()
+        .filter(matching)
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .is_some_and(|(_, is_yanked)| *is_yanked);
+      let matches_latest = latest
+        .zip(version_req.as_ref())
+        .map(|(latest, version_req)| version_req.matches(latest));
+      let majors_behind = latest
+        .zip(resolved)
+        .map(|(latest, resolved)| latest.major.saturating_sub(resolved.major));
+
+      ApiDependencyFreshness {
+        kind: dep.dependency_kind.into(),
+        dependency_type: dep.dependency_type.into(),
+        name: dep.dependency_name,
+        constraint: dep.dependency_constraint,
+        path: dep.dependency_path,
+        latest_version: latest.cloned().map(Version),
+        matches_latest,
+        majors_behind,
+        yanked,
+      }
+    })
+    .collect::<Vec<_>>();
+
+  Ok(deps)
+}
+
+/// The maximum number of package versions that are included in a license
+/// report, to bound the number of queries for very large dependency trees.
+const MAX_LICENSE_REPORT_PACKAGES: usize = 250;
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/licenses",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn get_license_report_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiLicenseReport> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let report = license_report(db, scope, package, version).await?;
+
+  Ok(report)
+}
+
+/// Walks the JSR dependency tree of a package version breadth-first, and
+/// collects the license of every package version in it. Each dependency
+/// resolves to the highest non-yanked version that satisfies its constraint,
+/// like it would for a fresh install.
+pub async fn license_report(
+  db: &Database,
+  scope: ScopeName,
+  package: PackageName,
+  version: Version,
+) -> Result<ApiLicenseReport, sqlx::Error> {
+  let mut packages = Vec::new();
+  let mut unresolved = IndexSet::new();
+  let mut truncated = false;
+
+  let mut visited = HashSet::new();
+  visited.insert((scope.clone(), package.clone(), version.clone()));
+  let mut queue = std::collections::VecDeque::new();
+  queue.push_back((scope, package, version, 0));
+
+  while let Some((scope, package, version, depth)) = queue.pop_front() {
+    let license = db
+      .get_package_version_license(&scope, &package, &version)
+      .await?;
+    let classification = crate::license::classify_license(license.as_deref());
+    packages.push(ApiLicenseReportPackage {
+      scope: scope.clone(),
+      package: package.clone(),
+      version: version.clone(),
+      license,
+      classification: classification.into(),
+      flagged: classification
+        != crate::license::LicenseClassification::Permissive,
+      depth,
+    });
+
+    let deps = db
+      .list_package_version_dependencies(&scope, &package, &version)
+      .await?;
+    let mut available_versions: HashMap<
+      String,
+      Vec<(deno_semver::Version, bool)>,
+    > = HashMap::new();
+    for (name, version, is_yanked) in db
+      .list_package_version_jsr_dependency_versions(&scope, &package, &version)
+      .await?
+    {
+      available_versions
+        .entry(name)
+        .or_default()
+        .push((version.0, is_yanked));
+    }
+
+    for dep in deps {
+      let resolved = if dep.dependency_kind == crate::db::DependencyKind::Jsr {
+        let version_req =
+          VersionReq::parse_from_specifier(&dep.dependency_constraint).ok();
+        let name = ScopedPackageName::new(dep.dependency_name.clone()).ok();
+        let version = available_versions
+          .get(&dep.dependency_name)
+          .map(|versions| versions.as_slice())
+          .unwrap_or_default()
+          .iter()
+          .filter(|(_, is_yanked)| !is_yanked)
+          .filter(|(version, _)| {
+            version_req
+              .as_ref()
+              .is_some_and(|version_req| version_req.matches(version))
+          })
+          .map(|(version, _)| version)
+          .max();
+        name.zip(version.cloned())
+      } else {
+        None
+      };
+
+      let Some((name, dep_version)) = resolved else {
+        unresolved.insert(ApiLicenseReportUnresolved {
+          kind: dep.dependency_kind.into(),
+          name: dep.dependency_name,
+          constraint: dep.dependency_constraint,
+        });
+        continue;
+      };
+      let key = (name.scope, name.package, Version(dep_version));
+      if visited.contains(&key) {
+        continue;
+      }
+      if visited.len() >= MAX_LICENSE_REPORT_PACKAGES {
+        truncated = true;
+        continue;
+      }
+      visited.insert(key.clone());
+      queue.push_back((key.0, key.1, key.2, depth + 1));
+    }
+  }
+
+  Ok(ApiLicenseReport {
+    packages,
+    unresolved: unresolved.into_iter().collect(),
+    truncated,
+  })
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/entrypoints",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn list_entrypoints_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiEntrypoint>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap();
+
+  let package_version = db
+    .get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let entrypoints = package_version
+    .meta
+    .entrypoints
+    .into_iter()
+    .map(ApiEntrypoint::from)
+    .collect();
+
+  Ok(entrypoints)
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/typescript",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn list_typescript_compatibility_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiTypeScriptCompatibility>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap();
+  let typescript_versions = req.data::<TypeScriptVersions>().unwrap();
+
+  let package_version = db
+    .get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let features = package_version.meta.typescript_features.as_deref();
+  let compatibility = typescript_versions
+    .0
+    .iter()
+    .map(|version| ApiTypeScriptCompatibility::new(*version, features))
+    .collect();
+
+  Ok(compatibility)
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/integrity",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn get_integrity_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPackageVersionIntegrity> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let files = db.list_package_files(&scope, &package, &version).await?;
+
+  Ok(ApiPackageVersionIntegrity::new(
+    scope, package, version, files,
+  ))
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/publish_info",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn get_publish_info_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPackageVersionPublishInfo> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap();
+  let registry_url = req.data::<RegistryUrl>().unwrap().0.clone();
+
+  let package_version = db
+    .get_package_version(&scope, &package, &version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let publishing_task = db
+    .list_publishing_tasks_for_package(&scope, &package)
+    .await?
+    .into_iter()
+    .find(|task| {
+      task.package_version == version
+        && task.status == PublishingTaskStatus::Success
+    })
+    .ok_or(ApiError::PublishNotFound)?;
+
+  let user = match publishing_task.user_id {
+    Some(user_id) => db.get_user_public(user_id).await?.map(Into::into),
+    None => None,
+  };
+  let github_repository = db
+    .get_publishing_task_github_repository(publishing_task.id)
+    .await?
+    .map(Into::into);
+  let github_workflow_run = db
+    .get_publishing_task_github_workflow_run(publishing_task.id)
+    .await?
+    .map(Into::into);
+
+  let provenance = match package_version.rekor_log_id {
+    Some(rekor_log_id) => {
+      let source = db
+        .get_package_version_source(&scope, &package, &version)
+        .await?;
+      Some(ApiPublishProvenance {
+        transparency_log_url: format!(
+          "https://search.sigstore.dev/?logIndex={rekor_log_id}"
+        ),
+        rekor_log_id,
+        source_repository: source.as_ref().map(|s| s.repository.clone()),
+        source_commit: source.map(|s| s.commit),
+      })
+    }
+    None => None,
+  };
+
+  let meta_url = registry_url
+    .join(&format!("@{scope}/{package}/{version}_meta.json"))
+    .unwrap()
+    .to_string();
+
+  // Scheduled publishes are not processed before their publish time.
+  let started_at = publishing_task
+    .publish_at
+    .map_or(publishing_task.created_at, |publish_at| {
+      publish_at.max(publishing_task.created_at)
+    });
+  let duration_ms = (publishing_task.updated_at - started_at)
+    .num_milliseconds()
+    .max(0);
+
+  Ok(ApiPackageVersionPublishInfo {
+    scope,
+    package,
+    version,
+    publishing_task_id: publishing_task.id,
+    user,
+    github_repository,
+    github_workflow_run,
+    provenance,
+    meta_url,
+    queued_at: publishing_task.created_at,
+    publish_at: publishing_task.publish_at,
+    completed_at: publishing_task.updated_at,
+    duration_ms,
+  })
+}
+
+/// Lists the imports of the version that point to a package on JSR or npm, or
+/// to a runtime built-in module, with what they resolved to at publish time.
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/specifiers",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn list_specifiers_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiPublishedSpecifier>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap();
+
+  let specifiers =
+    get_version_specifiers(db, &scope, &package, &version).await?;
+
+  Ok(specifiers.0.into_iter().map(Into::into).collect())
+}
+
+/// Returns the specifiers recorded by the publish of a package version.
+async fn get_version_specifiers(
+  db: &Database,
+  scope: &ScopeName,
+  package: &PackageName,
+  version: &Version,
+) -> Result<PublishedSpecifiers, ApiError> {
+  db.get_package_version(scope, package, version)
+    .await?
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let publishing_task = db
+    .list_publishing_tasks_for_package(scope, package)
+    .await?
+    .into_iter()
+    .find(|task| {
+      task.package_version == *version
+        && task.status == PublishingTaskStatus::Success
+    })
+    .ok_or(ApiError::PackageVersionSpecifiersNotFound)?;
+
+  let specifiers = db
+    .get_publishing_task_specifiers(publishing_task.id)
+    .await?
+    .ok_or(ApiError::PackageVersionSpecifiersNotFound)?;
+
+  Ok(specifiers)
+}
+
+/// Lists the symbols that the version imports from its JSR dependencies that
+/// are marked `@deprecated` in the latest version of the dependency, so that
+/// authors know which imports to migrate before they upgrade.
+#[instrument(
+  name = "GET /api/scopes/:scope/packages/:package/versions/:version/deprecated_imports",
+  skip(req),
+  err,
+  fields(scope, package, version)
+)]
+pub async fn list_deprecated_imports_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiDeprecatedImport>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  let version = req.param_version()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+  Span::current().record("version", field::display(&version));
+
+  let db = req.data::<Database>().unwrap();
+
+  let specifiers =
+    get_version_specifiers(db, &scope, &package, &version).await?;
+
+  // The referrers of each name imported from each export of each dependency.
+  let mut imports: IndexMap<
+    ScopedPackageName,
+    IndexMap<String, IndexMap<String, IndexSet<String>>>,
+  > = IndexMap::new();
+  for specifier in specifiers.0 {
+    if specifier.kind != PublishedSpecifierKind::Jsr {
+      continue;
+    }
+    let Ok(reference) = JsrPackageReqReference::from_str(&specifier.resolved)
+    else {
+      continue;
+    };
+    let Ok(dependency) =
+      ScopedPackageName::new(reference.req().name.to_string())
+    else {
+      continue;
+    };
+    let export = match reference.sub_path() {
+      Some(sub_path) => format!("./{sub_path}"),
+      None => ".".to_string(),
+    };
+    let names = imports
+      .entry(dependency)
+      .or_default()
+      .entry(export)
+      .or_default();
+    for name in specifier.imported_names {
+      names
+        .entry(name)
+        .or_default()
+        .insert(specifier.referrer.clone());
+    }
+  }
+
+  let mut deprecated_imports = vec![];
+  for (dependency, exports) in imports {
+    let Some(latest) = db
+      .get_latest_unyanked_version_for_package(
+        &dependency.scope,
+        &dependency.package,
+      )
+      .await?
+    else {
+      continue;
+    };
+    for (export, names) in exports {
+      let Some(symbols) = latest.meta.deprecated_symbols.get(&export) else {
+        continue;
+      };
+      for symbol in symbols {
+        // A namespace import uses every symbol of the export.
+        let referrers = names
+          .get(&symbol.name)
+          .into_iter()
+          .chain(names.get("*"))
+          .flatten()
+          .cloned()
+          .collect::<IndexSet<_>>();
+        if referrers.is_empty() {
+          continue;
+        }
+        deprecated_imports.push(ApiDeprecatedImport {
+          scope: dependency.scope.clone(),
+          package: dependency.package.clone(),
+          version: latest.version.clone(),
+          export: export.clone(),
+          name: symbol.name.clone(),
+          message: symbol.message.clone(),
+          referrers: referrers.into_iter().collect(),
+        });
+      }
+    }
+  }
+
+  Ok(deprecated_imports)
+}
+
+struct DepTreeLoader {
+  scope: ScopeName,
+  package: PackageName,
+  version: crate::ids::Version,
+  bucket: crate::buckets::BucketWithQueue,
+  exports: Arc<tokio::sync::Mutex<IndexMap<String, IndexMap<String, String>>>>,
+}
+
+impl DepTreeLoader {
+  fn load_inner(
+    &self,
+    specifier: &ModuleSpecifier,
+  ) -> deno_graph::source::LoadFuture {
+    use futures::FutureExt;
+    let specifier = specifier.clone();
+
+    match specifier.scheme() {
+      "file" => {
+        let Ok(path) = PackagePath::new(specifier.path().to_string()) else {
+          return async move { Ok(None) }.boxed();
+        };
+
+        let scope = self.scope.clone();
+        let package = self.package.clone();
+        let version = self.version.clone();
+        let bucket = self.bucket.clone();
+
+        async move {
+          let Some(bytes) = bucket
+            .download(
+              crate::gcs_paths::file_path(&scope, &package, &version, &path)
+                .into(),
+            )
+            .await
+            .map_err(|e| LoadError::Other(Arc::new(JsErrorBox::from_err(e))))?
+          else {
+            return Ok(None);
+          };
+
+          Ok(Some(deno_graph::source::LoadResponse::Module {
+            content: bytes.to_vec().into(),
+            specifier: specifier.clone(),
+            maybe_headers: None,
           }))
         }
         .boxed()
@@ -2175,21 +3922,44 @@ mod test {
   use hyper::StatusCode;
   use indexmap::IndexSet;
   use serde_json::json;
+  use sha2::Digest;
 
+  use crate::api::ApiBulkYankPackageVersionsResponse;
   use crate::api::ApiDependency;
+  use crate::api::ApiDependencyFreshness;
   use crate::api::ApiDependencyGraphItem;
   use crate::api::ApiDependencyKind;
+  use crate::api::ApiDependencyType;
   use crate::api::ApiDependent;
+  use crate::api::ApiDeprecatedImport;
+  use crate::api::ApiEntrypoint;
+  use crate::api::ApiLicenseClassification;
+  use crate::api::ApiLicenseReport;
+  use crate::api::ApiLicenseReportPackage;
+  use crate::api::ApiLicenseReportUnresolved;
   use crate::api::ApiList;
+  use crate::api::ApiMaintenanceStatus;
   use crate::api::ApiMetrics;
   use crate::api::ApiPackage;
   use crate::api::ApiPackageScore;
   use crate::api::ApiPackageVersion;
   use crate::api::ApiPackageVersionDocs;
+  use crate::api::ApiPackageVersionIntegrity;
+  use crate::api::ApiPackageVersionPublishInfo;
   use crate::api::ApiPackageVersionSource;
+  use crate::api::ApiPackageVersionSymbolDocs;
+  use crate::api::ApiPackageVersionWithUser;
+  use crate::api::ApiPublishedSpecifier;
+  use crate::api::ApiPublishedSpecifierKind;
+  use crate::api::ApiPublishingTask;
   use crate::api::ApiSource;
   use crate::api::ApiSourceDirEntry;
   use crate::api::ApiSourceDirEntryKind;
+  use crate::api::ApiStats;
+  use crate::api::ApiTypeScriptCompatibility;
+  use crate::api::ApiTypeScriptCompatibilityStatus;
+  use crate::api::ApiValidationLevel;
+  use crate::api::ApiYankReasonCategory;
   use crate::db::CreatePackageResult;
   use crate::db::CreatePublishingTaskResult;
   use crate::db::ExportsMap;
@@ -2209,9 +3979,11 @@ mod test {
   use crate::publish::tests::create_mock_tarball;
   use crate::publish::tests::process_tarball_setup;
   use crate::publish::tests::process_tarball_setup2;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
   use crate::token::create_token;
-  use crate::util::test::ApiResultExt;
-  use crate::util::test::TestSetup;
+  use crate::typescript::TypeScriptFeature;
+  use crate::typescript::TypeScriptVersion;
 
   #[tokio::test]
   async fn test_packages_list() {
@@ -2235,11 +4007,29 @@ mod test {
             id: i % 10,
             owner: "foo",
             name: "bar",
+            subdirectory: None,
           },
         )
         .await
         .unwrap();
       assert!(matches!(res, CreatePackageResult::Ok(_)));
+      t.ephemeral_database
+        .create_package_version_for_test(NewPackageVersion {
+          scope: &scope,
+          name: &name,
+          version: &"1.0.0".try_into().unwrap(),
+          user_id: None,
+          readme_path: None,
+          uses_npm: false,
+          exports: &ExportsMap::mock(),
+          bin: &Default::default(),
+          engines: &Default::default(),
+          keywords: &[],
+          license: None,
+          meta: Default::default(),
+        })
+        .await
+        .unwrap();
     }
 
     let mut resp = t.http().get("/api/packages").call().await.unwrap();
@@ -2284,6 +4074,32 @@ mod test {
     let packages: ApiList<ApiPackage> = resp.expect_ok().await;
     assert_eq!(packages.items.len(), 23);
 
+    let mut resp = t
+      .http()
+      .get("/api/packages?limit=100&page=1")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(
+      resp.headers()["link"],
+      "</api/packages?limit=100&page=2>; rel=\"next\""
+    );
+    let packages: serde_json::Value = resp.expect_ok().await;
+    assert_eq!(packages["hasMore"], true);
+
+    let mut resp = t
+      .http()
+      .get("/api/packages?limit=100&page=2")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(
+      resp.headers()["link"],
+      "</api/packages?limit=100&page=1>; rel=\"prev\""
+    );
+    let packages: serde_json::Value = resp.expect_ok().await;
+    assert_eq!(packages["hasMore"], false);
+
     let mut resp = t
       .http()
       .get("/api/packages?query=bar")
@@ -2398,29 +4214,264 @@ mod test {
       .expect_err_code(StatusCode::BAD_REQUEST, "packageNameNotAllowed")
       .await;
 
-    // create scope2 for user2, try creating a package with user1
-    let scope2 = ScopeName::new("scope2".into()).unwrap();
-    t.db().create_scope(&scope2, t.user2.user.id).await.unwrap();
+    // create scope2 for user2, try creating a package with user1
+    let scope2 = ScopeName::new("scope2".into()).unwrap();
+    t.db().create_scope(&scope2, t.user2.user.id).await.unwrap();
+    let mut resp = t
+      .http()
+      .post("/api/scopes/scope2/packages")
+      .body_json(json!({
+        "package": "foo"
+      }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn test_packages_get() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+
+    let name = PackageName::try_from("foo").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package(&scope, &name)
+      .await
+      .unwrap();
+    assert!(matches!(res, CreatePackageResult::Ok(_)));
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo")
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert_eq!(package.name, name);
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo2")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope2/packages/foo")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope__asd/packages/foo")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn test_package_versions_list() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+
+    let name = PackageName::try_from("foo").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package(&scope, &name)
+      .await
+      .unwrap();
+    assert!(matches!(res, CreatePackageResult::Ok(_)));
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions")
+      .call()
+      .await
+      .unwrap();
+    let versions: ApiList<ApiPackageVersionWithUser> = resp.expect_ok().await;
+    assert!(versions.items.is_empty());
+
+    t.ephemeral_database
+      .create_package_version_for_test(NewPackageVersion {
+        scope: &scope,
+        name: &name,
+        version: &"1.0.0".try_into().unwrap(),
+        user_id: None,
+        readme_path: None,
+        uses_npm: false,
+        exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
+        meta: Default::default(),
+      })
+      .await
+      .unwrap();
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions")
+      .call()
+      .await
+      .unwrap();
+    let versions: ApiList<ApiPackageVersionWithUser> = resp.expect_ok().await;
+    assert_eq!(versions.items.len(), 1);
+    assert_eq!(versions.items[0].version.to_string(), "1.0.0");
+
+    for version in ["1.1.0-rc.1", "1.1.0"] {
+      t.ephemeral_database
+        .create_package_version_for_test(NewPackageVersion {
+          scope: &scope,
+          name: &name,
+          version: &version.try_into().unwrap(),
+          user_id: None,
+          readme_path: None,
+          uses_npm: false,
+          exports: &ExportsMap::mock(),
+          bin: &Default::default(),
+          engines: &Default::default(),
+          keywords: &[],
+          license: None,
+          meta: Default::default(),
+        })
+        .await
+        .unwrap();
+    }
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions?limit=2")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(
+      resp.headers()["link"],
+      "</api/scopes/scope/packages/foo/versions?limit=2&page=2>; rel=\"next\""
+    );
+    let versions: ApiList<ApiPackageVersionWithUser> = resp.expect_ok().await;
+    assert_eq!(versions.total, 3);
+    assert_eq!(versions.items.len(), 2);
+
+    let mut resp = t
+      .http()
+      .get(
+        "/api/scopes/scope/packages/foo/versions?prerelease=false&compact=true",
+      )
+      .call()
+      .await
+      .unwrap();
+    let versions: serde_json::Value = resp.expect_ok().await;
+    assert_eq!(versions["total"], 2);
+    assert_eq!(versions["items"][0]["version"], "1.1.0");
+    assert!(versions["items"][0].get("user").is_none());
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions?since=2000-01-01T00:00:00Z")
+      .call()
+      .await
+      .unwrap();
+    let versions: ApiList<ApiPackageVersionWithUser> = resp.expect_ok().await;
+    assert_eq!(versions.total, 3);
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions?since=yesterday")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo2/versions")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn test_package_version() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    let name = PackageName::try_from("foo").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package(&scope, &name)
+      .await
+      .unwrap();
+    assert!(matches!(res, CreatePackageResult::Ok(_)));
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+
+    let version = Version::new("1.2.3").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package_version_for_test(NewPackageVersion {
+        scope: &scope,
+        name: &name,
+        version: &version,
+        user_id: None,
+        readme_path: None,
+        uses_npm: false,
+        exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
+        meta: Default::default(),
+      })
+      .await
+      .unwrap();
+
     let mut resp = t
       .http()
-      .post("/api/scopes/scope2/packages")
-      .body_json(json!({
-        "package": "foo"
-      }))
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3")
       .call()
       .await
       .unwrap();
-    resp
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
-      .await;
+    let version: ApiPackageVersion = resp.expect_ok().await;
+    assert_eq!(version.version, res.version);
+    assert_eq!(version.uses_npm, res.uses_npm);
   }
 
   #[tokio::test]
-  async fn test_packages_get() {
+  async fn test_package_version_yank_reason() {
     let mut t = TestSetup::new().await;
 
     let scope = t.scope.scope.clone();
-
     let name = PackageName::try_from("foo").unwrap();
     let res = t
       .ephemeral_database
@@ -2429,106 +4480,103 @@ mod test {
       .unwrap();
     assert!(matches!(res, CreatePackageResult::Ok(_)));
 
-    let mut resp = t
-      .http()
-      .get("/api/scopes/scope/packages/foo")
-      .call()
+    let version = Version::new("1.2.3").unwrap();
+    t.ephemeral_database
+      .create_package_version_for_test(NewPackageVersion {
+        scope: &scope,
+        name: &name,
+        version: &version,
+        user_id: None,
+        readme_path: None,
+        uses_npm: false,
+        exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
+        meta: Default::default(),
+      })
       .await
       .unwrap();
-    let package: ApiPackage = resp.expect_ok().await;
-    assert_eq!(package.name, name);
 
+    // 1.2.3 is the latest version, so yanking it has to be forced.
     let mut resp = t
       .http()
-      .get("/api/scopes/scope/packages/foo2")
+      .patch("/api/scopes/scope/packages/foo/versions/1.2.3")
+      .body_json(json!({ "yanked": true }))
       .call()
       .await
       .unwrap();
     resp
-      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .expect_err_code(StatusCode::CONFLICT, "packageVersionIsLatest")
       .await;
 
     let mut resp = t
       .http()
-      .get("/api/scopes/scope2/packages/foo")
+      .patch("/api/scopes/scope/packages/foo/versions/1.2.3")
+      .body_json(json!({
+        "yanked": true,
+        "yankReasonCategory": "security",
+        "yankReason": "  CVE-2024-0001  ",
+        "force": true,
+      }))
       .call()
       .await
       .unwrap();
-    resp
-      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
-      .await;
+    resp.expect_ok_no_content().await;
 
     let mut resp = t
       .http()
-      .get("/api/scopes/scope__asd/packages/foo")
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3")
       .call()
       .await
       .unwrap();
-    resp
-      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
-      .await;
-  }
-
-  #[tokio::test]
-  async fn test_package_versions_list() {
-    let mut t = TestSetup::new().await;
-
-    let scope = t.scope.scope.clone();
-
-    let name = PackageName::try_from("foo").unwrap();
-    let res = t
-      .ephemeral_database
-      .create_package(&scope, &name)
-      .await
-      .unwrap();
-    assert!(matches!(res, CreatePackageResult::Ok(_)));
+    let version: ApiPackageVersion = resp.expect_ok().await;
+    assert!(version.yanked);
+    assert_eq!(
+      version.yank_reason_category,
+      Some(ApiYankReasonCategory::Security)
+    );
+    assert_eq!(version.yank_reason.as_deref(), Some("CVE-2024-0001"));
 
     let mut resp = t
       .http()
-      .get("/api/scopes/scope/packages/foo/versions")
+      .patch("/api/scopes/scope/packages/foo/versions/1.2.3")
+      .body_json(json!({
+        "yanked": true,
+        "yankReason": "a".repeat(1001),
+      }))
       .call()
       .await
       .unwrap();
-    let versions: Vec<ApiPackageVersion> = resp.expect_ok().await;
-    assert!(versions.is_empty());
-
-    t.ephemeral_database
-      .create_package_version_for_test(NewPackageVersion {
-        scope: &scope,
-        name: &name,
-        version: &"1.0.0".try_into().unwrap(),
-        user_id: None,
-        readme_path: None,
-        uses_npm: false,
-        exports: &ExportsMap::mock(),
-        meta: Default::default(),
-      })
-      .await
-      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "yankReasonTooLong")
+      .await;
 
+    // unyanking clears the reason
     let mut resp = t
       .http()
-      .get("/api/scopes/scope/packages/foo/versions")
+      .patch("/api/scopes/scope/packages/foo/versions/1.2.3")
+      .body_json(json!({ "yanked": false }))
       .call()
       .await
       .unwrap();
-    let versions: Vec<ApiPackageVersion> = resp.expect_ok().await;
-    assert_eq!(versions.len(), 1);
-    assert_eq!(versions[0].version.to_string(), "1.0.0");
+    resp.expect_ok_no_content().await;
 
     let mut resp = t
       .http()
-      .get("/api/scopes/scope/packages/foo2/versions")
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3")
       .call()
       .await
       .unwrap();
-    resp
-      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
-      .await;
+    let version: ApiPackageVersion = resp.expect_ok().await;
+    assert!(!version.yanked);
+    assert_eq!(version.yank_reason_category, None);
+    assert_eq!(version.yank_reason, None);
   }
 
   #[tokio::test]
-  async fn test_package_version() {
+  async fn test_package_version_bulk_yank() {
     let mut t = TestSetup::new().await;
 
     let scope = t.scope.scope.clone();
@@ -2540,41 +4588,133 @@ mod test {
       .unwrap();
     assert!(matches!(res, CreatePackageResult::Ok(_)));
 
-    let mut resp = t
+    for version in ["1.0.0", "1.1.0", "1.2.0", "2.0.0"] {
+      let version = Version::new(version).unwrap();
+      t.ephemeral_database
+        .create_package_version_for_test(NewPackageVersion {
+          scope: &scope,
+          name: &name,
+          version: &version,
+          user_id: None,
+          readme_path: None,
+          uses_npm: false,
+          exports: &ExportsMap::mock(),
+          bin: &Default::default(),
+          engines: &Default::default(),
+          keywords: &[],
+          license: None,
+          meta: Default::default(),
+        })
+        .await
+        .unwrap();
+    }
+
+    let path = "/api/scopes/scope/packages/foo/versions/bulk_yank";
+    let res: ApiBulkYankPackageVersionsResponse = t
       .http()
-      .get("/api/scopes/scope/packages/foo/versions/1.2.3")
+      .post(path)
+      .body_json(json!({
+        "yanked": true,
+        "range": ">=1.1.0 <2.0.0",
+        "yankReasonCategory": "security",
+        "yankReason": "CVE-2024-0001",
+      }))
       .call()
       .await
-      .unwrap();
-    resp
-      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .unwrap()
+      .expect_ok()
       .await;
+    assert_eq!(res.versions.len(), 2);
 
-    let version = Version::new("1.2.3").unwrap();
-    let res = t
+    let versions = t
       .ephemeral_database
-      .create_package_version_for_test(NewPackageVersion {
-        scope: &scope,
-        name: &name,
-        version: &version,
-        user_id: None,
-        readme_path: None,
-        uses_npm: false,
-        exports: &ExportsMap::mock(),
-        meta: Default::default(),
-      })
+      .list_package_versions(&scope, &name)
       .await
       .unwrap();
+    let yanked = |v: &str| {
+      versions
+        .iter()
+        .find(|(version, _)| version.version.to_string() == v)
+        .unwrap()
+        .0
+        .is_yanked
+    };
+    assert!(!yanked("1.0.0"));
+    assert!(yanked("1.1.0"));
+    assert!(yanked("1.2.0"));
+    assert!(!yanked("2.0.0"));
 
-    let mut resp = t
+    let res: ApiBulkYankPackageVersionsResponse = t
       .http()
-      .get("/api/scopes/scope/packages/foo/versions/1.2.3")
+      .post(path)
+      .body_json(json!({
+        "yanked": false,
+        "versions": ["1.1.0", "1.2.0"],
+      }))
       .call()
       .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(res.versions.len(), 2);
+    let versions = t
+      .ephemeral_database
+      .list_package_versions(&scope, &name)
+      .await
       .unwrap();
-    let version: ApiPackageVersion = resp.expect_ok().await;
-    assert_eq!(version.version, res.version);
-    assert_eq!(version.uses_npm, res.uses_npm);
+    assert!(versions.iter().all(|(version, _)| !version.is_yanked));
+
+    t.http()
+      .post(path)
+      .body_json(json!({ "yanked": true, "range": "^2.0.0" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::CONFLICT, "packageVersionIsLatest")
+      .await;
+    let res: ApiBulkYankPackageVersionsResponse = t
+      .http()
+      .post(path)
+      .body_json(json!({ "yanked": true, "range": "^2.0.0", "force": true }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(res.versions.len(), 1);
+
+    t.http()
+      .post(path)
+      .body_json(json!({ "yanked": true, "versions": ["3.0.0"] }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+
+    t.http()
+      .post(path)
+      .body_json(json!({
+        "yanked": true,
+        "range": "^1.0.0",
+        "versions": ["1.0.0"],
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    let token = t.user3.token.clone();
+    t.http()
+      .post(path)
+      .token(Some(&token))
+      .body_json(json!({ "yanked": true, "range": "*" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
   }
 
   #[tokio::test]
@@ -2603,6 +4743,10 @@ mod test {
         readme_path: None,
         uses_npm: false,
         exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
         meta: Default::default(),
       })
       .await
@@ -2709,6 +4853,19 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
     let score: ApiPackageScore = resp.expect_ok().await;
     assert!(score.has_provenance);
 
+    // Certificate from a provider that is not accepted.
+    let gitlab_only = ProvenancePolicies(vec![ProvenancePolicy::new(
+      ProvenanceProvider::GitlabCi,
+    )]);
+    let bundle_copy =
+      serde_json::from_value(serde_json::to_value(&bundle).unwrap()).unwrap();
+    let res = verify(
+      format!("pkg:jsr/@{}/{}@1.0.0", scope, name),
+      bundle_copy,
+      &gitlab_only,
+    );
+    assert!(res.is_err());
+
     // Invalid subject.
     update_bundle_subject(
       &mut bundle,
@@ -2772,6 +4929,10 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
         readme_path: None,
         uses_npm: false,
         exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
         meta: Default::default(),
       })
       .await
@@ -2786,6 +4947,10 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
         readme_path: None,
         uses_npm: false,
         exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
         meta: Default::default(),
       })
       .await
@@ -2800,6 +4965,10 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
         readme_path: None,
         uses_npm: false,
         exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
         meta: Default::default(),
       })
       .await
@@ -3003,6 +5172,60 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
       .await;
   }
 
+  #[tokio::test]
+  async fn update_package_validation_level() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+
+    let name = PackageName::try_from("foo").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package(&scope, &name)
+      .await
+      .unwrap();
+    assert!(matches!(res, CreatePackageResult::Ok(_)));
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo")
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert_eq!(package.validation_level, ApiValidationLevel::Full);
+
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .body_json(json!({ "validationLevel": "lint_only" }))
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert_eq!(package.validation_level, ApiValidationLevel::LintOnly);
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo")
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert_eq!(package.validation_level, ApiValidationLevel::LintOnly);
+
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .body_json(json!({ "validationLevel": "strict" }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+  }
+
   #[tokio::test]
   async fn update_package_is_featured() {
     let mut t = TestSetup::new().await;
@@ -3147,6 +5370,11 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
           package_version: &Version::new(&format!("0.0.{i}")).unwrap(),
           config_file: &config_file,
           user_id: None,
+          github_repository_id: None,
+          github_workflow_run: None,
+          bypass_publish_freezes: false,
+          publish_at: None,
+          idempotency_key: None,
         })
         .await
         .unwrap();
@@ -3180,6 +5408,7 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
     let permission =
       Permission::PackagePublish(PackagePublishPermission::Scope {
         scope: ScopeName::new("otherscope".to_owned()).unwrap(),
+        bypass_publish_freezes: false,
       });
 
     let token = create_token(
@@ -3193,29 +5422,206 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
     .await
     .unwrap();
 
-    let scope = t.scope.scope.clone();
+    let scope = t.scope.scope.clone();
+
+    let name = PackageName::new("foo".to_owned()).unwrap();
+
+    let CreatePackageResult::Ok(_) =
+      t.db().create_package(&scope, &name).await.unwrap()
+    else {
+      unreachable!();
+    };
+
+    let data = create_mock_tarball("ok");
+    let mut resp = t
+      .http()
+      .post("/api/scopes/scope/packages/foo/versions/1.2.3?config=/jsr.json")
+      .gzip()
+      .token(Some(&token))
+      .body(Body::from(data))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "missingPermission")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn test_publishing_idempotency_key() {
+    let mut t = TestSetup::new().await;
+
+    let name = PackageName::new("foo".to_owned()).unwrap();
+    let CreatePackageResult::Ok(_) =
+      t.db().create_package(&t.scope.scope, &name).await.unwrap()
+    else {
+      unreachable!();
+    };
+
+    async fn publish(
+      t: &mut TestSetup,
+      idempotency_key: Option<&'static str>,
+    ) -> hyper::Response<Body> {
+      let mut http = t.http();
+      let mut req = http
+        .post("/api/scopes/scope/packages/foo/versions/1.2.3?config=/jsr.json")
+        .gzip()
+        .body(Body::from(create_mock_tarball("ok")));
+      if let Some(key) = idempotency_key {
+        req = req.header(
+          hyper::header::HeaderName::from_static("idempotency-key"),
+          hyper::header::HeaderValue::from_static(key),
+        );
+      }
+      req.call().await.unwrap()
+    }
+
+    let task: ApiPublishingTask =
+      publish(&mut t, Some("ci-run-1")).await.expect_ok().await;
+
+    // a retry with the same key gets the original task back
+    let retried: ApiPublishingTask =
+      publish(&mut t, Some("ci-run-1")).await.expect_ok().await;
+    assert_eq!(retried.id, task.id);
+
+    // other publishes of the version are still rejected as duplicates
+    publish(&mut t, Some("ci-run-2"))
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "duplicateVersionPublish")
+      .await;
+    publish(&mut t, None)
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "duplicateVersionPublish")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn test_publishing_tarball_sha256() {
+    let mut t = TestSetup::new().await;
 
     let name = PackageName::new("foo".to_owned()).unwrap();
-
     let CreatePackageResult::Ok(_) =
-      t.db().create_package(&scope, &name).await.unwrap()
+      t.db().create_package(&t.scope.scope, &name).await.unwrap()
     else {
       unreachable!();
     };
 
-    let data = create_mock_tarball("ok");
-    let mut resp = t
-      .http()
-      .post("/api/scopes/scope/packages/foo/versions/1.2.3?config=/jsr.json")
-      .gzip()
-      .token(Some(&token))
-      .body(Body::from(data))
-      .call()
+    let tarball = create_mock_tarball("ok");
+    let digest = format!("{:x}", sha2::Sha256::digest(&tarball));
+
+    async fn publish(
+      t: &mut TestSetup,
+      version: &str,
+      tarball: bytes::Bytes,
+      digest: &str,
+    ) -> hyper::Response<Body> {
+      t.http()
+        .post(format!(
+          "/api/scopes/scope/packages/foo/versions/{version}?config=/jsr.json"
+        ))
+        .gzip()
+        .header(
+          hyper::header::HeaderName::from_static("x-tarball-sha256"),
+          hyper::header::HeaderValue::from_str(digest).unwrap(),
+        )
+        .body(Body::from(tarball))
+        .call()
+        .await
+        .unwrap()
+    }
+
+    publish(&mut t, "1.2.3", tarball.clone(), "not-a-digest")
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    let wrong_digest = format!("{:x}", sha2::Sha256::digest(b"other"));
+    publish(&mut t, "1.2.3", tarball.clone(), &wrong_digest)
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "tarballDigestMismatch")
+      .await;
+    let tasks = t
+      .db()
+      .list_publishing_tasks_for_package(&t.scope.scope, &name)
       .await
       .unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].status, PublishingTaskStatus::Failure);
+
+    let task: ApiPublishingTask =
+      publish(&mut t, "1.2.4", tarball, &digest.to_uppercase())
+        .await
+        .expect_ok()
+        .await;
+    assert_eq!(task.package_version.to_string(), "1.2.4");
+  }
+
+  #[tokio::test]
+  async fn test_package_docs_landing_page() {
+    let mut t = TestSetup::new().await;
+
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("docs_landing_page")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    async fn index_main(t: &mut TestSetup) -> String {
+      let mut resp = t
+        .http()
+        .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs")
+        .call()
+        .await
+        .unwrap();
+      match resp.expect_ok::<ApiPackageVersionDocs>().await {
+        ApiPackageVersionDocs::Content { main, .. } => main,
+        ApiPackageVersionDocs::Redirect { .. } => panic!(),
+      }
+    }
+
+    async fn set_landing_page(
+      t: &mut TestSetup,
+      page: serde_json::Value,
+    ) -> hyper::Response<Body> {
+      t.http()
+        .patch("/api/scopes/scope/packages/foo")
+        .body_json(json!({ "docsLandingPage": page }))
+        .call()
+        .await
+        .unwrap()
+    }
+
+    let main = index_main(&mut t).await;
+    assert!(main.contains("Readme content"), "{main}");
+
+    // A markdown file.
+    let mut resp = set_landing_page(&mut t, json!("/guide.md")).await;
+    let package: ApiPackage = resp.expect_ok().await;
+    assert_eq!(package.docs_landing_page.as_deref(), Some("/guide.md"));
+    let main = index_main(&mut t).await;
+    assert!(main.contains("Getting started guide"), "{main}");
+    assert!(!main.contains("Readme content"), "{main}");
+
+    // An export.
+    let mut resp = set_landing_page(&mut t, json!("./cli")).await;
+    let _: ApiPackage = resp.expect_ok().await;
+    let main = index_main(&mut t).await;
+    assert!(main.contains("runCli"), "{main}");
+
+    // An export that this version does not have falls back to the README.
+    let mut resp = set_landing_page(&mut t, json!("./missing")).await;
+    let _: ApiPackage = resp.expect_ok().await;
+    let main = index_main(&mut t).await;
+    assert!(main.contains("Readme content"), "{main}");
+
+    // Invalid landing pages are rejected.
+    let mut resp = set_landing_page(&mut t, json!("/mod.ts")).await;
     resp
-      .expect_err_code(StatusCode::FORBIDDEN, "missingPermission")
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
       .await;
+
+    // Unsetting the landing page.
+    let mut resp = set_landing_page(&mut t, json!(null)).await;
+    let package: ApiPackage = resp.expect_ok().await;
+    assert!(package.docs_landing_page.is_none());
   }
 
   #[tokio::test]
@@ -3298,97 +5704,415 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
       .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs?symbol=hello")
       .call()
       .await
-      .unwrap();
-    let docs: ApiPackageVersionDocs = resp.expect_ok().await;
-    match docs {
-      ApiPackageVersionDocs::Content {
-        version,
-        css,
-        comrak_css: _,
-        script: _,
-        breadcrumbs,
-        toc,
-        main: _,
-      } => {
-        assert_eq!(version.version, task.package_version);
-        assert!(css.contains("{max-width:"), "{}", css);
-        assert!(
-          breadcrumbs.as_ref().unwrap().contains("hello"),
-          "{:?}",
-          breadcrumbs
-        );
-        assert!(toc.is_some(), "{:?}", toc);
-      }
-      ApiPackageVersionDocs::Redirect { .. } => panic!(),
-    }
+      .unwrap();
+    let docs: ApiPackageVersionDocs = resp.expect_ok().await;
+    match docs {
+      ApiPackageVersionDocs::Content {
+        version,
+        css,
+        comrak_css: _,
+        script: _,
+        breadcrumbs,
+        toc,
+        main: _,
+      } => {
+        assert_eq!(version.version, task.package_version);
+        assert!(css.contains("{max-width:"), "{}", css);
+        assert!(
+          breadcrumbs.as_ref().unwrap().contains("hello"),
+          "{:?}",
+          breadcrumbs
+        );
+        assert!(toc.is_some(), "{:?}", toc);
+      }
+      ApiPackageVersionDocs::Redirect { .. } => panic!(),
+    }
+
+    // symbol page
+    let mut resp = t
+      .http()
+      .get(format!(
+        "/api/scopes/scope/packages/foo/versions/1.2.3/docs?symbol={}",
+        urlencoding::encode("读取多键1")
+      ))
+      .call()
+      .await
+      .unwrap();
+    let docs: ApiPackageVersionDocs = resp.expect_ok().await;
+    match docs {
+      ApiPackageVersionDocs::Content {
+        version,
+        css,
+        comrak_css: _,
+        script: _,
+        breadcrumbs,
+        toc,
+        main: _,
+      } => {
+        assert_eq!(version.version, task.package_version);
+        assert!(css.contains("{max-width:"), "{}", css);
+        assert!(
+          breadcrumbs.as_ref().unwrap().contains("读取多键1"),
+          "{:?}",
+          breadcrumbs
+        );
+        assert!(toc.is_some(), "{:?}", toc);
+      }
+      ApiPackageVersionDocs::Redirect { .. } => panic!(),
+    }
+
+    // search
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs/search")
+      .call()
+      .await
+      .unwrap();
+    let search: serde_json::Value = resp.expect_ok().await;
+    assert_eq!(
+      search,
+      json!({"nodes":[{"kind":[{"kind":"Variable","char":"v","title":"Variable"}],"name":"hello","file":".","doc":"This is a test constant.","url":"/@scope/foo@1.2.3/doc/~/hello","deprecated":false},{"kind":[{"kind":"Variable","char":"v","title":"Variable"}],"name":"读取多键1","file":".","doc":"","url":"/@scope/foo@1.2.3/doc/~/读取多键1","deprecated":false}]}),
+    );
+
+    // embeddable symbol docs
+    let resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs/symbol/hello")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+      resp.headers().get("content-type").unwrap(),
+      "text/html; charset=utf-8"
+    );
+    let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+    assert!(std::str::from_utf8(&body).unwrap().contains("hello"));
+
+    let mut resp = t
+      .http()
+      .get(
+        "/api/scopes/scope/packages/foo/versions/1.2.3/docs/symbol/hello?format=json",
+      )
+      .call()
+      .await
+      .unwrap();
+    let docs: ApiPackageVersionSymbolDocs = resp.expect_ok().await;
+    assert_eq!(docs.symbol, "hello");
+    assert!(docs.html.contains("This is a test constant."), "{}", docs.html);
+    assert_eq!(docs.nodes.len(), 1, "{:?}", docs.nodes);
+    assert_eq!(docs.nodes[0]["kind"], "variable");
+    assert_eq!(
+      docs.nodes[0]["jsDoc"]["doc"],
+      "This is a test constant.",
+      "{:?}",
+      docs.nodes[0]
+    );
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs/symbol/asdf")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "entrypointOrSymbolNotFound")
+      .await;
+
+    // symbol doesn't exist
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs?symbol=asdf")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "entrypointOrSymbolNotFound")
+      .await;
+
+    // entrypoint doesn't exist
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs?entrypoint=asdf")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "entrypointOrSymbolNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn test_package_entrypoints() {
+    let mut t = TestSetup::new().await;
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/entrypoints")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("entrypoints")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/entrypoints")
+      .call()
+      .await
+      .unwrap();
+    let entrypoints: Vec<ApiEntrypoint> = resp.expect_ok().await;
+    assert_eq!(
+      entrypoints,
+      vec![
+        ApiEntrypoint {
+          name: ".".to_string(),
+          module_count: 2,
+          total_bytes: 235,
+          external_dependency_count: 0,
+          top_level_await: false,
+        },
+        ApiEntrypoint {
+          name: "./cli".to_string(),
+          module_count: 3,
+          total_bytes: 384,
+          external_dependency_count: 1,
+          top_level_await: true,
+        },
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_package_integrity() {
+    let mut t = TestSetup::new().await;
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/integrity")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/integrity")
+      .call()
+      .await
+      .unwrap();
+    let integrity: ApiPackageVersionIntegrity = resp.expect_ok().await;
+    assert_eq!(integrity.version.to_string(), "1.2.3");
+
+    let files = t
+      .db()
+      .list_package_files(
+        &integrity.scope,
+        &integrity.package,
+        &integrity.version,
+      )
+      .await
+      .unwrap();
+    assert_eq!(integrity.files.len(), files.len());
+    for file in files {
+      let checksum = integrity.files.get(&file.path.to_string()).unwrap();
+      assert!(checksum.as_ref().unwrap().starts_with("sha256-"));
+      assert_eq!(checksum, &file.checksum);
+    }
+
+    // The manifest hash can be recomputed from the files.
+    let manifest = serde_json::to_vec(&integrity.files).unwrap();
+    assert_eq!(
+      integrity.manifest_hash,
+      format!("sha256-{:x}", sha2::Sha256::digest(&manifest))
+    );
+  }
+
+  #[tokio::test]
+  async fn test_publish_info() {
+    let mut t = TestSetup::new().await;
+
+    t.http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/publish_info")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let info = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/publish_info")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPackageVersionPublishInfo>()
+      .await;
+    assert_eq!(info.publishing_task_id, task.id);
+    assert_eq!(info.user.unwrap().id, t.user1.user.id);
+    assert!(info.github_repository.is_none());
+    assert!(info.github_workflow_run.is_none());
+    assert!(info.provenance.is_none());
+    assert!(info.meta_url.ends_with("/@scope/foo/1.2.3_meta.json"));
+    assert_eq!(info.queued_at, task.created_at);
+    assert_eq!(info.completed_at, task.updated_at);
+    assert!(info.duration_ms >= 0);
+  }
+
+  #[tokio::test]
+  async fn test_specifiers() {
+    let mut t = TestSetup::new().await;
+
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("peer_dependencies")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let specifiers = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/specifiers")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Vec<ApiPublishedSpecifier>>()
+      .await;
+    assert_eq!(specifiers.len(), 2, "{specifiers:?}");
+    assert_eq!(specifiers[0].referrer, "/mod.ts");
+    assert_eq!(specifiers[0].specifier, "npm:chalk@5");
+    assert_eq!(specifiers[0].kind, ApiPublishedSpecifierKind::Npm);
+    assert!(specifiers[0].resolved.starts_with("npm:"));
+    assert_eq!(specifiers[1].specifier, "npm:express@4");
+
+    t.http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.4/specifiers")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+  }
 
-    // symbol page
-    let mut resp = t
+  #[tokio::test]
+  async fn test_deprecated_imports() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let task = process_tarball_setup2(
+      &t,
+      create_mock_tarball("depends_on_ok"),
+      &PackageName::try_from("bar").unwrap(),
+      &Version::try_from("1.2.3").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let specifiers = t
       .http()
-      .get(format!(
-        "/api/scopes/scope/packages/foo/versions/1.2.3/docs?symbol={}",
-        urlencoding::encode("读取多键1")
-      ))
+      .get("/api/scopes/scope/packages/bar/versions/1.2.3/specifiers")
       .call()
       .await
+      .unwrap()
+      .expect_ok::<Vec<ApiPublishedSpecifier>>()
+      .await;
+    let foo = specifiers
+      .iter()
+      .find(|specifier| specifier.specifier == "jsr:@scope/foo@1")
       .unwrap();
-    let docs: ApiPackageVersionDocs = resp.expect_ok().await;
-    match docs {
-      ApiPackageVersionDocs::Content {
-        version,
-        css,
-        comrak_css: _,
-        script: _,
-        breadcrumbs,
-        toc,
-        main: _,
-      } => {
-        assert_eq!(version.version, task.package_version);
-        assert!(css.contains("{max-width:"), "{}", css);
-        assert!(
-          breadcrumbs.as_ref().unwrap().contains("读取多键1"),
-          "{:?}",
-          breadcrumbs
-        );
-        assert!(toc.is_some(), "{:?}", toc);
-      }
-      ApiPackageVersionDocs::Redirect { .. } => panic!(),
-    }
+    assert_eq!(foo.imported_names, vec!["hello".to_string()]);
 
-    // search
-    let mut resp = t
+    let path =
+      "/api/scopes/scope/packages/bar/versions/1.2.3/deprecated_imports";
+    let deprecated_imports = t
       .http()
-      .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs/search")
+      .get(path)
       .call()
       .await
-      .unwrap();
-    let search: serde_json::Value = resp.expect_ok().await;
-    assert_eq!(
-      search,
-      json!({"nodes":[{"kind":[{"kind":"Variable","char":"v","title":"Variable"}],"name":"hello","file":".","doc":"This is a test constant.","url":"/@scope/foo@1.2.3/doc/~/hello","deprecated":false},{"kind":[{"kind":"Variable","char":"v","title":"Variable"}],"name":"读取多键1","file":".","doc":"","url":"/@scope/foo@1.2.3/doc/~/读取多键1","deprecated":false}]}),
-    );
+      .unwrap()
+      .expect_ok::<Vec<ApiDeprecatedImport>>()
+      .await;
+    assert!(deprecated_imports.is_empty(), "{deprecated_imports:?}");
 
-    // symbol doesn't exist
-    let mut resp = t
+    let task = process_tarball_setup2(
+      &t,
+      create_mock_tarball("deprecated_exports"),
+      &PackageName::try_from("foo").unwrap(),
+      &Version::try_from("1.3.0").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let deprecated_imports = t
       .http()
-      .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs?symbol=asdf")
+      .get(path)
       .call()
       .await
-      .unwrap();
-    resp
-      .expect_err_code(StatusCode::NOT_FOUND, "entrypointOrSymbolNotFound")
+      .unwrap()
+      .expect_ok::<Vec<ApiDeprecatedImport>>()
       .await;
+    assert_eq!(deprecated_imports.len(), 1, "{deprecated_imports:?}");
+    assert_eq!(deprecated_imports[0].package.to_string(), "foo");
+    assert_eq!(deprecated_imports[0].version.to_string(), "1.3.0");
+    assert_eq!(deprecated_imports[0].export, ".");
+    assert_eq!(deprecated_imports[0].name, "hello");
+    assert!(deprecated_imports[0]
+      .message
+      .as_ref()
+      .is_some_and(|message| message.starts_with("Use")));
+    assert_eq!(deprecated_imports[0].referrers, vec!["/mod.ts".to_string()]);
+  }
+
+  #[tokio::test]
+  async fn test_package_typescript_compatibility() {
+    let mut t = TestSetup::new().await;
+
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("typescript_features"))
+        .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
 
-    // entrypoint doesn't exist
     let mut resp = t
       .http()
-      .get("/api/scopes/scope/packages/foo/versions/1.2.3/docs?entrypoint=asdf")
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3/typescript")
       .call()
       .await
       .unwrap();
-    resp
-      .expect_err_code(StatusCode::NOT_FOUND, "entrypointOrSymbolNotFound")
-      .await;
+    let compatibility: Vec<ApiTypeScriptCompatibility> = resp.expect_ok().await;
+    assert_eq!(
+      compatibility,
+      vec![
+        ApiTypeScriptCompatibility {
+          version: TypeScriptVersion::new(4, 7),
+          status: ApiTypeScriptCompatibilityStatus::Incompatible,
+          unsupported_features: vec![TypeScriptFeature::ConstTypeParameters],
+        },
+        ApiTypeScriptCompatibility {
+          version: TypeScriptVersion::new(5, 0),
+          status: ApiTypeScriptCompatibilityStatus::Compatible,
+          unsupported_features: vec![],
+        },
+        ApiTypeScriptCompatibility {
+          version: TypeScriptVersion::new(5, 6),
+          status: ApiTypeScriptCompatibilityStatus::Compatible,
+          unsupported_features: vec![],
+        },
+      ]
+    );
   }
 
   #[tokio::test]
@@ -3462,15 +6186,19 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
       vec![
         ApiDependency {
           kind: ApiDependencyKind::Jsr,
+          dependency_type: ApiDependencyType::Regular,
           name: "@scope/foo".to_string(),
           constraint: "1".to_string(),
-          path: "".to_string()
+          path: "".to_string(),
+          is_archived: false,
         },
         ApiDependency {
           kind: ApiDependencyKind::Npm,
+          dependency_type: ApiDependencyType::Regular,
           name: "express".to_string(),
           constraint: "4".to_string(),
-          path: "".to_string()
+          path: "".to_string(),
+          is_archived: false,
         },
       ],
     );
@@ -3568,47 +6296,299 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
 
     let mut resp = t
       .http()
-      .get("/api/scopes/scope/packages/foo/dependents?limit=1&page=2")
+      .get("/api/scopes/scope/packages/foo/dependents?limit=1&page=2")
+      .call()
+      .await
+      .unwrap();
+    let dependents: ApiList<ApiDependent> = resp.expect_ok().await;
+    assert_eq!(
+      dependents.items,
+      vec![ApiDependent {
+        scope: "scope".try_into().unwrap(),
+        package: "baz".try_into().unwrap(),
+        versions: vec!["1.2.3".try_into().unwrap()],
+        total_versions: 1,
+      }]
+    );
+    assert_eq!(dependents.total, 2);
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/dependents?versions_per_package_limit=1")
+      .call()
+      .await
+      .unwrap();
+    let dependents: ApiList<ApiDependent> = resp.expect_ok().await;
+    assert_eq!(
+      dependents.items,
+      vec![
+        ApiDependent {
+          scope: "scope".try_into().unwrap(),
+          package: "bar".try_into().unwrap(),
+          versions: vec!["1.2.3".try_into().unwrap(),],
+          total_versions: 2,
+        },
+        ApiDependent {
+          scope: "scope".try_into().unwrap(),
+          package: "baz".try_into().unwrap(),
+          versions: vec!["1.2.3".try_into().unwrap()],
+          total_versions: 1,
+        },
+      ],
+    );
+    assert_eq!(dependents.total, 2);
+
+    // The counts maintained on publish match a full recount.
+    let corrected =
+      t.db().reconcile_package_dependent_counts().await.unwrap();
+    assert_eq!(corrected, 0);
+
+    // A dependent is only removed from the count once none of its versions
+    // depend on the package anymore.
+    let scope = ScopeName::try_from("scope").unwrap();
+    let version = Version::try_from("1.2.3").unwrap();
+    for (package, expected_total) in [("bar", 2), ("baz", 1)] {
+      let package = PackageName::try_from(package).unwrap();
+      let task = t
+        .db()
+        .list_publishing_tasks_for_package(&scope, &package)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|task| task.package_version == version)
+        .unwrap();
+      t.db()
+        .delete_package_version(
+          &scope,
+          &package,
+          &version,
+          task.id,
+          "test",
+          t.staff_user.user.id,
+          chrono::Utc::now(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+      let mut resp = t
+        .http()
+        .get("/api/scopes/scope/packages/foo/dependents")
+        .call()
+        .await
+        .unwrap();
+      let dependents: ApiList<ApiDependent> = resp.expect_ok().await;
+      assert_eq!(dependents.total, expected_total);
+    }
+    let corrected = t.db().reconcile_package_dependent_counts().await.unwrap();
+    assert_eq!(corrected, 0);
+  }
+
+  #[test]
+  fn normalize_github_subdirectory() {
+    assert_eq!(super::normalize_github_subdirectory("").unwrap(), None);
+    assert_eq!(super::normalize_github_subdirectory("/").unwrap(), None);
+    assert_eq!(
+      super::normalize_github_subdirectory("/packages/foo/").unwrap(),
+      Some("packages/foo".to_string())
+    );
+    for invalid in ["packages//foo", "../foo", "packages/./foo", "a\\b"] {
+      assert!(
+        matches!(
+          super::normalize_github_subdirectory(invalid),
+          Err(crate::api::ApiError::GithubRepositorySubdirectoryInvalid)
+        ),
+        "{invalid}"
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn test_package_license_report() {
+    let mut t = TestSetup::new().await;
+
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("license_file")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let task = crate::publish::tests::process_tarball_setup2(
+      &t,
+      create_mock_tarball("depends_on_ok"),
+      &PackageName::try_from("bar").unwrap(),
+      &Version::try_from("1.2.3").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/bar/versions/1.2.3/licenses")
+      .call()
+      .await
+      .unwrap();
+    let report: ApiLicenseReport = resp.expect_ok().await;
+    assert_eq!(
+      report,
+      ApiLicenseReport {
+        packages: vec![
+          ApiLicenseReportPackage {
+            scope: ScopeName::try_from("scope").unwrap(),
+            package: PackageName::try_from("bar").unwrap(),
+            version: Version::try_from("1.2.3").unwrap(),
+            license: None,
+            classification: ApiLicenseClassification::Unknown,
+            flagged: true,
+            depth: 0,
+          },
+          ApiLicenseReportPackage {
+            scope: ScopeName::try_from("scope").unwrap(),
+            package: PackageName::try_from("foo").unwrap(),
+            version: Version::try_from("1.2.3").unwrap(),
+            license: Some("MIT".to_string()),
+            classification: ApiLicenseClassification::Permissive,
+            flagged: false,
+            depth: 1,
+          },
+        ],
+        unresolved: vec![ApiLicenseReportUnresolved {
+          kind: ApiDependencyKind::Npm,
+          name: "express".to_string(),
+          constraint: "4".to_string(),
+        }],
+        truncated: false,
+      }
+    );
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/bar/versions/9.9.9/licenses")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn test_package_dependencies_freshness() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let task = crate::publish::tests::process_tarball_setup2(
+      &t,
+      create_mock_tarball("depends_on_ok"),
+      &PackageName::try_from("bar").unwrap(),
+      &Version::try_from("1.2.3").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let mut resp = t
+      .http()
+      .get(
+        "/api/scopes/scope/packages/bar/versions/1.2.3/dependencies/freshness",
+      )
+      .call()
+      .await
+      .unwrap();
+    let deps: Vec<ApiDependencyFreshness> = resp.expect_ok().await;
+    assert_eq!(
+      deps,
+      vec![
+        ApiDependencyFreshness {
+          kind: ApiDependencyKind::Jsr,
+          dependency_type: ApiDependencyType::Regular,
+          name: "@scope/foo".to_string(),
+          constraint: "1".to_string(),
+          path: "".to_string(),
+          latest_version: Some(Version::try_from("1.2.3").unwrap()),
+          matches_latest: Some(true),
+          majors_behind: Some(0),
+          yanked: false,
+        },
+        ApiDependencyFreshness {
+          kind: ApiDependencyKind::Npm,
+          dependency_type: ApiDependencyType::Regular,
+          name: "express".to_string(),
+          constraint: "4".to_string(),
+          path: "".to_string(),
+          latest_version: None,
+          matches_latest: None,
+          majors_behind: None,
+          yanked: false,
+        },
+      ],
+    );
+
+    // a new major version of the dependency is published
+    let task = crate::publish::tests::process_tarball_setup2(
+      &t,
+      create_mock_tarball("major_bump"),
+      &PackageName::try_from("foo").unwrap(),
+      &Version::try_from("2.0.0").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let mut resp = t
+      .http()
+      .get(
+        "/api/scopes/scope/packages/bar/versions/1.2.3/dependencies/freshness",
+      )
       .call()
       .await
       .unwrap();
-    let dependents: ApiList<ApiDependent> = resp.expect_ok().await;
+    let deps: Vec<ApiDependencyFreshness> = resp.expect_ok().await;
     assert_eq!(
-      dependents.items,
-      vec![ApiDependent {
-        scope: "scope".try_into().unwrap(),
-        package: "baz".try_into().unwrap(),
-        versions: vec!["1.2.3".try_into().unwrap()],
-        total_versions: 1,
-      }]
+      deps[0].latest_version,
+      Some(Version::try_from("2.0.0").unwrap())
     );
-    assert_eq!(dependents.total, 2);
+    assert_eq!(deps[0].matches_latest, Some(false));
+    assert_eq!(deps[0].majors_behind, Some(1));
+    assert!(!deps[0].yanked);
+
+    // the only version that satisfies the constraint is yanked
+    t.db()
+      .yank_package_version(
+        &ScopeName::try_from("scope").unwrap(),
+        &PackageName::try_from("foo").unwrap(),
+        &Version::try_from("1.2.3").unwrap(),
+        true,
+        None,
+        None,
+      )
+      .await
+      .unwrap();
 
     let mut resp = t
       .http()
-      .get("/api/scopes/scope/packages/foo/dependents?versions_per_package_limit=1")
+      .get(
+        "/api/scopes/scope/packages/bar/versions/1.2.3/dependencies/freshness",
+      )
       .call()
       .await
       .unwrap();
-    let dependents: ApiList<ApiDependent> = resp.expect_ok().await;
-    assert_eq!(
-      dependents.items,
-      vec![
-        ApiDependent {
-          scope: "scope".try_into().unwrap(),
-          package: "bar".try_into().unwrap(),
-          versions: vec!["1.2.3".try_into().unwrap(),],
-          total_versions: 2,
-        },
-        ApiDependent {
-          scope: "scope".try_into().unwrap(),
-          package: "baz".try_into().unwrap(),
-          versions: vec!["1.2.3".try_into().unwrap()],
-          total_versions: 1,
-        },
-      ],
-    );
-    assert_eq!(dependents.total, 2);
+    let deps: Vec<ApiDependencyFreshness> = resp.expect_ok().await;
+    assert_eq!(deps[0].majors_behind, None);
+    assert!(deps[0].yanked);
+
+    let mut resp = t
+      .http()
+      .get(
+        "/api/scopes/scope/packages/bar/versions/0.0.1/dependencies/freshness",
+      )
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
   }
 
   #[tokio::test]
@@ -3830,10 +6810,15 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
     t.db()
       .create_publishing_task(NewPublishingTask {
         user_id: Some(t.user1.user.id),
+        github_repository_id: None,
+        github_workflow_run: None,
         package_scope: &scope,
         package_name: &name,
         package_version: &version,
         config_file: &config_file,
+        bypass_publish_freezes: false,
+        publish_at: None,
+        idempotency_key: None,
       })
       .await
       .unwrap();
@@ -3919,6 +6904,284 @@ ggHohNAjhbzDaY2iBW/m3NC5dehGUP4T2GBo/cwGhg==
     assert!(!package.is_archived);
   }
 
+  #[tokio::test]
+  async fn package_successor() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    for name in ["foo", "bar"] {
+      let name = PackageName::try_from(name).unwrap();
+      let res = t
+        .ephemeral_database
+        .create_package(&scope, &name)
+        .await
+        .unwrap();
+      assert!(matches!(res, CreatePackageResult::Ok(_)));
+    }
+
+    // a package can not be its own successor
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .body_json(json!({
+        "successor": { "scope": "scope", "name": "foo" }
+      }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "invalidPackageSuccessor")
+      .await;
+
+    // the successor must exist
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .body_json(json!({
+        "successor": { "scope": "scope", "name": "baz" }
+      }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "invalidPackageSuccessor")
+      .await;
+
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .body_json(json!({
+        "successor": { "scope": "scope", "name": "bar" }
+      }))
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    let successor = package.successor.unwrap();
+    assert_eq!(successor.scope, scope);
+    assert_eq!(successor.name, PackageName::try_from("bar").unwrap());
+
+    // successors can not form a cycle
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/bar")
+      .body_json(json!({
+        "successor": { "scope": "scope", "name": "foo" }
+      }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "invalidPackageSuccessor")
+      .await;
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo")
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert!(package.successor.is_some());
+
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .body_json(json!({
+        "successor": null
+      }))
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert!(package.successor.is_none());
+  }
+
+  #[tokio::test]
+  async fn package_maintenance_status() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    let name = PackageName::try_from("foo").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package(&scope, &name)
+      .await
+      .unwrap();
+    assert!(matches!(res, CreatePackageResult::Ok(_)));
+    t.ephemeral_database
+      .create_package_version_for_test(NewPackageVersion {
+        scope: &scope,
+        name: &name,
+        version: &"1.0.0".try_into().unwrap(),
+        user_id: None,
+        readme_path: None,
+        uses_npm: false,
+        exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
+        meta: Default::default(),
+      })
+      .await
+      .unwrap();
+
+    // packages without recent activity are suggested as unmaintained, but the
+    // status is not set until a maintainer confirms it
+    let suggested = t
+      .ephemeral_database
+      .suggest_unmaintained_packages(
+        chrono::Utc::now() + chrono::Duration::days(1),
+      )
+      .await
+      .unwrap();
+    assert_eq!(suggested, vec![(scope.clone(), name.clone())]);
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo")
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert!(package.maintenance_status.is_none());
+    assert!(package.unmaintained_suggested_at.is_some());
+
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .body_json(json!({
+        "maintenanceStatus": "unmaintained"
+      }))
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert_eq!(
+      package.maintenance_status,
+      Some(ApiMaintenanceStatus::Unmaintained)
+    );
+    assert!(package.unmaintained_suggested_at.is_none());
+
+    let mut resp = t
+      .http()
+      .get("/api/packages?maintenanceStatus=unmaintained")
+      .call()
+      .await
+      .unwrap();
+    let packages: ApiList<ApiPackage> = resp.expect_ok().await;
+    assert_eq!(packages.items.len(), 1);
+    assert_eq!(packages.items[0].name, name);
+
+    let mut resp = t
+      .http()
+      .get("/api/packages?maintenanceStatus=actively_maintained")
+      .call()
+      .await
+      .unwrap();
+    let packages: ApiList<ApiPackage> = resp.expect_ok().await;
+    assert!(packages.items.is_empty());
+
+    let mut resp = t
+      .http()
+      .get("/api/packages?maintenanceStatus=abandoned")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/foo")
+      .body_json(json!({
+        "maintenanceStatus": null
+      }))
+      .call()
+      .await
+      .unwrap();
+    let package: ApiPackage = resp.expect_ok().await;
+    assert!(package.maintenance_status.is_none());
+  }
+
+  #[tokio::test]
+  async fn packages_without_usable_versions_are_not_listed() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    let name = PackageName::try_from("foo").unwrap();
+    let version = Version::new("1.0.0").unwrap();
+    let res = t
+      .ephemeral_database
+      .create_package(&scope, &name)
+      .await
+      .unwrap();
+    assert!(matches!(res, CreatePackageResult::Ok(_)));
+
+    async fn list(t: &mut TestSetup) -> Vec<String> {
+      let packages = t
+        .http()
+        .get("/api/packages")
+        .call()
+        .await
+        .unwrap()
+        .expect_ok::<ApiList<ApiPackage>>()
+        .await;
+      assert_eq!(packages.total, packages.items.len());
+      packages
+        .items
+        .into_iter()
+        .map(|package| package.name.to_string())
+        .collect()
+    }
+
+    // no publish completed yet
+    assert!(list(&mut t).await.is_empty());
+
+    t.ephemeral_database
+      .create_package_version_for_test(NewPackageVersion {
+        scope: &scope,
+        name: &name,
+        version: &version,
+        user_id: None,
+        readme_path: None,
+        uses_npm: false,
+        exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
+        meta: Default::default(),
+      })
+      .await
+      .unwrap();
+    assert_eq!(list(&mut t).await, vec!["foo"]);
+
+    t.ephemeral_database
+      .yank_package_version(&scope, &name, &version, true, None, None)
+      .await
+      .unwrap();
+    assert!(list(&mut t).await.is_empty());
+    let stats = t
+      .http()
+      .get("/api/stats")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiStats>()
+      .await;
+    assert!(stats.newest.is_empty());
+    assert!(stats.updated.is_empty());
+
+    t.ephemeral_database
+      .yank_package_version(&scope, &name, &version, false, None, None)
+      .await
+      .unwrap();
+    assert_eq!(list(&mut t).await, vec!["foo"]);
+  }
+
   #[tokio::test]
   async fn package_source() {
     let mut t: TestSetup = TestSetup::new().await;
@@ -1,23 +1,70 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use hyper::header::CACHE_CONTROL;
+use hyper::header::CONTENT_DISPOSITION;
+use hyper::header::CONTENT_TYPE;
 use hyper::Body;
 use hyper::Request;
+use hyper::Response;
+use hyper::StatusCode;
 use routerify::ext::RequestExt;
 use routerify::Router;
 use tracing::field;
 use tracing::instrument;
 use tracing::Span;
+use uuid::Uuid;
 
+use crate::analysis::DENO_AST_VERSION;
+use crate::analysis::DENO_DOC_VERSION;
+use crate::analysis::DENO_GRAPH_VERSION;
+use crate::buckets::Buckets;
 use crate::db::Database;
+use crate::db::PublishingTask;
+use crate::db::PublishingTaskError;
+use crate::db::PublishingTaskStatus;
+use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
+use crate::iam::ReqIamExt;
+use crate::publish::enqueue_publishing_task;
+use crate::publish::publish_queue;
+use crate::rate_limit::RateLimitKey;
+use crate::rate_limit::RateLimiter;
+use crate::tarball::gcs_tarball_path;
 use crate::util;
 use crate::util::ApiResult;
 use crate::util::RequestIdExt;
+use crate::RegistryUrl;
 
+use super::ApiAnalysisVersions;
 use super::ApiError;
 use super::ApiPublishingTask;
+use super::ApiPublishingTaskExplanation;
+
+/// How long a publishing task must have been pending before the user that
+/// submitted it can retry it.
+const RETRY_PENDING_AFTER: chrono::Duration = chrono::Duration::minutes(10);
 
 pub fn publishing_task_router() -> Router<Body, ApiError> {
   Router::builder()
     .get("/:publishing_task_id", util::json(get_handler))
+    .get(
+      "/:publishing_task_id/tarball",
+      util::auth(download_tarball_handler),
+    )
+    .get(
+      "/:publishing_task_id/diagnostic_log",
+      util::auth(download_diagnostic_log_handler),
+    )
+    .get(
+      "/:publishing_task_id/explanation",
+      util::json(get_explanation_handler),
+    )
+    .post(
+      "/:publishing_task_id/cancel",
+      util::auth(util::json(cancel_handler)),
+    )
+    .post(
+      "/:publishing_task_id/retry",
+      util::auth(util::json(retry_handler)),
+    )
     .build()
     .unwrap()
 }
@@ -42,3 +89,430 @@ pub async fn get_handler(req: Request<Body>) -> ApiResult<ApiPublishingTask> {
 
   Ok(publishing_task.into())
 }
+
+/// Returns the publishing task if its artifacts are still retained, and the
+/// current user submitted it.
+async fn get_task_with_artifacts(
+  req: &Request<Body>,
+  publishing_task_id: Uuid,
+) -> ApiResult<PublishingTask> {
+  let iam = req.iam();
+  let user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  let publishing_task = db
+    .get_publishing_task(publishing_task_id)
+    .await?
+    .ok_or(ApiError::PublishNotFound)?;
+
+  if publishing_task.user_id != Some(user.id) {
+    return Err(ApiError::ActorNotAuthorized);
+  }
+  if publishing_task
+    .artifacts_expire_at
+    .map_or(true, |expires_at| expires_at < chrono::Utc::now())
+  {
+    return Err(ApiError::PublishArtifactsNotFound);
+  }
+
+  Ok(publishing_task)
+}
+
+#[instrument(
+  name = "GET /api/publishing_tasks/:publishing_task_id/tarball",
+  skip(req),
+  err,
+  fields(publishing_task_id)
+)]
+pub async fn download_tarball_handler(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let publishing_task_id = req.param_uuid("publishing_task_id")?;
+  Span::current()
+    .record("publishing_task_id", field::display(&publishing_task_id));
+
+  let publishing_task =
+    get_task_with_artifacts(&req, publishing_task_id).await?;
+
+  let buckets = req.data::<Buckets>().unwrap();
+  let content = buckets
+    .publishing_bucket
+    .download(gcs_tarball_path(publishing_task.id).into())
+    .await?
+    .ok_or(ApiError::PublishArtifactsNotFound)?;
+
+  let resp = Response::builder()
+    .status(StatusCode::OK)
+    .header(CONTENT_TYPE, "application/x-tar")
+    .header(
+      CONTENT_DISPOSITION,
+      format!(
+        "attachment; filename=\"{}-{}-{}.tar\"",
+        publishing_task.package_scope,
+        publishing_task.package_name,
+        publishing_task.package_version
+      ),
+    )
+    .header(CACHE_CONTROL, CACHE_CONTROL_DO_NOT_CACHE)
+    .body(Body::from(content))
+    .unwrap();
+  Ok(resp)
+}
+
+#[instrument(
+  name = "GET /api/publishing_tasks/:publishing_task_id/diagnostic_log",
+  skip(req),
+  err,
+  fields(publishing_task_id)
+)]
+pub async fn download_diagnostic_log_handler(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let publishing_task_id = req.param_uuid("publishing_task_id")?;
+  Span::current()
+    .record("publishing_task_id", field::display(&publishing_task_id));
+
+  let publishing_task =
+    get_task_with_artifacts(&req, publishing_task_id).await?;
+
+  let db = req.data::<Database>().unwrap();
+  let diagnostic_log = db
+    .get_publishing_task_diagnostic_log(publishing_task.id)
+    .await?
+    .ok_or(ApiError::PublishArtifactsNotFound)?;
+
+  let resp = Response::builder()
+    .status(StatusCode::OK)
+    .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+    .header(CACHE_CONTROL, CACHE_CONTROL_DO_NOT_CACHE)
+    .body(Body::from(diagnostic_log))
+    .unwrap();
+  Ok(resp)
+}
+
+/// The steps that fix a publishing error, by error code. The codes that are
+/// documented in the troubleshooting docs link to their section there.
+fn remediation(code: &str) -> (&'static [&'static str], bool) {
+  match code {
+    "linkInTarball" => (
+      &[
+        "Remove the symlink or hardlink from the package.",
+        "Or exclude it from publishing with the 'exclude' field of the config file.",
+      ],
+      true,
+    ),
+    "invalidEntryType" => (
+      &[
+        "Remove the entry that is not a regular file or directory from the package.",
+        "Or exclude it from publishing with the 'exclude' field of the config file.",
+      ],
+      true,
+    ),
+    "invalidPath" | "invalidGitPath" => (
+      &[
+        "Rename the file or directory to a path that JSR allows.",
+        "Or exclude it from publishing with the 'exclude' field of the config file.",
+      ],
+      code == "invalidPath",
+    ),
+    "invalidExternalImport" => (
+      &[
+        "Replace the import with a 'jsr:', 'npm:', 'data:', 'bun:', or 'node:' specifier.",
+        "Or remove the import from the package.",
+      ],
+      true,
+    ),
+    "globalTypeAugmentation" => (
+      &["Remove the global type augmentation from the module."],
+      true,
+    ),
+    "commonJs" => (
+      &["Rewrite the CommonJS code as an ES module, and remove imports of '.cjs' and '.cts' files."],
+      true,
+    ),
+    "bannedTripleSlashDirectives" => (
+      &["Remove the triple slash directive from the module. Only '/// <reference types=\"...\" />' directives are allowed."],
+      true,
+    ),
+    "bannedImportAssertion" => (
+      &["Replace 'assert' with 'with' in the import, to use an import attribute."],
+      true,
+    ),
+    "fileTooLarge" | "packageTooLarge" => (
+      &[
+        "Exclude files that do not need to be published with the 'exclude' field of the config file.",
+        "If the package can not be made smaller, request a limit increase.",
+      ],
+      true,
+    ),
+    "caseInsensitiveDuplicatePath" => (
+      &["Rename, remove, or exclude one of the files, so that no two paths differ only in casing."],
+      true,
+    ),
+    "missingConfigFile" => (
+      &["Add a 'jsr.json' or 'deno.json' file to the package, and make sure it is not excluded from publishing."],
+      true,
+    ),
+    "invalidConfigFile" => (
+      &["Fix the syntax errors in the config file, so that it is valid JSON(C)."],
+      true,
+    ),
+    "configFileNameMismatch" => (
+      &["Set the 'name' field of the config file to the name of the package that is published."],
+      true,
+    ),
+    "configFileVersionMismatch" => (
+      &["Set the 'version' field of the config file to the version that is published."],
+      true,
+    ),
+    "configFileExportsInvalid" => (
+      &["Set the 'exports' field of the config file to a path like './mod.ts', or to an object that maps export names like '.' to paths."],
+      true,
+    ),
+    "configFileBinInvalid" => (
+      &["Set the 'bin' field of the config file to the path of a file in the package, or to an object that maps command names to such paths."],
+      true,
+    ),
+    "configFileEnginesInvalid" => (
+      &["Map only 'deno', 'node', or 'bun' to a minimum version like '>=1.2.3' in the 'engines' field of the config file."],
+      true,
+    ),
+    "configFileKeywordsInvalid" => (
+      &["Use at most 10 keywords of at most 32 letters, digits, and dashes in the 'keywords' field of the config file."],
+      true,
+    ),
+    "configFilePermissionsInvalid" => (
+      &["Map only 'net', 'env', 'read', 'write', 'run', 'sys', or 'ffi' to true, false, or a list of targets in the 'permissions' field of the config file."],
+      true,
+    ),
+    "configFileDependenciesInvalid" => (
+      &["List only 'jsr:' or 'npm:' package names without a version constraint in 'peerDependencies' and 'optionalDependencies', and only packages that the package imports."],
+      true,
+    ),
+    "graphError" => (
+      &[
+        "Fix the module that the error points at, so that it exists and has no syntax errors.",
+        "Reproduce the error locally with the tarball and diagnostic log of the publish, and the versions of the analysis tools.",
+      ],
+      true,
+    ),
+    "docError" => (
+      &[
+        "Check the package for syntax errors.",
+        "If the package is valid, report the error with the diagnostic log and the versions of the analysis tools.",
+      ],
+      true,
+    ),
+    "npmTarballError" => (
+      &["Report the error with the diagnostic log and the versions of the analysis tools."],
+      false,
+    ),
+    "analysisTimeout" | "analysisMemoryExceeded" => (
+      &[
+        "Exclude files that do not need to be published with the 'exclude' field of the config file.",
+        "Or split the package into multiple smaller packages.",
+      ],
+      true,
+    ),
+    "invalidJsrSpecifier" => (
+      &["Change the specifier to the form 'jsr:@<scope>/<name>@<version>/<path>'."],
+      true,
+    ),
+    "invalidNpmSpecifier" => (
+      &["Change the specifier to the form 'npm:<name>@<version>/<path>'."],
+      true,
+    ),
+    "missingConstraint" => (
+      &["Add a version constraint to the specifier, like 'jsr:@std/path@^1.0.0'."],
+      false,
+    ),
+    "invalidJsrScopedPackageName" => (
+      &["Change the specifier to reference a valid scoped package name, like '@std/path'."],
+      false,
+    ),
+    "unresolvableJsrDependency" => (
+      &[
+        "Change the version constraint of the dependency to match a published version.",
+        "Or publish a version of the dependency that matches the constraint first.",
+      ],
+      false,
+    ),
+    "invalidJsrDependencySubPath" => (
+      &["Import an export that the resolved version of the dependency has, or change the version constraint."],
+      false,
+    ),
+    "docsCoverageBelowMinimum" => (
+      &[
+        "Add JSDoc comments to the undocumented exported symbols of the export.",
+        "Or ask a scope admin to lower the minimum docs coverage of the scope.",
+      ],
+      true,
+    ),
+    "cancelled" => (&["Publish the version again."], false),
+    "tarballDigestMismatch" => (
+      &["Upload the tarball again, and make sure it is not modified after its digest is computed."],
+      false,
+    ),
+    _ => (&[], false),
+  }
+}
+
+/// Explains why a publish failed: what can be done to fix the error, which
+/// files and config fields caused it, and which versions of the analysis tools
+/// the registry used, so that the failure can be reproduced.
+#[instrument(
+  name = "GET /api/publishing_tasks/:publishing_task_id/explanation",
+  skip(req),
+  err,
+  fields(publishing_task_id)
+)]
+pub async fn get_explanation_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPublishingTaskExplanation> {
+  let publishing_task_id = req.param_uuid("publishing_task_id")?;
+  Span::current()
+    .record("publishing_task_id", field::display(&publishing_task_id));
+
+  let db = req.data::<Database>().unwrap();
+  let publishing_task = db
+    .get_publishing_task(publishing_task_id)
+    .await?
+    .ok_or(ApiError::PublishNotFound)?;
+
+  let error = match (publishing_task.status, publishing_task.error) {
+    (PublishingTaskStatus::Failure, Some(error)) => error,
+    _ => return Err(ApiError::PublishNotFailed),
+  };
+
+  let (steps, documented) = remediation(&error.code);
+  let docs_url = if documented {
+    let registry_url = &req.data::<RegistryUrl>().unwrap().0;
+    let mut url = registry_url
+      .join("docs/troubleshooting")
+      .map_err(|_| ApiError::InternalServerError)?;
+    url.set_fragment(Some(&error.code.to_lowercase()));
+    Some(url.into())
+  } else {
+    None
+  };
+
+  Ok(ApiPublishingTaskExplanation {
+    code: error.code,
+    message: error.message,
+    remediation: steps.iter().map(|step| step.to_string()).collect(),
+    docs_url,
+    files: error.files.into_iter().map(Into::into).collect(),
+    config_file: publishing_task.config_file,
+    config_fields: error.config_fields,
+    analysis_versions: ApiAnalysisVersions {
+      deno_ast: DENO_AST_VERSION.to_owned(),
+      deno_graph: DENO_GRAPH_VERSION.to_owned(),
+      deno_doc: DENO_DOC_VERSION.to_owned(),
+    },
+  })
+}
+
+/// Fails a publishing task that is stuck before it was processed, so that the
+/// version can be published again. Only the user that submitted the publish,
+/// or staff, can cancel it.
+#[instrument(
+  name = "POST /api/publishing_tasks/:publishing_task_id/cancel",
+  skip(req),
+  err,
+  fields(publishing_task_id, user.id)
+)]
+pub async fn cancel_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPublishingTask> {
+  let publishing_task_id = req.param_uuid("publishing_task_id")?;
+  Span::current()
+    .record("publishing_task_id", field::display(&publishing_task_id));
+
+  let iam = req.iam();
+  let user = iam.check_current_user_access()?;
+  Span::current().record("user.id", field::display(user.id));
+
+  let db = req.data::<Database>().unwrap();
+  let publishing_task = db
+    .get_publishing_task(publishing_task_id)
+    .await?
+    .ok_or(ApiError::PublishNotFound)?;
+  if publishing_task.user_id != Some(user.id) {
+    iam.check_admin_access()?;
+  }
+
+  // If a worker is still processing the task, it can not finalize the version
+  // anymore once the task has failed.
+  let publishing_task = db
+    .cancel_publishing_task(
+      publishing_task_id,
+      PublishingTaskError {
+        code: "cancelled".to_owned(),
+        message: "The publish was cancelled before it was processed."
+          .to_owned(),
+        ..Default::default()
+      },
+    )
+    .await?
+    .ok_or(ApiError::PublishNotCancellable)?;
+
+  let buckets = req.data::<Buckets>().unwrap();
+  buckets
+    .publishing_bucket
+    .bucket
+    .delete(&gcs_tarball_path(publishing_task.id))
+    .await?;
+
+  Ok(publishing_task.into())
+}
+
+/// Puts a publishing task that has been pending for a while back onto the
+/// publish queue, in case its queue message was lost. Only the user that
+/// submitted the publish can retry it, and it can then only be retried again
+/// once it has been pending for a while longer. Publishes that were submitted
+/// with only an OIDC token from a CI workflow have no user, so they can not be
+/// retried.
+#[instrument(
+  name = "POST /api/publishing_tasks/:publishing_task_id/retry",
+  skip(req),
+  err,
+  fields(publishing_task_id, user.id)
+)]
+pub async fn retry_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiPublishingTask> {
+  let publishing_task_id = req.param_uuid("publishing_task_id")?;
+  Span::current()
+    .record("publishing_task_id", field::display(&publishing_task_id));
+
+  let iam = req.iam();
+  let user = iam.check_current_user_access()?;
+  Span::current().record("user.id", field::display(user.id));
+
+  let rate_limiter = req.data::<RateLimiter>().unwrap();
+  if !rate_limiter.check(RateLimitKey::RetryPublish(user.id)) {
+    return Err(ApiError::RateLimitExceeded);
+  }
+
+  let db = req.data::<Database>().unwrap();
+  let publishing_task = db
+    .get_publishing_task(publishing_task_id)
+    .await?
+    .ok_or(ApiError::PublishNotFound)?;
+  if publishing_task.user_id != Some(user.id) {
+    return Err(ApiError::ActorNotAuthorized);
+  }
+
+  let publishing_task = db
+    .retry_pending_publishing_task(
+      publishing_task_id,
+      chrono::Utc::now() - RETRY_PENDING_AFTER,
+    )
+    .await?
+    .ok_or(ApiError::PublishNotRetryable)?;
+
+  let queue = publish_queue(&req);
+  enqueue_publishing_task(&*queue, publishing_task_id).await?;
+
+  Ok(publishing_task.into())
+}
@@ -0,0 +1,68 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use hyper::Body;
+use hyper::Request;
+use routerify::ext::RequestExt;
+use tracing::instrument;
+
+use crate::rate_limit::RateLimiter;
+use crate::rate_limit::RequestCosts;
+use crate::rate_limit::WINDOW;
+use crate::util::ApiResult;
+
+use super::ApiRateLimits;
+use super::ApiRequestCost;
+
+/// Describes the request quotas of this API instance, and how much each kind
+/// of request costs, so that clients can pace their requests up front.
+#[instrument(name = "GET /api/rate_limits", skip(req), err)]
+pub async fn rate_limits_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiRateLimits> {
+  let limits = req.data::<RateLimiter>().unwrap().limits();
+  let request_costs = req.data::<RequestCosts>().unwrap();
+
+  Ok(ApiRateLimits {
+    window_secs: WINDOW.as_secs(),
+    anonymous: limits.anonymous,
+    api_key: limits.api_key,
+    token: limits.token,
+    render_readme: limits.render_readme,
+    retry_publish: limits.retry_publish,
+    default_cost: request_costs.default_cost,
+    costs: request_costs
+      .rules
+      .iter()
+      .map(|rule| ApiRequestCost {
+        method: rule.method.clone(),
+        path: rule.path.clone(),
+        query: rule.query.clone(),
+        cost: rule.cost,
+      })
+      .collect(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::api::ApiRateLimits;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  #[tokio::test]
+  async fn rate_limits() {
+    let mut t = TestSetup::new().await;
+
+    let limits = t
+      .unauthed_http()
+      .get("/api/rate_limits")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiRateLimits>()
+      .await;
+    assert_eq!(limits.window_secs, 60);
+    assert_eq!(limits.anonymous, None);
+    assert_eq!(limits.default_cost, 1);
+    assert!(limits.costs.is_empty());
+  }
+}
@@ -0,0 +1,149 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use hyper::header;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use routerify::prelude::RequestExt;
+use tracing::field;
+use tracing::instrument;
+use tracing::Span;
+
+use crate::buckets::Buckets;
+use crate::db::Database;
+use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
+use crate::gcp::CACHE_CONTROL_IMMUTABLE;
+use crate::ids::PackageName;
+use crate::ids::ScopeName;
+use crate::ids::Version;
+use crate::util;
+use crate::util::ApiResult;
+use crate::RegistryUrl;
+
+use super::ApiError;
+
+/// Serves the `meta.json` and `<version>_meta.json` files of packages, and
+/// their signatures, on the registry host. The load balancer routes requests
+/// for these files here instead of to the modules bucket, so that requests for
+/// the old names of renamed or moved packages are redirected to their new
+/// names. The files of other packages are served from the modules bucket.
+///
+/// Module files are still served by the modules bucket directly, so they are
+/// not redirected.
+#[instrument(
+  name = "GET /:scope/:package/:file",
+  skip(req),
+  err,
+  fields(scope, package, file)
+)]
+pub async fn registry_metadata_handler(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let scope = req
+    .param("scope")
+    .unwrap()
+    .strip_prefix('@')
+    .and_then(|scope| ScopeName::try_from(scope).ok())
+    .ok_or(ApiError::PackageNotFound)?;
+  let package = PackageName::try_from(req.param("package").unwrap().as_str())
+    .map_err(|_| ApiError::PackageNotFound)?;
+  let file = req.param("file").unwrap().as_str();
+
+  let span = Span::current();
+  span.record("scope", field::display(&scope));
+  span.record("package", field::display(&package));
+  span.record("file", file);
+
+  let metadata_file = file.strip_suffix(".sig").unwrap_or(file);
+  let cache_control = if metadata_file == "meta.json" {
+    CACHE_CONTROL_DO_NOT_CACHE
+  } else if metadata_file
+    .strip_suffix("_meta.json")
+    .is_some_and(|version| Version::try_from(version).is_ok())
+  {
+    CACHE_CONTROL_IMMUTABLE
+  } else {
+    return Err(ApiError::PackageNotFound);
+  };
+
+  let buckets = req.data::<Buckets>().unwrap();
+  let path = format!("@{scope}/{package}/{file}");
+  if let Some(content) = buckets.modules_bucket.download(path.into()).await? {
+    return Ok(
+      Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CACHE_CONTROL, cache_control)
+        .body(Body::from(content))
+        .unwrap(),
+    );
+  }
+
+  let db = req.data::<Database>().unwrap();
+  let registry_url = &req.data::<RegistryUrl>().unwrap().0;
+  let redirect = db
+    .get_package_redirect(&scope, &package)
+    .await?
+    .ok_or(ApiError::PackageNotFound)?;
+
+  let location = registry_url
+    .join(&format!(
+      "@{}/{}/{}",
+      redirect.target_scope, redirect.target_name, file
+    ))
+    .unwrap();
+  Ok(util::moved_permanently(location.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+  use hyper::StatusCode;
+
+  use crate::db::PublishingTaskStatus;
+  use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
+  use crate::gcp::CACHE_CONTROL_IMMUTABLE;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  #[tokio::test]
+  async fn serves_metadata_from_bucket() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    for (path, cache_control) in [
+      ("/@scope/foo/meta.json", CACHE_CONTROL_DO_NOT_CACHE),
+      ("/@scope/foo/1.2.3_meta.json", CACHE_CONTROL_IMMUTABLE),
+    ] {
+      let mut resp = t.unauthed_http().get(path).call().await.unwrap();
+      assert_eq!(resp.status(), StatusCode::OK, "{path}");
+      assert_eq!(resp.headers().get("cache-control").unwrap(), cache_control);
+      let body = hyper::body::to_bytes(resp.body_mut()).await.unwrap();
+      let expected = t
+        .buckets()
+        .modules_bucket
+        .download(path.trim_start_matches('/').into())
+        .await
+        .unwrap()
+        .unwrap();
+      assert_eq!(body, expected);
+    }
+
+    // Only metadata files are served.
+    t.unauthed_http()
+      .get("/@scope/foo/jsr.json")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+    t.unauthed_http()
+      .get("/@scope/bar/meta.json")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+  }
+}
@@ -1,5 +1,6 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::sync::OnceLock;
 
 use crate::api::package::package_router;
@@ -13,6 +14,7 @@ use hyper::Response;
 use hyper::StatusCode;
 use routerify::ext::RequestExt;
 use routerify::Router;
+use sqlx::types::ipnetwork::IpNetwork;
 use tracing::field;
 use tracing::instrument;
 use tracing::Span;
@@ -24,6 +26,8 @@ use super::types::*;
 use crate::auth::lookup_user_by_github_login;
 use crate::auth::GithubOauth2Client;
 use crate::db::*;
+use crate::ids::ScopedPackageName;
+use crate::trust::TrustPolicy;
 use crate::util;
 use crate::util::decode_json;
 use crate::util::ApiResult;
@@ -37,6 +41,8 @@ pub fn scope_router() -> Router<Body, ApiError> {
     .patch("/:scope", util::auth(util::json(update_handler)))
     .delete("/:scope", util::auth(delete_handler))
     .get("/:scope/members", util::json(list_members_handler))
+    .get("/:scope/metrics", util::auth(util::json(metrics_handler)))
+    .get("/:scope/downloads", util::json(downloads_handler))
     .post(
       "/:scope/members",
       util::auth(util::json(invite_member_handler)),
@@ -54,10 +60,52 @@ pub fn scope_router() -> Router<Body, ApiError> {
       "/:scope/invites/:user_id",
       util::auth(delete_invite_handler),
     )
+    .get(
+      "/:scope/publish_freezes",
+      util::json(list_publish_freezes_handler),
+    )
+    .post(
+      "/:scope/publish_freezes",
+      util::auth(util::json(create_publish_freeze_handler)),
+    )
+    .delete(
+      "/:scope/publish_freezes/:freeze_id",
+      util::auth(delete_publish_freeze_handler),
+    )
+    .get(
+      "/:scope/publish_ip_ranges",
+      util::auth(util::json(list_publish_ip_ranges_handler)),
+    )
+    .post(
+      "/:scope/publish_ip_ranges",
+      util::auth(util::json(create_publish_ip_range_handler)),
+    )
+    .delete(
+      "/:scope/publish_ip_ranges/:range_id",
+      util::auth(delete_publish_ip_range_handler),
+    )
+    .get("/:scope/tokens", util::auth(util::json(list_tokens_handler)))
+    .post("/:scope/tokens", util::auth(util::json(create_token_handler)))
+    .delete("/:scope/tokens/:token_id", util::auth(delete_token_handler))
+    .get(
+      "/:scope/recovery_requests",
+      util::auth(util::json(list_recovery_requests_handler)),
+    )
+    .post(
+      "/:scope/recovery_requests",
+      util::auth(util::json(create_recovery_request_handler)),
+    )
     .build()
     .unwrap()
 }
 
+/// The largest scope README, in bytes.
+const MAX_SCOPE_README_SIZE: usize = 64 * 1024;
+/// The most packages a scope can pin to its landing page.
+const MAX_PINNED_PACKAGES: usize = 6;
+/// The most packages a single scope token can publish.
+const MAX_SCOPE_TOKEN_PACKAGES: usize = 100;
+
 static RESERVED_SCOPES: OnceLock<std::collections::HashSet<String>> =
   OnceLock::new();
 
@@ -71,10 +119,17 @@ async fn create_handler(mut req: Request<Body>) -> ApiResult<ApiScope> {
   let iam = req.iam();
   let user = iam.check_current_user_access()?;
 
+  // New accounts can create fewer scopes than their scope limit, until they
+  // gain trust.
+  let trust_policy = req.data::<TrustPolicy>().unwrap();
+  let trust = db.get_user_trust_signals(user.id).await?;
+  let scope_limit =
+    trust_policy.scope_limit(trust_policy.level(&trust), user.scope_limit);
+
   // TODO(bartlomieju): this should be done in a transaction and we should check
   // for no of scopes after creating it and if it exceeds the limit rollback.
   // How many scopes has this user created?
-  if user.scope_usage >= user.scope_limit.into() {
+  if user.scope_usage >= scope_limit.into() {
     return Err(ApiError::ScopeLimitReached);
   }
 
@@ -112,17 +167,20 @@ async fn get_handler(req: Request<Body>) -> ApiResult<ApiScopeOrFullScope> {
     .await?
     .ok_or(ApiError::ScopeNotFound)?;
 
+  let profile = db.get_scope_profile(&scope.scope).await?;
+
   let iam = req.iam();
-  if let Ok(()) = iam.check_scope_admin_access(&scope.scope).await {
+  let scope = if let Ok(()) = iam.check_scope_admin_access(&scope.scope).await {
     let user = db
       .get_user_public(scope.creator)
       .await?
       .ok_or(ApiError::ScopeNotFound)?;
     let usage = db.get_scope_usage(&scope.scope).await?;
-    Ok(ApiScopeOrFullScope::Full((scope, usage, user).into()))
+    ApiScopeOrFullScope::Full((scope, usage, user).into())
   } else {
-    Ok(ApiScopeOrFullScope::Partial(scope.into()))
-  }
+    ApiScopeOrFullScope::Partial(scope.into())
+  };
+  Ok(scope.with_profile(profile))
 }
 
 #[instrument(name = "PATCH /api/scopes/:scope", skip(req), err, fields(scope))]
@@ -156,6 +214,88 @@ async fn update_handler(
       )
       .await?
     }
+    ApiUpdateScopeRequest::ValidationLevel(validation_level) => {
+      iam.check_scope_admin_access(&scope).await?;
+      db.scope_set_validation_level(
+        &scope,
+        validation_level.map(ValidationLevel::from),
+      )
+      .await?
+    }
+    ApiUpdateScopeRequest::OutdatedDependenciesEmail(
+      outdated_dependencies_email,
+    ) => {
+      iam.check_scope_admin_access(&scope).await?;
+      db.scope_set_outdated_dependencies_email(
+        &scope,
+        outdated_dependencies_email,
+      )
+      .await?
+    }
+    ApiUpdateScopeRequest::DownloadAnomalyEmail(download_anomaly_email) => {
+      iam.check_scope_admin_access(&scope).await?;
+      db.scope_set_download_anomaly_email(&scope, download_anomaly_email)
+        .await?
+    }
+    ApiUpdateScopeRequest::Readme(readme) => {
+      iam.check_scope_admin_access(&scope).await?;
+      let readme = readme.as_deref().map(str::trim).filter(|r| !r.is_empty());
+      if readme.is_some_and(|r| r.len() > MAX_SCOPE_README_SIZE) {
+        return Err(ApiError::MalformedRequest {
+          msg: format!(
+            "readme must not be larger than {MAX_SCOPE_README_SIZE} bytes"
+          )
+          .into(),
+        });
+      }
+      db.scope_set_readme(&scope, readme).await?
+    }
+    ApiUpdateScopeRequest::PinnedPackages(packages) => {
+      iam.check_scope_admin_access(&scope).await?;
+      if packages.len() > MAX_PINNED_PACKAGES {
+        return Err(ApiError::MalformedRequest {
+          msg: format!(
+            "a scope must not pin more than {MAX_PINNED_PACKAGES} packages"
+          )
+          .into(),
+        });
+      }
+      let unique = packages.iter().collect::<HashSet<_>>();
+      if unique.len() != packages.len() {
+        return Err(ApiError::MalformedRequest {
+          msg: "a package must not be pinned more than once".into(),
+        });
+      }
+      let scoped_packages = packages
+        .iter()
+        .map(|package| ScopedPackageName {
+          scope: scope.clone(),
+          package: package.clone(),
+        })
+        .collect::<Vec<_>>();
+      if !db.list_missing_packages(&scoped_packages).await?.is_empty() {
+        return Err(ApiError::PackageNotFound);
+      }
+      db.scope_set_pinned_packages(&scope, &packages).await?
+    }
+    ApiUpdateScopeRequest::DocsCoveragePolicy(ApiDocsCoveragePolicy {
+      min_coverage,
+      enforce,
+    }) => {
+      iam.check_scope_admin_access(&scope).await?;
+      if min_coverage.is_some_and(|min| !(0.0..=1.0).contains(&min)) {
+        return Err(ApiError::MalformedRequest {
+          msg: "minCoverage must be between 0 and 1".into(),
+        });
+      }
+      if enforce && min_coverage.is_none() {
+        return Err(ApiError::MalformedRequest {
+          msg: "minCoverage must be set to enforce it".into(),
+        });
+      }
+      db.scope_set_docs_coverage_policy(&scope, min_coverage, enforce)
+        .await?
+    }
   };
 
   let user = db
@@ -163,10 +303,12 @@ async fn update_handler(
     .await?
     .ok_or(ApiError::ScopeNotFound)?;
   let usage = db.get_scope_usage(&updated_scope.scope).await?;
+  let profile = db.get_scope_profile(&updated_scope.scope).await?;
 
-  Ok(ApiScopeOrFullScope::Full(
-    (updated_scope, usage, user).into(),
-  ))
+  Ok(
+    ApiScopeOrFullScope::Full((updated_scope, usage, user).into())
+      .with_profile(profile),
+  )
 }
 
 #[instrument(name = "DELETE /api/scopes/:scope", skip(req), err, fields(scop))]
@@ -422,6 +564,54 @@ pub async fn delete_member_handler(
   Ok(resp)
 }
 
+#[instrument(
+  name = "GET /api/scopes/:scope/metrics",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn metrics_handler(req: Request<Body>) -> ApiResult<ApiScopeMetrics> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+
+  let metrics = db.get_scope_metrics(&scope).await?;
+
+  Ok(metrics.into())
+}
+
+/// Returns the daily downloads of all packages in a scope in the last 90 days.
+#[instrument(
+  name = "GET /api/scopes/:scope/downloads",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn downloads_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiScopeDownloads> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
+
+  let db = req.data::<Database>().unwrap();
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let current = chrono::Utc::now();
+  let start = current - chrono::Duration::days(90);
+
+  let total = db.get_scope_downloads_24h(&scope, start, current).await?;
+
+  Ok(ApiScopeDownloads {
+    total: total.into_iter().map(ApiDownloadDataPoint::from).collect(),
+  })
+}
+
 #[instrument(
   name = "GET /api/scopes/:scope/invites",
   skip(req),
@@ -481,285 +671,1424 @@ pub async fn delete_invite_handler(
   Ok(resp)
 }
 
-#[cfg(test)]
-pub mod tests {
-  use super::*;
-  use crate::ids::PackageName;
-  use crate::ids::ScopeName;
-  use crate::util::test::ApiResultExt;
-  use crate::util::test::TestSetup;
-  use serde_json::json;
-  use uuid::Uuid;
+#[instrument(
+  name = "GET /api/scopes/:scope/publish_freezes",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn list_publish_freezes_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiScopePublishFreeze>> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
 
-  #[tokio::test]
-  async fn scope_get_create() {
-    let mut t = TestSetup::new().await;
+  let db = req.data::<Database>().unwrap();
 
-    let mut resp = t.http().get("/api/scopes/scope1").call().await.unwrap();
-    let err = resp.expect_err(StatusCode::NOT_FOUND).await;
-    assert_eq!(err.code, "scopeNotFound");
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
 
-    let mut resp = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "scope1" }))
-      .call()
-      .await
-      .unwrap();
-    let scope: ApiScope = resp.expect_ok().await;
-    assert_eq!(scope.scope.to_string(), "scope1");
+  let freezes = db.list_scope_publish_freezes(&scope).await?;
 
-    let mut resp = t.http().get("/api/scopes/scope1").call().await.unwrap();
-    let scope: ApiScope = resp.expect_ok().await;
-    assert_eq!(scope.scope.to_string(), "scope1");
+  Ok(
+    freezes
+      .into_iter()
+      .map(ApiScopePublishFreeze::from)
+      .collect(),
+  )
+}
 
-    // duplicate scope name
-    let mut resp = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "scope1" }))
-      .call()
-      .await
-      .unwrap();
-    resp
-      .expect_err_code(StatusCode::CONFLICT, "scopeAlreadyExists")
-      .await;
+#[instrument(
+  name = "POST /api/scopes/:scope/publish_freezes",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn create_publish_freeze_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiScopePublishFreeze> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
 
-    // duplicate scope name (only difference is dashes)
-    let mut resp = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "scop-e1" }))
-      .call()
-      .await
-      .unwrap();
-    resp
-      .expect_err_code(StatusCode::CONFLICT, "scopeAlreadyExists")
-      .await;
+  let ApiCreateScopePublishFreezeRequest {
+    starts_at,
+    ends_at,
+    recurs_weekly,
+    reason,
+  } = decode_json(&mut req).await?;
 
-    // invalid name
-    let mut resp = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "scope 1" }))
-      .call()
-      .await
-      .unwrap();
-    let err = resp.expect_err(StatusCode::BAD_REQUEST).await;
-    assert_eq!(err.code, "malformedRequest");
+  let db = req.data::<Database>().unwrap();
 
-    // disallowed name
-    let mut resp = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "somebadword" }))
-      .call()
-      .await
-      .unwrap();
-    let err = resp.expect_err(StatusCode::BAD_REQUEST).await;
-    assert_eq!(err.code, "scopeNameNotAllowed");
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
 
-    // reserved name
-    let mut resp = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "react" }))
-      .call()
-      .await
-      .unwrap();
-    let err = resp.expect_err(StatusCode::BAD_REQUEST).await;
-    assert_eq!(err.code, "scopeNameReserved");
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+  let current_user = iam.check_current_user_access()?;
+
+  if starts_at >= ends_at {
+    return Err(ApiError::MalformedRequest {
+      msg: "startsAt must be before endsAt".into(),
+    });
+  }
+  if recurs_weekly && ends_at - starts_at >= chrono::Duration::weeks(1) {
+    return Err(ApiError::MalformedRequest {
+      msg: "weekly publish freezes must be shorter than a week".into(),
+    });
+  }
+  let reason = reason.trim();
+  if reason.is_empty() {
+    return Err(ApiError::MalformedRequest {
+      msg: "reason must not be empty".into(),
+    });
   }
 
-  #[tokio::test]
-  async fn scope_limit() {
-    let mut t = TestSetup::new().await;
+  let freeze = db
+    .create_scope_publish_freeze(NewScopePublishFreeze {
+      scope: &scope,
+      starts_at,
+      ends_at,
+      recurs_weekly,
+      reason,
+      created_by: current_user.id,
+    })
+    .await?;
 
-    let previously_created_scopes = t
-      .db()
-      .list_scopes_created_by_user(t.user1.user.id)
-      .await
-      .unwrap()
-      .len();
+  Ok(freeze.into())
+}
 
-    // In TestSetup a scope has already been created for this user.
-    assert_eq!(previously_created_scopes, 1);
+#[instrument(
+  name = "DELETE /api/scopes/:scope/publish_freezes/:freeze_id",
+  skip(req),
+  err,
+  fields(scope, freeze_id)
+)]
+pub async fn delete_publish_freeze_handler(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let scope = req.param_scope()?;
+  let freeze_id = req.param_uuid("freeze_id")?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("freeze_id", field::display(&freeze_id));
 
-    // Now create two more
-    let mut resp = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "scope1" }))
-      .call()
-      .await
-      .unwrap();
-    resp.expect_ok::<ApiScope>().await;
-    let mut resp: Response<Body> = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "scope2" }))
-      .call()
-      .await
-      .unwrap();
-    resp.expect_ok::<ApiScope>().await;
+  let db = req.data::<Database>().unwrap();
 
-    // Yet another one should fail due to limit.
-    let mut resp: Response<Body> = t
-      .http()
-      .post("/api/scopes")
-      .body_json(json!({ "scope": "scope3" }))
-      .call()
-      .await
-      .unwrap();
-    resp
-      .expect_err_code(StatusCode::BAD_REQUEST, "scopeLimitReached")
-      .await;
-  }
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
 
-  #[tokio::test]
-  async fn scope_update_gh_oidc_settings() {
-    let mut t = TestSetup::new().await;
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
 
-    t.db()
-      .add_user_to_scope(NewScopeMember {
-        scope: &t.scope.scope,
-        user_id: t.user2.user.id,
-        is_admin: false,
-      })
-      .await
-      .unwrap();
+  if !db.delete_scope_publish_freeze(&scope, freeze_id).await? {
+    return Err(ApiError::PublishFreezeNotFound);
+  }
 
-    let path = format!("/api/scopes/{}", t.scope.scope);
-    let token = t.user2.token.clone();
-    let mut resp = t
-      .http()
-      .patch(&path)
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/publish_ip_ranges",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn list_publish_ip_ranges_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiScopePublishIpRange>> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+
+  let ranges = db.list_scope_publish_ip_ranges(&scope).await?;
+
+  Ok(
+    ranges
+      .into_iter()
+      .map(ApiScopePublishIpRange::from)
+      .collect(),
+  )
+}
+
+#[instrument(
+  name = "POST /api/scopes/:scope/publish_ip_ranges",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn create_publish_ip_range_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiScopePublishIpRange> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
+
+  let ApiCreateScopePublishIpRangeRequest { cidr, description } =
+    decode_json(&mut req).await?;
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+  let current_user = iam.check_current_user_access()?;
+
+  let cidr = cidr.trim().parse::<IpNetwork>().map_err(|_| {
+    ApiError::MalformedRequest {
+      msg: "cidr must be an IP address or a range in CIDR notation".into(),
+    }
+  })?;
+  // Store the network address, so that `10.0.0.1/8` and `10.0.0.0/8` are
+  // the same range.
+  let cidr = IpNetwork::new(cidr.network(), cidr.prefix()).unwrap();
+  let description = description.trim();
+  if description.chars().count() > 200 {
+    return Err(ApiError::MalformedRequest {
+      msg: "description must be at most 200 characters".into(),
+    });
+  }
+
+  let range = db
+    .create_scope_publish_ip_range(NewScopePublishIpRange {
+      scope: &scope,
+      cidr,
+      description,
+      created_by: current_user.id,
+    })
+    .await
+    .map_err(|e| {
+      map_unique_violation(e, ApiError::PublishIpRangeAlreadyExists)
+    })?;
+
+  Ok(range.into())
+}
+
+#[instrument(
+  name = "DELETE /api/scopes/:scope/publish_ip_ranges/:range_id",
+  skip(req),
+  err,
+  fields(scope, range_id)
+)]
+pub async fn delete_publish_ip_range_handler(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let scope = req.param_scope()?;
+  let range_id = req.param_uuid("range_id")?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("range_id", field::display(&range_id));
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+
+  if !db.delete_scope_publish_ip_range(&scope, range_id).await? {
+    return Err(ApiError::PublishIpRangeNotFound);
+  }
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/tokens",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn list_tokens_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiToken>> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+
+  let tokens = db.list_scope_tokens(&scope).await?;
+
+  Ok(tokens.into_iter().map(ApiToken::from).collect())
+}
+
+/// Creates a token that can only publish the given packages of the scope.
+/// The token is owned by the admin that created it, and stops working if they
+/// are removed from the scope.
+#[instrument(
+  name = "POST /api/scopes/:scope/tokens",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn create_token_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiCreatedToken> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
+
+  let ApiCreateScopeTokenRequest {
+    description,
+    expires_at,
+    mut packages,
+  } = decode_json(&mut req).await?;
+
+  let description = crate::token::normalize_description(&description)?;
+  let mut seen = HashSet::new();
+  packages.retain(|package| seen.insert(package.clone()));
+  if packages.is_empty() {
+    return Err(ApiError::MalformedRequest {
+      msg: "packages must not be empty".into(),
+    });
+  }
+  if packages.len() > MAX_SCOPE_TOKEN_PACKAGES {
+    return Err(ApiError::MalformedRequest {
+      msg: format!(
+        "packages must not contain more than {MAX_SCOPE_TOKEN_PACKAGES} packages"
+      )
+      .into(),
+    });
+  }
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+  let user = iam.check_authorization_approve_access()?;
+
+  for package in &packages {
+    db.get_package(&scope, package)
+      .await?
+      .ok_or(ApiError::PackageNotFound)?;
+  }
+
+  let permissions = Permissions(vec![Permission::PackagePublish(
+    PackagePublishPermission::Packages {
+      scope: scope.clone(),
+      packages,
+    },
+  )]);
+  let secret = crate::token::create_token(
+    db,
+    user.id,
+    TokenType::Scope,
+    Some(description),
+    expires_at,
+    Some(permissions),
+  )
+  .await?;
+
+  let hash = crate::token::hash(&secret);
+  let token = db.get_token_by_hash(&hash).await?.unwrap();
+
+  Ok(ApiCreatedToken {
+    token: token.into(),
+    secret,
+  })
+}
+
+#[instrument(
+  name = "DELETE /api/scopes/:scope/tokens/:token_id",
+  skip(req),
+  err,
+  fields(scope, token_id)
+)]
+pub async fn delete_token_handler(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let scope = req.param_scope()?;
+  let token_id = req.param_uuid("token_id")?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("token_id", field::display(&token_id));
+
+  let db = req.data::<Database>().unwrap();
+
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let iam = req.iam();
+  iam.check_scope_admin_access(&scope).await?;
+
+  if !db.delete_scope_token(&scope, token_id).await? {
+    return Err(ApiError::TokenNotFound);
+  }
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
+#[instrument(
+  name = "GET /api/scopes/:scope/recovery_requests",
+  skip(req),
+  err,
+  fields(scope)
+)]
+pub async fn list_recovery_requests_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiScopeRecoveryRequest>> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
+
+  let db = req.data::<Database>().unwrap();
+  db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+
+  let iam = req.iam();
+  iam.check_scope_write_access(&scope).await?;
+
+  let requests = db.list_scope_recovery_requests(&scope).await?;
+  Ok(requests.into_iter().map(Into::into).collect())
+}
+
+/// Requests that the current user becomes the creator of a scope whose creator
+/// was blocked, so that the scope can be managed again. If the user is an
+/// admin of the scope and no other admin could object, the request is approved
+/// right away. Otherwise the other admins are notified, and staff review it.
+#[instrument(
+  name = "POST /api/scopes/:scope/recovery_requests",
+  skip(req),
+  err,
+  fields(scope, user.id)
+)]
+pub async fn create_recovery_request_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiScopeRecoveryRequest> {
+  let scope = req.param_scope()?;
+  Span::current().record("scope", field::display(&scope));
+
+  let ApiCreateScopeRecoveryRequest { reason } = decode_json(&mut req).await?;
+  let reason = reason.trim();
+  if reason.is_empty() || reason.len() > 1000 {
+    return Err(ApiError::MalformedRequest {
+      msg: "reason must be between 1 and 1000 characters long".into(),
+    });
+  }
+
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?.clone();
+  Span::current().record("user.id", field::display(current_user.id));
+
+  let db = req.data::<Database>().unwrap();
+  let scope_ = db.get_scope(&scope).await?.ok_or(ApiError::ScopeNotFound)?;
+  let scope_member = db
+    .get_scope_member(&scope, current_user.id)
+    .await?
+    .ok_or(ApiError::ActorNotScopeMember)?;
+
+  let creator = db.get_user(scope_.creator).await?;
+  if creator.is_some_and(|creator| !creator.is_blocked) {
+    return Err(ApiError::ScopeNotOrphaned);
+  }
+
+  // Recovering a scope makes the requester its creator, so it counts against
+  // their scope limit just like creating a new scope would.
+  let trust_policy = req.data::<TrustPolicy>().unwrap();
+  let trust = db.get_user_trust_signals(current_user.id).await?;
+  let scope_limit = trust_policy
+    .scope_limit(trust_policy.level(&trust), current_user.scope_limit);
+  if current_user.scope_usage >= scope_limit.into() {
+    return Err(ApiError::ScopeLimitReached);
+  }
+
+  let other_admins = db
+    .list_scope_admins(&scope)
+    .await?
+    .into_iter()
+    .filter(|admin| admin.id != current_user.id && !admin.is_blocked)
+    .collect::<Vec<_>>();
+
+  let request = db
+    .create_scope_recovery_request(
+      &scope,
+      current_user.id,
+      scope_.creator,
+      reason,
+    )
+    .await
+    .map_err(|e| {
+      map_unique_violation(e, ApiError::ScopeRecoveryAlreadyRequested)
+    })?;
+
+  if scope_member.is_admin && other_admins.is_empty() {
+    let request = db
+      .resolve_scope_recovery_request(
+        request.id,
+        ScopeRecoveryStatus::Approved,
+        None,
+      )
+      .await?
+      .ok_or(ApiError::ScopeRecoveryRequestNotPending)?;
+    return Ok(request.into());
+  }
+
+  let email_sender = req.data::<Option<EmailSender>>().unwrap();
+  let registry_url = req.data::<RegistryUrl>().unwrap();
+  if let Some(email_sender) = email_sender {
+    for admin in &other_admins {
+      let Some(ref email) = admin.email else {
+        continue;
+      };
+      let email_args = EmailArgs::ScopeRecoveryRequest {
+        name: Cow::Borrowed(&admin.name),
+        requester_name: Cow::Borrowed(&current_user.name),
+        scope: Cow::Borrowed(&scope),
+        reason: Cow::Borrowed(reason),
+        registry_url: Cow::Borrowed(registry_url.0.as_str()),
+        registry_name: Cow::Borrowed(&email_sender.from_name),
+        support_email: Cow::Borrowed(&email_sender.from),
+      };
+      email_sender
+        .send(email.clone(), email_args)
+        .await
+        .map_err(|e| {
+          tracing::error!("failed to send email: {:?}", e);
+          ApiError::InternalServerError
+        })?;
+    }
+  }
+
+  Ok(request.into())
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::ids::PackageName;
+  use crate::ids::PackagePath;
+  use crate::ids::ScopeName;
+  use crate::ids::Version;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+  use serde_json::json;
+  use uuid::Uuid;
+
+  #[tokio::test]
+  async fn scope_get_create() {
+    let mut t = TestSetup::new().await;
+
+    let mut resp = t.http().get("/api/scopes/scope1").call().await.unwrap();
+    let err = resp.expect_err(StatusCode::NOT_FOUND).await;
+    assert_eq!(err.code, "scopeNotFound");
+
+    let mut resp = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "scope1" }))
+      .call()
+      .await
+      .unwrap();
+    let scope: ApiScope = resp.expect_ok().await;
+    assert_eq!(scope.scope.to_string(), "scope1");
+
+    let mut resp = t.http().get("/api/scopes/scope1").call().await.unwrap();
+    let scope: ApiScope = resp.expect_ok().await;
+    assert_eq!(scope.scope.to_string(), "scope1");
+
+    // duplicate scope name
+    let mut resp = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "scope1" }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::CONFLICT, "scopeAlreadyExists")
+      .await;
+
+    // duplicate scope name (only difference is dashes)
+    let mut resp = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "scop-e1" }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::CONFLICT, "scopeAlreadyExists")
+      .await;
+
+    // invalid name
+    let mut resp = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "scope 1" }))
+      .call()
+      .await
+      .unwrap();
+    let err = resp.expect_err(StatusCode::BAD_REQUEST).await;
+    assert_eq!(err.code, "malformedRequest");
+
+    // disallowed name
+    let mut resp = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "somebadword" }))
+      .call()
+      .await
+      .unwrap();
+    let err = resp.expect_err(StatusCode::BAD_REQUEST).await;
+    assert_eq!(err.code, "scopeNameNotAllowed");
+
+    // reserved name
+    let mut resp = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "react" }))
+      .call()
+      .await
+      .unwrap();
+    let err = resp.expect_err(StatusCode::BAD_REQUEST).await;
+    assert_eq!(err.code, "scopeNameReserved");
+  }
+
+  #[tokio::test]
+  async fn scope_limit() {
+    let mut t = TestSetup::new().await;
+
+    let previously_created_scopes = t
+      .db()
+      .list_scopes_created_by_user(t.user1.user.id)
+      .await
+      .unwrap()
+      .len();
+
+    // In TestSetup a scope has already been created for this user.
+    assert_eq!(previously_created_scopes, 1);
+
+    // Now create two more
+    let mut resp = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "scope1" }))
+      .call()
+      .await
+      .unwrap();
+    resp.expect_ok::<ApiScope>().await;
+    let mut resp: Response<Body> = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "scope2" }))
+      .call()
+      .await
+      .unwrap();
+    resp.expect_ok::<ApiScope>().await;
+
+    // Yet another one should fail due to limit.
+    let mut resp: Response<Body> = t
+      .http()
+      .post("/api/scopes")
+      .body_json(json!({ "scope": "scope3" }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::BAD_REQUEST, "scopeLimitReached")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn scope_update_gh_oidc_settings() {
+    let mut t = TestSetup::new().await;
+
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &t.scope.scope,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
+      .await
+      .unwrap();
+
+    let path = format!("/api/scopes/{}", t.scope.scope);
+    let token = t.user2.token.clone();
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "ghActionsVerifyActor": true }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+
+    let token = t.user1.token.clone();
+    let mut resp = t
+      .http()
+      .patch(&path)
       .body_json(json!({ "ghActionsVerifyActor": true }))
       .token(Some(&token))
       .call()
       .await
-      .unwrap();
-    resp
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .unwrap();
+    let scope = resp.expect_ok::<ApiFullScope>().await;
+    assert!(scope.gh_actions_verify_actor);
+
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "ghActionsVerifyActor": false }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    let scope = resp.expect_ok::<ApiFullScope>().await;
+    assert!(!scope.gh_actions_verify_actor);
+  }
+
+  #[tokio::test]
+  async fn scope_update_require_publishing_from_ci() {
+    let mut t = TestSetup::new().await;
+
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &t.scope.scope,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
+      .await
+      .unwrap();
+
+    let path = format!("/api/scopes/{}", t.scope.scope);
+    let token = t.user2.token.clone();
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "requirePublishingFromCI": true }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+
+    let token = t.user1.token.clone();
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "requirePublishingFromCI": true }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    let scope = resp.expect_ok::<ApiFullScope>().await;
+    assert!(scope.require_publishing_from_ci);
+
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "requirePublishingFromCI": false }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    let scope = resp.expect_ok::<ApiFullScope>().await;
+    assert!(!scope.require_publishing_from_ci);
+  }
+
+  #[tokio::test]
+  async fn scope_update_outdated_dependencies_email() {
+    let mut t = TestSetup::new().await;
+
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &t.scope.scope,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
+      .await
+      .unwrap();
+
+    let path = format!("/api/scopes/{}", t.scope.scope);
+    let token = t.user2.token.clone();
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "outdatedDependenciesEmail": true }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+    assert!(t
+      .db()
+      .list_scopes_with_outdated_dependencies_email()
+      .await
+      .unwrap()
+      .is_empty());
+
+    let token = t.user1.token.clone();
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "outdatedDependenciesEmail": true }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    let scope = resp.expect_ok::<ApiFullScope>().await;
+    assert!(scope.outdated_dependencies_email);
+    assert_eq!(
+      t.db()
+        .list_scopes_with_outdated_dependencies_email()
+        .await
+        .unwrap(),
+      vec![t.scope.scope.clone()]
+    );
+
+    // admins without an email address are skipped
+    let admins = t
+      .db()
+      .list_scope_admin_emails(&t.scope.scope)
+      .await
+      .unwrap();
+    assert!(admins.is_empty(), "{admins:?}");
+
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "outdatedDependenciesEmail": false }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    let scope = resp.expect_ok::<ApiFullScope>().await;
+    assert!(!scope.outdated_dependencies_email);
+  }
+
+  #[tokio::test]
+  async fn scope_update_download_anomaly_email() {
+    let mut t = TestSetup::new().await;
+
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &t.scope.scope,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
+      .await
+      .unwrap();
+
+    let path = format!("/api/scopes/{}", t.scope.scope);
+    let token = t.user2.token.clone();
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "downloadAnomalyEmail": true }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+
+    let token = t.user1.token.clone();
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "downloadAnomalyEmail": true }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    let scope = resp.expect_ok::<ApiFullScope>().await;
+    assert!(scope.download_anomaly_email);
+    assert!(!scope.outdated_dependencies_email);
+
+    let mut resp = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "downloadAnomalyEmail": false }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    let scope = resp.expect_ok::<ApiFullScope>().await;
+    assert!(!scope.download_anomaly_email);
+  }
+
+  #[tokio::test]
+  async fn scope_update_docs_coverage_policy() {
+    let mut t = TestSetup::new().await;
+    let path = format!("/api/scopes/{}", t.scope.scope);
+
+    let scope = t
+      .http()
+      .patch(&path)
+      .body_json(json!({
+        "docsCoveragePolicy": { "minCoverage": 0.8, "enforce": true }
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiFullScope>()
+      .await;
+    assert_eq!(
+      scope.docs_coverage_policy,
+      ApiDocsCoveragePolicy {
+        min_coverage: Some(0.8),
+        enforce: true,
+      }
+    );
+
+    t.http()
+      .patch(&path)
+      .body_json(json!({
+        "docsCoveragePolicy": { "minCoverage": 1.5, "enforce": false }
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    t.http()
+      .patch(&path)
+      .body_json(json!({
+        "docsCoveragePolicy": { "minCoverage": null, "enforce": true }
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    let scope = t
+      .http()
+      .patch(&path)
+      .body_json(json!({
+        "docsCoveragePolicy": { "minCoverage": null, "enforce": false }
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiFullScope>()
+      .await;
+    assert_eq!(scope.docs_coverage_policy.min_coverage, None);
+  }
+
+  #[tokio::test]
+  async fn scope_update_profile() {
+    let mut t = TestSetup::new().await;
+
+    let scope_name = t.scope.scope.clone();
+    for name in ["foo", "bar"] {
+      t.db()
+        .create_package(&scope_name, &PackageName::try_from(name).unwrap())
+        .await
+        .unwrap();
+    }
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &scope_name,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
+      .await
+      .unwrap();
+
+    let path = format!("/api/scopes/{scope_name}");
+    let token = t.user2.token.clone();
+    t.http()
+      .patch(&path)
+      .body_json(json!({ "readme": "# Hello" }))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+
+    let scope = t
+      .http()
+      .patch(&path)
+      .body_json(json!({ "readme": "# Hello\n" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiFullScope>()
+      .await;
+    let profile = scope.profile.unwrap();
+    assert_eq!(profile.readme.as_deref(), Some("# Hello"));
+    assert!(profile.pinned_packages.is_empty());
+
+    t.http()
+      .patch(&path)
+      .body_json(json!({ "pinnedPackages": ["bar", "foo"] }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiFullScope>()
+      .await;
+
+    // the profile is public
+    let scope = t
+      .unauthed_http()
+      .get(&path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiScope>()
+      .await;
+    let profile = scope.profile.unwrap();
+    assert_eq!(profile.readme.as_deref(), Some("# Hello"));
+    assert_eq!(
+      profile.pinned_packages,
+      vec![
+        PackageName::try_from("bar").unwrap(),
+        PackageName::try_from("foo").unwrap()
+      ]
+    );
+
+    t.http()
+      .patch(&path)
+      .body_json(json!({ "pinnedPackages": ["foo", "foo"] }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
       .await;
 
-    let token = t.user1.token.clone();
-    let mut resp = t
+    t.http()
+      .patch(&path)
+      .body_json(json!({ "pinnedPackages": ["foo", "baz"] }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+
+    let scope = t
       .http()
       .patch(&path)
-      .body_json(json!({ "ghActionsVerifyActor": true }))
+      .body_json(json!({ "readme": null }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiFullScope>()
+      .await;
+    let profile = scope.profile.unwrap();
+    assert_eq!(profile.readme, None);
+    assert_eq!(profile.pinned_packages.len(), 2);
+  }
+
+  async fn list_members(t: &mut TestSetup) -> Vec<ApiScopeMember> {
+    // list
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope1/members")
+      .call()
+      .await
+      .unwrap();
+    resp.expect_ok().await
+  }
+
+  async fn add_member(
+    t: &mut TestSetup,
+    token: String,
+    sudo: bool,
+    github_login: String,
+  ) -> Response<Body> {
+    let body = json!({ "githubLogin": github_login });
+    t.http()
+      .post("/api/scopes/scope1/members")
+      .body_json(body)
+      .token(Some(&token))
+      .sudo(sudo)
+      .call()
+      .await
+      .unwrap()
+  }
+
+  async fn accept_invite(t: &mut TestSetup, token: String) -> Response<Body> {
+    t.http()
+      .post("/api/user/invites/scope1")
       .token(Some(&token))
       .call()
       .await
-      .unwrap();
-    let scope = resp.expect_ok::<ApiFullScope>().await;
-    assert!(scope.gh_actions_verify_actor);
+      .unwrap()
+  }
+
+  #[tokio::test]
+  async fn scope_members_add_as_admin() {
+    // - admin adds themselves
+    // - admin adds user
+    // - admin adds member
+
+    let mut t = TestSetup::new().await;
+
+    let scope_name = ScopeName::try_from("scope1").unwrap();
+    t.db()
+      .create_scope(&scope_name, t.user1.user.id)
+      .await
+      .unwrap();
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 1);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+
+    // user1 (admin) adds themselves
+    let github_login = t.user1.github_name.clone();
+    let token = t.user1.token.clone();
+    add_member(&mut t, token, false, github_login)
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "alreadyScopeMember")
+      .await;
+
+    // user1 (admin) adds user2
+    let github_login = t.user2.github_name.clone();
+    let token = t.user1.token.clone();
+    let mut resp = add_member(&mut t, token, false, github_login).await;
+    let invite: ApiScopeInvite = resp.expect_ok().await;
+    assert_eq!(invite.scope.to_string(), "scope1");
+    assert_eq!(invite.target_user.id, t.user2.user.id);
+    assert_eq!(invite.requesting_user.id, t.user1.user.id);
+
+    // accept invite from wrong account fails
+    let token = t.user3.token.clone();
+    accept_invite(&mut t, token)
+      .await
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeInviteNotFound")
+      .await;
+
+    // accept invite from invited account works
+    let token = t.user2.token.clone();
+    let mut resp = accept_invite(&mut t, token).await;
+    let member: ApiScopeMember = resp.expect_ok().await;
+    assert_eq!(member.scope.to_string(), "scope1");
+    assert_eq!(member.user.id, t.user2.user.id);
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
+
+    // user1 (admin) adds user2 again
+    let github_login = t.user2.github_name.clone();
+    let token = t.user1.token.clone();
+    add_member(&mut t, token, false, github_login)
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "alreadyScopeMember")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn scope_members_add_as_member() {
+    // - member adds themselves
+    // - member adds user
+
+    let mut t = TestSetup::new().await;
+
+    let scope_name = ScopeName::try_from("scope1").unwrap();
+    t.db()
+      .create_scope(&scope_name, t.user1.user.id)
+      .await
+      .unwrap();
+
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &scope_name,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
+      .await
+      .unwrap();
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
+
+    // user2 (member) adds themselves
+    let github_login = t.user2.github_name.clone();
+    let token = t.user2.token.clone();
+    add_member(&mut t, token, false, github_login)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+
+    // user2 (member) adds user3
+    let github_login = t.user3.github_name.clone();
+    let token = t.user2.token.clone();
+    add_member(&mut t, token, false, github_login)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+  }
+
+  #[tokio::test]
+  async fn scope_members_add_as_user() {
+    // - user adds member
+    // - user adds themselves
+
+    let mut t = TestSetup::new().await;
+
+    let scope_name = ScopeName::try_from("scope1").unwrap();
+    t.db()
+      .create_scope(&scope_name, t.user1.user.id)
+      .await
+      .unwrap();
+
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &scope_name,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
+      .await
+      .unwrap();
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
+
+    // user3 adds user2 (member)
+    let github_login = t.user2.github_name.clone();
+    let token = t.user3.token.clone();
+    add_member(&mut t, token, false, github_login)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
+
+    // user3 adds user3
+    let github_login = t.user2.github_name.clone();
+    let token = t.user3.token.clone();
+    add_member(&mut t, token, false, github_login)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+  }
+
+  #[tokio::test]
+  async fn scope_members_add_as_staff() {
+    // - staff adds user (no sudo)
+    // - staff adds user (sudo)
+
+    let mut t = TestSetup::new().await;
+
+    let scope_name = ScopeName::try_from("scope1").unwrap();
+    t.db()
+      .create_scope(&scope_name, t.user1.user.id)
+      .await
+      .unwrap();
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 1);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+
+    // staff adds user2 without sudo
+    let github_login = t.user2.github_name.clone();
+    let token = t.staff_user.token.clone();
+    add_member(&mut t, token, false, github_login)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
+
+    // staff adds user2
+    let github_login = t.user2.github_name.clone();
+    let token = t.staff_user.token.clone();
+    add_member(&mut t, token, true, github_login)
+      .await
+      .expect_ok::<ApiScopeInvite>()
+      .await;
+
+    let token2 = t.user2.token.clone();
+    accept_invite(&mut t, token2)
+      .await
+      .expect_ok::<ApiScopeMember>()
+      .await;
 
-    let mut resp = t
-      .http()
-      .patch(&path)
-      .body_json(json!({ "ghActionsVerifyActor": false }))
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+  }
+
+  async fn update_member_permission(
+    t: &mut TestSetup,
+    token: String,
+    sudo: bool,
+    user_id: Uuid,
+    is_admin: bool,
+  ) -> Response<Body> {
+    let body = json!({ "isAdmin": is_admin });
+    t.http()
+      .patch(format!("/api/scopes/scope1/members/{}", user_id))
+      .body_json(body)
       .token(Some(&token))
+      .sudo(sudo)
       .call()
       .await
-      .unwrap();
-    let scope = resp.expect_ok::<ApiFullScope>().await;
-    assert!(!scope.gh_actions_verify_actor);
+      .unwrap()
   }
 
   #[tokio::test]
-  async fn scope_update_require_publishing_from_ci() {
+  async fn scope_member_upgrade_permission_as_admin() {
+    // - admin upgrade themselves
+    // - admin upgrade member
+    // - admin upgrade user
+
     let mut t = TestSetup::new().await;
 
+    let scope_name = ScopeName::try_from("scope1").unwrap();
+    t.db()
+      .create_scope(&scope_name, t.user1.user.id)
+      .await
+      .unwrap();
+
     t.db()
       .add_user_to_scope(NewScopeMember {
-        scope: &t.scope.scope,
+        scope: &scope_name,
         user_id: t.user2.user.id,
         is_admin: false,
       })
       .await
       .unwrap();
 
-    let path = format!("/api/scopes/{}", t.scope.scope);
-    let token = t.user2.token.clone();
-    let mut resp = t
-      .http()
-      .patch(&path)
-      .body_json(json!({ "requirePublishingFromCI": true }))
-      .token(Some(&token))
-      .call()
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
+
+    // user1 (admin) upgrades themselves
+    let user_id = t.user1.user.id;
+    let token = t.user1.token.clone();
+    let member = update_member_permission(&mut t, token, false, user_id, true)
       .await
-      .unwrap();
-    resp
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .expect_ok::<ApiScopeMember>()
       .await;
+    assert!(member.is_admin);
 
+    // user1 (admin) upgrades user2 (member)
+    let user_id = t.user2.user.id;
     let token = t.user1.token.clone();
-    let mut resp = t
-      .http()
-      .patch(&path)
-      .body_json(json!({ "requirePublishingFromCI": true }))
-      .token(Some(&token))
-      .call()
+    let member = update_member_permission(&mut t, token, false, user_id, true)
       .await
-      .unwrap();
-    let scope = resp.expect_ok::<ApiFullScope>().await;
-    assert!(scope.require_publishing_from_ci);
+      .expect_ok::<ApiScopeMember>()
+      .await;
+    assert!(member.is_admin);
 
-    let mut resp = t
-      .http()
-      .patch(&path)
-      .body_json(json!({ "requirePublishingFromCI": false }))
-      .token(Some(&token))
-      .call()
+    // user1 (admin) upgrades user3 (not scope member)
+    let user_id = t.user3.user.id;
+    let token = t.user1.token.clone();
+    update_member_permission(&mut t, token, false, user_id, true)
       .await
-      .unwrap();
-    let scope = resp.expect_ok::<ApiFullScope>().await;
-    assert!(!scope.require_publishing_from_ci);
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
+      .await;
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
-  async fn list_members(t: &mut TestSetup) -> Vec<ApiScopeMember> {
-    // list
-    let mut resp = t
-      .http()
-      .get("/api/scopes/scope1/members")
-      .call()
+  #[tokio::test]
+  async fn scope_member_downgrade_permission_as_admin() {
+    // - admin downgrades themselves (last)
+    // - admin downgrades member
+    // - admin downgrades user
+    // - admin downgrades themselves
+
+    let mut t = TestSetup::new().await;
+
+    let scope_name = ScopeName::try_from("scope1").unwrap();
+    t.db()
+      .create_scope(&scope_name, t.user1.user.id)
       .await
       .unwrap();
-    resp.expect_ok().await
-  }
 
-  async fn add_member(
-    t: &mut TestSetup,
-    token: String,
-    sudo: bool,
-    github_login: String,
-  ) -> Response<Body> {
-    let body = json!({ "githubLogin": github_login });
-    t.http()
-      .post("/api/scopes/scope1/members")
-      .body_json(body)
-      .token(Some(&token))
-      .sudo(sudo)
-      .call()
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &scope_name,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
       .await
-      .unwrap()
-  }
+      .unwrap();
 
-  async fn accept_invite(t: &mut TestSetup, token: String) -> Response<Body> {
-    t.http()
-      .post("/api/user/invites/scope1")
-      .token(Some(&token))
-      .call()
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
+
+    // user1 (admin) downgrades user2 (member)
+    let user_id = t.user2.user.id;
+    let token = t.user1.token.clone();
+    let member = update_member_permission(&mut t, token, false, user_id, false)
       .await
-      .unwrap()
+      .expect_ok::<ApiScopeMember>()
+      .await;
+    assert!(!member.is_admin);
+
+    // user2 (admin) downgrades user3 (not scope member)
+    let user_id = t.user3.user.id;
+    let token = t.user1.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
+      .await
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
+      .await;
+
+    // user1 (admin) downgrades themselves
+    let user_id = t.user1.user.id;
+    let token = t.user1.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "scopeMustHaveAdmin")
+      .await;
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_members_add_as_admin() {
-    // - admin adds themselves
-    // - admin adds user
-    // - admin adds member
+  async fn scope_member_downgrade_permission_as_admin_not_last1() {
+    // - admin downgrades themselves (not-last)
 
     let mut t = TestSetup::new().await;
 
@@ -768,62 +2097,90 @@ pub mod tests {
       .create_scope(&scope_name, t.user1.user.id)
       .await
       .unwrap();
+
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &scope_name,
+        user_id: t.user2.user.id,
+        is_admin: true,
+      })
+      .await
+      .unwrap();
+
     let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 1);
+    assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user1 (admin) adds themselves
-    let github_login = t.user1.github_name.clone();
+    // user1 (admin) downgrades themselves
+    let user_id = t.user1.user.id;
     let token = t.user1.token.clone();
-    add_member(&mut t, token, false, github_login)
+    let member = update_member_permission(&mut t, token, false, user_id, false)
       .await
-      .expect_err_code(StatusCode::BAD_REQUEST, "alreadyScopeMember")
+      .expect_ok::<ApiScopeMember>()
       .await;
+    assert!(!member.is_admin);
 
-    // user1 (admin) adds user2
-    let github_login = t.user2.github_name.clone();
-    let token = t.user1.token.clone();
-    let mut resp = add_member(&mut t, token, false, github_login).await;
-    let invite: ApiScopeInvite = resp.expect_ok().await;
-    assert_eq!(invite.scope.to_string(), "scope1");
-    assert_eq!(invite.target_user.id, t.user2.user.id);
-    assert_eq!(invite.requesting_user.id, t.user1.user.id);
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(!members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
+  }
 
-    // accept invite from wrong account fails
-    let token = t.user3.token.clone();
-    accept_invite(&mut t, token)
+  #[tokio::test]
+  async fn scope_member_downgrade_permission_as_admin_not_last2() {
+    // - admin downgrades admin (not-last)
+
+    let mut t = TestSetup::new().await;
+
+    let scope_name = ScopeName::try_from("scope1").unwrap();
+    t.db()
+      .create_scope(&scope_name, t.user1.user.id)
       .await
-      .expect_err_code(StatusCode::NOT_FOUND, "scopeInviteNotFound")
-      .await;
+      .unwrap();
 
-    // accept invite from invited account works
-    let token = t.user2.token.clone();
-    let mut resp = accept_invite(&mut t, token).await;
-    let member: ApiScopeMember = resp.expect_ok().await;
-    assert_eq!(member.scope.to_string(), "scope1");
-    assert_eq!(member.user.id, t.user2.user.id);
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &scope_name,
+        user_id: t.user2.user.id,
+        is_admin: true,
+      })
+      .await
+      .unwrap();
 
     let members = list_members(&mut t).await;
     assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
+    assert!(members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user1 (admin) adds user2 again
-    let github_login = t.user2.github_name.clone();
+    // user1 (admin) downgrades user2 (admin)
+    let user_id = t.user2.user.id;
     let token = t.user1.token.clone();
-    add_member(&mut t, token, false, github_login)
+    let member = update_member_permission(&mut t, token, false, user_id, false)
       .await
-      .expect_err_code(StatusCode::BAD_REQUEST, "alreadyScopeMember")
+      .expect_ok::<ApiScopeMember>()
       .await;
+    assert!(!member.is_admin);
+
+    let members = list_members(&mut t).await;
+    assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_members_add_as_member() {
-    // - member adds themselves
-    // - member adds user
+  async fn scope_member_upgrade_permission_as_member() {
+    // - member upgrades admin
+    // - member upgrades themselves
+    // - member upgrades user
 
     let mut t = TestSetup::new().await;
 
@@ -849,30 +2206,43 @@ pub mod tests {
     assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user2 (member) adds themselves
-    let github_login = t.user2.github_name.clone();
+    // user2 (member) upgrades user1 (admin)
+    let user_id = t.user1.user.id;
     let token = t.user2.token.clone();
-    add_member(&mut t, token, false, github_login)
+    update_member_permission(&mut t, token, false, user_id, true)
       .await
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
       .await;
 
-    // user2 (member) adds user3
-    let github_login = t.user3.github_name.clone();
+    // user2 (member) upgrades themselves
+    let user_id = t.user2.user.id;
     let token = t.user2.token.clone();
-    add_member(&mut t, token, false, github_login)
+    update_member_permission(&mut t, token, false, user_id, true)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+
+    // user2 (member) upgrades user3 (user)
+    let user_id = t.user3.user.id;
+    let token = t.user2.token.clone();
+    update_member_permission(&mut t, token, false, user_id, true)
       .await
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
       .await;
 
     let members = list_members(&mut t).await;
     assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_members_add_as_user() {
-    // - user adds member
-    // - user adds themselves
+  async fn scope_member_downgrade_permission_as_member() {
+    // - member downgrades admin
+    // - member downgrades themselves
+    // - member downgrades user
 
     let mut t = TestSetup::new().await;
 
@@ -898,30 +2268,43 @@ pub mod tests {
     assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user3 adds user2 (member)
-    let github_login = t.user2.github_name.clone();
-    let token = t.user3.token.clone();
-    add_member(&mut t, token, false, github_login)
+    // user2 (member) downgrades user1 (admin)
+    let user_id = t.user1.user.id;
+    let token = t.user2.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
       .await;
 
-    // user3 adds user3
-    let github_login = t.user2.github_name.clone();
-    let token = t.user3.token.clone();
-    add_member(&mut t, token, false, github_login)
+    // user2 (member) downgrades themselves
+    let user_id = t.user2.user.id;
+    let token = t.user2.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
+
+    // user2 (member) downgrades user3 (not scope member)
+    let user_id = t.user3.user.id;
+    let token = t.user2.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
       .await;
 
     let members = list_members(&mut t).await;
     assert_eq!(members.len(), 2);
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_members_add_as_staff() {
-    // - staff adds user (no sudo)
-    // - staff adds user (sudo)
+  async fn scope_member_upgrade_permission_as_user() {
+    // - user upgrades admin
+    // - user upgrades member
+    // - user upgrades themselves
 
     let mut t = TestSetup::new().await;
 
@@ -931,60 +2314,67 @@ pub mod tests {
       .await
       .unwrap();
 
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &scope_name,
+        user_id: t.user2.user.id,
+        is_admin: false,
+      })
+      .await
+      .unwrap();
+
     let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 1);
+    assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // staff adds user2 without sudo
-    let github_login = t.user2.github_name.clone();
-    let token = t.staff_user.token.clone();
-    add_member(&mut t, token, false, github_login)
+    // user3 (user) upgrades user1 (admin)
+    let user_id = t.user1.user.id;
+    let token = t.user3.token.clone();
+    update_member_permission(&mut t, token, false, user_id, true)
       .await
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
-    // staff adds user2
-    let github_login = t.user2.github_name.clone();
-    let token = t.staff_user.token.clone();
-    add_member(&mut t, token, true, github_login)
+    // user3 (user) upgrades user2 (member)
+    let user_id = t.user2.user.id;
+    let token = t.user3.token.clone();
+    update_member_permission(&mut t, token, false, user_id, true)
       .await
-      .expect_ok::<ApiScopeInvite>()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
-    let token2 = t.user2.token.clone();
-    accept_invite(&mut t, token2)
+    // user3 (user) upgrades themselves
+    let user_id = t.user3.user.id;
+    let token = t.user3.token.clone();
+    update_member_permission(&mut t, token, false, user_id, true)
       .await
-      .expect_ok::<ApiScopeMember>()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
+
+    // user3 (user) upgrades admin_user (not scope member)
+    let user_id = t.staff_user.user.id;
+    let token = t.user3.token.clone();
+    update_member_permission(&mut t, token, false, user_id, true)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
     let members = list_members(&mut t).await;
     assert_eq!(members.len(), 2);
-  }
-
-  async fn update_member_permission(
-    t: &mut TestSetup,
-    token: String,
-    sudo: bool,
-    user_id: Uuid,
-    is_admin: bool,
-  ) -> Response<Body> {
-    let body = json!({ "isAdmin": is_admin });
-    t.http()
-      .patch(format!("/api/scopes/scope1/members/{}", user_id))
-      .body_json(body)
-      .token(Some(&token))
-      .sudo(sudo)
-      .call()
-      .await
-      .unwrap()
+    assert!(members[0].is_admin);
+    assert_eq!(members[0].user.id, t.user1.user.id);
+    assert!(!members[1].is_admin);
+    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_member_upgrade_permission_as_admin() {
-    // - admin upgrade themselves
-    // - admin upgrade member
-    // - admin upgrade user
+  async fn scope_member_downgrade_permission_as_user() {
+    // - user downgrades admin
+    // - user downgrades member
+    // - user downgrades themselves
 
     let mut t = TestSetup::new().await;
 
@@ -1010,46 +2400,51 @@ pub mod tests {
     assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user1 (admin) upgrades themselves
+    // user3 (user) downgrades user1 (admin)
     let user_id = t.user1.user.id;
-    let token = t.user1.token.clone();
-    let member = update_member_permission(&mut t, token, false, user_id, true)
+    let token = t.user3.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
       .await
-      .expect_ok::<ApiScopeMember>()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
-    assert!(member.is_admin);
 
-    // user1 (admin) upgrades user2 (member)
+    // user3 (user) upgrades user2 (member)
     let user_id = t.user2.user.id;
-    let token = t.user1.token.clone();
-    let member = update_member_permission(&mut t, token, false, user_id, true)
+    let token = t.user3.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
       .await
-      .expect_ok::<ApiScopeMember>()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
-    assert!(member.is_admin);
 
-    // user1 (admin) upgrades user3 (not scope member)
+    // user3 (user) upgrades themselves
     let user_id = t.user3.user.id;
-    let token = t.user1.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
+    let token = t.user3.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
       .await
-      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
+
+    // user3 (user) upgrades admin_user (not scope member)
+    let user_id = t.staff_user.user.id;
+    let token = t.user3.token.clone();
+    update_member_permission(&mut t, token, false, user_id, false)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
     let members = list_members(&mut t).await;
     assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(members[1].is_admin);
+    assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_member_downgrade_permission_as_admin() {
-    // - admin downgrades themselves (last)
-    // - admin downgrades member
-    // - admin downgrades user
-    // - admin downgrades themselves
+  async fn scope_member_upgrade_permission_as_staff() {
+    // - staff upgrades admin (sudo)
+    // - staff upgrades member (sudo)
+    // - staff upgrades user (sudo)
 
     let mut t = TestSetup::new().await;
 
@@ -1075,42 +2470,53 @@ pub mod tests {
     assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user1 (admin) downgrades user2 (member)
-    let user_id = t.user2.user.id;
-    let token = t.user1.token.clone();
-    let member = update_member_permission(&mut t, token, false, user_id, false)
+    // staff upgrades user1 (admin) without sudo
+    let user_id = t.user1.user.id;
+    let token = t.staff_user.token.clone();
+    update_member_permission(&mut t, token, false, user_id, true)
+      .await
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
+
+    // staff upgrades user1 (admin)
+    let user_id = t.user1.user.id;
+    let token = t.staff_user.token.clone();
+    let member = update_member_permission(&mut t, token, true, user_id, true)
       .await
       .expect_ok::<ApiScopeMember>()
       .await;
-    assert!(!member.is_admin);
+    assert!(member.is_admin);
 
-    // user2 (admin) downgrades user3 (not scope member)
-    let user_id = t.user3.user.id;
-    let token = t.user1.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
+    // staff upgrades user2 (member)
+    let user_id = t.user2.user.id;
+    let token = t.staff_user.token.clone();
+    let member = update_member_permission(&mut t, token, true, user_id, true)
       .await
-      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
+      .expect_ok::<ApiScopeMember>()
       .await;
+    assert!(member.is_admin);
 
-    // user1 (admin) downgrades themselves
-    let user_id = t.user1.user.id;
-    let token = t.user1.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
+    // staff upgrades user3 (not scope member)
+    let user_id = t.user3.user.id;
+    let token = t.staff_user.token.clone();
+    update_member_permission(&mut t, token, true, user_id, true)
       .await
-      .expect_err_code(StatusCode::BAD_REQUEST, "scopeMustHaveAdmin")
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
       .await;
 
     let members = list_members(&mut t).await;
     assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
+    assert!(members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_member_downgrade_permission_as_admin_not_last1() {
-    // - admin downgrades themselves (not-last)
+  async fn scope_member_downgrade_permission_as_staff() {
+    // - staff downgrades admin (sudo)
+    // - staff downgrades member (sudo)
+    // - staff downgrades user (sudo)
 
     let mut t = TestSetup::new().await;
 
@@ -1124,7 +2530,7 @@ pub mod tests {
       .add_user_to_scope(NewScopeMember {
         scope: &scope_name,
         user_id: t.user2.user.id,
-        is_admin: true,
+        is_admin: false,
       })
       .await
       .unwrap();
@@ -1133,29 +2539,55 @@ pub mod tests {
     assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(members[1].is_admin);
+    assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user1 (admin) downgrades themselves
+    // staff downgrades user1 (admin)
     let user_id = t.user1.user.id;
-    let token = t.user1.token.clone();
-    let member = update_member_permission(&mut t, token, false, user_id, false)
+    let token = t.staff_user.token.clone();
+    update_member_permission(&mut t, token, true, user_id, false)
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "scopeMustHaveAdmin")
+      .await;
+
+    // staff downgrades user2 (member)
+    let user_id = t.user2.user.id;
+    let token = t.staff_user.token.clone();
+    let member = update_member_permission(&mut t, token, true, user_id, false)
       .await
       .expect_ok::<ApiScopeMember>()
       .await;
     assert!(!member.is_admin);
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(!members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
+    // staff downgrades user3 (not scope member)
+    let user_id = t.user3.user.id;
+    let token = t.staff_user.token.clone();
+    update_member_permission(&mut t, token, true, user_id, false)
+      .await
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
+      .await;
+  }
+
+  async fn remove_member(
+    t: &mut TestSetup,
+    token: String,
+    sudo: bool,
+    user_id: Uuid,
+  ) -> Response<Body> {
+    t.http()
+      .delete(format!("/api/scopes/scope1/members/{}", user_id))
+      .token(Some(&token))
+      .sudo(sudo)
+      .call()
+      .await
+      .unwrap()
   }
 
   #[tokio::test]
-  async fn scope_member_downgrade_permission_as_admin_not_last2() {
-    // - admin downgrades admin (not-last)
+  async fn scope_members_remove_as_admin() {
+    // - admin removes themselves (last)
+    // - admin removes member
+    // - admin removes user
 
     let mut t = TestSetup::new().await;
 
@@ -1169,7 +2601,7 @@ pub mod tests {
       .add_user_to_scope(NewScopeMember {
         scope: &scope_name,
         user_id: t.user2.user.id,
-        is_admin: true,
+        is_admin: false,
       })
       .await
       .unwrap();
@@ -1178,31 +2610,39 @@ pub mod tests {
     assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(members[1].is_admin);
+    assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user1 (admin) downgrades user2 (admin)
+    // user1 (admin) removes themselves
+    let user_id = t.user1.user.id;
+    let token = t.user1.token.clone();
+    remove_member(&mut t, token, false, user_id)
+      .await
+      .expect_err_code(StatusCode::BAD_REQUEST, "scopeMustHaveAdmin")
+      .await;
+
+    // user1 (admin) removes user2 (member)
     let user_id = t.user2.user.id;
     let token = t.user1.token.clone();
-    let member = update_member_permission(&mut t, token, false, user_id, false)
+    let resp = remove_member(&mut t, token, false, user_id).await;
+    assert!(resp.status().is_success());
+
+    // user1 (owner) removes user3 (not scope member)
+    let user_id = t.user3.user.id;
+    let token = t.user1.token.clone();
+    remove_member(&mut t, token, false, user_id)
       .await
-      .expect_ok::<ApiScopeMember>()
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
       .await;
-    assert!(!member.is_admin);
 
     let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
+    assert_eq!(members.len(), 1);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_member_upgrade_permission_as_member() {
-    // - member upgrades admin
-    // - member upgrades themselves
-    // - member upgrades user
+  async fn scope_members_remove_as_admin_not_last1() {
+    // - admin removes themselves (not-last)
 
     let mut t = TestSetup::new().await;
 
@@ -1216,7 +2656,7 @@ pub mod tests {
       .add_user_to_scope(NewScopeMember {
         scope: &scope_name,
         user_id: t.user2.user.id,
-        is_admin: false,
+        is_admin: true,
       })
       .await
       .unwrap();
@@ -1225,46 +2665,26 @@ pub mod tests {
     assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
+    assert!(members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user2 (member) upgrades user1 (admin)
+    // user1 (admin) removes themselves
     let user_id = t.user1.user.id;
-    let token = t.user2.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
-      .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
-      .await;
-
-    // user2 (member) upgrades themselves
-    let user_id = t.user2.user.id;
-    let token = t.user2.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
-      .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
-      .await;
-
-    // user2 (member) upgrades user3 (user)
-    let user_id = t.user3.user.id;
-    let token = t.user2.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
+    let token = t.user1.token.clone();
+    remove_member(&mut t, token, false, user_id)
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .expect_ok_no_content()
       .await;
 
     let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
+    assert_eq!(members.len(), 1);
     assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
+    assert_eq!(members[0].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_member_downgrade_permission_as_member() {
-    // - member downgrades admin
-    // - member downgrades themselves
-    // - member downgrades user
+  async fn scope_members_remove_as_admin_not_last2() {
+    // - admin removes admin (not-last)
 
     let mut t = TestSetup::new().await;
 
@@ -1278,7 +2698,7 @@ pub mod tests {
       .add_user_to_scope(NewScopeMember {
         scope: &scope_name,
         user_id: t.user2.user.id,
-        is_admin: false,
+        is_admin: true,
       })
       .await
       .unwrap();
@@ -1287,46 +2707,28 @@ pub mod tests {
     assert_eq!(members.len(), 2);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
+    assert!(members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user2 (member) downgrades user1 (admin)
-    let user_id = t.user1.user.id;
-    let token = t.user2.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
-      .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
-      .await;
-
-    // user2 (member) downgrades themselves
+    // user1 (admin) removes user2 (admin)
     let user_id = t.user2.user.id;
-    let token = t.user2.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
-      .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
-      .await;
-
-    // user2 (member) downgrades user3 (not scope member)
-    let user_id = t.user3.user.id;
-    let token = t.user2.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
+    let token = t.user1.token.clone();
+    remove_member(&mut t, token, false, user_id)
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .expect_ok_no_content()
       .await;
 
     let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
+    assert_eq!(members.len(), 1);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_member_upgrade_permission_as_user() {
-    // - user upgrades admin
-    // - user upgrades member
-    // - user upgrades themselves
+  async fn scope_members_remove_as_member() {
+    // - member removes admin
+    // - member removes member
+    // - member removes user
 
     let mut t = TestSetup::new().await;
 
@@ -1352,51 +2754,40 @@ pub mod tests {
     assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user3 (user) upgrades user1 (admin)
+    // user2 (member) removes user1 (admin)
     let user_id = t.user1.user.id;
-    let token = t.user3.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
-      .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
-      .await;
-
-    // user3 (user) upgrades user2 (member)
-    let user_id = t.user2.user.id;
-    let token = t.user3.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
+    let token = t.user2.token.clone();
+    remove_member(&mut t, token, false, user_id)
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
       .await;
 
-    // user3 (user) upgrades themselves
+    // user2 (member) removes user3 (not scope member)
     let user_id = t.user3.user.id;
-    let token = t.user3.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
+    let token = t.user2.token.clone();
+    remove_member(&mut t, token, false, user_id)
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
       .await;
 
-    // user3 (user) upgrades admin_user (not scope member)
-    let user_id = t.staff_user.user.id;
-    let token = t.user3.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
+    // user2 (member) removes themselves
+    let user_id = t.user2.user.id;
+    let token = t.user2.token.clone();
+    remove_member(&mut t, token, false, user_id)
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .expect_ok_no_content()
       .await;
 
     let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
+    assert_eq!(members.len(), 1);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_member_downgrade_permission_as_user() {
-    // - user downgrades admin
-    // - user downgrades member
-    // - user downgrades themselves
+  async fn scope_members_remove_as_user() {
+    // - user removes admin
+    // - user removes member
+    // - user removes themselves
 
     let mut t = TestSetup::new().await;
 
@@ -1422,51 +2813,43 @@ pub mod tests {
     assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
 
-    // user3 (user) downgrades user1 (admin)
+    // user3 (not scope owner) removes user1 (admin)
     let user_id = t.user1.user.id;
     let token = t.user3.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
+    remove_member(&mut t, token, false, user_id)
       .await
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
-    // user3 (user) upgrades user2 (member)
+    // user3 (not scope owner) removes user2 (member)
     let user_id = t.user2.user.id;
     let token = t.user3.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
+    remove_member(&mut t, token, false, user_id)
       .await
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
-    // user3 (user) upgrades themselves
+    // user3 (not scope owner) removes themselves
     let user_id = t.user3.user.id;
     let token = t.user3.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
-      .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
-      .await;
-
-    // user3 (user) upgrades admin_user (not scope member)
-    let user_id = t.staff_user.user.id;
-    let token = t.user3.token.clone();
-    update_member_permission(&mut t, token, false, user_id, false)
+    remove_member(&mut t, token, false, user_id)
       .await
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
     let members = list_members(&mut t).await;
     assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
   }
 
-  #[tokio::test]
-  async fn scope_member_upgrade_permission_as_staff() {
-    // - staff upgrades admin (sudo)
-    // - staff upgrades member (sudo)
-    // - staff upgrades user (sudo)
+  #[tokio::test]
+  async fn scope_members_remove_as_staff() {
+    // - staff removes admin (not-last) (not sudo)
+    // - staff removes admin (not-last) (sudo)
+    // - staff removes admin (last) (sudo)
+    // - staff removes member (sudo)
+    // - staff removes user (sudo)
 
     let mut t = TestSetup::new().await;
 
@@ -1480,242 +2863,235 @@ pub mod tests {
       .add_user_to_scope(NewScopeMember {
         scope: &scope_name,
         user_id: t.user2.user.id,
+        is_admin: true,
+      })
+      .await
+      .unwrap();
+
+    t.db()
+      .add_user_to_scope(NewScopeMember {
+        scope: &scope_name,
+        user_id: t.user3.user.id,
         is_admin: false,
       })
       .await
       .unwrap();
 
     let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
+    assert_eq!(members.len(), 3);
     assert!(members[0].is_admin);
     assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
+    assert!(members[1].is_admin);
     assert_eq!(members[1].user.id, t.user2.user.id);
+    assert!(!members[2].is_admin);
+    assert_eq!(members[2].user.id, t.user3.user.id);
 
-    // staff upgrades user1 (admin) without sudo
+    // admin_user (staff) removes user1 (admin) without sudo
     let user_id = t.user1.user.id;
     let token = t.staff_user.token.clone();
-    update_member_permission(&mut t, token, false, user_id, true)
+    remove_member(&mut t, token, false, user_id)
       .await
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
-    // staff upgrades user1 (admin)
+    // admin_user (staff) removes user1 (admin)
     let user_id = t.user1.user.id;
     let token = t.staff_user.token.clone();
-    let member = update_member_permission(&mut t, token, true, user_id, true)
+    remove_member(&mut t, token, true, user_id)
       .await
-      .expect_ok::<ApiScopeMember>()
+      .expect_ok_no_content()
       .await;
-    assert!(member.is_admin);
 
-    // staff upgrades user2 (member)
+    // admin_user (staff) removes user2 (admin)
     let user_id = t.user2.user.id;
     let token = t.staff_user.token.clone();
-    let member = update_member_permission(&mut t, token, true, user_id, true)
+    remove_member(&mut t, token, true, user_id)
       .await
-      .expect_ok::<ApiScopeMember>()
+      .expect_err_code(StatusCode::BAD_REQUEST, "scopeMustHaveAdmin")
       .await;
-    assert!(member.is_admin);
 
-    // staff upgrades user3 (not scope member)
+    // admin_user (staff) removes user3 (member)
     let user_id = t.user3.user.id;
     let token = t.staff_user.token.clone();
-    update_member_permission(&mut t, token, true, user_id, true)
+    remove_member(&mut t, token, true, user_id)
+      .await
+      .expect_ok_no_content()
+      .await;
+
+    // admin_user (staff) removes admin_user (not scope member)
+    let user_id = t.staff_user.user.id;
+    let token = t.staff_user.token.clone();
+    remove_member(&mut t, token, true, user_id)
       .await
       .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
       .await;
 
     let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].user.id, t.user2.user.id);
   }
 
   #[tokio::test]
-  async fn scope_member_downgrade_permission_as_staff() {
-    // - staff downgrades admin (sudo)
-    // - staff downgrades member (sudo)
-    // - staff downgrades user (sudo)
-
+  async fn scope_delete() {
     let mut t = TestSetup::new().await;
 
+    // create scope
     let scope_name = ScopeName::try_from("scope1").unwrap();
     t.db()
       .create_scope(&scope_name, t.user1.user.id)
       .await
       .unwrap();
 
+    let url = format!("/api/scopes/{}", scope_name);
+    let mut resp = t.http().delete(&url).call().await.unwrap();
+    resp.expect_ok_no_content().await;
+
+    let mut resp = t.http().get(&url).call().await.unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn scope_delete_non_admin() {
+    let mut t = TestSetup::new().await;
+
+    // create scope
+    let scope_name = ScopeName::try_from("scope1").unwrap();
     t.db()
-      .add_user_to_scope(NewScopeMember {
+      .create_scope(&scope_name, t.user1.user.id)
+      .await
+      .unwrap();
+    t.db()
+      .add_scope_invite(NewScopeInvite {
+        target_user_id: t.user2.user.id,
+        requesting_user_id: t.user1.user.id,
         scope: &scope_name,
-        user_id: t.user2.user.id,
-        is_admin: false,
       })
       .await
       .unwrap();
-
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
-
-    // staff downgrades user1 (admin)
-    let user_id = t.user1.user.id;
-    let token = t.staff_user.token.clone();
-    update_member_permission(&mut t, token, true, user_id, false)
-      .await
-      .expect_err_code(StatusCode::BAD_REQUEST, "scopeMustHaveAdmin")
-      .await;
-
-    // staff downgrades user2 (member)
-    let user_id = t.user2.user.id;
-    let token = t.staff_user.token.clone();
-    let member = update_member_permission(&mut t, token, true, user_id, false)
-      .await
-      .expect_ok::<ApiScopeMember>()
-      .await;
-    assert!(!member.is_admin);
-
-    // staff downgrades user3 (not scope member)
-    let user_id = t.user3.user.id;
-    let token = t.staff_user.token.clone();
-    update_member_permission(&mut t, token, true, user_id, false)
+    t.db()
+      .accept_scope_invite(&t.user2.user.id, &scope_name)
       .await
-      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
-      .await;
-  }
+      .unwrap();
 
-  async fn remove_member(
-    t: &mut TestSetup,
-    token: String,
-    sudo: bool,
-    user_id: Uuid,
-  ) -> Response<Body> {
-    t.http()
-      .delete(format!("/api/scopes/scope1/members/{}", user_id))
+    let url = format!("/api/scopes/{}", scope_name);
+    let token = t.user2.token.clone();
+    let mut resp = t
+      .http()
+      .delete(&url)
       .token(Some(&token))
-      .sudo(sudo)
       .call()
       .await
-      .unwrap()
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .await;
   }
 
   #[tokio::test]
-  async fn scope_members_remove_as_admin() {
-    // - admin removes themselves (last)
-    // - admin removes member
-    // - admin removes user
-
+  async fn scope_delete_non_member() {
     let mut t = TestSetup::new().await;
 
+    // create scope
     let scope_name = ScopeName::try_from("scope1").unwrap();
     t.db()
       .create_scope(&scope_name, t.user1.user.id)
       .await
       .unwrap();
 
-    t.db()
-      .add_user_to_scope(NewScopeMember {
-        scope: &scope_name,
-        user_id: t.user2.user.id,
-        is_admin: false,
-      })
+    let url = format!("/api/scopes/{}", scope_name);
+    let token = t.user3.token.clone();
+    let mut resp = t
+      .http()
+      .delete(&url)
+      .token(Some(&token))
+      .call()
       .await
       .unwrap();
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
+  }
 
-    // user1 (admin) removes themselves
-    let user_id = t.user1.user.id;
-    let token = t.user1.token.clone();
-    remove_member(&mut t, token, false, user_id)
-      .await
-      .expect_err_code(StatusCode::BAD_REQUEST, "scopeMustHaveAdmin")
+  #[tokio::test]
+  async fn scope_delete_not_found() {
+    let mut t = TestSetup::new().await;
+
+    let url = "/api/scopes/scope42";
+    let mut resp = t.http().delete(url).call().await.unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeNotFound")
       .await;
+  }
 
-    // user1 (admin) removes user2 (member)
-    let user_id = t.user2.user.id;
-    let token = t.user1.token.clone();
-    let resp = remove_member(&mut t, token, false, user_id).await;
-    assert!(resp.status().is_success());
+  #[tokio::test]
+  async fn scope_delete_not_empty() {
+    let mut t = TestSetup::new().await;
 
-    // user1 (owner) removes user3 (not scope member)
-    let user_id = t.user3.user.id;
-    let token = t.user1.token.clone();
-    remove_member(&mut t, token, false, user_id)
+    // create scope and package
+    let scope_name = ScopeName::try_from("scope1").unwrap();
+    t.db()
+      .create_scope(&scope_name, t.user1.user.id)
       .await
-      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
-      .await;
+      .unwrap();
+    let name = PackageName::new("foo".to_owned()).unwrap();
+    t.ephemeral_database
+      .create_package(&scope_name, &name)
+      .await
+      .unwrap();
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 1);
-    assert_eq!(members[0].user.id, t.user1.user.id);
+    let url = format!("/api/scopes/{}", scope_name);
+    let mut resp = t.http().delete(&url).call().await.unwrap();
+    resp
+      .expect_err_code(StatusCode::CONFLICT, "scopeNotEmpty")
+      .await;
   }
 
   #[tokio::test]
-  async fn scope_members_remove_as_admin_not_last1() {
-    // - admin removes themselves (not-last)
-
+  async fn scope_delete_with_pending_invite() {
     let mut t = TestSetup::new().await;
 
+    // create scope and package
     let scope_name = ScopeName::try_from("scope1").unwrap();
     t.db()
       .create_scope(&scope_name, t.user1.user.id)
       .await
       .unwrap();
-
     t.db()
-      .add_user_to_scope(NewScopeMember {
+      .add_scope_invite(NewScopeInvite {
+        target_user_id: t.user3.user.id,
+        requesting_user_id: t.user1.user.id,
         scope: &scope_name,
-        user_id: t.user2.user.id,
-        is_admin: true,
       })
       .await
       .unwrap();
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
-
-    // user1 (admin) removes themselves
-    let user_id = t.user1.user.id;
-    let token = t.user1.token.clone();
-    remove_member(&mut t, token, false, user_id)
-      .await
-      .expect_ok_no_content()
-      .await;
-
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 1);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user2.user.id);
+    let url = format!("/api/scopes/{}", scope_name);
+    let mut resp = t.http().delete(&url).call().await.unwrap();
+    resp.expect_ok_no_content().await;
   }
 
   #[tokio::test]
-  async fn scope_members_remove_as_admin_not_last2() {
-    // - admin removes admin (not-last)
-
+  async fn scope_transfer() {
     let mut t = TestSetup::new().await;
 
+    // create scope
     let scope_name = ScopeName::try_from("scope1").unwrap();
     t.db()
       .create_scope(&scope_name, t.user1.user.id)
       .await
       .unwrap();
 
+    for i in 0..3 {
+      let scope_name = ScopeName::try_from(format!("temp{i}")).unwrap();
+      t.db()
+        .create_scope(&scope_name, t.user2.user.id)
+        .await
+        .unwrap();
+    }
+
     t.db()
       .add_user_to_scope(NewScopeMember {
         scope: &scope_name,
@@ -1725,409 +3101,681 @@ pub mod tests {
       .await
       .unwrap();
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
-
-    // user1 (admin) removes user2 (admin)
-    let user_id = t.user2.user.id;
+    let user_id = t.user1.user.id;
     let token = t.user1.token.clone();
     remove_member(&mut t, token, false, user_id)
       .await
-      .expect_ok_no_content()
+      .expect_err_code(StatusCode::BAD_REQUEST, "noScopeOwnerAvailable")
       .await;
-
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 1);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
   }
 
   #[tokio::test]
-  async fn scope_members_remove_as_member() {
-    // - member removes admin
-    // - member removes member
-    // - member removes user
-
+  async fn publish_freezes() {
     let mut t = TestSetup::new().await;
 
-    let scope_name = ScopeName::try_from("scope1").unwrap();
+    let scope_name = ScopeName::try_from("scope").unwrap();
+    let package_name = PackageName::new("foo".to_owned()).unwrap();
     t.db()
-      .create_scope(&scope_name, t.user1.user.id)
+      .create_package(&scope_name, &package_name)
       .await
       .unwrap();
 
-    t.db()
-      .add_user_to_scope(NewScopeMember {
-        scope: &scope_name,
-        user_id: t.user2.user.id,
-        is_admin: false,
+    let now = chrono::Utc::now();
+    let freeze: ApiScopePublishFreeze = t
+      .http()
+      .post("/api/scopes/scope/publish_freezes")
+      .body_json(json!({
+        "startsAt": now - chrono::Duration::hours(1),
+        "endsAt": now + chrono::Duration::hours(1),
+        "reason": "Release freeze",
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(freeze.reason, "Release freeze");
+    assert!(!freeze.recurs_weekly);
+
+    let freezes: Vec<ApiScopePublishFreeze> = t
+      .http()
+      .get("/api/scopes/scope/publish_freezes")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(freezes.len(), 1);
+
+    // Publishing is blocked while the freeze is active, unless bypassed.
+    let config_file = PackagePath::try_from("/jsr.json").unwrap();
+    let version = Version::new("1.0.0").unwrap();
+    let res = t
+      .db()
+      .create_publishing_task(NewPublishingTask {
+        package_scope: &scope_name,
+        package_name: &package_name,
+        package_version: &version,
+        config_file: &config_file,
+        user_id: None,
+        github_repository_id: None,
+        github_workflow_run: None,
+        bypass_publish_freezes: false,
+        publish_at: None,
+        idempotency_key: None,
       })
       .await
       .unwrap();
+    let CreatePublishingTaskResult::PublishFreezeActive(active) = res else {
+      panic!("expected active publish freeze, got {res:?}");
+    };
+    assert_eq!(active.id, freeze.id);
+    let res = t
+      .db()
+      .create_publishing_task(NewPublishingTask {
+        package_scope: &scope_name,
+        package_name: &package_name,
+        package_version: &version,
+        config_file: &config_file,
+        user_id: None,
+        github_repository_id: None,
+        github_workflow_run: None,
+        bypass_publish_freezes: true,
+        publish_at: None,
+        idempotency_key: None,
+      })
+      .await
+      .unwrap();
+    assert!(
+      matches!(res, CreatePublishingTaskResult::Created(_)),
+      "{res:?}"
+    );
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
-
-    // user2 (member) removes user1 (admin)
-    let user_id = t.user1.user.id;
-    let token = t.user2.token.clone();
-    remove_member(&mut t, token, false, user_id)
+    // Non-admins can not manage freezes.
+    let token = t.user3.token.clone();
+    t.http()
+      .delete(format!("/api/scopes/scope/publish_freezes/{}", freeze.id))
+      .token(Some(&token))
+      .call()
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
-    // user2 (member) removes user3 (not scope member)
-    let user_id = t.user3.user.id;
-    let token = t.user2.token.clone();
-    remove_member(&mut t, token, false, user_id)
+    t.http()
+      .delete(format!("/api/scopes/scope/publish_freezes/{}", freeze.id))
+      .call()
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    t.http()
+      .delete(format!("/api/scopes/scope/publish_freezes/{}", freeze.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "publishFreezeNotFound")
       .await;
 
-    // user2 (member) removes themselves
-    let user_id = t.user2.user.id;
-    let token = t.user2.token.clone();
-    remove_member(&mut t, token, false, user_id)
+    // A weekly freeze that started two weeks ago is active again now.
+    let freeze: ApiScopePublishFreeze = t
+      .http()
+      .post("/api/scopes/scope/publish_freezes")
+      .body_json(json!({
+        "startsAt": now - chrono::Duration::weeks(2) - chrono::Duration::minutes(10),
+        "endsAt": now - chrono::Duration::weeks(2) + chrono::Duration::minutes(10),
+        "recursWeekly": true,
+        "reason": "Weekly maintenance",
+      }))
+      .call()
       .await
-      .expect_ok_no_content()
+      .unwrap()
+      .expect_ok()
       .await;
+    let res = t
+      .db()
+      .create_publishing_task(NewPublishingTask {
+        package_scope: &scope_name,
+        package_name: &package_name,
+        package_version: &Version::new("1.0.1").unwrap(),
+        config_file: &config_file,
+        user_id: None,
+        github_repository_id: None,
+        github_workflow_run: None,
+        bypass_publish_freezes: false,
+        publish_at: None,
+        idempotency_key: None,
+      })
+      .await
+      .unwrap();
+    let CreatePublishingTaskResult::PublishFreezeActive(active) = res else {
+      panic!("expected active publish freeze, got {res:?}");
+    };
+    assert_eq!(active.id, freeze.id);
+    assert!(active.occurrence_ends_at(chrono::Utc::now()) > now);
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 1);
-    assert_eq!(members[0].user.id, t.user1.user.id);
+    // Weekly freezes must be shorter than a week.
+    t.http()
+      .post("/api/scopes/scope/publish_freezes")
+      .body_json(json!({
+        "startsAt": now,
+        "endsAt": now + chrono::Duration::weeks(1),
+        "recursWeekly": true,
+        "reason": "Too long",
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
   }
 
   #[tokio::test]
-  async fn scope_members_remove_as_user() {
-    // - user removes admin
-    // - user removes member
-    // - user removes themselves
-
+  async fn scope_metrics() {
     let mut t = TestSetup::new().await;
 
-    let scope_name = ScopeName::try_from("scope1").unwrap();
+    let scope_name = ScopeName::try_from("scope").unwrap();
+    let package_name = PackageName::new("foo".to_owned()).unwrap();
     t.db()
-      .create_scope(&scope_name, t.user1.user.id)
+      .create_package(&scope_name, &package_name)
       .await
       .unwrap();
-
     t.db()
-      .add_user_to_scope(NewScopeMember {
+      .create_package_version_for_test(NewPackageVersion {
         scope: &scope_name,
-        user_id: t.user2.user.id,
-        is_admin: false,
+        name: &package_name,
+        version: &Version::new("1.0.0").unwrap(),
+        user_id: Some(&t.user1.user.id),
+        readme_path: None,
+        uses_npm: false,
+        exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
+        meta: Default::default(),
       })
       .await
       .unwrap();
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(!members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
+    let metrics: ApiScopeMetrics = t
+      .http()
+      .get("/api/scopes/scope/metrics")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(
+      metrics
+        .publishes_per_week
+        .iter()
+        .map(|w| w.count)
+        .sum::<i64>(),
+      1
+    );
+    assert_eq!(metrics.package_downloads.len(), 1);
+    assert_eq!(metrics.package_downloads[0].package, package_name);
+    assert_eq!(metrics.package_downloads[0].downloads_total, 0);
+    assert_eq!(metrics.active_tokens, 0);
+    assert_eq!(metrics.members.len(), 1);
+    assert_eq!(metrics.members[0].user.id, t.user1.user.id);
+    assert_eq!(metrics.members[0].publishes_30d, 1);
+    assert!(metrics.members[0].last_published_at.is_some());
 
-    // user3 (not scope owner) removes user1 (admin)
-    let user_id = t.user1.user.id;
     let token = t.user3.token.clone();
-    remove_member(&mut t, token, false, user_id)
+    t.http()
+      .get("/api/scopes/scope/metrics")
+      .token(Some(&token))
+      .call()
       .await
+      .unwrap()
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
+  }
 
-    // user3 (not scope owner) removes user2 (member)
-    let user_id = t.user2.user.id;
-    let token = t.user3.token.clone();
-    remove_member(&mut t, token, false, user_id)
+  #[tokio::test]
+  async fn publish_ip_ranges() {
+    let mut t = TestSetup::new().await;
+
+    let scope_name = ScopeName::try_from("scope").unwrap();
+    let package_name = PackageName::new("foo".to_owned()).unwrap();
+    t.db()
+      .create_package(&scope_name, &package_name)
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .unwrap();
+
+    let range: ApiScopePublishIpRange = t
+      .http()
+      .post("/api/scopes/scope/publish_ip_ranges")
+      .body_json(json!({ "cidr": "10.1.2.3/16", "description": "CI runners" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
       .await;
+    assert_eq!(range.cidr, "10.1.0.0/16");
+    assert_eq!(range.description, "CI runners");
 
-    // user3 (not scope owner) removes themselves
-    let user_id = t.user3.user.id;
+    t.http()
+      .post("/api/scopes/scope/publish_ip_ranges")
+      .body_json(json!({ "cidr": "10.1.0.0/16" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::CONFLICT, "publishIpRangeAlreadyExists")
+      .await;
+    t.http()
+      .post("/api/scopes/scope/publish_ip_ranges")
+      .body_json(json!({ "cidr": "not an ip" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    let ranges: Vec<ApiScopePublishIpRange> = t
+      .http()
+      .get("/api/scopes/scope/publish_ip_ranges")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(ranges.len(), 1);
+
+    // Publishing from outside of the allowed ranges is rejected.
+    t.http()
+      .post("/api/scopes/scope/packages/foo/versions/1.0.0?config=/jsr.json")
+      .gzip()
+      .header(
+        hyper::header::HeaderName::from_static("x-forwarded-for"),
+        hyper::header::HeaderValue::from_static("192.168.0.1"),
+      )
+      .body(Body::from(create_mock_tarball("ok")))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "publishIpNotAllowed")
+      .await;
+    let task: ApiPublishingTask = t
+      .http()
+      .post("/api/scopes/scope/packages/foo/versions/1.0.0?config=/jsr.json")
+      .gzip()
+      .header(
+        hyper::header::HeaderName::from_static("x-forwarded-for"),
+        hyper::header::HeaderValue::from_static("10.1.200.7"),
+      )
+      .body(Body::from(create_mock_tarball("ok")))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(task.package_version.to_string(), "1.0.0");
+
+    // Non-admins can not manage ranges.
     let token = t.user3.token.clone();
-    remove_member(&mut t, token, false, user_id)
+    t.http()
+      .get("/api/scopes/scope/publish_ip_ranges")
+      .token(Some(&token))
+      .call()
       .await
+      .unwrap()
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
 
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 2);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert_eq!(members[1].user.id, t.user2.user.id);
+    t.http()
+      .delete(format!("/api/scopes/scope/publish_ip_ranges/{}", range.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    t.http()
+      .delete(format!("/api/scopes/scope/publish_ip_ranges/{}", range.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "publishIpRangeNotFound")
+      .await;
   }
 
   #[tokio::test]
-  async fn scope_members_remove_as_staff() {
-    // - staff removes admin (not-last) (not sudo)
-    // - staff removes admin (not-last) (sudo)
-    // - staff removes admin (last) (sudo)
-    // - staff removes member (sudo)
-    // - staff removes user (sudo)
-
+  async fn scope_tokens() {
     let mut t = TestSetup::new().await;
 
-    let scope_name = ScopeName::try_from("scope1").unwrap();
-    t.db()
-      .create_scope(&scope_name, t.user1.user.id)
-      .await
-      .unwrap();
-
-    t.db()
-      .add_user_to_scope(NewScopeMember {
-        scope: &scope_name,
-        user_id: t.user2.user.id,
-        is_admin: true,
-      })
-      .await
-      .unwrap();
+    let scope_name = ScopeName::try_from("scope").unwrap();
+    for name in ["foo", "bar"] {
+      t.db()
+        .create_package(&scope_name, &PackageName::try_from(name).unwrap())
+        .await
+        .unwrap();
+    }
 
-    t.db()
-      .add_user_to_scope(NewScopeMember {
-        scope: &scope_name,
-        user_id: t.user3.user.id,
-        is_admin: false,
-      })
+    t.http()
+      .post("/api/scopes/scope/tokens")
+      .body_json(json!({ "description": "CI", "packages": [] }))
+      .call()
       .await
-      .unwrap();
-
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 3);
-    assert!(members[0].is_admin);
-    assert_eq!(members[0].user.id, t.user1.user.id);
-    assert!(members[1].is_admin);
-    assert_eq!(members[1].user.id, t.user2.user.id);
-    assert!(!members[2].is_admin);
-    assert_eq!(members[2].user.id, t.user3.user.id);
-
-    // admin_user (staff) removes user1 (admin) without sudo
-    let user_id = t.user1.user.id;
-    let token = t.staff_user.token.clone();
-    remove_member(&mut t, token, false, user_id)
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+    t.http()
+      .post("/api/scopes/scope/tokens")
+      .body_json(json!({ "description": "CI", "packages": ["baz"] }))
+      .call()
       .await
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
       .await;
 
-    // admin_user (staff) removes user1 (admin)
-    let user_id = t.user1.user.id;
-    let token = t.staff_user.token.clone();
-    remove_member(&mut t, token, true, user_id)
+    let created: ApiCreatedToken = t
+      .http()
+      .post("/api/scopes/scope/tokens")
+      .body_json(json!({ "description": "CI", "packages": ["foo", "foo"] }))
+      .call()
       .await
-      .expect_ok_no_content()
+      .unwrap()
+      .expect_ok()
       .await;
+    assert!(created.secret.starts_with("jsrs_"));
+    assert!(matches!(created.token.r#type, ApiTokenType::Scope));
 
-    // admin_user (staff) removes user2 (admin)
-    let user_id = t.user2.user.id;
-    let token = t.staff_user.token.clone();
-    remove_member(&mut t, token, true, user_id)
+    let tokens: Vec<ApiToken> = t
+      .http()
+      .get("/api/scopes/scope/tokens")
+      .call()
       .await
-      .expect_err_code(StatusCode::BAD_REQUEST, "scopeMustHaveAdmin")
+      .unwrap()
+      .expect_ok()
       .await;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].id, created.token.id);
 
-    // admin_user (staff) removes user3 (member)
-    let user_id = t.user3.user.id;
-    let token = t.staff_user.token.clone();
-    remove_member(&mut t, token, true, user_id)
+    // Scope tokens are not listed as personal tokens of their creator.
+    let tokens: Vec<ApiToken> = t
+      .http()
+      .get("/api/user/tokens")
+      .call()
       .await
-      .expect_ok_no_content()
+      .unwrap()
+      .expect_ok()
       .await;
+    assert!(tokens.iter().all(|token| token.id != created.token.id));
 
-    // admin_user (staff) removes admin_user (not scope member)
-    let user_id = t.staff_user.user.id;
-    let token = t.staff_user.token.clone();
-    remove_member(&mut t, token, true, user_id)
+    // The token can only publish the packages it was created for.
+    let secret = created.secret.clone();
+    let task: ApiPublishingTask = t
+      .http()
+      .post("/api/scopes/scope/packages/foo/versions/1.0.0?config=/jsr.json")
+      .gzip()
+      .token(Some(&secret))
+      .body(Body::from(create_mock_tarball("ok")))
+      .call()
       .await
-      .expect_err_code(StatusCode::NOT_FOUND, "scopeMemberNotFound")
+      .unwrap()
+      .expect_ok()
       .await;
-
-    let members = list_members(&mut t).await;
-    assert_eq!(members.len(), 1);
-    assert_eq!(members[0].user.id, t.user2.user.id);
-  }
-
-  #[tokio::test]
-  async fn scope_delete() {
-    let mut t = TestSetup::new().await;
-
-    // create scope
-    let scope_name = ScopeName::try_from("scope1").unwrap();
-    t.db()
-      .create_scope(&scope_name, t.user1.user.id)
+    assert_eq!(task.package_version.to_string(), "1.0.0");
+    t.http()
+      .post("/api/scopes/scope/packages/bar/versions/1.0.0?config=/jsr.json")
+      .gzip()
+      .token(Some(&secret))
+      .body(Body::from(create_mock_tarball("ok")))
+      .call()
       .await
-      .unwrap();
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "missingPermission")
+      .await;
+    t.http()
+      .patch("/api/scopes/scope")
+      .token(Some(&secret))
+      .body_json(json!({ "ghActionsVerifyActor": true }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "missingPermission")
+      .await;
+    t.http()
+      .post("/api/scopes/scope/tokens")
+      .token(Some(&secret))
+      .body_json(json!({ "description": "CI", "packages": ["bar"] }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "missingPermission")
+      .await;
 
-    let url = format!("/api/scopes/{}", scope_name);
-    let mut resp = t.http().delete(&url).call().await.unwrap();
-    resp.expect_ok_no_content().await;
+    // Non-admins can not manage scope tokens.
+    let token = t.user3.token.clone();
+    t.http()
+      .get("/api/scopes/scope/tokens")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
+      .await;
 
-    let mut resp = t.http().get(&url).call().await.unwrap();
-    resp
-      .expect_err_code(StatusCode::NOT_FOUND, "scopeNotFound")
+    let path = format!("/api/scopes/scope/tokens/{}", created.token.id);
+    t.http()
+      .delete(&path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    t.http()
+      .delete(&path)
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "tokenNotFound")
       .await;
   }
 
   #[tokio::test]
-  async fn scope_delete_non_admin() {
+  async fn scope_recovery_requests() {
     let mut t = TestSetup::new().await;
 
-    // create scope
-    let scope_name = ScopeName::try_from("scope1").unwrap();
-    t.db()
-      .create_scope(&scope_name, t.user1.user.id)
-      .await
-      .unwrap();
+    let scope_name = ScopeName::try_from("scope").unwrap();
     t.db()
-      .add_scope_invite(NewScopeInvite {
-        target_user_id: t.user2.user.id,
-        requesting_user_id: t.user1.user.id,
+      .add_user_to_scope(NewScopeMember {
         scope: &scope_name,
+        user_id: t.user2.user.id,
+        is_admin: false,
       })
       .await
       .unwrap();
-    t.db()
-      .accept_scope_invite(&t.user2.user.id, &scope_name)
-      .await
-      .unwrap();
 
-    let url = format!("/api/scopes/{}", scope_name);
+    // The scope can not be recovered while its creator is in good standing.
     let token = t.user2.token.clone();
-    let mut resp = t
-      .http()
-      .delete(&url)
+    t.http()
+      .post("/api/scopes/scope/recovery_requests")
       .token(Some(&token))
+      .body_json(json!({ "reason": "the creator is gone" }))
       .call()
       .await
-      .unwrap();
-    resp
-      .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeAdmin")
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "scopeNotOrphaned")
       .await;
-  }
-
-  #[tokio::test]
-  async fn scope_delete_non_member() {
-    let mut t = TestSetup::new().await;
 
-    // create scope
-    let scope_name = ScopeName::try_from("scope1").unwrap();
-    t.db()
-      .create_scope(&scope_name, t.user1.user.id)
-      .await
-      .unwrap();
+    t.db().user_set_blocked(t.user1.user.id, true).await.unwrap();
 
-    let url = format!("/api/scopes/{}", scope_name);
+    // Non-members can not request to recover the scope.
     let token = t.user3.token.clone();
-    let mut resp = t
-      .http()
-      .delete(&url)
+    t.http()
+      .post("/api/scopes/scope/recovery_requests")
       .token(Some(&token))
+      .body_json(json!({ "reason": "the creator is gone" }))
       .call()
       .await
-      .unwrap();
-
-    resp
+      .unwrap()
       .expect_err_code(StatusCode::FORBIDDEN, "actorNotScopeMember")
       .await;
-  }
 
-  #[tokio::test]
-  async fn scope_delete_not_found() {
-    let mut t = TestSetup::new().await;
+    // A request from a non-admin member has to be reviewed by staff.
+    let token = t.user2.token.clone();
+    let request: ApiScopeRecoveryRequest = t
+      .http()
+      .post("/api/scopes/scope/recovery_requests")
+      .token(Some(&token))
+      .body_json(json!({ "reason": "the creator is gone" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(request.status, ScopeRecoveryStatus::Pending);
+    assert_eq!(request.previous_creator_id, t.user1.user.id);
+    t.http()
+      .post("/api/scopes/scope/recovery_requests")
+      .token(Some(&token))
+      .body_json(json!({ "reason": "the creator is gone" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::CONFLICT, "scopeRecoveryAlreadyRequested")
+      .await;
 
-    let url = "/api/scopes/scope42";
-    let mut resp = t.http().delete(url).call().await.unwrap();
-    resp
-      .expect_err_code(StatusCode::NOT_FOUND, "scopeNotFound")
+    let requests: Vec<ApiScopeRecoveryRequest> = t
+      .http()
+      .get("/api/scopes/scope/recovery_requests")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
       .await;
-  }
+    assert_eq!(requests.len(), 1);
 
-  #[tokio::test]
-  async fn scope_delete_not_empty() {
-    let mut t = TestSetup::new().await;
+    let token = t.staff_user.token.clone();
+    let list: ApiList<ApiScopeRecoveryRequest> = t
+      .http()
+      .get("/api/admin/scope_recovery_requests")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(list.total, 1);
 
-    // create scope and package
-    let scope_name = ScopeName::try_from("scope1").unwrap();
-    t.db()
-      .create_scope(&scope_name, t.user1.user.id)
+    let request: ApiScopeRecoveryRequest = t
+      .http()
+      .post(format!(
+        "/api/admin/scope_recovery_requests/{}/approve",
+        request.id
+      ))
+      .token(Some(&token))
+      .call()
       .await
-      .unwrap();
-    let name = PackageName::new("foo".to_owned()).unwrap();
-    t.ephemeral_database
-      .create_package(&scope_name, &name)
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(request.status, ScopeRecoveryStatus::Approved);
+    assert_eq!(request.reviewed_by, Some(t.staff_user.user.id));
+    t.http()
+      .post(format!(
+        "/api/admin/scope_recovery_requests/{}/reject",
+        request.id
+      ))
+      .token(Some(&token))
+      .call()
       .await
-      .unwrap();
-
-    let url = format!("/api/scopes/{}", scope_name);
-    let mut resp = t.http().delete(&url).call().await.unwrap();
-    resp
-      .expect_err_code(StatusCode::CONFLICT, "scopeNotEmpty")
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "scopeRecoveryRequestNotPending")
       .await;
-  }
 
-  #[tokio::test]
-  async fn scope_delete_with_pending_invite() {
-    let mut t = TestSetup::new().await;
+    let scope = t.db().get_scope(&scope_name).await.unwrap().unwrap();
+    assert_eq!(scope.creator, t.user2.user.id);
+    let member = t
+      .db()
+      .get_scope_member(&scope_name, t.user2.user.id)
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(member.is_admin);
 
-    // create scope and package
-    let scope_name = ScopeName::try_from("scope1").unwrap();
+    // An admin that no other admin could object to recovers the scope right
+    // away.
+    let scope_name = ScopeName::try_from("scope2").unwrap();
     t.db()
       .create_scope(&scope_name, t.user1.user.id)
       .await
       .unwrap();
     t.db()
-      .add_scope_invite(NewScopeInvite {
-        target_user_id: t.user3.user.id,
-        requesting_user_id: t.user1.user.id,
+      .add_user_to_scope(NewScopeMember {
         scope: &scope_name,
+        user_id: t.user3.user.id,
+        is_admin: true,
       })
       .await
       .unwrap();
 
-    let url = format!("/api/scopes/{}", scope_name);
-    let mut resp = t.http().delete(&url).call().await.unwrap();
-    resp.expect_ok_no_content().await;
+    let token = t.user3.token.clone();
+    let request: ApiScopeRecoveryRequest = t
+      .http()
+      .post("/api/scopes/scope2/recovery_requests")
+      .token(Some(&token))
+      .body_json(json!({ "reason": "the creator is gone" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(request.status, ScopeRecoveryStatus::Approved);
+    assert_eq!(request.reviewed_by, None);
+    let scope = t.db().get_scope(&scope_name).await.unwrap().unwrap();
+    assert_eq!(scope.creator, t.user3.user.id);
   }
 
   #[tokio::test]
-  async fn scope_transfer() {
+  async fn scope_downloads() {
     let mut t = TestSetup::new().await;
 
-    // create scope
-    let scope_name = ScopeName::try_from("scope1").unwrap();
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let yesterday =
+      chrono::Utc::now().date_naive() - chrono::Duration::days(1);
+    let entry = |kind, count| VersionDownloadCount {
+      scope: ScopeName::try_from("scope").unwrap(),
+      package: PackageName::try_from("foo").unwrap(),
+      version: Version::try_from("1.2.3").unwrap(),
+      time_bucket: yesterday.and_time(chrono::NaiveTime::MIN).and_utc(),
+      kind,
+      count,
+    };
     t.db()
-      .create_scope(&scope_name, t.user1.user.id)
+      .insert_download_entries(vec![
+        entry(DownloadKind::JsrMeta, 5),
+        entry(DownloadKind::NpmTgz, 2),
+      ])
       .await
       .unwrap();
 
-    for i in 0..3 {
-      let scope_name = ScopeName::try_from(format!("temp{i}")).unwrap();
-      t.db()
-        .create_scope(&scope_name, t.user2.user.id)
-        .await
-        .unwrap();
-    }
-
-    t.db()
-      .add_user_to_scope(NewScopeMember {
-        scope: &scope_name,
-        user_id: t.user2.user.id,
-        is_admin: true,
-      })
+    let downloads: ApiScopeDownloads = t
+      .http()
+      .get("/api/scopes/scope/downloads")
+      .call()
       .await
-      .unwrap();
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(downloads.total.len(), 2);
+    assert_eq!(
+      downloads.total.iter().map(|point| point.count).sum::<u64>(),
+      7
+    );
 
-    let user_id = t.user1.user.id;
-    let token = t.user1.token.clone();
-    remove_member(&mut t, token, false, user_id)
+    t.http()
+      .get("/api/scopes/scope2/downloads")
+      .call()
       .await
-      .expect_err_code(StatusCode::BAD_REQUEST, "noScopeOwnerAvailable")
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "scopeNotFound")
       .await;
   }
 }
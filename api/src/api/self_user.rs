@@ -1,42 +1,67 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use hyper::header::CACHE_CONTROL;
+use hyper::header::CONTENT_DISPOSITION;
+use hyper::header::CONTENT_TYPE;
 use hyper::Body;
 use hyper::Request;
 use hyper::Response;
 use hyper::StatusCode;
 use routerify::prelude::RequestExt;
 use routerify::Router;
+use routerify_query::RequestQueryExt;
 use tracing::field;
 use tracing::instrument;
 use tracing::Span;
 
 use std::borrow::Cow;
+use std::collections::HashSet;
 
+use crate::buckets::Buckets;
 use crate::db::Database;
 use crate::db::PackagePublishPermission;
 use crate::db::Permission;
 use crate::db::TokenType;
+use crate::db::UserExportStatus;
 use crate::db::UserPublic;
 use crate::emails::EmailArgs;
 use crate::emails::EmailSender;
+use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
+use crate::gcs_paths;
 use crate::iam::ReqIamExt;
+use crate::ids::ScopedPackageName;
+use crate::rate_limit::ApiKeyCache;
+use crate::trust::TrustPolicy;
 use crate::util;
 use crate::util::decode_json;
+use crate::util::pagination;
+use crate::util::ApiKeyUsages;
 use crate::util::ApiResult;
 use crate::util::RequestIdExt;
 use crate::RegistryUrl;
 
+use super::ApiApiKey;
+use super::ApiCreateApiKeyRequest;
+use super::ApiCreatePackageCollectionRequest;
 use super::ApiCreateTokenRequest;
+use super::ApiCreatedApiKey;
 use super::ApiCreatedToken;
 use super::ApiError;
 use super::ApiFullUser;
+use super::ApiList;
+use super::ApiPackage;
+use super::ApiPackageCollection;
 use super::ApiScope;
 use super::ApiScopeInvite;
 use super::ApiScopeMember;
 use super::ApiToken;
+use super::ApiUpdatePackageCollectionRequest;
+use super::ApiUserExport;
+use super::ApiUserTrust;
 
 pub fn self_user_router() -> Router<Body, ApiError> {
   Router::builder()
     .get("/", util::auth(util::json(get_handler)))
+    .get("/trust", util::auth(util::json(get_trust_handler)))
     .get("/scopes", util::auth(util::json(list_scopes_handler)))
     .get("/member/:scope", util::auth(util::json(get_member_handler)))
     .get("/invites", util::auth(util::json(list_invites_handler)))
@@ -48,6 +73,30 @@ pub fn self_user_router() -> Router<Body, ApiError> {
     .get("/tokens", util::auth(util::json(list_tokens)))
     .post("/tokens", util::auth(util::json(create_token)))
     .delete("/tokens/:id", util::auth(delete_token))
+    .get("/api_keys", util::auth(util::json(list_api_keys)))
+    .post("/api_keys", util::auth(util::json(create_api_key)))
+    .delete("/api_keys/:id", util::auth(delete_api_key))
+    .get("/export", util::auth(util::json(export_handler)))
+    .get("/export/:id/download", download_export_handler)
+    .get(
+      "/stars",
+      util::auth(util::paginated_json(list_stars_handler)),
+    )
+    .put("/stars/:scope/:package", util::auth(star_handler))
+    .delete("/stars/:scope/:package", util::auth(unstar_handler))
+    .get(
+      "/collections",
+      util::auth(util::json(list_collections_handler)),
+    )
+    .post(
+      "/collections",
+      util::auth(util::json(create_collection_handler)),
+    )
+    .patch(
+      "/collections/:id",
+      util::auth(util::json(update_collection_handler)),
+    )
+    .delete("/collections/:id", util::auth(delete_collection_handler))
     .build()
     .unwrap()
 }
@@ -55,10 +104,39 @@ pub fn self_user_router() -> Router<Body, ApiError> {
 #[instrument(name = "GET /api/user", skip(req), err)]
 pub async fn get_handler(req: Request<Body>) -> ApiResult<ApiFullUser> {
   let iam = req.iam();
-  let current_user = iam.check_current_user_access()?.to_owned();
+  let mut current_user = iam.check_current_user_access()?.to_owned();
+
+  // Report the scope limit that applies at the current trust level.
+  let db = req.data::<Database>().unwrap();
+  let trust_policy = req.data::<TrustPolicy>().unwrap();
+  let trust = db.get_user_trust_signals(current_user.id).await?;
+  current_user.scope_limit = trust_policy
+    .scope_limit(trust_policy.level(&trust), current_user.scope_limit);
+
   Ok(current_user.into())
 }
 
+#[instrument(name = "GET /api/user/trust", skip(req), err)]
+pub async fn get_trust_handler(req: Request<Body>) -> ApiResult<ApiUserTrust> {
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  let trust_policy = req.data::<TrustPolicy>().unwrap();
+  let trust = db.get_user_trust_signals(current_user.id).await?;
+  let level = trust_policy.level(&trust);
+  let limits = trust_policy.limits(level);
+
+  Ok(ApiUserTrust {
+    level,
+    scope_limit: trust_policy.scope_limit(level, current_user.scope_limit),
+    new_packages_per_day_limit: limits.new_packages_per_day,
+    new_packages_per_day_usage: trust.new_packages_per_day as u64,
+    publish_attempts_per_day_limit: limits.publish_attempts_per_day,
+    publish_attempts_per_day_usage: trust.publish_attempts_per_day as u64,
+  })
+}
+
 #[instrument(name = "GET /api/user/scopes", skip(req), err)]
 pub async fn list_scopes_handler(
   req: Request<Body>,
@@ -189,22 +267,7 @@ async fn create_token(
     permissions,
   } = decode_json(&mut req).await?;
 
-  let description = description.trim().replace('\n', " ").replace('\r', "");
-  if description.is_empty() {
-    return Err(ApiError::MalformedRequest {
-      msg: "description must not be empty".into(),
-    });
-  }
-  if description.len() > 250 {
-    return Err(ApiError::MalformedRequest {
-      msg: "description must not be longer than 250 characters".into(),
-    });
-  }
-  if description.contains(|c: char| c.is_control()) {
-    return Err(ApiError::MalformedRequest {
-      msg: "description must not contain control characters".into(),
-    });
-  }
+  let description = crate::token::normalize_description(&description)?;
 
   if let Some(permissions) = permissions.as_ref() {
     if permissions.0.len() != 1 {
@@ -213,6 +276,7 @@ async fn create_token(
       });
     }
   }
+  crate::token::check_user_token_permissions(permissions.as_ref())?;
 
   let iam = req.iam();
   let user = iam.check_authorization_approve_access()?;
@@ -240,10 +304,18 @@ async fn create_token(
         match &permissions.0[0] {
           Permission::PackagePublish(PackagePublishPermission::Scope {
             scope,
+            bypass_publish_freezes: false,
           }) => Cow::Owned(format!(
             "Publish new versions to any package in the @{} scope",
             scope
           )),
+          Permission::PackagePublish(PackagePublishPermission::Scope {
+            scope,
+            bypass_publish_freezes: true,
+          }) => Cow::Owned(format!(
+            "Publish new versions to any package in the @{} scope, including during publish freezes",
+            scope
+          )),
           Permission::PackagePublish(PackagePublishPermission::Package {
             scope,
             package,
@@ -251,6 +323,17 @@ async fn create_token(
             "Publish new versions of the @{}/{} package",
             scope, package
           )),
+          Permission::PackagePublish(PackagePublishPermission::Packages {
+            scope,
+            packages,
+          }) => Cow::Owned(format!(
+            "Publish new versions of the {} packages",
+            packages
+              .iter()
+              .map(|package| format!("@{}/{}", scope, package))
+              .collect::<Vec<_>>()
+              .join(", ")
+          )),
           Permission::PackagePublish(PackagePublishPermission::Version {
             scope,
             package,
@@ -315,17 +398,446 @@ async fn delete_token(req: Request<Body>) -> Result<Response<Body>, ApiError> {
   Ok(resp)
 }
 
+/// The maximum number of public API keys a user can have.
+const MAX_API_KEYS_PER_USER: usize = 10;
+
+#[instrument(name = "GET /api/user/api_keys", skip(req), err)]
+async fn list_api_keys(req: Request<Body>) -> ApiResult<Vec<ApiApiKey>> {
+  let iam = req.iam();
+  let user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  let usages = req.data::<ApiKeyUsages>().unwrap();
+  usages.flush(db).await?;
+  let api_keys = db.list_api_keys(user.id).await?;
+
+  Ok(api_keys.into_iter().map(ApiApiKey::from).collect())
+}
+
+#[instrument(name = "POST /api/user/api_keys", skip(req), err)]
+async fn create_api_key(mut req: Request<Body>) -> ApiResult<ApiCreatedApiKey> {
+  let ApiCreateApiKeyRequest { name } = decode_json(&mut req).await?;
+
+  let name = name.trim();
+  if name.is_empty() {
+    return Err(ApiError::MalformedRequest {
+      msg: "name must not be empty".into(),
+    });
+  }
+  if name.len() > 100 {
+    return Err(ApiError::MalformedRequest {
+      msg: "name must not be longer than 100 characters".into(),
+    });
+  }
+  if name.contains(|c: char| c.is_control()) {
+    return Err(ApiError::MalformedRequest {
+      msg: "name must not contain control characters".into(),
+    });
+  }
+
+  let iam = req.iam();
+  let user = iam.check_authorization_approve_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  if db.list_api_keys(user.id).await?.len() >= MAX_API_KEYS_PER_USER {
+    return Err(ApiError::ApiKeyLimitExceeded {
+      limit: MAX_API_KEYS_PER_USER,
+    });
+  }
+
+  let secret = crate::token::generate_api_key();
+  let api_key = db
+    .insert_api_key(user.id, &crate::token::hash(&secret), name)
+    .await?;
+
+  Ok(ApiCreatedApiKey {
+    secret,
+    api_key: api_key.into(),
+  })
+}
+
+#[instrument(
+  name = "DELETE /api/user/api_keys/:id",
+  skip(req),
+  err,
+  fields(id)
+)]
+async fn delete_api_key(req: Request<Body>) -> ApiResult<Response<Body>> {
+  let id = req.param_uuid("id")?;
+  Span::current().record("id", field::display(&id));
+
+  let iam = req.iam();
+  let user = iam.check_authorization_approve_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  if !db.delete_api_key(user.id, id).await? {
+    return Err(ApiError::ApiKeyNotFound);
+  }
+  req.data::<ApiKeyCache>().unwrap().invalidate(id);
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
+#[instrument(name = "GET /api/user/stars", skip(req), err)]
+pub async fn list_stars_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiList<ApiPackage>> {
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  let (start, limit) = pagination(&req);
+  let (total, packages) = db
+    .list_starred_packages(current_user.id, start, limit)
+    .await?;
+
+  Ok(ApiList {
+    items: packages.into_iter().map(ApiPackage::from).collect(),
+    total,
+  })
+}
+
+#[instrument(
+  name = "PUT /api/user/stars/:scope/:package",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn star_handler(req: Request<Body>) -> ApiResult<Response<Body>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  db.get_package(&scope, &package)
+    .await?
+    .ok_or(ApiError::PackageNotFound)?;
+  db.star_package(current_user.id, &scope, &package).await?;
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
+#[instrument(
+  name = "DELETE /api/user/stars/:scope/:package",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn unstar_handler(req: Request<Body>) -> ApiResult<Response<Body>> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  db.unstar_package(current_user.id, &scope, &package).await?;
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
+/// The maximum number of collections a user can have.
+const MAX_COLLECTIONS_PER_USER: usize = 50;
+/// The maximum number of packages in a collection.
+const MAX_COLLECTION_PACKAGES: usize = 100;
+
+fn validate_collection_name(name: &str) -> Result<&str, ApiError> {
+  let name = name.trim();
+  if name.is_empty() {
+    return Err(ApiError::MalformedRequest {
+      msg: "name must not be empty".into(),
+    });
+  }
+  if name.len() > 100 {
+    return Err(ApiError::MalformedRequest {
+      msg: "name must not be longer than 100 characters".into(),
+    });
+  }
+  if name.contains(|c: char| c.is_control()) {
+    return Err(ApiError::MalformedRequest {
+      msg: "name must not contain control characters".into(),
+    });
+  }
+  Ok(name)
+}
+
+fn validate_collection_description(
+  description: &str,
+) -> Result<&str, ApiError> {
+  let description = description.trim();
+  if description.len() > 250 {
+    return Err(ApiError::MalformedRequest {
+      msg: "description must not be longer than 250 characters".into(),
+    });
+  }
+  Ok(description)
+}
+
+async fn validate_collection_packages(
+  db: &Database,
+  packages: &[ScopedPackageName],
+) -> Result<(), ApiError> {
+  if packages.len() > MAX_COLLECTION_PACKAGES {
+    return Err(ApiError::MalformedRequest {
+      msg: format!(
+        "a collection must not contain more than {MAX_COLLECTION_PACKAGES} packages"
+      )
+      .into(),
+    });
+  }
+  let unique = packages.iter().collect::<HashSet<_>>();
+  if unique.len() != packages.len() {
+    return Err(ApiError::MalformedRequest {
+      msg: "a collection must not contain a package more than once".into(),
+    });
+  }
+  if !db.list_missing_packages(packages).await?.is_empty() {
+    return Err(ApiError::PackageNotFound);
+  }
+  Ok(())
+}
+
+#[instrument(name = "GET /api/user/collections", skip(req), err)]
+pub async fn list_collections_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiPackageCollection>> {
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  let collections =
+    db.list_package_collections_by_user(current_user.id).await?;
+
+  Ok(
+    collections
+      .into_iter()
+      .map(ApiPackageCollection::from)
+      .collect(),
+  )
+}
+
+#[instrument(name = "POST /api/user/collections", skip(req), err)]
+pub async fn create_collection_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiPackageCollection> {
+  let ApiCreatePackageCollectionRequest {
+    name,
+    description,
+    packages,
+  } = decode_json(&mut req).await?;
+  let name = validate_collection_name(&name)?;
+  let description = validate_collection_description(&description)?;
+
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  validate_collection_packages(db, &packages).await?;
+  if db
+    .list_package_collections_by_user(current_user.id)
+    .await?
+    .len()
+    >= MAX_COLLECTIONS_PER_USER
+  {
+    return Err(ApiError::PackageCollectionLimitExceeded {
+      limit: MAX_COLLECTIONS_PER_USER,
+    });
+  }
+
+  let collection = db
+    .create_package_collection(current_user.id, name, description, &packages)
+    .await?;
+
+  Ok(collection.into())
+}
+
+#[instrument(
+  name = "PATCH /api/user/collections/:id",
+  skip(req),
+  err,
+  fields(id)
+)]
+pub async fn update_collection_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiPackageCollection> {
+  let id = req.param_uuid("id")?;
+  Span::current().record("id", field::display(&id));
+
+  let ApiUpdatePackageCollectionRequest {
+    name,
+    description,
+    packages,
+  } = decode_json(&mut req).await?;
+  let name = name.as_deref().map(validate_collection_name).transpose()?;
+  let description = description
+    .as_deref()
+    .map(validate_collection_description)
+    .transpose()?;
+
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  if let Some(packages) = &packages {
+    validate_collection_packages(db, packages).await?;
+  }
+
+  let collection = db
+    .update_package_collection(
+      current_user.id,
+      id,
+      name,
+      description,
+      packages.as_deref(),
+    )
+    .await?
+    .ok_or(ApiError::PackageCollectionNotFound)?;
+
+  Ok(collection.into())
+}
+
+#[instrument(
+  name = "DELETE /api/user/collections/:id",
+  skip(req),
+  err,
+  fields(id)
+)]
+pub async fn delete_collection_handler(
+  req: Request<Body>,
+) -> ApiResult<Response<Body>> {
+  let id = req.param_uuid("id")?;
+  Span::current().record("id", field::display(&id));
+
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?;
+
+  let db = req.data::<Database>().unwrap();
+  if !db.delete_package_collection(current_user.id, id).await? {
+    return Err(ApiError::PackageCollectionNotFound);
+  }
+
+  let resp = Response::builder()
+    .status(StatusCode::NO_CONTENT)
+    .body(Body::empty())
+    .unwrap();
+  Ok(resp)
+}
+
+/// Requests an export of all personal data of the user. The export is
+/// created asynchronously, and a download link is emailed to the user once it
+/// is ready. At most one export is created per day; requesting another export
+/// in that time returns the existing one.
+#[instrument(name = "GET /api/user/export", skip(req), err)]
+pub async fn export_handler(req: Request<Body>) -> ApiResult<ApiUserExport> {
+  let iam = req.iam();
+  let user = iam.check_authorization_approve_access()?;
+  if user.email.is_none() {
+    return Err(ApiError::UserEmailRequired);
+  }
+
+  let db = req.data::<Database>().unwrap();
+  let export = db.get_or_create_user_export(user.id).await?;
+
+  Ok(export.into())
+}
+
+#[instrument(
+  name = "GET /api/user/export/:id/download",
+  skip(req),
+  err,
+  fields(id)
+)]
+pub async fn download_export_handler(
+  req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+  let id = req.param_uuid("id")?;
+  Span::current().record("id", field::display(&id));
+  let token = req
+    .query("token")
+    .ok_or(ApiError::UserExportNotFound)?
+    .to_owned();
+
+  let db = req.data::<Database>().unwrap();
+  let export = db
+    .get_user_export(id)
+    .await?
+    .ok_or(ApiError::UserExportNotFound)?;
+  if export.status != UserExportStatus::Success
+    || export.download_token_hash.as_deref()
+      != Some(crate::token::hash(&token).as_str())
+    || export
+      .expires_at
+      .map_or(true, |expires_at| expires_at < chrono::Utc::now())
+  {
+    return Err(ApiError::UserExportNotFound);
+  }
+
+  let buckets = req.data::<Buckets>().unwrap();
+  let content = buckets
+    .publishing_bucket
+    .download(gcs_paths::user_export_path(id).into())
+    .await?
+    .ok_or(ApiError::UserExportNotFound)?;
+
+  let resp = Response::builder()
+    .status(StatusCode::OK)
+    .header(CONTENT_TYPE, "application/json")
+    .header(
+      CONTENT_DISPOSITION,
+      format!("attachment; filename=\"jsr-export-{id}.json\""),
+    )
+    .header(CACHE_CONTROL, CACHE_CONTROL_DO_NOT_CACHE)
+    .body(Body::from(content))
+    .unwrap();
+  Ok(resp)
+}
+
 #[cfg(test)]
 mod tests {
+  use hyper::http::HeaderName;
+  use hyper::http::HeaderValue;
   use hyper::StatusCode;
   use serde_json::json;
 
+  use crate::api::ApiApiKey;
+  use crate::api::ApiCreatedApiKey;
   use crate::api::ApiCreatedToken;
   use crate::api::ApiFullUser;
+  use crate::api::ApiList;
+  use crate::api::ApiPackage;
+  use crate::api::ApiPackageCollection;
   use crate::api::ApiToken;
   use crate::api::ApiTokenType;
-  use crate::util::test::ApiResultExt;
-  use crate::util::test::TestSetup;
+  use crate::api::ApiUserExport;
+  use crate::db::CreatePackageResult;
+  use crate::db::ExportsMap;
+  use crate::db::NewPackageVersion;
+  use crate::db::NewUser;
+  use crate::db::TokenType;
+  use crate::db::UserExportStatus;
+  use crate::ids::PackageName;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+  use crate::user_export::process_user_export;
+  use crate::user_export::UserExportData;
 
   #[tokio::test]
   async fn list_tokens() {
@@ -422,6 +934,24 @@ mod tests {
       .expect_err_code(StatusCode::FORBIDDEN, "credentialNotInteractive")
       .await;
 
+    // package lists are only supported for scope tokens
+    t.http()
+      .post("/api/user/tokens")
+      .body_json(json!({
+        "description": "test token",
+        "expires_at": null,
+        "permissions": [{
+          "permission": "package/publish",
+          "scope": "scope",
+          "packages": ["foo"]
+        }]
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
     t.http()
       .delete(format!("/api/user/tokens/{}", token.id))
       .call()
@@ -465,4 +995,457 @@ mod tests {
       .expect_err_code(StatusCode::UNAUTHORIZED, "invalidBearerToken")
       .await;
   }
+
+  #[tokio::test]
+  async fn create_and_delete_api_key() {
+    let mut t = TestSetup::new().await;
+    let x_jsr_api_key = HeaderName::from_static("x-jsr-api-key");
+
+    let created: ApiCreatedApiKey = t
+      .http()
+      .post("/api/user/api_keys")
+      .body_json(json!({ "name": "search site" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert!(created.secret.starts_with("jsrk_"));
+    assert_eq!(created.api_key.name, "search site");
+    assert_eq!(created.api_key.request_count, 0);
+
+    // the key can be used for anonymous reads, and its usage is tracked
+    t.unauthed_http()
+      .get("/api/scopes/scope")
+      .header(
+        x_jsr_api_key.clone(),
+        HeaderValue::from_str(&created.secret).unwrap(),
+      )
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<serde_json::Value>()
+      .await;
+
+    // the key does not authenticate the user
+    t.unauthed_http()
+      .get("/api/user")
+      .header(
+        x_jsr_api_key.clone(),
+        HeaderValue::from_str(&created.secret).unwrap(),
+      )
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::UNAUTHORIZED, "missingAuthentication")
+      .await;
+
+    let api_keys: Vec<ApiApiKey> = t
+      .http()
+      .get("/api/user/api_keys")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(api_keys.len(), 1);
+    assert_eq!(api_keys[0].request_count, 2);
+    assert!(api_keys[0].last_used_at.is_some());
+
+    t.http()
+      .delete(format!("/api/user/api_keys/{}", created.api_key.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+
+    t.http()
+      .delete(format!("/api/user/api_keys/{}", created.api_key.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "apiKeyNotFound")
+      .await;
+
+    // deleted keys are rejected
+    t.unauthed_http()
+      .get("/api/scopes/scope")
+      .header(
+        x_jsr_api_key,
+        HeaderValue::from_str(&created.secret).unwrap(),
+      )
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::UNAUTHORIZED, "invalidApiKey")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn user_export() {
+    let mut t = TestSetup::new().await;
+    let db = t.db();
+
+    // an email address is required to receive the download link
+    t.http()
+      .get("/api/user/export")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "userEmailRequired")
+      .await;
+
+    let user = db
+      .insert_user(NewUser {
+        name: "User 5",
+        email: Some("user5@example.com"),
+        avatar_url: "",
+        github_id: None,
+        is_blocked: false,
+        is_staff: false,
+      })
+      .await
+      .unwrap();
+    let token = crate::token::create_token(
+      &db,
+      user.id,
+      TokenType::Web,
+      None,
+      None,
+      None,
+    )
+    .await
+    .unwrap();
+
+    let export: ApiUserExport = t
+      .http()
+      .get("/api/user/export")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(export.status, UserExportStatus::Pending);
+    assert!(export.expires_at.is_none());
+
+    // requesting another export returns the existing one
+    let export2: ApiUserExport = t
+      .http()
+      .get("/api/user/export")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(export.id, export2.id);
+
+    let claimed = db.claim_pending_user_exports(10).await.unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].status, UserExportStatus::Processing);
+    let registry_url = "http://jsr-tests.test".parse().unwrap();
+    let download_url =
+      process_user_export(&db, &t.buckets(), None, &registry_url, &claimed[0])
+        .await
+        .unwrap();
+    let path =
+      format!("{}?{}", download_url.path(), download_url.query().unwrap());
+
+    let export: ApiUserExport = t
+      .http()
+      .get("/api/user/export")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(export.status, UserExportStatus::Success);
+    assert!(export.expires_at.is_some());
+
+    let data: UserExportData = t
+      .unauthed_http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(data.user.id, user.id);
+    assert_eq!(data.user.email.as_deref(), Some("user5@example.com"));
+    assert_eq!(data.tokens.len(), 1);
+    assert!(data.scope_memberships.is_empty());
+
+    let path = format!("/api/user/export/{}/download?token=invalid", export.id);
+    t.unauthed_http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "userExportNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn stars() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    for name in ["foo", "bar"] {
+      let name = PackageName::try_from(name).unwrap();
+      let res = t
+        .ephemeral_database
+        .create_package(&scope, &name)
+        .await
+        .unwrap();
+      assert!(matches!(res, CreatePackageResult::Ok(_)));
+      t.ephemeral_database
+        .create_package_version_for_test(NewPackageVersion {
+          scope: &scope,
+          name: &name,
+          version: &"1.0.0".try_into().unwrap(),
+          user_id: None,
+          readme_path: None,
+          uses_npm: false,
+          exports: &ExportsMap::mock(),
+          bin: &Default::default(),
+          engines: &Default::default(),
+          keywords: &[],
+          license: None,
+          meta: Default::default(),
+        })
+        .await
+        .unwrap();
+    }
+
+    t.http()
+      .put("/api/user/stars/scope/bar")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    // starring twice is a no-op
+    t.http()
+      .put("/api/user/stars/scope/bar")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    t.http()
+      .put("/api/user/stars/scope/foo")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    let token = t.user2.token.clone();
+    t.http()
+      .put("/api/user/stars/scope/bar")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    t.http()
+      .put("/api/user/stars/scope/baz")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+
+    let stars = t
+      .http()
+      .get("/api/user/stars")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiList<ApiPackage>>()
+      .await;
+    assert_eq!(stars.total, 2);
+    assert_eq!(stars.items[0].name.to_string(), "foo");
+    assert_eq!(stars.items[0].star_count, 1);
+    assert_eq!(stars.items[1].name.to_string(), "bar");
+    assert_eq!(stars.items[1].star_count, 2);
+
+    let packages = t
+      .http()
+      .get("/api/packages?sort=stars")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiList<ApiPackage>>()
+      .await;
+    assert_eq!(packages.items[0].name.to_string(), "bar");
+    assert_eq!(packages.items[1].name.to_string(), "foo");
+
+    t.http()
+      .get("/api/packages?sort=downloads")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    t.http()
+      .delete("/api/user/stars/scope/bar")
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    let package = t
+      .http()
+      .get("/api/scopes/scope/packages/bar")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPackage>()
+      .await;
+    assert_eq!(package.star_count, 1);
+  }
+
+  #[tokio::test]
+  async fn collections() {
+    let mut t = TestSetup::new().await;
+
+    let scope = t.scope.scope.clone();
+    for name in ["foo", "bar"] {
+      let name = PackageName::try_from(name).unwrap();
+      let res = t
+        .ephemeral_database
+        .create_package(&scope, &name)
+        .await
+        .unwrap();
+      assert!(matches!(res, CreatePackageResult::Ok(_)));
+    }
+
+    let collection = t
+      .http()
+      .post("/api/user/collections")
+      .body_json(json!({
+        "name": " Testing tools ",
+        "packages": ["@scope/foo", "@scope/bar"],
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPackageCollection>()
+      .await;
+    assert_eq!(collection.name, "Testing tools");
+    assert_eq!(collection.description, "");
+    let user_id = t.user1.user.id;
+    assert_eq!(collection.user_id, user_id);
+    let names = |collection: &ApiPackageCollection| {
+      collection
+        .packages
+        .iter()
+        .map(|item| item.name.to_string())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&collection), vec!["foo", "bar"]);
+
+    t.http()
+      .post("/api/user/collections")
+      .body_json(json!({
+        "name": "Missing",
+        "packages": ["@scope/baz"],
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageNotFound")
+      .await;
+    t.http()
+      .post("/api/user/collections")
+      .body_json(json!({
+        "name": "Duplicates",
+        "packages": ["@scope/foo", "@scope/foo"],
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+
+    let path = format!("/api/user/collections/{}", collection.id);
+    let updated = t
+      .http()
+      .patch(&path)
+      .body_json(json!({
+        "description": "Packages for testing.",
+        "packages": ["@scope/bar", "@scope/foo"],
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPackageCollection>()
+      .await;
+    assert_eq!(updated.name, "Testing tools");
+    assert_eq!(updated.description, "Packages for testing.");
+    assert_eq!(names(&updated), vec!["bar", "foo"]);
+
+    // collections can only be changed by their owner
+    let token = t.user2.token.clone();
+    t.http()
+      .patch(&path)
+      .token(Some(&token))
+      .body_json(json!({ "name": "Mine" }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageCollectionNotFound")
+      .await;
+
+    // collections are public
+    let public = t
+      .unauthed_http()
+      .get(format!("/api/collections/{}", collection.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPackageCollection>()
+      .await;
+    assert_eq!(names(&public), vec!["bar", "foo"]);
+    let by_user = t
+      .unauthed_http()
+      .get(format!("/api/users/{user_id}/collections"))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Vec<ApiPackageCollection>>()
+      .await;
+    assert_eq!(by_user.len(), 1);
+    assert_eq!(by_user[0].id, collection.id);
+
+    t.http()
+      .delete(&path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok_no_content()
+      .await;
+    let collections = t
+      .http()
+      .get("/api/user/collections")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Vec<ApiPackageCollection>>()
+      .await;
+    assert!(collections.is_empty());
+    t.unauthed_http()
+      .get(format!("/api/collections/{}", collection.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageCollectionNotFound")
+      .await;
+  }
 }
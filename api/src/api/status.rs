@@ -0,0 +1,159 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use chrono::DateTime;
+use chrono::Utc;
+use hyper::Body;
+use hyper::Request;
+use routerify::ext::RequestExt;
+use tracing::instrument;
+
+use crate::db::Database;
+use crate::db::RegistryBacklogs;
+use crate::npm::NPM_TARBALL_REVISION;
+use crate::util::ApiResult;
+
+use super::ApiComponentStatus;
+use super::ApiRegistryStatus;
+use super::ApiStatusComponent;
+
+/// The lag, in seconds, after which a component is considered degraded, and
+/// after which it is considered to have a major outage.
+const PUBLISH_LAG_THRESHOLDS: (i64, i64) = (5 * 60, 30 * 60);
+const DOCS_GENERATION_LAG_THRESHOLDS: (i64, i64) = (10 * 60, 60 * 60);
+const SEARCH_INDEXING_LAG_THRESHOLDS: (i64, i64) = (10 * 60, 60 * 60);
+const NPM_BRIDGE_LAG_THRESHOLDS: (i64, i64) = (30 * 60, 6 * 60 * 60);
+
+#[instrument(name = "GET /api/status", skip(req), err)]
+pub async fn status_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiRegistryStatus> {
+  let db = req.data::<Database>().unwrap();
+  let backlogs = db
+    .get_registry_backlogs(NPM_TARBALL_REVISION as i32)
+    .await?;
+  Ok(registry_status(&backlogs, Utc::now()))
+}
+
+fn registry_status(
+  backlogs: &RegistryBacklogs,
+  now: DateTime<Utc>,
+) -> ApiRegistryStatus {
+  let components = vec![
+    component(
+      "publishing",
+      backlogs.pending_publishes,
+      backlogs.oldest_pending_publish,
+      PUBLISH_LAG_THRESHOLDS,
+      now,
+    ),
+    component(
+      "docsGeneration",
+      backlogs.processing_publishes,
+      backlogs.oldest_processing_publish,
+      DOCS_GENERATION_LAG_THRESHOLDS,
+      now,
+    ),
+    component(
+      "searchIndexing",
+      backlogs.pending_search_index_updates,
+      backlogs.oldest_pending_search_index_update,
+      SEARCH_INDEXING_LAG_THRESHOLDS,
+      now,
+    ),
+    component(
+      "npmBridge",
+      backlogs.missing_npm_tarballs,
+      backlogs.oldest_missing_npm_tarball,
+      NPM_BRIDGE_LAG_THRESHOLDS,
+      now,
+    ),
+  ];
+  let status = components
+    .iter()
+    .map(|component| component.status)
+    .max()
+    .unwrap_or(ApiComponentStatus::Operational);
+  ApiRegistryStatus { status, components }
+}
+
+fn component(
+  name: &str,
+  backlog: i64,
+  oldest: Option<DateTime<Utc>>,
+  (degraded_after, outage_after): (i64, i64),
+  now: DateTime<Utc>,
+) -> ApiStatusComponent {
+  let lag_seconds = oldest
+    .map(|oldest| (now - oldest).num_seconds().max(0))
+    .unwrap_or(0);
+  let status = if lag_seconds >= outage_after {
+    ApiComponentStatus::MajorOutage
+  } else if lag_seconds >= degraded_after {
+    ApiComponentStatus::Degraded
+  } else {
+    ApiComponentStatus::Operational
+  };
+  ApiStatusComponent {
+    name: name.to_string(),
+    status,
+    backlog,
+    lag_seconds,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Duration;
+  use chrono::Utc;
+
+  use super::registry_status;
+  use crate::api::ApiComponentStatus;
+  use crate::api::ApiRegistryStatus;
+  use crate::db::RegistryBacklogs;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  #[tokio::test]
+  async fn status() {
+    let mut t = TestSetup::new().await;
+    let mut resp = t.unauthed_http().get("/api/status").call().await.unwrap();
+    let body = resp.expect_ok::<ApiRegistryStatus>().await;
+    assert_eq!(body.status, ApiComponentStatus::Operational);
+    assert_eq!(body.components.len(), 4);
+    for component in body.components {
+      assert_eq!(component.status, ApiComponentStatus::Operational);
+      assert_eq!(component.backlog, 0);
+      assert_eq!(component.lag_seconds, 0);
+    }
+  }
+
+  #[test]
+  fn status_from_backlogs() {
+    let now = Utc::now();
+    let backlogs = RegistryBacklogs {
+      pending_publishes: 2,
+      oldest_pending_publish: Some(now - Duration::minutes(1)),
+      processing_publishes: 1,
+      oldest_processing_publish: Some(now - Duration::minutes(15)),
+      pending_search_index_updates: 0,
+      oldest_pending_search_index_update: None,
+      missing_npm_tarballs: 3,
+      oldest_missing_npm_tarball: Some(now - Duration::hours(7)),
+    };
+    let status = registry_status(&backlogs, now);
+    let statuses = status
+      .components
+      .iter()
+      .map(|c| (c.name.as_str(), c.status, c.backlog, c.lag_seconds))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      statuses,
+      vec![
+        ("publishing", ApiComponentStatus::Operational, 2, 60),
+        ("docsGeneration", ApiComponentStatus::Degraded, 1, 15 * 60),
+        ("searchIndexing", ApiComponentStatus::Operational, 0, 0),
+        ("npmBridge", ApiComponentStatus::MajorOutage, 3, 7 * 60 * 60),
+      ]
+    );
+    assert_eq!(status.status, ApiComponentStatus::MajorOutage);
+  }
+}
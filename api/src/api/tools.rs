@@ -0,0 +1,301 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::borrow::Cow;
+
+use deno_semver::jsr::JsrPackageReqReference;
+use hyper::Body;
+use hyper::Request;
+use routerify::prelude::RequestExt;
+use routerify::Router;
+use routerify_query::RequestQueryExt;
+use tracing::field;
+use tracing::instrument;
+use tracing::Span;
+
+use crate::db::Database;
+use crate::iam::ReqIamExt;
+use crate::ids::ScopedPackageName;
+use crate::npm::npm_module_paths;
+use crate::npm::NpmMappedJsrPackageName;
+use crate::rate_limit::RateLimitKey;
+use crate::rate_limit::RateLimiter;
+use crate::util;
+use crate::util::decode_json;
+use crate::util::ApiResult;
+use crate::util::CacheDuration;
+use crate::RegistryUrl;
+
+use super::ApiError;
+use super::ApiRenderReadmeRequest;
+use super::ApiRenderedReadme;
+use super::ApiResolvedNpmModule;
+use super::ApiResolvedTypes;
+
+/// The maximum size of a README that can be previewed. Rendering blocks the
+/// worker thread, so this bounds the time a single request can take.
+const MAX_README_SIZE: usize = 512 * 1024;
+
+pub fn tools_router() -> Router<Body, ApiError> {
+  Router::builder()
+    .post(
+      "/render_readme",
+      util::auth(util::json(render_readme_handler)),
+    )
+    .get(
+      "/resolve_types",
+      util::cache(
+        CacheDuration::ONE_MINUTE,
+        util::json(resolve_types_handler),
+      ),
+    )
+    .build()
+    .unwrap()
+}
+
+#[instrument(
+  name = "POST /api/tools/render_readme",
+  skip(req),
+  err,
+  fields(user.id, scope, package, version)
+)]
+pub async fn render_readme_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiRenderedReadme> {
+  let iam = req.iam();
+  let current_user = iam.check_current_user_access()?.to_owned();
+  Span::current().record("user.id", field::display(current_user.id));
+
+  let rate_limiter = req.data::<RateLimiter>().unwrap();
+  if !rate_limiter.check(RateLimitKey::RenderReadme(current_user.id)) {
+    return Err(ApiError::RateLimitExceeded);
+  }
+
+  let body: ApiRenderReadmeRequest = decode_json(&mut req).await?;
+  Span::current().record("scope", field::display(&body.scope));
+  Span::current().record("package", field::display(&body.package));
+  Span::current().record("version", field::display(&body.version));
+
+  if body.markdown.len() > MAX_README_SIZE {
+    return Err(ApiError::MalformedRequest {
+      msg: format!("markdown must be at most {MAX_README_SIZE} bytes").into(),
+    });
+  }
+
+  // The package does not have to exist yet. If it does, relative links are
+  // resolved against its linked GitHub repository, like on the package page.
+  let db = req.data::<Database>().unwrap();
+  let github_repository = db
+    .get_package(&body.scope, &body.package)
+    .await?
+    .and_then(|(_, repo, _)| repo);
+
+  let registry_url = req.data::<RegistryUrl>().unwrap().0.to_string();
+
+  let html = crate::docs::render_readme(
+    &body.markdown,
+    body.scope,
+    body.package,
+    body.version,
+    github_repository,
+    registry_url,
+  );
+
+  Ok(ApiRenderedReadme {
+    html,
+    css: Cow::Borrowed(deno_doc::html::STYLESHEET),
+    comrak_css: Cow::Borrowed(deno_doc::html::comrak::COMRAK_STYLESHEET),
+    script: Cow::Borrowed(deno_doc::html::SCRIPT_JS),
+  })
+}
+
+/// Resolves a `jsr:` specifier to the module it points to, like Deno would
+/// for a fresh install: the version constraint resolves to the highest version
+/// that is not yanked, and the sub path to an export of that version.
+#[instrument(
+  name = "GET /api/tools/resolve_types",
+  skip(req),
+  err,
+  fields(specifier)
+)]
+pub async fn resolve_types_handler(
+  req: Request<Body>,
+) -> ApiResult<ApiResolvedTypes> {
+  let specifier = req.query("specifier").ok_or_else(|| {
+    let msg = "Missing query parameter 'specifier'".into();
+    ApiError::MalformedRequest { msg }
+  })?;
+  Span::current().record("specifier", field::display(specifier));
+
+  let reference = JsrPackageReqReference::from_str(specifier).map_err(|err| {
+    let msg = format!("failed to parse specifier '{specifier}': {err}").into();
+    ApiError::MalformedRequest { msg }
+  })?;
+  let name = ScopedPackageName::new(reference.req().name.to_string())
+    .map_err(|err| {
+      let msg = format!("invalid package name in '{specifier}': {err}").into();
+      ApiError::MalformedRequest { msg }
+    })?;
+
+  let db = req.data::<Database>().unwrap();
+  db.get_package(&name.scope, &name.package)
+    .await?
+    .ok_or(ApiError::PackageNotFound)?;
+
+  let version_req = &reference.req().version_req;
+  let version = db
+    .list_package_versions(&name.scope, &name.package)
+    .await?
+    .into_iter()
+    .map(|(version, _)| version)
+    .filter(|version| !version.is_yanked)
+    .filter(|version| version_req.matches(&version.version.0))
+    .max_by(|a, b| a.version.cmp(&b.version))
+    .ok_or(ApiError::PackageVersionNotFound)?;
+
+  let export = match reference.sub_path() {
+    Some(sub_path) => format!("./{sub_path}"),
+    None => ".".to_owned(),
+  };
+  let path = version
+    .exports
+    .iter()
+    .find(|(key, _)| **key == export)
+    .map(|(_, path)| format!("/{}", path.trim_start_matches("./")))
+    .ok_or(ApiError::EntrypointOrSymbolNotFound)?;
+
+  let registry_url = &req.data::<RegistryUrl>().unwrap().0;
+  let url = registry_url
+    .join(&format!(
+      "@{}/{}/{}{path}",
+      name.scope, name.package, version.version
+    ))
+    .map_err(|_| ApiError::InternalServerError)?;
+
+  let media_type = deno_ast::MediaType::from_str(&path);
+  let (npm_path, npm_types_path) =
+    npm_module_paths(&path, version.meta.typescript_features.is_some());
+  let npm_name = NpmMappedJsrPackageName {
+    scope: &name.scope,
+    package: &name.package,
+  }
+  .to_string();
+
+  Ok(ApiResolvedTypes {
+    scope: name.scope,
+    package: name.package,
+    version: version.version,
+    path,
+    url: url.to_string(),
+    media_type: media_type.to_string(),
+    npm: ApiResolvedNpmModule {
+      name: npm_name,
+      path: npm_path,
+      types_path: npm_types_path,
+    },
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use hyper::StatusCode;
+  use serde_json::json;
+
+  use crate::api::ApiRenderedReadme;
+  use crate::api::ApiResolvedTypes;
+  use crate::db::PublishingTaskStatus;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  #[tokio::test]
+  async fn render_readme() {
+    let mut t = TestSetup::new().await;
+
+    let mut resp = t
+      .http()
+      .post("/api/tools/render_readme")
+      .body_json(json!({
+        "markdown": "# Hello\n\n<script>alert(1)</script>\n\n[guide](./docs/guide.md)",
+        "scope": "scope",
+        "package": "foo",
+        "version": "1.0.0",
+      }))
+      .call()
+      .await
+      .unwrap();
+    let readme: ApiRenderedReadme = resp.expect_ok().await;
+    assert!(readme.html.contains("Hello"), "{}", readme.html);
+    assert!(!readme.html.contains("<script>"), "{}", readme.html);
+    assert!(
+      readme.html.contains("/@scope/foo/1.0.0/./docs/guide.md"),
+      "{}",
+      readme.html
+    );
+
+    let mut resp = t
+      .unauthed_http()
+      .post("/api/tools/render_readme")
+      .body_json(json!({
+        "markdown": "# Hello",
+        "scope": "scope",
+        "package": "foo",
+        "version": "1.0.0",
+      }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::UNAUTHORIZED, "missingAuthentication")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn resolve_types() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    let resolved: ApiResolvedTypes = t
+      .http()
+      .get("/api/tools/resolve_types?specifier=jsr:@scope/foo@^1")
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(resolved.version.to_string(), "1.2.3");
+    assert_eq!(resolved.path, "/mod.ts");
+    assert!(
+      resolved.url.ends_with("/@scope/foo/1.2.3/mod.ts"),
+      "{}",
+      resolved.url
+    );
+    assert_eq!(resolved.media_type, "TypeScript");
+    assert_eq!(resolved.npm.name, "@jsr/scope__foo");
+    assert_eq!(resolved.npm.path, "/mod.js");
+    assert_eq!(resolved.npm.types_path.as_deref(), Some("/_dist/mod.d.ts"));
+
+    t.http()
+      .get("/api/tools/resolve_types?specifier=jsr:@scope/foo@^2")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageVersionNotFound")
+      .await;
+    t.http()
+      .get("/api/tools/resolve_types?specifier=jsr:@scope/foo@1/missing")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "entrypointOrSymbolNotFound")
+      .await;
+    t.http()
+      .get("/api/tools/resolve_types?specifier=npm:foo")
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+  }
+}
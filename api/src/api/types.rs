@@ -1,14 +1,25 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 
 use crate::db::*;
+use crate::iam::IamAction;
+use crate::iam::IamDecision;
+use crate::iam::IamRule;
 use crate::ids::PackageName;
 use crate::ids::PackagePath;
 use crate::ids::ScopeName;
+use crate::ids::ScopedPackageName;
 use crate::ids::Version;
+use crate::license::LicenseClassification;
 use crate::provenance::ProvenanceBundle;
+use crate::trust::TrustLevel;
+use crate::typescript::TypeScriptFeature;
+use crate::typescript::TypeScriptVersion;
 use chrono::DateTime;
+use chrono::NaiveDate;
 use chrono::Utc;
+use indexmap::IndexMap;
 use serde::Deserialize;
 use serde::Serialize;
 use uuid::Uuid;
@@ -17,6 +28,7 @@ use uuid::Uuid;
 #[serde(rename_all = "snake_case")]
 pub enum ApiPublishingTaskStatus {
   Pending,
+  Scheduled,
   Processing,
   Processed,
   Success,
@@ -27,6 +39,7 @@ impl From<PublishingTaskStatus> for ApiPublishingTaskStatus {
   fn from(value: PublishingTaskStatus) -> Self {
     match value {
       PublishingTaskStatus::Pending => ApiPublishingTaskStatus::Pending,
+      PublishingTaskStatus::Scheduled => ApiPublishingTaskStatus::Scheduled,
       PublishingTaskStatus::Processing => ApiPublishingTaskStatus::Processing,
       PublishingTaskStatus::Processed => ApiPublishingTaskStatus::Processed,
       PublishingTaskStatus::Success => ApiPublishingTaskStatus::Success,
@@ -51,6 +64,63 @@ impl From<PublishingTaskError> for ApiPublishingTaskError {
   }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPublishingTaskErrorFile {
+  pub path: String,
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+}
+
+impl From<PublishingTaskErrorFile> for ApiPublishingTaskErrorFile {
+  fn from(value: PublishingTaskErrorFile) -> Self {
+    Self {
+      path: value.path,
+      line: value.line,
+      column: value.column,
+    }
+  }
+}
+
+/// The exact versions of the tools that analyzed a package during publishing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiAnalysisVersions {
+  pub deno_ast: String,
+  pub deno_graph: String,
+  pub deno_doc: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPublishingTaskExplanation {
+  pub code: String,
+  pub message: String,
+  /// Steps that fix the error, in the order they should be tried.
+  pub remediation: Vec<String>,
+  pub docs_url: Option<String>,
+  pub files: Vec<ApiPublishingTaskErrorFile>,
+  pub config_file: PackagePath,
+  pub config_fields: Vec<String>,
+  pub analysis_versions: ApiAnalysisVersions,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPublishingTaskWarning {
+  pub code: String,
+  pub message: String,
+}
+
+impl From<PublishingTaskWarning> for ApiPublishingTaskWarning {
+  fn from(value: PublishingTaskWarning) -> Self {
+    Self {
+      code: value.code,
+      message: value.message,
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiPublishingTask {
@@ -61,10 +131,47 @@ pub struct ApiPublishingTask {
   pub package_scope: ScopeName,
   pub package_name: PackageName,
   pub package_version: Version,
+  pub size_report: Option<ApiPublishingTaskSizeReport>,
+  pub warnings: Vec<ApiPublishingTaskWarning>,
+  pub artifacts_expire_at: Option<DateTime<Utc>>,
+  pub publish_at: Option<DateTime<Utc>>,
   pub created_at: DateTime<Utc>,
   pub updated_at: DateTime<Utc>,
 }
 
+/// The sizes of the published package version. The deltas are relative to the
+/// version of the package that was published before it, if any.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPublishingTaskSizeReport {
+  pub unpacked_size: u64,
+  pub file_count: u64,
+  pub npm_tarball_size: u64,
+  pub previous_version: Option<Version>,
+  pub unpacked_size_delta: Option<i64>,
+  pub file_count_delta: Option<i64>,
+  pub npm_tarball_size_delta: Option<i64>,
+}
+
+impl From<PublishingTaskSizeReport> for ApiPublishingTaskSizeReport {
+  fn from(value: PublishingTaskSizeReport) -> Self {
+    let previous = value.previous.as_ref();
+    Self {
+      unpacked_size: value.unpacked_size as u64,
+      file_count: value.file_count as u64,
+      npm_tarball_size: value.npm_tarball_size as u64,
+      previous_version: previous.map(|previous| previous.version.clone()),
+      unpacked_size_delta: previous
+        .map(|previous| value.unpacked_size - previous.unpacked_size),
+      file_count_delta: previous
+        .map(|previous| (value.file_count - previous.file_count) as i64),
+      npm_tarball_size_delta: previous
+        .and_then(|previous| previous.npm_tarball_size)
+        .map(|size| (value.npm_tarball_size - size) as i64),
+    }
+  }
+}
+
 impl From<PublishingTask> for ApiPublishingTask {
   fn from(value: PublishingTask) -> Self {
     Self {
@@ -75,6 +182,10 @@ impl From<PublishingTask> for ApiPublishingTask {
       package_scope: value.package_scope,
       package_name: value.package_name,
       package_version: value.package_version,
+      size_report: value.size_report.map(Into::into),
+      warnings: value.warnings.0.into_iter().map(Into::into).collect(),
+      artifacts_expire_at: value.artifacts_expire_at,
+      publish_at: value.publish_at,
       created_at: value.created_at,
       updated_at: value.updated_at,
     }
@@ -216,12 +327,50 @@ impl From<Alias> for ApiAlias {
   }
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreatePackageRedirectRequest {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub target_scope: ScopeName,
+  pub target_package: PackageName,
+  /// When the redirect expires. Defaults to the end of the grace period.
+  pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPackageRedirect {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub target_scope: ScopeName,
+  pub target_package: PackageName,
+  pub expires_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<PackageRedirect> for ApiPackageRedirect {
+  fn from(redirect: PackageRedirect) -> Self {
+    Self {
+      scope: redirect.scope,
+      package: redirect.name,
+      target_scope: redirect.target_scope,
+      target_package: redirect.target_name,
+      expires_at: redirect.expires_at,
+      created_at: redirect.created_at,
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiScope {
   pub scope: ScopeName,
   pub updated_at: DateTime<Utc>,
   pub created_at: DateTime<Utc>,
+  /// Only set when a single scope is requested.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub profile: Option<ApiScopeProfile>,
 }
 
 impl From<Scope> for ApiScope {
@@ -230,6 +379,23 @@ impl From<Scope> for ApiScope {
       scope: scope.scope,
       updated_at: scope.updated_at,
       created_at: scope.created_at,
+      profile: None,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopeProfile {
+  pub readme: Option<String>,
+  pub pinned_packages: Vec<PackageName>,
+}
+
+impl From<ScopeProfile> for ApiScopeProfile {
+  fn from(profile: ScopeProfile) -> Self {
+    Self {
+      readme: profile.readme,
+      pinned_packages: profile.pinned_packages,
     }
   }
 }
@@ -256,6 +422,13 @@ pub struct ApiFullScope {
   pub gh_actions_verify_actor: bool,
   #[serde(rename = "requirePublishingFromCI")]
   pub require_publishing_from_ci: bool,
+  pub validation_level: Option<ApiValidationLevel>,
+  pub outdated_dependencies_email: bool,
+  pub download_anomaly_email: bool,
+  pub docs_coverage_policy: ApiDocsCoveragePolicy,
+  /// Only set when a single scope is requested.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub profile: Option<ApiScopeProfile>,
 }
 
 impl From<(Scope, ScopeUsage, UserPublic)> for ApiFullScope {
@@ -276,10 +449,27 @@ impl From<(Scope, ScopeUsage, UserPublic)> for ApiFullScope {
       },
       gh_actions_verify_actor: scope.verify_oidc_actor,
       require_publishing_from_ci: scope.require_publishing_from_ci,
+      validation_level: scope.validation_level.map(ApiValidationLevel::from),
+      outdated_dependencies_email: scope.outdated_dependencies_email,
+      download_anomaly_email: scope.download_anomaly_email,
+      docs_coverage_policy: ApiDocsCoveragePolicy {
+        min_coverage: scope.min_docs_coverage,
+        enforce: scope.enforce_min_docs_coverage,
+      },
+      profile: None,
     }
   }
 }
 
+/// The share of exported symbols of each entrypoint that must have JSDoc.
+/// Publishes below `min_coverage` get a warning, and fail if `enforce` is set.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDocsCoveragePolicy {
+  pub min_coverage: Option<f32>,
+  pub enforce: bool,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase", untagged)]
 pub enum ApiScopeOrFullScope {
@@ -287,6 +477,16 @@ pub enum ApiScopeOrFullScope {
   Full(ApiFullScope),
 }
 
+impl ApiScopeOrFullScope {
+  pub fn with_profile(mut self, profile: ScopeProfile) -> Self {
+    match &mut self {
+      Self::Partial(scope) => scope.profile = Some(profile.into()),
+      Self::Full(scope) => scope.profile = Some(profile.into()),
+    }
+    self
+  }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiCreateScopeRequest {
@@ -359,6 +559,141 @@ impl From<(ScopeInvite, UserPublic, UserPublic)> for ApiScopeInvite {
   }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopeMetrics {
+  pub publishes_per_week: Vec<ApiScopeWeeklyPublishes>,
+  pub package_downloads: Vec<ApiScopePackageDownloads>,
+  pub active_tokens: i64,
+  pub members: Vec<ApiScopeMemberActivity>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopeWeeklyPublishes {
+  pub week: DateTime<Utc>,
+  pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopePackageDownloads {
+  pub package: PackageName,
+  pub downloads_30d: i64,
+  pub downloads_total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopeMemberActivity {
+  pub user: ApiUser,
+  pub is_admin: bool,
+  pub publishes_30d: i64,
+  pub last_published_at: Option<DateTime<Utc>>,
+}
+
+impl From<ScopeMetrics> for ApiScopeMetrics {
+  fn from(metrics: ScopeMetrics) -> Self {
+    Self {
+      publishes_per_week: metrics
+        .publishes_per_week
+        .into_iter()
+        .map(|publishes| ApiScopeWeeklyPublishes {
+          week: publishes.week,
+          count: publishes.count,
+        })
+        .collect(),
+      package_downloads: metrics
+        .package_downloads
+        .into_iter()
+        .map(|downloads| ApiScopePackageDownloads {
+          package: downloads.package,
+          downloads_30d: downloads.downloads_30d,
+          downloads_total: downloads.downloads_total,
+        })
+        .collect(),
+      active_tokens: metrics.active_tokens,
+      members: metrics
+        .members
+        .into_iter()
+        .map(|member| ApiScopeMemberActivity {
+          user: member.user.into(),
+          is_admin: member.is_admin,
+          publishes_30d: member.publishes_30d,
+          last_published_at: member.last_published_at,
+        })
+        .collect(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopePublishFreeze {
+  pub id: Uuid,
+  pub scope: ScopeName,
+  pub starts_at: DateTime<Utc>,
+  pub ends_at: DateTime<Utc>,
+  pub recurs_weekly: bool,
+  pub reason: String,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<ScopePublishFreeze> for ApiScopePublishFreeze {
+  fn from(value: ScopePublishFreeze) -> Self {
+    Self {
+      id: value.id,
+      scope: value.scope,
+      starts_at: value.starts_at,
+      ends_at: value.ends_at,
+      recurs_weekly: value.recurs_weekly,
+      reason: value.reason,
+      created_at: value.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreateScopePublishFreezeRequest {
+  pub starts_at: DateTime<Utc>,
+  pub ends_at: DateTime<Utc>,
+  #[serde(default)]
+  pub recurs_weekly: bool,
+  pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopePublishIpRange {
+  pub id: Uuid,
+  pub scope: ScopeName,
+  /// The range in CIDR notation, for example `203.0.113.0/24`.
+  pub cidr: String,
+  pub description: String,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<ScopePublishIpRange> for ApiScopePublishIpRange {
+  fn from(value: ScopePublishIpRange) -> Self {
+    Self {
+      id: value.id,
+      scope: value.scope,
+      cidr: value.cidr.to_string(),
+      description: value.description,
+      created_at: value.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreateScopePublishIpRangeRequest {
+  pub cidr: String,
+  #[serde(default)]
+  pub description: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiPackageScore {
@@ -366,8 +701,14 @@ pub struct ApiPackageScore {
   pub has_readme_examples: bool,
   pub all_entrypoints_docs: bool,
   pub percentage_documented_symbols: f32,
+  /// The share of exported symbols of each entrypoint that have JSDoc, keyed
+  /// by export name. Empty for versions published before it was recorded.
+  pub docs_coverage: IndexMap<String, f32>,
   pub all_fast_check: bool,
   pub has_provenance: bool,
+  pub has_permissions_declaration: bool,
+  /// The permissions the version declares that it needs, if any.
+  pub permissions: Option<ApiPackageVersionPermissions>,
 
   // package wide
   pub has_description: bool,
@@ -405,6 +746,10 @@ impl From<(&PackageVersionMeta, &Package)> for ApiPackageScore {
       score += 1;
     }
 
+    if meta.permissions.is_some() {
+      score += 1;
+    }
+
     // You only need to document 80% of your symbols to get all the points.
     score += ((meta.percentage_documented_symbols / 0.8).min(1.0) * 5.0).floor()
       as u32;
@@ -449,8 +794,11 @@ impl From<(&PackageVersionMeta, &Package)> for ApiPackageScore {
       has_readme_examples: meta.has_readme_examples,
       all_entrypoints_docs: meta.all_entrypoints_docs,
       percentage_documented_symbols: meta.percentage_documented_symbols,
+      docs_coverage: meta.docs_coverage.clone(),
       all_fast_check: meta.all_fast_check,
       has_provenance: meta.has_provenance,
+      has_permissions_declaration: meta.permissions.is_some(),
+      permissions: meta.permissions.clone().map(Into::into),
       has_description: !package.description.is_empty(),
       at_least_one_runtime_compatible: compatible_runtimes_count >= 1,
       multiple_runtimes_compatible: compatible_runtimes_count >= 2,
@@ -467,13 +815,75 @@ pub struct ApiPackage {
   pub description: String,
   pub github_repository: Option<ApiGithubRepository>,
   pub runtime_compat: ApiRuntimeCompat,
+  pub validation_level: ApiValidationLevel,
   pub updated_at: DateTime<Utc>,
   pub created_at: DateTime<Utc>,
   pub version_count: u64,
+  pub star_count: u64,
   pub score: Option<u32>,
   pub latest_version: Option<String>,
   pub when_featured: Option<DateTime<Utc>>,
   pub is_archived: bool,
+  pub noindex: bool,
+  pub keywords: Vec<String>,
+  pub docs_landing_page: Option<String>,
+  /// The package that replaces this one, if it was renamed or moved.
+  pub successor: Option<ApiPackageSuccessor>,
+  pub maintenance_status: Option<ApiMaintenanceStatus>,
+  /// Set when the package has not been released in a long time, until a
+  /// scope admin confirms or dismisses the suggestion to mark it as
+  /// unmaintained.
+  pub unmaintained_suggested_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiMaintenanceStatus {
+  ActivelyMaintained,
+  MaintenanceOnly,
+  SeekingMaintainers,
+  Unmaintained,
+}
+
+impl From<MaintenanceStatus> for ApiMaintenanceStatus {
+  fn from(value: MaintenanceStatus) -> Self {
+    match value {
+      MaintenanceStatus::ActivelyMaintained => {
+        ApiMaintenanceStatus::ActivelyMaintained
+      }
+      MaintenanceStatus::MaintenanceOnly => {
+        ApiMaintenanceStatus::MaintenanceOnly
+      }
+      MaintenanceStatus::SeekingMaintainers => {
+        ApiMaintenanceStatus::SeekingMaintainers
+      }
+      MaintenanceStatus::Unmaintained => ApiMaintenanceStatus::Unmaintained,
+    }
+  }
+}
+
+impl From<ApiMaintenanceStatus> for MaintenanceStatus {
+  fn from(value: ApiMaintenanceStatus) -> Self {
+    match value {
+      ApiMaintenanceStatus::ActivelyMaintained => {
+        MaintenanceStatus::ActivelyMaintained
+      }
+      ApiMaintenanceStatus::MaintenanceOnly => {
+        MaintenanceStatus::MaintenanceOnly
+      }
+      ApiMaintenanceStatus::SeekingMaintainers => {
+        MaintenanceStatus::SeekingMaintainers
+      }
+      ApiMaintenanceStatus::Unmaintained => MaintenanceStatus::Unmaintained,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPackageSuccessor {
+  pub scope: ScopeName,
+  pub name: PackageName,
 }
 
 impl From<PackageWithGitHubRepoAndMeta> for ApiPackage {
@@ -488,9 +898,11 @@ impl From<PackageWithGitHubRepoAndMeta> for ApiPackage {
       description: package.description,
       github_repository: repo.map(ApiGithubRepository::from),
       runtime_compat: package.runtime_compat.into(),
+      validation_level: package.validation_level.into(),
       updated_at: package.updated_at,
       created_at: package.created_at,
       version_count: package.version_count as u64,
+      star_count: package.star_count as u64,
       score: package
         .latest_version
         .as_ref()
@@ -498,6 +910,15 @@ impl From<PackageWithGitHubRepoAndMeta> for ApiPackage {
       latest_version: package.latest_version,
       when_featured: package.when_featured,
       is_archived: package.is_archived,
+      noindex: package.noindex,
+      keywords: package.keywords,
+      docs_landing_page: package.docs_landing_page,
+      successor: package
+        .successor_scope
+        .zip(package.successor_name)
+        .map(|(scope, name)| ApiPackageSuccessor { scope, name }),
+      maintenance_status: package.maintenance_status.map(Into::into),
+      unmaintained_suggested_at: package.unmaintained_suggested_at,
     }
   }
 }
@@ -514,8 +935,13 @@ pub enum ApiUpdatePackageRequest {
   Description(String),
   GithubRepository(Option<ApiUpdatePackageGithubRepositoryRequest>),
   RuntimeCompat(ApiRuntimeCompat),
+  ValidationLevel(ApiValidationLevel),
   IsFeatured(bool),
   IsArchived(bool),
+  Noindex(bool),
+  DocsLandingPage(Option<String>),
+  Successor(Option<ApiPackageSuccessor>),
+  MaintenanceStatus(Option<ApiMaintenanceStatus>),
 }
 
 #[derive(Debug, Deserialize)]
@@ -523,6 +949,10 @@ pub enum ApiUpdatePackageRequest {
 pub struct ApiUpdatePackageGithubRepositoryRequest {
   pub owner: String,
   pub name: String,
+  /// The directory of the repository that the package lives in, for packages
+  /// in monorepos.
+  #[serde(default)]
+  pub subdirectory: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -535,6 +965,72 @@ pub struct ApiProvenanceStatementRequest {
 #[serde(rename_all = "camelCase")]
 pub struct ApiUpdatePackageVersionRequest {
   pub yanked: bool,
+  /// Why the version is yanked. Ignored when unyanking.
+  #[serde(default)]
+  pub yank_reason_category: Option<ApiYankReasonCategory>,
+  #[serde(default)]
+  pub yank_reason: Option<String>,
+  /// Yank the version even if it is the latest version of the package.
+  #[serde(default)]
+  pub force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiBulkYankPackageVersionsRequest {
+  pub yanked: bool,
+  /// A semver range that selects the versions to update. Mutually exclusive
+  /// with `versions`.
+  #[serde(default)]
+  pub range: Option<String>,
+  /// An explicit list of the versions to update. Mutually exclusive with
+  /// `range`.
+  #[serde(default)]
+  pub versions: Option<Vec<Version>>,
+  /// Why the versions are yanked. Ignored when unyanking.
+  #[serde(default)]
+  pub yank_reason_category: Option<ApiYankReasonCategory>,
+  #[serde(default)]
+  pub yank_reason: Option<String>,
+  /// Yank the versions even if one of them is the latest version of the
+  /// package.
+  #[serde(default)]
+  pub force: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiBulkYankPackageVersionsResponse {
+  /// The versions that were updated, newest first.
+  pub versions: Vec<Version>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiYankReasonCategory {
+  Security,
+  Broken,
+  Accidental,
+}
+
+impl From<YankReasonCategory> for ApiYankReasonCategory {
+  fn from(value: YankReasonCategory) -> Self {
+    match value {
+      YankReasonCategory::Security => ApiYankReasonCategory::Security,
+      YankReasonCategory::Broken => ApiYankReasonCategory::Broken,
+      YankReasonCategory::Accidental => ApiYankReasonCategory::Accidental,
+    }
+  }
+}
+
+impl From<ApiYankReasonCategory> for YankReasonCategory {
+  fn from(value: ApiYankReasonCategory) -> Self {
+    match value {
+      ApiYankReasonCategory::Security => YankReasonCategory::Security,
+      ApiYankReasonCategory::Broken => YankReasonCategory::Broken,
+      ApiYankReasonCategory::Accidental => YankReasonCategory::Accidental,
+    }
+  }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -543,6 +1039,7 @@ pub struct ApiGithubRepository {
   pub id: i64,
   pub owner: String,
   pub name: String,
+  pub subdirectory: Option<String>,
   pub updated_at: DateTime<Utc>,
   pub created_at: DateTime<Utc>,
 }
@@ -553,6 +1050,7 @@ impl From<GithubRepository> for ApiGithubRepository {
       id: repo.id,
       owner: repo.owner,
       name: repo.name,
+      subdirectory: repo.subdirectory,
       updated_at: repo.updated_at,
       created_at: repo.created_at,
     }
@@ -598,6 +1096,34 @@ impl From<ApiRuntimeCompat> for RuntimeCompat {
   }
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiValidationLevel {
+  Full,
+  LintOnly,
+  None,
+}
+
+impl From<ValidationLevel> for ApiValidationLevel {
+  fn from(value: ValidationLevel) -> Self {
+    match value {
+      ValidationLevel::Full => ApiValidationLevel::Full,
+      ValidationLevel::LintOnly => ApiValidationLevel::LintOnly,
+      ValidationLevel::None => ApiValidationLevel::None,
+    }
+  }
+}
+
+impl From<ApiValidationLevel> for ValidationLevel {
+  fn from(value: ApiValidationLevel) -> Self {
+    match value {
+      ApiValidationLevel::Full => ValidationLevel::Full,
+      ApiValidationLevel::LintOnly => ValidationLevel::LintOnly,
+      ApiValidationLevel::None => ValidationLevel::None,
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiPackageVersion {
@@ -605,15 +1131,77 @@ pub struct ApiPackageVersion {
   pub package: PackageName,
   pub version: Version,
   pub yanked: bool,
+  pub yank_reason_category: Option<ApiYankReasonCategory>,
+  pub yank_reason: Option<String>,
   pub uses_npm: bool,
   pub newer_versions_count: u64,
   pub lifetime_download_count: u64,
   pub rekor_log_id: Option<String>,
   pub readme_path: Option<PackagePath>,
+  pub engines: ApiPackageVersionEngines,
+  pub keywords: Vec<String>,
+  /// The permissions the version declares that it needs, or `None` if it
+  /// does not declare them.
+  pub permissions: Option<ApiPackageVersionPermissions>,
+  pub unpacked_size: u64,
+  pub file_count: u64,
+  pub npm_tarball_size: Option<u64>,
   pub updated_at: DateTime<Utc>,
   pub created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct ApiPackageVersionEngines {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deno: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub node: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub bun: Option<String>,
+}
+
+impl From<PackageVersionEngines> for ApiPackageVersionEngines {
+  fn from(value: PackageVersionEngines) -> Self {
+    ApiPackageVersionEngines {
+      deno: value.deno,
+      node: value.node,
+      bun: value.bun,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct ApiPackageVersionPermissions {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub net: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub env: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub read: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub write: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub run: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sys: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ffi: Option<PermissionTargets>,
+}
+
+impl From<PackageVersionPermissions> for ApiPackageVersionPermissions {
+  fn from(value: PackageVersionPermissions) -> Self {
+    ApiPackageVersionPermissions {
+      net: value.net,
+      env: value.env,
+      read: value.read,
+      write: value.write,
+      run: value.run,
+      sys: value.sys,
+      ffi: value.ffi,
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", tag = "kind")]
 #[allow(clippy::large_enum_variant)]
@@ -633,6 +1221,18 @@ pub enum ApiPackageVersionDocs {
   },
 }
 
+/// The documentation of a single symbol, for embedding it elsewhere.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPackageVersionSymbolDocs {
+  pub symbol: String,
+  pub css: Cow<'static, str>,
+  pub comrak_css: Cow<'static, str>,
+  pub html: String,
+  /// The doc nodes of the symbol, as generated by `deno_doc`.
+  pub nodes: Vec<serde_json::Value>,
+}
+
 impl From<PackageVersion> for ApiPackageVersion {
   fn from(value: PackageVersion) -> Self {
     ApiPackageVersion {
@@ -640,11 +1240,19 @@ impl From<PackageVersion> for ApiPackageVersion {
       package: value.name,
       version: value.version,
       yanked: value.is_yanked,
+      yank_reason_category: value.yank_reason_category.map(Into::into),
+      yank_reason: value.yank_reason,
       uses_npm: value.uses_npm,
       newer_versions_count: value.newer_versions_count as u64,
       lifetime_download_count: value.lifetime_download_count as u64,
       rekor_log_id: value.rekor_log_id,
       readme_path: value.readme_path,
+      engines: value.engines.into(),
+      keywords: value.keywords,
+      permissions: value.meta.permissions.map(Into::into),
+      unpacked_size: value.unpacked_size as u64,
+      file_count: value.file_count as u64,
+      npm_tarball_size: value.npm_tarball_size.map(|size| size as u64),
       updated_at: value.updated_at,
       created_at: value.created_at,
     }
@@ -681,6 +1289,9 @@ pub struct ApiPackageVersionSource {
   pub comrak_css: Cow<'static, str>,
   pub script: Cow<'static, str>,
   pub source: ApiSource,
+  /// The URL of the file or directory in the linked GitHub repository, at the
+  /// commit that the version was built from.
+  pub repository_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -691,6 +1302,8 @@ pub struct ApiPackageVersionWithUser {
   pub version: Version,
   pub user: Option<ApiUser>,
   pub yanked: bool,
+  pub yank_reason_category: Option<ApiYankReasonCategory>,
+  pub yank_reason: Option<String>,
   pub uses_npm: bool,
   pub newer_versions_count: i64,
   pub lifetime_download_count: i64,
@@ -714,6 +1327,10 @@ impl From<(PackageVersion, Option<UserPublic>)> for ApiPackageVersionWithUser {
       version: package_version.version,
       user: user.map(|user| user.into()),
       yanked: package_version.is_yanked,
+      yank_reason_category: package_version
+        .yank_reason_category
+        .map(Into::into),
+      yank_reason: package_version.yank_reason,
       uses_npm: package_version.uses_npm,
       newer_versions_count: package_version.newer_versions_count,
       lifetime_download_count: package_version.lifetime_download_count,
@@ -725,45 +1342,320 @@ impl From<(PackageVersion, Option<UserPublic>)> for ApiPackageVersionWithUser {
   }
 }
 
-#[derive(Debug, Deserialize)]
+/// Where a package version came from: who published it, from where, and how
+/// long the publish took.
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ApiAdminUpdateUserRequest {
-  pub is_staff: Option<bool>,
-  pub is_blocked: Option<bool>,
-  pub scope_limit: Option<i32>,
+pub struct ApiPackageVersionPublishInfo {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+  pub publishing_task_id: Uuid,
+  /// The user that published the version. For publishes from GitHub Actions,
+  /// this is the user that triggered the workflow, if they have an account.
+  pub user: Option<ApiUser>,
+  /// The repository whose GitHub Actions workflow published the version, if
+  /// it was published from GitHub Actions.
+  pub github_repository: Option<ApiGithubRepository>,
+  /// The GitHub Actions workflow run that published the version, if it was
+  /// published from GitHub Actions.
+  pub github_workflow_run: Option<ApiGithubWorkflowRun>,
+  pub provenance: Option<ApiPublishProvenance>,
+  /// The URL of the version metadata in the registry.
+  pub meta_url: String,
+  pub queued_at: DateTime<Utc>,
+  pub publish_at: Option<DateTime<Utc>>,
+  pub completed_at: DateTime<Utc>,
+  /// The time from when processing of the publish started until it completed.
+  pub duration_ms: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ApiAdminUpdateScopeRequest {
-  pub package_limit: Option<i32>,
-  pub new_package_per_week_limit: Option<i32>,
-  pub publish_attempts_per_week_limit: Option<i32>,
+pub struct ApiGithubWorkflowRun {
+  pub repository: String,
+  pub workflow_path: String,
+  #[serde(rename = "ref")]
+  pub git_ref: String,
+  pub run_id: i64,
+  pub run_url: String,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum ApiUpdateScopeRequest {
-  #[serde(rename = "ghActionsVerifyActor")]
-  GhActionsVerifyActor(bool),
-  #[serde(rename = "requirePublishingFromCI")]
-  RequirePublishingFromCI(bool),
+impl From<GithubWorkflowRun> for ApiGithubWorkflowRun {
+  fn from(run: GithubWorkflowRun) -> Self {
+    Self {
+      run_url: format!(
+        "https://github.com/{}/actions/runs/{}",
+        run.repository, run.run_id
+      ),
+      repository: run.repository,
+      workflow_path: run.workflow_path,
+      git_ref: run.git_ref,
+      run_id: run.run_id,
+    }
+  }
 }
 
+/// An import of a published module that points to a package on JSR or npm,
+/// or to a runtime built-in module, and what it resolved to when the version
+/// was published.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ApiStats {
-  pub newest: Vec<ApiPackage>,
-  pub updated: Vec<ApiPackageVersion>,
-  pub featured: Vec<ApiPackage>,
+pub struct ApiPublishedSpecifier {
+  pub referrer: String,
+  pub specifier: String,
+  pub resolved: String,
+  pub kind: ApiPublishedSpecifierKind,
+  pub imported_names: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ApiMetrics {
-  pub packages: usize,
-  pub packages_1d: usize,
-  pub packages_7d: usize,
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiPublishedSpecifierKind {
+  Jsr,
+  Npm,
+  Node,
+  Bun,
+}
+
+impl From<PublishedSpecifier> for ApiPublishedSpecifier {
+  fn from(value: PublishedSpecifier) -> Self {
+    Self {
+      referrer: value.referrer,
+      specifier: value.specifier,
+      resolved: value.resolved,
+      kind: match value.kind {
+        PublishedSpecifierKind::Jsr => ApiPublishedSpecifierKind::Jsr,
+        PublishedSpecifierKind::Npm => ApiPublishedSpecifierKind::Npm,
+        PublishedSpecifierKind::Node => ApiPublishedSpecifierKind::Node,
+        PublishedSpecifierKind::Bun => ApiPublishedSpecifierKind::Bun,
+      },
+      imported_names: value.imported_names,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPackageVersionArtifact {
+  pub filename: String,
+  pub size: u64,
+  pub sha256: String,
+  /// `None` while the file has not been uploaded yet.
+  pub uploaded_at: Option<DateTime<Utc>>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<PackageVersionArtifact> for ApiPackageVersionArtifact {
+  fn from(value: PackageVersionArtifact) -> Self {
+    Self {
+      filename: value.filename,
+      size: value.size as u64,
+      sha256: value.sha256,
+      uploaded_at: value.uploaded_at,
+      created_at: value.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreatePackageVersionArtifactRequest {
+  pub filename: String,
+  pub size: u64,
+  pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreatedPackageVersionArtifact {
+  pub artifact: ApiPackageVersionArtifact,
+  /// The file is uploaded to this URL with a `PUT` request, which needs no
+  /// further authentication.
+  pub upload_url: String,
+  pub upload_expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDeprecatedImport {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  /// The latest version of the dependency, in which the symbol is deprecated.
+  pub version: Version,
+  /// The export of the dependency the symbol is imported from, like `.`.
+  pub export: String,
+  pub name: String,
+  pub message: Option<String>,
+  /// The modules of the importing version that import the symbol.
+  pub referrers: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPublishProvenance {
+  pub rekor_log_id: String,
+  /// The entry of the provenance statement in the Sigstore transparency log.
+  pub transparency_log_url: String,
+  pub source_repository: Option<String>,
+  pub source_commit: Option<String>,
+}
+
+/// The fields of a package version that are returned by the versions list in
+/// compact mode.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPackageVersionSummary {
+  pub version: Version,
+  pub yanked: bool,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<PackageVersion> for ApiPackageVersionSummary {
+  fn from(package_version: PackageVersion) -> Self {
+    ApiPackageVersionSummary {
+      version: package_version.version,
+      yanked: package_version.is_yanked,
+      created_at: package_version.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum ApiPackageVersionListItem {
+  Full(ApiPackageVersionWithUser),
+  Compact(ApiPackageVersionSummary),
+}
+
+/// The trust level of the current user, and the limits that come with it.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiUserTrust {
+  pub level: TrustLevel,
+  pub scope_limit: i32,
+  pub new_packages_per_day_limit: Option<u32>,
+  pub new_packages_per_day_usage: u64,
+  pub publish_attempts_per_day_limit: Option<u32>,
+  pub publish_attempts_per_day_usage: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiUserModerationFlag {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub reason: String,
+  pub created_by: Option<Uuid>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<UserModerationFlag> for ApiUserModerationFlag {
+  fn from(flag: UserModerationFlag) -> Self {
+    Self {
+      id: flag.id,
+      user_id: flag.user_id,
+      reason: flag.reason,
+      created_by: flag.created_by,
+      created_at: flag.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreateUserModerationFlagRequest {
+  pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopeRecoveryRequest {
+  pub id: Uuid,
+  pub scope: ScopeName,
+  pub requester_id: Uuid,
+  pub previous_creator_id: Uuid,
+  pub reason: String,
+  pub status: ScopeRecoveryStatus,
+  pub reviewed_by: Option<Uuid>,
+  pub reviewed_at: Option<DateTime<Utc>>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<ScopeRecoveryRequest> for ApiScopeRecoveryRequest {
+  fn from(request: ScopeRecoveryRequest) -> Self {
+    Self {
+      id: request.id,
+      scope: request.scope,
+      requester_id: request.requester_id,
+      previous_creator_id: request.previous_creator,
+      reason: request.reason,
+      status: request.status,
+      reviewed_by: request.reviewed_by,
+      reviewed_at: request.reviewed_at,
+      created_at: request.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreateScopeRecoveryRequest {
+  pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiAdminUpdateUserRequest {
+  pub is_staff: Option<bool>,
+  pub is_blocked: Option<bool>,
+  pub scope_limit: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiAdminUpdateScopeRequest {
+  pub package_limit: Option<i32>,
+  pub new_package_per_week_limit: Option<i32>,
+  pub publish_attempts_per_week_limit: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ApiUpdateScopeRequest {
+  #[serde(rename = "ghActionsVerifyActor")]
+  GhActionsVerifyActor(bool),
+  #[serde(rename = "requirePublishingFromCI")]
+  RequirePublishingFromCI(bool),
+  #[serde(rename = "validationLevel")]
+  ValidationLevel(Option<ApiValidationLevel>),
+  #[serde(rename = "outdatedDependenciesEmail")]
+  OutdatedDependenciesEmail(bool),
+  #[serde(rename = "downloadAnomalyEmail")]
+  DownloadAnomalyEmail(bool),
+  #[serde(rename = "readme")]
+  Readme(Option<String>),
+  #[serde(rename = "pinnedPackages")]
+  PinnedPackages(Vec<PackageName>),
+  #[serde(rename = "docsCoveragePolicy")]
+  DocsCoveragePolicy(ApiDocsCoveragePolicy),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiStats {
+  pub newest: Vec<ApiPackage>,
+  pub updated: Vec<ApiPackageVersion>,
+  pub featured: Vec<ApiPackage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiMetrics {
+  pub packages: usize,
+  pub packages_1d: usize,
+  pub packages_7d: usize,
   pub packages_30d: usize,
 
   pub users: usize,
@@ -801,6 +1693,22 @@ pub struct ApiAuthorizationExchangeResponse {
   pub user: ApiUser,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiOidcExchangeRequest {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+  pub tarball_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiOidcExchangeResponse {
+  pub token: String,
+  pub expires_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiAuthorizationExchangeRequest {
@@ -841,21 +1749,238 @@ impl From<DependencyKind> for ApiDependencyKind {
   }
 }
 
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiDependencyType {
+  Regular,
+  Peer,
+  Optional,
+}
+
+impl From<DependencyType> for ApiDependencyType {
+  fn from(value: DependencyType) -> Self {
+    match value {
+      DependencyType::Regular => ApiDependencyType::Regular,
+      DependencyType::Peer => ApiDependencyType::Peer,
+      DependencyType::Optional => ApiDependencyType::Optional,
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct ApiDependency {
   pub kind: ApiDependencyKind,
+  #[serde(rename = "type")]
+  pub dependency_type: ApiDependencyType,
   pub name: String,
   pub constraint: String,
   pub path: String,
+  /// Whether the dependency is a JSR package that has been archived.
+  #[serde(rename = "isArchived")]
+  pub is_archived: bool,
 }
 
-impl From<PackageVersionDependency> for ApiDependency {
-  fn from(dep: PackageVersionDependency) -> Self {
+impl From<(PackageVersionDependency, bool)> for ApiDependency {
+  fn from((dep, is_archived): (PackageVersionDependency, bool)) -> Self {
     Self {
       kind: dep.dependency_kind.into(),
+      dependency_type: dep.dependency_type.into(),
       name: dep.dependency_name,
       constraint: dep.dependency_constraint,
       path: dep.dependency_path,
+      is_archived,
+    }
+  }
+}
+
+/// A dependency of a package version, annotated with how far behind the
+/// latest version of the dependency its constraint is. The latest version is
+/// only known for JSR dependencies.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDependencyFreshness {
+  pub kind: ApiDependencyKind,
+  #[serde(rename = "type")]
+  pub dependency_type: ApiDependencyType,
+  pub name: String,
+  pub constraint: String,
+  pub path: String,
+  /// The latest stable, non-yanked version of the dependency.
+  pub latest_version: Option<Version>,
+  /// Whether the latest version satisfies the constraint.
+  pub matches_latest: Option<bool>,
+  /// The number of major versions between the highest version that satisfies
+  /// the constraint and the latest version.
+  pub majors_behind: Option<u64>,
+  /// Whether the highest version that satisfies the constraint has been
+  /// yanked.
+  pub yanked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiLicenseClassification {
+  Permissive,
+  WeakCopyleft,
+  Copyleft,
+  Unknown,
+}
+
+impl From<LicenseClassification> for ApiLicenseClassification {
+  fn from(value: LicenseClassification) -> Self {
+    match value {
+      LicenseClassification::Permissive => Self::Permissive,
+      LicenseClassification::WeakCopyleft => Self::WeakCopyleft,
+      LicenseClassification::Copyleft => Self::Copyleft,
+      LicenseClassification::Unknown => Self::Unknown,
+    }
+  }
+}
+
+/// The licenses of a package version and of all JSR packages it transitively
+/// depends on.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiLicenseReport {
+  /// The package version itself comes first, followed by its dependencies in
+  /// breadth-first order.
+  pub packages: Vec<ApiLicenseReportPackage>,
+  /// Dependencies whose license is not known: npm dependencies, and JSR
+  /// dependencies that no published version satisfies.
+  pub unresolved: Vec<ApiLicenseReportUnresolved>,
+  /// Whether the dependency tree was too large to be resolved completely.
+  pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiLicenseReportPackage {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+  pub license: Option<String>,
+  pub classification: ApiLicenseClassification,
+  /// Whether the license is copyleft, or could not be detected.
+  pub flagged: bool,
+  /// The number of dependency edges between the package version the report
+  /// is for and this package.
+  pub depth: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiLicenseReportUnresolved {
+  pub kind: ApiDependencyKind,
+  pub name: String,
+  pub constraint: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiEntrypoint {
+  pub name: String,
+  pub module_count: u32,
+  pub total_bytes: u64,
+  pub external_dependency_count: u32,
+  pub top_level_await: bool,
+}
+
+impl From<(String, EntrypointStats)> for ApiEntrypoint {
+  fn from((name, stats): (String, EntrypointStats)) -> Self {
+    Self {
+      name,
+      module_count: stats.module_count,
+      total_bytes: stats.total_bytes,
+      external_dependency_count: stats.external_dependency_count,
+      top_level_await: stats.top_level_await,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTypeScriptCompatibilityStatus {
+  Compatible,
+  Incompatible,
+  /// The declaration output of the package version could not be checked,
+  /// because it has slow types or was published before checks were added.
+  Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTypeScriptCompatibility {
+  pub version: TypeScriptVersion,
+  pub status: ApiTypeScriptCompatibilityStatus,
+  pub unsupported_features: Vec<TypeScriptFeature>,
+}
+
+impl ApiTypeScriptCompatibility {
+  pub fn new(
+    version: TypeScriptVersion,
+    features: Option<&[TypeScriptFeature]>,
+  ) -> Self {
+    let Some(features) = features else {
+      return Self {
+        version,
+        status: ApiTypeScriptCompatibilityStatus::Unknown,
+        unsupported_features: vec![],
+      };
+    };
+    let unsupported_features = features
+      .iter()
+      .filter(|feature| feature.min_version() > version)
+      .copied()
+      .collect::<Vec<_>>();
+    Self {
+      version,
+      status: if unsupported_features.is_empty() {
+        ApiTypeScriptCompatibilityStatus::Compatible
+      } else {
+        ApiTypeScriptCompatibilityStatus::Incompatible
+      },
+      unsupported_features,
+    }
+  }
+}
+
+/// The checksums of the files of a package version, in a shape that is
+/// convenient for lockfiles.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPackageVersionIntegrity {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+  /// The `sha256-<hex>` checksum of every file, keyed by path. Files published
+  /// before checksums were recorded have a `null` checksum.
+  pub files: BTreeMap<String, Option<String>>,
+  /// The `sha256-<hex>` checksum of `files` serialized as JSON, with the keys
+  /// in sorted order and no whitespace.
+  pub manifest_hash: String,
+}
+
+impl ApiPackageVersionIntegrity {
+  pub fn new(
+    scope: ScopeName,
+    package: PackageName,
+    version: Version,
+    files: Vec<PackageFile>,
+  ) -> Self {
+    use sha2::Digest;
+
+    let files = files
+      .into_iter()
+      .map(|file| (file.path.to_string(), file.checksum))
+      .collect::<BTreeMap<_, _>>();
+    let manifest = serde_json::to_vec(&files).unwrap();
+    let manifest_hash = format!("sha256-{:x}", sha2::Sha256::digest(&manifest));
+    Self {
+      scope,
+      package,
+      version,
+      files,
+      manifest_hash,
     }
   }
 }
@@ -899,42 +2024,109 @@ impl From<DownloadDataPoint> for ApiDownloadDataPoint {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum ApiDownloadKind {
-  JsrMeta,
-  NpmTarball,
+#[serde(rename_all = "camelCase")]
+pub struct ApiDownloadAnomaly {
+  pub day: DateTime<Utc>,
+  pub kind: DownloadAnomalyKind,
+  pub count: u64,
+  pub mean: f64,
+  pub z_score: f64,
 }
 
-impl From<DownloadKind> for ApiDownloadKind {
-  fn from(value: DownloadKind) -> Self {
-    match value {
-      DownloadKind::JsrMeta => ApiDownloadKind::JsrMeta,
-      DownloadKind::NpmTgz => ApiDownloadKind::NpmTarball,
+impl From<DownloadAnomaly> for ApiDownloadAnomaly {
+  fn from(value: DownloadAnomaly) -> Self {
+    Self {
+      day: value.day,
+      kind: value.kind,
+      count: value.count as u64,
+      mean: value.mean,
+      z_score: value.z_score,
     }
   }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ApiList<T> {
-  pub items: Vec<T>,
-  pub total: usize,
+pub struct ApiPackageLinkCheck {
+  pub version: Version,
+  pub checked_link_count: u32,
+  pub checked_at: DateTime<Utc>,
+  pub broken_links: Vec<ApiBrokenLink>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum ApiTokenType {
-  Web,
-  Device,
-  Personal,
+impl From<(PackageLinkCheck, Vec<BrokenLink>)> for ApiPackageLinkCheck {
+  fn from((check, broken_links): (PackageLinkCheck, Vec<BrokenLink>)) -> Self {
+    Self {
+      version: check.version,
+      checked_link_count: check.checked_link_count as u32,
+      checked_at: check.checked_at,
+      broken_links: broken_links.into_iter().map(Into::into).collect(),
+    }
+  }
 }
 
-impl From<TokenType> for ApiTokenType {
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiBrokenLink {
+  pub url: String,
+  pub source: String,
+  pub status: Option<u16>,
+  pub error: Option<String>,
+}
+
+impl From<BrokenLink> for ApiBrokenLink {
+  fn from(value: BrokenLink) -> Self {
+    Self {
+      url: value.url,
+      source: value.source,
+      status: value.status.map(|status| status as u16),
+      error: value.error,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiDownloadKind {
+  JsrMeta,
+  NpmTarball,
+  /// Requests from web browsers, which are not counted as downloads.
+  Web,
+}
+
+impl From<DownloadKind> for ApiDownloadKind {
+  fn from(value: DownloadKind) -> Self {
+    match value {
+      DownloadKind::JsrMeta => ApiDownloadKind::JsrMeta,
+      DownloadKind::NpmTgz => ApiDownloadKind::NpmTarball,
+      DownloadKind::Web => ApiDownloadKind::Web,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiList<T> {
+  pub items: Vec<T>,
+  pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenType {
+  Web,
+  Device,
+  Personal,
+  Scope,
+}
+
+impl From<TokenType> for ApiTokenType {
   fn from(value: TokenType) -> Self {
     match value {
       TokenType::Web => ApiTokenType::Web,
       TokenType::Device => ApiTokenType::Device,
       TokenType::Personal => ApiTokenType::Personal,
+      TokenType::Scope => ApiTokenType::Scope,
     }
   }
 }
@@ -975,6 +2167,15 @@ pub struct ApiCreateTokenRequest {
   pub permissions: Option<Permissions>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreateScopeTokenRequest {
+  pub description: String,
+  pub expires_at: Option<DateTime<Utc>>,
+  /// The packages of the scope that the token can publish.
+  pub packages: Vec<PackageName>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiCreatedToken {
@@ -1002,3 +2203,619 @@ pub struct ApiPackageDownloadsRecentVersion {
   pub version: Version,
   pub downloads: Vec<ApiDownloadDataPoint>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiScopeDownloads {
+  pub total: Vec<ApiDownloadDataPoint>,
+}
+
+/// The total downloads of a package in a period, in the format of the npm
+/// downloads API.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiDownloadsPoint {
+  pub downloads: u64,
+  pub start: NaiveDate,
+  pub end: NaiveDate,
+  pub package: String,
+}
+
+/// The downloads of a package on each day of a period, in the format of the
+/// npm downloads API.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiDownloadsRange {
+  pub downloads: Vec<ApiDownloadsDay>,
+  pub start: NaiveDate,
+  pub end: NaiveDate,
+  pub package: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiDownloadsDay {
+  pub downloads: u64,
+  pub day: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiNpmTarballBuildProgress {
+  pub revision: i32,
+  pub total_versions: i64,
+  pub built_versions: i64,
+  pub claimed_versions: i64,
+  pub missing_versions: i64,
+  pub requested_packages: i64,
+  pub last_built_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ApiRebuildPackageVersionRequest {
+  /// Regenerate the documentation from the published files.
+  pub docs: bool,
+  /// Regenerate and re-sign the version and package metadata files.
+  pub metadata: bool,
+  /// Rebuild the npm tarball of the current revision, and the npm manifest.
+  pub npm_tarball: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum ApiRebuildStatus {
+  Skipped,
+  Rebuilt,
+  /// The rebuild was queued and happens in the background.
+  Queued,
+  Failed {
+    message: String,
+  },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRebuildPackageVersionResult {
+  pub docs: ApiRebuildStatus,
+  pub metadata: ApiRebuildStatus,
+  pub npm_tarball: ApiRebuildStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDeletePackageVersionRequest {
+  /// Why the version is deleted, for example a takedown notice.
+  pub reason: String,
+  /// Delete the version even if it is the latest version of the package.
+  #[serde(default)]
+  pub force: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDeletedPackageVersion {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+  pub publishing_task_id: Uuid,
+  pub reason: String,
+  pub deleted_by: Option<Uuid>,
+  pub restorable_until: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<DeletedPackageVersion> for ApiDeletedPackageVersion {
+  fn from(deleted: DeletedPackageVersion) -> Self {
+    Self {
+      scope: deleted.scope,
+      package: deleted.name,
+      version: deleted.version,
+      publishing_task_id: deleted.publishing_task_id,
+      reason: deleted.reason,
+      deleted_by: deleted.deleted_by,
+      restorable_until: deleted.restorable_until,
+      created_at: deleted.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDownloadIngestionCheckpoint {
+  pub window_start: DateTime<Utc>,
+  pub window_end: DateTime<Utc>,
+  pub complete: bool,
+  pub downloads: i64,
+  pub replayed_by: Option<Uuid>,
+  pub ingested_at: DateTime<Utc>,
+}
+
+impl From<DownloadIngestionCheckpoint> for ApiDownloadIngestionCheckpoint {
+  fn from(checkpoint: DownloadIngestionCheckpoint) -> Self {
+    Self {
+      window_start: checkpoint.window_start,
+      window_end: checkpoint.window_end,
+      complete: checkpoint.window_end - checkpoint.window_start
+        >= crate::tasks::DOWNLOAD_INGESTION_WINDOW,
+      downloads: checkpoint.downloads,
+      replayed_by: checkpoint.replayed_by,
+      ingested_at: checkpoint.ingested_at,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiReplayDownloadIngestionRequest {
+  /// Rounded down to the start of a window.
+  pub start: DateTime<Utc>,
+  /// Rounded up to the end of a window, but not past the current time.
+  pub end: DateTime<Utc>,
+}
+
+impl From<NpmTarballBuildProgress> for ApiNpmTarballBuildProgress {
+  fn from(progress: NpmTarballBuildProgress) -> Self {
+    Self {
+      revision: progress.revision,
+      total_versions: progress.total_versions,
+      built_versions: progress.built_versions,
+      claimed_versions: progress.claimed_versions,
+      missing_versions: progress.total_versions - progress.built_versions,
+      requested_packages: progress.requested_packages,
+      last_built_at: progress.last_built_at,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDatabasePoolStats {
+  pub limit: u32,
+  pub in_use: u32,
+  pub size: u32,
+  pub idle: u32,
+  pub wait_p50_ms: f64,
+  pub wait_p95_ms: f64,
+  pub wait_p99_ms: f64,
+}
+
+impl From<PoolStats> for ApiDatabasePoolStats {
+  fn from(stats: PoolStats) -> Self {
+    Self {
+      limit: stats.limit,
+      in_use: stats.in_use,
+      size: stats.size,
+      idle: stats.idle,
+      wait_p50_ms: stats.wait_p50.as_secs_f64() * 1000.0,
+      wait_p95_ms: stats.wait_p95.as_secs_f64() * 1000.0,
+      wait_p99_ms: stats.wait_p99.as_secs_f64() * 1000.0,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiAuditFindingSeverity {
+  Warning,
+  Error,
+}
+
+impl From<AuditFindingSeverity> for ApiAuditFindingSeverity {
+  fn from(value: AuditFindingSeverity) -> Self {
+    match value {
+      AuditFindingSeverity::Warning => Self::Warning,
+      AuditFindingSeverity::Error => Self::Error,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiAuditFinding {
+  pub kind: String,
+  pub severity: ApiAuditFindingSeverity,
+  pub subject: String,
+  pub message: String,
+}
+
+impl From<AuditFinding> for ApiAuditFinding {
+  fn from(value: AuditFinding) -> Self {
+    Self {
+      kind: value.kind,
+      severity: value.severity.into(),
+      subject: value.subject,
+      message: value.message,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiConsistencyAudit {
+  pub id: Uuid,
+  pub created_at: DateTime<Utc>,
+  pub findings: Vec<ApiAuditFinding>,
+}
+
+impl From<(ConsistencyAudit, Vec<AuditFinding>)> for ApiConsistencyAudit {
+  fn from((audit, findings): (ConsistencyAudit, Vec<AuditFinding>)) -> Self {
+    Self {
+      id: audit.id,
+      created_at: audit.created_at,
+      findings: findings.into_iter().map(Into::into).collect(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiUserExport {
+  pub id: Uuid,
+  pub status: UserExportStatus,
+  /// When the download link that was emailed to the user expires.
+  pub expires_at: Option<DateTime<Utc>>,
+  pub updated_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<UserExport> for ApiUserExport {
+  fn from(value: UserExport) -> Self {
+    Self {
+      id: value.id,
+      status: value.status,
+      expires_at: value.expires_at,
+      updated_at: value.updated_at,
+      created_at: value.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiIamSimulateRequest {
+  pub principal: ApiIamSimulatePrincipal,
+  pub action: ApiIamAction,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ApiIamSimulatePrincipal {
+  /// A user acting through the web interface.
+  #[serde(rename_all = "camelCase")]
+  User {
+    user_id: Uuid,
+    #[serde(default)]
+    sudo: bool,
+  },
+  /// A user acting through one of their tokens, with the permissions of that
+  /// token.
+  #[serde(rename_all = "camelCase")]
+  Token {
+    token_id: Uuid,
+    #[serde(default)]
+    sudo: bool,
+  },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ApiIamAction {
+  Publish {
+    scope: ScopeName,
+    package: PackageName,
+    version: Version,
+  },
+  ScopeWrite {
+    scope: ScopeName,
+  },
+  ScopeAdmin {
+    scope: ScopeName,
+  },
+}
+
+impl From<ApiIamAction> for IamAction {
+  fn from(value: ApiIamAction) -> Self {
+    match value {
+      ApiIamAction::Publish {
+        scope,
+        package,
+        version,
+      } => IamAction::Publish {
+        scope,
+        package,
+        version,
+      },
+      ApiIamAction::ScopeWrite { scope } => IamAction::ScopeWrite { scope },
+      ApiIamAction::ScopeAdmin { scope } => IamAction::ScopeAdmin { scope },
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiIamSimulateResponse {
+  pub allowed: bool,
+  /// The rule that allowed the action.
+  pub rule: Option<IamRule>,
+  /// The token permission that allowed the action, if the token has
+  /// restricted permissions.
+  pub permission: Option<Permission>,
+  /// The error code the action would be rejected with.
+  pub code: Option<String>,
+  pub message: Option<String>,
+}
+
+impl From<IamDecision> for ApiIamSimulateResponse {
+  fn from(value: IamDecision) -> Self {
+    Self {
+      allowed: true,
+      rule: Some(value.rule),
+      permission: value.permission,
+      code: None,
+      message: None,
+    }
+  }
+}
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ApiComponentStatus {
+  Operational,
+  Degraded,
+  MajorOutage,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiStatusComponent {
+  pub name: String,
+  pub status: ApiComponentStatus,
+  /// The number of items waiting to be processed by the component.
+  pub backlog: i64,
+  /// How long the oldest item in the backlog has been waiting, in seconds.
+  pub lag_seconds: i64,
+}
+
+/// The request quotas, in units per window. `None` means unlimited.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRateLimits {
+  pub window_secs: u64,
+  pub anonymous: Option<u32>,
+  pub api_key: Option<u32>,
+  pub token: Option<u32>,
+  /// README previews rendered per user, counted separately.
+  pub render_readme: Option<u32>,
+  /// Retries of stuck publishes per user, counted separately.
+  pub retry_publish: Option<u32>,
+  pub default_cost: u32,
+  /// The cost of requests that match a rule. The first matching rule applies.
+  pub costs: Vec<ApiRequestCost>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRequestCost {
+  pub method: Option<String>,
+  pub path: String,
+  pub query: Option<String>,
+  pub cost: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRegistryStatus {
+  /// The worst status of all components.
+  pub status: ApiComponentStatus,
+  pub components: Vec<ApiStatusComponent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiApiKey {
+  pub id: Uuid,
+  pub name: String,
+  pub request_count: i64,
+  pub last_used_at: Option<DateTime<Utc>>,
+  pub updated_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for ApiApiKey {
+  fn from(value: ApiKey) -> Self {
+    Self {
+      id: value.id,
+      name: value.name,
+      request_count: value.request_count,
+      last_used_at: value.last_used_at,
+      updated_at: value.updated_at,
+      created_at: value.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreateApiKeyRequest {
+  pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreatedApiKey {
+  pub secret: String,
+  pub api_key: ApiApiKey,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDeprecatedRouteUsage {
+  pub route: String,
+  pub token_id: Option<Uuid>,
+  pub user_id: Option<Uuid>,
+  pub user_agent: String,
+  pub request_count: i64,
+  pub first_used_at: DateTime<Utc>,
+  pub last_used_at: DateTime<Utc>,
+}
+
+impl From<DeprecatedRouteUsage> for ApiDeprecatedRouteUsage {
+  fn from(value: DeprecatedRouteUsage) -> Self {
+    Self {
+      route: value.route,
+      token_id: value.token_id,
+      user_id: value.user_id,
+      user_agent: value.user_agent,
+      request_count: value.request_count,
+      first_used_at: value.first_used_at,
+      last_used_at: value.last_used_at,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPackageCollection {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub name: String,
+  pub description: String,
+  pub packages: Vec<ApiPackageCollectionItem>,
+  pub updated_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPackageCollectionItem {
+  pub scope: ScopeName,
+  pub name: PackageName,
+}
+
+impl From<PackageCollection> for ApiPackageCollection {
+  fn from(value: PackageCollection) -> Self {
+    Self {
+      id: value.id,
+      user_id: value.user_id,
+      name: value.name,
+      description: value.description,
+      packages: value
+        .packages
+        .into_iter()
+        .map(|package| ApiPackageCollectionItem {
+          scope: package.scope,
+          name: package.package,
+        })
+        .collect(),
+      updated_at: value.updated_at,
+      created_at: value.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCreatePackageCollectionRequest {
+  pub name: String,
+  #[serde(default)]
+  pub description: String,
+  /// The packages in the collection, like `@std/fs`, in order.
+  pub packages: Vec<ScopedPackageName>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiUpdatePackageCollectionRequest {
+  pub name: Option<String>,
+  pub description: Option<String>,
+  /// Replaces all packages of the collection.
+  pub packages: Option<Vec<ScopedPackageName>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRenderReadmeRequest {
+  pub markdown: String,
+  /// The package the README belongs to. Relative links are resolved against
+  /// the files of this package version, or against the linked GitHub
+  /// repository if the package has one.
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRenderedReadme {
+  pub html: String,
+  pub css: Cow<'static, str>,
+  pub comrak_css: Cow<'static, str>,
+  pub script: Cow<'static, str>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiResolvedTypes {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+  /// The path of the module in the package version, like `/mod.ts`.
+  pub path: String,
+  pub url: String,
+  pub media_type: String,
+  pub npm: ApiResolvedNpmModule,
+}
+
+/// Where the module is found in the npm compatibility package.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiResolvedNpmModule {
+  pub name: String,
+  pub path: String,
+  /// The declarations of the module, if they are not in `path` itself.
+  pub types_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiWatchRequest {
+  /// The packages to watch, like `@std/fs`.
+  pub packages: Vec<ScopedPackageName>,
+  /// The cursor returned by the previous watch request. Without a cursor, the
+  /// response returns immediately with a cursor for the current time.
+  #[serde(default)]
+  pub cursor: Option<DateTime<Utc>>,
+  /// How long to wait for a new version before responding without one.
+  #[serde(default)]
+  pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiWatchResponse {
+  /// Versions of the watched packages published after the cursor, oldest
+  /// first.
+  pub versions: Vec<ApiWatchedVersion>,
+  /// The cursor to pass to the next watch request.
+  pub cursor: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiWatchedVersion {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<PublishEvent> for ApiWatchedVersion {
+  fn from(event: PublishEvent) -> Self {
+    Self {
+      scope: event.scope,
+      package: event.package,
+      version: event.version,
+      created_at: event.created_at,
+    }
+  }
+}
@@ -13,6 +13,7 @@ use crate::util::ApiResult;
 use crate::util::RequestIdExt;
 
 use super::ApiError;
+use super::ApiPackageCollection;
 use super::ApiScope;
 use super::ApiUser;
 
@@ -20,6 +21,7 @@ pub fn users_router() -> Router<Body, ApiError> {
   Router::builder()
     .get("/:id", util::json(get_handler))
     .get("/:id/scopes", util::json(get_scopes_handler))
+    .get("/:id/collections", util::json(get_collections_handler))
     .build()
     .unwrap()
 }
@@ -54,3 +56,30 @@ pub async fn get_scopes_handler(
 
   Ok(scopes.into_iter().map(ApiScope::from).collect())
 }
+
+#[instrument(
+  name = "GET /api/users/:id/collections",
+  skip(req),
+  err,
+  fields(id)
+)]
+pub async fn get_collections_handler(
+  req: Request<Body>,
+) -> ApiResult<Vec<ApiPackageCollection>> {
+  let id = req.param_uuid("id")?;
+  Span::current().record("id", field::display(id));
+
+  let db = req.data::<Database>().unwrap();
+  db.get_user_public(id)
+    .await?
+    .ok_or(ApiError::UserNotFound)?;
+
+  let collections = db.list_package_collections_by_user(id).await?;
+
+  Ok(
+    collections
+      .into_iter()
+      .map(ApiPackageCollection::from)
+      .collect(),
+  )
+}
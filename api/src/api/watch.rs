@@ -0,0 +1,181 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::Utc;
+use hyper::Body;
+use hyper::Request;
+use routerify::prelude::RequestExt;
+use tracing::instrument;
+use tracing::Span;
+
+use crate::db::Database;
+use crate::util::decode_json;
+use crate::util::ApiResult;
+
+use super::ApiError;
+use super::ApiWatchRequest;
+use super::ApiWatchResponse;
+
+/// The most packages a single watch request can watch.
+const MAX_WATCHED_PACKAGES: usize = 100;
+/// The most versions returned by a single watch request. The rest are returned
+/// by the next request, with the returned cursor.
+const MAX_WATCH_VERSIONS: i64 = 100;
+const DEFAULT_WATCH_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_WATCH_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often the database is checked for new versions while a request waits.
+/// Publishes can be processed by any API instance, so there is no in-process
+/// event to wait for.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Waits until one of the watched packages has a version that was published
+/// after the cursor, or until the timeout passes, so that tools can follow a
+/// set of packages without polling every package separately.
+#[instrument(name = "POST /api/watch", skip(req), err, fields(packages))]
+pub async fn watch_handler(
+  mut req: Request<Body>,
+) -> ApiResult<ApiWatchResponse> {
+  let ApiWatchRequest {
+    packages,
+    cursor,
+    timeout_secs,
+  } = decode_json(&mut req).await?;
+  Span::current().record("packages", packages.len());
+
+  if packages.is_empty() || packages.len() > MAX_WATCHED_PACKAGES {
+    return Err(ApiError::MalformedRequest {
+      msg: format!(
+        "between 1 and {MAX_WATCHED_PACKAGES} packages must be watched"
+      )
+      .into(),
+    });
+  }
+
+  let Some(cursor) = cursor else {
+    return Ok(ApiWatchResponse {
+      versions: vec![],
+      cursor: Utc::now(),
+    });
+  };
+
+  let timeout = timeout_secs
+    .map(Duration::from_secs)
+    .unwrap_or(DEFAULT_WATCH_TIMEOUT)
+    .min(MAX_WATCH_TIMEOUT);
+  let deadline = Instant::now() + timeout;
+
+  let db = req.data::<Database>().unwrap();
+  loop {
+    let events = db
+      .list_publish_events_for_packages(&packages, cursor, MAX_WATCH_VERSIONS)
+      .await?;
+    if let Some(last) = events.last() {
+      return Ok(ApiWatchResponse {
+        cursor: last.created_at,
+        versions: events.into_iter().map(Into::into).collect(),
+      });
+    }
+
+    let now = Instant::now();
+    if now >= deadline {
+      return Ok(ApiWatchResponse {
+        versions: vec![],
+        cursor,
+      });
+    }
+    tokio::time::sleep(WATCH_POLL_INTERVAL.min(deadline - now)).await;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use hyper::StatusCode;
+  use serde_json::json;
+
+  use crate::api::ApiWatchResponse;
+  use crate::db::PublishingTaskStatus;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  #[tokio::test]
+  async fn watch() {
+    let mut t = TestSetup::new().await;
+
+    // without a cursor, the watch starts now
+    let start = t
+      .http()
+      .post("/api/watch")
+      .body_json(json!({ "packages": ["@scope/foo", "@scope/bar"] }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiWatchResponse>()
+      .await;
+    assert!(start.versions.is_empty());
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let resp = t
+      .http()
+      .post("/api/watch")
+      .body_json(json!({
+        "packages": ["@scope/foo", "@scope/bar"],
+        "cursor": start.cursor,
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiWatchResponse>()
+      .await;
+    assert_eq!(resp.versions.len(), 1);
+    assert_eq!(resp.versions[0].package.to_string(), "foo");
+    assert_eq!(resp.versions[0].version.to_string(), "1.2.3");
+    assert_eq!(resp.cursor, resp.versions[0].created_at);
+
+    // nothing new was published after the returned cursor
+    let next = t
+      .http()
+      .post("/api/watch")
+      .body_json(json!({
+        "packages": ["@scope/foo"],
+        "cursor": resp.cursor,
+        "timeoutSecs": 0,
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiWatchResponse>()
+      .await;
+    assert!(next.versions.is_empty());
+    assert_eq!(next.cursor, resp.cursor);
+
+    // other packages are not reported
+    let other = t
+      .http()
+      .post("/api/watch")
+      .body_json(json!({
+        "packages": ["@scope/bar"],
+        "cursor": start.cursor,
+        "timeoutSecs": 0,
+      }))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiWatchResponse>()
+      .await;
+    assert!(other.versions.is_empty());
+
+    t.http()
+      .post("/api/watch")
+      .body_json(json!({ "packages": [] }))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
+  }
+}
@@ -366,7 +366,7 @@ mod tests {
   use serde_json::json;
 
   //use super::*;
-  use crate::util::test::{ApiResultExt, TestSetup};
+  use crate::testing::{ApiResultExt, TestSetup};
 
   #[tokio::test]
   async fn user_admin_api() {
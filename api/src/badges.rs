@@ -0,0 +1,402 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+//! SVG badges for the latest version, the downloads and the score of a
+//! package, that can be embedded in READMEs without going through a
+//! third-party badge service.
+
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use routerify::ext::RequestExt;
+use routerify_query::RequestQueryExt;
+use tracing::field;
+use tracing::instrument;
+use tracing::Span;
+
+use crate::api::ApiError;
+use crate::api::ApiPackage;
+use crate::db::Database;
+use crate::db::DownloadKind;
+use crate::util::RequestIdExt;
+
+const DEFAULT_LABEL_COLOR: &str = "#f7df1e";
+const DEFAULT_COLOR: &str = "#083344";
+const NOT_FOUND_COLOR: &str = "#9f9f9f";
+
+// Badges are embedded in READMEs that are viewed far more often than packages
+// change, so they are cached at the edge for longer than in browsers.
+const CACHE_CONTROL: &str =
+  "public, max-age=300, s-maxage=3600, stale-while-revalidate=86400";
+const NOT_FOUND_CACHE_CONTROL: &str = "public, max-age=60";
+
+const LOGO_WIDTH: usize = 26;
+const PADDING: usize = 6;
+
+struct Badge<'a> {
+  label: &'a str,
+  message: &'a str,
+  label_color: &'a str,
+  color: &'a str,
+}
+
+#[instrument(
+  name = "GET /badges/:scope/:package/version.svg",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn version_badge_handler(
+  req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let db = req.data::<Database>().unwrap();
+  let message = db
+    .get_package(&scope, &package)
+    .await?
+    .map(|(package, _, _)| package.latest_version);
+  let message = match message {
+    Some(Some(version)) => Some(version),
+    Some(None) => Some("unpublished".to_string()),
+    None => None,
+  };
+
+  Ok(badge_response(&req, "", message.as_deref()))
+}
+
+#[instrument(
+  name = "GET /badges/:scope/:package/downloads.svg",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn downloads_badge_handler(
+  req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let db = req.data::<Database>().unwrap();
+  let message = if db.get_package(&scope, &package).await?.is_some() {
+    let end = chrono::Utc::now();
+    let start = end - chrono::Duration::days(30);
+    let count = db
+      .get_package_downloads_24h(&scope, &package, start, end)
+      .await?
+      .into_iter()
+      .filter(|point| point.kind != DownloadKind::Web)
+      .map(|point| point.count)
+      .sum::<i64>();
+    Some(format!("{}/month", format_download_count(count)))
+  } else {
+    None
+  };
+
+  Ok(badge_response(&req, "downloads", message.as_deref()))
+}
+
+#[instrument(
+  name = "GET /badges/:scope/:package/score.svg",
+  skip(req),
+  err,
+  fields(scope, package)
+)]
+pub async fn score_badge_handler(
+  req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+  let scope = req.param_scope()?;
+  let package = req.param_package()?;
+  Span::current().record("scope", field::display(&scope));
+  Span::current().record("package", field::display(&package));
+
+  let db = req.data::<Database>().unwrap();
+  let message = db.get_package(&scope, &package).await?.map(|package| {
+    match ApiPackage::from(package).score {
+      Some(score) => format!("{score}%"),
+      None => "unpublished".to_string(),
+    }
+  });
+
+  Ok(badge_response(&req, "score", message.as_deref()))
+}
+
+/// Renders the badge for a message, or a grey "not found" badge if the
+/// package does not exist. The label and the colors can be changed with the
+/// `label`, `labelColor` and `color` query parameters.
+fn badge_response(
+  req: &Request<Body>,
+  default_label: &str,
+  message: Option<&str>,
+) -> Response<Body> {
+  let label = req.query("label").map(String::as_str);
+  let label_color = req.query("labelColor").and_then(|c| parse_color(c));
+  let color = req.query("color").and_then(|c| parse_color(c));
+
+  let svg = render_badge(&Badge {
+    label: label.unwrap_or(default_label),
+    message: message.unwrap_or("not found"),
+    label_color: label_color.as_deref().unwrap_or(DEFAULT_LABEL_COLOR),
+    color: match message {
+      Some(_) => color.as_deref().unwrap_or(DEFAULT_COLOR),
+      None => NOT_FOUND_COLOR,
+    },
+  });
+
+  Response::builder()
+    .header("Content-Type", "image/svg+xml; charset=utf-8")
+    .header(
+      "Cache-Control",
+      if message.is_some() {
+        CACHE_CONTROL
+      } else {
+        NOT_FOUND_CACHE_CONTROL
+      },
+    )
+    .header("Access-Control-Allow-Origin", "*")
+    .body(Body::from(svg))
+    .unwrap()
+}
+
+/// Only plain hex and `rgb(r,g,b)` colors are accepted from query parameters,
+/// so that they can be interpolated into the SVG safely.
+fn parse_color(value: &str) -> Option<String> {
+  let hex = value.strip_prefix('#').unwrap_or(value);
+  if matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+  {
+    return Some(format!("#{hex}"));
+  }
+  let components = value.strip_prefix("rgb(")?.strip_suffix(')')?;
+  let components = components.split(',').collect::<Vec<_>>();
+  let valid = components.len() == 3
+    && components.iter().all(|c| {
+      (1..=3).contains(&c.len()) && c.chars().all(|c| c.is_ascii_digit())
+    });
+  valid.then(|| value.to_string())
+}
+
+fn rgb(color: &str) -> (u32, u32, u32) {
+  if let Some(components) = color
+    .strip_prefix("rgb(")
+    .and_then(|c| c.strip_suffix(')'))
+  {
+    let mut components =
+      components.split(',').map(|c| c.parse::<u32>().unwrap_or(0));
+    let mut next = || components.next().unwrap_or(0);
+    return (next(), next(), next());
+  }
+  let hex = color.trim_start_matches('#');
+  let hex = if hex.len() == 3 {
+    hex.chars().flat_map(|c| [c, c]).collect::<String>()
+  } else {
+    hex.to_string()
+  };
+  let component =
+    |i: usize| u32::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+  (component(0), component(2), component(4))
+}
+
+/// Whether a color is light enough that text on it should be dark.
+fn is_light(color: &str) -> bool {
+  let (r, g, b) = rgb(color);
+  (r * 299 + g * 587 + b * 114) / 1000 > 150
+}
+
+/// Approximates the width of text rendered in Verdana at 11px, which badges
+/// are rendered with.
+fn text_width(text: &str) -> usize {
+  text
+    .chars()
+    .map(|c| match c {
+      'f' | 'i' | 'j' | 'l' | 'r' | 't' | '.' | ',' | ':' | ';' | '!' | '|'
+      | '\'' | '(' | ')' | ' ' => 4,
+      'm' | 'w' | 'M' | 'W' | '%' | '@' => 10,
+      _ => 7,
+    })
+    .sum()
+}
+
+fn escape_xml(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+/// Renders a flat badge with the JSR logo.
+fn render_badge(badge: &Badge) -> String {
+  let label = escape_xml(badge.label);
+  let message = escape_xml(badge.message);
+
+  let label_text_width = text_width(badge.label);
+  let label_width = PADDING
+    + LOGO_WIDTH
+    + if label_text_width > 0 {
+      4 + label_text_width
+    } else {
+      0
+    }
+    + PADDING;
+  let message_width = PADDING + text_width(badge.message) + PADDING;
+  let width = label_width + message_width;
+  let title = if badge.label.is_empty() {
+    format!("jsr: {message}")
+  } else {
+    format!("{label}: {message}")
+  };
+
+  let label_text = if badge.label.is_empty() {
+    String::new()
+  } else {
+    format!(
+      r#"<text x="{}" y="14" fill="{}">{label}</text>"#,
+      PADDING + LOGO_WIDTH + 4,
+      text_color(badge.label_color),
+    )
+  };
+
+  format!(
+    r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{title}">
+  <title>{title}</title>
+  <clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="{label_color}"/>
+    <rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>
+  </g>
+  <svg x="{PADDING}" y="3" width="{LOGO_WIDTH}" height="14" viewBox="0 0 13 7">
+    <path fill="#083344" d="M0 6h4v1h7V5h2V1H9V0H2v2H0"/>
+    <path fill="#f7df1e" d="M4 5V1H3v3H2V3H1v2h6V4H5V1h3v1h4v2h-1V3h-1v3H9V2H6v1h2v3H5V5"/>
+  </svg>
+  <g font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    {label_text}
+    <text x="{message_x}" y="14" fill="{message_color}">{message}</text>
+  </g>
+</svg>
+"##,
+    label_color = badge.label_color,
+    color = badge.color,
+    message_x = label_width + PADDING,
+    message_color = text_color(badge.color),
+  )
+}
+
+fn text_color(background: &str) -> &'static str {
+  if is_light(background) {
+    "#083344"
+  } else {
+    "#fff"
+  }
+}
+
+/// Formats a download count the way badges usually do, e.g. `12.3k`.
+fn format_download_count(count: i64) -> String {
+  if count < 1000 {
+    count.to_string()
+  } else if count < 10_000 {
+    format!("{:.1}k", count as f64 / 1000.0)
+  } else if count < 1_000_000 {
+    format!("{:.0}k", count as f64 / 1000.0)
+  } else if count < 10_000_000 {
+    format!("{:.1}M", count as f64 / 1_000_000.0)
+  } else {
+    format!("{:.0}M", count as f64 / 1_000_000.0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use hyper::StatusCode;
+
+  use super::format_download_count;
+  use super::parse_color;
+  use crate::db::DownloadKind;
+  use crate::db::VersionDownloadCount;
+  use crate::ids::PackageName;
+  use crate::ids::ScopeName;
+  use crate::ids::Version;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::testing::TestSetup;
+
+  #[test]
+  fn colors() {
+    assert_eq!(parse_color("f7df1e").as_deref(), Some("#f7df1e"));
+    assert_eq!(parse_color("#fff").as_deref(), Some("#fff"));
+    assert_eq!(parse_color("rgb(8,51,68)").as_deref(), Some("rgb(8,51,68)"));
+    assert_eq!(parse_color("red"), None);
+    assert_eq!(parse_color("#fff\"/><script>"), None);
+    assert_eq!(parse_color("rgb(8,51)"), None);
+  }
+
+  #[test]
+  fn download_counts() {
+    assert_eq!(format_download_count(999), "999");
+    assert_eq!(format_download_count(1234), "1.2k");
+    assert_eq!(format_download_count(12345), "12k");
+    assert_eq!(format_download_count(1_234_567), "1.2M");
+  }
+
+  async fn get_badge(t: &mut TestSetup, path: &str) -> String {
+    let mut resp = t.unauthed_http().get(path).call().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+      resp.headers().get("Content-Type").unwrap(),
+      "image/svg+xml; charset=utf-8"
+    );
+    let bytes = hyper::body::to_bytes(resp.body_mut()).await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+  }
+
+  #[tokio::test]
+  async fn badges() {
+    let mut t = TestSetup::new().await;
+
+    let svg = get_badge(&mut t, "/badges/scope/foo/version.svg").await;
+    assert!(svg.contains(">not found<"));
+
+    process_tarball_setup(&t, create_mock_tarball("ok")).await;
+
+    let svg = get_badge(&mut t, "/badges/scope/foo/version.svg").await;
+    assert!(svg.contains(">1.2.3<"));
+    assert!(svg.contains("aria-label=\"jsr: 1.2.3\""));
+
+    let svg = get_badge(&mut t, "/badges/scope/foo/score.svg").await;
+    assert!(svg.contains(">score<"));
+    assert!(svg.contains("%<"));
+
+    let svg = get_badge(
+      &mut t,
+      "/badges/scope/foo/version.svg?label=jsr&color=ff0000",
+    )
+    .await;
+    assert!(svg.contains(">jsr<"));
+    assert!(svg.contains("fill=\"#ff0000\""));
+
+    let yesterday =
+      chrono::Utc::now().date_naive() - chrono::Duration::days(1);
+    let entry = |kind, count| VersionDownloadCount {
+      scope: ScopeName::try_from("scope").unwrap(),
+      package: PackageName::try_from("foo").unwrap(),
+      version: Version::try_from("1.2.3").unwrap(),
+      time_bucket: yesterday.and_time(chrono::NaiveTime::MIN).and_utc(),
+      kind,
+      count,
+    };
+    t.db()
+      .insert_download_entries(vec![
+        entry(DownloadKind::JsrMeta, 1200),
+        entry(DownloadKind::NpmTgz, 300),
+        entry(DownloadKind::Web, 100),
+      ])
+      .await
+      .unwrap();
+
+    let svg = get_badge(&mut t, "/badges/scope/foo/downloads.svg").await;
+    assert!(svg.contains(">1.5k/month<"));
+    assert!(svg.contains(">downloads<"));
+  }
+}
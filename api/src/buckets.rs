@@ -80,6 +80,7 @@ pub struct Buckets {
   pub modules_bucket: BucketWithQueue,
   pub docs_bucket: BucketWithQueue,
   pub npm_bucket: BucketWithQueue,
+  pub artifacts_bucket: BucketWithQueue,
 }
 
 struct UploadTask {
@@ -0,0 +1,121 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+//! Counters that are aggregated in memory and written to the database
+//! periodically, so that counting a request does not write to the database on
+//! the request path. Counts that were not flushed yet are lost when an
+//! instance shuts down, so these are only suitable for usage statistics.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often the counters of an instance are written to the database.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Once this many keys are buffered, counts for new keys are dropped until the
+/// next flush, so that clients can not grow the buffer without bounds.
+const MAX_BUFFERED_KEYS: usize = 10_000;
+
+pub struct BufferedCounters<K> {
+  counts: Arc<Mutex<HashMap<K, u64>>>,
+}
+
+impl<K> Clone for BufferedCounters<K> {
+  fn clone(&self) -> Self {
+    Self {
+      counts: self.counts.clone(),
+    }
+  }
+}
+
+impl<K> Default for BufferedCounters<K> {
+  fn default() -> Self {
+    Self {
+      counts: Default::default(),
+    }
+  }
+}
+
+impl<K: Eq + Hash> BufferedCounters<K> {
+  pub fn increment(&self, key: K) {
+    let mut counts = self.counts.lock().unwrap();
+    let len = counts.len();
+    match counts.entry(key) {
+      Entry::Occupied(mut entry) => *entry.get_mut() += 1,
+      Entry::Vacant(entry) if len < MAX_BUFFERED_KEYS => {
+        entry.insert(1);
+      }
+      Entry::Vacant(_) => {}
+    }
+  }
+
+  /// Takes the counts buffered since the last call.
+  pub fn take(&self) -> HashMap<K, u64> {
+    std::mem::take(&mut *self.counts.lock().unwrap())
+  }
+
+  /// Adds counts that were taken back to the buffer, so that they are written
+  /// by the next flush if writing them failed.
+  pub fn restore(&self, taken: HashMap<K, u64>) {
+    let mut counts = self.counts.lock().unwrap();
+    for (key, count) in taken {
+      *counts.entry(key).or_default() += count;
+    }
+  }
+}
+
+/// Calls `flush` every [`FLUSH_INTERVAL`] for as long as the process runs.
+pub fn spawn_flush_loop<F, Fut>(mut flush: F)
+where
+  F: FnMut() -> Fut + Send + 'static,
+  Fut: Future<Output = ()> + Send,
+{
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately.
+    interval.tick().await;
+    loop {
+      interval.tick().await;
+      flush().await;
+    }
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::BufferedCounters;
+  use super::MAX_BUFFERED_KEYS;
+
+  #[test]
+  fn buffered_counters() {
+    let counters = BufferedCounters::default();
+    counters.increment("a");
+    counters.increment("a");
+    counters.increment("b");
+
+    let taken = counters.take();
+    assert_eq!(taken.len(), 2);
+    assert_eq!(taken["a"], 2);
+    assert_eq!(taken["b"], 1);
+    assert!(counters.take().is_empty());
+
+    counters.increment("a");
+    counters.restore(taken);
+    let taken = counters.take();
+    assert_eq!(taken["a"], 3);
+    assert_eq!(taken["b"], 1);
+
+    let counters = BufferedCounters::default();
+    for i in 0..MAX_BUFFERED_KEYS + 10 {
+      counters.increment(i);
+    }
+    counters.increment(0);
+    let taken = counters.take();
+    assert_eq!(taken.len(), MAX_BUFFERED_KEYS);
+    assert_eq!(taken[&0], 2);
+  }
+}
@@ -1,7 +1,11 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::str::FromStr;
+use std::sync::Arc;
+
 use chrono::DateTime;
 use chrono::Utc;
 use sqlx::migrate;
+use sqlx::postgres::PgConnectOptions;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::Result;
 use tracing::instrument;
@@ -11,26 +15,67 @@ use crate::api::ApiMetrics;
 use crate::ids::PackageName;
 use crate::ids::PackagePath;
 use crate::ids::ScopeName;
+use crate::ids::ScopedPackageName;
 use crate::ids::Version;
 
 use super::models::*;
+use super::pool::monitor_pool;
+use super::pool::PoolLimiter;
+use super::pool::PoolOptions;
+use super::pool::PoolStats;
+use super::pool::PooledConnection;
+use super::pool::PooledTransaction;
 
 #[derive(Debug, Clone)]
 pub struct Database {
   pool: sqlx::PgPool,
+  limiter: Arc<PoolLimiter>,
 }
 
 impl Database {
   pub async fn connect(
     database_url: &str,
-    pool_size: u32,
+    pool_options: PoolOptions,
+    acquire_timeout: std::time::Duration,
+  ) -> anyhow::Result<Self> {
+    Self::connect_in_schema(database_url, None, pool_options, acquire_timeout)
+      .await
+  }
+
+  /// Connects like `connect`, but if a schema is given, all tables are kept in
+  /// that schema instead of the default one. The schema is created if it does
+  /// not exist yet, and migrated separately. This lets a staging namespace
+  /// share a database server with production.
+  pub async fn connect_in_schema(
+    database_url: &str,
+    schema: Option<&str>,
+    pool_options: PoolOptions,
     acquire_timeout: std::time::Duration,
   ) -> anyhow::Result<Self> {
+    let mut connect_options = PgConnectOptions::from_str(database_url)?;
+    if let Some(schema) = schema {
+      anyhow::ensure!(
+        !schema.is_empty()
+          && schema
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+          && !schema.starts_with(|c: char| c.is_ascii_digit()),
+        "invalid database schema name: {schema}"
+      );
+      // Extensions stay installed in the public schema.
+      connect_options =
+        connect_options.options([("search_path", format!("{schema},public"))]);
+    }
     let pool = PgPoolOptions::new()
-      .max_connections(pool_size)
+      .max_connections(pool_options.max_connections())
       .acquire_timeout(acquire_timeout)
-      .connect(database_url)
+      .connect_with(connect_options)
       .await?;
+    if let Some(schema) = schema {
+      sqlx::query(&format!("CREATE SCHEMA IF NOT EXISTS {schema}"))
+        .execute(&pool)
+        .await?;
+    }
     if std::env::var("DATABASE_DISABLE_MIGRATIONS").is_err() {
       migrate!("./migrations")
         .run(&pool)
@@ -38,7 +83,38 @@ impl Database {
         .expect("database schema error");
     }
     println!("Database ready");
-    Ok(Database { pool })
+    let limiter = Arc::new(PoolLimiter::new(
+      pool_options.initial_size(),
+      acquire_timeout,
+    ));
+    Ok(Database { pool, limiter })
+  }
+
+  /// Starts a background task that periodically reports the connection pool
+  /// stats, and resizes the pool if it is in adaptive mode.
+  pub fn spawn_pool_monitor(
+    &self,
+    pool_options: PoolOptions,
+    interval: std::time::Duration,
+  ) {
+    tokio::spawn(monitor_pool(
+      self.pool.clone(),
+      self.limiter.clone(),
+      pool_options,
+      interval,
+    ));
+  }
+
+  pub fn pool_stats(&self) -> PoolStats {
+    self.limiter.stats(&self.pool)
+  }
+
+  async fn acquire(&self) -> Result<PooledConnection> {
+    self.limiter.acquire(&self.pool).await
+  }
+
+  async fn begin(&self) -> Result<PooledTransaction> {
+    self.limiter.begin(&self.pool).await
   }
 
   #[instrument(name = "Database::get_user", skip(self), err)]
@@ -52,7 +128,7 @@ impl Database {
       WHERE id = $1"#,
       id
     )
-    .fetch_optional(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
@@ -65,7 +141,7 @@ impl Database {
       WHERE id = $1"#,
       id
     )
-    .fetch_optional(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
@@ -83,7 +159,7 @@ impl Database {
       WHERE github_id = $1"#,
       github_id
     )
-    .fetch_optional(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
@@ -94,7 +170,7 @@ impl Database {
     limit: i64,
     maybe_search_query: Option<&str>,
   ) -> Result<(usize, Vec<User>)> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
 
     let maybe_id = maybe_search_query
       .and_then(|search_query| Uuid::parse_str(search_query).ok());
@@ -151,7 +227,7 @@ impl Database {
       new_user.is_blocked,
       new_user.is_staff
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -178,7 +254,7 @@ impl Database {
       new_user.is_blocked,
       new_user.is_staff
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -198,7 +274,7 @@ impl Database {
       is_staff,
       user_id
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -218,7 +294,7 @@ impl Database {
       is_blocked,
       user_id
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -238,7 +314,7 @@ impl Database {
       scope_limit,
       user_id
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -254,7 +330,7 @@ impl Database {
       "#,
       id
     )
-    .fetch_optional(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
@@ -265,11 +341,12 @@ impl Database {
     name: &PackageName,
   ) -> Result<Option<PackageWithGitHubRepoAndMeta>> {
     sqlx::query!(
-      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat "package_runtime_compat: RuntimeCompat", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.updated_at "package_updated_at", packages.created_at "package_created_at",
+      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat "package_runtime_compat: RuntimeCompat", packages.validation_level "package_validation_level: ValidationLevel", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.noindex "package_noindex", packages.keywords "package_keywords", packages.docs_landing_page "package_docs_landing_page", packages.successor_scope "package_successor_scope: ScopeName", packages.successor_name "package_successor_name: PackageName", packages.maintenance_status "package_maintenance_status: MaintenanceStatus", packages.maintenance_status_updated_at "package_maintenance_status_updated_at", packages.unmaintained_suggested_at "package_unmaintained_suggested_at", packages.updated_at "package_updated_at", packages.created_at "package_created_at",
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "package_version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "package_star_count!",
         (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_latest_version",
         (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta",
-        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?"
+        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?", packages.github_repository_subdirectory "github_repository_subdirectory"
       FROM packages
       LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
       WHERE packages.scope = $1 AND packages.name = $2"#,
@@ -283,18 +360,29 @@ impl Database {
         description: r.package_description,
         github_repository_id: r.package_github_repository_id,
         runtime_compat: r.package_runtime_compat,
+        validation_level: r.package_validation_level,
         created_at: r.package_created_at,
         updated_at: r.package_updated_at,
         version_count: r.package_version_count,
+        star_count: r.package_star_count,
         latest_version: r.package_latest_version,
         when_featured: r.package_when_featured,
         is_archived: r.package_is_archived,
+        noindex: r.package_noindex,
+        keywords: r.package_keywords,
+        docs_landing_page: r.package_docs_landing_page,
+        successor_scope: r.package_successor_scope,
+        successor_name: r.package_successor_name,
+        maintenance_status: r.package_maintenance_status,
+        maintenance_status_updated_at: r.package_maintenance_status_updated_at,
+        unmaintained_suggested_at: r.package_unmaintained_suggested_at,
       };
       let github_repository = if r.package_github_repository_id.is_some() {
         Some(GithubRepository {
           id: r.github_repository_id.unwrap(),
           owner: r.github_repository_owner.unwrap(),
           name: r.github_repository_name.unwrap(),
+          subdirectory: r.github_repository_subdirectory,
           created_at: r.github_repository_created_at.unwrap(),
           updated_at: r.github_repository_updated_at.unwrap(),
         })
@@ -306,7 +394,7 @@ impl Database {
 
       (package, github_repository, meta)
     })
-  .fetch_optional(&self.pool)
+  .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
@@ -316,14 +404,15 @@ impl Database {
     scope: &ScopeName,
     name: &PackageName,
   ) -> Result<CreatePackageResult> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
     let res = sqlx::query_as!(
       Package,
       r#"
       INSERT INTO packages (scope, name)
       VALUES ($1, $2)
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", when_featured, is_archived, updated_at, created_at,
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
         (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "latest_version"
       "#,
       scope as _,
@@ -361,17 +450,20 @@ impl Database {
     package_name: &PackageName,
     version: &Version,
     rekor_log_id: &str,
+    source: Option<&PackageVersionSource>,
   ) -> Result<()> {
     sqlx::query!(
       r#"UPDATE package_versions
-      SET rekor_log_id = $1, meta = jsonb_set_lax(meta, '{hasProvenance}', 'true'::jsonb, true)
+      SET rekor_log_id = $1, meta = jsonb_set_lax(meta, '{hasProvenance}', 'true'::jsonb, true), source_repository = $5, source_commit = $6
       WHERE scope = $2 AND name = $3 AND version = $4 AND rekor_log_id IS NULL AND created_at > now() - '2 minute'::interval"#,
       rekor_log_id,
       package_scope as _,
       package_name as _,
-      version as _
+      version as _,
+      source.map(|source| source.repository.as_str()),
+      source.map(|source| source.commit.as_str())
     )
-    .execute(&self.pool)
+    .execute(&mut *self.acquire().await?)
     .await?;
 
     Ok(())
@@ -388,8 +480,9 @@ impl Database {
       r#"UPDATE packages
       SET description = $3
       WHERE scope = $1 AND name = $2
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", when_featured, is_archived, updated_at, created_at,
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
         (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version",
         (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta""#,
       scope as _,
@@ -403,17 +496,27 @@ impl Database {
           description: r.description,
           github_repository_id: r.github_repository_id,
           runtime_compat: r.runtime_compat,
+          validation_level: r.validation_level,
           updated_at: r.updated_at,
           created_at: r.created_at,
           version_count: r.version_count,
+          star_count: r.star_count,
           latest_version: r.latest_version,
           when_featured: r.when_featured,
           is_archived: r.is_archived,
+          noindex: r.noindex,
+          keywords: r.keywords,
+          docs_landing_page: r.docs_landing_page,
+          successor_scope: r.successor_scope,
+          successor_name: r.successor_name,
+          maintenance_status: r.maintenance_status,
+          maintenance_status_updated_at: r.maintenance_status_updated_at,
+          unmaintained_suggested_at: r.unmaintained_suggested_at,
         };
 
         (package, None, r.package_version_meta.unwrap_or_default())
       })
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -424,9 +527,9 @@ impl Database {
     name: &PackageName,
     repo: NewGithubRepository<'_>,
   ) -> Result<(Package, GithubRepository, PackageVersionMeta)> {
-    let mut tx = self.pool.begin().await?;
-    let repo = sqlx::query_as!(
-      GithubRepository,
+    let mut tx = self.begin().await?;
+    let subdirectory = repo.subdirectory;
+    let repo = sqlx::query!(
       "INSERT INTO github_repositories (id, owner, name)
       VALUES ($1, $2, $3)
       ON CONFLICT(id) DO UPDATE
@@ -436,20 +539,30 @@ impl Database {
       repo.owner,
       repo.name
     )
+    .map(|r| GithubRepository {
+      id: r.id,
+      owner: r.owner,
+      name: r.name,
+      subdirectory: subdirectory.map(ToOwned::to_owned),
+      updated_at: r.updated_at,
+      created_at: r.created_at,
+    })
     .fetch_one(&mut *tx)
     .await?;
 
     let (package, meta) = sqlx::query!(
       r#"UPDATE packages
-      SET github_repository_id = $3
+      SET github_repository_id = $3, github_repository_subdirectory = $4
       WHERE scope = $1 AND name = $2
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", when_featured, is_archived, updated_at, created_at,
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
         (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "latest_version",
         (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta""#,
       scope as _,
       name as _,
-      repo.id
+      repo.id,
+      repo.subdirectory
     )
       .map(|r| {
         let package = Package {
@@ -458,12 +571,22 @@ impl Database {
           description: r.description,
           github_repository_id: r.github_repository_id,
           runtime_compat: r.runtime_compat,
+          validation_level: r.validation_level,
           updated_at: r.updated_at,
           created_at: r.created_at,
           version_count: r.version_count,
+          star_count: r.star_count,
           latest_version: r.latest_version,
           when_featured: r.when_featured,
           is_archived: r.is_archived,
+          noindex: r.noindex,
+          keywords: r.keywords,
+          docs_landing_page: r.docs_landing_page,
+          successor_scope: r.successor_scope,
+          successor_name: r.successor_name,
+          maintenance_status: r.maintenance_status,
+          maintenance_status_updated_at: r.maintenance_status_updated_at,
+          unmaintained_suggested_at: r.unmaintained_suggested_at,
         };
 
         (package, r.package_version_meta.unwrap_or_default())
@@ -489,15 +612,16 @@ impl Database {
     let package = sqlx::query_as!(
       Package,
       r#"UPDATE packages
-      SET github_repository_id = NULL
+      SET github_repository_id = NULL, github_repository_subdirectory = NULL
       WHERE scope = $1 AND name = $2
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", when_featured, is_archived, updated_at, created_at,
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
         (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
       scope as _,
       name as _,
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await?;
 
     Ok(package)
@@ -519,14 +643,43 @@ impl Database {
       r#"UPDATE packages
       SET runtime_compat = $3
       WHERE scope = $1 AND name = $2
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", when_featured, is_archived, updated_at, created_at,
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
         (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
       scope as _,
       name as _,
       runtime_compat as _
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::update_package_validation_level",
+    skip(self),
+    err
+  )]
+  pub async fn update_package_validation_level(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    validation_level: ValidationLevel,
+  ) -> Result<Package> {
+    sqlx::query_as!(
+      Package,
+      r#"UPDATE packages
+      SET validation_level = $3
+      WHERE scope = $1 AND name = $2
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
+        (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
+        (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
+      scope as _,
+      name as _,
+      validation_level as _
+    )
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -543,14 +696,15 @@ impl Database {
       r#"UPDATE packages
       SET when_featured = $3
       WHERE scope = $1 AND name = $2
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", when_featured, is_archived, updated_at, created_at,
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
         (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
       scope as _,
       name as _,
       when_featured,
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -566,14 +720,177 @@ impl Database {
       r#"UPDATE packages
       SET is_archived = $3
       WHERE scope = $1 AND name = $2
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", when_featured, is_archived, updated_at, created_at,
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
         (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
       scope as _,
       name as _,
       is_archived,
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Copies the keywords of a package version to the package. This is done
+  /// when the version becomes the latest version of the package.
+  #[instrument(
+    name = "Database::update_package_keywords_from_version",
+    skip(self),
+    err
+  )]
+  pub async fn update_package_keywords_from_version(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"UPDATE packages
+      SET keywords = (SELECT keywords FROM package_versions WHERE scope = $1 AND name = $2 AND version = $3)
+      WHERE scope = $1 AND name = $2"#,
+      scope as _,
+      name as _,
+      version as _,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  #[instrument(
+    name = "Database::update_package_docs_landing_page",
+    skip(self),
+    err
+  )]
+  pub async fn update_package_docs_landing_page(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    docs_landing_page: Option<&str>,
+  ) -> Result<Package> {
+    sqlx::query_as!(
+      Package,
+      r#"UPDATE packages
+      SET docs_landing_page = $3
+      WHERE scope = $1 AND name = $2
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
+        (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
+        (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
+      scope as _,
+      name as _,
+      docs_landing_page,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::update_package_successor", skip(self), err)]
+  pub async fn update_package_successor(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    successor: Option<(&ScopeName, &PackageName)>,
+  ) -> Result<Package> {
+    let (successor_scope, successor_name) = successor.unzip();
+    sqlx::query_as!(
+      Package,
+      r#"UPDATE packages
+      SET successor_scope = $3, successor_name = $4
+      WHERE scope = $1 AND name = $2
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
+        (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
+        (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
+      scope as _,
+      name as _,
+      successor_scope as _,
+      successor_name as _,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Sets the maintenance status of the package, which also confirms or
+  /// dismisses a pending suggestion to mark the package as unmaintained.
+  #[instrument(
+    name = "Database::update_package_maintenance_status",
+    skip(self),
+    err
+  )]
+  pub async fn update_package_maintenance_status(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    maintenance_status: Option<MaintenanceStatus>,
+  ) -> Result<Package> {
+    sqlx::query_as!(
+      Package,
+      r#"UPDATE packages
+      SET maintenance_status = $3, maintenance_status_updated_at = now(), unmaintained_suggested_at = NULL
+      WHERE scope = $1 AND name = $2
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
+        (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
+        (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
+      scope as _,
+      name as _,
+      maintenance_status as _,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Suggests marking packages as unmaintained if they have not had a release
+  /// since `inactive_since`, and their maintenance status was not set since
+  /// then either. Returns the packages that a suggestion was made for.
+  #[instrument(
+    name = "Database::suggest_unmaintained_packages",
+    skip(self),
+    err
+  )]
+  pub async fn suggest_unmaintained_packages(
+    &self,
+    inactive_since: DateTime<Utc>,
+  ) -> Result<Vec<(ScopeName, PackageName)>> {
+    sqlx::query!(
+      r#"UPDATE packages
+      SET unmaintained_suggested_at = now()
+      WHERE unmaintained_suggested_at IS NULL
+        AND maintenance_status IS DISTINCT FROM 'unmaintained'
+        AND NOT is_archived
+        AND (maintenance_status_updated_at IS NULL OR maintenance_status_updated_at < $1)
+        AND (SELECT MAX(created_at) FROM package_versions WHERE package_versions.scope = packages.scope AND package_versions.name = packages.name) < $1
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName""#,
+      inactive_since,
+    )
+    .map(|r| (r.scope, r.name))
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::update_package_noindex", skip(self), err)]
+  pub async fn update_package_noindex(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    noindex: bool,
+  ) -> Result<Package> {
+    sqlx::query_as!(
+      Package,
+      r#"UPDATE packages
+      SET noindex = $3
+      WHERE scope = $1 AND name = $2
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", description, github_repository_id, runtime_compat as "runtime_compat: RuntimeCompat", validation_level as "validation_level: ValidationLevel", when_featured, is_archived, noindex, keywords, docs_landing_page, successor_scope as "successor_scope: ScopeName", successor_name as "successor_name: PackageName", maintenance_status as "maintenance_status: MaintenanceStatus", maintenance_status_updated_at, unmaintained_suggested_at, updated_at, created_at,
+        (SELECT COUNT(created_at) FROM package_versions WHERE scope = scope AND name = name) as "version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "star_count!",
+        (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version""#,
+      scope as _,
+      name as _,
+      noindex,
+    )
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -596,6 +913,11 @@ impl Database {
             publish_attempts_per_week_limit,
             verify_oidc_actor,
             require_publishing_from_ci,
+            validation_level,
+            outdated_dependencies_email,
+            download_anomaly_email,
+            min_docs_coverage,
+            enforce_min_docs_coverage,
             updated_at,
             created_at
         ),
@@ -611,6 +933,11 @@ impl Database {
         publish_attempts_per_week_limit,
         verify_oidc_actor,
         require_publishing_from_ci,
+        validation_level as "validation_level: ValidationLevel",
+        outdated_dependencies_email,
+        download_anomaly_email,
+        min_docs_coverage,
+        enforce_min_docs_coverage,
         updated_at,
         created_at
         FROM ins_scope
@@ -618,7 +945,7 @@ impl Database {
       scope as _,
       user_id
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -630,7 +957,7 @@ impl Database {
     new_package_per_week_limit: Option<i32>,
     publish_attempts_per_week_limit: Option<i32>,
   ) -> Result<(Scope, ScopeUsage, UserPublic)> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
 
     if let Some(package_limit) = package_limit {
       sqlx::query!(
@@ -680,6 +1007,11 @@ impl Database {
       scopes.publish_attempts_per_week_limit as "scope_publish_attempts_per_week_limit",
       scopes.verify_oidc_actor as "scope_verify_oidc_actor",
       scopes.require_publishing_from_ci as "scope_require_publishing_from_ci",
+      scopes.validation_level as "scope_validation_level: ValidationLevel",
+      scopes.outdated_dependencies_email as "scope_outdated_dependencies_email",
+      scopes.download_anomaly_email as "scope_download_anomaly_email",
+      scopes.min_docs_coverage as "scope_min_docs_coverage",
+      scopes.enforce_min_docs_coverage as "scope_enforce_min_docs_coverage",
       scopes.updated_at as "scope_updated_at",
       scopes.created_at as "scope_created_at",
       users.id as "user_id", users.name as "user_name", users.avatar_url as "user_avatar_url", users.github_id as "user_github_id", users.updated_at as "user_updated_at", users.created_at as "user_created_at",
@@ -702,6 +1034,11 @@ impl Database {
           publish_attempts_per_week_limit: r.scope_publish_attempts_per_week_limit,
           verify_oidc_actor: r.scope_verify_oidc_actor,
           require_publishing_from_ci: r.scope_require_publishing_from_ci,
+          validation_level: r.scope_validation_level,
+          outdated_dependencies_email: r.scope_outdated_dependencies_email,
+          download_anomaly_email: r.scope_download_anomaly_email,
+          min_docs_coverage: r.scope_min_docs_coverage,
+          enforce_min_docs_coverage: r.scope_enforce_min_docs_coverage,
         };
         let usage = ScopeUsage {
           package: r.usage_package.unwrap().try_into().unwrap(),
@@ -734,7 +1071,7 @@ impl Database {
     limit: i64,
     maybe_search_query: Option<&str>,
   ) -> Result<(usize, Vec<(Scope, ScopeUsage, UserPublic)>)> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
     let search = format!("%{}%", maybe_search_query.unwrap_or(""));
     let scopes = sqlx::query!(
       r#" WITH usage AS (
@@ -752,6 +1089,11 @@ impl Database {
       scopes.updated_at as "scope_updated_at",
       scopes.verify_oidc_actor as "scope_verify_oidc_actor",
       scopes.require_publishing_from_ci as "scope_require_publishing_from_ci",
+      scopes.validation_level as "scope_validation_level: ValidationLevel",
+      scopes.outdated_dependencies_email as "scope_outdated_dependencies_email",
+      scopes.download_anomaly_email as "scope_download_anomaly_email",
+      scopes.min_docs_coverage as "scope_min_docs_coverage",
+      scopes.enforce_min_docs_coverage as "scope_enforce_min_docs_coverage",
       scopes.created_at as "scope_created_at",
       users.id as "user_id", users.name as "user_name", users.avatar_url as "user_avatar_url", users.github_id as "user_github_id", users.updated_at as "user_updated_at", users.created_at as "user_created_at",
       usage.package as "usage_package", usage.new_package_per_week as "usage_new_package_per_week", usage.publish_attempts_per_week as "usage_publish_attempts_per_week"
@@ -778,6 +1120,11 @@ impl Database {
           publish_attempts_per_week_limit: r.scope_publish_attempts_per_week_limit,
           verify_oidc_actor: r.scope_verify_oidc_actor,
           require_publishing_from_ci: r.scope_require_publishing_from_ci,
+          validation_level: r.scope_validation_level,
+          outdated_dependencies_email: r.scope_outdated_dependencies_email,
+          download_anomaly_email: r.scope_download_anomaly_email,
+          min_docs_coverage: r.scope_min_docs_coverage,
+          enforce_min_docs_coverage: r.scope_enforce_min_docs_coverage,
         };
         let usage = ScopeUsage {
           package: r.usage_package.unwrap().try_into().unwrap(),
@@ -826,13 +1173,18 @@ impl Database {
       publish_attempts_per_week_limit,
       verify_oidc_actor,
       require_publishing_from_ci,
+      validation_level as "validation_level: ValidationLevel",
+      outdated_dependencies_email,
+      download_anomaly_email,
+      min_docs_coverage,
+      enforce_min_docs_coverage,
       updated_at,
       created_at
       FROM scopes WHERE creator = $1
       ORDER BY scope ASC"#,
       user_id
     )
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
@@ -848,12 +1200,17 @@ impl Database {
       publish_attempts_per_week_limit,
       verify_oidc_actor,
       require_publishing_from_ci,
+      validation_level as "validation_level: ValidationLevel",
+      outdated_dependencies_email,
+      download_anomaly_email,
+      min_docs_coverage,
+      enforce_min_docs_coverage,
       updated_at,
       created_at
       FROM scopes WHERE scope = $1"#,
       scope as _
     )
-    .fetch_optional(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
@@ -873,10 +1230,102 @@ impl Database {
           publish_attempts_per_week: r.publish_attempts_per_week.unwrap().try_into().unwrap(),
         }
       })
-      .fetch_one(&self.pool)
+      .fetch_one(&mut *self.acquire().await?)
       .await
   }
 
+  #[instrument(name = "Database::get_scope_metrics", skip(self), err)]
+  pub async fn get_scope_metrics(
+    &self,
+    scope: &ScopeName,
+  ) -> Result<ScopeMetrics> {
+    let mut tx = self.begin().await?;
+
+    let publishes_per_week = sqlx::query_as!(
+      ScopeWeeklyPublishes,
+      r#"SELECT date_trunc('week', created_at) as "week!", COUNT(*) as "count!"
+      FROM package_versions
+      WHERE scope = $1 AND created_at > now() - '12 weeks'::interval
+      GROUP BY 1
+      ORDER BY 1 ASC"#,
+      scope as _,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let package_downloads = sqlx::query_as!(
+      ScopePackageDownloads,
+      r#"SELECT packages.name as "package: PackageName",
+        COALESCE(SUM(dl.count) FILTER (WHERE dl.time_bucket > now() - '30 days'::interval), 0) as "downloads_30d!",
+        COALESCE(SUM(dl.count), 0) as "downloads_total!"
+      FROM packages
+      LEFT JOIN version_download_counts_24h as dl ON dl.scope = packages.scope AND dl.package = packages.name AND dl.kind != 'web'
+      WHERE packages.scope = $1
+      GROUP BY packages.name
+      ORDER BY 2 DESC, packages.name ASC"#,
+      scope as _,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let active_tokens = sqlx::query!(
+      r#"SELECT COUNT(*) as "count!"
+      FROM tokens
+      INNER JOIN scope_members ON scope_members.user_id = tokens.user_id AND scope_members.scope = $1
+      WHERE tokens.type IN ('personal', 'scope')
+      AND (tokens.expires_at IS NULL OR tokens.expires_at > now())
+      AND (tokens.permissions IS NULL OR EXISTS (
+        SELECT 1 FROM jsonb_array_elements(tokens.permissions) AS permission
+        WHERE permission->>'scope' = $1
+      ))"#,
+      scope as _,
+    )
+    .map(|r| r.count)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let members = sqlx::query!(
+      r#"SELECT users.id as "user_id", users.name as "user_name", users.avatar_url as "user_avatar_url", users.github_id as "user_github_id", users.updated_at as "user_updated_at", users.created_at as "user_created_at",
+        scope_members.is_admin,
+        (SELECT COUNT(*) FROM package_versions
+          WHERE package_versions.scope = scope_members.scope
+          AND package_versions.user_id = scope_members.user_id
+          AND package_versions.created_at > now() - '30 days'::interval) as "publishes_30d!",
+        (SELECT MAX(package_versions.created_at) FROM package_versions
+          WHERE package_versions.scope = scope_members.scope
+          AND package_versions.user_id = scope_members.user_id) as "last_published_at"
+      FROM scope_members
+      INNER JOIN users ON scope_members.user_id = users.id
+      WHERE scope_members.scope = $1
+      ORDER BY users.name ASC"#,
+      scope as _,
+    )
+    .map(|r| ScopeMemberActivity {
+      user: UserPublic {
+        id: r.user_id,
+        name: r.user_name,
+        avatar_url: r.user_avatar_url,
+        github_id: r.user_github_id,
+        updated_at: r.user_updated_at,
+        created_at: r.user_created_at,
+      },
+      is_admin: r.is_admin,
+      publishes_30d: r.publishes_30d,
+      last_published_at: r.last_published_at,
+    })
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(ScopeMetrics {
+      publishes_per_week,
+      package_downloads,
+      active_tokens,
+      members,
+    })
+  }
+
   #[instrument(name = "Database::scope_set_verify_oidc_actor", skip(self), err)]
   pub async fn scope_set_verify_oidc_actor(
     &self,
@@ -895,6 +1344,11 @@ impl Database {
           publish_attempts_per_week_limit,
           verify_oidc_actor,
           require_publishing_from_ci,
+          validation_level as "validation_level: ValidationLevel",
+          outdated_dependencies_email,
+          download_anomaly_email,
+          min_docs_coverage,
+          enforce_min_docs_coverage,
           updated_at,
           created_at
 
@@ -902,7 +1356,7 @@ impl Database {
       verify_oidc_actor,
       scope as _
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -928,6 +1382,11 @@ impl Database {
           publish_attempts_per_week_limit,
           verify_oidc_actor,
           require_publishing_from_ci,
+          validation_level as "validation_level: ValidationLevel",
+          outdated_dependencies_email,
+          download_anomaly_email,
+          min_docs_coverage,
+          enforce_min_docs_coverage,
           updated_at,
           created_at
 
@@ -935,156 +1394,308 @@ impl Database {
       require_publishing_from_ci,
       scope as _
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::list_packages_by_scope", skip(self), err)]
-  pub async fn list_packages_by_scope(
+  #[instrument(name = "Database::scope_set_validation_level", skip(self), err)]
+  pub async fn scope_set_validation_level(
     &self,
     scope: &ScopeName,
-    show_archived: bool,
-    start: i64,
-    limit: i64,
-  ) -> Result<(usize, Vec<PackageWithGitHubRepoAndMeta>)> {
-    let mut tx = self.pool.begin().await?;
+    validation_level: Option<ValidationLevel>,
+  ) -> Result<Scope> {
+    sqlx::query_as!(
+      Scope,
+      r#"
+        UPDATE scopes SET validation_level = $1 WHERE scope = $2
+        RETURNING
+          scope as "scope: ScopeName",
+          creator,
+          package_limit,
+          new_package_per_week_limit,
+          publish_attempts_per_week_limit,
+          verify_oidc_actor,
+          require_publishing_from_ci,
+          validation_level as "validation_level: ValidationLevel",
+          outdated_dependencies_email,
+          download_anomaly_email,
+          min_docs_coverage,
+          enforce_min_docs_coverage,
+          updated_at,
+          created_at
 
-    let packages = sqlx::query!(
-      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
-        (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "package_version_count!",
-        (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_latest_version",
-        (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta",
-        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?"
-      FROM packages
-      LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
-      WHERE packages.scope = $1 AND ($2 = true OR packages.is_archived = false)
-      ORDER BY packages.name
-      OFFSET $3 LIMIT $4"#,
-      scope as _,
-      show_archived,
-      start,
-      limit
+      "#,
+      validation_level as _,
+      scope as _
     )
-    .map(|r| {
-      let package = Package {
-        scope: r.package_scope,
-        name: r.package_name,
-        description: r.package_description,
-        github_repository_id: r.package_github_repository_id,
-        runtime_compat: r.package_runtime_compat,
-        created_at: r.package_created_at,
-        updated_at: r.package_updated_at,
-        version_count: r.package_version_count,
-        latest_version: r.package_latest_version,
-        when_featured: r.package_when_featured,
-        is_archived: r.package_is_archived,
-      };
-      let github_repository = if r.package_github_repository_id.is_some() {
-        Some(GithubRepository {
-          id: r.github_repository_id.unwrap(),
-          owner: r.github_repository_owner.unwrap(),
-          name: r.github_repository_name.unwrap(),
-          created_at: r.github_repository_created_at.unwrap(),
-          updated_at: r.github_repository_updated_at.unwrap(),
-        })
-      } else {
-        None
-      };
-
-      let meta = r.package_version_meta.unwrap_or_default();
-
-      (package, github_repository, meta)
-    })
-    .fetch_all(&mut *tx)
-    .await?;
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
 
-    let total_packages = sqlx::query!(
-      r#"SELECT COUNT(created_at) FROM packages WHERE scope = $1 AND ($2 = true OR packages.is_archived = false);"#,
+  #[instrument(
+    name = "Database::scope_set_outdated_dependencies_email",
+    skip(self),
+    err
+  )]
+  pub async fn scope_set_outdated_dependencies_email(
+    &self,
+    scope: &ScopeName,
+    outdated_dependencies_email: bool,
+  ) -> Result<Scope> {
+    sqlx::query_as!(
+      Scope,
+      r#"
+        UPDATE scopes SET outdated_dependencies_email = $1 WHERE scope = $2
+        RETURNING
+          scope as "scope: ScopeName",
+          creator,
+          package_limit,
+          new_package_per_week_limit,
+          publish_attempts_per_week_limit,
+          verify_oidc_actor,
+          require_publishing_from_ci,
+          validation_level as "validation_level: ValidationLevel",
+          outdated_dependencies_email,
+          download_anomaly_email,
+          min_docs_coverage,
+          enforce_min_docs_coverage,
+          updated_at,
+          created_at
+
+      "#,
+      outdated_dependencies_email,
+      scope as _
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::scope_set_download_anomaly_email",
+    skip(self),
+    err
+  )]
+  pub async fn scope_set_download_anomaly_email(
+    &self,
+    scope: &ScopeName,
+    download_anomaly_email: bool,
+  ) -> Result<Scope> {
+    sqlx::query_as!(
+      Scope,
+      r#"
+        UPDATE scopes SET download_anomaly_email = $1 WHERE scope = $2
+        RETURNING
+          scope as "scope: ScopeName",
+          creator,
+          package_limit,
+          new_package_per_week_limit,
+          publish_attempts_per_week_limit,
+          verify_oidc_actor,
+          require_publishing_from_ci,
+          validation_level as "validation_level: ValidationLevel",
+          outdated_dependencies_email,
+          download_anomaly_email,
+          min_docs_coverage,
+          enforce_min_docs_coverage,
+          updated_at,
+          created_at
+
+      "#,
+      download_anomaly_email,
+      scope as _
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::scope_set_docs_coverage_policy",
+    skip(self),
+    err
+  )]
+  pub async fn scope_set_docs_coverage_policy(
+    &self,
+    scope: &ScopeName,
+    min_docs_coverage: Option<f32>,
+    enforce_min_docs_coverage: bool,
+  ) -> Result<Scope> {
+    sqlx::query_as!(
+      Scope,
+      r#"
+        UPDATE scopes SET min_docs_coverage = $1, enforce_min_docs_coverage = $2
+        WHERE scope = $3
+        RETURNING
+          scope as "scope: ScopeName",
+          creator,
+          package_limit,
+          new_package_per_week_limit,
+          publish_attempts_per_week_limit,
+          verify_oidc_actor,
+          require_publishing_from_ci,
+          validation_level as "validation_level: ValidationLevel",
+          outdated_dependencies_email,
+          download_anomaly_email,
+          min_docs_coverage,
+          enforce_min_docs_coverage,
+          updated_at,
+          created_at
+
+      "#,
+      min_docs_coverage,
+      enforce_min_docs_coverage,
+      scope as _
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::get_scope_profile", skip(self), err)]
+  pub async fn get_scope_profile(
+    &self,
+    scope: &ScopeName,
+  ) -> Result<ScopeProfile> {
+    let mut tx = self.begin().await?;
+
+    let readme =
+      sqlx::query!(r#"SELECT readme FROM scopes WHERE scope = $1"#, scope as _)
+        .map(|r| r.readme)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let pinned_packages = sqlx::query!(
+      r#"SELECT name "name: PackageName" FROM scope_pinned_packages
+      WHERE scope = $1
+      ORDER BY position"#,
+      scope as _
+    )
+    .map(|r| r.name)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(ScopeProfile {
+      readme,
+      pinned_packages,
+    })
+  }
+
+  #[instrument(name = "Database::scope_set_readme", skip(self, readme), err)]
+  pub async fn scope_set_readme(
+    &self,
+    scope: &ScopeName,
+    readme: Option<&str>,
+  ) -> Result<Scope> {
+    sqlx::query_as!(
+      Scope,
+      r#"
+        UPDATE scopes SET readme = $1 WHERE scope = $2
+        RETURNING
+          scope as "scope: ScopeName",
+          creator,
+          package_limit,
+          new_package_per_week_limit,
+          publish_attempts_per_week_limit,
+          verify_oidc_actor,
+          require_publishing_from_ci,
+          validation_level as "validation_level: ValidationLevel",
+          outdated_dependencies_email,
+          download_anomaly_email,
+          min_docs_coverage,
+          enforce_min_docs_coverage,
+          updated_at,
+          created_at
+
+      "#,
+      readme,
+      scope as _
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Replaces the pinned packages of the scope. The packages are shown in the
+  /// order they are passed in.
+  #[instrument(name = "Database::scope_set_pinned_packages", skip(self), err)]
+  pub async fn scope_set_pinned_packages(
+    &self,
+    scope: &ScopeName,
+    packages: &[PackageName],
+  ) -> Result<Scope> {
+    let mut tx = self.begin().await?;
+
+    sqlx::query!(
+      r#"DELETE FROM scope_pinned_packages WHERE scope = $1"#,
+      scope as _
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+      r#"INSERT INTO scope_pinned_packages (scope, name, position)
+      SELECT $1, t.name, t.position
+      FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS t(name, position)"#,
       scope as _,
-      show_archived,
+      packages as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let scope = sqlx::query_as!(
+      Scope,
+      r#"
+        UPDATE scopes SET updated_at = now() WHERE scope = $1
+        RETURNING
+          scope as "scope: ScopeName",
+          creator,
+          package_limit,
+          new_package_per_week_limit,
+          publish_attempts_per_week_limit,
+          verify_oidc_actor,
+          require_publishing_from_ci,
+          validation_level as "validation_level: ValidationLevel",
+          outdated_dependencies_email,
+          download_anomaly_email,
+          min_docs_coverage,
+          enforce_min_docs_coverage,
+          updated_at,
+          created_at
+
+      "#,
+      scope as _
     )
-    .map(|r| r.count.unwrap())
     .fetch_one(&mut *tx)
     .await?;
 
     tx.commit().await?;
 
-    Ok((total_packages as usize, packages))
+    Ok(scope)
   }
 
-  #[instrument(name = "Database::list_packages", skip(self), err)]
-  pub async fn list_packages(
+  #[instrument(name = "Database::list_packages_by_scope", skip(self), err)]
+  pub async fn list_packages_by_scope(
     &self,
+    scope: &ScopeName,
+    show_archived: bool,
     start: i64,
     limit: i64,
-    maybe_search_query: Option<&str>,
-    maybe_github_repo_id: Option<i64>,
   ) -> Result<(usize, Vec<PackageWithGitHubRepoAndMeta>)> {
-    let mut tx = self.pool.begin().await?;
-
-    let (
-      scope_ilike_query,
-      scope_exact_query,
-      package_ilike_query,
-      package_exact_query,
-    ) = if let Some(search_query) = maybe_search_query {
-      // 1. Strip leading `@`.
-      let search_query = search_query.strip_prefix('@').unwrap_or(search_query);
-
-      // 2. If there's a space in the search query, we're gonna split it
-      // and use the first term for scope search and the reminder for package
-      // search.
-      let (scope_query, package_query) = if let Some((
-        scope_query,
-        package_query,
-      )) = search_query.split_once(' ')
-      {
-        (scope_query, package_query)
-      } else {
-        // 3. If there's no space in the search query, we're gonna split it
-        // at `/` and use the first term for scope search and the reminder for package
-        // search.
-        search_query
-          .split_once('/')
-          .unwrap_or((search_query, search_query))
-      };
+    let mut tx = self.begin().await?;
 
-      (
-        format!("%{}%", scope_query),
-        scope_query.to_string(),
-        format!("%{}%", package_query),
-        package_query.to_string(),
-      )
-    } else {
-      (
-        "%%".to_string(),
-        "".to_string(),
-        "%%".to_string(),
-        "".to_string(),
-      )
-    };
     let packages = sqlx::query!(
-      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
+      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.validation_level as "package_validation_level: ValidationLevel", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.noindex "package_noindex", packages.keywords "package_keywords", packages.docs_landing_page "package_docs_landing_page", packages.successor_scope "package_successor_scope: ScopeName", packages.successor_name "package_successor_name: PackageName", packages.maintenance_status "package_maintenance_status: MaintenanceStatus", packages.maintenance_status_updated_at "package_maintenance_status_updated_at", packages.unmaintained_suggested_at "package_unmaintained_suggested_at", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "package_version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "package_star_count!",
         (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_latest_version",
         (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta",
-        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?"
-       FROM packages
-       LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
-       WHERE (packages.scope ILIKE $1 OR packages.name ILIKE $2) AND (packages.github_repository_id = $5 OR $5 IS NULL) AND NOT packages.is_archived
-       ORDER BY
-         CASE
-           WHEN packages.name ILIKE $3 THEN 1 -- Exact match for package name
-           WHEN packages.scope ILIKE $4 THEN 2 -- Exact match for scope name
-           ELSE 3 -- Fuzzy matches will be ordered by package name and then scope name below
-        END,
-        packages.name ASC, packages.scope ASC
-       OFFSET $6 LIMIT $7"#,
-      scope_ilike_query,
-      package_ilike_query,
-      package_exact_query,
-      scope_exact_query,
-      maybe_github_repo_id,
+        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?", packages.github_repository_subdirectory "github_repository_subdirectory"
+      FROM packages
+      LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
+      WHERE packages.scope = $1 AND ($2 = true OR packages.is_archived = false)
+      ORDER BY packages.name
+      OFFSET $3 LIMIT $4"#,
+      scope as _,
+      show_archived,
       start,
       limit
     )
@@ -1095,65 +1706,357 @@ impl Database {
         description: r.package_description,
         github_repository_id: r.package_github_repository_id,
         runtime_compat: r.package_runtime_compat,
+        validation_level: r.package_validation_level,
         created_at: r.package_created_at,
         updated_at: r.package_updated_at,
         version_count: r.package_version_count,
+        star_count: r.package_star_count,
         latest_version: r.package_latest_version,
         when_featured: r.package_when_featured,
         is_archived: r.package_is_archived,
+        noindex: r.package_noindex,
+        keywords: r.package_keywords,
+        docs_landing_page: r.package_docs_landing_page,
+        successor_scope: r.package_successor_scope,
+        successor_name: r.package_successor_name,
+        maintenance_status: r.package_maintenance_status,
+        maintenance_status_updated_at: r.package_maintenance_status_updated_at,
+        unmaintained_suggested_at: r.package_unmaintained_suggested_at,
       };
       let github_repository = if r.package_github_repository_id.is_some() {
         Some(GithubRepository {
           id: r.github_repository_id.unwrap(),
           owner: r.github_repository_owner.unwrap(),
           name: r.github_repository_name.unwrap(),
+          subdirectory: r.github_repository_subdirectory,
           created_at: r.github_repository_created_at.unwrap(),
           updated_at: r.github_repository_updated_at.unwrap(),
         })
       } else {
         None
       };
+
       let meta = r.package_version_meta.unwrap_or_default();
+
       (package, github_repository, meta)
     })
     .fetch_all(&mut *tx)
     .await?;
 
     let total_packages = sqlx::query!(
-      r#"SELECT COUNT(created_at) FROM packages WHERE (packages.scope ILIKE $1 OR packages.name ILIKE $2) AND (packages.github_repository_id = $3 OR $3 IS NULL);"#,
-      scope_ilike_query,
-      package_ilike_query,
-      maybe_github_repo_id,
+      r#"SELECT COUNT(created_at) FROM packages WHERE scope = $1 AND ($2 = true OR packages.is_archived = false);"#,
+      scope as _,
+      show_archived,
     )
-      .map(|r| r.count.unwrap())
-      .fetch_one(&mut *tx)
-      .await?;
+    .map(|r| r.count.unwrap())
+    .fetch_one(&mut *tx)
+    .await?;
 
     tx.commit().await?;
 
     Ok((total_packages as usize, packages))
   }
 
-  #[instrument(name = "Database::package_stats", skip(self), err)]
-  pub async fn package_stats(
+  #[instrument(name = "Database::star_package", skip(self), err)]
+  pub async fn star_package(
     &self,
-  ) -> Result<(
-    Vec<PackageWithGitHubRepoAndMeta>,
-    Vec<PackageVersion>,
-    Vec<PackageWithGitHubRepoAndMeta>,
-  )> {
-    let newest = sqlx::query!(
-      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
-        (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "package_version_count!",
-        (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_latest_version",
-        (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta",
-        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?"
-      FROM packages
-      LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
-      WHERE (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND is_yanked = false AND version IS NOT NULL ORDER BY version DESC LIMIT 1) IS NOT NULL AND NOT packages.is_archived
-      ORDER BY packages.created_at DESC
-      LIMIT 10"#,
-    )
+    user_id: Uuid,
+    scope: &ScopeName,
+    name: &PackageName,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"INSERT INTO package_stars (user_id, scope, name)
+      VALUES ($1, $2, $3)
+      ON CONFLICT DO NOTHING"#,
+      user_id,
+      scope as _,
+      name as _
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  #[instrument(name = "Database::unstar_package", skip(self), err)]
+  pub async fn unstar_package(
+    &self,
+    user_id: Uuid,
+    scope: &ScopeName,
+    name: &PackageName,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"DELETE FROM package_stars WHERE user_id = $1 AND scope = $2 AND name = $3"#,
+      user_id,
+      scope as _,
+      name as _
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  #[instrument(name = "Database::list_starred_packages", skip(self), err)]
+  pub async fn list_starred_packages(
+    &self,
+    user_id: Uuid,
+    start: i64,
+    limit: i64,
+  ) -> Result<(usize, Vec<PackageWithGitHubRepoAndMeta>)> {
+    let mut tx = self.begin().await?;
+
+    let packages = sqlx::query!(
+      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.validation_level as "package_validation_level: ValidationLevel", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.noindex "package_noindex", packages.keywords "package_keywords", packages.docs_landing_page "package_docs_landing_page", packages.successor_scope "package_successor_scope: ScopeName", packages.successor_name "package_successor_name: PackageName", packages.maintenance_status "package_maintenance_status: MaintenanceStatus", packages.maintenance_status_updated_at "package_maintenance_status_updated_at", packages.unmaintained_suggested_at "package_unmaintained_suggested_at", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
+        (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "package_version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "package_star_count!",
+        (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_latest_version",
+        (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta",
+        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?", packages.github_repository_subdirectory "github_repository_subdirectory"
+      FROM package_stars
+      JOIN packages ON package_stars.scope = packages.scope AND package_stars.name = packages.name
+      LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
+      WHERE package_stars.user_id = $1
+      ORDER BY package_stars.created_at DESC
+      OFFSET $2 LIMIT $3"#,
+      user_id,
+      start,
+      limit
+    )
+    .map(|r| {
+      let package = Package {
+        scope: r.package_scope,
+        name: r.package_name,
+        description: r.package_description,
+        github_repository_id: r.package_github_repository_id,
+        runtime_compat: r.package_runtime_compat,
+        validation_level: r.package_validation_level,
+        created_at: r.package_created_at,
+        updated_at: r.package_updated_at,
+        version_count: r.package_version_count,
+        star_count: r.package_star_count,
+        latest_version: r.package_latest_version,
+        when_featured: r.package_when_featured,
+        is_archived: r.package_is_archived,
+        noindex: r.package_noindex,
+        keywords: r.package_keywords,
+        docs_landing_page: r.package_docs_landing_page,
+        successor_scope: r.package_successor_scope,
+        successor_name: r.package_successor_name,
+        maintenance_status: r.package_maintenance_status,
+        maintenance_status_updated_at: r.package_maintenance_status_updated_at,
+        unmaintained_suggested_at: r.package_unmaintained_suggested_at,
+      };
+      let github_repository = if r.package_github_repository_id.is_some() {
+        Some(GithubRepository {
+          id: r.github_repository_id.unwrap(),
+          owner: r.github_repository_owner.unwrap(),
+          name: r.github_repository_name.unwrap(),
+          subdirectory: r.github_repository_subdirectory,
+          created_at: r.github_repository_created_at.unwrap(),
+          updated_at: r.github_repository_updated_at.unwrap(),
+        })
+      } else {
+        None
+      };
+
+      let meta = r.package_version_meta.unwrap_or_default();
+
+      (package, github_repository, meta)
+    })
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let total_stars = sqlx::query!(
+      r#"SELECT COUNT(created_at) FROM package_stars WHERE user_id = $1;"#,
+      user_id,
+    )
+    .map(|r| r.count.unwrap())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((total_stars as usize, packages))
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  #[instrument(name = "Database::list_packages", skip(self), err)]
+  pub async fn list_packages(
+    &self,
+    start: i64,
+    limit: i64,
+    maybe_search_query: Option<&str>,
+    maybe_github_repo_id: Option<i64>,
+    maybe_engine: Option<(&str, Vec<i32>)>,
+    maybe_keyword: Option<&str>,
+    maybe_maintenance_status: Option<MaintenanceStatus>,
+    sort_by_stars: bool,
+  ) -> Result<(usize, Vec<PackageWithGitHubRepoAndMeta>)> {
+    let mut tx = self.begin().await?;
+
+    let (engine_name, engine_version) = maybe_engine.unzip();
+
+    let (
+      scope_ilike_query,
+      scope_exact_query,
+      package_ilike_query,
+      package_exact_query,
+    ) = if let Some(search_query) = maybe_search_query {
+      // 1. Strip leading `@`.
+      let search_query = search_query.strip_prefix('@').unwrap_or(search_query);
+
+      // 2. If there's a space in the search query, we're gonna split it
+      // and use the first term for scope search and the reminder for package
+      // search.
+      let (scope_query, package_query) = if let Some((
+        scope_query,
+        package_query,
+      )) = search_query.split_once(' ')
+      {
+        (scope_query, package_query)
+      } else {
+        // 3. If there's no space in the search query, we're gonna split it
+        // at `/` and use the first term for scope search and the reminder for package
+        // search.
+        search_query
+          .split_once('/')
+          .unwrap_or((search_query, search_query))
+      };
+
+      (
+        format!("%{}%", scope_query),
+        scope_query.to_string(),
+        format!("%{}%", package_query),
+        package_query.to_string(),
+      )
+    } else {
+      (
+        "%%".to_string(),
+        "".to_string(),
+        "%%".to_string(),
+        "".to_string(),
+      )
+    };
+    let packages = sqlx::query!(
+      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.validation_level as "package_validation_level: ValidationLevel", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.noindex "package_noindex", packages.keywords "package_keywords", packages.docs_landing_page "package_docs_landing_page", packages.successor_scope "package_successor_scope: ScopeName", packages.successor_name "package_successor_name: PackageName", packages.maintenance_status "package_maintenance_status: MaintenanceStatus", packages.maintenance_status_updated_at "package_maintenance_status_updated_at", packages.unmaintained_suggested_at "package_unmaintained_suggested_at", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
+        (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "package_version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "package_star_count!",
+        (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_latest_version",
+        (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta",
+        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?", packages.github_repository_subdirectory "github_repository_subdirectory"
+       FROM packages
+       LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
+       WHERE (packages.scope ILIKE $1 OR packages.name ILIKE $2) AND (packages.github_repository_id = $5 OR $5 IS NULL) AND NOT packages.is_archived AND packages.has_usable_version
+         AND ($8::text IS NULL OR COALESCE((SELECT string_to_array(engines->>$8, '.')::int[] <= $9 FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1), true))
+         AND ($10::text IS NULL OR $10 = ANY(packages.keywords))
+         AND ($11::maintenance_status IS NULL OR packages.maintenance_status = $11)
+       ORDER BY
+         CASE WHEN $12 THEN (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) END DESC NULLS LAST,
+         CASE
+           WHEN packages.name ILIKE $3 THEN 1 -- Exact match for package name
+           WHEN packages.scope ILIKE $4 THEN 2 -- Exact match for scope name
+           ELSE 3 -- Fuzzy matches will be ordered by package name and then scope name below
+        END,
+        packages.successor_scope IS NOT NULL, -- Packages that moved to a successor come last
+        packages.name ASC, packages.scope ASC
+       OFFSET $6 LIMIT $7"#,
+      scope_ilike_query,
+      package_ilike_query,
+      package_exact_query,
+      scope_exact_query,
+      maybe_github_repo_id,
+      start,
+      limit,
+      engine_name,
+      engine_version.as_deref(),
+      maybe_keyword,
+      maybe_maintenance_status as _,
+      sort_by_stars,
+    )
+    .map(|r| {
+      let package = Package {
+        scope: r.package_scope,
+        name: r.package_name,
+        description: r.package_description,
+        github_repository_id: r.package_github_repository_id,
+        runtime_compat: r.package_runtime_compat,
+        validation_level: r.package_validation_level,
+        created_at: r.package_created_at,
+        updated_at: r.package_updated_at,
+        version_count: r.package_version_count,
+        star_count: r.package_star_count,
+        latest_version: r.package_latest_version,
+        when_featured: r.package_when_featured,
+        is_archived: r.package_is_archived,
+        noindex: r.package_noindex,
+        keywords: r.package_keywords,
+        docs_landing_page: r.package_docs_landing_page,
+        successor_scope: r.package_successor_scope,
+        successor_name: r.package_successor_name,
+        maintenance_status: r.package_maintenance_status,
+        maintenance_status_updated_at: r.package_maintenance_status_updated_at,
+        unmaintained_suggested_at: r.package_unmaintained_suggested_at,
+      };
+      let github_repository = if r.package_github_repository_id.is_some() {
+        Some(GithubRepository {
+          id: r.github_repository_id.unwrap(),
+          owner: r.github_repository_owner.unwrap(),
+          name: r.github_repository_name.unwrap(),
+          subdirectory: r.github_repository_subdirectory,
+          created_at: r.github_repository_created_at.unwrap(),
+          updated_at: r.github_repository_updated_at.unwrap(),
+        })
+      } else {
+        None
+      };
+      let meta = r.package_version_meta.unwrap_or_default();
+      (package, github_repository, meta)
+    })
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let total_packages = sqlx::query!(
+      r#"SELECT COUNT(created_at) FROM packages WHERE (packages.scope ILIKE $1 OR packages.name ILIKE $2) AND (packages.github_repository_id = $3 OR $3 IS NULL) AND packages.has_usable_version
+        AND ($4::text IS NULL OR COALESCE((SELECT string_to_array(engines->>$4, '.')::int[] <= $5 FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1), true))
+        AND ($6::text IS NULL OR $6 = ANY(packages.keywords))
+        AND ($7::maintenance_status IS NULL OR packages.maintenance_status = $7);"#,
+      scope_ilike_query,
+      package_ilike_query,
+      maybe_github_repo_id,
+      engine_name,
+      engine_version.as_deref(),
+      maybe_keyword,
+      maybe_maintenance_status as _,
+    )
+      .map(|r| r.count.unwrap())
+      .fetch_one(&mut *tx)
+      .await?;
+
+    tx.commit().await?;
+
+    Ok((total_packages as usize, packages))
+  }
+
+  #[instrument(name = "Database::package_stats", skip(self), err)]
+  pub async fn package_stats(
+    &self,
+  ) -> Result<(
+    Vec<PackageWithGitHubRepoAndMeta>,
+    Vec<PackageVersion>,
+    Vec<PackageWithGitHubRepoAndMeta>,
+  )> {
+    let newest = sqlx::query!(
+      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.validation_level as "package_validation_level: ValidationLevel", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.noindex "package_noindex", packages.keywords "package_keywords", packages.docs_landing_page "package_docs_landing_page", packages.successor_scope "package_successor_scope: ScopeName", packages.successor_name "package_successor_name: PackageName", packages.maintenance_status "package_maintenance_status: MaintenanceStatus", packages.maintenance_status_updated_at "package_maintenance_status_updated_at", packages.unmaintained_suggested_at "package_unmaintained_suggested_at", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
+        (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "package_version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "package_star_count!",
+        (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_latest_version",
+        (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta",
+        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?", packages.github_repository_subdirectory "github_repository_subdirectory"
+      FROM packages
+      LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
+      WHERE packages.has_usable_version AND NOT packages.is_archived
+      ORDER BY packages.created_at DESC
+      LIMIT 10"#,
+    )
     .map(|r| {
       let package = Package {
         scope: r.package_scope,
@@ -1161,18 +2064,29 @@ impl Database {
         description: r.package_description,
         github_repository_id: r.package_github_repository_id,
         runtime_compat: r.package_runtime_compat,
+        validation_level: r.package_validation_level,
         created_at: r.package_created_at,
         updated_at: r.package_updated_at,
         version_count: r.package_version_count,
+        star_count: r.package_star_count,
         latest_version: r.package_latest_version,
         when_featured: r.package_when_featured,
         is_archived: r.package_is_archived,
+        noindex: r.package_noindex,
+        keywords: r.package_keywords,
+        docs_landing_page: r.package_docs_landing_page,
+        successor_scope: r.package_successor_scope,
+        successor_name: r.package_successor_name,
+        maintenance_status: r.package_maintenance_status,
+        maintenance_status_updated_at: r.package_maintenance_status_updated_at,
+        unmaintained_suggested_at: r.package_unmaintained_suggested_at,
       };
       let github_repository = if r.package_github_repository_id.is_some() {
         Some(GithubRepository {
           id: r.github_repository_id.unwrap(),
           owner: r.github_repository_owner.unwrap(),
           name: r.github_repository_name.unwrap(),
+          subdirectory: r.github_repository_subdirectory,
           created_at: r.github_repository_created_at.unwrap(),
           updated_at: r.github_repository_updated_at.unwrap(),
         })
@@ -1182,12 +2096,12 @@ impl Database {
       let meta = r.package_version_meta.unwrap_or_default();
       (package, github_repository, meta)
     })
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await?;
 
     let updated = sqlx::query_as!(
       PackageVersion,
-      r#"SELECT package_versions.scope as "scope: ScopeName", package_versions.name as "name: PackageName", package_versions.version as "version: Version", package_versions.user_id, package_versions.readme_path as "readme_path: PackagePath", package_versions.exports as "exports: ExportsMap", package_versions.is_yanked, package_versions.uses_npm, package_versions.meta as "meta: PackageVersionMeta", package_versions.updated_at, package_versions.created_at, package_versions.rekor_log_id,
+      r#"SELECT package_versions.scope as "scope: ScopeName", package_versions.name as "name: PackageName", package_versions.version as "version: Version", package_versions.user_id, package_versions.readme_path as "readme_path: PackagePath", package_versions.exports as "exports: ExportsMap", package_versions.bin as "bin: BinMap", package_versions.engines as "engines: PackageVersionEngines", package_versions.keywords, package_versions.unpacked_size, package_versions.file_count, package_versions.is_yanked, package_versions.yank_reason_category as "yank_reason_category: YankReasonCategory", package_versions.yank_reason, package_versions.uses_npm, package_versions.meta as "meta: PackageVersionMeta", package_versions.updated_at, package_versions.created_at, package_versions.rekor_log_id,
       (SELECT COUNT(*)
         FROM package_versions AS pv
         WHERE pv.scope = package_versions.scope
@@ -1199,25 +2113,34 @@ impl Database {
         FROM version_download_counts_24h as dl
         WHERE dl.scope = package_versions.scope
         AND dl.package = package_versions.name
-        AND dl.version = package_versions.version) as "lifetime_download_count!"
+        AND dl.version = package_versions.version
+        AND dl.kind != 'web') as "lifetime_download_count!",
+      (SELECT npm.size
+        FROM npm_tarballs as npm
+        WHERE npm.scope = package_versions.scope
+        AND npm.name = package_versions.name
+        AND npm.version = package_versions.version
+        ORDER BY npm.revision DESC
+        LIMIT 1) as "npm_tarball_size"
       FROM package_versions
       JOIN packages ON packages.scope = package_versions.scope AND packages.name = package_versions.name
-      WHERE NOT packages.is_archived
+      WHERE NOT packages.is_archived AND NOT package_versions.is_yanked
       ORDER BY package_versions.created_at DESC
       LIMIT 10"#,
     )
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await?;
 
     let featured = sqlx::query!(
-      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
+      r#"SELECT packages.scope "package_scope: ScopeName", packages.name "package_name: PackageName", packages.description "package_description", packages.github_repository_id "package_github_repository_id", packages.runtime_compat as "package_runtime_compat: RuntimeCompat", packages.validation_level as "package_validation_level: ValidationLevel", packages.when_featured "package_when_featured", packages.is_archived "package_is_archived", packages.noindex "package_noindex", packages.keywords "package_keywords", packages.docs_landing_page "package_docs_landing_page", packages.successor_scope "package_successor_scope: ScopeName", packages.successor_name "package_successor_name: PackageName", packages.maintenance_status "package_maintenance_status: MaintenanceStatus", packages.maintenance_status_updated_at "package_maintenance_status_updated_at", packages.unmaintained_suggested_at "package_unmaintained_suggested_at", packages.updated_at "package_updated_at",  packages.created_at "package_created_at",
         (SELECT COUNT(created_at) FROM package_versions WHERE scope = packages.scope AND name = packages.name) as "package_version_count!",
+        (SELECT COUNT(created_at) FROM package_stars WHERE scope = packages.scope AND name = packages.name) as "package_star_count!",
         (SELECT version FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_latest_version",
         (SELECT meta FROM package_versions WHERE scope = packages.scope AND name = packages.name AND version NOT LIKE '%-%' AND is_yanked = false ORDER BY version DESC LIMIT 1) as "package_version_meta: PackageVersionMeta",
-        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?"
+        github_repositories.id "github_repository_id?", github_repositories.owner "github_repository_owner?", github_repositories.name "github_repository_name?", github_repositories.updated_at "github_repository_updated_at?", github_repositories.created_at "github_repository_created_at?", packages.github_repository_subdirectory "github_repository_subdirectory"
       FROM packages
       LEFT JOIN github_repositories ON packages.github_repository_id = github_repositories.id
-      WHERE packages.when_featured IS NOT NULL AND NOT packages.is_archived
+      WHERE packages.when_featured IS NOT NULL AND packages.has_usable_version AND NOT packages.is_archived
       ORDER BY packages.when_featured DESC
       LIMIT 10"#,
     )
@@ -1228,18 +2151,29 @@ impl Database {
         description: r.package_description,
         github_repository_id: r.package_github_repository_id,
         runtime_compat: r.package_runtime_compat,
+        validation_level: r.package_validation_level,
         created_at: r.package_created_at,
         updated_at: r.package_updated_at,
         version_count: r.package_version_count,
+        star_count: r.package_star_count,
         latest_version: r.package_latest_version,
         when_featured: r.package_when_featured,
         is_archived: r.package_is_archived,
+        noindex: r.package_noindex,
+        keywords: r.package_keywords,
+        docs_landing_page: r.package_docs_landing_page,
+        successor_scope: r.package_successor_scope,
+        successor_name: r.package_successor_name,
+        maintenance_status: r.package_maintenance_status,
+        maintenance_status_updated_at: r.package_maintenance_status_updated_at,
+        unmaintained_suggested_at: r.package_unmaintained_suggested_at,
       };
       let github_repository = if r.package_github_repository_id.is_some() {
         Some(GithubRepository {
           id: r.github_repository_id.unwrap(),
           owner: r.github_repository_owner.unwrap(),
           name: r.github_repository_name.unwrap(),
+          subdirectory: r.github_repository_subdirectory,
           created_at: r.github_repository_created_at.unwrap(),
           updated_at: r.github_repository_updated_at.unwrap(),
         })
@@ -1249,7 +2183,7 @@ impl Database {
       let meta = r.package_version_meta.unwrap_or_default();
       (package, github_repository, meta)
     })
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await?;
 
     Ok((newest, updated, featured))
@@ -1269,7 +2203,7 @@ impl Database {
       WHERE
         package_versions.name IS NOT NULL
     "#)
-      .fetch_one(&self.pool)
+      .fetch_one(&mut *self.acquire().await?)
       .await?;
 
     let users = sqlx::query!(r#"
@@ -1281,7 +2215,7 @@ impl Database {
       FROM
         users;
       "#)
-      .fetch_one(&self.pool)
+      .fetch_one(&mut *self.acquire().await?)
       .await?;
 
     let package_versions =
@@ -1294,7 +2228,7 @@ impl Database {
       FROM
         package_versions;
       "#)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *self.acquire().await?)
         .await?;
 
     Ok(ApiMetrics {
@@ -1338,7 +2272,7 @@ impl Database {
     name: &PackageName,
   ) -> Result<Vec<(PackageVersion, Option<UserPublic>)>> {
     sqlx::query!(
-      r#"SELECT package_versions.scope as "package_version_scope: ScopeName", package_versions.name as "package_version_name: PackageName", package_versions.version as "package_version_version: Version", package_versions.user_id as "package_version_user_id", package_versions.readme_path as "package_version_readme_path: PackagePath", package_versions.exports as "package_version_exports: ExportsMap", package_versions.is_yanked as "package_version_is_yanked", package_versions.uses_npm as "package_version_uses_npm", package_versions.meta as "package_version_meta: PackageVersionMeta", package_versions.updated_at as "package_version_updated_at", package_versions.created_at as "package_version_created_at", package_versions.rekor_log_id as "package_version_rekor_log_id",
+      r#"SELECT package_versions.scope as "package_version_scope: ScopeName", package_versions.name as "package_version_name: PackageName", package_versions.version as "package_version_version: Version", package_versions.user_id as "package_version_user_id", package_versions.readme_path as "package_version_readme_path: PackagePath", package_versions.exports as "package_version_exports: ExportsMap", package_versions.bin as "package_version_bin: BinMap", package_versions.engines as "package_version_engines: PackageVersionEngines", package_versions.keywords as "package_version_keywords", package_versions.unpacked_size as "package_version_unpacked_size", package_versions.file_count as "package_version_file_count", package_versions.is_yanked as "package_version_is_yanked", package_versions.yank_reason_category as "package_version_yank_reason_category: YankReasonCategory", package_versions.yank_reason as "package_version_yank_reason", package_versions.uses_npm as "package_version_uses_npm", package_versions.meta as "package_version_meta: PackageVersionMeta", package_versions.updated_at as "package_version_updated_at", package_versions.created_at as "package_version_created_at", package_versions.rekor_log_id as "package_version_rekor_log_id",
       (SELECT COUNT(*)
         FROM package_versions AS pv
         WHERE pv.scope = package_versions.scope
@@ -1350,7 +2284,15 @@ impl Database {
         FROM version_download_counts_24h as dl
         WHERE dl.scope = package_versions.scope
         AND dl.package = package_versions.name
-        AND dl.version = package_versions.version) as "package_version_lifetime_download_count!",
+        AND dl.version = package_versions.version
+        AND dl.kind != 'web') as "package_version_lifetime_download_count!",
+      (SELECT npm.size
+        FROM npm_tarballs as npm
+        WHERE npm.scope = package_versions.scope
+        AND npm.name = package_versions.name
+        AND npm.version = package_versions.version
+        ORDER BY npm.revision DESC
+        LIMIT 1) as "package_version_npm_tarball_size",
       users.id as "user_id?", users.name as "user_name?", users.avatar_url as "user_avatar_url?", users.github_id as "user_github_id", users.updated_at as "user_updated_at?", users.created_at as "user_created_at?"
       FROM package_versions
       LEFT JOIN users ON package_versions.user_id = users.id
@@ -1366,9 +2308,17 @@ impl Database {
         version: r.package_version_version,
         user_id: r.package_version_user_id,
         exports: r.package_version_exports,
+        bin: r.package_version_bin,
+        engines: r.package_version_engines,
+        keywords: r.package_version_keywords,
         is_yanked: r.package_version_is_yanked,
+        yank_reason_category: r.package_version_yank_reason_category,
+        yank_reason: r.package_version_yank_reason,
         readme_path: r.package_version_readme_path,
         uses_npm: r.package_version_uses_npm,
+        unpacked_size: r.package_version_unpacked_size,
+        file_count: r.package_version_file_count,
+        npm_tarball_size: r.package_version_npm_tarball_size,
         newer_versions_count: r.package_version_newer_versions_count,
         lifetime_download_count: r.package_version_lifetime_download_count,
         meta: r.package_version_meta,
@@ -1394,55 +2344,178 @@ impl Database {
 
       (package_version, user)
     })
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(
-    name = "Database::get_latest_unyanked_version_for_package",
-    skip(self),
-    err
-  )]
-  pub async fn get_latest_unyanked_version_for_package(
+  #[allow(clippy::type_complexity)]
+  #[instrument(name = "Database::list_package_versions_page", skip(self), err)]
+  pub async fn list_package_versions_page(
     &self,
     scope: &ScopeName,
     name: &PackageName,
-  ) -> Result<Option<PackageVersion>> {
-    sqlx::query_as!(
-      PackageVersion,
-      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", user_id, readme_path as "readme_path: PackagePath", exports as "exports: ExportsMap", is_yanked, uses_npm, meta as "meta: PackageVersionMeta", updated_at, created_at, rekor_log_id,
+    include_prereleases: bool,
+    since: Option<DateTime<Utc>>,
+    start: i64,
+    limit: i64,
+  ) -> Result<(usize, Vec<(PackageVersion, Option<UserPublic>)>)> {
+    let mut tx = self.begin().await?;
+
+    let versions = sqlx::query!(
+      r#"SELECT package_versions.scope as "package_version_scope: ScopeName", package_versions.name as "package_version_name: PackageName", package_versions.version as "package_version_version: Version", package_versions.user_id as "package_version_user_id", package_versions.readme_path as "package_version_readme_path: PackagePath", package_versions.exports as "package_version_exports: ExportsMap", package_versions.bin as "package_version_bin: BinMap", package_versions.engines as "package_version_engines: PackageVersionEngines", package_versions.keywords as "package_version_keywords", package_versions.unpacked_size as "package_version_unpacked_size", package_versions.file_count as "package_version_file_count", package_versions.is_yanked as "package_version_is_yanked", package_versions.yank_reason_category as "package_version_yank_reason_category: YankReasonCategory", package_versions.yank_reason as "package_version_yank_reason", package_versions.uses_npm as "package_version_uses_npm", package_versions.meta as "package_version_meta: PackageVersionMeta", package_versions.updated_at as "package_version_updated_at", package_versions.created_at as "package_version_created_at", package_versions.rekor_log_id as "package_version_rekor_log_id",
       (SELECT COUNT(*)
         FROM package_versions AS pv
         WHERE pv.scope = package_versions.scope
         AND pv.name = package_versions.name
         AND pv.version > package_versions.version
         AND pv.version NOT LIKE '%-%'
-        AND pv.is_yanked = false) as "newer_versions_count!",
+        AND pv.is_yanked = false) as "package_version_newer_versions_count!",
       (SELECT COALESCE(SUM(dl.count), 0)
         FROM version_download_counts_24h as dl
         WHERE dl.scope = package_versions.scope
         AND dl.package = package_versions.name
-        AND dl.version = package_versions.version) as "lifetime_download_count!"
+        AND dl.version = package_versions.version
+        AND dl.kind != 'web') as "package_version_lifetime_download_count!",
+      (SELECT npm.size
+        FROM npm_tarballs as npm
+        WHERE npm.scope = package_versions.scope
+        AND npm.name = package_versions.name
+        AND npm.version = package_versions.version
+        ORDER BY npm.revision DESC
+        LIMIT 1) as "package_version_npm_tarball_size",
+      users.id as "user_id?", users.name as "user_name?", users.avatar_url as "user_avatar_url?", users.github_id as "user_github_id", users.updated_at as "user_updated_at?", users.created_at as "user_created_at?"
       FROM package_versions
-      WHERE scope = $1 AND name = $2 AND version NOT LIKE '%-%' AND is_yanked = false
-      ORDER BY version DESC
-      LIMIT 1"#,
+      LEFT JOIN users ON package_versions.user_id = users.id
+      WHERE package_versions.scope = $1 AND package_versions.name = $2
+      AND ($3 OR package_versions.version NOT LIKE '%-%')
+      AND ($4::timestamptz IS NULL OR package_versions.created_at >= $4)
+      ORDER BY package_versions.version DESC
+      OFFSET $5 LIMIT $6"#,
       scope as _,
       name as _,
+      include_prereleases,
+      since,
+      start,
+      limit,
     )
-    .fetch_optional(&self.pool)
-    .await
-  }
-
-  #[instrument(
-    name = "Database::list_latest_package_versions",
-    skip(self),
-    err
-  )]
-  pub async fn list_latest_unyanked_versions_for_package(
-    &self,
-    scope: &ScopeName,
-    name: &PackageName,
+    .map(|r| {
+      let package_version = PackageVersion {
+        scope: r.package_version_scope,
+        name: r.package_version_name,
+        version: r.package_version_version,
+        user_id: r.package_version_user_id,
+        exports: r.package_version_exports,
+        bin: r.package_version_bin,
+        engines: r.package_version_engines,
+        keywords: r.package_version_keywords,
+        is_yanked: r.package_version_is_yanked,
+        yank_reason_category: r.package_version_yank_reason_category,
+        yank_reason: r.package_version_yank_reason,
+        readme_path: r.package_version_readme_path,
+        uses_npm: r.package_version_uses_npm,
+        unpacked_size: r.package_version_unpacked_size,
+        file_count: r.package_version_file_count,
+        npm_tarball_size: r.package_version_npm_tarball_size,
+        newer_versions_count: r.package_version_newer_versions_count,
+        lifetime_download_count: r.package_version_lifetime_download_count,
+        meta: r.package_version_meta,
+        updated_at: r.package_version_updated_at,
+        created_at: r.package_version_created_at,
+        rekor_log_id: r.package_version_rekor_log_id,
+      };
+
+      let user = if r.package_version_user_id.is_some() {
+        let user = UserPublic {
+          id: r.user_id.unwrap(),
+          name: r.user_name.unwrap(),
+          avatar_url: r.user_avatar_url.unwrap(),
+          github_id: r.user_github_id,
+          updated_at: r.user_updated_at.unwrap(),
+          created_at: r.user_created_at.unwrap(),
+        };
+
+        Some(user)
+      } else {
+        None
+      };
+
+      (package_version, user)
+    })
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let total = sqlx::query!(
+      r#"SELECT COUNT(*) as "count!" FROM package_versions
+      WHERE scope = $1 AND name = $2
+      AND ($3 OR version NOT LIKE '%-%')
+      AND ($4::timestamptz IS NULL OR created_at >= $4)"#,
+      scope as _,
+      name as _,
+      include_prereleases,
+      since,
+    )
+    .map(|r| r.count)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((total as usize, versions))
+  }
+
+  #[instrument(
+    name = "Database::get_latest_unyanked_version_for_package",
+    skip(self),
+    err
+  )]
+  pub async fn get_latest_unyanked_version_for_package(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+  ) -> Result<Option<PackageVersion>> {
+    sqlx::query_as!(
+      PackageVersion,
+      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", user_id, readme_path as "readme_path: PackagePath", exports as "exports: ExportsMap", bin as "bin: BinMap", engines as "engines: PackageVersionEngines", keywords, unpacked_size, file_count, is_yanked, yank_reason_category as "yank_reason_category: YankReasonCategory", yank_reason, uses_npm, meta as "meta: PackageVersionMeta", updated_at, created_at, rekor_log_id,
+      (SELECT COUNT(*)
+        FROM package_versions AS pv
+        WHERE pv.scope = package_versions.scope
+        AND pv.name = package_versions.name
+        AND pv.version > package_versions.version
+        AND pv.version NOT LIKE '%-%'
+        AND pv.is_yanked = false) as "newer_versions_count!",
+      (SELECT COALESCE(SUM(dl.count), 0)
+        FROM version_download_counts_24h as dl
+        WHERE dl.scope = package_versions.scope
+        AND dl.package = package_versions.name
+        AND dl.version = package_versions.version
+        AND dl.kind != 'web') as "lifetime_download_count!",
+      (SELECT npm.size
+        FROM npm_tarballs as npm
+        WHERE npm.scope = package_versions.scope
+        AND npm.name = package_versions.name
+        AND npm.version = package_versions.version
+        ORDER BY npm.revision DESC
+        LIMIT 1) as "npm_tarball_size"
+      FROM package_versions
+      WHERE scope = $1 AND name = $2 AND version NOT LIKE '%-%' AND is_yanked = false
+      ORDER BY version DESC
+      LIMIT 1"#,
+      scope as _,
+      name as _,
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::list_latest_package_versions",
+    skip(self),
+    err
+  )]
+  pub async fn list_latest_unyanked_versions_for_package(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
     limit: u32,
   ) -> Result<Vec<Version>> {
     sqlx::query!(
@@ -1456,7 +2529,7 @@ impl Database {
       name as _,
     )
     .map(|r| r.version)
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
@@ -1469,7 +2542,7 @@ impl Database {
   ) -> Result<Option<PackageVersion>> {
     sqlx::query_as!(
       PackageVersion,
-      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", user_id, readme_path as "readme_path: PackagePath", exports as "exports: ExportsMap", is_yanked, uses_npm, meta as "meta: PackageVersionMeta", updated_at, created_at, rekor_log_id,
+      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", user_id, readme_path as "readme_path: PackagePath", exports as "exports: ExportsMap", bin as "bin: BinMap", engines as "engines: PackageVersionEngines", keywords, unpacked_size, file_count, is_yanked, yank_reason_category as "yank_reason_category: YankReasonCategory", yank_reason, uses_npm, meta as "meta: PackageVersionMeta", updated_at, created_at, rekor_log_id,
       (SELECT COUNT(*)
         FROM package_versions AS pv
         WHERE pv.scope = package_versions.scope
@@ -1481,14 +2554,22 @@ impl Database {
         FROM version_download_counts_24h as dl
         WHERE dl.scope = package_versions.scope
         AND dl.package = package_versions.name
-        AND dl.version = package_versions.version) as "lifetime_download_count!"
+        AND dl.version = package_versions.version
+        AND dl.kind != 'web') as "lifetime_download_count!",
+      (SELECT npm.size
+        FROM npm_tarballs as npm
+        WHERE npm.scope = package_versions.scope
+        AND npm.name = package_versions.name
+        AND npm.version = package_versions.version
+        ORDER BY npm.revision DESC
+        LIMIT 1) as "npm_tarball_size"
       FROM package_versions
       WHERE scope = $1 AND name = $2 AND version = $3"#,
       scope as _,
       name as _,
       version as _
     )
-    .fetch_optional(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
@@ -1500,55 +2581,140 @@ impl Database {
     new_package_files: &[NewPackageFile<'_>],
     new_package_version_dependencies: &[NewPackageVersionDependency<'_>],
     new_npm_tarball: NewNpmTarball<'_>,
+    warnings: &PublishingTaskWarnings,
   ) -> Result<PublishingTask> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
+
+    let unpacked_size = new_package_files
+      .iter()
+      .map(|file| file.size as i64)
+      .sum::<i64>();
+    let file_count = new_package_files.len() as i32;
+
+    let previous = sqlx::query!(
+      r#"SELECT version as "version: Version", unpacked_size, file_count,
+      (SELECT npm.size
+        FROM npm_tarballs as npm
+        WHERE npm.scope = package_versions.scope
+        AND npm.name = package_versions.name
+        AND npm.version = package_versions.version
+        ORDER BY npm.revision DESC
+        LIMIT 1) as "npm_tarball_size"
+      FROM package_versions
+      WHERE scope = $1 AND name = $2
+      ORDER BY created_at DESC
+      LIMIT 1"#,
+      new_package_version.scope as _,
+      new_package_version.name as _,
+    )
+    .map(|r| PublishingTaskSizeReportPrevious {
+      version: r.version,
+      unpacked_size: r.unpacked_size,
+      file_count: r.file_count,
+      npm_tarball_size: r.npm_tarball_size,
+    })
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let size_report = PublishingTaskSizeReport {
+      unpacked_size,
+      file_count,
+      npm_tarball_size: new_npm_tarball.size,
+      previous,
+    };
 
     sqlx::query!(
-      r#"INSERT INTO package_versions (scope, name, version, user_id, readme_path, exports, uses_npm, meta)
-      VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+      r#"INSERT INTO package_versions (scope, name, version, user_id, readme_path, exports, bin, engines, uses_npm, meta, unpacked_size, file_count, keywords, license)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"#,
       new_package_version.scope as _,
       new_package_version.name as _,
       new_package_version.version as _,
       new_package_version.user_id as _,
       new_package_version.readme_path as _,
       new_package_version.exports as _,
+      new_package_version.bin as _,
+      new_package_version.engines as _,
       new_package_version.uses_npm as _,
       new_package_version.meta as _,
+      unpacked_size,
+      file_count,
+      new_package_version.keywords,
+      new_package_version.license,
     )
     .execute(&mut *tx)
     .await?;
 
+    // Files and dependencies are inserted with a single statement each, as
+    // packages can contain thousands of files.
+    let mut paths = Vec::with_capacity(new_package_files.len());
+    let mut sizes = Vec::with_capacity(new_package_files.len());
+    let mut checksums = Vec::with_capacity(new_package_files.len());
     for new_package_file in new_package_files {
-      sqlx::query!(
-        r#"INSERT INTO package_files (scope, name, version, path, size, checksum)
-        VALUES ($1, $2, $3, $4, $5, $6)"#,
-        new_package_file.scope as _,
-        new_package_file.name as _,
-        new_package_file.version as _,
-        new_package_file.path as _,
-        new_package_file.size,
-        new_package_file.checksum,
-      )
-      .execute(&mut *tx)
-      .await?;
+      debug_assert_eq!(new_package_file.scope, new_package_version.scope);
+      debug_assert_eq!(new_package_file.name, new_package_version.name);
+      debug_assert_eq!(new_package_file.version, new_package_version.version);
+      paths.push(&**new_package_file.path);
+      sizes.push(new_package_file.size);
+      checksums.push(new_package_file.checksum);
     }
 
+    sqlx::query!(
+      r#"INSERT INTO package_files (scope, name, version, path, size, checksum)
+      SELECT $1, $2, $3, temp.path, temp.size, temp.checksum
+      FROM UNNEST($4::TEXT[], $5::INT[], $6::TEXT[]) as temp(path, size, checksum)"#,
+      new_package_version.scope as _,
+      new_package_version.name as _,
+      new_package_version.version as _,
+      &paths as _,
+      &sizes as _,
+      &checksums as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let mut kinds = Vec::with_capacity(new_package_version_dependencies.len());
+    let mut types = Vec::with_capacity(new_package_version_dependencies.len());
+    let mut names = Vec::with_capacity(new_package_version_dependencies.len());
+    let mut constraints =
+      Vec::with_capacity(new_package_version_dependencies.len());
+    let mut dependency_paths =
+      Vec::with_capacity(new_package_version_dependencies.len());
     for new_package_version_dependency in new_package_version_dependencies {
-      sqlx::query!(
-        r#"INSERT INTO package_version_dependencies (package_scope, package_name, package_version, dependency_kind, dependency_name, dependency_constraint, dependency_path)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
-        new_package_version_dependency.package_scope as _,
-        new_package_version_dependency.package_name as _,
-        new_package_version_dependency.package_version as _,
-        new_package_version_dependency.dependency_kind as _,
-        new_package_version_dependency.dependency_name as _,
-        new_package_version_dependency.dependency_constraint as _,
-        new_package_version_dependency.dependency_path as _,
-      )
-      .execute(&mut *tx)
-      .await?;
+      debug_assert_eq!(
+        new_package_version_dependency.package_scope,
+        new_package_version.scope
+      );
+      debug_assert_eq!(
+        new_package_version_dependency.package_name,
+        new_package_version.name
+      );
+      debug_assert_eq!(
+        new_package_version_dependency.package_version,
+        new_package_version.version
+      );
+      kinds.push(new_package_version_dependency.dependency_kind);
+      types.push(new_package_version_dependency.dependency_type);
+      names.push(new_package_version_dependency.dependency_name);
+      constraints.push(new_package_version_dependency.dependency_constraint);
+      dependency_paths.push(new_package_version_dependency.dependency_path);
     }
 
+    sqlx::query!(
+      r#"INSERT INTO package_version_dependencies (package_scope, package_name, package_version, dependency_kind, dependency_type, dependency_name, dependency_constraint, dependency_path)
+      SELECT $1, $2, $3, temp.dependency_kind, temp.dependency_type, temp.dependency_name, temp.dependency_constraint, temp.dependency_path
+      FROM UNNEST($4::dependency_kind[], $5::dependency_type[], $6::TEXT[], $7::TEXT[], $8::TEXT[]) as temp(dependency_kind, dependency_type, dependency_name, dependency_constraint, dependency_path)"#,
+      new_package_version.scope as _,
+      new_package_version.name as _,
+      new_package_version.version as _,
+      &kinds as _,
+      &types as _,
+      &names as _,
+      &constraints as _,
+      &dependency_paths as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
     sqlx::query!(
       r#"INSERT INTO npm_tarballs (scope, name, version, revision, sha1, sha512, size)
       VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
@@ -1566,10 +2732,12 @@ impl Database {
     let task = sqlx::query_as!(
       PublishingTask,
       r#"UPDATE publishing_tasks
-      SET status = 'processed'
+      SET status = 'processed', size_report = $2, warnings = $3
       WHERE id = $1 AND status = 'processing'
-      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", created_at, updated_at"#,
+      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at"#,
       publishing_task_id,
+      size_report as _,
+      warnings as _,
     )
     .fetch_one(&mut *tx)
     .await?;
@@ -1586,9 +2754,9 @@ impl Database {
   ) -> Result<PackageVersion> {
     sqlx::query_as!(
       PackageVersion,
-      r#"INSERT INTO package_versions (scope, name, version, user_id, readme_path, exports, uses_npm, meta)
-      VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", user_id, readme_path as "readme_path: PackagePath", exports as "exports: ExportsMap", is_yanked, uses_npm, meta as "meta: PackageVersionMeta", updated_at, created_at, rekor_log_id,
+      r#"INSERT INTO package_versions (scope, name, version, user_id, readme_path, exports, bin, engines, uses_npm, meta, keywords, license)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", user_id, readme_path as "readme_path: PackagePath", exports as "exports: ExportsMap", bin as "bin: BinMap", engines as "engines: PackageVersionEngines", keywords, unpacked_size, file_count, is_yanked, yank_reason_category as "yank_reason_category: YankReasonCategory", yank_reason, uses_npm, meta as "meta: PackageVersionMeta", updated_at, created_at, rekor_log_id,
       (SELECT COUNT(*)
         FROM package_versions AS pv
         WHERE pv.scope = package_versions.scope
@@ -1600,17 +2768,29 @@ impl Database {
         FROM version_download_counts_24h as dl
         WHERE dl.scope = package_versions.scope
         AND dl.package = package_versions.name
-        AND dl.version = package_versions.version) as "lifetime_download_count!""#,
+        AND dl.version = package_versions.version
+        AND dl.kind != 'web') as "lifetime_download_count!",
+      (SELECT npm.size
+        FROM npm_tarballs as npm
+        WHERE npm.scope = package_versions.scope
+        AND npm.name = package_versions.name
+        AND npm.version = package_versions.version
+        ORDER BY npm.revision DESC
+        LIMIT 1) as "npm_tarball_size""#,
       new_package_version.scope as _,
       new_package_version.name as _,
       new_package_version.version as _,
       new_package_version.user_id as _,
       new_package_version.readme_path as _,
       new_package_version.exports as _,
+      new_package_version.bin as _,
+      new_package_version.engines as _,
       new_package_version.uses_npm as _,
       new_package_version.meta as _,
+      new_package_version.keywords,
+      new_package_version.license,
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -1621,13 +2801,17 @@ impl Database {
     name: &PackageName,
     version: &Version,
     yank: bool,
+    yank_reason_category: Option<YankReasonCategory>,
+    yank_reason: Option<&str>,
   ) -> Result<PackageVersion> {
     sqlx::query_as!(
       PackageVersion,
       r#"UPDATE package_versions
-      SET is_yanked = $4
+      SET is_yanked = $4,
+        yank_reason_category = CASE WHEN $4 THEN $5::yank_reason_category ELSE NULL END,
+        yank_reason = CASE WHEN $4 THEN $6 ELSE NULL END
       WHERE scope = $1 AND name = $2 AND version = $3
-      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", user_id, readme_path as "readme_path: PackagePath", exports as "exports: ExportsMap", is_yanked, uses_npm, meta as "meta: PackageVersionMeta", updated_at, created_at, rekor_log_id,
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", user_id, readme_path as "readme_path: PackagePath", exports as "exports: ExportsMap", bin as "bin: BinMap", engines as "engines: PackageVersionEngines", keywords, unpacked_size, file_count, is_yanked, yank_reason_category as "yank_reason_category: YankReasonCategory", yank_reason, uses_npm, meta as "meta: PackageVersionMeta", updated_at, created_at, rekor_log_id,
       (SELECT COUNT(*)
         FROM package_versions AS pv
         WHERE pv.scope = package_versions.scope
@@ -1639,16 +2823,57 @@ impl Database {
         FROM version_download_counts_24h as dl
         WHERE dl.scope = package_versions.scope
         AND dl.package = package_versions.name
-        AND dl.version = package_versions.version) as "lifetime_download_count!""#,
+        AND dl.version = package_versions.version
+        AND dl.kind != 'web') as "lifetime_download_count!",
+      (SELECT npm.size
+        FROM npm_tarballs as npm
+        WHERE npm.scope = package_versions.scope
+        AND npm.name = package_versions.name
+        AND npm.version = package_versions.version
+        ORDER BY npm.revision DESC
+        LIMIT 1) as "npm_tarball_size""#,
       scope as _,
       name as _,
       version as _,
-      yank
+      yank,
+      yank_reason_category as _,
+      yank_reason,
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
+  /// Yanks or unyanks several versions of a package at once. Either all
+  /// versions are updated, or none are.
+  #[instrument(name = "Database::yank_package_versions", skip(self), err)]
+  pub async fn yank_package_versions(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    versions: &[Version],
+    yank: bool,
+    yank_reason_category: Option<YankReasonCategory>,
+    yank_reason: Option<&str>,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"UPDATE package_versions
+      SET is_yanked = $4,
+        yank_reason_category = CASE WHEN $4 THEN $5::yank_reason_category ELSE NULL END,
+        yank_reason = CASE WHEN $4 THEN $6 ELSE NULL END
+      WHERE scope = $1 AND name = $2 AND version = ANY($3::TEXT[])"#,
+      scope as _,
+      name as _,
+      versions as _,
+      yank,
+      yank_reason_category as _,
+      yank_reason,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+
+    Ok(())
+  }
+
   #[instrument(name = "Database::get_package_file", skip(self), err)]
   pub async fn get_package_file(
     &self,
@@ -1667,7 +2892,7 @@ impl Database {
       version as _,
       path as _
     )
-    .fetch_optional(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
@@ -1687,7 +2912,7 @@ impl Database {
       name as _,
       version as _
     )
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
@@ -1708,7 +2933,7 @@ impl Database {
       new_package_file.size,
       new_package_file.checksum
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -1723,18 +2948,19 @@ impl Database {
   ) -> Result<PackageVersionDependency> {
     sqlx::query_as!(
       PackageVersionDependency,
-      r#"INSERT INTO package_version_dependencies (package_scope, package_name, package_version, dependency_kind, dependency_name, dependency_constraint, dependency_path)
-      VALUES ($1, $2, $3, $4, $5, $6, $7)
-      RETURNING package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", dependency_kind as "dependency_kind: DependencyKind", dependency_name, dependency_constraint, dependency_path, updated_at, created_at"#,
+      r#"INSERT INTO package_version_dependencies (package_scope, package_name, package_version, dependency_kind, dependency_type, dependency_name, dependency_constraint, dependency_path)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+      RETURNING package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", dependency_kind as "dependency_kind: DependencyKind", dependency_type as "dependency_type: DependencyType", dependency_name, dependency_constraint, dependency_path, updated_at, created_at"#,
       new_package_version_dependency.package_scope as _,
       new_package_version_dependency.package_name as _,
       new_package_version_dependency.package_version as _,
       new_package_version_dependency.dependency_kind as _,
+      new_package_version_dependency.dependency_type as _,
       new_package_version_dependency.dependency_name as _,
       new_package_version_dependency.dependency_constraint as _,
       new_package_version_dependency.dependency_path as _
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -1760,7 +2986,7 @@ impl Database {
       new_npm_tarball.sha512,
       new_npm_tarball.size
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -1798,7 +3024,7 @@ impl Database {
         created_at: row.created_at,
       }
     })
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await?;
     Ok(rows)
   }
@@ -1844,7 +3070,7 @@ impl Database {
         created_at: row.created_at,
       }
     })
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await?;
     Ok(rows)
   }
@@ -1877,7 +3103,7 @@ impl Database {
       target_jsr_name as _,
       target_npm
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await?;
     let target =
       match (row.target_jsr_scope, row.target_jsr_name, row.target_npm) {
@@ -1907,7 +3133,46 @@ impl Database {
       scope as _,
       user_id
     )
-    .fetch_optional(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Lists the names and email addresses of the admins of a scope that have
+  /// an email address and are not blocked.
+  #[instrument(name = "Database::list_scope_admin_emails", skip(self), err)]
+  pub async fn list_scope_admin_emails(
+    &self,
+    scope: &ScopeName,
+  ) -> Result<Vec<(String, String)>> {
+    sqlx::query!(
+      r#"SELECT users.name, users.email as "email!"
+      FROM scope_members
+      JOIN users ON scope_members.user_id = users.id
+      WHERE scope_members.scope = $1 AND scope_members.is_admin = true AND users.email IS NOT NULL AND users.is_blocked = false
+      ORDER BY users.name ASC"#,
+      scope as _
+    )
+    .map(|r| (r.name, r.email))
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::list_scopes_with_outdated_dependencies_email",
+    skip(self),
+    err
+  )]
+  pub async fn list_scopes_with_outdated_dependencies_email(
+    &self,
+  ) -> Result<Vec<ScopeName>> {
+    sqlx::query!(
+      r#"SELECT scope as "scope: ScopeName"
+      FROM scopes
+      WHERE outdated_dependencies_email = true
+      ORDER BY scope ASC"#
+    )
+    .map(|r| r.scope)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
@@ -1943,7 +3208,7 @@ impl Database {
       };
       (scope_member, user)
     })
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
@@ -1962,6 +3227,11 @@ impl Database {
       scopes.publish_attempts_per_week_limit,
       scopes.verify_oidc_actor,
       scopes.require_publishing_from_ci,
+      scopes.validation_level as "validation_level: ValidationLevel",
+      scopes.outdated_dependencies_email,
+      scopes.download_anomaly_email,
+      scopes.min_docs_coverage,
+      scopes.enforce_min_docs_coverage,
       scopes.updated_at,
       scopes.created_at
       FROM scopes
@@ -1969,7 +3239,7 @@ impl Database {
       WHERE user_id = $1"#,
       id
     )
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
@@ -1987,7 +3257,7 @@ impl Database {
       new_scope_invite.target_user_id,
       new_scope_invite.requesting_user_id,
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -2005,7 +3275,7 @@ impl Database {
       new_scope_member.user_id,
       new_scope_member.is_admin,
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
@@ -2050,7 +3320,7 @@ impl Database {
         };
         (scope_invite, target_user, requesting_user)
       })
-      .fetch_all(&self.pool)
+      .fetch_all(&mut *self.acquire().await?)
       .await
   }
 
@@ -2095,7 +3365,7 @@ impl Database {
         };
         (scope_invite, target_user, requesting_user)
       })
-      .fetch_all(&self.pool)
+      .fetch_all(&mut *self.acquire().await?)
       .await
   }
 
@@ -2105,7 +3375,7 @@ impl Database {
     target_user_id: &Uuid,
     scope: &ScopeName,
   ) -> Result<Option<ScopeMember>> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
 
     let res = sqlx::query!(
       r#"DELETE FROM scope_invites WHERE target_user_id = $1 AND scope = $2"#,
@@ -2144,19 +3414,139 @@ impl Database {
       target_user_id,
       scope as _,
     )
-    .execute(&self.pool)
+    .execute(&mut *self.acquire().await?)
     .await?;
 
     Ok(())
   }
 
+  #[instrument(name = "Database::list_scope_publish_freezes", skip(self), err)]
+  pub async fn list_scope_publish_freezes(
+    &self,
+    scope: &ScopeName,
+  ) -> Result<Vec<ScopePublishFreeze>> {
+    sqlx::query_as!(
+      ScopePublishFreeze,
+      r#"SELECT id, scope as "scope: ScopeName", starts_at, ends_at, recurs_weekly, reason, created_by, created_at
+      FROM scope_publish_freezes
+      WHERE scope = $1
+      ORDER BY starts_at ASC"#,
+      scope as _,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::create_scope_publish_freeze", skip(self), err)]
+  pub async fn create_scope_publish_freeze(
+    &self,
+    new_freeze: NewScopePublishFreeze<'_>,
+  ) -> Result<ScopePublishFreeze> {
+    sqlx::query_as!(
+      ScopePublishFreeze,
+      r#"INSERT INTO scope_publish_freezes (scope, starts_at, ends_at, recurs_weekly, reason, created_by)
+      VALUES ($1, $2, $3, $4, $5, $6)
+      RETURNING id, scope as "scope: ScopeName", starts_at, ends_at, recurs_weekly, reason, created_by, created_at"#,
+      new_freeze.scope as _,
+      new_freeze.starts_at,
+      new_freeze.ends_at,
+      new_freeze.recurs_weekly,
+      new_freeze.reason,
+      new_freeze.created_by,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::delete_scope_publish_freeze", skip(self), err)]
+  pub async fn delete_scope_publish_freeze(
+    &self,
+    scope: &ScopeName,
+    id: Uuid,
+  ) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"DELETE FROM scope_publish_freezes WHERE scope = $1 AND id = $2"#,
+      scope as _,
+      id,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+  }
+
+  #[instrument(
+    name = "Database::list_scope_publish_ip_ranges",
+    skip(self),
+    err
+  )]
+  pub async fn list_scope_publish_ip_ranges(
+    &self,
+    scope: &ScopeName,
+  ) -> Result<Vec<ScopePublishIpRange>> {
+    sqlx::query_as!(
+      ScopePublishIpRange,
+      r#"SELECT id, scope as "scope: ScopeName", cidr, description, created_by, created_at
+      FROM scope_publish_ip_ranges
+      WHERE scope = $1
+      ORDER BY cidr ASC"#,
+      scope as _,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::create_scope_publish_ip_range",
+    skip(self),
+    err
+  )]
+  pub async fn create_scope_publish_ip_range(
+    &self,
+    new_range: NewScopePublishIpRange<'_>,
+  ) -> Result<ScopePublishIpRange> {
+    sqlx::query_as!(
+      ScopePublishIpRange,
+      r#"INSERT INTO scope_publish_ip_ranges (scope, cidr, description, created_by)
+      VALUES ($1, $2, $3, $4)
+      RETURNING id, scope as "scope: ScopeName", cidr, description, created_by, created_at"#,
+      new_range.scope as _,
+      new_range.cidr,
+      new_range.description,
+      new_range.created_by,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::delete_scope_publish_ip_range",
+    skip(self),
+    err
+  )]
+  pub async fn delete_scope_publish_ip_range(
+    &self,
+    scope: &ScopeName,
+    id: Uuid,
+  ) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"DELETE FROM scope_publish_ip_ranges WHERE scope = $1 AND id = $2"#,
+      scope as _,
+      id,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+  }
+
   #[instrument(name = "Database::delete_package", skip(self), err)]
   pub async fn delete_package(
     &self,
     scope: &ScopeName,
     name: &PackageName,
   ) -> Result<bool> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
 
     let status = sqlx::query!(
       r#"SELECT count(*) FROM publishing_tasks WHERE package_scope = $1 AND package_name = $2 AND status != 'failure'"#,
@@ -2198,7 +3588,7 @@ impl Database {
 
   #[instrument(name = "Database::delete_scope", skip(self), err)]
   pub async fn delete_scope(&self, scope: &ScopeName) -> Result<bool> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
 
     sqlx::query!(r#"DELETE FROM scope_members WHERE scope = $1"#, scope as _,)
       .execute(&mut *tx)
@@ -2231,11 +3621,11 @@ impl Database {
     }
   }
 
-  pub async fn transfer_scope<'a>(
+  pub async fn transfer_scope(
     &self,
     scope: &ScopeName,
     is_creator: bool,
-    tx: &mut sqlx::Transaction<'a, sqlx::Postgres>,
+    tx: &mut PooledTransaction,
   ) -> Result<Option<ScopeMemberUpdateResult>> {
     let admins_n = sqlx::query!(
       r#"SELECT COUNT(user_id) FROM scope_members WHERE scope = $1 AND is_admin = true"#,
@@ -2289,7 +3679,7 @@ impl Database {
     user_id: Uuid,
     is_admin: bool,
   ) -> Result<ScopeMemberUpdateResult> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
     let maybe_scope_member = sqlx::query!(
       r#"UPDATE scope_members
       SET is_admin = $1
@@ -2338,7 +3728,7 @@ impl Database {
     scope: &ScopeName,
     user_id: Uuid,
   ) -> Result<ScopeMemberUpdateResult> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
 
     let maybe_scope_member = sqlx::query!(
       r#"DELETE FROM scope_members WHERE scope = $1 AND user_id = $2
@@ -2381,12 +3771,12 @@ impl Database {
     &self,
     task: NewPublishingTask<'_>,
   ) -> Result<CreatePublishingTaskResult> {
-    let mut tx = self.pool.begin().await?;
+    let mut tx = self.begin().await?;
 
     // only allow insert if no non status==failure tasks exist
     let already_processing = sqlx::query_as!(
       PublishingTask,
-      r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", created_at, updated_at
+      r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at
       FROM publishing_tasks
       WHERE package_scope = $1 AND package_name = $2 AND package_version = $3 AND status != 'failure'
       LIMIT 1"#,
@@ -2397,23 +3787,84 @@ impl Database {
     .fetch_optional(&mut *tx)
     .await?;
     if let Some(already_processing) = already_processing {
+      if let Some(idempotency_key) = task.idempotency_key {
+        let existing_key = sqlx::query!(
+          r#"SELECT idempotency_key FROM publishing_tasks WHERE id = $1"#,
+          already_processing.id,
+        )
+        .map(|r| r.idempotency_key)
+        .fetch_one(&mut *tx)
+        .await?;
+        if existing_key.as_deref() == Some(idempotency_key) {
+          return Ok(CreatePublishingTaskResult::Retried(already_processing));
+        }
+      }
       return Ok(CreatePublishingTaskResult::Exists(already_processing));
     }
 
-    let task = sqlx::query_as!(
+    if !task.bypass_publish_freezes {
+      // Weekly freezes are active if the time since starts_at, modulo a week,
+      // falls within the length of the window.
+      let active_freeze = sqlx::query_as!(
+        ScopePublishFreeze,
+        r#"SELECT id, scope as "scope: ScopeName", starts_at, ends_at, recurs_weekly, reason, created_by, created_at
+        FROM scope_publish_freezes
+        WHERE scope = $1 AND starts_at <= now() AND (
+          CASE WHEN recurs_weekly
+            THEN mod(extract(epoch FROM now() - starts_at)::bigint, 604800) < extract(epoch FROM ends_at - starts_at)
+            ELSE now() < ends_at
+          END
+        )
+        ORDER BY ends_at DESC
+        LIMIT 1"#,
+        task.package_scope as _,
+      )
+      .fetch_optional(&mut *tx)
+      .await?;
+      if let Some(active_freeze) = active_freeze {
+        return Ok(CreatePublishingTaskResult::PublishFreezeActive(
+          active_freeze,
+        ));
+      }
+    }
+
+    // Another instance may have created a task for the same version since the
+    // check above. The unique index on in-flight tasks lets only one insert
+    // win, and the others attach to the winning task.
+    let inserted = sqlx::query_as!(
       PublishingTask,
-      r#"INSERT INTO publishing_tasks (user_id, package_scope, package_name, package_version, config_file)
-      VALUES ($1, $2, $3, $4, $5)
-      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", created_at, updated_at"#,
+      r#"INSERT INTO publishing_tasks (user_id, package_scope, package_name, package_version, config_file, publish_at, idempotency_key, github_repository_id, github_workflow_run)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+      ON CONFLICT (package_scope, package_name, package_version) WHERE status != 'failure' DO NOTHING
+      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at"#,
       task.user_id,
       task.package_scope as _,
       task.package_name as _,
       task.package_version as _,
       task.config_file as _,
+      task.publish_at,
+      task.idempotency_key,
+      task.github_repository_id,
+      task.github_workflow_run as _,
     )
-    .fetch_one(&mut *tx)
+    .fetch_optional(&mut *tx)
     .await?;
-
+    let Some(task) = inserted else {
+      let winner = sqlx::query_as!(
+        PublishingTask,
+        r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at
+        FROM publishing_tasks
+        WHERE package_scope = $1 AND package_name = $2 AND package_version = $3 AND status != 'failure'"#,
+        task.package_scope as _,
+        task.package_name as _,
+        task.package_version as _
+      )
+      .fetch_one(&mut *tx)
+      .await?;
+      tx.rollback().await?;
+      return Ok(CreatePublishingTaskResult::Raced(winner));
+    };
+
     let publish_attempts_per_week_limit = sqlx::query!(
       r#"
       SELECT publish_attempts_per_week_limit FROM scopes WHERE scope = $1;
@@ -2430,753 +3881,3417 @@ impl Database {
       "#,
       task.package_scope as _,
     )
-    .map(|r| {
-      r.count.unwrap()
-    })
-    .fetch_one(&mut *tx)
+    .map(|r| {
+      r.count.unwrap()
+    })
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if publish_attempts_from_last_week > publish_attempts_per_week_limit as i64
+    {
+      tx.rollback().await?;
+      return Ok(
+        CreatePublishingTaskResult::WeeklyPublishAttemptsLimitExceeded(
+          publish_attempts_per_week_limit,
+        ),
+      );
+    }
+
+    tx.commit().await?;
+
+    Ok(CreatePublishingTaskResult::Created(task))
+  }
+
+  #[instrument(name = "Database::get_publishing_task", skip(self), err)]
+  pub async fn get_publishing_task(
+    &self,
+    id: Uuid,
+  ) -> Result<Option<PublishingTask>> {
+    sqlx::query_as!(
+      PublishingTask,
+      r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at
+      FROM publishing_tasks
+      WHERE id = $1"#,
+      id
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::list_publishing_tasks", skip(self), err)]
+  pub async fn list_publishing_tasks(
+    &self,
+    start: i64,
+    limit: i64,
+    maybe_search_query: Option<&str>,
+  ) -> Result<(usize, Vec<PublishingTask>)> {
+    let mut tx = self.begin().await?;
+    let search = format!("%{}%", maybe_search_query.unwrap_or(""));
+    let publishing_tasks = sqlx::query_as!(
+      PublishingTask,
+      r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at
+      FROM publishing_tasks WHERE package_scope ILIKE $1 OR package_name ILIKE $1 OR package_version ILIKE $1 ORDER BY created_at DESC OFFSET $2 LIMIT $3"#,
+      search,
+      start,
+      limit,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let total_publishing_tasks = sqlx::query!(
+      r#"SELECT COUNT(created_at) FROM publishing_tasks WHERE package_scope ILIKE $1 OR package_name ILIKE $1 OR package_version ILIKE $1;"#,
+      search,
+    )
+    .map(|r| r.count.unwrap())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((total_publishing_tasks as usize, publishing_tasks))
+  }
+
+  /// Gets the GitHub repository whose Actions workflow requested a publishing
+  /// task, if it was requested from GitHub Actions.
+  #[instrument(
+    name = "Database::get_publishing_task_github_repository",
+    skip(self),
+    err
+  )]
+  pub async fn get_publishing_task_github_repository(
+    &self,
+    id: Uuid,
+  ) -> Result<Option<GithubRepository>> {
+    sqlx::query!(
+      r#"SELECT github_repositories.id, github_repositories.owner, github_repositories.name, github_repositories.updated_at, github_repositories.created_at
+      FROM publishing_tasks
+      JOIN github_repositories ON publishing_tasks.github_repository_id = github_repositories.id
+      WHERE publishing_tasks.id = $1"#,
+      id,
+    )
+    .map(|r| GithubRepository {
+      id: r.id,
+      owner: r.owner,
+      name: r.name,
+      subdirectory: None,
+      updated_at: r.updated_at,
+      created_at: r.created_at,
+    })
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Gets the GitHub Actions workflow run that requested a publishing task, if
+  /// it was requested from GitHub Actions.
+  #[instrument(
+    name = "Database::get_publishing_task_github_workflow_run",
+    skip(self),
+    err
+  )]
+  pub async fn get_publishing_task_github_workflow_run(
+    &self,
+    id: Uuid,
+  ) -> Result<Option<GithubWorkflowRun>> {
+    let run = sqlx::query!(
+      r#"SELECT github_workflow_run as "github_workflow_run: GithubWorkflowRun" FROM publishing_tasks WHERE id = $1"#,
+      id,
+    )
+    .map(|r| r.github_workflow_run)
+    .fetch_optional(&mut *self.acquire().await?)
+    .await?;
+    Ok(run.flatten())
+  }
+
+  #[instrument(
+    name = "Database::set_publishing_task_specifiers",
+    skip(self, specifiers),
+    err
+  )]
+  pub async fn set_publishing_task_specifiers(
+    &self,
+    id: Uuid,
+    specifiers: &PublishedSpecifiers,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"UPDATE publishing_tasks SET specifiers = $2 WHERE id = $1"#,
+      id,
+      specifiers as _,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  /// Returns the external specifiers that were found by the analysis of the
+  /// publishing task. `None` for tasks that were processed before specifiers
+  /// were recorded.
+  #[instrument(
+    name = "Database::get_publishing_task_specifiers",
+    skip(self),
+    err
+  )]
+  pub async fn get_publishing_task_specifiers(
+    &self,
+    id: Uuid,
+  ) -> Result<Option<PublishedSpecifiers>> {
+    let specifiers = sqlx::query!(
+      r#"SELECT specifiers as "specifiers: PublishedSpecifiers" FROM publishing_tasks WHERE id = $1"#,
+      id,
+    )
+    .map(|r| r.specifiers)
+    .fetch_optional(&mut *self.acquire().await?)
+    .await?;
+    Ok(specifiers.flatten())
+  }
+
+  #[instrument(
+    name = "Database::list_publishing_tasks_for_package",
+    skip(self),
+    err
+  )]
+  pub async fn list_publishing_tasks_for_package(
+    &self,
+    scope_name: &ScopeName,
+    package_name: &PackageName,
+  ) -> Result<Vec<PublishingTask>> {
+    sqlx::query_as!(
+      PublishingTask,
+      r#"SELECT publishing_tasks.id, publishing_tasks.status as "status: PublishingTaskStatus", publishing_tasks.error as "error: PublishingTaskError", publishing_tasks.size_report as "size_report: PublishingTaskSizeReport", publishing_tasks.warnings as "warnings: PublishingTaskWarnings", publishing_tasks.user_id, publishing_tasks.package_scope as "package_scope: ScopeName", publishing_tasks.package_name as "package_name: PackageName", publishing_tasks.package_version as "package_version: Version", publishing_tasks.config_file as "config_file: PackagePath", publishing_tasks.artifacts_expire_at, publishing_tasks.publish_at, publishing_tasks.created_at, publishing_tasks.updated_at
+      FROM publishing_tasks
+      JOIN packages ON publishing_tasks.package_scope = packages.scope AND publishing_tasks.package_name = packages.name
+      WHERE publishing_tasks.package_scope = $1 AND publishing_tasks.package_name = $2 AND publishing_tasks.created_at >= packages.created_at
+      ORDER BY publishing_tasks.package_version DESC"#,
+      scope_name as _,
+      package_name as _,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::update_publishing_task_status",
+    skip(self),
+    err
+  )]
+  pub async fn update_publishing_task_status(
+    &self,
+    id: Uuid,
+    prev_status: PublishingTaskStatus,
+    new_status: PublishingTaskStatus,
+    new_error: Option<PublishingTaskError>,
+  ) -> Result<PublishingTask> {
+    assert_eq!(
+      new_error.is_some(),
+      new_status == PublishingTaskStatus::Failure,
+      "error must be set if and only if status is failure"
+    );
+    sqlx::query_as!(
+      PublishingTask,
+      r#"UPDATE publishing_tasks
+      SET status = $1, error = $2
+      WHERE id = $3 AND status = $4
+      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at"#,
+      new_status as _,
+      new_error as _,
+      id,
+      prev_status as _,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Fails a publishing task that has not been processed yet with the given
+  /// error. Returns `None` if the task does not exist, or has already been
+  /// processed or failed.
+  #[instrument(name = "Database::cancel_publishing_task", skip(self), err)]
+  pub async fn cancel_publishing_task(
+    &self,
+    id: Uuid,
+    error: PublishingTaskError,
+  ) -> Result<Option<PublishingTask>> {
+    sqlx::query_as!(
+      PublishingTask,
+      r#"UPDATE publishing_tasks
+      SET status = 'failure', error = $2
+      WHERE id = $1 AND status IN ('pending', 'processing', 'scheduled')
+      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at"#,
+      id,
+      error as _,
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Marks a publishing task that has been pending since before the given
+  /// time as retried, by bumping its `updated_at`. Returns `None` if the task
+  /// does not exist, is not pending, or was pending or retried more recently.
+  #[instrument(
+    name = "Database::retry_pending_publishing_task",
+    skip(self),
+    err
+  )]
+  pub async fn retry_pending_publishing_task(
+    &self,
+    id: Uuid,
+    pending_before: DateTime<Utc>,
+  ) -> Result<Option<PublishingTask>> {
+    sqlx::query_as!(
+      PublishingTask,
+      r#"UPDATE publishing_tasks
+      SET updated_at = now()
+      WHERE id = $1 AND status = 'pending' AND updated_at < $2
+      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at"#,
+      id,
+      pending_before,
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::list_due_scheduled_publishing_tasks",
+    skip(self),
+    err
+  )]
+  pub async fn list_due_scheduled_publishing_tasks(
+    &self,
+  ) -> Result<Vec<PublishingTask>> {
+    sqlx::query_as!(
+      PublishingTask,
+      r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at
+      FROM publishing_tasks
+      WHERE status = 'scheduled' AND publish_at <= now()
+      ORDER BY publish_at ASC"#,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::retain_publishing_task_artifacts",
+    skip(self, diagnostic_log),
+    err
+  )]
+  pub async fn retain_publishing_task_artifacts(
+    &self,
+    id: Uuid,
+    diagnostic_log: &str,
+    expires_at: DateTime<Utc>,
+  ) -> Result<PublishingTask> {
+    sqlx::query_as!(
+      PublishingTask,
+      r#"UPDATE publishing_tasks
+      SET artifacts_expire_at = $2, diagnostic_log = $3
+      WHERE id = $1
+      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at"#,
+      id,
+      expires_at,
+      diagnostic_log,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Records the SHA-256 digest of the tarball that was uploaded for a
+  /// publishing task, as a lowercase hex string.
+  #[instrument(
+    name = "Database::set_publishing_task_tarball_sha256",
+    skip(self),
+    err
+  )]
+  pub async fn set_publishing_task_tarball_sha256(
+    &self,
+    id: Uuid,
+    tarball_sha256: &str,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"UPDATE publishing_tasks SET tarball_sha256 = $2 WHERE id = $1"#,
+      id,
+      tarball_sha256,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  #[instrument(
+    name = "Database::get_publishing_task_diagnostic_log",
+    skip(self),
+    err
+  )]
+  pub async fn get_publishing_task_diagnostic_log(
+    &self,
+    id: Uuid,
+  ) -> Result<Option<String>> {
+    sqlx::query!(
+      r#"SELECT diagnostic_log FROM publishing_tasks WHERE id = $1 AND artifacts_expire_at > now()"#,
+      id
+    )
+    .map(|r| r.diagnostic_log)
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+    .map(Option::flatten)
+  }
+
+  #[instrument(
+    name = "Database::list_expired_publishing_task_artifacts",
+    skip(self),
+    err
+  )]
+  pub async fn list_expired_publishing_task_artifacts(
+    &self,
+  ) -> Result<Vec<Uuid>> {
+    sqlx::query!(
+      r#"SELECT id FROM publishing_tasks WHERE artifacts_expire_at <= now()"#
+    )
+    .map(|r| r.id)
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::clear_publishing_task_artifacts",
+    skip(self),
+    err
+  )]
+  pub async fn clear_publishing_task_artifacts(&self, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      r#"UPDATE publishing_tasks
+      SET artifacts_expire_at = NULL, diagnostic_log = NULL
+      WHERE id = $1"#,
+      id
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  /// Deletes a package version and everything that belongs to it, and
+  /// records the deletion so that the version can be restored until
+  /// `restorable_until`. Returns `None` if the version does not exist.
+  #[allow(clippy::too_many_arguments)]
+  #[instrument(name = "Database::delete_package_version", skip(self), err)]
+  pub async fn delete_package_version(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+    publishing_task_id: Uuid,
+    reason: &str,
+    deleted_by: Uuid,
+    restorable_until: DateTime<Utc>,
+  ) -> Result<Option<DeletedPackageVersion>> {
+    let mut tx = self.begin().await?;
+
+    sqlx::query!(
+      r#"DELETE FROM package_version_dependencies WHERE package_scope = $1 AND package_name = $2 AND package_version = $3"#,
+      scope as _,
+      name as _,
+      version as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+      r#"DELETE FROM package_files WHERE scope = $1 AND name = $2 AND version = $3"#,
+      scope as _,
+      name as _,
+      version as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+      r#"DELETE FROM npm_tarballs WHERE scope = $1 AND name = $2 AND version = $3"#,
+      scope as _,
+      name as _,
+      version as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let res = sqlx::query!(
+      r#"DELETE FROM package_versions WHERE scope = $1 AND name = $2 AND version = $3"#,
+      scope as _,
+      name as _,
+      version as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+      return Ok(None);
+    }
+
+    let deleted = sqlx::query_as!(
+      DeletedPackageVersion,
+      r#"INSERT INTO deleted_package_versions (scope, name, version, publishing_task_id, reason, deleted_by, restorable_until)
+      VALUES ($1, $2, $3, $4, $5, $6, $7)
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", publishing_task_id, reason, deleted_by, restorable_until, created_at"#,
+      scope as _,
+      name as _,
+      version as _,
+      publishing_task_id,
+      reason,
+      deleted_by,
+      restorable_until,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(deleted))
+  }
+
+  #[instrument(name = "Database::get_deleted_package_version", skip(self), err)]
+  pub async fn get_deleted_package_version(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Option<DeletedPackageVersion>> {
+    sqlx::query_as!(
+      DeletedPackageVersion,
+      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", publishing_task_id, reason, deleted_by, restorable_until, created_at
+      FROM deleted_package_versions
+      WHERE scope = $1 AND name = $2 AND version = $3"#,
+      scope as _,
+      name as _,
+      version as _,
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Removes the deletion record of a version that is still restorable, and
+  /// resets its successful publishing task to pending, so that processing the
+  /// task publishes the version again. Returns `None` if the version is not
+  /// restorable.
+  #[instrument(
+    name = "Database::restore_deleted_package_version",
+    skip(self),
+    err
+  )]
+  pub async fn restore_deleted_package_version(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Option<DeletedPackageVersion>> {
+    let mut tx = self.begin().await?;
+
+    let Some(deleted) = sqlx::query_as!(
+      DeletedPackageVersion,
+      r#"DELETE FROM deleted_package_versions
+      WHERE scope = $1 AND name = $2 AND version = $3 AND restorable_until > now()
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", publishing_task_id, reason, deleted_by, restorable_until, created_at"#,
+      scope as _,
+      name as _,
+      version as _,
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+      return Ok(None);
+    };
+
+    let res = sqlx::query!(
+      r#"UPDATE publishing_tasks SET status = 'pending', error = NULL
+      WHERE id = $1 AND status = 'success'"#,
+      deleted.publishing_task_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+      return Ok(None);
+    }
+
+    tx.commit().await?;
+
+    Ok(Some(deleted))
+  }
+
+  #[instrument(
+    name = "Database::list_expired_deleted_package_versions",
+    skip(self),
+    err
+  )]
+  pub async fn list_expired_deleted_package_versions(
+    &self,
+  ) -> Result<Vec<DeletedPackageVersion>> {
+    sqlx::query_as!(
+      DeletedPackageVersion,
+      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", publishing_task_id, reason, deleted_by, restorable_until, created_at
+      FROM deleted_package_versions
+      WHERE restorable_until <= now()"#,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Removes the deletion record of a version once its retained tarball was
+  /// deleted. The version can no longer be restored after this.
+  #[instrument(
+    name = "Database::purge_deleted_package_version",
+    skip(self),
+    err
+  )]
+  pub async fn purge_deleted_package_version(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"DELETE FROM deleted_package_versions WHERE scope = $1 AND name = $2 AND version = $3"#,
+      scope as _,
+      name as _,
+      version as _,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  #[instrument(name = "Database::get_oauth_state", skip(self), err)]
+  pub async fn get_oauth_state(
+    &self,
+    csrf_token: &str,
+  ) -> Result<Option<OauthState>> {
+    sqlx::query_as!(
+      OauthState,
+      "SELECT csrf_token, pkce_code_verifier, redirect_url, updated_at, created_at FROM oauth_states WHERE csrf_token = $1",
+      csrf_token
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::insert_oauth_state", skip(self, new_oauth_state), err, fields(oauth_state.csrf_token = %new_oauth_state.csrf_token, oauth_state.redirect_url = %new_oauth_state.redirect_url))]
+  pub async fn insert_oauth_state<'a>(
+    &self,
+    new_oauth_state: NewOauthState<'a>,
+  ) -> Result<OauthState> {
+    sqlx::query_as!(
+      OauthState,
+      "INSERT INTO oauth_states (csrf_token, pkce_code_verifier, redirect_url)
+      VALUES ($1, $2, $3)
+      RETURNING csrf_token, pkce_code_verifier, redirect_url, updated_at, created_at",
+      new_oauth_state.csrf_token,
+      new_oauth_state.pkce_code_verifier,
+      new_oauth_state.redirect_url,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::delete_oauth_state", skip(self), err)]
+  pub async fn delete_oauth_state(
+    &self,
+    csrf_token: &str,
+  ) -> Result<Option<OauthState>> {
+    sqlx::query_as!(
+      OauthState,
+      "DELETE FROM oauth_states
+      WHERE csrf_token = $1
+      RETURNING csrf_token, pkce_code_verifier, redirect_url, updated_at, created_at",
+      csrf_token
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::insert_github_identity", skip(self, new_github_identity), err, fields(github_identity.github_id = new_github_identity.github_id))]
+  pub async fn upsert_github_identity(
+    &self,
+    new_github_identity: NewGithubIdentity,
+  ) -> Result<GithubIdentity> {
+    sqlx::query_as!(
+      GithubIdentity,
+      "INSERT INTO github_identities (github_id, access_token, access_token_expires_at, refresh_token, refresh_token_expires_at) VALUES ($1, $2, $3, $4, $5)
+      ON CONFLICT (github_id) DO
+      UPDATE SET access_token = $2, access_token_expires_at = $3, refresh_token = $4, refresh_token_expires_at = $5
+      RETURNING github_id, access_token, access_token_expires_at, refresh_token, refresh_token_expires_at, updated_at, created_at",
+      new_github_identity.github_id,
+      new_github_identity.access_token,
+      new_github_identity.access_token_expires_at,
+      new_github_identity.refresh_token,
+      new_github_identity.refresh_token_expires_at,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::get_github_identity", skip(self), err)]
+  pub async fn get_github_identity(
+    &self,
+    github_id: i64,
+  ) -> Result<GithubIdentity> {
+    sqlx::query_as!(
+      GithubIdentity,
+      "SELECT github_id, access_token, access_token_expires_at, refresh_token, refresh_token_expires_at, updated_at, created_at
+      FROM github_identities
+      WHERE github_id = $1",
+      github_id
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::insert_token", skip(self, new_token), err, fields(token.r#type = ?new_token.r#type))]
+  pub async fn insert_token(&self, new_token: NewToken) -> Result<Token> {
+    sqlx::query_as!(
+      Token,
+      r#"INSERT INTO tokens (hash, user_id, type, description, expires_at, permissions)
+      VALUES ($1, $2, $3, $4, $5, $6)
+      RETURNING id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at"#,
+      new_token.hash,
+      new_token.user_id,
+      new_token.r#type as _,
+      new_token.description,
+      new_token.expires_at,
+      new_token.permissions as _,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::get_token_by_hash", skip(self), err)]
+  pub async fn get_token_by_hash(&self, hash: &str) -> Result<Option<Token>> {
+    sqlx::query_as!(Token, r#"SELECT id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at FROM tokens WHERE hash = $1"#, hash)
+      .fetch_optional(&mut *self.acquire().await?)
+      .await
+  }
+
+  #[instrument(name = "Database::get_token", skip(self), err)]
+  pub async fn get_token(&self, id: Uuid) -> Result<Option<Token>> {
+    sqlx::query_as!(Token, r#"SELECT id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at FROM tokens WHERE id = $1"#, id)
+      .fetch_optional(&mut *self.acquire().await?)
+      .await
+  }
+
+  #[instrument(name = "Database::list_token", skip(self), err)]
+  pub async fn list_tokens(&self, user_id: Uuid) -> Result<Vec<Token>> {
+    // list a user's tokens where the expiration date is at most 1 day in the past
+    sqlx::query_as!(
+      Token,
+      r#"SELECT id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at
+      FROM tokens
+      WHERE user_id = $1 AND type <> 'scope' AND (expires_at > now() - interval '1 day' OR expires_at IS NULL)
+      ORDER BY expires_at DESC NULLS FIRST, created_at DESC
+      "#,
+      user_id
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::delete_token", skip(self), err)]
+  pub async fn delete_token(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"DELETE FROM tokens WHERE user_id = $1 ANd id = $2 AND type <> 'scope'"#,
+      user_id,
+      id
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(res.rows_affected() > 0)
+  }
+
+  /// Lists the scope tokens of a scope, whichever admin created them, where
+  /// the expiration date is at most 1 day in the past.
+  #[instrument(name = "Database::list_scope_tokens", skip(self), err)]
+  pub async fn list_scope_tokens(
+    &self,
+    scope: &ScopeName,
+  ) -> Result<Vec<Token>> {
+    sqlx::query_as!(
+      Token,
+      r#"SELECT id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at
+      FROM tokens
+      WHERE type = 'scope' AND permissions->0->>'scope' = $1 AND (expires_at > now() - interval '1 day' OR expires_at IS NULL)
+      ORDER BY expires_at DESC NULLS FIRST, created_at DESC
+      "#,
+      scope as _,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::delete_scope_token", skip(self), err)]
+  pub async fn delete_scope_token(
+    &self,
+    scope: &ScopeName,
+    id: Uuid,
+  ) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"DELETE FROM tokens WHERE id = $1 AND type = 'scope' AND permissions->0->>'scope' = $2"#,
+      id,
+      scope as _,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(res.rows_affected() > 0)
+  }
+
+  #[instrument(name = "Database::insert_api_key", skip(self, hash), err)]
+  pub async fn insert_api_key(
+    &self,
+    user_id: Uuid,
+    hash: &str,
+    name: &str,
+  ) -> Result<ApiKey> {
+    sqlx::query_as!(
+      ApiKey,
+      r#"INSERT INTO api_keys (user_id, hash, name) VALUES ($1, $2, $3)
+      RETURNING id, user_id, hash, name, request_count, last_used_at, updated_at, created_at"#,
+      user_id,
+      hash,
+      name
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::get_api_key_by_hash", skip(self, hash), err)]
+  pub async fn get_api_key_by_hash(
+    &self,
+    hash: &str,
+  ) -> Result<Option<ApiKey>> {
+    sqlx::query_as!(
+      ApiKey,
+      r#"SELECT id, user_id, hash, name, request_count, last_used_at, updated_at, created_at
+      FROM api_keys WHERE hash = $1"#,
+      hash
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::list_api_keys", skip(self), err)]
+  pub async fn list_api_keys(&self, user_id: Uuid) -> Result<Vec<ApiKey>> {
+    sqlx::query_as!(
+      ApiKey,
+      r#"SELECT id, user_id, hash, name, request_count, last_used_at, updated_at, created_at
+      FROM api_keys WHERE user_id = $1
+      ORDER BY created_at DESC"#,
+      user_id
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::delete_api_key", skip(self), err)]
+  pub async fn delete_api_key(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"DELETE FROM api_keys WHERE user_id = $1 AND id = $2"#,
+      user_id,
+      id
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(res.rows_affected() > 0)
+  }
+
+  /// Adds the buffered request counts of API keys to their totals.
+  #[instrument(
+    name = "Database::record_api_key_usages",
+    skip(self, usages),
+    err,
+    fields(usages = usages.len())
+  )]
+  pub async fn record_api_key_usages(
+    &self,
+    usages: &[(Uuid, i64)],
+  ) -> Result<()> {
+    let (ids, counts): (Vec<Uuid>, Vec<i64>) = usages.iter().copied().unzip();
+    sqlx::query!(
+      r#"UPDATE api_keys
+      SET request_count = api_keys.request_count + usages.count, last_used_at = now()
+      FROM UNNEST($1::uuid[], $2::bigint[]) AS usages(id, count)
+      WHERE api_keys.id = usages.id"#,
+      &ids,
+      &counts
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  /// Adds the buffered request counts of deprecated routes to the usage
+  /// report.
+  #[instrument(
+    name = "Database::record_deprecated_route_usages",
+    skip(self, usages),
+    err,
+    fields(usages = usages.len())
+  )]
+  pub async fn record_deprecated_route_usages(
+    &self,
+    usages: &[(&DeprecatedRouteUsageKey, i64)],
+  ) -> Result<()> {
+    let mut tx = self.begin().await?;
+    for (key, count) in usages {
+      sqlx::query!(
+        r#"INSERT INTO deprecated_route_usages (route, token_id, user_id, user_agent, request_count)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (route, token_id, user_id, user_agent)
+        DO UPDATE SET request_count = deprecated_route_usages.request_count + $5, last_used_at = now()"#,
+        key.route,
+        key.token_id,
+        key.user_id,
+        key.user_agent,
+        count
+      )
+      .execute(&mut *tx)
+      .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+  }
+
+  /// Lists the consumers of deprecated routes, the most recently active first.
+  #[instrument(
+    name = "Database::list_deprecated_route_usages",
+    skip(self),
+    err
+  )]
+  pub async fn list_deprecated_route_usages(
+    &self,
+  ) -> Result<Vec<DeprecatedRouteUsage>> {
+    sqlx::query_as!(
+      DeprecatedRouteUsage,
+      r#"SELECT route, token_id, user_id, user_agent, request_count, first_used_at, last_used_at
+      FROM deprecated_route_usages
+      ORDER BY route, last_used_at DESC"#
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Inserts a new OIDC publish token, and deletes tokens that have expired.
+  #[instrument(
+    name = "Database::insert_oidc_publish_token",
+    skip(self, new_token),
+    err
+  )]
+  pub async fn insert_oidc_publish_token(
+    &self,
+    new_token: NewOidcPublishToken<'_>,
+  ) -> Result<OidcPublishToken> {
+    let mut tx = self.begin().await?;
+
+    sqlx::query!("DELETE FROM oidc_publish_tokens WHERE expires_at < now()")
+      .execute(&mut *tx)
+      .await?;
+
+    let token = sqlx::query_as!(
+      OidcPublishToken,
+      r#"INSERT INTO oidc_publish_tokens (hash, repository_id, user_id, scope, name, version, tarball_hash, expires_at, github_workflow_run)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+      RETURNING id, hash, repository_id, github_workflow_run as "github_workflow_run: GithubWorkflowRun", user_id, scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", tarball_hash, expires_at, created_at"#,
+      new_token.hash,
+      new_token.repository_id,
+      new_token.user_id,
+      new_token.scope as _,
+      new_token.name as _,
+      new_token.version as _,
+      new_token.tarball_hash,
+      new_token.expires_at,
+      new_token.github_workflow_run as _,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(token)
+  }
+
+  #[instrument(
+    name = "Database::get_oidc_publish_token_by_hash",
+    skip(self, hash),
+    err
+  )]
+  pub async fn get_oidc_publish_token_by_hash(
+    &self,
+    hash: &str,
+  ) -> Result<Option<OidcPublishToken>> {
+    sqlx::query_as!(
+      OidcPublishToken,
+      r#"SELECT id, hash, repository_id, github_workflow_run as "github_workflow_run: GithubWorkflowRun", user_id, scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", tarball_hash, expires_at, created_at
+      FROM oidc_publish_tokens WHERE hash = $1"#,
+      hash
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::create_authorization",
+    skip(self, new_authorization),
+    err
+  )]
+  pub async fn create_authorization(
+    &self,
+    new_authorization: NewAuthorization<'_>,
+  ) -> Result<Authorization> {
+    sqlx::query_as!(
+      Authorization,
+      r#"INSERT INTO authorizations (exchange_token, code, challenge, permissions, expires_at)
+      VALUES ($1, $2, $3, $4, $5)
+      RETURNING exchange_token, code, challenge, permissions "permissions: _", approved, user_id, expires_at, created_at, updated_at"#,
+      new_authorization.exchange_token,
+      new_authorization.code,
+      new_authorization.challenge,
+      new_authorization.permissions as _,
+      new_authorization.expires_at,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::get_authorization_by_code", skip(self), err)]
+  pub async fn get_authorization_by_code(
+    &self,
+    code: &str,
+  ) -> Result<Option<Authorization>> {
+    sqlx::query_as!(
+      Authorization,
+      r#"SELECT exchange_token, code, challenge, permissions "permissions: _", approved, user_id, expires_at, created_at, updated_at
+      FROM authorizations
+      WHERE code = $1"#,
+      code
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::get_authorization_by_exchange_token",
+    skip(self, exchange_token),
+    err
+  )]
+  pub async fn get_authorization_by_exchange_token_and_remove_if_complete(
+    &self,
+    exchange_token: &str,
+  ) -> Result<Option<Authorization>> {
+    let mut tx = self.begin().await?;
+
+    let maybe_authorization = sqlx::query_as!(
+      Authorization,
+      r#"DELETE FROM authorizations
+      WHERE exchange_token = $1
+      RETURNING exchange_token, code, challenge, permissions "permissions: _", approved, user_id, expires_at, created_at, updated_at"#,
+      exchange_token
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(authorization) = &maybe_authorization {
+      if authorization.user_id.is_some() {
+        tx.commit().await?;
+      }
+    }
+
+    Ok(maybe_authorization)
+  }
+
+  #[instrument(name = "Database::update_authorization", skip(self), err)]
+  pub async fn update_authorization(
+    &self,
+    code: &str,
+    approved: bool,
+    user_id: Uuid,
+  ) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"UPDATE authorizations
+      SET approved = $1, user_id = $2
+      WHERE code = $3 AND approved IS NULL"#,
+      approved,
+      user_id,
+      code
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(res.rows_affected() > 0)
+  }
+
+  #[instrument(
+    name = "Database::list_package_version_dependencies",
+    skip(self),
+    err
+  )]
+  pub async fn list_package_version_dependencies(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Vec<PackageVersionDependency>> {
+    sqlx::query_as!(
+      PackageVersionDependency,
+      r#"SELECT package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", dependency_kind as "dependency_kind: DependencyKind", dependency_type as "dependency_type: DependencyType", dependency_name, dependency_constraint, dependency_path, updated_at, created_at
+      FROM package_version_dependencies
+      WHERE package_scope = $1 AND package_name = $2 AND package_version = $3
+      ORDER BY dependency_kind ASC, dependency_name ASC, dependency_constraint ASC, dependency_path ASC"#,
+      scope as _,
+      name as _,
+      version as _
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Lists the names of the JSR packages that the given package version
+  /// depends on, and that have been archived.
+  #[instrument(
+    name = "Database::list_archived_package_version_dependencies",
+    skip(self),
+    err
+  )]
+  pub async fn list_archived_package_version_dependencies(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Vec<String>> {
+    sqlx::query!(
+      r#"SELECT DISTINCT package_version_dependencies.dependency_name
+      FROM package_version_dependencies
+      JOIN packages ON package_version_dependencies.dependency_name = '@' || packages.scope || '/' || packages.name
+      WHERE package_version_dependencies.package_scope = $1 AND package_version_dependencies.package_name = $2 AND package_version_dependencies.package_version = $3 AND package_version_dependencies.dependency_kind = 'jsr' AND packages.is_archived = true
+      ORDER BY package_version_dependencies.dependency_name"#,
+      scope as _,
+      name as _,
+      version as _
+    )
+    .map(|r| r.dependency_name)
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Lists all versions of the JSR packages that the given package version
+  /// depends on, together with the dependency name and whether the version
+  /// has been yanked.
+  #[instrument(
+    name = "Database::list_package_version_jsr_dependency_versions",
+    skip(self),
+    err
+  )]
+  pub async fn list_package_version_jsr_dependency_versions(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Vec<(String, Version, bool)>> {
+    sqlx::query!(
+      r#"SELECT DISTINCT package_version_dependencies.dependency_name, package_versions.version as "version: Version", package_versions.is_yanked
+      FROM package_version_dependencies
+      JOIN package_versions ON package_version_dependencies.dependency_name = '@' || package_versions.scope || '/' || package_versions.name
+      WHERE package_version_dependencies.package_scope = $1 AND package_version_dependencies.package_name = $2 AND package_version_dependencies.package_version = $3 AND package_version_dependencies.dependency_kind = 'jsr'"#,
+      scope as _,
+      name as _,
+      version as _
+    )
+    .map(|r| (r.dependency_name, r.version, r.is_yanked))
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Gets the repository and commit that a package version was built from,
+  /// according to its provenance statement.
+  #[instrument(name = "Database::get_package_version_source", skip(self), err)]
+  pub async fn get_package_version_source(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Option<PackageVersionSource>> {
+    let source = sqlx::query!(
+      r#"SELECT source_repository, source_commit FROM package_versions WHERE scope = $1 AND name = $2 AND version = $3"#,
+      scope as _,
+      name as _,
+      version as _
+    )
+    .map(|r| {
+      Some(PackageVersionSource {
+        repository: r.source_repository?,
+        commit: r.source_commit?,
+      })
+    })
+    .fetch_optional(&mut *self.acquire().await?)
+    .await?;
+    Ok(source.flatten())
+  }
+
+  /// Gets the license that was detected for a package version when it was
+  /// published. Returns `None` if the version does not exist, or if no license
+  /// was detected.
+  #[instrument(name = "Database::get_package_version_license", skip(self), err)]
+  pub async fn get_package_version_license(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Option<String>> {
+    let license = sqlx::query!(
+      r#"SELECT license FROM package_versions WHERE scope = $1 AND name = $2 AND version = $3"#,
+      scope as _,
+      name as _,
+      version as _
+    )
+    .map(|r| r.license)
+    .fetch_optional(&mut *self.acquire().await?)
+    .await?;
+    Ok(license.flatten())
+  }
+
+  #[instrument(name = "Database::list_package_dependents", skip(self), err)]
+  pub async fn list_package_dependents(
+    &self,
+    kind: DependencyKind,
+    name: &str,
+    start: i64,
+    limit: i64,
+    versions_per_package_limit: i64,
+  ) -> Result<(usize, Vec<Dependent>)> {
+    let mut tx = self.begin().await?;
+    let dependents = sqlx::query_as!(
+      Dependent,
+      r#"
+      SELECT
+        package_scope as "scope: ScopeName",
+        package_name as "name: PackageName",
+        (ARRAY_AGG(DISTINCT package_version))[:$5] as "versions!: Vec<Version>",
+        COUNT(DISTINCT package_version) as "total_versions!"
+      FROM
+        package_version_dependencies
+      WHERE
+        dependency_kind = $1 AND dependency_name = $2
+      GROUP BY package_scope, package_name
+      ORDER BY package_scope ASC, package_name ASC OFFSET $3 LIMIT $4;
+      "#,
+      kind as _,
+      name,
+      start,
+      limit,
+      versions_per_package_limit as i32,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let total_unique_package_dependents = sqlx::query!(
+      r#"SELECT dependent_count FROM package_dependent_counts
+      WHERE dependency_kind = $1 AND dependency_name = $2;"#,
+      kind as _,
+      name,
+    )
+    .map(|r| r.dependent_count)
+    .fetch_optional(&mut *tx)
+    .await?
+    .unwrap_or(0);
+
+    tx.commit().await?;
+
+    Ok((total_unique_package_dependents as usize, dependents))
+  }
+
+  /// Recounts the dependents of every package, correcting counts that the
+  /// triggers on `package_version_dependencies` got wrong because of
+  /// concurrent publishes. Returns how many counts were corrected.
+  #[instrument(
+    name = "Database::reconcile_package_dependent_counts",
+    skip(self),
+    err
+  )]
+  pub async fn reconcile_package_dependent_counts(&self) -> Result<u64> {
+    let mut tx = self.begin().await?;
+
+    let updated = sqlx::query!(
+      r#"INSERT INTO package_dependent_counts (dependency_kind, dependency_name, dependent_count)
+      SELECT dependency_kind, dependency_name, COUNT(DISTINCT (package_scope, package_name))
+      FROM package_version_dependencies
+      GROUP BY dependency_kind, dependency_name
+      ON CONFLICT (dependency_kind, dependency_name)
+      DO UPDATE SET dependent_count = EXCLUDED.dependent_count
+      WHERE package_dependent_counts.dependent_count <> EXCLUDED.dependent_count"#
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let removed = sqlx::query!(
+      r#"UPDATE package_dependent_counts SET dependent_count = 0
+      WHERE dependent_count <> 0 AND NOT EXISTS (
+        SELECT 1 FROM package_version_dependencies
+        WHERE package_version_dependencies.dependency_kind = package_dependent_counts.dependency_kind
+        AND package_version_dependencies.dependency_name = package_dependent_counts.dependency_name
+      )"#
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(updated + removed)
+  }
+
+  #[instrument(name = "Database::check_bad_word", skip(self), err)]
+  pub async fn check_is_bad_word(&self, word: &str) -> Result<bool> {
+    let res = sqlx::query!("SELECT * FROM bad_words WHERE word = $1", word)
+      .fetch_optional(&mut *self.acquire().await?)
+      .await?;
+    Ok(res.is_some())
+  }
+
+  #[instrument(name = "Database::add_bad_word_for_test", skip(self), err)]
+  pub async fn add_bad_word_for_test(&self, word: &str) -> Result<()> {
+    sqlx::query!("INSERT INTO bad_words (word) VALUES ($1)", word)
+      .execute(&mut *self.acquire().await?)
+      .await?;
+
+    Ok(())
+  }
+
+  #[instrument(
+    name = "Database::get_latest_npm_tarball_for_version",
+    skip(self),
+    err
+  )]
+  pub async fn get_latest_npm_tarball_for_version(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Option<NpmTarball>> {
+    sqlx::query_as!(
+      NpmTarball,
+      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", revision, sha1, sha512, size, updated_at, created_at
+      FROM npm_tarballs
+      WHERE scope = $1 AND name = $2 AND version = $3
+      ORDER BY revision DESC
+      LIMIT 1"#,
+      scope as _,
+      name as _,
+      version as _
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::get_latest_npm_tarball_for_version",
+    skip(self),
+    err
+  )]
+  pub async fn get_npm_tarball(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+    revision: i32,
+  ) -> Result<Option<NpmTarball>> {
+    sqlx::query_as!(
+      NpmTarball,
+      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", revision, sha1, sha512, size, updated_at, created_at
+      FROM npm_tarballs
+      WHERE scope = $1 AND name = $2 AND version = $3 AND revision = $4
+      LIMIT 1"#,
+      scope as _,
+      name as _,
+      version as _,
+      revision,
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Forgets that the npm tarball of a revision was built, so that it is built
+  /// again.
+  #[instrument(name = "Database::delete_npm_tarball", skip(self), err)]
+  pub async fn delete_npm_tarball(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+    revision: i32,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"DELETE FROM npm_tarballs
+      WHERE scope = $1 AND name = $2 AND version = $3 AND revision = $4"#,
+      scope as _,
+      name as _,
+      version as _,
+      revision,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  #[instrument(
+    name = "Database::record_npm_tarball_build_requests",
+    skip(self, requests),
+    err
+  )]
+  pub async fn record_npm_tarball_build_requests(
+    &self,
+    requests: Vec<NpmTarballBuildRequest>,
+  ) -> Result<()> {
+    if requests.is_empty() {
+      return Ok(());
+    }
+
+    let mut tx = self.begin().await?;
+    insert_npm_tarball_build_requests(&mut tx, requests).await?;
+    tx.commit().await?;
+
+    Ok(())
+  }
+
+  #[instrument(
+    name = "Database::get_npm_not_found_scraped_until",
+    skip(self),
+    err
+  )]
+  pub async fn get_npm_not_found_scraped_until(
+    &self,
+  ) -> Result<Option<DateTime<Utc>>> {
+    sqlx::query!(r#"SELECT scraped_until FROM npm_not_found_scrape_checkpoint"#)
+      .map(|r| r.scraped_until)
+      .fetch_optional(&mut *self.acquire().await?)
+      .await
+  }
+
+  /// Records the npm requests resulting in a 404 that were counted from the
+  /// request logs up to `scraped_until`. The counts of all packages are
+  /// multiplied by `decay` first, so that packages that are no longer
+  /// requested lose their priority over time.
+  #[instrument(
+    name = "Database::record_npm_not_found_requests",
+    skip(self, requests),
+    err
+  )]
+  pub async fn record_npm_not_found_requests(
+    &self,
+    requests: Vec<NpmTarballBuildRequest>,
+    scraped_until: DateTime<Utc>,
+    decay: f64,
+  ) -> Result<()> {
+    let mut tx = self.begin().await?;
+
+    sqlx::query!(
+      r#"UPDATE npm_tarball_build_requests
+      SET request_count = floor(request_count * $1::double precision)::integer
+      WHERE request_count > 0"#,
+      decay,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if !requests.is_empty() {
+      insert_npm_tarball_build_requests(&mut tx, requests).await?;
+    }
+
+    sqlx::query!(
+      r#"INSERT INTO npm_not_found_scrape_checkpoint (scraped_until)
+      VALUES ($1)
+      ON CONFLICT (id) DO UPDATE SET scraped_until = EXCLUDED.scraped_until"#,
+      scraped_until,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+  }
+
+  /// Claim up to `limit` package versions that do not have a npm tarball for
+  /// the given revision yet. Versions of packages that were recently requested
+  /// from the npm endpoint are claimed first, the rest in publish order.
+  ///
+  /// A claim is held until it is released, or until `claim_duration` passes,
+  /// at which point another worker may pick the version up again. Concurrent
+  /// callers never receive the same version while a claim is held.
+  #[instrument(name = "Database::claim_missing_npm_tarballs", skip(self), err)]
+  pub async fn claim_missing_npm_tarballs(
+    &self,
+    current_revision: i32,
+    limit: i64,
+    claim_duration: chrono::Duration,
+  ) -> Result<Vec<(ScopeName, PackageName, Version)>> {
+    let claimed_until = Utc::now() + claim_duration;
+    sqlx::query!(
+      r#"
+      WITH candidates AS (
+        SELECT package_versions.scope, package_versions.name, package_versions.version
+        FROM package_versions
+        LEFT JOIN npm_tarball_build_requests ON npm_tarball_build_requests.scope = package_versions.scope AND npm_tarball_build_requests.name = package_versions.name AND npm_tarball_build_requests.last_requested_at > now() - INTERVAL '1 day'
+        WHERE NOT EXISTS (
+          SELECT 1
+          FROM npm_tarballs
+          WHERE npm_tarballs.scope = package_versions.scope AND npm_tarballs.name = package_versions.name AND npm_tarballs.version = package_versions.version AND npm_tarballs.revision = $1
+        ) AND NOT EXISTS (
+          SELECT 1
+          FROM npm_tarball_build_claims
+          WHERE npm_tarball_build_claims.scope = package_versions.scope AND npm_tarball_build_claims.name = package_versions.name AND npm_tarball_build_claims.version = package_versions.version AND npm_tarball_build_claims.revision = $1 AND npm_tarball_build_claims.claimed_until > now()
+        )
+        ORDER BY npm_tarball_build_requests.request_count DESC NULLS LAST, package_versions.created_at ASC
+        LIMIT $2
+      )
+      INSERT INTO npm_tarball_build_claims (scope, name, version, revision, claimed_until)
+      SELECT scope, name, version, $1, $3 FROM candidates
+      ON CONFLICT (scope, name, version, revision) DO UPDATE
+      SET claimed_until = EXCLUDED.claimed_until
+      WHERE npm_tarball_build_claims.claimed_until <= now()
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version"
+      "#,
+      current_revision,
+      limit,
+      claimed_until,
+    )
+    .map(|r| (r.scope, r.name, r.version))
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Claim a single package version for building its npm tarball. Returns
+  /// `false` if another worker currently holds the claim.
+  #[instrument(name = "Database::claim_npm_tarball_build", skip(self), err)]
+  pub async fn claim_npm_tarball_build(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+    revision: i32,
+    claim_duration: chrono::Duration,
+  ) -> Result<bool> {
+    let claimed_until = Utc::now() + claim_duration;
+    let res = sqlx::query!(
+      r#"INSERT INTO npm_tarball_build_claims (scope, name, version, revision, claimed_until)
+      VALUES ($1, $2, $3, $4, $5)
+      ON CONFLICT (scope, name, version, revision) DO UPDATE
+      SET claimed_until = EXCLUDED.claimed_until
+      WHERE npm_tarball_build_claims.claimed_until <= now()"#,
+      scope as _,
+      name as _,
+      version as _,
+      revision,
+      claimed_until,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(res.rows_affected() > 0)
+  }
+
+  #[instrument(
+    name = "Database::release_npm_tarball_build_claim",
+    skip(self),
+    err
+  )]
+  pub async fn release_npm_tarball_build_claim(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+    revision: i32,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"DELETE FROM npm_tarball_build_claims
+      WHERE scope = $1 AND name = $2 AND version = $3 AND revision <= $4"#,
+      scope as _,
+      name as _,
+      version as _,
+      revision,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  #[instrument(
+    name = "Database::get_npm_tarball_build_progress",
+    skip(self),
+    err
+  )]
+  pub async fn get_npm_tarball_build_progress(
+    &self,
+    current_revision: i32,
+  ) -> Result<NpmTarballBuildProgress> {
+    sqlx::query!(
+      r#"
+      SELECT
+        (SELECT COUNT(*) FROM package_versions) as "total_versions!",
+        (SELECT COUNT(*) FROM npm_tarballs WHERE revision = $1) as "built_versions!",
+        (SELECT COUNT(*) FROM npm_tarball_build_claims WHERE revision = $1 AND claimed_until > now()) as "claimed_versions!",
+        (SELECT COUNT(*)
+          FROM npm_tarball_build_requests
+          WHERE last_requested_at > now() - INTERVAL '1 day'
+          AND EXISTS (
+            SELECT 1
+            FROM package_versions
+            WHERE package_versions.scope = npm_tarball_build_requests.scope AND package_versions.name = npm_tarball_build_requests.name
+            AND NOT EXISTS (
+              SELECT 1
+              FROM npm_tarballs
+              WHERE npm_tarballs.scope = package_versions.scope AND npm_tarballs.name = package_versions.name AND npm_tarballs.version = package_versions.version AND npm_tarballs.revision = $1
+            )
+          )) as "requested_packages!",
+        (SELECT MAX(created_at) FROM npm_tarballs WHERE revision = $1) as "last_built_at"
+      "#,
+      current_revision,
+    )
+    .map(|r| NpmTarballBuildProgress {
+      revision: current_revision,
+      total_versions: r.total_versions,
+      built_versions: r.built_versions,
+      claimed_versions: r.claimed_versions,
+      requested_packages: r.requested_packages,
+      last_built_at: r.last_built_at,
+    })
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Only publishing tasks and package versions that were created in the last
+  /// day are counted towards the backlogs. Older publishing tasks are
+  /// considered abandoned.
+  #[instrument(name = "Database::get_registry_backlogs", skip(self), err)]
+  pub async fn get_registry_backlogs(
+    &self,
+    current_npm_tarball_revision: i32,
+  ) -> Result<RegistryBacklogs> {
+    sqlx::query!(
+      r#"
+      WITH recent_tasks AS (
+        SELECT status, updated_at
+        FROM publishing_tasks
+        WHERE created_at > now() - INTERVAL '1 day'
+      ),
+      missing_npm_tarballs AS (
+        SELECT package_versions.created_at
+        FROM package_versions
+        WHERE package_versions.created_at > now() - INTERVAL '1 day' AND NOT EXISTS (
+          SELECT 1
+          FROM npm_tarballs
+          WHERE npm_tarballs.scope = package_versions.scope AND npm_tarballs.name = package_versions.name AND npm_tarballs.version = package_versions.version AND npm_tarballs.revision = $1
+        )
+      )
+      SELECT
+        (SELECT COUNT(*) FROM recent_tasks WHERE status = 'pending') as "pending_publishes!",
+        (SELECT MIN(updated_at) FROM recent_tasks WHERE status = 'pending') as "oldest_pending_publish",
+        (SELECT COUNT(*) FROM recent_tasks WHERE status = 'processing') as "processing_publishes!",
+        (SELECT MIN(updated_at) FROM recent_tasks WHERE status = 'processing') as "oldest_processing_publish",
+        (SELECT COUNT(*) FROM search_index_outbox) as "pending_search_index_updates!",
+        (SELECT MIN(enqueued_at) FROM search_index_outbox) as "oldest_pending_search_index_update",
+        (SELECT COUNT(*) FROM missing_npm_tarballs) as "missing_npm_tarballs!",
+        (SELECT MIN(created_at) FROM missing_npm_tarballs) as "oldest_missing_npm_tarball"
+      "#,
+      current_npm_tarball_revision,
+    )
+    .map(|r| RegistryBacklogs {
+      pending_publishes: r.pending_publishes,
+      oldest_pending_publish: r.oldest_pending_publish,
+      processing_publishes: r.processing_publishes,
+      oldest_processing_publish: r.oldest_processing_publish,
+      pending_search_index_updates: r.pending_search_index_updates,
+      oldest_pending_search_index_update: r.oldest_pending_search_index_update,
+      missing_npm_tarballs: r.missing_npm_tarballs,
+      oldest_missing_npm_tarball: r.oldest_missing_npm_tarball,
+    })
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Claims up to `limit` packages whose search index entry is out of date, so
+  /// that other search index tasks skip them for `claim_duration`.
+  #[instrument(name = "Database::claim_search_index_updates", skip(self), err)]
+  pub async fn claim_search_index_updates(
+    &self,
+    limit: i64,
+    claim_duration: chrono::Duration,
+  ) -> Result<Vec<SearchIndexUpdate>> {
+    let claimed_until = Utc::now() + claim_duration;
+    sqlx::query_as!(
+      SearchIndexUpdate,
+      r#"
+      UPDATE search_index_outbox SET next_attempt_at = $2
+      WHERE (scope, name) IN (
+        SELECT scope, name
+        FROM search_index_outbox
+        WHERE next_attempt_at <= now()
+        ORDER BY enqueued_at ASC
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+      )
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", revision, attempts, enqueued_at
+      "#,
+      limit,
+      claimed_until,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Removes a package from the search index outbox once it has been indexed,
+  /// unless it changed again since it was claimed.
+  #[instrument(
+    name = "Database::complete_search_index_update",
+    skip(self),
+    err
+  )]
+  pub async fn complete_search_index_update(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    revision: i32,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"DELETE FROM search_index_outbox WHERE scope = $1 AND name = $2 AND revision = $3"#,
+      scope as _,
+      name as _,
+      revision,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  /// Records a failed attempt at indexing a package, and schedules the next
+  /// attempt. If the package changed again since it was claimed, it is retried
+  /// right away instead.
+  #[instrument(name = "Database::fail_search_index_update", skip(self), err)]
+  pub async fn fail_search_index_update(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    revision: i32,
+    error: &str,
+    next_attempt_at: DateTime<Utc>,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"UPDATE search_index_outbox
+      SET attempts = attempts + 1, last_error = $4, next_attempt_at = $5
+      WHERE scope = $1 AND name = $2 AND revision = $3"#,
+      scope as _,
+      name as _,
+      revision,
+      error,
+      next_attempt_at,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
+  }
+
+  /// Validates cross-table invariants, and records the violations that were
+  /// found as a new audit, replacing the previous one. Publishing tasks that
+  /// have not progressed since `stale_tasks_before` are reported as stuck.
+  #[instrument(name = "Database::run_consistency_audit", skip(self), err)]
+  pub async fn run_consistency_audit(
+    &self,
+    stale_tasks_before: DateTime<Utc>,
+  ) -> Result<(ConsistencyAudit, Vec<AuditFinding>)> {
+    let mut tx = self.begin().await?;
+
+    let audit = sqlx::query_as!(
+      ConsistencyAudit,
+      r#"INSERT INTO consistency_audits DEFAULT VALUES RETURNING id, created_at"#
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let findings = sqlx::query_as!(
+      AuditFinding,
+      r#"
+      INSERT INTO consistency_audit_findings (audit_id, kind, severity, subject, message)
+      SELECT $1, findings.kind, findings.severity, findings.subject, findings.message
+      FROM (
+        SELECT 'orphaned_package_version' AS kind, 'error'::audit_finding_severity AS severity,
+          '@' || package_versions.scope || '/' || package_versions.name || '@' || package_versions.version AS subject,
+          'The package of this version does not exist.' AS message
+        FROM package_versions
+        WHERE NOT EXISTS (SELECT 1 FROM packages WHERE packages.scope = package_versions.scope AND packages.name = package_versions.name)
+
+        UNION ALL
+
+        SELECT 'orphaned_npm_tarball', 'error'::audit_finding_severity,
+          '@' || npm_tarballs.scope || '/' || npm_tarballs.name || '@' || npm_tarballs.version || ' (revision ' || npm_tarballs.revision || ')',
+          'The package version of this npm tarball does not exist.'
+        FROM npm_tarballs
+        WHERE NOT EXISTS (SELECT 1 FROM package_versions WHERE package_versions.scope = npm_tarballs.scope AND package_versions.name = npm_tarballs.name AND package_versions.version = npm_tarballs.version)
+
+        UNION ALL
+
+        SELECT 'scope_creator_not_member', 'error'::audit_finding_severity,
+          '@' || scopes.scope,
+          'The creator of this scope (' || scopes.creator || ') is not a member of it.'
+        FROM scopes
+        WHERE scopes.creator IS NOT NULL AND NOT EXISTS (SELECT 1 FROM scope_members WHERE scope_members.scope = scopes.scope AND scope_members.user_id = scopes.creator)
+
+        UNION ALL
+
+        SELECT 'stuck_publishing_task', 'warning'::audit_finding_severity,
+          publishing_tasks.id::text,
+          'This publishing task of @' || publishing_tasks.package_scope || '/' || publishing_tasks.package_name || '@' || publishing_tasks.package_version || ' has been ' || publishing_tasks.status || ' since ' || publishing_tasks.updated_at || '.'
+        FROM publishing_tasks
+        WHERE publishing_tasks.status IN ('pending', 'processing', 'processed') AND publishing_tasks.updated_at < $2
+
+        UNION ALL
+
+        SELECT 'successful_publishing_task_without_version', 'error'::audit_finding_severity,
+          publishing_tasks.id::text,
+          'This publishing task succeeded, but @' || publishing_tasks.package_scope || '/' || publishing_tasks.package_name || '@' || publishing_tasks.package_version || ' does not exist.'
+        FROM publishing_tasks
+        JOIN packages ON packages.scope = publishing_tasks.package_scope AND packages.name = publishing_tasks.package_name
+        WHERE publishing_tasks.status = 'success' AND publishing_tasks.created_at >= packages.created_at AND NOT EXISTS (SELECT 1 FROM package_versions WHERE package_versions.scope = publishing_tasks.package_scope AND package_versions.name = publishing_tasks.package_name AND package_versions.version = publishing_tasks.package_version)
+      ) AS findings
+      RETURNING kind, severity as "severity: AuditFindingSeverity", subject, message"#,
+      audit.id,
+      stale_tasks_before,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    sqlx::query!("DELETE FROM consistency_audits WHERE id != $1", audit.id)
+      .execute(&mut *tx)
+      .await?;
+
+    tx.commit().await?;
+
+    Ok((audit, findings))
+  }
+
+  #[instrument(
+    name = "Database::get_latest_consistency_audit",
+    skip(self),
+    err
+  )]
+  pub async fn get_latest_consistency_audit(
+    &self,
+  ) -> Result<Option<(ConsistencyAudit, Vec<AuditFinding>)>> {
+    let mut tx = self.begin().await?;
+
+    let Some(audit) = sqlx::query_as!(
+      ConsistencyAudit,
+      r#"SELECT id, created_at FROM consistency_audits ORDER BY created_at DESC LIMIT 1"#
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+      return Ok(None);
+    };
+
+    let findings = sqlx::query_as!(
+      AuditFinding,
+      r#"SELECT kind, severity as "severity: AuditFindingSeverity", subject, message
+      FROM consistency_audit_findings
+      WHERE audit_id = $1
+      ORDER BY severity DESC, kind, subject"#,
+      audit.id,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some((audit, findings)))
+  }
+
+  #[instrument(name = "Database::list_all_scopes_for_sitemap", skip(self), err)]
+  #[allow(clippy::type_complexity)]
+  pub async fn list_all_scopes_for_sitemap(
+    &self,
+  ) -> Result<Vec<(ScopeName, DateTime<Utc>, Option<DateTime<Utc>>)>> {
+    sqlx::query!(
+      r#"
+        SELECT
+          scope as "scope: ScopeName",
+          updated_at,
+          (SELECT updated_at FROM packages WHERE scope = scope ORDER BY updated_At DESC LIMIT 1) as "latest_package_created_at"
+        FROM scopes
+        ORDER BY scope ASC
+        LIMIT 50000
+      "#
+    )
+    .map(|r| (r.scope, r.updated_at, r.latest_package_created_at))
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::list_all_packages_for_sitemap",
+    skip(self),
+    err
+  )]
+  #[allow(clippy::type_complexity)]
+  pub async fn list_all_packages_for_sitemap(
+    &self,
+  ) -> Result<Vec<(ScopeName, PackageName, DateTime<Utc>, DateTime<Utc>)>> {
+    sqlx::query!(
+      r#"SELECT
+        scope as "scope: ScopeName", name as "name: PackageName", updated_at,
+        (SELECT created_at FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version_updated_at!"
+      FROM packages
+      WHERE has_usable_version AND NOT noindex
+      ORDER BY scope ASC, name ASC
+      LIMIT 50000"#
+    )
+    .map(|r| {
+      (
+        r.scope,
+        r.name,
+        r.updated_at,
+        r.latest_version_updated_at,
+      )
+    })
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::insert_download_entries",
+    skip(self, entries),
+    err
+  )]
+  pub async fn insert_download_entries(
+    &self,
+    entries: Vec<VersionDownloadCount>,
+  ) -> Result<()> {
+    if entries.is_empty() {
+      return Ok(());
+    }
+
+    let mut tx = self.begin().await?;
+
+    let mut scopes = Vec::with_capacity(entries.len());
+    let mut packages = Vec::with_capacity(entries.len());
+    let mut versions = Vec::with_capacity(entries.len());
+    let mut time_buckets = Vec::with_capacity(entries.len());
+    let mut kinds = Vec::with_capacity(entries.len());
+    let mut counts = Vec::with_capacity(entries.len());
+
+    let mut smallest_time_bucket = Utc::now();
+    let mut largest_time_bucket = DateTime::from_timestamp_nanos(0);
+
+    for entry in entries {
+      scopes.push(entry.scope);
+      packages.push(entry.package);
+      versions.push(entry.version);
+      time_buckets.push(entry.time_bucket);
+      kinds.push(entry.kind);
+      counts.push(entry.count);
+
+      if entry.time_bucket < smallest_time_bucket {
+        smallest_time_bucket = entry.time_bucket;
+      }
+      if entry.time_bucket > largest_time_bucket {
+        largest_time_bucket = entry.time_bucket;
+      }
+    }
+
+    // Upsert data into version_download_counts_4h
+    sqlx::query!(
+      r#"
+      INSERT INTO version_download_counts_4h (scope, package, version, time_bucket, kind, count)
+      SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TIMESTAMPTZ[], $5::download_kind[], $6::INT[]) as temp(scope, package, version, time_bucket, kind, count)
+      WHERE (SELECT COUNT(*) FROM package_versions WHERE package_versions.scope = temp.scope AND package_versions.name = temp.package AND version = temp.version) > 0
+      ON CONFLICT (scope, package, version, time_bucket, kind) DO UPDATE SET count = EXCLUDED.count
+      "#,
+      &scopes as _,
+      &packages as _,
+      &versions as _,
+      &time_buckets,
+      &kinds as _,
+      &counts as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Compute data in version_download_counts_24h from version_download_counts_4h between smallest_timestamp and largest_timestamp.
+    // smallest_timestamp must be truncated down to the nearest day and largest_timestamp must be truncated up to the nearest day.
+    sqlx::query!(
+      r#"
+      INSERT INTO version_download_counts_24h (scope, package, version, time_bucket, kind, count)
+      SELECT scope, package, version, date_trunc('day', time_bucket), kind, SUM(count)
+      FROM version_download_counts_4h
+      WHERE time_bucket >= date_trunc('day', $1::timestamptz) AND time_bucket < date_trunc('day', $2::timestamptz) + interval '1 day'
+      GROUP BY scope, package, version, date_trunc('day', time_bucket), kind
+      ON CONFLICT (scope, package, version, time_bucket, kind) DO UPDATE SET count = EXCLUDED.count
+      "#,
+      smallest_time_bucket,
+      largest_time_bucket,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+  }
+
+  #[instrument(
+    name = "Database::get_package_version_downloads_4h",
+    skip(self),
+    err
+  )]
+  pub async fn get_package_version_downloads_4h(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> Result<Vec<DownloadDataPoint>> {
+    sqlx::query_as!(
+      DownloadDataPoint,
+      r#"
+      SELECT time_bucket, kind as "kind: DownloadKind", count
+      FROM version_download_counts_4h
+      WHERE scope = $1 AND package = $2 AND version = $3 AND time_bucket >= $4 AND time_bucket < $5
+      ORDER BY time_bucket ASC
+      "#,
+      scope as _,
+      name as _,
+      version as _,
+      start,
+      end,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::get_package_version_downloads_24h",
+    skip(self),
+    err
+  )]
+  pub async fn get_package_version_downloads_24h(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> Result<Vec<DownloadDataPoint>> {
+    sqlx::query_as!(
+      DownloadDataPoint,
+      r#"
+      SELECT time_bucket, kind as "kind: DownloadKind", count
+      FROM version_download_counts_24h
+      WHERE scope = $1 AND package = $2 AND version = $3 AND time_bucket >= $4 AND time_bucket < $5
+      ORDER BY time_bucket ASC
+      "#,
+      scope as _,
+      name as _,
+      version as _,
+      start,
+      end,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::get_package_downloads_24h", skip(self), err)]
+  pub async fn get_package_downloads_24h(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> Result<Vec<DownloadDataPoint>> {
+    sqlx::query_as!(
+      DownloadDataPoint,
+      r#"
+    SELECT time_bucket, kind as "kind: DownloadKind", SUM(count) as "count!"
+    FROM version_download_counts_24h
+    WHERE scope = $1 AND package = $2 AND time_bucket >= $3 AND time_bucket < $4
+    GROUP BY time_bucket, kind
+    ORDER BY time_bucket ASC
+    "#,
+      scope as _,
+      name as _,
+      start,
+      end,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::get_scope_downloads_24h", skip(self), err)]
+  pub async fn get_scope_downloads_24h(
+    &self,
+    scope: &ScopeName,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> Result<Vec<DownloadDataPoint>> {
+    sqlx::query_as!(
+      DownloadDataPoint,
+      r#"
+    SELECT time_bucket, kind as "kind: DownloadKind", SUM(count) as "count!"
+    FROM version_download_counts_24h
+    WHERE scope = $1 AND time_bucket >= $2 AND time_bucket < $3
+    GROUP BY time_bucket, kind
+    ORDER BY time_bucket ASC
+    "#,
+      scope as _,
+      start,
+      end,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Lists the daily downloads, not counting web requests, of every package
+  /// that had at least `min_count` downloads on one of the days between
+  /// `start` and `end`. Days without downloads are left out.
+  #[instrument(
+    name = "Database::list_package_daily_downloads",
+    skip(self),
+    err
+  )]
+  pub async fn list_package_daily_downloads(
+    &self,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    min_count: i64,
+  ) -> Result<Vec<PackageDailyDownloads>> {
+    sqlx::query_as!(
+      PackageDailyDownloads,
+      r#"
+    WITH daily AS (
+      SELECT scope, package, time_bucket, SUM(count) as count
+      FROM version_download_counts_24h
+      WHERE time_bucket >= $1 AND time_bucket < $2 AND kind <> 'web'
+      GROUP BY scope, package, time_bucket
+    )
+    SELECT scope as "scope: ScopeName", package as "package: PackageName", time_bucket as "day", count as "count!"
+    FROM daily
+    WHERE (scope, package) IN (SELECT scope, package FROM daily WHERE count >= $3)
+    ORDER BY scope, package, time_bucket
+    "#,
+      start,
+      end,
+      min_count,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Records download anomalies, skipping those that were already recorded.
+  /// Returns the newly recorded anomalies.
+  #[instrument(
+    name = "Database::insert_download_anomalies",
+    skip(self, anomalies),
+    err
+  )]
+  pub async fn insert_download_anomalies(
+    &self,
+    anomalies: Vec<NewDownloadAnomaly>,
+  ) -> Result<Vec<DownloadAnomaly>> {
+    let mut scopes = Vec::with_capacity(anomalies.len());
+    let mut packages = Vec::with_capacity(anomalies.len());
+    let mut days = Vec::with_capacity(anomalies.len());
+    let mut kinds = Vec::with_capacity(anomalies.len());
+    let mut counts = Vec::with_capacity(anomalies.len());
+    let mut means = Vec::with_capacity(anomalies.len());
+    let mut stddevs = Vec::with_capacity(anomalies.len());
+    let mut z_scores = Vec::with_capacity(anomalies.len());
+    for anomaly in anomalies {
+      scopes.push(anomaly.scope);
+      packages.push(anomaly.package);
+      days.push(anomaly.day);
+      kinds.push(anomaly.kind);
+      counts.push(anomaly.count);
+      means.push(anomaly.mean);
+      stddevs.push(anomaly.stddev);
+      z_scores.push(anomaly.z_score);
+    }
+
+    sqlx::query_as!(
+      DownloadAnomaly,
+      r#"
+      INSERT INTO download_anomalies (scope, package, day, kind, count, mean, stddev, z_score)
+      SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TIMESTAMPTZ[], $4::download_anomaly_kind[], $5::BIGINT[], $6::FLOAT8[], $7::FLOAT8[], $8::FLOAT8[])
+      ON CONFLICT (scope, package, day) DO NOTHING
+      RETURNING scope as "scope: ScopeName", package as "package: PackageName", day, kind as "kind: DownloadAnomalyKind", count, mean, stddev, z_score, created_at
+      "#,
+      &scopes as _,
+      &packages as _,
+      &days,
+      &kinds as _,
+      &counts,
+      &means,
+      &stddevs,
+      &z_scores,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(
+    name = "Database::list_package_download_anomalies",
+    skip(self),
+    err
+  )]
+  pub async fn list_package_download_anomalies(
+    &self,
+    scope: &ScopeName,
+    package: &PackageName,
+  ) -> Result<Vec<DownloadAnomaly>> {
+    sqlx::query_as!(
+      DownloadAnomaly,
+      r#"
+      SELECT scope as "scope: ScopeName", package as "package: PackageName", day, kind as "kind: DownloadAnomalyKind", count, mean, stddev, z_score, created_at
+      FROM download_anomalies
+      WHERE scope = $1 AND package = $2
+      ORDER BY day DESC
+      LIMIT 100
+      "#,
+      scope as _,
+      package as _,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Returns the most downloaded packages of the last 30 days, out of the top
+  /// `popular_limit`, whose links were not checked since `checked_before`.
+  #[instrument(
+    name = "Database::list_packages_due_for_link_check",
+    skip(self),
+    err
+  )]
+  pub async fn list_packages_due_for_link_check(
+    &self,
+    popular_limit: i64,
+    checked_before: DateTime<Utc>,
+    limit: i64,
+  ) -> Result<Vec<(ScopeName, PackageName)>> {
+    sqlx::query!(
+      r#"
+      WITH popular AS (
+        SELECT packages.scope, packages.name, COALESCE(SUM(counts.count), 0) AS downloads
+        FROM packages
+        LEFT JOIN version_download_counts_24h counts ON counts.scope = packages.scope AND counts.package = packages.name AND counts.time_bucket >= now() - interval '30 days'
+        WHERE packages.is_archived = false
+        GROUP BY packages.scope, packages.name
+        ORDER BY downloads DESC
+        LIMIT $1
+      )
+      SELECT popular.scope as "scope: ScopeName", popular.name as "name: PackageName"
+      FROM popular
+      LEFT JOIN package_link_checks ON package_link_checks.scope = popular.scope AND package_link_checks.package = popular.name
+      WHERE package_link_checks.checked_at IS NULL OR package_link_checks.checked_at < $2
+      ORDER BY popular.downloads DESC
+      LIMIT $3
+      "#,
+      popular_limit,
+      checked_before,
+      limit,
+    )
+    .map(|r| (r.scope, r.name))
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Records a link check of a package, replacing the broken links found by
+  /// the previous check.
+  #[instrument(
+    name = "Database::record_package_link_check",
+    skip(self, broken_links),
+    err
+  )]
+  pub async fn record_package_link_check(
+    &self,
+    scope: &ScopeName,
+    package: &PackageName,
+    version: &Version,
+    checked_link_count: i32,
+    broken_links: Vec<BrokenLink>,
+  ) -> Result<PackageLinkCheck> {
+    let mut tx = self.begin().await?;
+    let check = sqlx::query_as!(
+      PackageLinkCheck,
+      r#"
+      INSERT INTO package_link_checks (scope, package, version, checked_link_count)
+      VALUES ($1, $2, $3, $4)
+      ON CONFLICT (scope, package) DO UPDATE SET version = $3, checked_link_count = $4, checked_at = now()
+      RETURNING scope as "scope: ScopeName", package as "package: PackageName", version as "version: Version", checked_link_count, checked_at
+      "#,
+      scope as _,
+      package as _,
+      version as _,
+      checked_link_count,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+      r#"DELETE FROM package_broken_links WHERE scope = $1 AND package = $2"#,
+      scope as _,
+      package as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let mut urls = Vec::with_capacity(broken_links.len());
+    let mut sources = Vec::with_capacity(broken_links.len());
+    let mut statuses = Vec::with_capacity(broken_links.len());
+    let mut errors = Vec::with_capacity(broken_links.len());
+    for link in broken_links {
+      urls.push(link.url);
+      sources.push(link.source);
+      statuses.push(link.status);
+      errors.push(link.error);
+    }
+    sqlx::query!(
+      r#"
+      INSERT INTO package_broken_links (scope, package, url, source, status, error)
+      SELECT $1, $2, * FROM UNNEST($3::TEXT[], $4::TEXT[], $5::INT4[], $6::TEXT[])
+      ON CONFLICT (scope, package, url) DO NOTHING
+      "#,
+      scope as _,
+      package as _,
+      &urls,
+      &sources,
+      &statuses as _,
+      &errors as _,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(check)
+  }
+
+  /// Returns the last link check of a package and the broken links it found.
+  #[instrument(name = "Database::get_package_link_check", skip(self), err)]
+  pub async fn get_package_link_check(
+    &self,
+    scope: &ScopeName,
+    package: &PackageName,
+  ) -> Result<Option<(PackageLinkCheck, Vec<BrokenLink>)>> {
+    let Some(check) = sqlx::query_as!(
+      PackageLinkCheck,
+      r#"
+      SELECT scope as "scope: ScopeName", package as "package: PackageName", version as "version: Version", checked_link_count, checked_at
+      FROM package_link_checks
+      WHERE scope = $1 AND package = $2
+      "#,
+      scope as _,
+      package as _,
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await?
+    else {
+      return Ok(None);
+    };
+
+    let broken_links = sqlx::query_as!(
+      BrokenLink,
+      r#"
+      SELECT url, source, status, error
+      FROM package_broken_links
+      WHERE scope = $1 AND package = $2
+      ORDER BY source, url
+      "#,
+      scope as _,
+      package as _,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await?;
+
+    Ok(Some((check, broken_links)))
+  }
+
+  /// Returns the redirect for the old name of a package, if it has not
+  /// expired yet and no new package has taken the old name.
+  #[instrument(name = "Database::get_package_redirect", skip(self), err)]
+  pub async fn get_package_redirect(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+  ) -> Result<Option<PackageRedirect>> {
+    sqlx::query_as!(
+      PackageRedirect,
+      r#"
+      SELECT scope as "scope: ScopeName", name as "name: PackageName", target_scope as "target_scope: ScopeName", target_name as "target_name: PackageName", expires_at, created_at
+      FROM package_redirects
+      WHERE scope = $1 AND name = $2 AND expires_at > now()
+        AND NOT EXISTS (SELECT 1 FROM packages WHERE packages.scope = $1 AND packages.name = $2)
+      "#,
+      scope as _,
+      name as _,
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::list_package_redirects", skip(self), err)]
+  pub async fn list_package_redirects(&self) -> Result<Vec<PackageRedirect>> {
+    sqlx::query_as!(
+      PackageRedirect,
+      r#"
+      SELECT scope as "scope: ScopeName", name as "name: PackageName", target_scope as "target_scope: ScopeName", target_name as "target_name: PackageName", expires_at, created_at
+      FROM package_redirects
+      WHERE expires_at > now()
+      ORDER BY created_at DESC
+      "#,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Creates a redirect from the old name of a package to its new name, or
+  /// replaces the existing redirect for the old name.
+  #[instrument(name = "Database::upsert_package_redirect", skip(self), err)]
+  pub async fn upsert_package_redirect(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+    target_scope: &ScopeName,
+    target_name: &PackageName,
+    expires_at: DateTime<Utc>,
+  ) -> Result<PackageRedirect> {
+    sqlx::query_as!(
+      PackageRedirect,
+      r#"
+      INSERT INTO package_redirects (scope, name, target_scope, target_name, expires_at)
+      VALUES ($1, $2, $3, $4, $5)
+      ON CONFLICT (scope, name) DO UPDATE SET target_scope = $3, target_name = $4, expires_at = $5, created_at = now()
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", target_scope as "target_scope: ScopeName", target_name as "target_name: PackageName", expires_at, created_at
+      "#,
+      scope as _,
+      name as _,
+      target_scope as _,
+      target_name as _,
+      expires_at,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Deletes the redirect for the old name of a package. Returns whether a
+  /// redirect existed.
+  #[instrument(name = "Database::delete_package_redirect", skip(self), err)]
+  pub async fn delete_package_redirect(
+    &self,
+    scope: &ScopeName,
+    name: &PackageName,
+  ) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"DELETE FROM package_redirects WHERE scope = $1 AND name = $2"#,
+      scope as _,
+      name as _,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+  }
+
+  #[instrument(name = "Database::list_download_counts_4h", skip(self), err)]
+  pub async fn list_download_counts_4h(
+    &self,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> Result<Vec<VersionDownloadCount>> {
+    sqlx::query_as!(
+      VersionDownloadCount,
+      r#"
+      SELECT scope as "scope: ScopeName", package as "package: PackageName", version as "version: Version", time_bucket, kind as "kind: DownloadKind", count
+      FROM version_download_counts_4h
+      WHERE time_bucket >= $1 AND time_bucket < $2
+      ORDER BY time_bucket, scope, package, version, kind
+      "#,
+      start,
+      end,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::list_publish_events", skip(self), err)]
+  pub async fn list_publish_events(
+    &self,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> Result<Vec<PublishEvent>> {
+    sqlx::query_as!(
+      PublishEvent,
+      r#"
+      SELECT scope as "scope: ScopeName", name as "package: PackageName", version as "version: Version", user_id, created_at
+      FROM package_versions
+      WHERE created_at >= $1 AND created_at < $2
+      ORDER BY created_at
+      "#,
+      start,
+      end,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Lists the versions of the given packages that were published after
+  /// `after`, oldest first.
+  #[instrument(
+    name = "Database::list_publish_events_for_packages",
+    skip(self, packages),
+    err
+  )]
+  pub async fn list_publish_events_for_packages(
+    &self,
+    packages: &[ScopedPackageName],
+    after: DateTime<Utc>,
+    limit: i64,
+  ) -> Result<Vec<PublishEvent>> {
+    let (scopes, names) = split_scoped_package_names(packages);
+    sqlx::query_as!(
+      PublishEvent,
+      r#"
+      SELECT scope as "scope: ScopeName", name as "package: PackageName", version as "version: Version", user_id, created_at
+      FROM package_versions
+      WHERE (scope, name) IN (SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[])) AND created_at > $3
+      ORDER BY created_at
+      LIMIT $4
+      "#,
+      &scopes as _,
+      &names as _,
+      after,
+      limit,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::get_user_export", skip(self), err)]
+  pub async fn get_user_export(&self, id: Uuid) -> Result<Option<UserExport>> {
+    sqlx::query_as!(
+      UserExport,
+      r#"SELECT id, user_id, status "status: UserExportStatus", download_token_hash, expires_at, updated_at, created_at FROM user_exports WHERE id = $1"#,
+      id
+    )
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Returns the latest export of the user that was requested in the last
+  /// day and did not fail, or creates a new one.
+  #[instrument(name = "Database::get_or_create_user_export", skip(self), err)]
+  pub async fn get_or_create_user_export(
+    &self,
+    user_id: Uuid,
+  ) -> Result<UserExport> {
+    let mut tx = self.begin().await?;
+
+    let existing = sqlx::query_as!(
+      UserExport,
+      r#"SELECT id, user_id, status "status: UserExportStatus", download_token_hash, expires_at, updated_at, created_at FROM user_exports
+      WHERE user_id = $1 AND status != 'failure' AND created_at > now() - interval '1 day'
+      ORDER BY created_at DESC
+      LIMIT 1
+      FOR UPDATE"#,
+      user_id
+    )
+    .fetch_optional(&mut *tx)
     .await?;
 
-    if publish_attempts_from_last_week > publish_attempts_per_week_limit as i64
-    {
-      tx.rollback().await?;
-      return Ok(
-        CreatePublishingTaskResult::WeeklyPublishAttemptsLimitExceeded(
-          publish_attempts_per_week_limit,
-        ),
-      );
-    }
+    let export = match existing {
+      Some(export) => export,
+      None => {
+        sqlx::query_as!(
+          UserExport,
+          r#"INSERT INTO user_exports (user_id) VALUES ($1)
+          RETURNING id, user_id, status "status: UserExportStatus", download_token_hash, expires_at, updated_at, created_at"#,
+          user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+      }
+    };
 
     tx.commit().await?;
 
-    Ok(CreatePublishingTaskResult::Created(task))
+    Ok(export)
   }
 
-  #[instrument(name = "Database::get_publishing_task", skip(self), err)]
-  pub async fn get_publishing_task(
+  /// Marks up to `limit` pending exports as processing and returns them.
+  /// Exports that have been processing for a long time are assumed to have
+  /// been abandoned by a crashed task, and are picked up again.
+  #[instrument(name = "Database::claim_pending_user_exports", skip(self), err)]
+  pub async fn claim_pending_user_exports(
     &self,
-    id: Uuid,
-  ) -> Result<Option<PublishingTask>> {
+    limit: i64,
+  ) -> Result<Vec<UserExport>> {
     sqlx::query_as!(
-      PublishingTask,
-      r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", created_at, updated_at
-      FROM publishing_tasks
-      WHERE id = $1"#,
-      id
+      UserExport,
+      r#"UPDATE user_exports SET status = 'processing'
+      WHERE id IN (
+        SELECT id FROM user_exports
+        WHERE status = 'pending' OR (status = 'processing' AND updated_at < now() - interval '15 minutes')
+        ORDER BY created_at
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+      )
+      RETURNING id, user_id, status "status: UserExportStatus", download_token_hash, expires_at, updated_at, created_at"#,
+      limit
     )
-    .fetch_optional(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::list_publishing_tasks", skip(self), err)]
-  pub async fn list_publishing_tasks(
+  #[instrument(name = "Database::complete_user_export", skip(self), err)]
+  pub async fn complete_user_export(
     &self,
-    start: i64,
-    limit: i64,
-    maybe_search_query: Option<&str>,
-  ) -> Result<(usize, Vec<PublishingTask>)> {
-    let mut tx = self.pool.begin().await?;
-    let search = format!("%{}%", maybe_search_query.unwrap_or(""));
-    let publishing_tasks = sqlx::query_as!(
-      PublishingTask,
-      r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", created_at, updated_at
-      FROM publishing_tasks WHERE package_scope ILIKE $1 OR package_name ILIKE $1 OR package_version ILIKE $1 ORDER BY created_at DESC OFFSET $2 LIMIT $3"#,
-      search,
-      start,
-      limit,
+    id: Uuid,
+    download_token_hash: &str,
+    expires_at: DateTime<Utc>,
+  ) -> Result<UserExport> {
+    sqlx::query_as!(
+      UserExport,
+      r#"UPDATE user_exports SET status = 'success', download_token_hash = $2, expires_at = $3
+      WHERE id = $1
+      RETURNING id, user_id, status "status: UserExportStatus", download_token_hash, expires_at, updated_at, created_at"#,
+      id,
+      download_token_hash,
+      expires_at
     )
-    .fetch_all(&mut *tx)
-    .await?;
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
 
-    let total_publishing_tasks = sqlx::query!(
-      r#"SELECT COUNT(created_at) FROM publishing_tasks WHERE package_scope ILIKE $1 OR package_name ILIKE $1 OR package_version ILIKE $1;"#,
-      search,
+  #[instrument(name = "Database::fail_user_export", skip(self), err)]
+  pub async fn fail_user_export(&self, id: Uuid) -> Result<()> {
+    sqlx::query!(
+      r#"UPDATE user_exports SET status = 'failure' WHERE id = $1"#,
+      id
     )
-    .map(|r| r.count.unwrap())
-    .fetch_one(&mut *tx)
+    .execute(&mut *self.acquire().await?)
     .await?;
+    Ok(())
+  }
 
-    tx.commit().await?;
+  /// Lists exports whose download link has expired, and failed exports that
+  /// are no longer relevant.
+  #[instrument(name = "Database::list_expired_user_exports", skip(self), err)]
+  pub async fn list_expired_user_exports(&self) -> Result<Vec<UserExport>> {
+    sqlx::query_as!(
+      UserExport,
+      r#"SELECT id, user_id, status "status: UserExportStatus", download_token_hash, expires_at, updated_at, created_at FROM user_exports
+      WHERE expires_at < now() OR (status = 'failure' AND created_at < now() - interval '7 days')"#
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
 
-    Ok((total_publishing_tasks as usize, publishing_tasks))
+  #[instrument(name = "Database::delete_user_export", skip(self), err)]
+  pub async fn delete_user_export(&self, id: Uuid) -> Result<()> {
+    sqlx::query!(r#"DELETE FROM user_exports WHERE id = $1"#, id)
+      .execute(&mut *self.acquire().await?)
+      .await?;
+    Ok(())
   }
 
   #[instrument(
-    name = "Database::list_publishing_tasks_for_package",
+    name = "Database::list_scope_memberships_by_user",
     skip(self),
     err
   )]
-  pub async fn list_publishing_tasks_for_package(
+  pub async fn list_scope_memberships_by_user(
     &self,
-    scope_name: &ScopeName,
-    package_name: &PackageName,
-  ) -> Result<Vec<PublishingTask>> {
+    user_id: Uuid,
+  ) -> Result<Vec<ScopeMember>> {
     sqlx::query_as!(
-      PublishingTask,
-      r#"SELECT publishing_tasks.id, publishing_tasks.status as "status: PublishingTaskStatus", publishing_tasks.error as "error: PublishingTaskError", publishing_tasks.user_id, publishing_tasks.package_scope as "package_scope: ScopeName", publishing_tasks.package_name as "package_name: PackageName", publishing_tasks.package_version as "package_version: Version", publishing_tasks.config_file as "config_file: PackagePath", publishing_tasks.created_at, publishing_tasks.updated_at
-      FROM publishing_tasks
-      JOIN packages ON publishing_tasks.package_scope = packages.scope AND publishing_tasks.package_name = packages.name
-      WHERE publishing_tasks.package_scope = $1 AND publishing_tasks.package_name = $2 AND publishing_tasks.created_at >= packages.created_at
-      ORDER BY publishing_tasks.package_version DESC"#,
-      scope_name as _,
-      package_name as _,
+      ScopeMember,
+      r#"SELECT scope as "scope: ScopeName", user_id, is_admin, updated_at, created_at
+      FROM scope_members
+      WHERE user_id = $1
+      ORDER BY created_at"#,
+      user_id
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  /// Lists all tokens of a user, including long expired ones.
+  #[instrument(name = "Database::list_all_tokens", skip(self), err)]
+  pub async fn list_all_tokens(&self, user_id: Uuid) -> Result<Vec<Token>> {
+    sqlx::query_as!(
+      Token,
+      r#"SELECT id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at
+      FROM tokens
+      WHERE user_id = $1
+      ORDER BY created_at"#,
+      user_id
     )
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
   #[instrument(
-    name = "Database::update_publishing_task_status",
+    name = "Database::list_publishing_tasks_by_user",
     skip(self),
     err
   )]
-  pub async fn update_publishing_task_status(
+  pub async fn list_publishing_tasks_by_user(
     &self,
-    id: Uuid,
-    prev_status: PublishingTaskStatus,
-    new_status: PublishingTaskStatus,
-    new_error: Option<PublishingTaskError>,
-  ) -> Result<PublishingTask> {
-    assert_eq!(
-      new_error.is_some(),
-      new_status == PublishingTaskStatus::Failure,
-      "error must be set if and only if status is failure"
-    );
+    user_id: Uuid,
+  ) -> Result<Vec<PublishingTask>> {
     sqlx::query_as!(
       PublishingTask,
-      r#"UPDATE publishing_tasks
-      SET status = $1, error = $2
-      WHERE id = $3 AND status = $4
-      RETURNING id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", created_at, updated_at"#,
-      new_status as _,
-      new_error as _,
-      id,
-      prev_status as _,
+      r#"SELECT id, status as "status: PublishingTaskStatus", error as "error: PublishingTaskError", size_report as "size_report: PublishingTaskSizeReport", warnings as "warnings: PublishingTaskWarnings", user_id, package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", config_file as "config_file: PackagePath", artifacts_expire_at, publish_at, created_at, updated_at
+      FROM publishing_tasks
+      WHERE user_id = $1
+      ORDER BY created_at"#,
+      user_id
     )
-    .fetch_one(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::get_oauth_state", skip(self), err)]
-  pub async fn get_oauth_state(
-    &self,
-    csrf_token: &str,
-  ) -> Result<Option<OauthState>> {
-    sqlx::query_as!(
-      OauthState,
-      "SELECT csrf_token, pkce_code_verifier, redirect_url, updated_at, created_at FROM oauth_states WHERE csrf_token = $1",
-      csrf_token
+  #[instrument(name = "Database::enqueue_task", skip(self, body), err)]
+  pub async fn enqueue_task(&self, queue: &str, body: &[u8]) -> Result<()> {
+    sqlx::query!(
+      r#"INSERT INTO queued_tasks (queue, body) VALUES ($1, $2)"#,
+      queue,
+      body,
     )
-    .fetch_optional(&self.pool)
-    .await
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
   }
 
-  #[instrument(name = "Database::insert_oauth_state", skip(self, new_oauth_state), err, fields(oauth_state.csrf_token = %new_oauth_state.csrf_token, oauth_state.redirect_url = %new_oauth_state.redirect_url))]
-  pub async fn insert_oauth_state<'a>(
+  /// Claims the task of the queue that has been waiting the longest. The task
+  /// is hidden from other workers for `lease`, after which it is handed out
+  /// again unless it was completed or rescheduled.
+  #[instrument(name = "Database::claim_queued_task", skip(self), err)]
+  pub async fn claim_queued_task(
     &self,
-    new_oauth_state: NewOauthState<'a>,
-  ) -> Result<OauthState> {
+    queue: &str,
+    lease: chrono::Duration,
+  ) -> Result<Option<QueuedTask>> {
+    let claimed_until = Utc::now() + lease;
     sqlx::query_as!(
-      OauthState,
-      "INSERT INTO oauth_states (csrf_token, pkce_code_verifier, redirect_url)
-      VALUES ($1, $2, $3)
-      RETURNING csrf_token, pkce_code_verifier, redirect_url, updated_at, created_at",
-      new_oauth_state.csrf_token,
-      new_oauth_state.pkce_code_verifier,
-      new_oauth_state.redirect_url,
+      QueuedTask,
+      r#"UPDATE queued_tasks
+      SET attempts = attempts + 1, run_after = $2
+      WHERE id = (
+        SELECT id FROM queued_tasks
+        WHERE queue = $1 AND run_after <= now()
+        ORDER BY run_after
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+      )
+      RETURNING id, queue, body, attempts, run_after, created_at"#,
+      queue,
+      claimed_until,
     )
-    .fetch_one(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::delete_oauth_state", skip(self), err)]
-  pub async fn delete_oauth_state(
+  #[instrument(name = "Database::delete_queued_task", skip(self), err)]
+  pub async fn delete_queued_task(&self, id: Uuid) -> Result<()> {
+    sqlx::query!(r#"DELETE FROM queued_tasks WHERE id = $1"#, id)
+      .execute(&mut *self.acquire().await?)
+      .await?;
+    Ok(())
+  }
+
+  #[instrument(name = "Database::reschedule_queued_task", skip(self), err)]
+  pub async fn reschedule_queued_task(
     &self,
-    csrf_token: &str,
-  ) -> Result<Option<OauthState>> {
-    sqlx::query_as!(
-      OauthState,
-      "DELETE FROM oauth_states
-      WHERE csrf_token = $1
-      RETURNING csrf_token, pkce_code_verifier, redirect_url, updated_at, created_at",
-      csrf_token
+    id: Uuid,
+    run_after: DateTime<Utc>,
+  ) -> Result<()> {
+    sqlx::query!(
+      r#"UPDATE queued_tasks SET run_after = $2 WHERE id = $1"#,
+      id,
+      run_after,
     )
-    .fetch_optional(&self.pool)
-    .await
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(())
   }
 
-  #[instrument(name = "Database::insert_github_identity", skip(self, new_github_identity), err, fields(github_identity.github_id = new_github_identity.github_id))]
-  pub async fn upsert_github_identity(
+  #[instrument(
+    name = "Database::upsert_download_ingestion_checkpoint",
+    skip(self),
+    err
+  )]
+  pub async fn upsert_download_ingestion_checkpoint(
     &self,
-    new_github_identity: NewGithubIdentity,
-  ) -> Result<GithubIdentity> {
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    downloads: i64,
+    replayed_by: Option<Uuid>,
+  ) -> Result<DownloadIngestionCheckpoint> {
     sqlx::query_as!(
-      GithubIdentity,
-      "INSERT INTO github_identities (github_id, access_token, access_token_expires_at, refresh_token, refresh_token_expires_at) VALUES ($1, $2, $3, $4, $5)
-      ON CONFLICT (github_id) DO
-      UPDATE SET access_token = $2, access_token_expires_at = $3, refresh_token = $4, refresh_token_expires_at = $5
-      RETURNING github_id, access_token, access_token_expires_at, refresh_token, refresh_token_expires_at, updated_at, created_at",
-      new_github_identity.github_id,
-      new_github_identity.access_token,
-      new_github_identity.access_token_expires_at,
-      new_github_identity.refresh_token,
-      new_github_identity.refresh_token_expires_at,
+      DownloadIngestionCheckpoint,
+      r#"INSERT INTO download_ingestion_checkpoints (window_start, window_end, downloads, replayed_by)
+      VALUES ($1, $2, $3, $4)
+      ON CONFLICT (window_start) DO UPDATE SET window_end = EXCLUDED.window_end, downloads = EXCLUDED.downloads, replayed_by = EXCLUDED.replayed_by, ingested_at = now()
+      RETURNING window_start, window_end, downloads, replayed_by, ingested_at"#,
+      window_start,
+      window_end,
+      downloads,
+      replayed_by,
     )
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::get_github_identity", skip(self), err)]
-  pub async fn get_github_identity(
+  /// Returns the end of the latest 4 hour window that was ingested completely.
+  #[instrument(
+    name = "Database::get_download_ingestion_complete_until",
+    skip(self),
+    err
+  )]
+  pub async fn get_download_ingestion_complete_until(
     &self,
-    github_id: i64,
-  ) -> Result<GithubIdentity> {
-    sqlx::query_as!(
-      GithubIdentity,
-      "SELECT github_id, access_token, access_token_expires_at, refresh_token, refresh_token_expires_at, updated_at, created_at
-      FROM github_identities
-      WHERE github_id = $1",
-      github_id
+  ) -> Result<Option<DateTime<Utc>>> {
+    sqlx::query!(
+      r#"SELECT MAX(window_end) as "window_end" FROM download_ingestion_checkpoints WHERE window_end - window_start >= interval '4 hours'"#
     )
-    .fetch_one(&self.pool)
+    .map(|r| r.window_end)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::insert_token", skip(self, new_token), err, fields(token.r#type = ?new_token.r#type))]
-  pub async fn insert_token(&self, new_token: NewToken) -> Result<Token> {
-    sqlx::query_as!(
-      Token,
-      r#"INSERT INTO tokens (hash, user_id, type, description, expires_at, permissions)
-      VALUES ($1, $2, $3, $4, $5, $6)
-      RETURNING id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at"#,
-      new_token.hash,
-      new_token.user_id,
-      new_token.r#type as _,
-      new_token.description,
-      new_token.expires_at,
-      new_token.permissions as _,
+  #[instrument(
+    name = "Database::list_download_ingestion_checkpoints",
+    skip(self),
+    err
+  )]
+  pub async fn list_download_ingestion_checkpoints(
+    &self,
+    start: i64,
+    limit: i64,
+  ) -> Result<(usize, Vec<DownloadIngestionCheckpoint>)> {
+    let mut tx = self.begin().await?;
+    let checkpoints = sqlx::query_as!(
+      DownloadIngestionCheckpoint,
+      r#"SELECT window_start, window_end, downloads, replayed_by, ingested_at
+      FROM download_ingestion_checkpoints ORDER BY window_start DESC OFFSET $1 LIMIT $2"#,
+      start,
+      limit,
     )
-    .fetch_one(&self.pool)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let total =
+      sqlx::query!(r#"SELECT COUNT(*) FROM download_ingestion_checkpoints"#)
+        .map(|r| r.count.unwrap())
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((total as usize, checkpoints))
+  }
+  /// Returns the packages of the list that do not exist.
+  #[instrument(name = "Database::list_missing_packages", skip(self), err)]
+  pub async fn list_missing_packages(
+    &self,
+    packages: &[ScopedPackageName],
+  ) -> Result<Vec<ScopedPackageName>> {
+    let (scopes, names) = split_scoped_package_names(packages);
+    sqlx::query!(
+      r#"SELECT t.scope "scope!: ScopeName", t.name "name!: PackageName"
+      FROM UNNEST($1::TEXT[], $2::TEXT[]) AS t(scope, name)
+      WHERE NOT EXISTS (SELECT 1 FROM packages WHERE packages.scope = t.scope AND packages.name = t.name)"#,
+      &scopes as _,
+      &names as _,
+    )
+    .map(|r| ScopedPackageName {
+      scope: r.scope,
+      package: r.name,
+    })
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::get_token_by_hash", skip(self), err)]
-  pub async fn get_token_by_hash(&self, hash: &str) -> Result<Option<Token>> {
-    sqlx::query_as!(Token, r#"SELECT id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at FROM tokens WHERE hash = $1"#, hash)
-      .fetch_optional(&self.pool)
-      .await
+  #[instrument(name = "Database::create_package_collection", skip(self), err)]
+  pub async fn create_package_collection(
+    &self,
+    user_id: Uuid,
+    name: &str,
+    description: &str,
+    packages: &[ScopedPackageName],
+  ) -> Result<PackageCollection> {
+    let mut tx = self.begin().await?;
+
+    let collection = sqlx::query!(
+      r#"INSERT INTO package_collections (user_id, name, description)
+      VALUES ($1, $2, $3)
+      RETURNING id, user_id, name, description, updated_at, created_at"#,
+      user_id,
+      name,
+      description,
+    )
+    .map(|r| PackageCollection {
+      id: r.id,
+      user_id: r.user_id,
+      name: r.name,
+      description: r.description,
+      packages: packages.to_vec(),
+      updated_at: r.updated_at,
+      created_at: r.created_at,
+    })
+    .fetch_one(&mut *tx)
+    .await?;
+
+    insert_package_collection_items(&mut tx, collection.id, packages).await?;
+
+    tx.commit().await?;
+
+    Ok(collection)
   }
 
-  #[instrument(name = "Database::list_token", skip(self), err)]
-  pub async fn list_tokens(&self, user_id: Uuid) -> Result<Vec<Token>> {
-    // list a user's tokens where the expiration date is at most 1 day in the past
-    sqlx::query_as!(
-      Token,
-      r#"SELECT id, hash, user_id, type "type: _", description, expires_at, permissions "permissions: _", updated_at, created_at
-      FROM tokens
-      WHERE user_id = $1 AND (expires_at > now() - interval '1 day' OR expires_at IS NULL)
-      ORDER BY expires_at DESC NULLS FIRST, created_at DESC
-      "#,
-      user_id
+  /// Updates the fields of a collection of the user that are set. Setting the
+  /// packages replaces all packages of the collection. Returns `None` if the
+  /// user has no collection with the ID.
+  #[instrument(name = "Database::update_package_collection", skip(self), err)]
+  pub async fn update_package_collection(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+    name: Option<&str>,
+    description: Option<&str>,
+    packages: Option<&[ScopedPackageName]>,
+  ) -> Result<Option<PackageCollection>> {
+    let mut tx = self.begin().await?;
+
+    let updated = sqlx::query!(
+      r#"UPDATE package_collections
+      SET name = COALESCE($3, name), description = COALESCE($4, description), updated_at = now()
+      WHERE user_id = $1 AND id = $2"#,
+      user_id,
+      id,
+      name,
+      description,
     )
-    .fetch_all(&self.pool)
-    .await
+    .execute(&mut *tx)
+    .await?;
+    if updated.rows_affected() == 0 {
+      return Ok(None);
+    }
+
+    if let Some(packages) = packages {
+      sqlx::query!(
+        r#"DELETE FROM package_collection_items WHERE collection_id = $1"#,
+        id
+      )
+      .execute(&mut *tx)
+      .await?;
+      insert_package_collection_items(&mut tx, id, packages).await?;
+    }
+
+    tx.commit().await?;
+
+    self.get_package_collection(id).await
   }
 
-  #[instrument(name = "Database::delete_token", skip(self), err)]
-  pub async fn delete_token(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+  #[instrument(name = "Database::delete_package_collection", skip(self), err)]
+  pub async fn delete_package_collection(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+  ) -> Result<bool> {
     let res = sqlx::query!(
-      r#"DELETE FROM tokens WHERE user_id = $1 ANd id = $2"#,
+      r#"DELETE FROM package_collections WHERE user_id = $1 AND id = $2"#,
       user_id,
       id
     )
-    .execute(&self.pool)
+    .execute(&mut *self.acquire().await?)
     .await?;
     Ok(res.rows_affected() > 0)
   }
 
-  #[instrument(
-    name = "Database::create_authorization",
-    skip(self, new_authorization),
-    err
-  )]
-  pub async fn create_authorization(
+  #[instrument(name = "Database::get_package_collection", skip(self), err)]
+  pub async fn get_package_collection(
     &self,
-    new_authorization: NewAuthorization<'_>,
-  ) -> Result<Authorization> {
-    sqlx::query_as!(
-      Authorization,
-      r#"INSERT INTO authorizations (exchange_token, code, challenge, permissions, expires_at)
-      VALUES ($1, $2, $3, $4, $5)
-      RETURNING exchange_token, code, challenge, permissions "permissions: _", approved, user_id, expires_at, created_at, updated_at"#,
-      new_authorization.exchange_token,
-      new_authorization.code,
-      new_authorization.challenge,
-      new_authorization.permissions as _,
-      new_authorization.expires_at,
-    )
-    .fetch_one(&self.pool)
-    .await
-  }
+    id: Uuid,
+  ) -> Result<Option<PackageCollection>> {
+    let mut tx = self.begin().await?;
 
-  #[instrument(name = "Database::get_authorization_by_code", skip(self), err)]
-  pub async fn get_authorization_by_code(
-    &self,
-    code: &str,
-  ) -> Result<Option<Authorization>> {
-    sqlx::query_as!(
-      Authorization,
-      r#"SELECT exchange_token, code, challenge, permissions "permissions: _", approved, user_id, expires_at, created_at, updated_at
-      FROM authorizations
-      WHERE code = $1"#,
-      code
+    let Some(mut collection) = sqlx::query!(
+      r#"SELECT id, user_id, name, description, updated_at, created_at
+      FROM package_collections WHERE id = $1"#,
+      id
     )
-    .fetch_optional(&self.pool)
-    .await
+    .map(|r| PackageCollection {
+      id: r.id,
+      user_id: r.user_id,
+      name: r.name,
+      description: r.description,
+      packages: vec![],
+      updated_at: r.updated_at,
+      created_at: r.created_at,
+    })
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+      return Ok(None);
+    };
+
+    collection.packages = list_package_collection_items(&mut tx, &[id])
+      .await?
+      .into_iter()
+      .map(|(_, package)| package)
+      .collect();
+
+    tx.commit().await?;
+
+    Ok(Some(collection))
   }
 
   #[instrument(
-    name = "Database::get_authorization_by_exchange_token",
-    skip(self, exchange_token),
+    name = "Database::list_package_collections_by_user",
+    skip(self),
     err
   )]
-  pub async fn get_authorization_by_exchange_token_and_remove_if_complete(
+  pub async fn list_package_collections_by_user(
     &self,
-    exchange_token: &str,
-  ) -> Result<Option<Authorization>> {
-    let mut tx = self.pool.begin().await?;
+    user_id: Uuid,
+  ) -> Result<Vec<PackageCollection>> {
+    let mut tx = self.begin().await?;
 
-    let maybe_authorization = sqlx::query_as!(
-      Authorization,
-      r#"DELETE FROM authorizations
-      WHERE exchange_token = $1
-      RETURNING exchange_token, code, challenge, permissions "permissions: _", approved, user_id, expires_at, created_at, updated_at"#,
-      exchange_token
+    let mut collections = sqlx::query!(
+      r#"SELECT id, user_id, name, description, updated_at, created_at
+      FROM package_collections WHERE user_id = $1
+      ORDER BY created_at DESC"#,
+      user_id
     )
-    .fetch_optional(&mut *tx)
+    .map(|r| PackageCollection {
+      id: r.id,
+      user_id: r.user_id,
+      name: r.name,
+      description: r.description,
+      packages: vec![],
+      updated_at: r.updated_at,
+      created_at: r.created_at,
+    })
+    .fetch_all(&mut *tx)
     .await?;
 
-    if let Some(authorization) = &maybe_authorization {
-      if authorization.user_id.is_some() {
-        tx.commit().await?;
+    let ids = collections.iter().map(|c| c.id).collect::<Vec<_>>();
+    let items = list_package_collection_items(&mut tx, &ids).await?;
+    for (collection_id, package) in items {
+      if let Some(collection) =
+        collections.iter_mut().find(|c| c.id == collection_id)
+      {
+        collection.packages.push(package);
       }
     }
 
-    Ok(maybe_authorization)
+    tx.commit().await?;
+
+    Ok(collections)
   }
 
-  #[instrument(name = "Database::update_authorization", skip(self), err)]
-  pub async fn update_authorization(
+  #[instrument(
+    name = "Database::list_package_version_artifacts",
+    skip(self),
+    err
+  )]
+  pub async fn list_package_version_artifacts(
     &self,
-    code: &str,
-    approved: bool,
-    user_id: Uuid,
-  ) -> Result<bool> {
-    let res = sqlx::query!(
-      r#"UPDATE authorizations
-      SET approved = $1, user_id = $2
-      WHERE code = $3 AND approved IS NULL"#,
-      approved,
-      user_id,
-      code
+    scope: &ScopeName,
+    name: &PackageName,
+    version: &Version,
+  ) -> Result<Vec<PackageVersionArtifact>> {
+    sqlx::query_as!(
+      PackageVersionArtifact,
+      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", filename, size, sha256, user_id, upload_token_hash, upload_expires_at, uploaded_at, updated_at, created_at
+      FROM package_version_artifacts
+      WHERE scope = $1 AND name = $2 AND version = $3
+      ORDER BY filename"#,
+      scope as _,
+      name as _,
+      version as _,
     )
-    .execute(&self.pool)
-    .await?;
-    Ok(res.rows_affected() > 0)
+    .fetch_all(&mut *self.acquire().await?)
+    .await
   }
 
   #[instrument(
-    name = "Database::list_package_version_dependencies",
+    name = "Database::get_package_version_artifact",
     skip(self),
     err
   )]
-  pub async fn list_package_version_dependencies(
+  pub async fn get_package_version_artifact(
     &self,
     scope: &ScopeName,
     name: &PackageName,
     version: &Version,
-  ) -> Result<Vec<PackageVersionDependency>> {
+    filename: &str,
+  ) -> Result<Option<PackageVersionArtifact>> {
     sqlx::query_as!(
-      PackageVersionDependency,
-      r#"SELECT package_scope as "package_scope: ScopeName", package_name as "package_name: PackageName", package_version as "package_version: Version", dependency_kind as "dependency_kind: DependencyKind", dependency_name, dependency_constraint, dependency_path, updated_at, created_at
-      FROM package_version_dependencies
-      WHERE package_scope = $1 AND package_name = $2 AND package_version = $3
-      ORDER BY dependency_kind ASC, dependency_name ASC, dependency_constraint ASC, dependency_path ASC"#,
+      PackageVersionArtifact,
+      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", filename, size, sha256, user_id, upload_token_hash, upload_expires_at, uploaded_at, updated_at, created_at
+      FROM package_version_artifacts
+      WHERE scope = $1 AND name = $2 AND version = $3 AND filename = $4"#,
       scope as _,
       name as _,
-      version as _
+      version as _,
+      filename,
     )
-    .fetch_all(&self.pool)
+    .fetch_optional(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::list_package_dependents", skip(self), err)]
-  pub async fn list_package_dependents(
+  /// Creates an artifact that is waiting for its upload. A pending artifact
+  /// with the same filename is replaced. Returns `None` if an artifact with the
+  /// same filename was already uploaded.
+  #[instrument(
+    name = "Database::create_package_version_artifact",
+    skip(self, new_artifact),
+    err
+  )]
+  pub async fn create_package_version_artifact(
     &self,
-    kind: DependencyKind,
-    name: &str,
-    start: i64,
-    limit: i64,
-    versions_per_package_limit: i64,
-  ) -> Result<(usize, Vec<Dependent>)> {
-    let mut tx = self.pool.begin().await?;
-    let dependents = sqlx::query_as!(
-      Dependent,
-      r#"
-      SELECT
-        package_scope as "scope: ScopeName",
-        package_name as "name: PackageName",
-        (ARRAY_AGG(DISTINCT package_version))[:$5] as "versions!: Vec<Version>",
-        COUNT(DISTINCT package_version) as "total_versions!"
-      FROM
-        package_version_dependencies
-      WHERE
-        dependency_kind = $1 AND dependency_name = $2
-      GROUP BY package_scope, package_name
-      ORDER BY package_scope ASC, package_name ASC OFFSET $3 LIMIT $4;
-      "#,
-      kind as _,
-      name,
-      start,
-      limit,
-      versions_per_package_limit as i32,
-    )
-    .fetch_all(&mut *tx)
-    .await?;
-
-    let total_unique_package_dependents = sqlx::query!(
-      r#"SELECT COUNT(DISTINCT (package_scope, package_name)) FROM package_version_dependencies
-      WHERE dependency_kind = $1 AND dependency_name = $2;"#,
-      kind as _,
-      name,
+    new_artifact: NewPackageVersionArtifact<'_>,
+  ) -> Result<Option<PackageVersionArtifact>> {
+    sqlx::query_as!(
+      PackageVersionArtifact,
+      r#"INSERT INTO package_version_artifacts (scope, name, version, filename, size, sha256, user_id, upload_token_hash, upload_expires_at)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+      ON CONFLICT (scope, name, version, filename) DO UPDATE
+      SET size = EXCLUDED.size, sha256 = EXCLUDED.sha256, user_id = EXCLUDED.user_id, upload_token_hash = EXCLUDED.upload_token_hash, upload_expires_at = EXCLUDED.upload_expires_at, created_at = now()
+      WHERE package_version_artifacts.uploaded_at IS NULL
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", filename, size, sha256, user_id, upload_token_hash, upload_expires_at, uploaded_at, updated_at, created_at"#,
+      new_artifact.scope as _,
+      new_artifact.name as _,
+      new_artifact.version as _,
+      new_artifact.filename,
+      new_artifact.size,
+      new_artifact.sha256,
+      new_artifact.user_id,
+      new_artifact.upload_token_hash,
+      new_artifact.upload_expires_at,
     )
-    .map(|r| r.count.unwrap())
-    .fetch_one(&mut *tx)
-    .await?;
-
-    tx.commit().await?;
-
-    Ok((total_unique_package_dependents as usize, dependents))
-  }
-
-  #[instrument(name = "Database::check_bad_word", skip(self), err)]
-  pub async fn check_is_bad_word(&self, word: &str) -> Result<bool> {
-    let res = sqlx::query!("SELECT * FROM bad_words WHERE word = $1", word)
-      .fetch_optional(&self.pool)
-      .await?;
-    Ok(res.is_some())
-  }
-
-  #[instrument(name = "Database::add_bad_word_for_test", skip(self), err)]
-  pub async fn add_bad_word_for_test(&self, word: &str) -> Result<()> {
-    sqlx::query!("INSERT INTO bad_words (word) VALUES ($1)", word)
-      .execute(&self.pool)
-      .await?;
-
-    Ok(())
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
   }
 
   #[instrument(
-    name = "Database::get_latest_npm_tarball_for_version",
+    name = "Database::complete_package_version_artifact_upload",
     skip(self),
     err
   )]
-  pub async fn get_latest_npm_tarball_for_version(
+  pub async fn complete_package_version_artifact_upload(
     &self,
     scope: &ScopeName,
     name: &PackageName,
     version: &Version,
-  ) -> Result<Option<NpmTarball>> {
+    filename: &str,
+  ) -> Result<PackageVersionArtifact> {
     sqlx::query_as!(
-      NpmTarball,
-      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", revision, sha1, sha512, size, updated_at, created_at
-      FROM npm_tarballs
-      WHERE scope = $1 AND name = $2 AND version = $3
-      ORDER BY revision DESC
-      LIMIT 1"#,
+      PackageVersionArtifact,
+      r#"UPDATE package_version_artifacts
+      SET uploaded_at = now(), upload_token_hash = NULL, upload_expires_at = NULL
+      WHERE scope = $1 AND name = $2 AND version = $3 AND filename = $4
+      RETURNING scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", filename, size, sha256, user_id, upload_token_hash, upload_expires_at, uploaded_at, updated_at, created_at"#,
       scope as _,
       name as _,
-      version as _
+      version as _,
+      filename,
     )
-    .fetch_optional(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
   #[instrument(
-    name = "Database::get_latest_npm_tarball_for_version",
+    name = "Database::delete_package_version_artifact",
     skip(self),
     err
   )]
-  pub async fn get_npm_tarball(
+  pub async fn delete_package_version_artifact(
     &self,
     scope: &ScopeName,
     name: &PackageName,
     version: &Version,
-    revision: i32,
-  ) -> Result<Option<NpmTarball>> {
-    sqlx::query_as!(
-      NpmTarball,
-      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version", revision, sha1, sha512, size, updated_at, created_at
-      FROM npm_tarballs
-      WHERE scope = $1 AND name = $2 AND version = $3 AND revision = $4
-      LIMIT 1"#,
+    filename: &str,
+  ) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"DELETE FROM package_version_artifacts
+      WHERE scope = $1 AND name = $2 AND version = $3 AND filename = $4"#,
       scope as _,
       name as _,
       version as _,
-      revision,
+      filename,
+    )
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(res.rows_affected() > 0)
+  }
+
+  #[instrument(name = "Database::get_user_trust_signals", skip(self), err)]
+  pub async fn get_user_trust_signals(
+    &self,
+    user_id: Uuid,
+  ) -> Result<UserTrustSignals> {
+    sqlx::query_as!(
+      UserTrustSignals,
+      r#"SELECT
+        users.created_at,
+        users.is_staff,
+        users.email IS NOT NULL as "has_email!",
+        EXISTS (
+          SELECT 1 FROM publishing_tasks
+          JOIN package_versions ON package_versions.scope = publishing_tasks.package_scope AND package_versions.name = publishing_tasks.package_name AND package_versions.version = publishing_tasks.package_version
+          WHERE publishing_tasks.user_id = users.id AND publishing_tasks.status = 'success' AND package_versions.rekor_log_id IS NOT NULL
+        ) as "has_provenance_publish!",
+        (SELECT COUNT(*) FROM user_moderation_flags WHERE user_moderation_flags.user_id = users.id) as "moderation_flags!",
+        (SELECT COUNT(*) FROM packages JOIN scopes ON scopes.scope = packages.scope WHERE scopes.creator = users.id AND packages.created_at > now() - '1 day'::interval) as "new_packages_per_day!",
+        (SELECT COUNT(*) FROM publishing_tasks WHERE publishing_tasks.user_id = users.id AND publishing_tasks.created_at > now() - '1 day'::interval) as "publish_attempts_per_day!"
+      FROM users
+      WHERE users.id = $1"#,
+      user_id,
+    )
+    .fetch_one(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::list_user_moderation_flags", skip(self), err)]
+  pub async fn list_user_moderation_flags(
+    &self,
+    user_id: Uuid,
+  ) -> Result<Vec<UserModerationFlag>> {
+    sqlx::query_as!(
+      UserModerationFlag,
+      r#"SELECT id, user_id, reason, created_by, created_at
+      FROM user_moderation_flags
+      WHERE user_id = $1
+      ORDER BY created_at DESC"#,
+      user_id,
+    )
+    .fetch_all(&mut *self.acquire().await?)
+    .await
+  }
+
+  #[instrument(name = "Database::create_user_moderation_flag", skip(self), err)]
+  pub async fn create_user_moderation_flag(
+    &self,
+    user_id: Uuid,
+    reason: &str,
+    created_by: Uuid,
+  ) -> Result<UserModerationFlag> {
+    sqlx::query_as!(
+      UserModerationFlag,
+      r#"INSERT INTO user_moderation_flags (user_id, reason, created_by)
+      VALUES ($1, $2, $3)
+      RETURNING id, user_id, reason, created_by, created_at"#,
+      user_id,
+      reason,
+      created_by,
     )
-    .fetch_optional(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
-  #[instrument(name = "Database::list_missing_npm_tarballs", skip(self), err)]
-  pub async fn list_missing_npm_tarballs(
+  #[instrument(name = "Database::delete_user_moderation_flag", skip(self), err)]
+  pub async fn delete_user_moderation_flag(
     &self,
-    current_revision: i32,
-  ) -> Result<Vec<(ScopeName, PackageName, Version)>> {
-    // List all package versions (scope, name, version) that do not have a npm_tarball with the current_revision revision
-    sqlx::query!(
-      r#"SELECT scope as "scope: ScopeName", name as "name: PackageName", version as "version: Version"
-      FROM package_versions
-      WHERE NOT EXISTS (
-        SELECT 1
-        FROM npm_tarballs
-        WHERE npm_tarballs.scope = package_versions.scope AND npm_tarballs.name = package_versions.name AND npm_tarballs.version = package_versions.version AND npm_tarballs.revision = $1
-      )
-      ORDER BY created_at ASC
-      LIMIT 1000
-      "#,
-      current_revision,
+    user_id: Uuid,
+    flag_id: Uuid,
+  ) -> Result<bool> {
+    let res = sqlx::query!(
+      r#"DELETE FROM user_moderation_flags WHERE user_id = $1 AND id = $2"#,
+      user_id,
+      flag_id,
     )
-    .map(|r| {
-      (r.scope, r.name, r.version)
-    })
-    .fetch_all(&self.pool)
-    .await
+    .execute(&mut *self.acquire().await?)
+    .await?;
+    Ok(res.rows_affected() > 0)
   }
 
-  #[instrument(name = "Database::list_all_scopes_for_sitemap", skip(self), err)]
-  #[allow(clippy::type_complexity)]
-  pub async fn list_all_scopes_for_sitemap(
+  /// The admins of a scope, with their email addresses and whether they are
+  /// blocked.
+  #[instrument(name = "Database::list_scope_admins", skip(self), err)]
+  pub async fn list_scope_admins(
     &self,
-  ) -> Result<Vec<(ScopeName, DateTime<Utc>, Option<DateTime<Utc>>)>> {
-    sqlx::query!(
-      r#"
-        SELECT
-          scope as "scope: ScopeName",
-          updated_at,
-          (SELECT updated_at FROM packages WHERE scope = scope ORDER BY updated_At DESC LIMIT 1) as "latest_package_created_at"
-        FROM scopes
-        ORDER BY scope ASC
-        LIMIT 50000
-      "#
+    scope: &ScopeName,
+  ) -> Result<Vec<User>> {
+    sqlx::query_as!(
+      User,
+      r#"SELECT id, name, email, avatar_url, updated_at, created_at, github_id, is_blocked, is_staff, scope_limit,
+        (SELECT COUNT(created_at) FROM scope_invites WHERE target_user_id = id) as "invite_count!",
+        (SELECT COUNT(created_at) FROM scopes WHERE creator = id) as "scope_usage!"
+      FROM users
+      WHERE id IN (SELECT user_id FROM scope_members WHERE scope = $1 AND is_admin = true)
+      ORDER BY name ASC"#,
+      scope as _,
     )
-    .map(|r| (r.scope, r.updated_at, r.latest_package_created_at))
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
   #[instrument(
-    name = "Database::list_all_packages_for_sitemap",
-    skip(self),
+    name = "Database::create_scope_recovery_request",
+    skip(self, reason),
     err
   )]
-  #[allow(clippy::type_complexity)]
-  pub async fn list_all_packages_for_sitemap(
+  pub async fn create_scope_recovery_request(
     &self,
-  ) -> Result<Vec<(ScopeName, PackageName, DateTime<Utc>, DateTime<Utc>)>> {
-    sqlx::query!(
-      r#"SELECT
-        scope as "scope: ScopeName", name as "name: PackageName", updated_at,
-        (SELECT created_at FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) as "latest_version_updated_at!"
-      FROM packages
-      WHERE (SELECT version FROM package_versions WHERE scope = scope AND name = name ORDER BY version DESC LIMIT 1) IS NOT NULL
-      ORDER BY scope ASC, name ASC
-      LIMIT 50000"#
+    scope: &ScopeName,
+    requester_id: Uuid,
+    previous_creator: Uuid,
+    reason: &str,
+  ) -> Result<ScopeRecoveryRequest> {
+    sqlx::query_as!(
+      ScopeRecoveryRequest,
+      r#"INSERT INTO scope_recovery_requests (scope, requester_id, previous_creator, reason)
+      VALUES ($1, $2, $3, $4)
+      RETURNING id, scope as "scope: ScopeName", requester_id, previous_creator, reason, status as "status: ScopeRecoveryStatus", reviewed_by, reviewed_at, created_at"#,
+      scope as _,
+      requester_id,
+      previous_creator,
+      reason,
     )
-    .map(|r| {
-      (
-        r.scope,
-        r.name,
-        r.updated_at,
-        r.latest_version_updated_at,
-      )
-    })
-    .fetch_all(&self.pool)
+    .fetch_one(&mut *self.acquire().await?)
     .await
   }
 
   #[instrument(
-    name = "Database::insert_download_entries",
-    skip(self, entries),
+    name = "Database::get_scope_recovery_request",
+    skip(self),
     err
   )]
-  pub async fn insert_download_entries(
+  pub async fn get_scope_recovery_request(
     &self,
-    entries: Vec<VersionDownloadCount>,
-  ) -> Result<()> {
-    if entries.is_empty() {
-      return Ok(());
-    }
-
-    let mut tx = self.pool.begin().await?;
-
-    let mut scopes = Vec::with_capacity(entries.len());
-    let mut packages = Vec::with_capacity(entries.len());
-    let mut versions = Vec::with_capacity(entries.len());
-    let mut time_buckets = Vec::with_capacity(entries.len());
-    let mut kinds = Vec::with_capacity(entries.len());
-    let mut counts = Vec::with_capacity(entries.len());
-
-    let mut smallest_time_bucket = Utc::now();
-    let mut largest_time_bucket = DateTime::from_timestamp_nanos(0);
-
-    for entry in entries {
-      scopes.push(entry.scope);
-      packages.push(entry.package);
-      versions.push(entry.version);
-      time_buckets.push(entry.time_bucket);
-      kinds.push(entry.kind);
-      counts.push(entry.count);
-
-      if entry.time_bucket < smallest_time_bucket {
-        smallest_time_bucket = entry.time_bucket;
-      }
-      if entry.time_bucket > largest_time_bucket {
-        largest_time_bucket = entry.time_bucket;
-      }
-    }
-
-    // Upsert data into version_download_counts_4h
-    sqlx::query!(
-      r#"
-      INSERT INTO version_download_counts_4h (scope, package, version, time_bucket, kind, count)
-      SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TIMESTAMPTZ[], $5::download_kind[], $6::INT[]) as temp(scope, package, version, time_bucket, kind, count)
-      WHERE (SELECT COUNT(*) FROM package_versions WHERE package_versions.scope = temp.scope AND package_versions.name = temp.package AND version = temp.version) > 0
-      ON CONFLICT (scope, package, version, time_bucket, kind) DO UPDATE SET count = EXCLUDED.count
-      "#,
-      &scopes as _,
-      &packages as _,
-      &versions as _,
-      &time_buckets,
-      &kinds as _,
-      &counts as _,
-    )
-    .execute(&mut *tx)
-    .await?;
-
-    // Compute data in version_download_counts_24h from version_download_counts_4h between smallest_timestamp and largest_timestamp.
-    // smallest_timestamp must be truncated down to the nearest day and largest_timestamp must be truncated up to the nearest day.
-    sqlx::query!(
-      r#"
-      INSERT INTO version_download_counts_24h (scope, package, version, time_bucket, kind, count)
-      SELECT scope, package, version, date_trunc('day', time_bucket), kind, SUM(count)
-      FROM version_download_counts_4h
-      WHERE time_bucket >= date_trunc('day', $1::timestamptz) AND time_bucket < date_trunc('day', $2::timestamptz) + interval '1 day'
-      GROUP BY scope, package, version, date_trunc('day', time_bucket), kind
-      ON CONFLICT (scope, package, version, time_bucket, kind) DO UPDATE SET count = EXCLUDED.count
-      "#,
-      smallest_time_bucket,
-      largest_time_bucket,
+    id: Uuid,
+  ) -> Result<Option<ScopeRecoveryRequest>> {
+    sqlx::query_as!(
+      ScopeRecoveryRequest,
+      r#"SELECT id, scope as "scope: ScopeName", requester_id, previous_creator, reason, status as "status: ScopeRecoveryStatus", reviewed_by, reviewed_at, created_at
+      FROM scope_recovery_requests
+      WHERE id = $1"#,
+      id,
     )
-    .execute(&mut *tx)
-    .await?;
-
-    tx.commit().await?;
-
-    Ok(())
+    .fetch_optional(&mut *self.acquire().await?)
+    .await
   }
 
   #[instrument(
-    name = "Database::get_package_version_downloads_4h",
+    name = "Database::list_scope_recovery_requests",
     skip(self),
     err
   )]
-  pub async fn get_package_version_downloads_4h(
+  pub async fn list_scope_recovery_requests(
     &self,
     scope: &ScopeName,
-    name: &PackageName,
-    version: &Version,
-    start: DateTime<Utc>,
-    end: DateTime<Utc>,
-  ) -> Result<Vec<DownloadDataPoint>> {
+  ) -> Result<Vec<ScopeRecoveryRequest>> {
     sqlx::query_as!(
-      DownloadDataPoint,
-      r#"
-      SELECT time_bucket, kind as "kind: DownloadKind", count
-      FROM version_download_counts_4h
-      WHERE scope = $1 AND package = $2 AND version = $3 AND time_bucket >= $4 AND time_bucket < $5
-      ORDER BY time_bucket ASC
-      "#,
+      ScopeRecoveryRequest,
+      r#"SELECT id, scope as "scope: ScopeName", requester_id, previous_creator, reason, status as "status: ScopeRecoveryStatus", reviewed_by, reviewed_at, created_at
+      FROM scope_recovery_requests
+      WHERE scope = $1
+      ORDER BY created_at DESC"#,
       scope as _,
-      name as _,
-      version as _,
-      start,
-      end,
     )
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *self.acquire().await?)
     .await
   }
 
   #[instrument(
-    name = "Database::get_package_version_downloads_24h",
+    name = "Database::list_pending_scope_recovery_requests",
     skip(self),
     err
   )]
-  pub async fn get_package_version_downloads_24h(
+  pub async fn list_pending_scope_recovery_requests(
     &self,
-    scope: &ScopeName,
-    name: &PackageName,
-    version: &Version,
-    start: DateTime<Utc>,
-    end: DateTime<Utc>,
-  ) -> Result<Vec<DownloadDataPoint>> {
-    sqlx::query_as!(
-      DownloadDataPoint,
-      r#"
-      SELECT time_bucket, kind as "kind: DownloadKind", count
-      FROM version_download_counts_24h
-      WHERE scope = $1 AND package = $2 AND version = $3 AND time_bucket >= $4 AND time_bucket < $5
-      ORDER BY time_bucket ASC
-      "#,
-      scope as _,
-      name as _,
-      version as _,
+    start: i64,
+    limit: i64,
+  ) -> Result<(usize, Vec<ScopeRecoveryRequest>)> {
+    let mut tx = self.begin().await?;
+    let requests = sqlx::query_as!(
+      ScopeRecoveryRequest,
+      r#"SELECT id, scope as "scope: ScopeName", requester_id, previous_creator, reason, status as "status: ScopeRecoveryStatus", reviewed_by, reviewed_at, created_at
+      FROM scope_recovery_requests
+      WHERE status = 'pending'
+      ORDER BY created_at ASC
+      OFFSET $1 LIMIT $2"#,
       start,
-      end,
+      limit,
     )
-    .fetch_all(&self.pool)
-    .await
+    .fetch_all(&mut *tx)
+    .await?;
+    let total = sqlx::query!(
+      r#"SELECT COUNT(*) as "count!" FROM scope_recovery_requests WHERE status = 'pending'"#,
+    )
+    .map(|r| r.count)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok((total as usize, requests))
   }
 
-  #[instrument(name = "Database::get_package_downloads_24h", skip(self), err)]
-  pub async fn get_package_downloads_24h(
+  /// Approves or rejects a pending recovery request. Approving it makes the
+  /// requester the creator and an admin of the scope, unless the creator of
+  /// the scope has changed since the request was made. Returns `None` if the
+  /// request is not pending.
+  #[instrument(
+    name = "Database::resolve_scope_recovery_request",
+    skip(self),
+    err
+  )]
+  pub async fn resolve_scope_recovery_request(
     &self,
-    scope: &ScopeName,
-    name: &PackageName,
-    start: DateTime<Utc>,
-    end: DateTime<Utc>,
-  ) -> Result<Vec<DownloadDataPoint>> {
-    sqlx::query_as!(
-      DownloadDataPoint,
-      r#"
-    SELECT time_bucket, kind as "kind: DownloadKind", SUM(count) as "count!"
-    FROM version_download_counts_24h
-    WHERE scope = $1 AND package = $2 AND time_bucket >= $3 AND time_bucket < $4
-    GROUP BY time_bucket, kind
-    ORDER BY time_bucket ASC
-    "#,
-      scope as _,
-      name as _,
-      start,
-      end,
+    id: Uuid,
+    status: ScopeRecoveryStatus,
+    reviewed_by: Option<Uuid>,
+  ) -> Result<Option<ScopeRecoveryRequest>> {
+    let mut tx = self.begin().await?;
+    let Some(request) = sqlx::query_as!(
+      ScopeRecoveryRequest,
+      r#"UPDATE scope_recovery_requests
+      SET status = $2, reviewed_by = $3, reviewed_at = now()
+      WHERE id = $1 AND status = 'pending'
+      RETURNING id, scope as "scope: ScopeName", requester_id, previous_creator, reason, status as "status: ScopeRecoveryStatus", reviewed_by, reviewed_at, created_at"#,
+      id,
+      status as _,
+      reviewed_by,
     )
-    .fetch_all(&self.pool)
-    .await
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+      return Ok(None);
+    };
+
+    if status == ScopeRecoveryStatus::Approved {
+      let res = sqlx::query!(
+        r#"UPDATE scopes SET creator = $1 WHERE scope = $2 AND creator = $3"#,
+        request.requester_id,
+        request.scope as _,
+        request.previous_creator,
+      )
+      .execute(&mut *tx)
+      .await?;
+      if res.rows_affected() == 0 {
+        return Ok(None);
+      }
+      sqlx::query!(
+        r#"INSERT INTO scope_members (scope, user_id, is_admin)
+        VALUES ($1, $2, true)
+        ON CONFLICT (scope, user_id) DO UPDATE SET is_admin = true"#,
+        request.scope as _,
+        request.requester_id,
+      )
+      .execute(&mut *tx)
+      .await?;
+    }
+
+    tx.commit().await?;
+    Ok(Some(request))
   }
 }
 
 async fn finalize_package_creation(
-  mut tx: sqlx::Transaction<'_, sqlx::Postgres>,
+  mut tx: PooledTransaction,
   scope: &ScopeName,
 ) -> Result<Option<CreatePackageResult>, sqlx::Error> {
   let (package_limit, new_package_per_week_limit) = sqlx::query!(
@@ -3231,6 +7346,94 @@ async fn finalize_package_creation(
   Ok(None)
 }
 
+fn split_scoped_package_names(
+  packages: &[ScopedPackageName],
+) -> (Vec<ScopeName>, Vec<PackageName>) {
+  packages
+    .iter()
+    .map(|p| (p.scope.clone(), p.package.clone()))
+    .unzip()
+}
+
+async fn insert_npm_tarball_build_requests(
+  tx: &mut PooledTransaction,
+  requests: Vec<NpmTarballBuildRequest>,
+) -> Result<(), sqlx::Error> {
+  let mut scopes = Vec::with_capacity(requests.len());
+  let mut names = Vec::with_capacity(requests.len());
+  let mut counts = Vec::with_capacity(requests.len());
+  let mut last_requested_ats = Vec::with_capacity(requests.len());
+  for request in requests {
+    scopes.push(request.scope);
+    names.push(request.name);
+    counts.push(request.count);
+    last_requested_ats.push(request.last_requested_at);
+  }
+
+  // Requests for packages that do not exist (anymore) are silently dropped.
+  sqlx::query!(
+    r#"
+    INSERT INTO npm_tarball_build_requests (scope, name, request_count, last_requested_at)
+    SELECT temp.scope, temp.name, temp.count, temp.last_requested_at
+    FROM UNNEST($1::TEXT[], $2::TEXT[], $3::INT[], $4::TIMESTAMPTZ[]) as temp(scope, name, count, last_requested_at)
+    WHERE EXISTS (SELECT 1 FROM packages WHERE packages.scope = temp.scope AND packages.name = temp.name)
+    ON CONFLICT (scope, name) DO UPDATE
+    SET request_count = npm_tarball_build_requests.request_count + EXCLUDED.request_count,
+      last_requested_at = GREATEST(npm_tarball_build_requests.last_requested_at, EXCLUDED.last_requested_at)
+    "#,
+    &scopes as _,
+    &names as _,
+    &counts,
+    &last_requested_ats,
+  )
+  .execute(&mut **tx)
+  .await?;
+  Ok(())
+}
+
+async fn insert_package_collection_items(
+  tx: &mut PooledTransaction,
+  collection_id: Uuid,
+  packages: &[ScopedPackageName],
+) -> Result<(), sqlx::Error> {
+  let (scopes, names) = split_scoped_package_names(packages);
+  sqlx::query!(
+    r#"INSERT INTO package_collection_items (collection_id, scope, name, position)
+    SELECT $1, t.scope, t.name, t.position
+    FROM UNNEST($2::TEXT[], $3::TEXT[]) WITH ORDINALITY AS t(scope, name, position)"#,
+    collection_id,
+    &scopes as _,
+    &names as _,
+  )
+  .execute(&mut **tx)
+  .await?;
+  Ok(())
+}
+
+/// Returns the packages of the collections, ordered by their position.
+async fn list_package_collection_items(
+  tx: &mut PooledTransaction,
+  collection_ids: &[Uuid],
+) -> Result<Vec<(Uuid, ScopedPackageName)>, sqlx::Error> {
+  sqlx::query!(
+    r#"SELECT collection_id, scope "scope: ScopeName", name "name: PackageName"
+    FROM package_collection_items WHERE collection_id = ANY($1)
+    ORDER BY collection_id, position"#,
+    collection_ids
+  )
+  .map(|r| {
+    (
+      r.collection_id,
+      ScopedPackageName {
+        scope: r.scope,
+        package: r.name,
+      },
+    )
+  })
+  .fetch_all(&mut **tx)
+  .await
+}
+
 #[derive(Debug)]
 pub enum ScopeMemberUpdateResult {
   Ok(ScopeMember),
@@ -3240,6 +7443,7 @@ pub enum ScopeMemberUpdateResult {
 }
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum CreatePackageResult {
   Ok(Package),
   AlreadyExists,
@@ -3251,5 +7455,11 @@ pub enum CreatePackageResult {
 pub enum CreatePublishingTaskResult {
   Created(PublishingTask),
   Exists(PublishingTask),
+  /// A task for the version exists that was created with the same
+  /// idempotency key.
+  Retried(PublishingTask),
+  /// A task for the version was created concurrently, and won the race.
+  Raced(PublishingTask),
   WeeklyPublishAttemptsLimitExceeded(i32),
+  PublishFreezeActive(ScopePublishFreeze),
 }
@@ -1,6 +1,7 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 #![allow(dead_code)]
 use super::Database;
+use super::PoolOptions;
 use once_cell::sync::Lazy;
 use sqlx::Connection;
 use sqlx::Executor;
@@ -54,9 +55,13 @@ impl EphemeralDatabase {
 
     pg_execute(format!("CREATE DATABASE \"{database_name}\""));
 
-    let database = Database::connect(&database_url, 1, Duration::from_secs(5))
-      .await
-      .unwrap();
+    let database = Database::connect(
+      &database_url,
+      PoolOptions::fixed(1),
+      Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
 
     Self {
       database: Some(database),
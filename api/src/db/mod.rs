@@ -3,6 +3,7 @@ mod database;
 #[cfg(test)]
 mod ephemeral_database;
 pub(crate) mod models;
+mod pool;
 #[cfg(test)]
 mod tests;
 
@@ -10,3 +11,6 @@ pub use database::*;
 #[cfg(test)]
 pub use ephemeral_database::EphemeralDatabase;
 pub use models::*;
+pub use pool::AdaptivePoolOptions;
+pub use pool::PoolOptions;
+pub use pool::PoolStats;
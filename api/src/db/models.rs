@@ -7,6 +7,7 @@ use chrono::Utc;
 use indexmap::IndexMap;
 use serde::Deserialize;
 use serde::Serialize;
+use sqlx::types::ipnetwork::IpNetwork;
 use sqlx::types::Json;
 use sqlx::ValueRef;
 use thiserror::Error;
@@ -17,7 +18,9 @@ use crate::ids::PackageNameValidateError;
 use crate::ids::PackagePath;
 use crate::ids::ScopeName;
 use crate::ids::ScopeNameValidateError;
+use crate::ids::ScopedPackageName;
 use crate::ids::Version;
+use crate::typescript::TypeScriptFeature;
 
 #[derive(Debug, Clone)]
 pub struct User {
@@ -73,6 +76,10 @@ pub struct NewUser<'s> {
 pub enum PublishingTaskStatus {
   /// The task is scheduled to start processing.
   Pending,
+  /// The tarball of the task has been validated, but the version is only
+  /// published once `publish_at` has passed. The task is then reset to
+  /// pending and processed again.
+  Scheduled,
   /// The task is currently being processed. Processing entails unpacking the
   /// package tarball, validating the package, and publishing individual files
   /// to the registry. It is finalized by uploading the package version
@@ -97,14 +104,37 @@ pub struct PublishingTask {
   pub package_version: Version,
   pub config_file: PackagePath,
   pub user_id: Option<Uuid>,
+  pub size_report: Option<PublishingTaskSizeReport>,
+  pub warnings: PublishingTaskWarnings,
+  /// If the publish failed, the uploaded tarball and a diagnostic log are
+  /// retained for debugging until this time.
+  pub artifacts_expire_at: Option<DateTime<Utc>>,
+  /// If set, the version is not published before this time.
+  pub publish_at: Option<DateTime<Utc>>,
   pub created_at: DateTime<Utc>,
   pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PublishingTaskError {
   pub code: String,
   pub message: String,
+  /// The files of the package that caused the error, if it is about specific
+  /// files.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub files: Vec<PublishingTaskErrorFile>,
+  /// The fields of the config file that caused the error, if it is about
+  /// specific fields.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub config_fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishingTaskErrorFile {
+  pub path: String,
+  /// The 1-based line, if the error is about a specific location in the file.
+  pub line: Option<usize>,
+  pub column: Option<usize>,
 }
 
 impl sqlx::Decode<'_, sqlx::Postgres> for PublishingTaskError {
@@ -135,12 +165,220 @@ impl sqlx::Type<sqlx::Postgres> for PublishingTaskError {
   }
 }
 
+/// The sizes of a published package version, compared to the sizes of the
+/// version of the package that was published before it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PublishingTaskSizeReport {
+  pub unpacked_size: i64,
+  pub file_count: i32,
+  pub npm_tarball_size: i32,
+  pub previous: Option<PublishingTaskSizeReportPrevious>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PublishingTaskSizeReportPrevious {
+  pub version: Version,
+  pub unpacked_size: i64,
+  pub file_count: i32,
+  pub npm_tarball_size: Option<i32>,
+}
+
+impl sqlx::Decode<'_, sqlx::Postgres> for PublishingTaskSizeReport {
+  fn decode(
+    value: sqlx::postgres::PgValueRef<'_>,
+  ) -> Result<Self, Box<dyn std::error::Error + 'static + Send + Sync>> {
+    let s: sqlx::types::Json<PublishingTaskSizeReport> =
+      sqlx::Decode::<'_, sqlx::Postgres>::decode(value)?;
+    Ok(s.0)
+  }
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Postgres> for PublishingTaskSizeReport {
+  fn encode_by_ref(
+    &self,
+    buf: &mut <sqlx::Postgres as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+  ) -> sqlx::encode::IsNull {
+    <sqlx::types::Json<&PublishingTaskSizeReport> as sqlx::Encode<
+      '_,
+      sqlx::Postgres,
+    >>::encode_by_ref(&sqlx::types::Json(self), buf)
+  }
+}
+
+impl sqlx::Type<sqlx::Postgres> for PublishingTaskSizeReport {
+  fn type_info() -> <sqlx::Postgres as sqlx::Database>::TypeInfo {
+    <sqlx::types::Json<PublishingTaskSizeReport> as sqlx::Type<
+      sqlx::Postgres,
+    >>::type_info()
+  }
+}
+
+/// The GitHub Actions workflow run that requested a publish, as stated by the
+/// claims of its OIDC token.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GithubWorkflowRun {
+  /// The repository, like `denoland/deno`.
+  pub repository: String,
+  /// The path of the workflow file, like `.github/workflows/publish.yml`.
+  pub workflow_path: String,
+  /// The git ref that the workflow ran for, like `refs/heads/main`.
+  pub git_ref: String,
+  pub run_id: i64,
+}
+
+impl sqlx::Decode<'_, sqlx::Postgres> for GithubWorkflowRun {
+  fn decode(
+    value: sqlx::postgres::PgValueRef<'_>,
+  ) -> Result<Self, Box<dyn std::error::Error + 'static + Send + Sync>> {
+    let s: sqlx::types::Json<GithubWorkflowRun> =
+      sqlx::Decode::<'_, sqlx::Postgres>::decode(value)?;
+    Ok(s.0)
+  }
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Postgres> for GithubWorkflowRun {
+  fn encode_by_ref(
+    &self,
+    buf: &mut <sqlx::Postgres as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+  ) -> sqlx::encode::IsNull {
+    <sqlx::types::Json<&GithubWorkflowRun> as sqlx::Encode<
+      '_,
+      sqlx::Postgres,
+    >>::encode_by_ref(&sqlx::types::Json(self), buf)
+  }
+}
+
+impl sqlx::Type<sqlx::Postgres> for GithubWorkflowRun {
+  fn type_info() -> <sqlx::Postgres as sqlx::Database>::TypeInfo {
+    <sqlx::types::Json<GithubWorkflowRun> as sqlx::Type<sqlx::Postgres>>::type_info()
+  }
+}
+
+/// A non-fatal problem that was found while publishing a package version.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PublishingTaskWarning {
+  pub code: String,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct PublishingTaskWarnings(pub Vec<PublishingTaskWarning>);
+
+impl sqlx::Decode<'_, sqlx::Postgres> for PublishingTaskWarnings {
+  fn decode(
+    value: sqlx::postgres::PgValueRef<'_>,
+  ) -> Result<Self, Box<dyn std::error::Error + 'static + Send + Sync>> {
+    let s: sqlx::types::Json<PublishingTaskWarnings> =
+      sqlx::Decode::<'_, sqlx::Postgres>::decode(value)?;
+    Ok(s.0)
+  }
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Postgres> for PublishingTaskWarnings {
+  fn encode_by_ref(
+    &self,
+    buf: &mut <sqlx::Postgres as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+  ) -> sqlx::encode::IsNull {
+    <sqlx::types::Json<&PublishingTaskWarnings> as sqlx::Encode<
+      '_,
+      sqlx::Postgres,
+    >>::encode_by_ref(&sqlx::types::Json(self), buf)
+  }
+}
+
+impl sqlx::Type<sqlx::Postgres> for PublishingTaskWarnings {
+  fn type_info() -> <sqlx::Postgres as sqlx::Database>::TypeInfo {
+    <sqlx::types::Json<PublishingTaskWarnings> as sqlx::Type<
+      sqlx::Postgres,
+    >>::type_info()
+  }
+}
+
+/// An import of a published module that points outside of the package, with
+/// what it resolved to at publish time.
+#[derive(
+  Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct PublishedSpecifier {
+  /// The path of the importing module, like `/mod.ts`.
+  pub referrer: String,
+  /// The specifier as written in the module, like `jsr:@std/fs@1`.
+  pub specifier: String,
+  /// The resolved specifier, like `jsr:@std/fs@^1.0.0`.
+  pub resolved: String,
+  pub kind: PublishedSpecifierKind,
+  /// The names that static imports and re-exports of the referrer take from
+  /// the specifier, like `copy`. Namespace imports and `export *` are recorded
+  /// as `*`, default imports as `default`.
+  #[serde(default)]
+  pub imported_names: Vec<String>,
+}
+
+#[derive(
+  Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishedSpecifierKind {
+  Jsr,
+  Npm,
+  /// A Node.js built-in module, like `node:fs`.
+  Node,
+  /// A Bun built-in module, like `bun:sqlite`.
+  Bun,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct PublishedSpecifiers(pub Vec<PublishedSpecifier>);
+
+impl sqlx::Decode<'_, sqlx::Postgres> for PublishedSpecifiers {
+  fn decode(
+    value: sqlx::postgres::PgValueRef<'_>,
+  ) -> Result<Self, Box<dyn std::error::Error + 'static + Send + Sync>> {
+    let s: sqlx::types::Json<PublishedSpecifiers> =
+      sqlx::Decode::<'_, sqlx::Postgres>::decode(value)?;
+    Ok(s.0)
+  }
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Postgres> for PublishedSpecifiers {
+  fn encode_by_ref(
+    &self,
+    buf: &mut <sqlx::Postgres as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+  ) -> sqlx::encode::IsNull {
+    <sqlx::types::Json<&PublishedSpecifiers> as sqlx::Encode<
+      '_,
+      sqlx::Postgres,
+    >>::encode_by_ref(&sqlx::types::Json(self), buf)
+  }
+}
+
+impl sqlx::Type<sqlx::Postgres> for PublishedSpecifiers {
+  fn type_info() -> <sqlx::Postgres as sqlx::Database>::TypeInfo {
+    <sqlx::types::Json<PublishedSpecifiers> as sqlx::Type<
+      sqlx::Postgres,
+    >>::type_info()
+  }
+}
+
 pub struct NewPublishingTask<'s> {
   pub package_scope: &'s ScopeName,
   pub package_name: &'s PackageName,
   pub package_version: &'s Version,
   pub config_file: &'s PackagePath,
   pub user_id: Option<Uuid>,
+  /// The repository whose GitHub Actions workflow requested the publish, if it
+  /// was authenticated with an OIDC token.
+  pub github_repository_id: Option<i64>,
+  pub github_workflow_run: Option<GithubWorkflowRun>,
+  /// Whether the publish may go ahead during an active publish freeze of the
+  /// scope.
+  pub bypass_publish_freezes: bool,
+  pub publish_at: Option<DateTime<Utc>>,
+  /// A key chosen by the client. A retry of the publish with the same key
+  /// returns the existing task instead of failing as a duplicate.
+  pub idempotency_key: Option<&'s str>,
 }
 
 #[derive(Debug)]
@@ -154,6 +392,27 @@ pub struct Scope {
   pub publish_attempts_per_week_limit: i32,
   pub verify_oidc_actor: bool,
   pub require_publishing_from_ci: bool,
+  /// Overrides the validation level of all packages in the scope.
+  pub validation_level: Option<ValidationLevel>,
+  /// Whether the scope admins receive a weekly email about packages in the
+  /// scope with outdated dependencies.
+  pub outdated_dependencies_email: bool,
+  /// Whether the scope admins receive an email when the downloads of a
+  /// package in the scope spike or drop off unexpectedly.
+  pub download_anomaly_email: bool,
+  /// The share of exported symbols of each entrypoint that must have JSDoc,
+  /// between 0 and 1. Publishes below it get a warning.
+  pub min_docs_coverage: Option<f32>,
+  /// Whether publishes below `min_docs_coverage` fail instead.
+  pub enforce_min_docs_coverage: bool,
+}
+
+/// The content of the landing page of a scope.
+#[derive(Debug)]
+pub struct ScopeProfile {
+  pub readme: Option<String>,
+  /// The pinned packages of the scope, in the order they are shown in.
+  pub pinned_packages: Vec<PackageName>,
 }
 
 #[derive(Debug)]
@@ -163,6 +422,37 @@ pub struct ScopeUsage {
   pub publish_attempts_per_week: i32,
 }
 
+#[derive(Debug)]
+pub struct ScopeMetrics {
+  pub publishes_per_week: Vec<ScopeWeeklyPublishes>,
+  pub package_downloads: Vec<ScopePackageDownloads>,
+  /// Unexpired personal access tokens of scope members that can act on the
+  /// scope.
+  pub active_tokens: i64,
+  pub members: Vec<ScopeMemberActivity>,
+}
+
+#[derive(Debug)]
+pub struct ScopeWeeklyPublishes {
+  pub week: DateTime<Utc>,
+  pub count: i64,
+}
+
+#[derive(Debug)]
+pub struct ScopePackageDownloads {
+  pub package: PackageName,
+  pub downloads_30d: i64,
+  pub downloads_total: i64,
+}
+
+#[derive(Debug)]
+pub struct ScopeMemberActivity {
+  pub user: UserPublic,
+  pub is_admin: bool,
+  pub publishes_30d: i64,
+  pub last_published_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug)]
 pub struct ScopeMember {
   pub scope: ScopeName,
@@ -195,6 +485,98 @@ pub struct NewScopeInvite<'s> {
   pub scope: &'s ScopeName,
 }
 
+/// A window of time during which no new versions may be published to any
+/// package in a scope, unless the publish is made with an override.
+#[derive(Debug, Clone)]
+pub struct ScopePublishFreeze {
+  pub id: Uuid,
+  pub scope: ScopeName,
+  pub starts_at: DateTime<Utc>,
+  pub ends_at: DateTime<Utc>,
+  pub recurs_weekly: bool,
+  pub reason: String,
+  pub created_by: Option<Uuid>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl ScopePublishFreeze {
+  /// Returns the end of the occurrence of this freeze that contains `now`. For
+  /// one-off freezes, this is always `ends_at`.
+  pub fn occurrence_ends_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+    if !self.recurs_weekly || now < self.starts_at {
+      return self.ends_at;
+    }
+    let weeks = (now - self.starts_at).num_weeks();
+    self.ends_at + chrono::Duration::weeks(weeks)
+  }
+}
+
+#[derive(Debug)]
+pub struct NewScopePublishFreeze<'s> {
+  pub scope: &'s ScopeName,
+  pub starts_at: DateTime<Utc>,
+  pub ends_at: DateTime<Utc>,
+  pub recurs_weekly: bool,
+  pub reason: &'s str,
+  pub created_by: Uuid,
+}
+
+/// A range of IP addresses that new versions of the packages in a scope may be
+/// published from. If a scope has no ranges, publishing is allowed from
+/// anywhere.
+#[derive(Debug, Clone)]
+pub struct ScopePublishIpRange {
+  pub id: Uuid,
+  pub scope: ScopeName,
+  pub cidr: IpNetwork,
+  pub description: String,
+  pub created_by: Option<Uuid>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// A task of a queue that is backed by Postgres.
+#[derive(Debug, Clone)]
+pub struct QueuedTask {
+  pub id: Uuid,
+  pub queue: String,
+  pub body: Vec<u8>,
+  /// How often the task was claimed, including the current attempt.
+  pub attempts: i32,
+  pub run_after: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct NewScopePublishIpRange<'s> {
+  pub scope: &'s ScopeName,
+  pub cidr: IpNetwork,
+  pub description: &'s str,
+  pub created_by: Uuid,
+}
+
+/// How strictly the public API of a package is validated during publishing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, sqlx::Type)]
+#[sqlx(type_name = "validation_level", rename_all = "snake_case")]
+pub enum ValidationLevel {
+  /// Slow types are reported, and the declarations in the npm tarball are
+  /// generated from the fast check output.
+  #[default]
+  Full,
+  /// Slow types are reported, but the fast check output is not used, so the
+  /// npm tarball falls back to the source files for types.
+  LintOnly,
+  /// The public API is not validated at all.
+  None,
+}
+
+impl ValidationLevel {
+  /// The validation level to use for a package, taking the override of the
+  /// scope into account.
+  pub fn effective(scope: &Scope, package: &Package) -> Self {
+    scope.validation_level.unwrap_or(package.validation_level)
+  }
+}
+
 #[derive(Debug)]
 pub struct Package {
   pub scope: ScopeName,
@@ -202,12 +584,47 @@ pub struct Package {
   pub description: String,
   pub github_repository_id: Option<i64>,
   pub runtime_compat: RuntimeCompat,
+  pub validation_level: ValidationLevel,
   pub updated_at: DateTime<Utc>,
   pub created_at: DateTime<Utc>,
   pub version_count: i64,
+  /// How many users starred the package.
+  pub star_count: i64,
   pub latest_version: Option<String>,
   pub when_featured: Option<DateTime<Utc>>,
   pub is_archived: bool,
+  /// The keywords of the latest version of the package.
+  pub keywords: Vec<String>,
+  /// The export or markdown file that is shown at the root of the docs,
+  /// instead of the README. See `DocsLandingPage`.
+  pub docs_landing_page: Option<String>,
+  /// The package that replaces this one, if it was renamed or moved. Set
+  /// together with `successor_name`.
+  pub successor_scope: Option<ScopeName>,
+  pub successor_name: Option<PackageName>,
+  /// Set by scope admins to tell users how actively the package is
+  /// maintained. `None` if it was never set.
+  pub maintenance_status: Option<MaintenanceStatus>,
+  pub maintenance_status_updated_at: Option<DateTime<Utc>>,
+  /// When the package was suggested to be marked as unmaintained because it
+  /// has not been released in a long time. Cleared when the maintenance
+  /// status is set.
+  pub unmaintained_suggested_at: Option<DateTime<Utc>>,
+  /// Whether search engines are asked not to index the package, and the
+  /// package is left out of the sitemap. Set by staff, for example for
+  /// packages that are part of a spam campaign.
+  pub noindex: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "maintenance_status", rename_all = "snake_case")]
+pub enum MaintenanceStatus {
+  ActivelyMaintained,
+  /// Only bug and security fixes are made.
+  MaintenanceOnly,
+  /// The current maintainers are looking for someone to take over.
+  SeekingMaintainers,
+  Unmaintained,
 }
 
 #[derive(Debug)]
@@ -217,9 +634,18 @@ pub struct PackageVersion {
   pub version: Version,
   pub user_id: Option<Uuid>,
   pub exports: ExportsMap,
+  pub bin: BinMap,
+  pub engines: PackageVersionEngines,
+  pub keywords: Vec<String>,
   pub is_yanked: bool,
+  /// Why the version was yanked. Only set for yanked versions.
+  pub yank_reason_category: Option<YankReasonCategory>,
+  pub yank_reason: Option<String>,
   pub readme_path: Option<PackagePath>,
   pub uses_npm: bool,
+  pub unpacked_size: i64,
+  pub file_count: i32,
+  pub npm_tarball_size: Option<i32>,
   pub newer_versions_count: i64,
   pub lifetime_download_count: i64,
   pub meta: PackageVersionMeta,
@@ -228,6 +654,27 @@ pub struct PackageVersion {
   pub created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "yank_reason_category", rename_all = "lowercase")]
+pub enum YankReasonCategory {
+  /// The version has a security vulnerability.
+  Security,
+  /// The version does not work.
+  Broken,
+  /// The version was published by accident.
+  Accidental,
+}
+
+impl std::fmt::Display for YankReasonCategory {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      YankReasonCategory::Security => write!(f, "security issue"),
+      YankReasonCategory::Broken => write!(f, "broken"),
+      YankReasonCategory::Accidental => write!(f, "published by accident"),
+    }
+  }
+}
+
 #[derive(Debug)]
 pub struct NewPackageVersion<'s> {
   pub scope: &'s ScopeName,
@@ -236,10 +683,25 @@ pub struct NewPackageVersion<'s> {
   pub user_id: Option<&'s Uuid>,
   pub readme_path: Option<&'s PackagePath>,
   pub exports: &'s ExportsMap,
+  pub bin: &'s BinMap,
+  pub engines: &'s PackageVersionEngines,
+  pub keywords: &'s [String],
+  /// The SPDX expression of the license, as detected at publish time.
+  pub license: Option<&'s str>,
   pub uses_npm: bool,
   pub meta: PackageVersionMeta,
 }
 
+/// The repository and commit that a package version was built from, as
+/// recorded in the signing certificate of its provenance statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageVersionSource {
+  /// The URL of the repository, like `https://github.com/denoland/deno`.
+  pub repository: String,
+  /// The full hash of the commit.
+  pub commit: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase", default)]
 pub struct PackageVersionMeta {
@@ -249,6 +711,48 @@ pub struct PackageVersionMeta {
   pub percentage_documented_symbols: f32,
   pub all_fast_check: bool,
   pub has_provenance: bool,
+  /// Stats about the module graph of each entrypoint, keyed by export name.
+  pub entrypoints: IndexMap<String, EntrypointStats>,
+  /// The TypeScript features used in the declaration output of the package,
+  /// or `None` if no declarations could be generated because of slow types.
+  pub typescript_features: Option<Vec<TypeScriptFeature>>,
+  /// The exported symbols of each entrypoint that are marked `@deprecated`,
+  /// keyed by export name. Entrypoints without deprecated symbols are left out.
+  pub deprecated_symbols: IndexMap<String, Vec<DeprecatedSymbol>>,
+  /// The share of exported symbols of each entrypoint that have JSDoc,
+  /// between 0 and 1, keyed by export name.
+  pub docs_coverage: IndexMap<String, f32>,
+  /// The permissions the package declares that it needs in the `permissions`
+  /// field of its config file, or `None` if it does not declare any.
+  pub permissions: Option<PackageVersionPermissions>,
+  /// The runtime compatibility of the package when this version was
+  /// published. The runtime conditions in the exports of its npm tarballs are
+  /// derived from it, so that rebuilding a tarball does not pick up later
+  /// changes. `None` for versions published before it was recorded.
+  pub runtime_compat: Option<RuntimeCompat>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeprecatedSymbol {
+  pub name: String,
+  /// The text of the `@deprecated` tag, if any.
+  pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EntrypointStats {
+  /// The number of modules of the package statically imported by the
+  /// entrypoint, including the entrypoint itself.
+  pub module_count: u32,
+  /// The total size in bytes of those modules.
+  pub total_bytes: u64,
+  /// The number of distinct `jsr:` and `npm:` packages statically imported by
+  /// those modules.
+  pub external_dependency_count: u32,
+  /// Whether any of those modules uses top-level await.
+  pub top_level_await: bool,
 }
 
 impl sqlx::Decode<'_, sqlx::Postgres> for PackageVersionMeta {
@@ -284,6 +788,115 @@ impl sqlx::Type<sqlx::Postgres> for PackageVersionMeta {
   }
 }
 
+/// The minimum runtime versions a package version declares in the `engines`
+/// field of its config file. Versions are always in `MAJOR.MINOR.PATCH` form.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PackageVersionEngines {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deno: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub node: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub bun: Option<String>,
+}
+
+impl PackageVersionEngines {
+  pub fn iter(&self) -> impl Iterator<Item = (&'static str, &String)> {
+    [
+      ("deno", &self.deno),
+      ("node", &self.node),
+      ("bun", &self.bun),
+    ]
+    .into_iter()
+    .filter_map(|(engine, version)| Some((engine, version.as_ref()?)))
+  }
+}
+
+impl sqlx::Decode<'_, sqlx::Postgres> for PackageVersionEngines {
+  fn decode(
+    value: sqlx::postgres::PgValueRef<'_>,
+  ) -> Result<Self, Box<dyn std::error::Error + 'static + Send + Sync>> {
+    let s: sqlx::types::Json<PackageVersionEngines> =
+      sqlx::Decode::<'_, sqlx::Postgres>::decode(value)?;
+    Ok(s.0)
+  }
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Postgres> for PackageVersionEngines {
+  fn encode_by_ref(
+    &self,
+    buf: &mut <sqlx::Postgres as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+  ) -> sqlx::encode::IsNull {
+    <sqlx::types::Json<&PackageVersionEngines> as sqlx::Encode<
+      '_,
+      sqlx::Postgres,
+    >>::encode_by_ref(&Json(self), buf)
+  }
+}
+
+impl sqlx::Type<sqlx::Postgres> for PackageVersionEngines {
+  fn type_info() -> <sqlx::Postgres as sqlx::Database>::TypeInfo {
+    <sqlx::types::Json<PackageVersionEngines> as sqlx::Type<
+      sqlx::Postgres,
+    >>::type_info()
+  }
+}
+
+/// The Deno permissions a package version declares that it needs in the
+/// `permissions` field of its config file. Permissions that are not needed
+/// are `None`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PackageVersionPermissions {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub net: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub env: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub read: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub write: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub run: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sys: Option<PermissionTargets>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ffi: Option<PermissionTargets>,
+}
+
+impl PackageVersionPermissions {
+  pub const NAMES: [&'static str; 7] =
+    ["net", "env", "read", "write", "run", "sys", "ffi"];
+
+  pub fn get_mut(
+    &mut self,
+    name: &str,
+  ) -> Option<&mut Option<PermissionTargets>> {
+    match name {
+      "net" => Some(&mut self.net),
+      "env" => Some(&mut self.env),
+      "read" => Some(&mut self.read),
+      "write" => Some(&mut self.write),
+      "run" => Some(&mut self.run),
+      "sys" => Some(&mut self.sys),
+      "ffi" => Some(&mut self.ffi),
+      _ => None,
+    }
+  }
+}
+
+/// The targets a permission is needed for, like hosts for `net` or variable
+/// names for `env`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum PermissionTargets {
+  /// The permission is needed for any target. Always `true`.
+  Any(bool),
+  /// The permission is only needed for these targets.
+  Only(Vec<String>),
+}
+
 #[derive(Debug)]
 pub struct PackageFile {
   pub scope: ScopeName,
@@ -315,6 +928,9 @@ pub enum TokenType {
   Device,
   /// Personal access token obtained through the web UI.
   Personal,
+  /// Token created by a scope admin, that can only publish the packages of the
+  /// scope that it was created for.
+  Scope,
 }
 
 impl TokenType {
@@ -323,6 +939,7 @@ impl TokenType {
       Self::Web => "jsrw",
       Self::Device => "jsrd",
       Self::Personal => "jsrp",
+      Self::Scope => "jsrs",
     }
   }
 }
@@ -433,7 +1050,18 @@ pub enum PackagePublishPermission {
     package: PackageName,
   },
   #[serde(rename_all = "camelCase")]
-  Scope { scope: ScopeName },
+  Packages {
+    scope: ScopeName,
+    packages: Vec<PackageName>,
+  },
+  #[serde(rename_all = "camelCase")]
+  Scope {
+    scope: ScopeName,
+    /// Allows publishing during publish freezes of the scope, if the token
+    /// belongs to an admin of the scope.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    bypass_publish_freezes: bool,
+  },
 }
 
 impl sqlx::Decode<'_, sqlx::Postgres> for Permissions {
@@ -562,6 +1190,10 @@ pub struct GithubRepository {
   pub id: i64,
   pub owner: String,
   pub name: String,
+  /// The directory of the repository that the package lives in, if it is not
+  /// the root of the repository. This is stored on the package, as a
+  /// repository can contain many packages.
+  pub subdirectory: Option<String>,
   pub updated_at: DateTime<Utc>,
   pub created_at: DateTime<Utc>,
 }
@@ -570,6 +1202,7 @@ pub struct NewGithubRepository<'s> {
   pub id: i64,
   pub owner: &'s str,
   pub name: &'s str,
+  pub subdirectory: Option<&'s str>,
 }
 
 #[derive(Debug)]
@@ -660,6 +1293,55 @@ impl sqlx::Type<sqlx::Postgres> for ExportsMap {
   }
 }
 
+/// Mapping of executable names to the path of the module that is run when the
+/// executable is invoked, relative to the config file.
+#[derive(Debug, Clone, Default)]
+pub struct BinMap(IndexMap<String, String>);
+
+impl BinMap {
+  pub fn new(bin: IndexMap<String, String>) -> Self {
+    Self(bin)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+    self.0.iter()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+}
+
+impl sqlx::Decode<'_, sqlx::Postgres> for BinMap {
+  fn decode(
+    value: sqlx::postgres::PgValueRef<'_>,
+  ) -> Result<Self, Box<dyn std::error::Error + 'static + Send + Sync>> {
+    let s: sqlx::types::Json<IndexMap<String, String>> =
+      sqlx::Decode::<'_, sqlx::Postgres>::decode(value)?;
+    Ok(BinMap(s.0))
+  }
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Postgres> for BinMap {
+  fn encode_by_ref(
+    &self,
+    buf: &mut <sqlx::Postgres as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+  ) -> sqlx::encode::IsNull {
+    <sqlx::types::Json<&IndexMap<String, String>> as sqlx::Encode<
+      '_,
+      sqlx::Postgres,
+    >>::encode_by_ref(&sqlx::types::Json(&self.0), buf)
+  }
+}
+
+impl sqlx::Type<sqlx::Postgres> for BinMap {
+  fn type_info() -> <sqlx::Postgres as sqlx::Database>::TypeInfo {
+    <sqlx::types::Json<IndexMap<String, String>> as sqlx::Type<
+      sqlx::Postgres,
+    >>::type_info()
+  }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, sqlx::Type)]
 #[sqlx(type_name = "dependency_kind", rename_all = "lowercase")]
 pub enum DependencyKind {
@@ -667,6 +1349,29 @@ pub enum DependencyKind {
   Npm,
 }
 
+impl sqlx::postgres::PgHasArrayType for DependencyKind {
+  fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+    sqlx::postgres::PgTypeInfo::with_name("_dependency_kind")
+  }
+}
+
+/// How a dependency is declared in the npm compatible package.json. Imported
+/// packages are regular dependencies, unless they are listed in the
+/// `peerDependencies` or `optionalDependencies` field of the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, sqlx::Type)]
+#[sqlx(type_name = "dependency_type", rename_all = "lowercase")]
+pub enum DependencyType {
+  Regular,
+  Peer,
+  Optional,
+}
+
+impl sqlx::postgres::PgHasArrayType for DependencyType {
+  fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+    sqlx::postgres::PgTypeInfo::with_name("_dependency_type")
+  }
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct PackageVersionDependency {
@@ -674,6 +1379,7 @@ pub struct PackageVersionDependency {
   pub package_name: PackageName,
   pub package_version: Version,
   pub dependency_kind: DependencyKind,
+  pub dependency_type: DependencyType,
   pub dependency_name: String,
   pub dependency_constraint: String,
   pub dependency_path: String,
@@ -703,6 +1409,7 @@ pub struct NewPackageVersionDependency<'s> {
   pub package_name: &'s PackageName,
   pub package_version: &'s Version,
   pub dependency_kind: DependencyKind,
+  pub dependency_type: DependencyType,
   pub dependency_name: &'s str,
   pub dependency_constraint: &'s str,
   pub dependency_path: &'s str,
@@ -736,8 +1443,60 @@ pub struct NewNpmTarball<'s> {
   pub size: i32,
 }
 
+#[derive(Debug, Clone)]
+pub struct NpmTarballBuildRequest {
+  pub scope: ScopeName,
+  pub name: PackageName,
+  pub count: i32,
+  pub last_requested_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NpmTarballBuildProgress {
+  pub revision: i32,
+  pub total_versions: i64,
+  pub built_versions: i64,
+  pub claimed_versions: i64,
+  pub requested_packages: i64,
+  pub last_built_at: Option<DateTime<Utc>>,
+}
+
+/// A package whose search index entry is out of date.
+#[derive(Debug, Clone)]
+pub struct SearchIndexUpdate {
+  pub scope: ScopeName,
+  pub name: PackageName,
+  pub revision: i32,
+  /// The number of failed attempts at indexing the package since it last
+  /// changed.
+  pub attempts: i32,
+  pub enqueued_at: DateTime<Utc>,
+}
+
+/// The backlogs of the registry pipelines, used to report the health of the
+/// registry. Each backlog has a size and the time the oldest item in it was
+/// queued.
+#[derive(Debug, Clone)]
+pub struct RegistryBacklogs {
+  /// Publishing tasks that have not started processing yet.
+  pub pending_publishes: i64,
+  pub oldest_pending_publish: Option<DateTime<Utc>>,
+  /// Publishing tasks that are being analyzed, which includes generating the
+  /// docs of the package version.
+  pub processing_publishes: i64,
+  pub oldest_processing_publish: Option<DateTime<Utc>>,
+  /// Packages that changed, but whose search index entry has not been
+  /// updated yet.
+  pub pending_search_index_updates: i64,
+  pub oldest_pending_search_index_update: Option<DateTime<Utc>>,
+  /// Package versions without an npm tarball for the current revision.
+  /// Tarballs for these are built on demand when requested.
+  pub missing_npm_tarballs: i64,
+  pub oldest_missing_npm_tarball: Option<DateTime<Utc>>,
+}
+
 /// Keys reference https://runtime-keys.proposal.wintercg.org/.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RuntimeCompat {
   #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -792,6 +1551,29 @@ pub struct VersionDownloadCount {
   pub count: i64,
 }
 
+/// A window of the request logs that download counts were ingested from.
+#[derive(Debug, Clone)]
+pub struct DownloadIngestionCheckpoint {
+  pub window_start: DateTime<Utc>,
+  /// Until the window is over, this is the time it was last ingested up to.
+  pub window_end: DateTime<Utc>,
+  /// The sum of all download counts of the window, across all kinds.
+  pub downloads: i64,
+  pub replayed_by: Option<Uuid>,
+  pub ingested_at: DateTime<Utc>,
+}
+
+/// The publish of a package version, as exported to analytics sinks and
+/// reported to watchers.
+#[derive(Debug, Clone)]
+pub struct PublishEvent {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub version: Version,
+  pub user_id: Option<Uuid>,
+  pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone)]
 pub struct DownloadDataPoint {
   pub time_bucket: DateTime<Utc>,
@@ -817,6 +1599,10 @@ pub enum DownloadKind {
   JsrMeta,
   /// A download of the NPM tarball.
   NpmTgz,
+  /// A request for the version's $version_meta.json file or one of its module
+  /// files from a web browser, for example while viewing the package on the
+  /// website. This is not counted as a download.
+  Web,
 }
 
 impl sqlx::postgres::PgHasArrayType for DownloadKind {
@@ -824,3 +1610,330 @@ impl sqlx::postgres::PgHasArrayType for DownloadKind {
     sqlx::postgres::PgTypeInfo::with_name("_download_kind")
   }
 }
+
+/// The total downloads of a package on one day, not counting web requests.
+#[derive(Debug, Clone)]
+pub struct PackageDailyDownloads {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub day: DateTime<Utc>,
+  pub count: i64,
+}
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "download_anomaly_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadAnomalyKind {
+  /// Far more downloads than on the days before.
+  Spike,
+  /// Far fewer downloads than on the days before.
+  Cliff,
+}
+
+impl sqlx::postgres::PgHasArrayType for DownloadAnomalyKind {
+  fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+    sqlx::postgres::PgTypeInfo::with_name("_download_anomaly_kind")
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadAnomaly {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub day: DateTime<Utc>,
+  pub kind: DownloadAnomalyKind,
+  pub count: i64,
+  /// The mean of the daily downloads in the baseline period before the day.
+  pub mean: f64,
+  /// The standard deviation of the daily downloads in the baseline period.
+  pub stddev: f64,
+  pub z_score: f64,
+  pub created_at: DateTime<Utc>,
+}
+
+/// The last check of the external links in the README and docs of a package.
+#[derive(Debug, Clone)]
+pub struct PackageLinkCheck {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  /// The version whose README and docs were checked.
+  pub version: Version,
+  pub checked_link_count: i32,
+  pub checked_at: DateTime<Utc>,
+}
+
+/// An external link that was found to be broken in the last link check of a
+/// package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+  pub url: String,
+  /// Where the link was found, either the path of the README or `docs`.
+  pub source: String,
+  /// The HTTP status of the response, or `None` if no response was received.
+  pub status: Option<i32>,
+  pub error: Option<String>,
+}
+
+/// A redirect from the old name of a renamed or moved package to its new name.
+#[derive(Debug, Clone)]
+pub struct PackageRedirect {
+  pub scope: ScopeName,
+  pub name: PackageName,
+  pub target_scope: ScopeName,
+  pub target_name: PackageName,
+  /// After this time, the old name is no longer redirected.
+  pub expires_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewDownloadAnomaly {
+  pub scope: ScopeName,
+  pub package: PackageName,
+  pub day: DateTime<Utc>,
+  pub kind: DownloadAnomalyKind,
+  pub count: i64,
+  pub mean: f64,
+  pub stddev: f64,
+  pub z_score: f64,
+}
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "user_export_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum UserExportStatus {
+  /// The export is waiting to be picked up by the export task.
+  Pending,
+  /// The export task is collecting the data of the user.
+  Processing,
+  /// The export has been uploaded and the download link was emailed to the
+  /// user.
+  Success,
+  /// The export could not be completed.
+  Failure,
+}
+
+/// A read-only key that identifies a client of the public API, so that it is
+/// rate limited separately from (and more generously than) anonymous traffic.
+/// API keys carry no permissions of the user that created them.
+#[derive(Debug, Clone)]
+pub struct ApiKey {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub hash: String,
+  pub name: String,
+  pub request_count: i64,
+  pub last_used_at: Option<DateTime<Utc>>,
+  pub updated_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// The requests made by one consumer to a deprecated API route.
+#[derive(Debug, Clone)]
+pub struct DeprecatedRouteUsage {
+  pub route: String,
+  pub token_id: Option<Uuid>,
+  pub user_id: Option<Uuid>,
+  pub user_agent: String,
+  pub request_count: i64,
+  pub first_used_at: DateTime<Utc>,
+  pub last_used_at: DateTime<Utc>,
+}
+
+/// Who called a deprecated route, as it is counted in the usage report.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeprecatedRouteUsageKey {
+  pub route: &'static str,
+  pub token_id: Option<Uuid>,
+  pub user_id: Option<Uuid>,
+  pub user_agent: String,
+}
+
+/// A public, user curated list of packages.
+#[derive(Debug, Clone)]
+pub struct PackageCollection {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub name: String,
+  pub description: String,
+  /// The packages in the collection, in the order the user chose.
+  pub packages: Vec<ScopedPackageName>,
+  pub updated_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// A supplementary file, like a prebuilt binary, that was attached to a package
+/// version after it was published.
+#[derive(Debug, Clone)]
+pub struct PackageVersionArtifact {
+  pub scope: ScopeName,
+  pub name: PackageName,
+  pub version: Version,
+  pub filename: String,
+  /// The size in bytes that the uploader declared.
+  pub size: i64,
+  /// The hex encoded SHA-256 digest that the uploader declared.
+  pub sha256: String,
+  pub user_id: Option<Uuid>,
+  pub upload_token_hash: Option<String>,
+  pub upload_expires_at: Option<DateTime<Utc>>,
+  /// `None` while the file has not been uploaded yet.
+  pub uploaded_at: Option<DateTime<Utc>>,
+  pub updated_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct NewPackageVersionArtifact<'s> {
+  pub scope: &'s ScopeName,
+  pub name: &'s PackageName,
+  pub version: &'s Version,
+  pub filename: &'s str,
+  pub size: i64,
+  pub sha256: &'s str,
+  pub user_id: Option<Uuid>,
+  pub upload_token_hash: &'s str,
+  pub upload_expires_at: DateTime<Utc>,
+}
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "scope_recovery_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ScopeRecoveryStatus {
+  Pending,
+  Approved,
+  Rejected,
+}
+
+/// A request by a scope member to become the creator of a scope, after its
+/// creator was blocked.
+#[derive(Debug, Clone)]
+pub struct ScopeRecoveryRequest {
+  pub id: Uuid,
+  pub scope: ScopeName,
+  pub requester_id: Uuid,
+  pub previous_creator: Uuid,
+  pub reason: String,
+  pub status: ScopeRecoveryStatus,
+  /// The staff member that reviewed the request. Requests that are approved
+  /// without review have no reviewer.
+  pub reviewed_by: Option<Uuid>,
+  pub reviewed_at: Option<DateTime<Utc>>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// A flag that staff raised on an account. Flagged accounts are held to the
+/// limits of new accounts.
+#[derive(Debug, Clone)]
+pub struct UserModerationFlag {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub reason: String,
+  pub created_by: Option<Uuid>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// What is known about an account when its trust level is evaluated, and its
+/// recent activity that counts towards the limits of its trust level.
+#[derive(Debug, Clone)]
+pub struct UserTrustSignals {
+  pub created_at: DateTime<Utc>,
+  pub is_staff: bool,
+  pub has_email: bool,
+  /// Whether a version that the user published has a provenance attestation.
+  pub has_provenance_publish: bool,
+  pub moderation_flags: i64,
+  /// Packages created in the last day in scopes that the user created.
+  pub new_packages_per_day: i64,
+  pub publish_attempts_per_day: i64,
+}
+
+/// An export of all personal data of a user, which is made available for
+/// download for a limited time.
+#[derive(Debug, Clone)]
+pub struct UserExport {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub status: UserExportStatus,
+  pub download_token_hash: Option<String>,
+  pub expires_at: Option<DateTime<Utc>>,
+  pub updated_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// A short-lived token that a GitHub Actions workflow obtained by exchanging
+/// its OIDC token. It only allows publishing a single package version.
+#[derive(Debug, Clone)]
+pub struct OidcPublishToken {
+  pub id: Uuid,
+  pub hash: String,
+  pub repository_id: i64,
+  pub github_workflow_run: Option<GithubWorkflowRun>,
+  pub user_id: Option<Uuid>,
+  pub scope: ScopeName,
+  pub name: PackageName,
+  pub version: Version,
+  pub tarball_hash: String,
+  pub expires_at: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewOidcPublishToken<'s> {
+  pub hash: &'s str,
+  pub repository_id: i64,
+  pub github_workflow_run: Option<GithubWorkflowRun>,
+  pub user_id: Option<Uuid>,
+  pub scope: &'s ScopeName,
+  pub name: &'s PackageName,
+  pub version: &'s Version,
+  pub tarball_hash: &'s str,
+  pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "audit_finding_severity", rename_all = "lowercase")]
+pub enum AuditFindingSeverity {
+  /// The data is inconsistent, but this may resolve itself, for example once
+  /// a stuck task is retried.
+  Warning,
+  /// The data is inconsistent, and must be repaired manually.
+  Error,
+}
+
+/// A run of the nightly database consistency audit.
+#[derive(Debug, Clone)]
+pub struct ConsistencyAudit {
+  pub id: Uuid,
+  pub created_at: DateTime<Utc>,
+}
+
+/// A violation of a cross-table invariant that was found by a consistency
+/// audit.
+#[derive(Debug, Clone)]
+pub struct AuditFinding {
+  pub kind: String,
+  pub severity: AuditFindingSeverity,
+  pub subject: String,
+  pub message: String,
+}
+
+/// A package version that was deleted by staff. Its original tarball is
+/// retained until `restorable_until`, so that the version can be restored.
+#[derive(Debug, Clone)]
+pub struct DeletedPackageVersion {
+  pub scope: ScopeName,
+  pub name: PackageName,
+  pub version: Version,
+  pub publishing_task_id: Uuid,
+  pub reason: String,
+  pub deleted_by: Option<Uuid>,
+  pub restorable_until: DateTime<Utc>,
+  pub created_at: DateTime<Utc>,
+}
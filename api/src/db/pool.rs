@@ -0,0 +1,361 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::collections::VecDeque;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use sqlx::pool::PoolConnection;
+use sqlx::PgConnection;
+use sqlx::PgPool;
+use sqlx::Postgres;
+use sqlx::Result;
+use sqlx::Transaction;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tracing::info;
+
+/// The time window that acquire wait time percentiles are computed over.
+const WAIT_SAMPLE_WINDOW: Duration = Duration::from_secs(60);
+/// The maximum number of acquire wait times that are kept around.
+const MAX_WAIT_SAMPLES: usize = 4096;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolOptions {
+  /// The number of connections that may be in use at the same time. In
+  /// adaptive mode, this is the initial size.
+  pub size: u32,
+  pub adaptive: Option<AdaptivePoolOptions>,
+}
+
+/// Options for resizing the pool based on how long queries wait for a
+/// connection.
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptivePoolOptions {
+  pub min_size: u32,
+  pub max_size: u32,
+  /// The pool grows when the p95 acquire wait time is above this, and shrinks
+  /// when it is well below it and most connections are unused.
+  pub target_wait: Duration,
+}
+
+impl PoolOptions {
+  #[cfg(test)]
+  pub fn fixed(size: u32) -> Self {
+    Self {
+      size,
+      adaptive: None,
+    }
+  }
+
+  /// The number of connections that may be in use at the same time when the
+  /// pool is created.
+  pub(super) fn initial_size(&self) -> u32 {
+    match self.adaptive {
+      Some(adaptive) => self.size.max(adaptive.min_size).min(adaptive.max_size),
+      None => self.size,
+    }
+  }
+
+  /// The number of connections the underlying pool may open.
+  pub(super) fn max_connections(&self) -> u32 {
+    match self.adaptive {
+      Some(adaptive) => adaptive.max_size,
+      None => self.size,
+    }
+  }
+}
+
+/// A snapshot of the state of the connection pool.
+#[derive(Debug, Clone)]
+pub struct PoolStats {
+  /// The number of connections that may be in use at the same time.
+  pub limit: u32,
+  /// The number of connections currently in use.
+  pub in_use: u32,
+  /// The number of open connections, including idle ones.
+  pub size: u32,
+  /// The number of open connections that are idle.
+  pub idle: u32,
+  pub wait_p50: Duration,
+  pub wait_p95: Duration,
+  pub wait_p99: Duration,
+}
+
+/// Limits the number of connections that are in use at the same time, and
+/// records how long each acquire had to wait. The underlying sqlx pool can not
+/// be resized, so it is opened with the maximum number of connections and the
+/// limit is enforced here instead.
+#[derive(Debug)]
+pub(super) struct PoolLimiter {
+  semaphore: Arc<Semaphore>,
+  limit: AtomicU32,
+  acquire_timeout: Duration,
+  waits: Mutex<VecDeque<(Instant, Duration)>>,
+}
+
+impl PoolLimiter {
+  pub(super) fn new(limit: u32, acquire_timeout: Duration) -> Self {
+    Self {
+      semaphore: Arc::new(Semaphore::new(limit as usize)),
+      limit: AtomicU32::new(limit),
+      acquire_timeout,
+      waits: Mutex::new(VecDeque::new()),
+    }
+  }
+
+  async fn permit(&self) -> Result<(OwnedSemaphorePermit, Instant)> {
+    let start = Instant::now();
+    let permit = tokio::time::timeout(
+      self.acquire_timeout,
+      self.semaphore.clone().acquire_owned(),
+    )
+    .await
+    .map_err(|_| sqlx::Error::PoolTimedOut)?
+    .map_err(|_| sqlx::Error::PoolClosed)?;
+    Ok((permit, start))
+  }
+
+  pub(super) async fn acquire(
+    &self,
+    pool: &PgPool,
+  ) -> Result<PooledConnection> {
+    let (permit, start) = self.permit().await?;
+    let conn = pool.acquire().await?;
+    self.record_wait(start.elapsed());
+    Ok(PooledConnection {
+      conn,
+      _permit: permit,
+    })
+  }
+
+  pub(super) async fn begin(&self, pool: &PgPool) -> Result<PooledTransaction> {
+    let (permit, start) = self.permit().await?;
+    let tx = pool.begin().await?;
+    self.record_wait(start.elapsed());
+    Ok(PooledTransaction {
+      tx,
+      _permit: permit,
+    })
+  }
+
+  fn record_wait(&self, wait: Duration) {
+    let mut waits = self.waits.lock().unwrap();
+    if waits.len() == MAX_WAIT_SAMPLES {
+      waits.pop_front();
+    }
+    waits.push_back((Instant::now(), wait));
+  }
+
+  pub(super) fn stats(&self, pool: &PgPool) -> PoolStats {
+    let mut waits = {
+      let mut waits = self.waits.lock().unwrap();
+      while waits
+        .front()
+        .is_some_and(|(at, _)| at.elapsed() > WAIT_SAMPLE_WINDOW)
+      {
+        waits.pop_front();
+      }
+      waits.iter().map(|(_, wait)| *wait).collect::<Vec<_>>()
+    };
+    waits.sort();
+
+    let limit = self.limit.load(Ordering::Relaxed);
+    let available = self.semaphore.available_permits() as u32;
+    PoolStats {
+      limit,
+      in_use: limit.saturating_sub(available),
+      size: pool.size(),
+      idle: pool.num_idle() as u32,
+      wait_p50: percentile(&waits, 50),
+      wait_p95: percentile(&waits, 95),
+      wait_p99: percentile(&waits, 99),
+    }
+  }
+
+  async fn resize(&self, new_limit: u32) {
+    let limit = self.limit.load(Ordering::Relaxed);
+    match new_limit.cmp(&limit) {
+      std::cmp::Ordering::Greater => {
+        self.semaphore.add_permits((new_limit - limit) as usize);
+      }
+      std::cmp::Ordering::Less => {
+        // Wait for connections to be returned, and then take their permits
+        // out of circulation.
+        let Ok(permits) = self.semaphore.acquire_many(limit - new_limit).await
+        else {
+          return;
+        };
+        permits.forget();
+      }
+      std::cmp::Ordering::Equal => {}
+    }
+    self.limit.store(new_limit, Ordering::Relaxed);
+  }
+}
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+  if sorted.is_empty() {
+    return Duration::ZERO;
+  }
+  let index = (sorted.len() * p).div_ceil(100).saturating_sub(1);
+  sorted[index.min(sorted.len() - 1)]
+}
+
+/// Computes the limit for the next interval of the adaptive pool. The pool
+/// grows by a quarter when queries wait for connections for too long, and
+/// shrinks by one connection at a time when it is mostly idle.
+fn next_limit(options: &AdaptivePoolOptions, stats: &PoolStats) -> u32 {
+  let limit = stats.limit;
+  if stats.wait_p95 > options.target_wait {
+    (limit + (limit / 4).max(1)).min(options.max_size)
+  } else if stats.wait_p95 < options.target_wait / 4 && stats.in_use * 2 < limit
+  {
+    limit.saturating_sub(1).max(options.min_size)
+  } else {
+    limit
+  }
+}
+
+/// Periodically reports the pool stats, and resizes the pool if it is in
+/// adaptive mode.
+pub(super) async fn monitor_pool(
+  pool: PgPool,
+  limiter: Arc<PoolLimiter>,
+  options: PoolOptions,
+  interval: Duration,
+) {
+  let mut interval = tokio::time::interval(interval);
+  loop {
+    interval.tick().await;
+    if pool.is_closed() {
+      return;
+    }
+    let stats = limiter.stats(&pool);
+    info!(
+      pool.limit = stats.limit,
+      pool.in_use = stats.in_use,
+      pool.size = stats.size,
+      pool.idle = stats.idle,
+      pool.wait_p50_ms = stats.wait_p50.as_millis() as u64,
+      pool.wait_p95_ms = stats.wait_p95.as_millis() as u64,
+      pool.wait_p99_ms = stats.wait_p99.as_millis() as u64,
+      "database pool stats"
+    );
+    if let Some(adaptive) = &options.adaptive {
+      let new_limit = next_limit(adaptive, &stats);
+      if new_limit != stats.limit {
+        info!(
+          pool.limit = stats.limit,
+          pool.new_limit = new_limit,
+          "resizing database pool"
+        );
+        limiter.resize(new_limit).await;
+      }
+    }
+  }
+}
+
+/// A connection from the pool, which counts against the pool limit until it
+/// is dropped.
+pub struct PooledConnection {
+  conn: PoolConnection<Postgres>,
+  _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledConnection {
+  type Target = PgConnection;
+
+  fn deref(&self) -> &Self::Target {
+    &self.conn
+  }
+}
+
+impl DerefMut for PooledConnection {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.conn
+  }
+}
+
+/// A transaction on a connection from the pool, which counts against the pool
+/// limit until it is committed, rolled back, or dropped.
+pub struct PooledTransaction {
+  tx: Transaction<'static, Postgres>,
+  _permit: OwnedSemaphorePermit,
+}
+
+impl PooledTransaction {
+  pub async fn commit(self) -> Result<()> {
+    self.tx.commit().await
+  }
+
+  pub async fn rollback(self) -> Result<()> {
+    self.tx.rollback().await
+  }
+}
+
+impl Deref for PooledTransaction {
+  type Target = PgConnection;
+
+  fn deref(&self) -> &Self::Target {
+    &self.tx
+  }
+}
+
+impl DerefMut for PooledTransaction {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.tx
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::next_limit;
+  use super::percentile;
+  use super::AdaptivePoolOptions;
+  use super::PoolStats;
+
+  fn stats(limit: u32, in_use: u32, wait_p95_ms: u64) -> PoolStats {
+    PoolStats {
+      limit,
+      in_use,
+      size: limit,
+      idle: limit - in_use,
+      wait_p50: Duration::ZERO,
+      wait_p95: Duration::from_millis(wait_p95_ms),
+      wait_p99: Duration::from_millis(wait_p95_ms),
+    }
+  }
+
+  #[test]
+  fn adaptive_next_limit() {
+    let options = AdaptivePoolOptions {
+      min_size: 2,
+      max_size: 10,
+      target_wait: Duration::from_millis(100),
+    };
+    // Grows when queries wait too long, up to the maximum.
+    assert_eq!(next_limit(&options, &stats(3, 3, 200)), 4);
+    assert_eq!(next_limit(&options, &stats(8, 8, 200)), 10);
+    assert_eq!(next_limit(&options, &stats(10, 10, 200)), 10);
+    // Stays the same when the wait time is acceptable.
+    assert_eq!(next_limit(&options, &stats(5, 5, 50)), 5);
+    // Shrinks when mostly idle, down to the minimum.
+    assert_eq!(next_limit(&options, &stats(5, 1, 0)), 4);
+    assert_eq!(next_limit(&options, &stats(2, 0, 0)), 2);
+  }
+
+  #[test]
+  fn wait_percentiles() {
+    let waits = (1..=100).map(Duration::from_millis).collect::<Vec<_>>();
+    assert_eq!(percentile(&waits, 50), Duration::from_millis(50));
+    assert_eq!(percentile(&waits, 95), Duration::from_millis(95));
+    assert_eq!(percentile(&[], 95), Duration::ZERO);
+  }
+}
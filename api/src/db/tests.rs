@@ -25,10 +25,15 @@ async fn publishing_tasks() {
   let CreatePublishingTaskResult::Created(pt) = db
     .create_publishing_task(NewPublishingTask {
       user_id: Some(user_id),
+      github_repository_id: None,
+      github_workflow_run: None,
       package_scope: &scope_name,
       package_name: &package_name,
       package_version: &version,
       config_file: &config_file,
+      bypass_publish_freezes: false,
+      publish_at: None,
+      idempotency_key: None,
     })
     .await
     .unwrap()
@@ -40,10 +45,15 @@ async fn publishing_tasks() {
   let res = db
     .create_publishing_task(NewPublishingTask {
       user_id: Some(user_id),
+      github_repository_id: None,
+      github_workflow_run: None,
       package_scope: &scope_name,
       package_name: &package_name,
       package_version: &version,
       config_file: &config_file,
+      bypass_publish_freezes: false,
+      publish_at: None,
+      idempotency_key: None,
     })
     .await
     .unwrap();
@@ -71,6 +81,7 @@ async fn publishing_tasks() {
       Some(PublishingTaskError {
         code: "invalidConfigFile".to_string(),
         message: "Your config file is invalid.".to_string(),
+        ..Default::default()
       }),
     )
     .await
@@ -82,10 +93,15 @@ async fn publishing_tasks() {
   let CreatePublishingTaskResult::Created(pt4) = db
     .create_publishing_task(NewPublishingTask {
       user_id: Some(user_id),
+      github_repository_id: None,
+      github_workflow_run: None,
       package_scope: &scope_name,
       package_name: &package_name,
       package_version: &version,
       config_file: &config_file,
+      bypass_publish_freezes: false,
+      publish_at: None,
+      idempotency_key: None,
     })
     .await
     .unwrap()
@@ -333,10 +349,15 @@ async fn create_package_version_and_finalize_publishing_task() {
   let CreatePublishingTaskResult::Created(task) = db
     .create_publishing_task(NewPublishingTask {
       user_id: Some(bob.id),
+      github_repository_id: None,
+      github_workflow_run: None,
       package_scope: &scope,
       package_name: &package_name,
       package_version: &version,
       config_file: &config_file,
+      bypass_publish_freezes: false,
+      publish_at: None,
+      idempotency_key: None,
     })
     .await
     .unwrap()
@@ -353,8 +374,42 @@ async fn create_package_version_and_finalize_publishing_task() {
   .await
   .unwrap();
 
-  let package_files = vec![];
-  let package_version_dependencies = vec![];
+  let paths = (0..1000)
+    .map(|i| PackagePath::try_from(format!("/mod{i}.ts")).unwrap())
+    .collect::<Vec<_>>();
+  let package_files = paths
+    .iter()
+    .map(|path| NewPackageFile {
+      scope: &scope,
+      name: &package_name,
+      version: &version,
+      path,
+      size: 10,
+      checksum: Some("sha256-abc"),
+    })
+    .collect::<Vec<_>>();
+  let package_version_dependencies = vec![
+    NewPackageVersionDependency {
+      package_scope: &scope,
+      package_name: &package_name,
+      package_version: &version,
+      dependency_kind: DependencyKind::Jsr,
+      dependency_type: DependencyType::Regular,
+      dependency_name: "@std/fs",
+      dependency_constraint: "^1.0.0",
+      dependency_path: "",
+    },
+    NewPackageVersionDependency {
+      package_scope: &scope,
+      package_name: &package_name,
+      package_version: &version,
+      dependency_kind: DependencyKind::Npm,
+      dependency_type: DependencyType::Peer,
+      dependency_name: "react",
+      dependency_constraint: "^18",
+      dependency_path: "jsx-runtime",
+    },
+  ];
   let npm_tarball = NewNpmTarball {
     scope: &scope,
     name: &package_name,
@@ -376,11 +431,16 @@ async fn create_package_version_and_finalize_publishing_task() {
         readme_path: None,
         uses_npm: true,
         exports: &ExportsMap::mock(),
+        bin: &Default::default(),
+        engines: &Default::default(),
+        keywords: &[],
+        license: None,
         meta: Default::default(),
       },
       &package_files,
       &package_version_dependencies,
       npm_tarball,
+      &Default::default(),
     )
     .await
     .unwrap();
@@ -394,6 +454,32 @@ async fn create_package_version_and_finalize_publishing_task() {
   assert!(pv.uses_npm);
   assert_eq!(pv.readme_path, None);
   assert_eq!(pv.user_id, None);
+  assert_eq!(pv.file_count, 1000);
+  assert_eq!(pv.unpacked_size, 10000);
+
+  let files = db
+    .list_package_files(&scope, &package_name, &version)
+    .await
+    .unwrap();
+  assert_eq!(files.len(), 1000);
+  assert!(files.iter().all(
+    |file| file.size == 10 && file.checksum.as_deref() == Some("sha256-abc")
+  ));
+
+  let mut dependencies = db
+    .list_package_version_dependencies(&scope, &package_name, &version)
+    .await
+    .unwrap();
+  dependencies.sort_by(|a, b| a.dependency_name.cmp(&b.dependency_name));
+  assert_eq!(dependencies.len(), 2);
+  assert_eq!(dependencies[0].dependency_name, "@std/fs");
+  assert_eq!(dependencies[0].dependency_kind, DependencyKind::Jsr);
+  assert_eq!(dependencies[0].dependency_type, DependencyType::Regular);
+  assert_eq!(dependencies[1].dependency_name, "react");
+  assert_eq!(dependencies[1].dependency_kind, DependencyKind::Npm);
+  assert_eq!(dependencies[1].dependency_type, DependencyType::Peer);
+  assert_eq!(dependencies[1].dependency_constraint, "^18");
+  assert_eq!(dependencies[1].dependency_path, "jsx-runtime");
 
   let task = db
     .update_publishing_task_status(
@@ -443,6 +529,10 @@ async fn package_files() {
       user_id: None,
       readme_path: None,
       exports: &ExportsMap::mock(),
+      bin: &Default::default(),
+      engines: &Default::default(),
+      keywords: &[],
+      license: None,
       uses_npm: false,
       meta: Default::default(),
     })
@@ -618,3 +708,227 @@ async fn aliases() {
     "expected 'violates foreign key constraint'"
   );
 }
+
+#[tokio::test]
+async fn npm_tarball_build_claims() {
+  let db = EphemeralDatabase::create().await;
+
+  let scope = ScopeName::try_from("scope").unwrap();
+  let foo = PackageName::try_from("foo").unwrap();
+  let bar = PackageName::try_from("bar").unwrap();
+  let version = Version::try_from("1.0.0").unwrap();
+  let revision = NPM_TARBALL_REVISION as i32;
+
+  db.create_scope(&scope, uuid::Uuid::nil()).await.unwrap();
+  for name in [&foo, &bar] {
+    db.create_package(&scope, name).await.unwrap();
+    db.create_package_version_for_test(NewPackageVersion {
+      scope: &scope,
+      name,
+      version: &version,
+      exports: &ExportsMap::mock(),
+      bin: &Default::default(),
+      engines: &Default::default(),
+      keywords: &[],
+      license: None,
+      user_id: None,
+      readme_path: None,
+      uses_npm: false,
+      meta: Default::default(),
+    })
+    .await
+    .unwrap();
+  }
+
+  let progress = db.get_npm_tarball_build_progress(revision).await.unwrap();
+  assert_eq!(progress.total_versions, 2);
+  assert_eq!(progress.built_versions, 0);
+  assert_eq!(progress.claimed_versions, 0);
+  assert_eq!(progress.requested_packages, 0);
+
+  // bar was published after foo, but was requested from the npm endpoint, so
+  // it is claimed first
+  db.record_npm_tarball_build_requests(vec![
+    NpmTarballBuildRequest {
+      scope: scope.clone(),
+      name: bar.clone(),
+      count: 5,
+      last_requested_at: Utc::now(),
+    },
+    // requests for unknown packages are ignored
+    NpmTarballBuildRequest {
+      scope: scope.clone(),
+      name: PackageName::try_from("unknown").unwrap(),
+      count: 10,
+      last_requested_at: Utc::now(),
+    },
+  ])
+  .await
+  .unwrap();
+
+  let progress = db.get_npm_tarball_build_progress(revision).await.unwrap();
+  assert_eq!(progress.requested_packages, 1);
+
+  let claimed = db
+    .claim_missing_npm_tarballs(revision, 1, chrono::Duration::minutes(15))
+    .await
+    .unwrap();
+  assert_eq!(claimed, vec![(scope.clone(), bar.clone(), version.clone())]);
+
+  // a claimed version is not handed out to another worker
+  let claimed = db
+    .claim_missing_npm_tarballs(revision, 10, chrono::Duration::minutes(15))
+    .await
+    .unwrap();
+  assert_eq!(claimed, vec![(scope.clone(), foo.clone(), version.clone())]);
+
+  let claimed = db
+    .claim_missing_npm_tarballs(revision, 10, chrono::Duration::minutes(15))
+    .await
+    .unwrap();
+  assert!(claimed.is_empty());
+
+  let progress = db.get_npm_tarball_build_progress(revision).await.unwrap();
+  assert_eq!(progress.claimed_versions, 2);
+
+  db.create_npm_tarball(NewNpmTarball {
+    scope: &scope,
+    name: &bar,
+    version: &version,
+    revision,
+    sha1: "",
+    sha512: "",
+    size: 0,
+  })
+  .await
+  .unwrap();
+  db.release_npm_tarball_build_claim(&scope, &bar, &version, revision)
+    .await
+    .unwrap();
+
+  // an expired claim is picked up again
+  db.release_npm_tarball_build_claim(&scope, &foo, &version, revision)
+    .await
+    .unwrap();
+  let claimed = db
+    .claim_missing_npm_tarballs(revision, 10, chrono::Duration::zero())
+    .await
+    .unwrap();
+  assert_eq!(claimed, vec![(scope.clone(), foo.clone(), version.clone())]);
+  let claimed = db
+    .claim_missing_npm_tarballs(revision, 10, chrono::Duration::minutes(15))
+    .await
+    .unwrap();
+  assert_eq!(claimed, vec![(scope.clone(), foo.clone(), version.clone())]);
+
+  let progress = db.get_npm_tarball_build_progress(revision).await.unwrap();
+  assert_eq!(progress.built_versions, 1);
+  assert_eq!(progress.claimed_versions, 1);
+  assert_eq!(progress.requested_packages, 0);
+  assert!(progress.last_built_at.is_some());
+}
+
+#[tokio::test]
+async fn npm_not_found_requests() {
+  let db = EphemeralDatabase::create().await;
+
+  let scope = ScopeName::try_from("scope").unwrap();
+  let foo = PackageName::try_from("foo").unwrap();
+  let bar = PackageName::try_from("bar").unwrap();
+  let version = Version::try_from("1.0.0").unwrap();
+  let revision = NPM_TARBALL_REVISION as i32;
+
+  db.create_scope(&scope, uuid::Uuid::nil()).await.unwrap();
+  for name in [&foo, &bar] {
+    db.create_package(&scope, name).await.unwrap();
+    db.create_package_version_for_test(NewPackageVersion {
+      scope: &scope,
+      name,
+      version: &version,
+      exports: &ExportsMap::mock(),
+      bin: &Default::default(),
+      engines: &Default::default(),
+      keywords: &[],
+      license: None,
+      user_id: None,
+      readme_path: None,
+      uses_npm: false,
+      meta: Default::default(),
+    })
+    .await
+    .unwrap();
+  }
+  let request = |name: &PackageName, count| NpmTarballBuildRequest {
+    scope: scope.clone(),
+    name: name.clone(),
+    count,
+    last_requested_at: Utc::now(),
+  };
+
+  assert_eq!(db.get_npm_not_found_scraped_until().await.unwrap(), None);
+
+  let scraped_until = Utc::now() - chrono::Duration::minutes(15);
+  db.record_npm_not_found_requests(
+    vec![request(&foo, 10), request(&bar, 3)],
+    scraped_until,
+    1.0,
+  )
+  .await
+  .unwrap();
+  assert_eq!(
+    db.get_npm_not_found_scraped_until()
+      .await
+      .unwrap()
+      .map(|t| t.timestamp_micros()),
+    Some(scraped_until.timestamp_micros())
+  );
+
+  // older counts decay, so bar (3 / 2 + 6 = 7) is now ahead of foo (10 / 2)
+  let scraped_until = Utc::now();
+  db.record_npm_not_found_requests(vec![request(&bar, 6)], scraped_until, 0.5)
+    .await
+    .unwrap();
+  assert_eq!(
+    db.get_npm_not_found_scraped_until()
+      .await
+      .unwrap()
+      .map(|t| t.timestamp_micros()),
+    Some(scraped_until.timestamp_micros())
+  );
+
+  let claimed = db
+    .claim_missing_npm_tarballs(revision, 1, chrono::Duration::minutes(15))
+    .await
+    .unwrap();
+  assert_eq!(claimed, vec![(scope.clone(), bar.clone(), version.clone())]);
+}
+
+#[tokio::test]
+async fn connect_in_schema() {
+  let db = EphemeralDatabase::create().await;
+  let staging = Database::connect_in_schema(
+    &db.database_url,
+    Some("staging"),
+    PoolOptions::fixed(1),
+    std::time::Duration::from_secs(5),
+  )
+  .await
+  .unwrap();
+
+  let user_id = uuid::Uuid::default();
+  let scope_name: ScopeName = "scope".try_into().unwrap();
+  staging.create_scope(&scope_name, user_id).await.unwrap();
+
+  // The staging namespace does not share any data with the default one.
+  assert!(staging.get_scope(&scope_name).await.unwrap().is_some());
+  assert!(db.get_scope(&scope_name).await.unwrap().is_none());
+
+  let invalid = Database::connect_in_schema(
+    &db.database_url,
+    Some("staging; DROP TABLE users"),
+    PoolOptions::fixed(1),
+    std::time::Duration::from_secs(5),
+  )
+  .await;
+  assert!(invalid.is_err());
+}
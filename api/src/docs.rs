@@ -1,7 +1,9 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 use crate::db::GithubRepository;
+use crate::db::PackageVersionSource;
 use crate::db::RuntimeCompat;
 use crate::ids::PackageName;
+use crate::ids::PackagePath;
 use crate::ids::ScopeName;
 use crate::ids::Version;
 use anyhow::Context;
@@ -295,6 +297,44 @@ pub enum DocsRequest {
   Symbol(ModuleSpecifier, String),
 }
 
+/// The page that is shown at the root of the docs of a package, instead of
+/// the README.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocsLandingPage {
+  /// An export of the package, like `./cli`.
+  Export(String),
+  /// A markdown file in the package, like `/docs/intro.md`.
+  File(PackagePath),
+}
+
+impl DocsLandingPage {
+  pub fn parse(value: &str) -> Result<Self, String> {
+    if value == "." || value.starts_with("./") {
+      return Ok(Self::Export(value.to_string()));
+    }
+    let lowercase = value.to_ascii_lowercase();
+    if value.starts_with('/')
+      && (lowercase.ends_with(".md") || lowercase.ends_with(".markdown"))
+    {
+      let path = PackagePath::try_from(value).map_err(|err| err.to_string())?;
+      return Ok(Self::File(path));
+    }
+    Err(format!(
+      "'{value}' must be an export like './cli', or a markdown file like '/docs/intro.md'"
+    ))
+  }
+
+  /// The name of the entrypoint as used in the `entrypoint` query of the docs
+  /// endpoint, if the landing page is an export.
+  pub fn entrypoint(&self) -> Option<&str> {
+    match self {
+      Self::Export(name) if name == "." => Some("."),
+      Self::Export(name) => name.strip_prefix("./"),
+      Self::File(_) => None,
+    }
+  }
+}
+
 #[derive(Debug)]
 pub enum GeneratedDocsOutput {
   Docs(GeneratedDocs),
@@ -351,6 +391,34 @@ pub fn get_docs_info(
   }
 }
 
+/// Returns the URL under which the files of a package version can be viewed
+/// in its linked GitHub repository, at the commit that the version was built
+/// from. Returns `None` if the provenance of the version refers to a different
+/// repository than the linked one, for example because the package was linked
+/// to another repository after publishing.
+pub fn github_source_url_base(
+  github_repository: &GithubRepository,
+  source: &PackageVersionSource,
+) -> Option<String> {
+  let repository_url = format!(
+    "https://github.com/{}/{}",
+    github_repository.owner, github_repository.name
+  );
+  let source_repository = source.repository.trim_end_matches(".git");
+  if !source_repository.eq_ignore_ascii_case(&repository_url) {
+    return None;
+  }
+  let subdirectory = github_repository
+    .subdirectory
+    .as_ref()
+    .map(|subdirectory| format!("/{subdirectory}"))
+    .unwrap_or_default();
+  Some(format!(
+    "{repository_url}/blob/{}{subdirectory}",
+    source.commit
+  ))
+}
+
 fn get_url_rewriter(
   base: String,
   github_repository: Option<GithubRepository>,
@@ -362,6 +430,13 @@ fn get_url_rewriter(
     }
 
     let base = if let Some(github_repository) = &github_repository {
+      // Paths in the package are relative to the subdirectory of the
+      // repository that the package lives in.
+      let subdirectory = github_repository
+        .subdirectory
+        .as_ref()
+        .map(|subdirectory| format!("/{subdirectory}"))
+        .unwrap_or_default();
       if url.rsplit_once('.').is_some_and(|(_path, extension)| {
         matches!(
           extension,
@@ -379,12 +454,12 @@ fn get_url_rewriter(
         )
       }) {
         format!(
-          "https://raw.githubusercontent.com/{}/{}/HEAD",
+          "https://raw.githubusercontent.com/{}/{}/HEAD{subdirectory}",
           github_repository.owner, github_repository.name
         )
       } else {
         format!(
-          "https://github.com/{}/{}/blob/HEAD",
+          "https://github.com/{}/{}/blob/HEAD{subdirectory}",
           github_repository.owner, github_repository.name
         )
       }
@@ -432,6 +507,7 @@ pub fn get_generate_ctx<'a>(
   version: Version,
   version_is_latest: bool,
   github_repository: Option<GithubRepository>,
+  source_url_base: Option<String>,
   has_readme: bool,
   runtime_compat: RuntimeCompat,
   registry_url: String,
@@ -479,6 +555,7 @@ pub fn get_generate_ctx<'a>(
         version,
         version_is_latest,
         registry_url,
+        source_url_base,
         deno_types: DENO_TYPES
           .get_or_init(|| {
             serde_json::from_str(include_str!("./docs/deno_types.json"))
@@ -518,6 +595,44 @@ pub fn get_generate_ctx<'a>(
   .unwrap()
 }
 
+/// Renders a README exactly like the index page of the package docs does,
+/// including the rewriting of relative links, so that authors can preview it
+/// before publishing.
+#[instrument(name = "render_readme", skip(markdown, github_repository))]
+pub fn render_readme(
+  markdown: &str,
+  scope: ScopeName,
+  package: PackageName,
+  version: Version,
+  github_repository: Option<GithubRepository>,
+  registry_url: String,
+) -> String {
+  let ctx = get_generate_ctx(
+    DocNodesByUrl::new(),
+    None,
+    IndexMap::new(),
+    scope,
+    package,
+    version,
+    false,
+    github_repository,
+    None,
+    true,
+    RuntimeCompat::default(),
+    registry_url,
+  );
+  let render_ctx = RenderContext::new(&ctx, &[], UrlResolveKind::Root);
+  deno_doc::html::jsdoc::markdown_to_html(
+    &render_ctx,
+    markdown,
+    deno_doc::html::jsdoc::MarkdownToHTMLOptions {
+      title_only: false,
+      no_toc: false,
+    },
+  )
+  .unwrap_or_default()
+}
+
 #[allow(clippy::too_many_arguments)]
 #[instrument(
   name = "generate_docs_html",
@@ -534,6 +649,7 @@ pub fn generate_docs_html(
   version: Version,
   version_is_latest: bool,
   github_repository: Option<GithubRepository>,
+  source_url_base: Option<String>,
   readme: Option<String>,
   runtime_compat: RuntimeCompat,
   registry_url: String,
@@ -547,6 +663,7 @@ pub fn generate_docs_html(
     version,
     version_is_latest,
     github_repository,
+    source_url_base,
     readme.is_some(),
     runtime_compat,
     registry_url,
@@ -1030,6 +1147,9 @@ struct DocResolver {
   version: Version,
   version_is_latest: bool,
   registry_url: String,
+  /// Where the files of the package version can be viewed in the linked
+  /// repository, at the commit the version was built from.
+  source_url_base: Option<String>,
   deno_types: std::collections::HashSet<Vec<String>>,
   web_types: std::collections::HashMap<Vec<String>, String>,
 }
@@ -1137,6 +1257,13 @@ impl HrefResolver for DocResolver {
 
   fn resolve_source(&self, location: &Location) -> Option<String> {
     let url = Url::parse(&location.filename).ok()?;
+    if let Some(source_url_base) = &self.source_url_base {
+      return Some(format!(
+        "{source_url_base}{}#L{}",
+        url.path(),
+        location.line
+      ));
+    }
     Some(format!(
       "/@{}/{}/{}{}#L{}",
       self.scope,
@@ -1261,6 +1388,24 @@ mod tests {
   use super::*;
   use deno_doc::html::ShortPath;
 
+  #[test]
+  fn parse_docs_landing_page() {
+    let page = DocsLandingPage::parse("./cli").unwrap();
+    assert_eq!(page, DocsLandingPage::Export("./cli".to_string()));
+    assert_eq!(page.entrypoint(), Some("cli"));
+    assert_eq!(DocsLandingPage::parse(".").unwrap().entrypoint(), Some("."));
+
+    let page = DocsLandingPage::parse("/docs/intro.md").unwrap();
+    assert_eq!(
+      page,
+      DocsLandingPage::File(PackagePath::try_from("/docs/intro.md").unwrap())
+    );
+    assert_eq!(page.entrypoint(), None);
+
+    assert!(DocsLandingPage::parse("/mod.ts").is_err());
+    assert!(DocsLandingPage::parse("cli").is_err());
+  }
+
   #[test]
   fn url_resolver_test() {
     let resolver = DocResolver {
@@ -1269,6 +1414,7 @@ mod tests {
       version: Version::new("0.0.1").unwrap(),
       version_is_latest: false,
       registry_url: "".to_string(),
+      source_url_base: None,
       deno_types: Default::default(),
       web_types: Default::default(),
     };
@@ -1419,6 +1565,38 @@ mod tests {
     }
   }
 
+  #[test]
+  fn github_source_url_base() {
+    let mut repo = GithubRepository {
+      id: 0,
+      owner: "foo".to_string(),
+      name: "bar".to_string(),
+      subdirectory: None,
+      updated_at: Default::default(),
+      created_at: Default::default(),
+    };
+    let source = PackageVersionSource {
+      repository: "https://github.com/Foo/Bar".to_string(),
+      commit: "0123456789abcdef0123456789abcdef01234567".to_string(),
+    };
+    assert_eq!(
+      super::github_source_url_base(&repo, &source).as_deref(),
+      Some("https://github.com/foo/bar/blob/0123456789abcdef0123456789abcdef01234567")
+    );
+
+    repo.subdirectory = Some("packages/baz".to_string());
+    assert_eq!(
+      super::github_source_url_base(&repo, &source).as_deref(),
+      Some("https://github.com/foo/bar/blob/0123456789abcdef0123456789abcdef01234567/packages/baz")
+    );
+
+    let source = PackageVersionSource {
+      repository: "https://github.com/foo/other".to_string(),
+      ..source
+    };
+    assert_eq!(super::github_source_url_base(&repo, &source), None);
+  }
+
   #[test]
   fn test_url_rewriter() {
     let base = String::from("/@foo/bar/1.2.3");
@@ -1467,11 +1645,12 @@ mod tests {
     );
 
     let rewriter = get_url_rewriter(
-      base,
+      base.clone(),
       Some(GithubRepository {
         id: 0,
         owner: "foo".to_string(),
         name: "bar".to_string(),
+        subdirectory: None,
         updated_at: Default::default(),
         created_at: Default::default(),
       }),
@@ -1502,5 +1681,28 @@ mod tests {
       ),
       "https://raw.githubusercontent.com/foo/bar/HEAD/./src/assets/logo.svg"
     );
+
+    let rewriter = get_url_rewriter(
+      base,
+      Some(GithubRepository {
+        id: 0,
+        owner: "foo".to_string(),
+        name: "bar".to_string(),
+        subdirectory: Some("packages/baz".to_string()),
+        updated_at: Default::default(),
+        created_at: Default::default(),
+      }),
+      true,
+    );
+
+    assert_eq!(
+      rewriter(None, "src/assets/foo"),
+      "https://github.com/foo/bar/blob/HEAD/packages/baz/src/assets/foo"
+    );
+
+    assert_eq!(
+      rewriter(None, "src/assets/logo.svg"),
+      "https://raw.githubusercontent.com/foo/bar/HEAD/packages/baz/src/assets/logo.svg"
+    );
   }
 }
@@ -1,6 +1,10 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 use bytes::Bytes;
+use futures::Future;
 use futures::Stream;
+use futures::TryStreamExt;
 use hyper::HeaderMap;
 use hyper::StatusCode;
 use percent_encoding::NON_ALPHANUMERIC;
@@ -19,11 +23,15 @@ use std::time::Instant;
 use thiserror::Error;
 use tracing::error;
 use tracing::instrument;
+use tracing::warn;
 
 pub const CACHE_CONTROL_IMMUTABLE: &str = "public, max-age=31536000, immutable";
 pub const CACHE_CONTROL_DO_NOT_CACHE: &str = "no-cache, no-store, max-age=0";
 const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
+/// CRC32C (Castagnoli), the checksum GCS stores for every object.
+static CRC32C: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISCSI);
+
 #[derive(Deserialize)]
 pub struct AccessTokenResponse {
   access_token: String,
@@ -146,6 +154,48 @@ pub struct Bucket {
   pub(crate) client: Client,
   pub(crate) name: String,
   pub(crate) endpoint: String,
+  pub(crate) retry: GcsRetryConfig,
+}
+
+/// How requests to GCS are retried when they fail with a transient error.
+#[derive(Debug, Clone, Copy)]
+pub struct GcsRetryConfig {
+  /// The maximum number of attempts for each request, including the first.
+  pub max_attempts: u32,
+  /// The backoff before the first retry. It doubles with every further
+  /// attempt, up to `max_backoff`, and is randomly shortened by up to half to
+  /// spread out retries of concurrent requests.
+  pub initial_backoff: Duration,
+  pub max_backoff: Duration,
+  /// If set, a read that has not completed after this long is sent again,
+  /// and whichever response arrives first is used.
+  pub hedge_after: Option<Duration>,
+}
+
+impl Default for GcsRetryConfig {
+  fn default() -> Self {
+    Self {
+      max_attempts: 4,
+      initial_backoff: Duration::from_millis(200),
+      max_backoff: Duration::from_secs(5),
+      hedge_after: None,
+    }
+  }
+}
+
+impl GcsRetryConfig {
+  fn backoff(&self, attempt: u32) -> Duration {
+    let max = self
+      .initial_backoff
+      .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+      .min(self.max_backoff);
+    let max_ms = max.as_millis() as u64;
+    let jittered_ms = {
+      use rand::Rng;
+      rand::thread_rng().gen_range(max_ms / 2..=max_ms)
+    };
+    Duration::from_millis(jittered_ms)
+  }
 }
 
 #[derive(Debug, Error, deno_error::JsError)]
@@ -163,19 +213,31 @@ pub enum GcsError {
   Reqwest(#[from] reqwest::Error),
   #[error("stream failed: {0}")]
   Stream(anyhow::Error),
+  #[error("checksum of the uploaded object does not match: expected {expected}, got {actual}")]
+  ChecksumMismatch { expected: String, actual: String },
 }
 
 impl GcsError {
-  /// 408, 429, and 5xx errors are retryable.
+  /// 408, 429, and 5xx errors, timeouts and connection errors, and corrupted
+  /// uploads are retryable.
   /// https://cloud.google.com/storage/docs/retry-strategy
   pub fn is_retryable(&self) -> bool {
-    matches!(
-      self,
-      Self::RequestTimeout | Self::TooManyRequests | Self::Server(_)
-    )
+    match self {
+      Self::RequestTimeout
+      | Self::TooManyRequests
+      | Self::Server(_)
+      | Self::ChecksumMismatch { .. } => true,
+      Self::Reqwest(err) => err.is_timeout() || err.is_connect(),
+      Self::AccessToken(_) | Self::Stream(_) => false,
+    }
   }
 }
 
+#[derive(Deserialize)]
+struct ObjectResource {
+  crc32c: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct GcsUploadOptions<'a> {
   pub content_type: Option<Cow<'a, str>>,
@@ -190,9 +252,57 @@ impl Bucket {
       name,
       endpoint: endpoint
         .unwrap_or_else(|| "https://storage.googleapis.com".to_owned()),
+      retry: GcsRetryConfig::default(),
+    }
+  }
+
+  pub fn with_retry_config(mut self, retry: GcsRetryConfig) -> Self {
+    self.retry = retry;
+    self
+  }
+
+  /// Runs the request until it succeeds, fails with an error that is not
+  /// retryable, or runs out of attempts. Only idempotent requests may be
+  /// retried.
+  async fn with_retries<T, F, Fut>(&self, mut op: F) -> Result<T, GcsError>
+  where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, GcsError>>,
+  {
+    let mut attempt = 1;
+    loop {
+      match op().await {
+        Err(err) if err.is_retryable() && attempt < self.retry.max_attempts => {
+          let backoff = self.retry.backoff(attempt);
+          warn!(bucket = %self.name, attempt, ?backoff, "retrying GCS request: {err}");
+          tokio::time::sleep(backoff).await;
+          attempt += 1;
+        }
+        res => return res,
+      }
     }
   }
 
+  /// Sends the read again if it has not completed after the configured hedge
+  /// delay, and returns the first successful response of the two.
+  async fn hedged<T, F, Fut>(&self, mut op: F) -> Result<T, GcsError>
+  where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, GcsError>>,
+  {
+    let Some(hedge_after) = self.retry.hedge_after else {
+      return op().await;
+    };
+    let mut first = Box::pin(op());
+    if let Ok(res) = tokio::time::timeout(hedge_after, &mut first).await {
+      return res;
+    }
+    let second = Box::pin(op());
+    futures::future::select_ok([first, second])
+      .await
+      .map(|(res, _)| res)
+  }
+
   fn error_if_failed(resp: Response) -> Result<Response, GcsError> {
     let status_code = resp.status();
     if status_code == StatusCode::REQUEST_TIMEOUT {
@@ -257,6 +367,12 @@ impl Bucket {
 
   #[instrument(name = "gcp::Bucket::download", skip(self), err, fields(bucket = %self.name))]
   pub async fn download(&self, path: &str) -> Result<Option<Bytes>, GcsError> {
+    self
+      .with_retries(move || self.hedged(move || self.download_once(path)))
+      .await
+  }
+
+  async fn download_once(&self, path: &str) -> Result<Option<Bytes>, GcsError> {
     let resp = self.download_resp(path).await?;
     if resp.status() == 404 {
       return Ok(None);
@@ -266,6 +382,35 @@ impl Bucket {
     Ok(Some(bytes))
   }
 
+  /// Deletes the object at `path`. Returns `false` if it did not exist.
+  #[instrument(name = "gcp::Bucket::delete", skip(self), err, fields(bucket = %self.name))]
+  pub async fn delete(&self, path: &str) -> Result<bool, GcsError> {
+    self.with_retries(move || self.delete_once(path)).await
+  }
+
+  async fn delete_once(&self, path: &str) -> Result<bool, GcsError> {
+    let path = percent_encoding::utf8_percent_encode(path, NON_ALPHANUMERIC);
+    let url =
+      format!("{}/storage/v1/b/{}/o/{}", self.endpoint, self.name, path);
+    let token = self
+      .client
+      .get_access_token()
+      .await
+      .map_err(GcsError::AccessToken)?;
+    let resp = self
+      .client
+      .http()
+      .delete(url)
+      .bearer_auth(token)
+      .send()
+      .await?;
+    if resp.status() == 404 {
+      return Ok(false);
+    }
+    Bucket::error_if_failed(resp)?;
+    Ok(true)
+  }
+
   #[instrument(name = "gcp::Bucket::download_stream", skip(self), err, fields(bucket = %self.name))]
   pub async fn download_stream(
     &self,
@@ -288,6 +433,24 @@ impl Bucket {
   ) -> Result<
     Option<(HeaderMap, impl Stream<Item = Result<Bytes, reqwest::Error>>)>,
     GcsError,
+  > {
+    // Only the request is retried. Once the body is streaming, errors are
+    // passed on to the consumer of the stream.
+    self
+      .with_retries(move || {
+        self.download_stream_once(path, offset, accept_encoding)
+      })
+      .await
+  }
+
+  async fn download_stream_once(
+    &self,
+    path: &str,
+    offset: Option<usize>,
+    accept_encoding: &str,
+  ) -> Result<
+    Option<(HeaderMap, impl Stream<Item = Result<Bytes, reqwest::Error>>)>,
+    GcsError,
   > {
     let path = percent_encoding::utf8_percent_encode(path, NON_ALPHANUMERIC);
     let url = format!(
@@ -322,12 +485,14 @@ impl Bucket {
     Ok(Some((headers, resp.bytes_stream())))
   }
 
+  /// Uploads the object, and returns the CRC32C checksum that GCS computed
+  /// for it, if it reported one.
   async fn upload_inner(
     &self,
     path: &str,
     mut media_part: Part,
     options: &GcsUploadOptions<'_>,
-  ) -> Result<(), GcsError> {
+  ) -> Result<Option<String>, GcsError> {
     let url = format!(
       "{}/upload/storage/v1/b/{}/o?uploadType=multipart",
       self.endpoint, self.name
@@ -354,9 +519,23 @@ impl Bucket {
       .part("media", media_part);
 
     let resp = request_builder.multipart(form).send().await?;
-    Bucket::error_if_failed(resp)?;
+    let resp = Bucket::error_if_failed(resp)?;
+    let object = resp.json::<ObjectResource>().await.ok();
 
-    Ok(())
+    Ok(object.and_then(|object| object.crc32c))
+  }
+
+  fn verify_crc32c(
+    actual: Option<String>,
+    expected: u32,
+  ) -> Result<(), GcsError> {
+    let expected = BASE64_STANDARD.encode(expected.to_be_bytes());
+    match actual {
+      Some(actual) if actual != expected => {
+        Err(GcsError::ChecksumMismatch { expected, actual })
+      }
+      _ => Ok(()),
+    }
   }
 
   #[instrument(name = "gcp::Bucket::upload", skip(self, data), err, fields(bucket = %self.name, size = %data.len()))]
@@ -366,8 +545,15 @@ impl Bucket {
     data: Bytes,
     options: &GcsUploadOptions<'_>,
   ) -> Result<(), GcsError> {
+    let expected = CRC32C.checksum(&data);
     self
-      .upload_inner(path, Part::bytes(Cow::Owned(data.to_vec())), options)
+      .with_retries(move || {
+        let part = Part::bytes(Cow::Owned(data.to_vec()));
+        async move {
+          let actual = self.upload_inner(path, part, options).await?;
+          Self::verify_crc32c(actual, expected)
+        }
+      })
       .await
   }
 
@@ -380,9 +566,20 @@ impl Bucket {
     stream: S,
     options: &GcsUploadOptions<'_>,
   ) -> Result<(), GcsError> {
-    self
+    // The stream can only be consumed once, so the upload is not retried
+    // here. `BucketWithQueue` buffers streams to retry them.
+    let digest = Arc::new(Mutex::new(CRC32C.digest()));
+    let stream = stream.inspect_ok({
+      let digest = digest.clone();
+      move |chunk| digest.lock().unwrap().update(chunk)
+    });
+    let actual = self
       .upload_inner(path, Part::stream(Body::wrap_stream(stream)), options)
-      .await
+      .await?;
+    let expected =
+      std::mem::replace(&mut *digest.lock().unwrap(), CRC32C.digest())
+        .finalize();
+    Self::verify_crc32c(actual, expected)
   }
 }
 
@@ -533,6 +730,48 @@ impl BigQuery {
     let json = resp.json().await?;
     Ok(json)
   }
+
+  /// Streams rows into a table. Rows with the same insert id that are inserted
+  /// within a short time of each other are only inserted once.
+  #[instrument("gcp::BigQuery::insert_all", skip(self, rows), err, fields(project = %self.project, rows = rows.len()))]
+  pub async fn insert_all(
+    &self,
+    dataset: &str,
+    table: &str,
+    rows: &[(String, serde_json::Value)],
+  ) -> Result<(), anyhow::Error> {
+    let url = format!(
+      "{}/bigquery/v2/projects/{}/datasets/{}/tables/{}/insertAll",
+      self.endpoint, self.project, dataset, table
+    );
+    let rows = rows
+      .iter()
+      .map(|(insert_id, json)| json!({ "insertId": insert_id, "json": json }))
+      .collect::<Vec<_>>();
+    let token = self.client.get_access_token().await?;
+    let resp = self
+      .client
+      .http()
+      .post(url)
+      .bearer_auth(token)
+      .json(&json!({ "rows": rows }))
+      .send()
+      .await?;
+    let status = resp.status();
+    if status != StatusCode::OK {
+      let body = resp.text().await?;
+      return Err(anyhow::anyhow!(
+        "Failed to insert rows into BigQuery (status={status}): {body}"
+      ));
+    }
+    let res: serde_json::Value = resp.json().await?;
+    if let Some(errors) = res.get("insertErrors") {
+      return Err(anyhow::anyhow!(
+        "Failed to insert rows into BigQuery: {errors}"
+      ));
+    }
+    Ok(())
+  }
 }
 
 /// Fake Google Cloud Storage
@@ -661,4 +900,26 @@ mod tests {
     let response = bucket.download("does_not_exist.txt").await.unwrap();
     assert!(response.is_none());
   }
+
+  #[test]
+  fn verify_crc32c() {
+    let expected = CRC32C.checksum(b"hello world");
+    Bucket::verify_crc32c(Some("yZRlqg==".to_owned()), expected).unwrap();
+    Bucket::verify_crc32c(None, expected).unwrap();
+    let err =
+      Bucket::verify_crc32c(Some("AAAAAA==".to_owned()), expected).unwrap_err();
+    assert!(err.is_retryable());
+  }
+
+  #[test]
+  fn retry_backoff() {
+    let retry = GcsRetryConfig::default();
+    for attempt in 1..10 {
+      let backoff = retry.backoff(attempt);
+      let max =
+        (retry.initial_backoff * 2u32.pow(attempt - 1)).min(retry.max_backoff);
+      assert!(backoff <= max, "{backoff:?} > {max:?}");
+      assert!(backoff >= max / 2, "{backoff:?} < {:?}", max / 2);
+    }
+  }
 }
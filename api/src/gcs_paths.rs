@@ -5,6 +5,7 @@ use crate::ids::PackagePath;
 use crate::ids::ScopeName;
 use crate::ids::Version;
 use crate::npm::NpmMappedJsrPackageName;
+use uuid::Uuid;
 
 pub fn file_path(
   scope: &ScopeName,
@@ -43,6 +44,11 @@ pub fn version_metadata(
   format!("@{scope}/{package_name}/{version}_meta.json")
 }
 
+/// The detached signatures of the metadata file at `path`.
+pub fn metadata_signature(path: &str) -> String {
+  format!("{path}.sig")
+}
+
 pub fn npm_version_manifest_path(
   scope: &ScopeName,
   package_name: &PackageName,
@@ -54,6 +60,30 @@ pub fn npm_version_manifest_path(
   format!("{npm_mapped_package_name}")
 }
 
+/// An artifact that a maintainer attached to a package version, in the
+/// artifacts bucket.
+pub fn package_version_artifact_path(
+  scope: &ScopeName,
+  package_name: &PackageName,
+  version: &Version,
+  filename: &str,
+) -> String {
+  format!("@{scope}/{package_name}/{version}/{filename}")
+}
+
+/// The export of the personal data of a user, in the publishing bucket. It is
+/// deleted once the download link expires.
+pub fn user_export_path(id: Uuid) -> String {
+  format!("user_exports/{id}.json")
+}
+
+/// The retained tarball of a package version that was deleted by staff, in the
+/// publishing bucket. It is deleted once the version can no longer be
+/// restored.
+pub fn deleted_version_tarball_path(publishing_task_id: Uuid) -> String {
+  format!("deleted_versions/{publishing_task_id}.tar.gz")
+}
+
 pub fn npm_tarball_path(
   scope: &ScopeName,
   package_name: &PackageName,
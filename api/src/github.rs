@@ -5,9 +5,12 @@ use std::fmt::Display;
 use std::str::FromStr;
 
 use crate::api::ApiError;
+use crate::db::GithubWorkflowRun;
 use crate::util::ApiResult;
 use crate::util::USER_AGENT;
 use anyhow::Context;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine as _;
 use hyper::StatusCode;
 use percent_encoding::AsciiSet;
 use percent_encoding::CONTROLS;
@@ -108,6 +111,48 @@ impl GitHubUserClient {
     let repo: Repository = res.json().await?;
     Ok(Some(repo))
   }
+
+  /// Gets the contents of a file on the default branch of a repository.
+  /// Returns `None` if the file does not exist, or is not a regular file.
+  #[instrument(name = "GitHubUserClient::get_file_contents", skip(self), err)]
+  pub async fn get_file_contents(
+    &self,
+    owner: &str,
+    name: &str,
+    path: &str,
+  ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+    let owner = sanitize_url_part(owner);
+    let name = sanitize_url_part(name);
+    let path = sanitize_url_part(path);
+    let res = self
+      .request(&format!("/repos/{owner}/{name}/contents/{path}"))
+      .await?;
+    let status = res.status();
+    if status == StatusCode::NOT_FOUND {
+      return Ok(None);
+    } else if !status.is_success() {
+      let response = res.text().await?;
+      return Err(anyhow::anyhow!(
+        "failed to get file '{path}' in repository '{owner}/{name}' (status {status}): {response}",
+      ));
+    }
+    // Directories are returned as an array of entries.
+    let Ok(file) = res.json::<FileContents>().await else {
+      return Ok(None);
+    };
+    if file.r#type != "file" {
+      return Ok(None);
+    }
+    let content = file
+      .content
+      .chars()
+      .filter(|c| !c.is_ascii_whitespace())
+      .collect::<String>();
+    let content = BASE64_STANDARD
+      .decode(content)
+      .context("failed to decode file contents")?;
+    Ok(Some(content))
+  }
 }
 
 #[derive(Debug, Deserialize, Clone, Eq, PartialEq)]
@@ -137,6 +182,13 @@ pub struct Repository {
   pub permissions: RepositoryPermissions,
 }
 
+#[derive(Deserialize)]
+struct FileContents {
+  r#type: String,
+  #[serde(default)]
+  content: String,
+}
+
 #[derive(Deserialize)]
 pub struct RepositoryOwner {
   pub login: String,
@@ -194,6 +246,38 @@ pub struct GitHubClaims {
   #[serde(deserialize_with = "deserialize_number_from_string")]
   pub actor_id: i64,
   pub aud: String,
+  /// The repository, like `denoland/deno`.
+  #[serde(default)]
+  pub repository: Option<String>,
+  /// The workflow file and ref, like
+  /// `denoland/deno/.github/workflows/publish.yml@refs/heads/main`.
+  #[serde(default)]
+  pub workflow_ref: Option<String>,
+  #[serde(default, rename = "ref")]
+  pub git_ref: Option<String>,
+  #[serde(default)]
+  pub run_id: Option<String>,
+}
+
+impl GitHubClaims {
+  /// The workflow run that the token was issued to, if the token has all the
+  /// claims that describe it.
+  pub fn workflow_run(&self) -> Option<GithubWorkflowRun> {
+    let repository = self.repository.clone()?;
+    let workflow_path = self
+      .workflow_ref
+      .as_deref()?
+      .strip_prefix(&format!("{repository}/"))?
+      .split_once('@')?
+      .0
+      .to_owned();
+    Some(GithubWorkflowRun {
+      repository,
+      workflow_path,
+      git_ref: self.git_ref.clone()?,
+      run_id: self.run_id.as_deref()?.parse().ok()?,
+    })
+  }
 }
 
 #[instrument(name = "github::verify_oidc_token", err, skip(token))]
@@ -251,3 +335,39 @@ pub async fn verify_oidc_token(token: &str) -> ApiResult<GitHubClaims> {
 
   Ok(decoded.claims)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn claims_workflow_run() {
+    let claims: GitHubClaims = serde_json::from_value(serde_json::json!({
+      "repository_id": "123",
+      "actor_id": "456",
+      "aud": "{}",
+      "repository": "denoland/deno",
+      "workflow_ref": "denoland/deno/.github/workflows/publish.yml@refs/tags/v1.0.0",
+      "ref": "refs/tags/v1.0.0",
+      "run_id": "9876543210",
+    }))
+    .unwrap();
+    assert_eq!(
+      claims.workflow_run(),
+      Some(GithubWorkflowRun {
+        repository: "denoland/deno".into(),
+        workflow_path: ".github/workflows/publish.yml".into(),
+        git_ref: "refs/tags/v1.0.0".into(),
+        run_id: 9876543210,
+      })
+    );
+
+    let claims: GitHubClaims = serde_json::from_value(serde_json::json!({
+      "repository_id": 123,
+      "actor_id": 456,
+      "aud": "{}",
+    }))
+    .unwrap();
+    assert_eq!(claims.workflow_run(), None);
+  }
+}
@@ -2,16 +2,22 @@
 use hyper::Body;
 use hyper::Request;
 use routerify::prelude::RequestExt;
+use serde::Deserialize;
+use serde::Serialize;
+use std::net::IpAddr;
 use uuid::Uuid;
 
 use crate::api::ApiError;
 use crate::db::Database;
+use crate::db::GithubWorkflowRun;
+use crate::db::OidcPublishToken;
 use crate::db::PackagePublishPermission;
 use crate::db::Permission;
 use crate::db::Permissions;
 use crate::db::Token;
 use crate::db::TokenType;
 use crate::db::User;
+use crate::github::GitHubClaims;
 use crate::ids::PackageName;
 use crate::ids::ScopeName;
 use crate::ids::Version;
@@ -23,9 +29,29 @@ pub struct IamHandler<'s> {
   permissions: Option<Permissions>,
   interactive: bool,
   sudo: bool,
+  /// The IP address the request was made from, if known.
+  client_ip: Option<IpAddr>,
 }
 
 impl<'s> IamHandler<'s> {
+  pub fn new(db: &'s Database, info: IamInfo) -> Self {
+    let IamInfo {
+      principal,
+      permissions,
+      interactive,
+      sudo,
+      token_id: _,
+    } = info;
+    IamHandler {
+      db,
+      principal,
+      permissions,
+      interactive,
+      sudo,
+      client_ip: None,
+    }
+  }
+
   pub fn is_anonymous(&self) -> bool {
     matches!(self.principal, Principal::Anonymous)
   }
@@ -34,6 +60,13 @@ impl<'s> IamHandler<'s> {
     &self,
     scope: &ScopeName,
   ) -> Result<(), ApiError> {
+    self.scope_write_access(scope).await.map(|_| ())
+  }
+
+  async fn scope_write_access(
+    &self,
+    scope: &ScopeName,
+  ) -> Result<IamRule, ApiError> {
     if self.permissions.is_some() {
       // There is no specific permission that allows scope write access, so if
       // the permissions are restricted, this action is also restricted.
@@ -41,14 +74,16 @@ impl<'s> IamHandler<'s> {
     }
 
     match &self.principal {
-      Principal::User(user) if user.is_staff && self.sudo => Ok(()),
+      Principal::User(user) if user.is_staff && self.sudo => {
+        Ok(IamRule::StaffSudo)
+      }
       Principal::User(user) => {
         self
           .db
           .get_scope_member(scope, user.id)
           .await?
           .ok_or(ApiError::ActorNotScopeMember)?;
-        Ok(())
+        Ok(IamRule::ScopeMember)
       }
       Principal::GitHubActions { .. } => Err(ApiError::ActorNotAuthorized),
       Principal::Anonymous => Err(ApiError::MissingAuthentication),
@@ -59,6 +94,13 @@ impl<'s> IamHandler<'s> {
     &self,
     scope: &ScopeName,
   ) -> Result<(), ApiError> {
+    self.scope_admin_access(scope).await.map(|_| ())
+  }
+
+  async fn scope_admin_access(
+    &self,
+    scope: &ScopeName,
+  ) -> Result<IamRule, ApiError> {
     if self.permissions.is_some() {
       // There is no specific permission that allows scope admin access, so if
       // the permissions are restricted, this action is also restricted.
@@ -66,7 +108,9 @@ impl<'s> IamHandler<'s> {
     }
 
     match &self.principal {
-      Principal::User(user) if user.is_staff && self.sudo => Ok(()),
+      Principal::User(user) if user.is_staff && self.sudo => {
+        Ok(IamRule::StaffSudo)
+      }
       Principal::User(user) => {
         let scope_member = self
           .db
@@ -76,7 +120,7 @@ impl<'s> IamHandler<'s> {
         if !scope_member.is_admin {
           return Err(ApiError::ActorNotScopeAdmin);
         }
-        Ok(())
+        Ok(IamRule::ScopeAdmin)
       }
       Principal::GitHubActions { .. } => Err(ApiError::ActorNotAuthorized),
       Principal::Anonymous => Err(ApiError::MissingAuthentication),
@@ -113,12 +157,74 @@ impl<'s> IamHandler<'s> {
   }
 
   pub async fn check_publish_access(
+    &self,
+    scope: &ScopeName,
+    package: &PackageName,
+    version: &Version,
+  ) -> Result<(PublishAccessRestriction, Option<Uuid>), ApiError> {
+    let access = self.publish_access(scope, package, version).await?;
+    if access.rule != IamRule::StaffSudo {
+      self.check_publish_ip(scope).await?;
+    }
+    Ok((access.restriction, access.user_id))
+  }
+
+  /// Checks that the request comes from one of the IP ranges that the scope
+  /// allows publishing from, if it restricts publishing to any.
+  async fn check_publish_ip(&self, scope: &ScopeName) -> Result<(), ApiError> {
+    let ranges = self.db.list_scope_publish_ip_ranges(scope).await?;
+    if ranges.is_empty() {
+      return Ok(());
+    }
+    let allowed = self
+      .client_ip
+      .is_some_and(|ip| ranges.iter().any(|range| range.cidr.contains(ip)));
+    if !allowed {
+      return Err(ApiError::PublishIpNotAllowed);
+    }
+    Ok(())
+  }
+
+  /// Whether a publish to the scope may go ahead during an active publish
+  /// freeze. Staff can override freezes with sudo. Scope admins can override
+  /// them with a token that was explicitly granted the override.
+  pub async fn can_bypass_publish_freezes(
+    &self,
+    scope_: &ScopeName,
+  ) -> Result<bool, ApiError> {
+    match &self.principal {
+      Principal::User(user) if user.is_staff && self.sudo => Ok(true),
+      Principal::User(user) => {
+        let granted = self.permissions.as_ref().is_some_and(|permissions| {
+          permissions.0.iter().any(|permission| {
+            matches!(
+              permission,
+              Permission::PackagePublish(PackagePublishPermission::Scope {
+                scope,
+                bypass_publish_freezes: true,
+              }) if scope == scope_
+            )
+          })
+        });
+        if !granted {
+          return Ok(false);
+        }
+        let scope_member = self.db.get_scope_member(scope_, user.id).await?;
+        Ok(scope_member.is_some_and(|member| member.is_admin))
+      }
+      Principal::GitHubActions { .. } => Ok(false),
+      Principal::Anonymous => Ok(false),
+    }
+  }
+
+  async fn publish_access(
     &self,
     scope_: &ScopeName,
     package_: &PackageName,
     version_: &Version,
-  ) -> Result<(PublishAccessRestriction, Option<Uuid>), ApiError> {
-    let access_restriction = if let Some(permissions) = &self.permissions {
+  ) -> Result<PublishAccess, ApiError> {
+    let (restriction, permission) = if let Some(permissions) = &self.permissions
+    {
       let access_restriction =
         permissions
           .0
@@ -133,30 +239,48 @@ impl<'s> IamHandler<'s> {
               && package == package_
               && version == version_ =>
             {
-              Some(PublishAccessRestriction {
-                tarball_hash: Some(tarball_hash.clone()),
-              })
+              Some((
+                PublishAccessRestriction {
+                  tarball_hash: Some(tarball_hash.clone()),
+                },
+                Some(permission.clone()),
+              ))
             }
             Permission::PackagePublish(PackagePublishPermission::Package {
               scope,
               package,
-            }) if scope == scope_ && package == package_ => {
-              Some(PublishAccessRestriction { tarball_hash: None })
-            }
+            }) if scope == scope_ && package == package_ => Some((
+              PublishAccessRestriction { tarball_hash: None },
+              Some(permission.clone()),
+            )),
+            Permission::PackagePublish(PackagePublishPermission::Packages {
+              scope,
+              packages,
+            }) if scope == scope_ && packages.contains(package_) => Some((
+              PublishAccessRestriction { tarball_hash: None },
+              Some(permission.clone()),
+            )),
             Permission::PackagePublish(PackagePublishPermission::Scope {
               scope,
-            }) if scope == scope_ => {
-              Some(PublishAccessRestriction { tarball_hash: None })
-            }
+              ..
+            }) if scope == scope_ => Some((
+              PublishAccessRestriction { tarball_hash: None },
+              Some(permission.clone()),
+            )),
             _ => None,
           });
       access_restriction.ok_or(ApiError::MissingPermission)?
     } else {
-      PublishAccessRestriction { tarball_hash: None }
+      (PublishAccessRestriction { tarball_hash: None }, None)
     };
     match &self.principal {
       Principal::User(user) if user.is_staff && self.sudo => {
-        Ok((access_restriction, Some(user.id)))
+        Ok(PublishAccess {
+          restriction,
+          user_id: Some(user.id),
+          rule: IamRule::StaffSudo,
+          permission,
+        })
       }
       Principal::User(user) => {
         let scope = self
@@ -172,9 +296,14 @@ impl<'s> IamHandler<'s> {
           .get_scope_member(scope_, user.id)
           .await?
           .ok_or(ApiError::ActorNotScopeMember)?;
-        Ok((access_restriction, Some(user.id)))
+        Ok(PublishAccess {
+          restriction,
+          user_id: Some(user.id),
+          rule: IamRule::ScopeMember,
+          permission,
+        })
       }
-      Principal::GitHubActions { repo_id, user } => {
+      Principal::GitHubActions { repo_id, user, .. } => {
         let scope = self
           .db
           .get_scope(scope_)
@@ -196,12 +325,47 @@ impl<'s> IamHandler<'s> {
         if package.github_repository_id != Some(*repo_id) {
           return Err(ApiError::ActorNotAuthorized);
         }
-        Ok((access_restriction, user.as_ref().map(|user| user.id)))
+        Ok(PublishAccess {
+          restriction,
+          user_id: user.as_ref().map(|user| user.id),
+          rule: IamRule::LinkedGitHubRepository,
+          permission,
+        })
       }
       Principal::Anonymous => Err(ApiError::MissingAuthentication),
     }
   }
 
+  /// Evaluates whether the action would be allowed, with the same rules that
+  /// are used when the action is actually performed. Returns the rule that
+  /// allowed the action, or the error that the action would fail with.
+  pub async fn simulate(
+    &self,
+    action: &IamAction,
+  ) -> Result<IamDecision, ApiError> {
+    match action {
+      IamAction::Publish {
+        scope,
+        package,
+        version,
+      } => {
+        let access = self.publish_access(scope, package, version).await?;
+        Ok(IamDecision {
+          rule: access.rule,
+          permission: access.permission,
+        })
+      }
+      IamAction::ScopeWrite { scope } => Ok(IamDecision {
+        rule: self.scope_write_access(scope).await?,
+        permission: None,
+      }),
+      IamAction::ScopeAdmin { scope } => Ok(IamDecision {
+        rule: self.scope_admin_access(scope).await?,
+        permission: None,
+      }),
+    }
+  }
+
   pub fn check_current_user_access(&self) -> Result<&User, ApiError> {
     if self.permissions.is_some() {
       // There is no specific permission that allows access to current user, so
@@ -244,10 +408,60 @@ pub struct PublishAccessRestriction {
   pub tarball_hash: Option<String>,
 }
 
+struct PublishAccess {
+  restriction: PublishAccessRestriction,
+  user_id: Option<Uuid>,
+  rule: IamRule,
+  permission: Option<Permission>,
+}
+
+/// An action that the IAM policy can be evaluated for, without performing it.
+#[derive(Debug, Clone)]
+pub enum IamAction {
+  /// Publishing a version of a package.
+  Publish {
+    scope: ScopeName,
+    package: PackageName,
+    version: Version,
+  },
+  /// Modifying the packages of a scope, like creating a package or updating
+  /// its description.
+  ScopeWrite { scope: ScopeName },
+  /// Administering a scope, like managing its members and settings.
+  ScopeAdmin { scope: ScopeName },
+}
+
+/// The rule of the IAM policy that allowed an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IamRule {
+  /// The principal is a staff user using sudo.
+  StaffSudo,
+  /// The principal is a member of the scope.
+  ScopeMember,
+  /// The principal is an admin of the scope.
+  ScopeAdmin,
+  /// The principal is a GitHub Actions workflow in the repository that is
+  /// linked to the package.
+  LinkedGitHubRepository,
+}
+
+#[derive(Debug, Clone)]
+pub struct IamDecision {
+  pub rule: IamRule,
+  /// The token permission that allowed the action, if the credential has
+  /// restricted permissions.
+  pub permission: Option<Permission>,
+}
+
 #[derive(Clone)]
 pub enum Principal {
   User(User),
-  GitHubActions { repo_id: i64, user: Option<User> },
+  GitHubActions {
+    repo_id: i64,
+    workflow_run: Option<GithubWorkflowRun>,
+    user: Option<User>,
+  },
   Anonymous,
 }
 
@@ -268,6 +482,9 @@ pub struct IamInfo {
   /// Whether the request is being made with sudo privileges, which allows
   /// staff users to bypass some access restrictions.
   pub sudo: bool,
+  /// The token the request is authenticated with, if it is a device or
+  /// personal access token or a web session.
+  pub token_id: Option<Uuid>,
 }
 
 impl IamInfo {
@@ -277,6 +494,7 @@ impl IamInfo {
       permissions: None,
       interactive: false,
       sudo: false,
+      token_id: None,
     }
   }
 }
@@ -289,19 +507,48 @@ impl From<(Token, User, bool)> for IamInfo {
       permissions: token.permissions,
       interactive: token.r#type == TokenType::Web,
       sudo,
+      token_id: Some(token.id),
     }
   }
 }
 
-impl From<(i64, GithubOidcTokenAud, Option<User>)> for IamInfo {
+impl From<(GitHubClaims, GithubOidcTokenAud, Option<User>)> for IamInfo {
   fn from(
-    (repo_id, aud, user): (i64, GithubOidcTokenAud, Option<User>),
+    (claims, aud, user): (GitHubClaims, GithubOidcTokenAud, Option<User>),
   ) -> Self {
     IamInfo {
-      principal: Principal::GitHubActions { repo_id, user },
+      principal: Principal::GitHubActions {
+        repo_id: claims.repository_id,
+        workflow_run: claims.workflow_run(),
+        user,
+      },
       permissions: Some(aud.permissions),
       interactive: false,
       sudo: false,
+      token_id: None,
+    }
+  }
+}
+
+impl From<(OidcPublishToken, Option<User>)> for IamInfo {
+  fn from((token, user): (OidcPublishToken, Option<User>)) -> Self {
+    let permission =
+      Permission::PackagePublish(PackagePublishPermission::Version {
+        scope: token.scope,
+        package: token.name,
+        version: token.version,
+        tarball_hash: token.tarball_hash,
+      });
+    IamInfo {
+      principal: Principal::GitHubActions {
+        repo_id: token.repository_id,
+        workflow_run: token.github_workflow_run,
+        user,
+      },
+      permissions: Some(Permissions(vec![permission])),
+      interactive: false,
+      sudo: false,
+      token_id: None,
     }
   }
 }
@@ -312,19 +559,195 @@ pub trait ReqIamExt {
 
 impl ReqIamExt for Request<Body> {
   fn iam(&self) -> IamHandler {
-    let db = self.data().unwrap();
-    let IamInfo {
-      principal,
-      permissions,
-      interactive,
-      sudo,
-    } = self.context().unwrap();
-    IamHandler {
-      db,
-      principal,
-      permissions,
-      interactive,
+    let mut iam =
+      IamHandler::new(self.data().unwrap(), self.context().unwrap());
+    iam.client_ip = crate::util::client_ip(self);
+    iam
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::IamAction;
+  use super::IamHandler;
+  use super::IamInfo;
+  use super::IamRule;
+  use super::Principal;
+  use crate::api::ApiError;
+  use crate::db::OidcPublishToken;
+  use crate::db::PackagePublishPermission;
+  use crate::db::Permission;
+  use crate::db::Permissions;
+  use crate::db::User;
+  use crate::testing::TestSetup;
+  use chrono::Utc;
+  use uuid::Uuid;
+
+  fn user(user: &User, sudo: bool) -> IamInfo {
+    IamInfo {
+      principal: Principal::User(user.clone()),
+      permissions: None,
+      interactive: true,
       sudo,
+      token_id: None,
+    }
+  }
+
+  fn publish() -> IamAction {
+    IamAction::Publish {
+      scope: "scope".try_into().unwrap(),
+      package: "foo".try_into().unwrap(),
+      version: "1.0.0".try_into().unwrap(),
+    }
+  }
+
+  fn scope_write() -> IamAction {
+    IamAction::ScopeWrite {
+      scope: "scope".try_into().unwrap(),
+    }
+  }
+
+  fn scope_admin() -> IamAction {
+    IamAction::ScopeAdmin {
+      scope: "scope".try_into().unwrap(),
     }
   }
+
+  #[tokio::test]
+  async fn simulate_users() {
+    let t = TestSetup::new().await;
+    let db = t.db();
+
+    // user1 is an admin of the scope
+    let iam = IamHandler::new(&db, user(&t.user1.user, false));
+    let decision = iam.simulate(&publish()).await.unwrap();
+    assert_eq!(decision.rule, IamRule::ScopeMember);
+    assert!(decision.permission.is_none());
+    let decision = iam.simulate(&scope_write()).await.unwrap();
+    assert_eq!(decision.rule, IamRule::ScopeMember);
+    let decision = iam.simulate(&scope_admin()).await.unwrap();
+    assert_eq!(decision.rule, IamRule::ScopeAdmin);
+
+    // user2 is not a member of the scope
+    let iam = IamHandler::new(&db, user(&t.user2.user, false));
+    for action in [publish(), scope_write(), scope_admin()] {
+      let err = iam.simulate(&action).await.unwrap_err();
+      assert!(matches!(err, ApiError::ActorNotScopeMember), "{err:?}");
+    }
+
+    // staff users only bypass the checks with sudo
+    let iam = IamHandler::new(&db, user(&t.staff_user.user, false));
+    let err = iam.simulate(&scope_admin()).await.unwrap_err();
+    assert!(matches!(err, ApiError::ActorNotScopeMember), "{err:?}");
+    let iam = IamHandler::new(&db, user(&t.staff_user.user, true));
+    let decision = iam.simulate(&scope_admin()).await.unwrap();
+    assert_eq!(decision.rule, IamRule::StaffSudo);
+
+    let iam = IamHandler::new(&db, IamInfo::anonymous());
+    let err = iam.simulate(&publish()).await.unwrap_err();
+    assert!(matches!(err, ApiError::MissingAuthentication), "{err:?}");
+  }
+
+  #[tokio::test]
+  async fn simulate_restricted_permissions() {
+    let t = TestSetup::new().await;
+    let db = t.db();
+
+    let permission =
+      Permission::PackagePublish(PackagePublishPermission::Package {
+        scope: "scope".try_into().unwrap(),
+        package: "foo".try_into().unwrap(),
+      });
+    let iam = IamHandler::new(
+      &db,
+      IamInfo {
+        principal: Principal::User(t.user1.user.clone()),
+        permissions: Some(Permissions(vec![permission])),
+        interactive: false,
+        sudo: false,
+        token_id: None,
+      },
+    );
+
+    let decision = iam.simulate(&publish()).await.unwrap();
+    assert_eq!(decision.rule, IamRule::ScopeMember);
+    assert!(matches!(
+      decision.permission,
+      Some(Permission::PackagePublish(
+        PackagePublishPermission::Package { .. }
+      ))
+    ));
+
+    let other_package = IamAction::Publish {
+      scope: "scope".try_into().unwrap(),
+      package: "bar".try_into().unwrap(),
+      version: "1.0.0".try_into().unwrap(),
+    };
+    let err = iam.simulate(&other_package).await.unwrap_err();
+    assert!(matches!(err, ApiError::MissingPermission), "{err:?}");
+
+    // restricted permissions never allow scope management
+    let err = iam.simulate(&scope_write()).await.unwrap_err();
+    assert!(matches!(err, ApiError::MissingPermission), "{err:?}");
+
+    let permission =
+      Permission::PackagePublish(PackagePublishPermission::Packages {
+        scope: "scope".try_into().unwrap(),
+        packages: vec!["foo".try_into().unwrap()],
+      });
+    let iam = IamHandler::new(
+      &db,
+      IamInfo {
+        principal: Principal::User(t.user1.user.clone()),
+        permissions: Some(Permissions(vec![permission])),
+        interactive: false,
+        sudo: false,
+        token_id: None,
+      },
+    );
+    let decision = iam.simulate(&publish()).await.unwrap();
+    assert_eq!(decision.rule, IamRule::ScopeMember);
+    let err = iam.simulate(&other_package).await.unwrap_err();
+    assert!(matches!(err, ApiError::MissingPermission), "{err:?}");
+    let err = iam.simulate(&scope_admin()).await.unwrap_err();
+    assert!(matches!(err, ApiError::MissingPermission), "{err:?}");
+  }
+
+  #[tokio::test]
+  async fn simulate_oidc_publish_token() {
+    let t = TestSetup::new().await;
+    let db = t.db();
+    db.create_package(&"scope".try_into().unwrap(), &"foo".try_into().unwrap())
+      .await
+      .unwrap();
+
+    let token = OidcPublishToken {
+      id: Uuid::new_v4(),
+      hash: "hash".into(),
+      repository_id: 1,
+      github_workflow_run: None,
+      user_id: Some(t.user1.user.id),
+      scope: "scope".try_into().unwrap(),
+      name: "foo".try_into().unwrap(),
+      version: "1.0.0".try_into().unwrap(),
+      tarball_hash: "sha256-abc".into(),
+      expires_at: Utc::now(),
+      created_at: Utc::now(),
+    };
+    let iam =
+      IamHandler::new(&db, IamInfo::from((token, Some(t.user1.user.clone()))));
+
+    // the token only allows publishing the version it was issued for
+    let other_version = IamAction::Publish {
+      scope: "scope".try_into().unwrap(),
+      package: "foo".try_into().unwrap(),
+      version: "1.0.1".try_into().unwrap(),
+    };
+    let err = iam.simulate(&other_version).await.unwrap_err();
+    assert!(matches!(err, ApiError::MissingPermission), "{err:?}");
+
+    // and only if the package is linked to the repository of the workflow
+    let err = iam.simulate(&publish()).await.unwrap_err();
+    assert!(matches!(err, ApiError::ActorNotAuthorized), "{err:?}");
+  }
 }
@@ -0,0 +1,242 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::collections::HashMap;
+
+use crate::ids::PackagePath;
+
+/// The names of files at the root of a package that are checked for a license
+/// text, compared case-insensitively.
+const LICENSE_FILE_NAMES: &[&str] = &[
+  "/license",
+  "/license.md",
+  "/license.txt",
+  "/licence",
+  "/licence.md",
+  "/licence.txt",
+  "/copying",
+  "/copying.md",
+  "/copying.txt",
+];
+
+const MAX_LICENSE_FIELD_LENGTH: usize = 100;
+
+/// Detects the license of a package version. An SPDX expression in the
+/// `license` field of the config file takes precedence. Otherwise the license
+/// is detected from the text of a LICENSE file at the root of the package.
+pub fn detect_license(
+  config_license: Option<&serde_json::Value>,
+  files: &HashMap<PackagePath, Vec<u8>>,
+) -> Option<String> {
+  if let Some(serde_json::Value::String(license)) = config_license {
+    let license = license.trim();
+    let valid = !license.is_empty()
+      && license.len() <= MAX_LICENSE_FIELD_LENGTH
+      && license.chars().all(|c| {
+        c.is_ascii_alphanumeric()
+          || matches!(c, '.' | '-' | '+' | '(' | ')' | ' ')
+      });
+    if valid {
+      return Some(license.to_owned());
+    }
+  }
+
+  let (_, text) = files.iter().find(|(path, _)| {
+    let path = path.to_string().to_lowercase();
+    LICENSE_FILE_NAMES.contains(&path.as_str())
+  })?;
+  let text = std::str::from_utf8(text).ok()?;
+  detect_license_text(text).map(ToOwned::to_owned)
+}
+
+/// Identifies common licenses by phrases that only occur in their text, and
+/// returns their SPDX identifier.
+fn detect_license_text(text: &str) -> Option<&'static str> {
+  let text = text
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ")
+    .to_lowercase();
+
+  if text.contains("gnu affero general public license") {
+    return Some("AGPL-3.0");
+  }
+  if text.contains("gnu lesser general public license") {
+    if text.contains("version 2.1") {
+      return Some("LGPL-2.1");
+    }
+    return Some("LGPL-3.0");
+  }
+  if text.contains("gnu general public license") {
+    if text.contains("version 2,") || text.contains("version 2 ") {
+      return Some("GPL-2.0");
+    }
+    return Some("GPL-3.0");
+  }
+  if text.contains("mozilla public license") && text.contains("2.0") {
+    return Some("MPL-2.0");
+  }
+  if text.contains("apache license") && text.contains("version 2.0") {
+    return Some("Apache-2.0");
+  }
+  if text.contains(
+    "this is free and unencumbered software released into the public domain",
+  ) {
+    return Some("Unlicense");
+  }
+  if text.contains("permission is hereby granted, free of charge, to any person obtaining a copy") {
+    return Some("MIT");
+  }
+  if text.contains("permission to use, copy, modify, and/or distribute this software for any purpose with or without fee is hereby granted") {
+    if text.contains("provided that the above copyright notice") {
+      return Some("ISC");
+    }
+    return Some("0BSD");
+  }
+  if text.contains("redistribution and use in source and binary forms") {
+    if text.contains("endorse or promote products") {
+      return Some("BSD-3-Clause");
+    }
+    return Some("BSD-2-Clause");
+  }
+  None
+}
+
+/// How restrictive a license is for consumers of a package, from least to
+/// most restrictive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LicenseClassification {
+  Permissive,
+  /// Modifications to the licensed files must be shared under the same
+  /// license, but the files can be combined with differently licensed code.
+  WeakCopyleft,
+  /// Works that include the licensed code must be shared under the same
+  /// license.
+  Copyleft,
+  /// The license is missing or not recognized.
+  Unknown,
+}
+
+const PERMISSIVE_LICENSES: &[&str] = &[
+  "0BSD",
+  "APACHE-2.0",
+  "BLUEOAK-1.0.0",
+  "BSD-2-CLAUSE",
+  "BSD-3-CLAUSE",
+  "BSL-1.0",
+  "CC-BY-4.0",
+  "CC0-1.0",
+  "ISC",
+  "MIT",
+  "MIT-0",
+  "PYTHON-2.0",
+  "UNLICENSE",
+  "WTFPL",
+  "ZLIB",
+];
+const WEAK_COPYLEFT_LICENSES: &[&str] = &[
+  "CDDL-1.0", "EPL-1.0", "EPL-2.0", "LGPL-2.0", "LGPL-2.1", "LGPL-3.0",
+  "MPL-2.0",
+];
+const COPYLEFT_LICENSES: &[&str] = &[
+  "AGPL-3.0", "EUPL-1.2", "GPL-2.0", "GPL-3.0", "OSL-3.0", "SSPL-1.0",
+];
+
+/// Classifies a license, given as an SPDX expression. For `OR` expressions the
+/// least restrictive choice counts, and for `AND` expressions the most
+/// restrictive part.
+pub fn classify_license(license: Option<&str>) -> LicenseClassification {
+  let Some(license) = license else {
+    return LicenseClassification::Unknown;
+  };
+  let license = license.replace(['(', ')'], " ").to_uppercase();
+  license
+    .split(" OR ")
+    .map(|alternative| {
+      alternative
+        .split(" AND ")
+        .map(classify_license_id)
+        .max()
+        .unwrap_or(LicenseClassification::Unknown)
+    })
+    .min()
+    .unwrap_or(LicenseClassification::Unknown)
+}
+
+fn classify_license_id(id: &str) -> LicenseClassification {
+  let id = id.trim();
+  let id = id
+    .strip_suffix("-ONLY")
+    .or_else(|| id.strip_suffix("-OR-LATER"))
+    .or_else(|| id.strip_suffix('+'))
+    .unwrap_or(id);
+  if PERMISSIVE_LICENSES.contains(&id) {
+    LicenseClassification::Permissive
+  } else if WEAK_COPYLEFT_LICENSES.contains(&id) {
+    LicenseClassification::WeakCopyleft
+  } else if COPYLEFT_LICENSES.contains(&id) {
+    LicenseClassification::Copyleft
+  } else {
+    LicenseClassification::Unknown
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn detect_license() {
+    let mut files = HashMap::new();
+    files.insert(
+      PackagePath::try_from("/LICENSE").unwrap(),
+      b"MIT License\n\nCopyright (c) 2024 Luca\n\nPermission is hereby granted, free of charge, to any person obtaining a\ncopy of this software".to_vec(),
+    );
+    assert_eq!(super::detect_license(None, &files).as_deref(), Some("MIT"));
+    assert_eq!(
+      super::detect_license(Some(&serde_json::json!("Apache-2.0")), &files)
+        .as_deref(),
+      Some("Apache-2.0")
+    );
+    // invalid config values fall back to the license file
+    assert_eq!(
+      super::detect_license(Some(&serde_json::json!(["MIT"])), &files)
+        .as_deref(),
+      Some("MIT")
+    );
+    assert_eq!(super::detect_license(None, &HashMap::new()), None);
+  }
+
+  #[test]
+  fn detect_license_text() {
+    assert_eq!(
+      super::detect_license_text(
+        "GNU GENERAL PUBLIC LICENSE\n   Version 3, 29 June 2007"
+      ),
+      Some("GPL-3.0")
+    );
+    assert_eq!(
+      super::detect_license_text(
+        "GNU LESSER GENERAL PUBLIC LICENSE\n   Version 2.1, February 1999"
+      ),
+      Some("LGPL-2.1")
+    );
+    assert_eq!(
+      super::detect_license_text(
+        "Redistribution and use in source and binary forms, with or without modification, are permitted. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote products derived from this software."
+      ),
+      Some("BSD-3-Clause")
+    );
+    assert_eq!(super::detect_license_text("All rights reserved."), None);
+  }
+
+  #[test]
+  fn classify_license() {
+    use LicenseClassification::*;
+    assert_eq!(super::classify_license(Some("MIT")), Permissive);
+    assert_eq!(super::classify_license(Some("GPL-3.0-only")), Copyleft);
+    assert_eq!(super::classify_license(Some("LGPL-2.1+")), WeakCopyleft);
+    assert_eq!(super::classify_license(Some("MIT OR GPL-3.0")), Permissive);
+    assert_eq!(super::classify_license(Some("(MIT AND GPL-2.0)")), Copyleft);
+    assert_eq!(super::classify_license(Some("Proprietary")), Unknown);
+    assert_eq!(super::classify_license(None), Unknown);
+  }
+}
@@ -0,0 +1,283 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use comrak::nodes::NodeValue;
+use hyper::client::connect::dns::Name;
+use indexmap::IndexSet;
+use reqwest::dns::Addrs;
+use reqwest::dns::Resolve;
+use reqwest::dns::Resolving;
+use url::Host;
+use url::Url;
+
+/// How long a single link may take to respond before it is considered broken.
+pub const LINK_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Returns the external `http:` and `https:` links and images in a markdown
+/// document, in the order they appear, without duplicates.
+pub fn extract_external_links(markdown: &str) -> Vec<Url> {
+  let arena = comrak::Arena::new();
+  let mut options = comrak::Options::default();
+  options.extension.autolink = true;
+  options.extension.table = true;
+  let root = comrak::parse_document(&arena, markdown, &options);
+
+  let mut links = IndexSet::new();
+  for node in root.descendants() {
+    let url = match &node.data.borrow().value {
+      NodeValue::Link(link) | NodeValue::Image(link) => link.url.clone(),
+      _ => continue,
+    };
+    let Ok(mut url) = Url::parse(&url) else {
+      continue;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+      continue;
+    }
+    // Fragments are never sent to the server.
+    url.set_fragment(None);
+    links.insert(url);
+  }
+  links.into_iter().collect()
+}
+
+/// Whether an address is reachable on the public internet. Links to other
+/// addresses are never requested, so that package authors can not make the
+/// link check task reach internal services.
+pub fn is_public_address(ip: IpAddr) -> bool {
+  match ip {
+    IpAddr::V4(ip) => {
+      let [a, b, ..] = ip.octets();
+      !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // 100.64.0.0/10, shared address space
+        || (a == 100 && (64..128).contains(&b))
+        // 0.0.0.0/8
+        || a == 0
+        // 240.0.0.0/4, reserved
+        || a >= 240)
+    }
+    IpAddr::V6(ip) => {
+      if let Some(ip) = ip.to_ipv4_mapped() {
+        return is_public_address(IpAddr::V4(ip));
+      }
+      let segments = ip.segments();
+      let first = segments[0];
+      !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // fc00::/7, unique local
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10, link local
+        || (first & 0xffc0) == 0xfe80
+        // fec0::/10, site local
+        || (first & 0xffc0) == 0xfec0
+        // 64:ff9b::/96 and 64:ff9b:1::/48, NAT64, which can embed any IPv4
+        // address
+        || (first == 0x64 && segments[1] == 0xff9b))
+    }
+  }
+}
+
+/// Why a link is considered broken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkFailure {
+  /// The HTTP status of the response, or `None` if no response was received.
+  pub status: Option<u16>,
+  pub error: Option<String>,
+}
+
+/// Checks whether a link is broken. Redirects are not followed, and links to
+/// hosts that are not on the public internet are skipped. The client must be
+/// created with [`link_check_client`], which checks the addresses again when
+/// it connects, as the host may resolve to different addresses by then. Only
+/// responses that indicate that the target is gone, or that the server failed,
+/// count as broken, as many sites deny automated requests with other
+/// statuses.
+pub async fn check_link(
+  client: &reqwest::Client,
+  url: &Url,
+) -> Option<LinkFailure> {
+  let failure = |error: &str| {
+    Some(LinkFailure {
+      status: None,
+      error: Some(error.to_owned()),
+    })
+  };
+
+  let port = url.port_or_known_default()?;
+  let addrs = match url.host()? {
+    Host::Domain(domain) => match tokio::time::timeout(
+      LINK_CHECK_TIMEOUT,
+      tokio::net::lookup_host((domain, port)),
+    )
+    .await
+    {
+      Ok(Ok(addrs)) => addrs.collect::<Vec<_>>(),
+      Ok(Err(_)) | Err(_) => return failure("the host could not be resolved"),
+    },
+    Host::Ipv4(ip) => vec![SocketAddr::new(ip.into(), port)],
+    Host::Ipv6(ip) => vec![SocketAddr::new(ip.into(), port)],
+  };
+  if addrs.is_empty() {
+    return failure("the host could not be resolved");
+  }
+  if !addrs.iter().all(|addr| is_public_address(addr.ip())) {
+    return None;
+  }
+
+  let mut res = client.head(url.clone()).send().await;
+  if let Ok(resp) = &res {
+    // Some servers do not implement HEAD requests.
+    if matches!(resp.status().as_u16(), 405 | 501) {
+      res = client.get(url.clone()).send().await;
+    }
+  }
+  match res {
+    Ok(resp) => {
+      let status = resp.status();
+      if matches!(status.as_u16(), 404 | 410) || status.is_server_error() {
+        Some(LinkFailure {
+          status: Some(status.as_u16()),
+          error: None,
+        })
+      } else {
+        None
+      }
+    }
+    Err(err) if err.is_timeout() => failure("the request timed out"),
+    Err(err) if err.is_connect() => failure("the connection failed"),
+    Err(_) => failure("the request failed"),
+  }
+}
+
+/// Resolves hosts for the link check client, failing for hosts that resolve
+/// to any address that is not on the public internet. The client connects to
+/// the addresses that were checked here, so a host that resolves to a public
+/// address when [`check_link`] looks it up can not resolve to an internal
+/// address when the request is made.
+struct PublicAddressResolver;
+
+impl Resolve for PublicAddressResolver {
+  fn resolve(&self, name: Name) -> Resolving {
+    Box::pin(async move {
+      let addrs = tokio::net::lookup_host((name.as_str(), 0))
+        .await?
+        .collect::<Vec<_>>();
+      if !addrs.iter().all(|addr| is_public_address(addr.ip())) {
+        return Err(
+          format!("{} resolves to a non-public address", name.as_str()).into(),
+        );
+      }
+      let addrs: Addrs = Box::new(addrs.into_iter());
+      Ok(addrs)
+    })
+  }
+}
+
+/// Creates the HTTP client that links are checked with. It does not use a
+/// proxy, so that it resolves hosts itself.
+pub fn link_check_client() -> reqwest::Client {
+  reqwest::Client::builder()
+    .timeout(LINK_CHECK_TIMEOUT)
+    .redirect(reqwest::redirect::Policy::none())
+    .no_proxy()
+    .dns_resolver(Arc::new(PublicAddressResolver))
+    .user_agent("jsr-link-checker (+https://jsr.io/docs/scoring)")
+    .build()
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::IpAddr;
+
+  #[test]
+  fn extract_external_links() {
+    let markdown = r#"# Hello
+
+See [the docs](https://example.com/docs#usage) and
+[the docs again](https://example.com/docs), or ![logo](http://example.com/logo.png).
+
+Relative links like [this](./mod.ts) and [mail](mailto:a@example.com) are
+skipped. Bare links like https://deno.com are found too.
+"#;
+    let links = super::extract_external_links(markdown)
+      .into_iter()
+      .map(|url| url.to_string())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      links,
+      vec![
+        "https://example.com/docs",
+        "http://example.com/logo.png",
+        "https://deno.com/",
+      ]
+    );
+  }
+
+  #[test]
+  fn is_public_address() {
+    for ip in [
+      "127.0.0.1",
+      "10.0.0.1",
+      "172.16.0.1",
+      "192.168.1.1",
+      "169.254.169.254",
+      "100.64.0.1",
+      "0.0.0.0",
+      "::1",
+      "fd00::1",
+      "fe80::1",
+      "::ffff:127.0.0.1",
+      "224.0.0.1",
+      "255.255.255.254",
+      "ff02::1",
+      "fec0::1",
+      "64:ff9b::a9fe:a9fe",
+      "64:ff9b:1::a00:1",
+    ] {
+      let ip: IpAddr = ip.parse().unwrap();
+      assert!(!super::is_public_address(ip), "{ip}");
+    }
+    for ip in ["1.1.1.1", "8.8.8.8", "2606:4700:4700::1111"] {
+      let ip: IpAddr = ip.parse().unwrap();
+      assert!(super::is_public_address(ip), "{ip}");
+    }
+  }
+
+  #[tokio::test]
+  async fn client_rejects_internal_hosts() {
+    // The client must not connect to internal addresses even if a host did
+    // not resolve to them when it was checked.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let err = super::link_check_client()
+      .get(format!("http://localhost:{port}/"))
+      .send()
+      .await
+      .unwrap_err();
+    assert!(err.is_connect(), "{err:?}");
+  }
+
+  #[tokio::test]
+  async fn check_link_skips_internal_ip_literals() {
+    let client = super::link_check_client();
+    for url in [
+      "http://127.0.0.1/",
+      "http://[::1]/",
+      "http://[fd00::1]:8080/",
+    ] {
+      let url = url::Url::parse(url).unwrap();
+      assert_eq!(super::check_link(&client, &url).await, None, "{url}");
+    }
+  }
+}
@@ -1,9 +1,12 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 mod analysis;
+mod analytics;
 mod api;
 mod auth;
+mod badges;
 mod buckets;
 mod config;
+mod counters;
 mod db;
 mod docs;
 mod emails;
@@ -13,51 +16,99 @@ mod gcs_paths;
 mod github;
 mod iam;
 mod ids;
+mod license;
+mod link_check;
+mod memory;
 mod metadata;
 mod npm;
 mod orama;
 mod provenance;
 mod publish;
+mod queue;
+mod rate_limit;
+mod signing;
 mod sitemap;
 mod tarball;
 mod task_queue;
 mod tasks;
+#[cfg(test)]
+mod testing;
 mod token;
+mod topics;
 mod traced_router;
 mod tracing;
 mod tree_sitter;
+mod trust;
+mod typescript;
+mod user_export;
 mod util;
 
+use crate::analysis::AnalysisLimits;
+use crate::analytics::AnalyticsSink;
+use crate::analytics::AnalyticsSinks;
 use crate::api::api_router;
+use crate::api::registry_metadata_handler;
 use crate::api::ApiError;
+use crate::api::NpmTarballBuildDemand;
+use crate::api::NpmTarballOnDemandBuilds;
 use crate::api::PublishQueue;
 use crate::auth::GithubOauth2Client;
 use crate::buckets::BucketWithQueue;
 use crate::buckets::Buckets;
 use crate::config::Config;
+use crate::db::AdaptivePoolOptions;
 use crate::db::Database;
+use crate::db::PoolOptions;
+use crate::emails::EmailBackend;
 use crate::emails::EmailSender;
+use crate::emails::PostmarkBackend;
+use crate::emails::SmtpBackend;
 use crate::errors_internal::error_handler;
 use crate::gcp::Queue;
 use crate::orama::OramaClient;
+use crate::provenance::ProvenancePolicies;
+use crate::queue::InProcessQueue;
+use crate::queue::PostgresQueue;
+use crate::queue::QueueBackend;
+use crate::queue::TaskQueue;
+use crate::rate_limit::ApiKeyCache;
+use crate::rate_limit::RateLimiter;
+use crate::rate_limit::RateLimits;
+use crate::rate_limit::RequestCosts;
+use crate::signing::metadata_keys_handler;
+use crate::signing::MetadataSigner;
+use crate::signing::METADATA_KEYS_PATH;
 use crate::sitemap::packages_sitemap_handler;
+use crate::sitemap::robots_txt_handler;
 use crate::sitemap::scopes_sitemap_handler;
 use crate::sitemap::sitemap_index_handler;
+use crate::sitemap::RobotsConfig;
 use crate::tasks::tasks_router;
 use crate::tasks::NpmTarballBuildQueue;
 use crate::traced_router::TracedRouterService;
 use crate::tracing::setup_tracing;
 use crate::tracing::TracingExportTarget;
+use crate::trust::TrustPolicy;
+use crate::typescript::TypeScriptVersion;
+use crate::typescript::TypeScriptVersions;
+use crate::util::ApiKeyUsages;
+use crate::util::DeprecatedRouteUsages;
 
+use ::tracing::error;
 use clap::Parser;
 use hyper::Body;
 use hyper::Server;
 use routerify::Router;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 use tasks::LogsBigQueryTable;
+use tasks::UnmaintainedSuggestionAfter;
 use url::Url;
 
+#[global_allocator]
+static GLOBAL: memory::TrackingAllocator = memory::TrackingAllocator;
+
 pub struct MainRouterOptions {
   database: Database,
   buckets: Buckets,
@@ -66,15 +117,27 @@ pub struct MainRouterOptions {
   email_sender: Option<EmailSender>,
   registry_url: Url,
   npm_url: Url,
-  publish_queue: Option<Queue>,
-  npm_tarball_build_queue: Option<Queue>,
+  npm_include_yanked_versions: bool,
+  publish_queue: Option<Arc<dyn TaskQueue>>,
+  npm_tarball_build_queue: Option<Arc<dyn TaskQueue>>,
   logs_bigquery_table: Option<(gcp::BigQuery, /* logs_table_id */ String)>,
+  analytics_sinks: AnalyticsSinks,
+  typescript_versions: Vec<TypeScriptVersion>,
+  analysis_limits: AnalysisLimits,
+  provenance_policies: ProvenancePolicies,
+  robots: RobotsConfig,
+  metadata_signer: MetadataSigner,
+  rate_limits: RateLimits,
+  request_costs: RequestCosts,
+  trust_policy: TrustPolicy,
+  unmaintained_suggestion_after: chrono::Duration,
   expose_api: bool,
   expose_tasks: bool,
 }
 
 pub struct RegistryUrl(pub Url);
 pub struct NpmUrl(pub Url);
+pub struct NpmIncludeYankedVersions(pub bool);
 
 pub(crate) fn main_router(
   MainRouterOptions {
@@ -85,13 +148,53 @@ pub(crate) fn main_router(
     email_sender,
     registry_url,
     npm_url,
+    npm_include_yanked_versions,
     publish_queue,
     npm_tarball_build_queue,
     logs_bigquery_table,
+    analytics_sinks,
+    typescript_versions,
+    analysis_limits,
+    provenance_policies,
+    robots,
+    metadata_signer,
+    rate_limits,
+    request_costs,
+    trust_policy,
+    unmaintained_suggestion_after,
     expose_api,
     expose_tasks,
   }: MainRouterOptions,
 ) -> Router<Body, ApiError> {
+  let deprecated_route_usages = DeprecatedRouteUsages::default();
+  {
+    let usages = deprecated_route_usages.clone();
+    let db = database.clone();
+    counters::spawn_flush_loop(move || {
+      let usages = usages.clone();
+      let db = db.clone();
+      async move {
+        if let Err(err) = usages.flush(&db).await {
+          error!("failed to flush deprecated route usages: {err}");
+        }
+      }
+    });
+  }
+  let api_key_usages = ApiKeyUsages::default();
+  {
+    let usages = api_key_usages.clone();
+    let db = database.clone();
+    counters::spawn_flush_loop(move || {
+      let usages = usages.clone();
+      let db = db.clone();
+      async move {
+        if let Err(err) = usages.flush(&db).await {
+          error!("failed to flush API key usages: {err}");
+        }
+      }
+    });
+  }
+
   let builder = Router::builder()
     .data(database)
     .data(buckets)
@@ -100,21 +203,52 @@ pub(crate) fn main_router(
     .data(email_sender)
     .data(RegistryUrl(registry_url))
     .data(NpmUrl(npm_url))
+    .data(NpmIncludeYankedVersions(npm_include_yanked_versions))
     .data(PublishQueue(publish_queue))
     .data(NpmTarballBuildQueue(npm_tarball_build_queue))
+    .data(NpmTarballOnDemandBuilds::default())
+    .data(NpmTarballBuildDemand::default())
     .data(LogsBigQueryTable(logs_bigquery_table))
+    .data(analytics_sinks)
+    .data(TypeScriptVersions(typescript_versions))
+    .data(analysis_limits)
+    .data(provenance_policies)
+    .data(robots)
+    .data(metadata_signer)
+    .data(RateLimiter::new(rate_limits))
+    .data(ApiKeyCache::default())
+    .data(api_key_usages)
+    .data(deprecated_route_usages)
+    .data(request_costs)
+    .data(trust_policy)
+    .data(UnmaintainedSuggestionAfter(unmaintained_suggestion_after))
     .middleware(routerify_query::query_parser())
     .err_handler_with_info(error_handler);
 
   let builder = if expose_api {
     builder
       .scope("/api", api_router())
+      .get("/robots.txt", robots_txt_handler)
+      .get(METADATA_KEYS_PATH, metadata_keys_handler)
       .get("/sitemap.xml", sitemap_index_handler)
       .get("/sitemap-scopes.xml", scopes_sitemap_handler)
       .get("/sitemap-packages.xml", packages_sitemap_handler)
+      .get(
+        "/badges/:scope/:package/version.svg",
+        badges::version_badge_handler,
+      )
+      .get(
+        "/badges/:scope/:package/downloads.svg",
+        badges::downloads_badge_handler,
+      )
+      .get(
+        "/badges/:scope/:package/score.svg",
+        badges::score_badge_handler,
+      )
       .get("/login", auth::login_handler)
       .get("/login/callback", auth::login_callback_handler)
       .get("/logout", auth::logout_handler)
+      .get("/:scope/:package/:file", registry_metadata_handler)
   } else {
     builder
   };
@@ -128,6 +262,33 @@ pub(crate) fn main_router(
   builder.build().unwrap()
 }
 
+/// Creates the buckets of a namespace. The name of each bucket is the
+/// configured name with `prefix` prepended.
+fn create_buckets(
+  gcp_client: &gcp::Client,
+  retry_config: &gcp::GcsRetryConfig,
+  config: &Config,
+  prefix: &str,
+) -> Buckets {
+  let bucket = |name: &str| {
+    BucketWithQueue::new(
+      gcp::Bucket::new(
+        gcp_client.clone(),
+        format!("{prefix}{name}"),
+        config.gcs_endpoint.clone(),
+      )
+      .with_retry_config(*retry_config),
+    )
+  };
+  Buckets {
+    publishing_bucket: bucket(&config.publishing_bucket),
+    modules_bucket: bucket(&config.modules_bucket),
+    docs_bucket: bucket(&config.docs_bucket),
+    npm_bucket: bucket(&config.npm_bucket),
+    artifacts_bucket: bucket(&config.artifacts_bucket),
+  }
+}
+
 #[tokio::main]
 async fn main() {
   dotenvy::dotenv().ok();
@@ -136,56 +297,45 @@ async fn main() {
 
   let export_target = if config.cloud_trace {
     TracingExportTarget::CloudTrace
-  } else if let Some(otlp_endpoint) = config.otlp_endpoint {
+  } else if let Some(otlp_endpoint) = config.otlp_endpoint.clone() {
     TracingExportTarget::Otlp(otlp_endpoint)
   } else {
     TracingExportTarget::None
   };
   setup_tracing("api", export_target).await;
 
+  let pool_options = PoolOptions {
+    size: config.database_pool_size,
+    adaptive: config.database_pool_adaptive.then(|| AdaptivePoolOptions {
+      min_size: config.database_pool_min_size,
+      max_size: config.database_pool_max_size,
+      target_wait: Duration::from_millis(config.database_pool_target_wait_ms),
+    }),
+  };
   let database = Database::connect(
     &config.database_url,
-    config.database_pool_size,
+    pool_options,
     Duration::from_secs(5),
   )
   .await
   .unwrap();
+  database.spawn_pool_monitor(pool_options, Duration::from_secs(10));
 
   let gcp_client = gcp::Client::new(config.metadata_strategy);
-  let publishing_bucket = BucketWithQueue::new(gcp::Bucket::new(
-    gcp_client.clone(),
-    config.publishing_bucket,
-    config.gcs_endpoint.clone(),
-  ));
-  let modules_bucket = BucketWithQueue::new(gcp::Bucket::new(
-    gcp_client.clone(),
-    config.modules_bucket,
-    config.gcs_endpoint.clone(),
-  ));
-  let docs_bucket = BucketWithQueue::new(gcp::Bucket::new(
-    gcp_client.clone(),
-    config.docs_bucket,
-    config.gcs_endpoint.clone(),
-  ));
-  let npm_bucket = BucketWithQueue::new(gcp::Bucket::new(
-    gcp_client.clone(),
-    config.npm_bucket,
-    config.gcs_endpoint,
-  ));
-  let buckets = Buckets {
-    publishing_bucket,
-    modules_bucket: modules_bucket.clone(),
-    docs_bucket,
-    npm_bucket,
+  let gcs_retry = gcp::GcsRetryConfig {
+    max_attempts: config.gcs_max_attempts,
+    hedge_after: config.gcs_hedge_after_ms.map(Duration::from_millis),
+    ..Default::default()
   };
-
-  let publish_queue = config
-    .publish_queue_id
-    .map(|id| Queue::new(gcp_client.clone(), id, None));
-
-  let npm_tarball_build_queue = config
-    .npm_tarball_build_queue_id
-    .map(|id: String| Queue::new(gcp_client.clone(), id, None));
+  let buckets = create_buckets(&gcp_client, &gcs_retry, &config, "");
+  let staging_buckets = (!config.staging_hosts.is_empty()).then(|| {
+    create_buckets(
+      &gcp_client,
+      &gcs_retry,
+      &config,
+      &config.staging_bucket_prefix,
+    )
+  });
 
   let logs_bigquery_table =
     config.logs_bigquery_table_id.map(|logs_table_id| {
@@ -201,6 +351,16 @@ async fn main() {
       )
     });
 
+  let analytics_sinks = AnalyticsSinks(
+    config
+      .analytics_sinks
+      .into_iter()
+      .map(|sink| {
+        AnalyticsSink::new(sink, &gcp_client, config.gcp_project_id.as_deref())
+      })
+      .collect(),
+  );
+
   let github_client = GithubOauth2Client::new(
     oauth2::ClientId::new(config.github_client_id),
     Some(oauth2::ClientSecret::new(config.github_client_secret)),
@@ -232,20 +392,207 @@ async fn main() {
     None
   };
 
-  let email_sender = config.postmark_token.map(|token| {
+  let email_backend: Option<Arc<dyn EmailBackend>> =
+    match (config.postmark_token, config.smtp_url) {
+      (Some(_), Some(_)) => {
+        panic!("only one of postmark_token and smtp_url can be set")
+      }
+      (Some(token), None) => Some(Arc::new(PostmarkBackend(
+        postmark::reqwest::PostmarkClient::builder()
+          .token(token)
+          .build(),
+      ))),
+      (None, Some(smtp_url)) => Some(Arc::new(
+        SmtpBackend::from_url(&smtp_url).expect("smtp_url is invalid"),
+      )),
+      (None, None) => None,
+    };
+  let request_costs = match &config.request_costs_file {
+    Some(path) => RequestCosts::from_file(path).unwrap_or_else(|err| {
+      panic!(
+        "failed to load request costs from {}: {err}",
+        path.display()
+      )
+    }),
+    None => RequestCosts::default(),
+  };
+  let trust_policy = match &config.trust_policy_file {
+    Some(path) => TrustPolicy::from_file(path).unwrap_or_else(|err| {
+      panic!("failed to load trust policy from {}: {err}", path.display())
+    }),
+    None => TrustPolicy::default(),
+  };
+
+  let email_sender = email_backend.map(|backend| {
     EmailSender::new(
-      postmark::reqwest::PostmarkClient::builder()
-        .token(token)
-        .build(),
+      backend,
       config
         .email_from
-        .expect("email_from must be set when postmark_token is set"),
-      config
-        .email_from_name
-        .expect("email_from_name must be set when postmark_token is set"),
+        .expect("email_from must be set when an email backend is configured"),
+      config.email_from_name.expect(
+        "email_from_name must be set when an email backend is configured",
+      ),
     )
   });
 
+  let analysis_limits = AnalysisLimits {
+    stage_timeout: Duration::from_secs(config.analysis_stage_timeout_secs),
+    memory_limit: config.analysis_memory_limit_mb * 1024 * 1024,
+  };
+  let metadata_signer = MetadataSigner::new(
+    config.metadata_signing_key,
+    config.metadata_verification_keys,
+  );
+
+  let publish_task_handler = publish::publish_task_handler(
+    buckets.clone(),
+    config.registry_url.clone(),
+    config.npm_url.clone(),
+    config.npm_include_yanked_versions,
+    analysis_limits,
+    database.clone(),
+    orama_client.clone(),
+    metadata_signer.clone(),
+  );
+  let npm_tarball_build_task_handler = tasks::npm_tarball_build_task_handler(
+    database.clone(),
+    buckets.clone(),
+    config.registry_url.clone(),
+    config.npm_url.clone(),
+    config.npm_include_yanked_versions,
+  );
+  #[allow(clippy::type_complexity)]
+  let (publish_queue, npm_tarball_build_queue): (
+    Option<Arc<dyn TaskQueue>>,
+    Option<Arc<dyn TaskQueue>>,
+  ) = match config.queue_backend {
+    QueueBackend::CloudTasks => (
+      config.publish_queue_id.map(|id| -> Arc<dyn TaskQueue> {
+        Arc::new(Queue::new(gcp_client.clone(), id, None))
+      }),
+      config
+        .npm_tarball_build_queue_id
+        .map(|id| -> Arc<dyn TaskQueue> {
+          Arc::new(Queue::new(gcp_client.clone(), id, None))
+        }),
+    ),
+    QueueBackend::Postgres => {
+      let publish_queue = PostgresQueue::new(database.clone(), "publish");
+      let npm_tarball_build_queue =
+        PostgresQueue::new(database.clone(), "npm_tarball_build");
+      if config.tasks {
+        publish_queue
+          .spawn_workers(publish_task_handler, config.queue_worker_concurrency);
+        npm_tarball_build_queue.spawn_workers(
+          npm_tarball_build_task_handler,
+          config.queue_worker_concurrency,
+        );
+      }
+      (
+        Some(Arc::new(publish_queue)),
+        Some(Arc::new(npm_tarball_build_queue)),
+      )
+    }
+    QueueBackend::InProcess => (
+      Some(Arc::new(InProcessQueue::new(publish_task_handler))),
+      Some(Arc::new(InProcessQueue::new(
+        npm_tarball_build_task_handler,
+      ))),
+    ),
+  };
+
+  // The staging namespace shares the process, but not the data, with the
+  // production namespace. Its tasks are always run in-process, and it does not
+  // send emails, update the search index or export logs and analytics.
+  let staging_router = match staging_buckets {
+    Some(buckets) => {
+      // Anyone can publish to the staging namespace, so its metadata files
+      // must never be signed with a production key.
+      let staging_metadata_signer = MetadataSigner::new(
+        config.staging_metadata_signing_key.clone(),
+        vec![],
+      );
+      assert!(
+        !staging_metadata_signer.shares_keys_with(&metadata_signer),
+        "the staging metadata signing key must not be a production key"
+      );
+      let database = Database::connect_in_schema(
+        &config.database_url,
+        Some(&config.staging_database_schema),
+        pool_options,
+        Duration::from_secs(5),
+      )
+      .await
+      .unwrap();
+      let registry_url = config
+        .staging_registry_url
+        .clone()
+        .unwrap_or_else(|| config.registry_url.clone());
+      let npm_url = config
+        .staging_npm_url
+        .clone()
+        .unwrap_or_else(|| config.npm_url.clone());
+      let publish_queue: Arc<dyn TaskQueue> =
+        Arc::new(InProcessQueue::new(publish::publish_task_handler(
+          buckets.clone(),
+          registry_url.clone(),
+          npm_url.clone(),
+          config.npm_include_yanked_versions,
+          analysis_limits,
+          database.clone(),
+          None,
+          staging_metadata_signer.clone(),
+        )));
+      let npm_tarball_build_queue: Arc<dyn TaskQueue> =
+        Arc::new(InProcessQueue::new(tasks::npm_tarball_build_task_handler(
+          database.clone(),
+          buckets.clone(),
+          registry_url.clone(),
+          npm_url.clone(),
+          config.npm_include_yanked_versions,
+        )));
+      Some(main_router(MainRouterOptions {
+        database,
+        buckets,
+        github_client: github_client.clone(),
+        orama_client: None,
+        email_sender: None,
+        registry_url,
+        npm_url,
+        npm_include_yanked_versions: config.npm_include_yanked_versions,
+        publish_queue: Some(publish_queue),
+        npm_tarball_build_queue: Some(npm_tarball_build_queue),
+        logs_bigquery_table: None,
+        analytics_sinks: AnalyticsSinks::default(),
+        typescript_versions: config.typescript_versions.clone(),
+        analysis_limits,
+        provenance_policies: ProvenancePolicies(
+          config.provenance_providers.clone(),
+        ),
+        robots: RobotsConfig {
+          disallow: config.robots_disallow.clone(),
+          blocked_user_agents: config.robots_blocked_user_agents.clone(),
+        },
+        metadata_signer: staging_metadata_signer,
+        rate_limits: RateLimits {
+          anonymous: config.anonymous_rate_limit,
+          api_key: config.api_key_rate_limit,
+          token: config.token_rate_limit,
+          render_readme: Some(config.render_readme_rate_limit),
+          retry_publish: Some(config.retry_publish_rate_limit),
+        },
+        request_costs: request_costs.clone(),
+        trust_policy: trust_policy.clone(),
+        unmaintained_suggestion_after: chrono::Duration::days(
+          config.unmaintained_suggestion_days,
+        ),
+        expose_api: config.api,
+        expose_tasks: config.tasks,
+      }))
+    }
+    None => None,
+  };
+
   let router = main_router(MainRouterOptions {
     database,
     buckets,
@@ -254,15 +601,42 @@ async fn main() {
     email_sender,
     registry_url: config.registry_url,
     npm_url: config.npm_url,
+    npm_include_yanked_versions: config.npm_include_yanked_versions,
     publish_queue,
     npm_tarball_build_queue,
     logs_bigquery_table,
+    analytics_sinks,
+    typescript_versions: config.typescript_versions,
+    analysis_limits,
+    provenance_policies: ProvenancePolicies(config.provenance_providers),
+    robots: RobotsConfig {
+      disallow: config.robots_disallow,
+      blocked_user_agents: config.robots_blocked_user_agents,
+    },
+    metadata_signer,
+    rate_limits: RateLimits {
+      anonymous: config.anonymous_rate_limit,
+      api_key: config.api_key_rate_limit,
+      token: config.token_rate_limit,
+      render_readme: Some(config.render_readme_rate_limit),
+      retry_publish: Some(config.retry_publish_rate_limit),
+    },
+    request_costs,
+    trust_policy,
+    unmaintained_suggestion_after: chrono::Duration::days(
+      config.unmaintained_suggestion_days,
+    ),
     expose_api: config.api,
     expose_tasks: config.tasks,
   });
 
   // Create a Service from the router above to handle incoming requests.
-  let service = TracedRouterService::new(router, true).unwrap();
+  let mut service = TracedRouterService::new(router, true).unwrap();
+  if let Some(staging_router) = staging_router {
+    service = service
+      .with_host_router(config.staging_hosts, staging_router)
+      .unwrap();
+  }
 
   // The address on which the server will be listening.
   let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
@@ -278,7 +652,7 @@ async fn main() {
 
 #[cfg(test)]
 mod tests {
-  use crate::util::test::TestSetup;
+  use crate::testing::TestSetup;
   use serde_json::json;
 
   // Test the case where everything is fine and a publishing task is created.
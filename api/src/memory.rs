@@ -0,0 +1,92 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+
+thread_local! {
+  static THREAD_ALLOCATED: Cell<i64> = const { Cell::new(0) };
+}
+
+/// A global allocator that wraps the system allocator and keeps track of the
+/// number of bytes allocated by each thread, so that memory limits can be
+/// enforced for work that is confined to a single thread, like package
+/// analysis.
+///
+/// Memory that is freed on a different thread than it was allocated on is
+/// attributed to the freeing thread, so the count is an approximation.
+pub struct TrackingAllocator;
+
+impl TrackingAllocator {
+  #[inline]
+  fn record(delta: i64) {
+    // `try_with` fails during thread teardown, in which case the allocation
+    // is not tracked.
+    let _ = THREAD_ALLOCATED
+      .try_with(|allocated| allocated.set(allocated.get().wrapping_add(delta)));
+  }
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    let ptr = System.alloc(layout);
+    if !ptr.is_null() {
+      Self::record(layout.size() as i64);
+    }
+    ptr
+  }
+
+  unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+    let ptr = System.alloc_zeroed(layout);
+    if !ptr.is_null() {
+      Self::record(layout.size() as i64);
+    }
+    ptr
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout);
+    Self::record(-(layout.size() as i64));
+  }
+
+  unsafe fn realloc(
+    &self,
+    ptr: *mut u8,
+    layout: Layout,
+    new_size: usize,
+  ) -> *mut u8 {
+    let new_ptr = System.realloc(ptr, layout, new_size);
+    if !new_ptr.is_null() {
+      Self::record(new_size as i64 - layout.size() as i64);
+    }
+    new_ptr
+  }
+}
+
+/// The net number of bytes allocated by the current thread so far. Only the
+/// difference between two readings on the same thread is meaningful.
+pub fn thread_allocated_bytes() -> i64 {
+  THREAD_ALLOCATED.with(|allocated| allocated.get())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::thread_allocated_bytes;
+
+  #[test]
+  fn tracks_thread_allocations() {
+    let before = thread_allocated_bytes();
+    let data = vec![0u8; 1024 * 1024];
+    assert!(thread_allocated_bytes() - before >= 1024 * 1024);
+    drop(data);
+    assert!(thread_allocated_bytes() - before < 1024 * 1024);
+
+    // Allocations on other threads are not attributed to this thread.
+    let before = thread_allocated_bytes();
+    let data = std::thread::spawn(|| vec![0u8; 1024 * 1024])
+      .join()
+      .unwrap();
+    assert!(thread_allocated_bytes() - before < 1024 * 1024);
+    drop(data);
+  }
+}
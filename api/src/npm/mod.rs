@@ -17,26 +17,32 @@ use std::borrow::Cow;
 use url::Url;
 
 use crate::db::Database;
+use crate::db::YankReasonCategory;
 use crate::ids::PackageName;
 use crate::ids::ScopeName;
 use crate::npm::tarball::create_npm_dependencies;
+use crate::npm::tarball::create_npm_engines;
+use crate::npm::tarball::npm_bin_path;
 use crate::npm::types::NpmDistInfo;
 use crate::npm::types::NpmPackageInfo;
 
+pub use self::specifiers::npm_module_paths;
 pub use self::tarball::create_npm_tarball;
 pub use self::tarball::NpmTarball;
 pub use self::tarball::NpmTarballFiles;
 pub use self::tarball::NpmTarballOptions;
+pub use self::types::NpmJsrVersionInfo;
 pub use self::types::NpmMappedJsrPackageName;
 use self::types::NpmVersionInfo;
 
-pub const NPM_TARBALL_REVISION: u32 = 11;
+pub const NPM_TARBALL_REVISION: u32 = 12;
 
 pub async fn generate_npm_version_manifest<'a>(
   db: &Database,
   npm_url: &Url,
   scope: &'a ScopeName,
   name: &'a PackageName,
+  include_yanked_versions: bool,
 ) -> Result<NpmPackageInfo<'a>, anyhow::Error> {
   let (package, _, _) = db
     .get_package(scope, name)
@@ -69,11 +75,36 @@ pub async fn generate_npm_version_manifest<'a>(
       .to_rfc3339_opts(SecondsFormat::Millis, true),
   );
 
+  // All versions of a package that moved to a successor are deprecated.
+  let successor_deprecation = package
+    .successor_scope
+    .as_ref()
+    .zip(package.successor_name.as_ref())
+    .map(|(scope, package)| {
+      format!(
+        "This package has moved to @{scope}/{package}. Install {} instead.",
+        NpmMappedJsrPackageName { scope, package }
+      )
+    });
+
+  let mut latest = None;
   for (version, _) in versions {
-    // We don't publish yanked versions in the NPM manifest.
-    if version.is_yanked {
+    // By default yanked versions are left out of the manifest. When enabled,
+    // they stay in it so that exact versions (like those in lockfiles) can
+    // still be installed, but they are marked as deprecated so npm avoids
+    // them when resolving ranges, and prints why they were yanked when they
+    // are installed.
+    if version.is_yanked && !include_yanked_versions {
       continue;
     }
+    let deprecated = if version.is_yanked {
+      Some(yank_deprecation_message(
+        version.yank_reason_category,
+        version.yank_reason.as_deref(),
+      ))
+    } else {
+      successor_deprecation.clone()
+    };
 
     // Skip versions that don't have a tarball.
     let Some(npm_tarball) = db
@@ -100,7 +131,11 @@ pub async fn generate_npm_version_manifest<'a>(
         name: StackString::from_string(dep.dependency_name),
         version_req,
       };
-      Cow::Owned((dep.dependency_kind, PackageReqReference { req, sub_path }))
+      Cow::Owned((
+        dep.dependency_kind,
+        dep.dependency_type,
+        PackageReqReference { req, sub_path },
+      ))
     });
     let npm_dependencies = create_npm_dependencies(dependencies)?;
 
@@ -130,8 +165,20 @@ pub async fn generate_npm_version_manifest<'a>(
         integrity: format!("sha512-{}", npm_tarball.sha512),
       },
       dependencies: npm_dependencies,
+      bin: version
+        .bin
+        .iter()
+        .map(|(name, _)| (name.clone(), npm_bin_path(name)))
+        .collect(),
+      engines: create_npm_engines(&version.engines),
+      deprecated,
+      jsr: None,
     };
 
+    if latest.is_none() && !version.is_yanked {
+      latest = Some(version.version.clone());
+    }
+
     out
       .versions
       .insert(version.version.clone(), npm_version_info);
@@ -143,9 +190,24 @@ pub async fn generate_npm_version_manifest<'a>(
     );
   }
 
-  if let Some((version, _)) = out.versions.first() {
-    out.dist_tags.insert("latest".to_string(), version.clone());
+  if let Some(version) = latest {
+    out.dist_tags.insert("latest".to_string(), version);
   }
 
   Ok(out)
 }
+
+fn yank_deprecation_message(
+  category: Option<YankReasonCategory>,
+  reason: Option<&str>,
+) -> String {
+  let mut message = "This version has been yanked".to_string();
+  if let Some(category) = category {
+    message.push_str(&format!(" ({category})"));
+  }
+  match reason {
+    Some(reason) => message.push_str(&format!(": {reason}")),
+    None => message.push('.'),
+  }
+  message
+}
@@ -198,10 +198,62 @@ pub fn rewrite_path_extension(
   Some(format!("{}/{}.{}", basename, name, new_ext))
 }
 
+/// Returns where the npm tarball of a package version puts the source of a
+/// module, and its declarations if they differ from the source, following the
+/// same rewrites as `create_npm_tarball`. `emits_declarations` is whether
+/// declarations were generated from the fast check output of the package
+/// version. The declarations of JavaScript modules come from their
+/// `@ts-types` dependencies, which are not known without the module graph.
+pub fn npm_module_paths(
+  path: &str,
+  emits_declarations: bool,
+) -> (String, Option<String>) {
+  match deno_ast::MediaType::from_str(path) {
+    deno_ast::MediaType::TypeScript | deno_ast::MediaType::Mts => {
+      let source = rewrite_path_extension(path, Extension::Js)
+        .unwrap_or_else(|| path.to_owned());
+      let declarations = if emits_declarations {
+        rewrite_path_extension(path, Extension::Dts)
+          .map(|path| format!("/_dist{path}"))
+      } else {
+        None
+      };
+      (source, declarations)
+    }
+    deno_ast::MediaType::Jsx => {
+      let source = rewrite_path_extension(path, Extension::Js)
+        .unwrap_or_else(|| path.to_owned());
+      (source, None)
+    }
+    _ => (path.to_owned(), None),
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_npm_module_paths() {
+    assert_eq!(
+      npm_module_paths("/mod.ts", true),
+      ("/mod.js".to_owned(), Some("/_dist/mod.d.ts".to_owned()))
+    );
+    assert_eq!(
+      npm_module_paths("/src/mod.mts", false),
+      ("/src/mod.js".to_owned(), None)
+    );
+    assert_eq!(
+      npm_module_paths("/mod.jsx", true),
+      ("/mod.js".to_owned(), None)
+    );
+    assert_eq!(npm_module_paths("/mod.js", true), ("/mod.js".to_owned(), None));
+    assert_eq!(
+      npm_module_paths("/mod.d.ts", true),
+      ("/mod.d.ts".to_owned(), None)
+    );
+  }
+
   #[test]
   fn test_rewrite_specifier_jsr() {
     assert_eq!(
@@ -26,8 +26,13 @@ use tracing::error;
 use url::Url;
 
 use crate::buckets::BucketWithQueue;
+use crate::db::BinMap;
 use crate::db::DependencyKind;
+use crate::db::DependencyType;
 use crate::db::ExportsMap;
+use crate::db::PackageVersionEngines;
+use crate::db::RuntimeCompat;
+use crate::db::ValidationLevel;
 use crate::ids::PackageName;
 use crate::ids::PackagePath;
 use crate::ids::ScopeName;
@@ -42,6 +47,7 @@ use super::specifiers::rewrite_file_specifier;
 use super::specifiers::Extension;
 use super::specifiers::RewriteKind;
 use super::specifiers::SpecifierRewriter;
+use super::types::NpmDependencies;
 use super::types::NpmExportConditions;
 use super::types::NpmMappedJsrPackageName;
 use super::types::NpmPackageJson;
@@ -66,7 +72,7 @@ pub enum NpmTarballFiles<'a> {
 
 pub struct NpmTarballOptions<
   'a,
-  Deps: Iterator<Item = &'a (DependencyKind, PackageReqReference)>,
+  Deps: Iterator<Item = &'a (DependencyKind, DependencyType, PackageReqReference)>,
 > {
   pub graph: &'a ModuleGraph,
   pub analyzer: &'a CapturingModuleAnalyzer,
@@ -75,6 +81,10 @@ pub struct NpmTarballOptions<
   pub package: &'a PackageName,
   pub version: &'a Version,
   pub exports: &'a ExportsMap,
+  pub bin: &'a BinMap,
+  pub engines: &'a PackageVersionEngines,
+  pub runtime_compat: &'a RuntimeCompat,
+  pub validation_level: ValidationLevel,
   pub files: NpmTarballFiles<'a>,
   pub dependencies: Deps,
 }
@@ -82,7 +92,7 @@ pub struct NpmTarballOptions<
 pub async fn create_npm_tarball<'a>(
   opts: NpmTarballOptions<
     'a,
-    impl Iterator<Item = &'a (DependencyKind, PackageReqReference)>,
+    impl Iterator<Item = &'a (DependencyKind, DependencyType, PackageReqReference)>,
   >,
 ) -> Result<NpmTarball, anyhow::Error> {
   let NpmTarballOptions {
@@ -93,10 +103,19 @@ pub async fn create_npm_tarball<'a>(
     package,
     version,
     exports,
+    bin,
+    engines,
+    runtime_compat,
+    validation_level,
     files,
     dependencies,
   } = opts;
 
+  // Declarations are only generated from the fast check output if the public
+  // API of the package is fully validated. Otherwise, types come from the
+  // source files.
+  let emit_declarations = validation_level == ValidationLevel::Full;
+
   let npm_package_id = NpmMappedJsrPackageName { scope, package };
 
   let npm_dependencies =
@@ -158,7 +177,7 @@ pub async fn create_npm_tarball<'a>(
           source_rewrites.insert(module.specifier(), source_specifier);
         }
 
-        if js.fast_check_module().is_some() {
+        if emit_declarations && js.fast_check_module().is_some() {
           let declaration_specifier = rewrite_file_specifier(
             module.specifier(),
             "/_dist",
@@ -274,7 +293,9 @@ pub async fn create_npm_tarball<'a>(
         package_files
           .insert(format!("{}.map", source_target.path()), source_map);
 
-        if let Some(fast_check_module) = js.fast_check_module() {
+        if let Some(fast_check_module) =
+          js.fast_check_module().filter(|_| emit_declarations)
+        {
           let declaration_target =
             declaration_rewrites.get(&js.specifier).unwrap();
           let specifier_rewriter = SpecifierRewriter {
@@ -342,17 +363,23 @@ pub async fn create_npm_tarball<'a>(
   }
 
   let npm_exports = create_npm_exports(
+    graph,
     exports,
+    runtime_compat,
     &package_files,
     &source_rewrites,
     &declaration_rewrites,
   );
 
+  let npm_bin = create_npm_bin(bin, &mut package_files, &source_rewrites);
+
   let pkg_json = NpmPackageJson {
     name: npm_package_id,
     version: version.clone(),
     module_type: "module".to_string(),
     exports: npm_exports,
+    bin: npm_bin,
+    engines: create_npm_engines(engines),
     dependencies: npm_dependencies,
     homepage,
     revision: NPM_TARBALL_REVISION,
@@ -531,11 +558,20 @@ fn rewrite_specifiers(
 }
 
 pub fn create_npm_dependencies<'a>(
-  dependencies: impl Iterator<Item = Cow<'a, (DependencyKind, PackageReqReference)>>,
-) -> Result<IndexMap<String, String>, anyhow::Error> {
-  let mut npm_dependencies = IndexMap::new();
+  dependencies: impl Iterator<
+    Item = Cow<'a, (DependencyKind, DependencyType, PackageReqReference)>,
+  >,
+) -> Result<NpmDependencies, anyhow::Error> {
+  let mut all_npm_dependencies = NpmDependencies::default();
   for dep in dependencies {
-    let (kind, req) = &*dep;
+    let (kind, dependency_type, req) = &*dep;
+    let npm_dependencies = match dependency_type {
+      DependencyType::Regular => &mut all_npm_dependencies.dependencies,
+      DependencyType::Peer => &mut all_npm_dependencies.peer_dependencies,
+      DependencyType::Optional => {
+        &mut all_npm_dependencies.optional_dependencies
+      }
+    };
     match kind {
       DependencyKind::Jsr => {
         let jsr_name = ScopedPackageName::new(req.req.name.to_string())?;
@@ -552,12 +588,16 @@ pub fn create_npm_dependencies<'a>(
       }
     }
   }
-  npm_dependencies.sort_keys();
-  Ok(npm_dependencies)
+  all_npm_dependencies.dependencies.sort_keys();
+  all_npm_dependencies.peer_dependencies.sort_keys();
+  all_npm_dependencies.optional_dependencies.sort_keys();
+  Ok(all_npm_dependencies)
 }
 
 pub fn create_npm_exports(
+  graph: &ModuleGraph,
   exports: &ExportsMap,
+  runtime_compat: &RuntimeCompat,
   package_files: &IndexMap<String, Vec<u8>>,
   source_rewrites: &HashMap<&ModuleSpecifier, ModuleSpecifier>,
   declaration_rewrites: &HashMap<&ModuleSpecifier, ModuleSpecifier>,
@@ -569,6 +609,7 @@ pub fn create_npm_exports(
   for (key, path) in exports.iter() {
     let mut conditions = NpmExportConditions {
       types: None,
+      runtimes: IndexMap::new(),
       default: None,
     };
 
@@ -604,11 +645,136 @@ pub fn create_npm_exports(
       }
     }
 
+    if let Some(default) = &conditions.default {
+      // Entrypoints that statically import Node.js built-ins can not be
+      // bundled for browsers, unless the package explicitly says otherwise.
+      let browser = runtime_compat
+        .browser
+        .or_else(|| imports_node_builtins(graph, &specifier).then_some(false));
+      // Runtimes that match multiple conditions (e.g. Bun also matches
+      // `node`) pick the first one, so supported runtimes come first.
+      let runtimes = [
+        ("deno", runtime_compat.deno),
+        ("bun", runtime_compat.bun),
+        ("workerd", runtime_compat.workerd),
+        ("node", runtime_compat.node),
+        ("browser", browser),
+      ];
+      // Conditions for supported runtimes are only needed to avoid matching
+      // the condition of an unsupported runtime.
+      if runtimes
+        .iter()
+        .any(|(_, supported)| *supported == Some(false))
+      {
+        for (condition, supported) in runtimes {
+          match supported {
+            Some(true) => {
+              conditions.runtimes.insert(condition, Some(default.clone()));
+            }
+            Some(false) => {
+              conditions.runtimes.insert(condition, None);
+            }
+            None => {}
+          }
+        }
+      }
+    }
+
     npm_exports.insert(key.clone(), conditions);
   }
   npm_exports
 }
 
+/// Whether any module that is statically imported from the given entrypoint,
+/// directly or transitively, is a Node.js built-in module.
+fn imports_node_builtins(
+  graph: &ModuleGraph,
+  entrypoint: &ModuleSpecifier,
+) -> bool {
+  let mut seen = HashSet::new();
+  let mut pending = vec![entrypoint];
+  while let Some(specifier) = pending.pop() {
+    if !seen.insert(specifier) {
+      continue;
+    }
+    if specifier.scheme() == "node" {
+      return true;
+    }
+    let Some(js) = graph.get(specifier).and_then(|module| module.js()) else {
+      continue;
+    };
+    for dep in js.dependencies.values() {
+      if dep.is_dynamic {
+        continue;
+      }
+      if let Some(specifier) = dep.maybe_code.maybe_specifier() {
+        pending.push(specifier);
+      }
+    }
+  }
+  false
+}
+
+pub fn create_npm_engines(
+  engines: &PackageVersionEngines,
+) -> IndexMap<String, String> {
+  engines
+    .iter()
+    .map(|(engine, version)| (engine.to_string(), format!(">={version}")))
+    .collect()
+}
+
+/// The path in the tarball of the wrapper script for the given executable.
+pub fn npm_bin_path(name: &str) -> String {
+  format!("./_bin/{name}.js")
+}
+
+/// Emits a wrapper script for every executable in the bin map, and returns the
+/// `bin` field for the package.json. The wrappers have a node shebang and
+/// import the emitted JavaScript for the executable's entrypoint, so that they
+/// can be run directly by npm, for example through `npx`.
+pub fn create_npm_bin(
+  bin: &BinMap,
+  package_files: &mut IndexMap<String, Vec<u8>>,
+  source_rewrites: &HashMap<&ModuleSpecifier, ModuleSpecifier>,
+) -> IndexMap<String, String> {
+  let package_json_specifier =
+    ModuleSpecifier::parse("file:///package.json").unwrap();
+
+  let mut npm_bin = IndexMap::new();
+  for (name, path) in bin.iter() {
+    let specifier = ModuleSpecifier::parse(&format!(
+      "file:///{}",
+      path.trim_start_matches('.').trim_start_matches('/')
+    ))
+    .unwrap();
+
+    let Some(source_specifier) = follow_specifier(&specifier, source_rewrites)
+    else {
+      continue;
+    };
+    if source_specifier.scheme() != "file"
+      || !package_files.contains_key(source_specifier.path())
+    {
+      continue;
+    }
+
+    let wrapper_specifier =
+      package_json_specifier.join(&npm_bin_path(name)).unwrap();
+    let wrapper = format!(
+      "#!/usr/bin/env node\nimport \"{}\";\n",
+      relative_import_specifier(&wrapper_specifier, source_specifier)
+    );
+    package_files
+      .insert(wrapper_specifier.path().to_owned(), wrapper.into_bytes());
+    npm_bin.insert(
+      name.clone(),
+      relative_import_specifier(&package_json_specifier, &wrapper_specifier),
+    );
+  }
+  npm_bin
+}
+
 #[cfg(test)]
 mod tests {
   use std::collections::HashMap;
@@ -635,10 +801,15 @@ mod tests {
   use crate::analysis::ModuleAnalyzer;
   use crate::analysis::PassthroughJsrUrlProvider;
   use crate::db::DependencyKind;
+  use crate::db::DependencyType;
+  use crate::db::RuntimeCompat;
+  use crate::db::ValidationLevel;
   use crate::ids::PackagePath;
   use crate::npm::tests::helpers;
   use crate::npm::tests::helpers::Spec;
   use crate::npm::NPM_TARBALL_REVISION;
+  use crate::tarball::bin_map_from_json;
+  use crate::tarball::engines_from_json;
   use crate::tarball::exports_map_from_json;
 
   use super::create_npm_tarball;
@@ -660,6 +831,20 @@ mod tests {
       }
     };
 
+    let bin = match bin_map_from_json(spec.jsr_json.bin.clone(), &package) {
+      Ok(bin) => bin,
+      Err(e) => {
+        return Err(anyhow::anyhow!("failed to parse bin: {}", e));
+      }
+    };
+
+    let engines = match engines_from_json(spec.jsr_json.engines.clone()) {
+      Ok(engines) => engines,
+      Err(e) => {
+        return Err(anyhow::anyhow!("failed to parse engines: {}", e));
+      }
+    };
+
     let mut files = HashMap::new();
     let mut memory_files = vec![];
     for file in &spec.files {
@@ -703,6 +888,13 @@ mod tests {
       let specifier = Url::parse(&raw).unwrap();
       roots.push(specifier);
     }
+    for (_, path) in bin.iter() {
+      let raw = format!("file://{}", path.strip_prefix('.').unwrap());
+      let specifier = Url::parse(&raw).unwrap();
+      if !roots.contains(&specifier) {
+        roots.push(specifier);
+      }
+    }
 
     let module_analyzer = ModuleAnalyzer::default();
     graph
@@ -737,10 +929,15 @@ mod tests {
       ),
     });
 
-    let deps: Vec<(DependencyKind, PackageReqReference)> = vec![];
+    let deps: Vec<(DependencyKind, DependencyType, PackageReqReference)> =
+      vec![];
 
     let npm_tarball = create_npm_tarball(NpmTarballOptions {
       exports: &exports,
+      bin: &bin,
+      engines: &engines,
+      runtime_compat: &RuntimeCompat::default(),
+      validation_level: ValidationLevel::Full,
       package: &package,
       registry_url: &Url::parse("http://jsr.test").unwrap(),
       scope: &scope,
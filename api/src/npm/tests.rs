@@ -43,6 +43,7 @@ pub mod helpers {
         && !self.specifier.starts_with("http:")
         && !self.specifier.starts_with("npm:")
         && !self.specifier.starts_with("jsr:")
+        && !self.specifier.starts_with("node:")
       {
         Url::parse(&format!("file:///{}", self.specifier)).unwrap()
       } else {
@@ -2,6 +2,7 @@
 use indexmap::IndexMap;
 use serde::Serialize;
 
+use crate::db::RuntimeCompat;
 use crate::ids::PackageName;
 use crate::ids::ScopeName;
 use crate::ids::Version;
@@ -50,7 +51,35 @@ pub struct NpmVersionInfo<'a> {
   pub version: Version,
   pub description: String,
   pub dist: NpmDistInfo,
-  pub dependencies: IndexMap<String, String>,
+  #[serde(flatten)]
+  pub dependencies: NpmDependencies,
+  #[serde(skip_serializing_if = "IndexMap::is_empty")]
+  pub bin: IndexMap<String, String>,
+  #[serde(skip_serializing_if = "IndexMap::is_empty")]
+  pub engines: IndexMap<String, String>,
+  /// Shown by npm when the version is installed.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deprecated: Option<String>,
+  /// Only included in manifests that are served to clients that ask for it,
+  /// so that the manifests stay valid packuments for strict npm clients.
+  #[serde(rename = "_jsr", skip_serializing_if = "Option::is_none")]
+  pub jsr: Option<NpmJsrVersionInfo>,
+}
+
+/// Information about a version that JSR shows on its package pages, for npm
+/// tooling that understands JSR.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NpmJsrVersionInfo {
+  /// Whether the version was published with a provenance attestation.
+  pub has_provenance: bool,
+  /// The transparency log entry of the provenance attestation.
+  pub rekor_log_id: Option<String>,
+  /// Whether the scope requires publishing from CI with provenance.
+  pub provenance_required: bool,
+  /// The JSR score of the package, as a percentage.
+  pub score: u32,
+  pub runtime_compat: RuntimeCompat,
 }
 
 #[derive(Debug, Serialize)]
@@ -64,10 +93,24 @@ pub struct NpmPackageInfo<'a> {
   pub time: IndexMap<String, String>,
 }
 
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NpmDependencies {
+  pub dependencies: IndexMap<String, String>,
+  #[serde(skip_serializing_if = "IndexMap::is_empty")]
+  pub peer_dependencies: IndexMap<String, String>,
+  #[serde(skip_serializing_if = "IndexMap::is_empty")]
+  pub optional_dependencies: IndexMap<String, String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct NpmExportConditions {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub types: Option<String>,
+  /// Runtime specific conditions, keyed by the condition name. A `null` target
+  /// marks the export as unavailable in that runtime.
+  #[serde(flatten)]
+  pub runtimes: IndexMap<&'static str, Option<String>>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub default: Option<String>,
 }
@@ -80,8 +123,13 @@ pub struct NpmPackageJson<'a> {
 
   #[serde(rename = "type")]
   pub module_type: String,
-  pub dependencies: IndexMap<String, String>,
+  #[serde(flatten)]
+  pub dependencies: NpmDependencies,
   pub exports: IndexMap<String, NpmExportConditions>,
+  #[serde(skip_serializing_if = "IndexMap::is_empty")]
+  pub bin: IndexMap<String, String>,
+  #[serde(skip_serializing_if = "IndexMap::is_empty")]
+  pub engines: IndexMap<String, String>,
 
   #[serde(rename = "_jsr_revision")]
   pub revision: u32,
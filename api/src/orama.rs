@@ -3,6 +3,7 @@
 
 use std::sync::Arc;
 
+use crate::api::ApiMaintenanceStatus;
 use crate::api::ApiPackageScore;
 use crate::db::Package;
 use crate::db::PackageVersionMeta;
@@ -52,15 +53,34 @@ impl OramaClient {
     Ok(response)
   }
 
-  #[instrument(name = "OramaClient::upsert_package", skip(self))]
-  pub fn upsert_package(&self, package: &Package, meta: &PackageVersionMeta) {
-    if package.version_count == 0 || package.is_archived {
-      return;
+  async fn notify(
+    &self,
+    index_id: &str,
+    body: serde_json::Value,
+  ) -> Result<(), anyhow::Error> {
+    let path = format!("/webhooks/{index_id}/notify");
+    let res = self.request(&path, body).await?;
+    let status = res.status();
+    if !status.is_success() {
+      let response = res.text().await.unwrap_or_default();
+      anyhow::bail!("status {status}: {response}");
     }
+    Ok(())
+  }
 
-    if package.description.starts_with("INTERNAL") {
-      self.delete_package(&package.scope, &package.name);
-      return;
+  /// Updates the search index entry of a package. Packages that should not be
+  /// searchable are removed from the search index instead.
+  #[instrument(name = "OramaClient::upsert_package", skip(self), err)]
+  pub async fn upsert_package(
+    &self,
+    package: &Package,
+    meta: &PackageVersionMeta,
+  ) -> Result<(), anyhow::Error> {
+    if package.version_count == 0
+      || package.is_archived
+      || package.description.starts_with("INTERNAL")
+    {
+      return self.delete_package(&package.scope, &package.name).await;
     }
 
     let id = format!("@{}/{}", package.scope, package.name);
@@ -68,6 +88,17 @@ impl OramaClient {
       .latest_version
       .as_ref()
       .map(|_| ApiPackageScore::from((meta, package)).score_percentage());
+    let successor = package
+      .successor_scope
+      .as_ref()
+      .zip(package.successor_name.as_ref())
+      .map(|(scope, name)| format!("@{scope}/{name}"));
+    // Packages that moved to a successor are ranked below all other packages.
+    let rank = if successor.is_some() {
+      0
+    } else {
+      score.unwrap_or(0)
+    };
     let body = serde_json::json!({
       "upsert": [
         {
@@ -76,61 +107,28 @@ impl OramaClient {
           "name": &package.name,
           "description": &package.description,
           "runtimeCompat": &package.runtime_compat,
+          "keywords": &package.keywords,
           "score": score,
-          "_omc:number": score.unwrap_or(0),
+          "successor": successor,
+          "maintenanceStatus": package
+            .maintenance_status
+            .map(ApiMaintenanceStatus::from),
+          "_omc:number": rank,
         }
       ]
     });
-    let span = Span::current();
-    let client = self.clone();
-    let path = format!("/webhooks/{}/notify", self.package_index_id);
-    tokio::spawn(
-      async move {
-        let res = match  client.request(&path, body).await {
-          Ok(res) => res,
-          Err(err) => {
-            error!("failed to OramaClient::upsert_package: {err}");
-            return;
-          }
-        };
-        let status = res.status();
-        if !status.is_success() {
-          let response = res.text().await.unwrap_or_default();
-          error!(
-            "failed to OramaClient::upsert_package for {id} (status {status}): {response}"
-          );
-        }
-      }
-      .instrument(span),
-    );
+    self.notify(&self.package_index_id, body).await
   }
 
-  #[instrument(name = "OramaClient::delete_package", skip(self))]
-  pub fn delete_package(&self, scope: &ScopeName, package: &PackageName) {
+  #[instrument(name = "OramaClient::delete_package", skip(self), err)]
+  pub async fn delete_package(
+    &self,
+    scope: &ScopeName,
+    package: &PackageName,
+  ) -> Result<(), anyhow::Error> {
     let id = format!("@{scope}/{package}");
     let body = serde_json::json!({ "remove": [id] });
-    let span = Span::current();
-    let client = self.clone();
-    let path = format!("/webhooks/{}/notify", self.package_index_id);
-    tokio::spawn(
-      async move {
-        let res = match client.request(&path, body).await {
-          Ok(res) => res,
-          Err(err) => {
-            error!("failed to OramaClient::delete_package: {err}");
-            return;
-          }
-        };
-        let status = res.status();
-        if !status.is_success() {
-          let response = res.text().await.unwrap_or_default();
-          error!(
-            "failed to OramaClient::delete_package for {id} (status {status}): {response}"
-          );
-        }
-      }
-        .instrument(span),
-    );
+    self.notify(&self.package_index_id, body).await
   }
 
   #[instrument(name = "OramaClient::upsert_symbols", skip(self))]
@@ -1,12 +1,18 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::str::FromStr;
+
 use anyhow::{bail, Result};
 use base64::prelude::BASE64_STANDARD;
 use base64::Engine as _;
 use serde::Deserialize;
 use serde::Serialize;
+use x509_parser::certificate::X509Certificate as ParsedCertificate;
+use x509_parser::extensions::GeneralName;
 use x509_parser::parse_x509_certificate;
 use x509_parser::pem::parse_x509_pem;
 
+use crate::db::PackageVersionSource;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Signature {
@@ -117,10 +123,224 @@ pub enum ProvenanceAttestationSubject {
   Subject(Subject),
 }
 
+/// The Fulcio certificate extension that holds the OIDC issuer of the identity
+/// token, as a raw string. Deprecated in favor of `OID_ISSUER_V2`.
+const OID_ISSUER_V1: &str = "1.3.6.1.4.1.57264.1.1";
+/// The Fulcio certificate extension that holds the OIDC issuer of the identity
+/// token, as a DER encoded UTF8String.
+const OID_ISSUER_V2: &str = "1.3.6.1.4.1.57264.1.8";
+/// The Fulcio certificate extension that holds the URL of the repository the
+/// workflow ran on, as a DER encoded UTF8String.
+const OID_SOURCE_REPOSITORY_URI: &str = "1.3.6.1.4.1.57264.1.12";
+/// The Fulcio certificate extension that holds the commit the workflow ran
+/// on, as a DER encoded UTF8String.
+const OID_SOURCE_REPOSITORY_DIGEST: &str = "1.3.6.1.4.1.57264.1.13";
+
+/// A CI provider that Fulcio issues signing certificates for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvenanceProvider {
+  GithubActions,
+  GitlabCi,
+  Buildkite,
+}
+
+impl ProvenanceProvider {
+  fn default_issuer(&self) -> &'static str {
+    match self {
+      Self::GithubActions => "https://token.actions.githubusercontent.com",
+      Self::GitlabCi => "https://gitlab.com",
+      Self::Buildkite => "https://agent.buildkite.com",
+    }
+  }
+
+  fn default_identity_prefix(&self) -> &'static str {
+    match self {
+      Self::GithubActions => "https://github.com/",
+      Self::GitlabCi => "https://gitlab.com/",
+      Self::Buildkite => "https://buildkite.com/",
+    }
+  }
+}
+
+impl std::fmt::Display for ProvenanceProvider {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::GithubActions => write!(f, "github"),
+      Self::GitlabCi => write!(f, "gitlab"),
+      Self::Buildkite => write!(f, "buildkite"),
+    }
+  }
+}
+
+/// The signing certificates that are accepted for a CI provider. A
+/// certificate matches the policy if it was issued for an identity token from
+/// `issuer`, and its subject alternative name (the identity of the workflow
+/// that signed the attestation) starts with `identity_prefix`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvenancePolicy {
+  pub provider: ProvenanceProvider,
+  pub issuer: String,
+  pub identity_prefix: String,
+}
+
+impl ProvenancePolicy {
+  pub fn new(provider: ProvenanceProvider) -> Self {
+    Self {
+      provider,
+      issuer: provider.default_issuer().to_string(),
+      identity_prefix: provider.default_identity_prefix().to_string(),
+    }
+  }
+}
+
+impl FromStr for ProvenancePolicy {
+  type Err = anyhow::Error;
+
+  /// Parses `provider` or `provider=https://instance.example.com`. The latter
+  /// is used for self-hosted instances, where both the OIDC issuer and the
+  /// workflow identities are on the instance URL.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (provider, instance) = match s.trim().split_once('=') {
+      Some((provider, instance)) => (provider, Some(instance)),
+      None => (s.trim(), None),
+    };
+    let provider = match provider {
+      "github" => ProvenanceProvider::GithubActions,
+      "gitlab" => ProvenanceProvider::GitlabCi,
+      "buildkite" => ProvenanceProvider::Buildkite,
+      _ => bail!(
+        "Invalid provenance provider '{provider}', expected 'github', 'gitlab' or 'buildkite'"
+      ),
+    };
+    let mut policy = Self::new(provider);
+    if let Some(instance) = instance {
+      let url = url::Url::parse(instance)?;
+      if url.scheme() != "https" {
+        bail!("Invalid provenance provider instance '{instance}', expected an https URL");
+      }
+      let instance = url.as_str().trim_end_matches('/');
+      policy.issuer = instance.to_string();
+      policy.identity_prefix = format!("{instance}/");
+    }
+    Ok(policy)
+  }
+}
+
+/// The CI providers whose signing certificates are accepted for provenance
+/// attestations.
+#[derive(Debug, Clone)]
+pub struct ProvenancePolicies(pub Vec<ProvenancePolicy>);
+
+impl Default for ProvenancePolicies {
+  fn default() -> Self {
+    Self(vec![
+      ProvenancePolicy::new(ProvenanceProvider::GithubActions),
+      ProvenancePolicy::new(ProvenanceProvider::GitlabCi),
+      ProvenancePolicy::new(ProvenanceProvider::Buildkite),
+    ])
+  }
+}
+
+#[derive(Debug)]
+pub struct VerifiedProvenance {
+  pub rekor_log_id: String,
+  pub provider: ProvenanceProvider,
+  /// The repository and commit that the attested build ran on, if the
+  /// signing certificate records them.
+  pub source: Option<PackageVersionSource>,
+}
+
+/// Reads the source repository and commit from the Fulcio extensions of a
+/// signing certificate. Certificates issued before these extensions were
+/// introduced do not have them.
+fn certificate_source(
+  cert: &ParsedCertificate,
+) -> Option<PackageVersionSource> {
+  let mut repository = None;
+  let mut commit = None;
+  for extension in cert.extensions() {
+    match extension.oid.to_id_string().as_str() {
+      OID_SOURCE_REPOSITORY_URI => {
+        repository = der_utf8_string(extension.value).ok()
+      }
+      OID_SOURCE_REPOSITORY_DIGEST => {
+        commit = der_utf8_string(extension.value).ok()
+      }
+      _ => {}
+    }
+  }
+  let commit = commit?;
+  // SHA-1 or SHA-256 object names.
+  if !matches!(commit.len(), 40 | 64)
+    || !commit.chars().all(|c| c.is_ascii_hexdigit())
+  {
+    return None;
+  }
+  Some(PackageVersionSource {
+    repository: repository?,
+    commit: commit.to_ascii_lowercase(),
+  })
+}
+
+/// Reads the OIDC issuer from the Fulcio extensions of a signing certificate.
+fn certificate_issuer(cert: &ParsedCertificate) -> Result<String> {
+  let mut issuer_v1 = None;
+  for extension in cert.extensions() {
+    match extension.oid.to_id_string().as_str() {
+      OID_ISSUER_V2 => return der_utf8_string(extension.value),
+      OID_ISSUER_V1 => {
+        issuer_v1 = Some(String::from_utf8(extension.value.to_vec())?)
+      }
+      _ => {}
+    }
+  }
+  match issuer_v1 {
+    Some(issuer) => Ok(issuer),
+    None => bail!("Signing certificate has no issuer"),
+  }
+}
+
+fn der_utf8_string(value: &[u8]) -> Result<String> {
+  let [0x0c, len, rest @ ..] = value else {
+    bail!("Invalid UTF8String");
+  };
+  let (len, rest) = if len & 0x80 == 0 {
+    (*len as usize, rest)
+  } else {
+    let n = (len & 0x7f) as usize;
+    if n == 0 || n > 4 || rest.len() < n {
+      bail!("Invalid UTF8String");
+    }
+    let len = rest[..n]
+      .iter()
+      .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+    (len, &rest[n..])
+  };
+  if rest.len() != len {
+    bail!("Invalid UTF8String");
+  }
+  Ok(String::from_utf8(rest.to_vec())?)
+}
+
+/// Reads the workflow identity from the subject alternative name of a signing
+/// certificate.
+fn certificate_identity(cert: &ParsedCertificate) -> Result<String> {
+  let Some(san) = cert.subject_alternative_name()? else {
+    bail!("Signing certificate has no subject alternative name");
+  };
+  for name in &san.value.general_names {
+    if let GeneralName::URI(uri) = name {
+      return Ok(uri.to_string());
+    }
+  }
+  bail!("Signing certificate has no URI subject alternative name")
+}
+
 pub fn verify(
   subject_name: String,
   bundle: ProvenanceBundle,
-) -> Result<String> {
+  policies: &ProvenancePolicies,
+) -> Result<VerifiedProvenance> {
   // Extract subject from the DSSE envelope
   let subject = {
     let payload =
@@ -159,6 +379,57 @@ pub fn verify(
   let issuer_pub_key = fulcio.public_key();
   x509.verify_signature(Some(issuer_pub_key))?;
 
+  // Check that the certificate was issued to a CI provider that is accepted.
+  let issuer = certificate_issuer(&x509)?;
+  let identity = certificate_identity(&x509)?;
+  let Some(policy) = policies.0.iter().find(|policy| {
+    policy.issuer == issuer && identity.starts_with(&policy.identity_prefix)
+  }) else {
+    bail!("Signing certificate issuer '{issuer}' is not accepted");
+  };
+
   let tls = &bundle.verification_material.tlog_entries[0];
-  Ok(tls.log_index.to_string())
+  Ok(VerifiedProvenance {
+    rekor_log_id: tls.log_index.to_string(),
+    provider: policy.provider,
+    source: certificate_source(&x509),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::der_utf8_string;
+  use super::ProvenancePolicy;
+  use super::ProvenanceProvider;
+
+  #[test]
+  fn parse_provenance_policy() {
+    let policy = "github".parse::<ProvenancePolicy>().unwrap();
+    assert_eq!(policy.provider, ProvenanceProvider::GithubActions);
+    assert_eq!(policy.issuer, "https://token.actions.githubusercontent.com");
+    assert_eq!(policy.identity_prefix, "https://github.com/");
+
+    let policy = "gitlab=https://gitlab.example.com/"
+      .parse::<ProvenancePolicy>()
+      .unwrap();
+    assert_eq!(policy.provider, ProvenanceProvider::GitlabCi);
+    assert_eq!(policy.issuer, "https://gitlab.example.com");
+    assert_eq!(policy.identity_prefix, "https://gitlab.example.com/");
+
+    assert!("circleci".parse::<ProvenancePolicy>().is_err());
+    assert!("gitlab=http://gitlab.example.com"
+      .parse::<ProvenancePolicy>()
+      .is_err());
+  }
+
+  #[test]
+  fn decode_der_utf8_string() {
+    assert_eq!(der_utf8_string(b"\x0c\x03abc").unwrap(), "abc");
+    let long = "a".repeat(200);
+    let mut value = vec![0x0c, 0x81, 200];
+    value.extend_from_slice(long.as_bytes());
+    assert_eq!(der_utf8_string(&value).unwrap(), long);
+    assert!(der_utf8_string(b"\x0c\x04abc").is_err());
+    assert!(der_utf8_string(b"\x13\x03abc").is_err());
+  }
 }
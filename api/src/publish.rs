@@ -1,38 +1,57 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::Arc;
 
+use crate::analysis::AnalysisLimits;
 use crate::api::ApiError;
+use crate::api::PublishQueue;
 use crate::buckets::Buckets;
 use crate::buckets::UploadTaskBody;
+use crate::db::BinMap;
 use crate::db::Database;
 use crate::db::DependencyKind;
+use crate::db::DependencyType;
 use crate::db::ExportsMap;
 use crate::db::NewNpmTarball;
 use crate::db::NewPackageFile;
 use crate::db::NewPackageVersion;
 use crate::db::NewPackageVersionDependency;
+use crate::db::PackageVersionEngines;
 use crate::db::PackageVersionMeta;
+use crate::db::PublishedSpecifiers;
 use crate::db::PublishingTask;
 use crate::db::PublishingTaskError;
 use crate::db::PublishingTaskStatus;
+use crate::db::PublishingTaskWarning;
+use crate::db::PublishingTaskWarnings;
 use crate::gcp::GcsUploadOptions;
 use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
 use crate::gcp::CACHE_CONTROL_IMMUTABLE;
+use crate::ids::PackageName;
 use crate::ids::PackagePath;
+use crate::ids::ScopeName;
+use crate::ids::Version;
 use crate::metadata::ManifestEntry;
 use crate::metadata::PackageMetadata;
 use crate::metadata::VersionMetadata;
 use crate::npm::generate_npm_version_manifest;
 use crate::npm::NPM_TARBALL_REVISION;
 use crate::orama::OramaClient;
+use crate::queue::InProcessQueue;
+use crate::queue::TaskHandler;
+use crate::queue::TaskQueue;
+use crate::signing::upload_metadata_signature;
+use crate::signing::MetadataSigner;
 use crate::tarball::process_tarball;
 use crate::tarball::NpmTarballInfo;
 use crate::tarball::ProcessTarballOutput;
 use crate::util::decode_json;
 use crate::util::ApiResult;
+use crate::NpmIncludeYankedVersions;
 use crate::NpmUrl;
 use crate::RegistryUrl;
+use bytes::Bytes;
 use deno_semver::package::PackageReqReference;
 use hyper::Body;
 use hyper::Request;
@@ -43,6 +62,11 @@ use tracing::instrument;
 use url::Url;
 use uuid::Uuid;
 
+/// How long the tarball and diagnostic log of a failed publish are retained,
+/// so that the submitter can download them to debug the failure.
+pub const PUBLISH_ARTIFACTS_RETENTION: chrono::Duration =
+  chrono::Duration::days(7);
+
 #[instrument(
   name = "POST /tasks/publish",
   skip(req),
@@ -57,23 +81,118 @@ pub async fn publish_handler(mut req: Request<Body>) -> ApiResult<()> {
   let orama_client = req.data::<Option<OramaClient>>().unwrap().clone();
   let registry_url = req.data::<RegistryUrl>().unwrap().0.clone();
   let npm_url = req.data::<NpmUrl>().unwrap().0.clone();
+  let npm_include_yanked_versions =
+    req.data::<NpmIncludeYankedVersions>().unwrap().0;
+  let analysis_limits = *req.data::<AnalysisLimits>().unwrap();
+  let metadata_signer = req.data::<MetadataSigner>().unwrap().clone();
 
   publish_task(
     publishing_task_id,
     buckets,
     registry_url,
     npm_url,
+    npm_include_yanked_versions,
+    analysis_limits,
     db,
     orama_client,
+    metadata_signer,
   )
   .await?;
 
   Ok(())
 }
 
+/// Creates the handler that processes publishing tasks from a Postgres-backed
+/// or in-process task queue. Cloud Tasks delivers them to [`publish_handler`]
+/// instead.
+#[allow(clippy::too_many_arguments)]
+pub fn publish_task_handler(
+  buckets: Buckets,
+  registry_url: Url,
+  npm_url: Url,
+  npm_include_yanked_versions: bool,
+  analysis_limits: AnalysisLimits,
+  db: Database,
+  orama_client: Option<OramaClient>,
+  metadata_signer: MetadataSigner,
+) -> TaskHandler {
+  Arc::new(move |body| {
+    let fut = publish_task_from_body(
+      body,
+      buckets.clone(),
+      registry_url.clone(),
+      npm_url.clone(),
+      npm_include_yanked_versions,
+      analysis_limits,
+      db.clone(),
+      orama_client.clone(),
+      metadata_signer.clone(),
+    );
+    Box::pin(fut)
+  })
+}
+
+/// Returns the queue that publishing tasks are put onto to be processed. If no
+/// publish queue is configured, the tasks are processed in the background of
+/// this instance.
+pub fn publish_queue(req: &Request<Body>) -> Arc<dyn TaskQueue> {
+  if let Some(queue) = &req.data::<PublishQueue>().unwrap().0 {
+    return queue.clone();
+  }
+  Arc::new(InProcessQueue::new(publish_task_handler(
+    req.data::<Buckets>().unwrap().clone(),
+    req.data::<RegistryUrl>().unwrap().0.clone(),
+    req.data::<NpmUrl>().unwrap().0.clone(),
+    req.data::<NpmIncludeYankedVersions>().unwrap().0,
+    *req.data::<AnalysisLimits>().unwrap(),
+    req.data::<Database>().unwrap().clone(),
+    req.data::<Option<OramaClient>>().unwrap().clone(),
+    req.data::<MetadataSigner>().unwrap().clone(),
+  )))
+}
+
+/// Puts a publishing task onto a queue returned by [`publish_queue`].
+pub async fn enqueue_publishing_task(
+  queue: &dyn TaskQueue,
+  publishing_task_id: Uuid,
+) -> Result<(), ApiError> {
+  let body = serde_json::to_vec(&publishing_task_id).unwrap();
+  queue.enqueue(body.into()).await?;
+  Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn publish_task_from_body(
+  body: Bytes,
+  buckets: Buckets,
+  registry_url: Url,
+  npm_url: Url,
+  npm_include_yanked_versions: bool,
+  analysis_limits: AnalysisLimits,
+  db: Database,
+  orama_client: Option<OramaClient>,
+  metadata_signer: MetadataSigner,
+) -> Result<(), anyhow::Error> {
+  let publishing_task_id: Uuid = serde_json::from_slice(&body)?;
+  publish_task(
+    publishing_task_id,
+    buckets,
+    registry_url,
+    npm_url,
+    npm_include_yanked_versions,
+    analysis_limits,
+    db,
+    orama_client,
+    metadata_signer,
+  )
+  .await?;
+  Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 #[instrument(
   name = "publish_task",
-  skip(buckets, db, registry_url, orama_client),
+  skip(buckets, db, registry_url, orama_client, metadata_signer),
   err
 )]
 pub async fn publish_task(
@@ -81,8 +200,11 @@ pub async fn publish_task(
   buckets: Buckets,
   registry_url: Url,
   npm_url: Url,
+  npm_include_yanked_versions: bool,
+  analysis_limits: AnalysisLimits,
   db: Database,
   orama_client: Option<OramaClient>,
+  metadata_signer: MetadataSigner,
 ) -> Result<(), ApiError> {
   let mut publishing_task = db
     .get_publishing_task(publish_id)
@@ -100,7 +222,9 @@ pub async fn publish_task(
           &db,
           &buckets,
           &orama_client,
+          &metadata_signer,
           registry_url.clone(),
+          analysis_limits,
           &mut publishing_task,
         )
         .await;
@@ -116,14 +240,36 @@ pub async fn publish_task(
           return Err(err.into());
         }
       }
+      PublishingTaskStatus::Scheduled => return Ok(()),
       PublishingTaskStatus::Processing => {
         error!("publishing task already processing");
         return Err(ApiError::InternalServerError);
       }
       PublishingTaskStatus::Processed => {
-        upload_package_manifest(&db, &buckets, &publishing_task).await?;
-        upload_npm_version_manifest(&db, &buckets, &npm_url, &publishing_task)
+        let latest = upload_package_manifest(
+          &db,
+          &buckets,
+          &metadata_signer,
+          &publishing_task.package_scope,
+          &publishing_task.package_name,
+        )
+        .await?;
+        if latest.as_ref() == Some(&publishing_task.package_version) {
+          db.update_package_keywords_from_version(
+            &publishing_task.package_scope,
+            &publishing_task.package_name,
+            &publishing_task.package_version,
+          )
           .await?;
+        }
+        upload_npm_version_manifest(
+          &db,
+          &buckets,
+          &npm_url,
+          &publishing_task,
+          npm_include_yanked_versions,
+        )
+        .await?;
         publishing_task = db
           .update_publishing_task_status(
             publishing_task.id,
@@ -134,19 +280,7 @@ pub async fn publish_task(
           .await?;
       }
       PublishingTaskStatus::Failure => return Ok(()),
-      PublishingTaskStatus::Success => {
-        if let Some(orama_client) = orama_client {
-          let (package, _, meta) = db
-            .get_package(
-              &publishing_task.package_scope,
-              &publishing_task.package_name,
-            )
-            .await?
-            .ok_or(ApiError::InternalServerError)?;
-          orama_client.upsert_package(&package, &meta);
-        }
-        return Ok(());
-      }
+      PublishingTaskStatus::Success => return Ok(()),
     }
   }
 }
@@ -155,7 +289,9 @@ async fn process_publishing_task(
   db: &Database,
   buckets: &Buckets,
   orama_client: &Option<OramaClient>,
+  metadata_signer: &MetadataSigner,
   registry_url: Url,
+  analysis_limits: AnalysisLimits,
   publishing_task: &mut PublishingTask,
 ) -> Result<(), anyhow::Error> {
   *publishing_task = db
@@ -167,62 +303,117 @@ async fn process_publishing_task(
     )
     .await?;
 
-  let output =
-    match process_tarball(db, buckets, registry_url, publishing_task).await {
-      Ok(output) => output,
-      Err(err) => match err.user_error_code() {
-        Some(code) => {
-          // non retryable, fatal error
-          error!("Error processing tarball, fatal: {}", err);
-          *publishing_task = db
-            .update_publishing_task_status(
-              publishing_task.id,
-              PublishingTaskStatus::Processing,
-              PublishingTaskStatus::Failure,
-              Some(PublishingTaskError {
-                code: code.to_owned(),
-                message: err.to_string(),
-              }),
-            )
-            .await?;
-          return Ok(());
-        }
-        None => {
-          // retryable errors
-          return Err(anyhow::Error::from(err));
-        }
-      },
-    };
+  let output = match process_tarball(
+    db,
+    buckets,
+    registry_url,
+    analysis_limits,
+    publishing_task,
+  )
+  .await
+  {
+    Ok(output) => output,
+    Err(err) => match err.user_error_code() {
+      Some(code) => {
+        // non retryable, fatal error
+        error!("Error processing tarball, fatal: {}", err);
+        *publishing_task = db
+          .update_publishing_task_status(
+            publishing_task.id,
+            PublishingTaskStatus::Processing,
+            PublishingTaskStatus::Failure,
+            Some(PublishingTaskError {
+              code: code.to_owned(),
+              message: err.to_string(),
+              files: err.files(),
+              config_fields: err.config_fields(),
+            }),
+          )
+          .await?;
+        let diagnostic_log = format!("{code}: {err}\n\n{err:#?}\n");
+        *publishing_task = db
+          .retain_publishing_task_artifacts(
+            publishing_task.id,
+            &diagnostic_log,
+            chrono::Utc::now() + PUBLISH_ARTIFACTS_RETENTION,
+          )
+          .await?;
+        return Ok(());
+      }
+      None => {
+        // retryable errors
+        return Err(anyhow::Error::from(err));
+      }
+    },
+  };
+
+  // The tarball is valid, but the version must not be published before the
+  // scheduled time. The scheduled_publishes task resets the task to pending
+  // once that time has passed, and it is then processed again from scratch.
+  if publishing_task
+    .publish_at
+    .is_some_and(|publish_at| publish_at > chrono::Utc::now())
+  {
+    *publishing_task = db
+      .update_publishing_task_status(
+        publishing_task.id,
+        PublishingTaskStatus::Processing,
+        PublishingTaskStatus::Scheduled,
+        None,
+      )
+      .await?;
+    return Ok(());
+  }
 
   let ProcessTarballOutput {
     file_infos,
     module_graph_2,
     exports,
+    bin,
+    engines,
+    keywords,
+    license,
     dependencies,
     npm_tarball_info,
     readme_path,
     meta,
     doc_search_json,
+    warnings,
+    specifiers,
   } = output;
 
   upload_version_manifest(
     buckets,
-    publishing_task,
+    metadata_signer,
+    &publishing_task.package_scope,
+    &publishing_task.package_name,
+    &publishing_task.package_version,
     &file_infos,
     exports.clone().into_inner(),
     module_graph_2,
   )
   .await?;
 
+  db.set_publishing_task_specifiers(
+    publishing_task.id,
+    &PublishedSpecifiers(specifiers),
+  )
+  .await?;
+
   create_package_version_and_npm_tarball_and_update_publishing_task(
     db,
     publishing_task,
     &file_infos,
     exports,
+    bin,
+    engines,
+    keywords,
+    license,
     dependencies,
     &npm_tarball_info,
     readme_path,
     meta,
+    warnings,
   )
   .await?;
 
@@ -237,18 +428,19 @@ async fn process_publishing_task(
   Ok(())
 }
 
-async fn upload_version_manifest(
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn upload_version_manifest(
   buckets: &Buckets,
-  publishing_task: &PublishingTask,
+  metadata_signer: &MetadataSigner,
+  scope: &ScopeName,
+  name: &PackageName,
+  version: &Version,
   file_infos: &[crate::tarball::FileInfo],
   exports: IndexMap<String, String>,
   module_graph_2: HashMap<String, deno_graph::ModuleInfo>,
 ) -> Result<(), anyhow::Error> {
-  let version_metadata_gcs_path = crate::gcs_paths::version_metadata(
-    &publishing_task.package_scope,
-    &publishing_task.package_name,
-    &publishing_task.package_version,
-  );
+  let version_metadata_gcs_path =
+    crate::gcs_paths::version_metadata(scope, name, version);
   let manifest = file_infos
     .iter()
     .map(|file_info| {
@@ -270,8 +462,8 @@ async fn upload_version_manifest(
   buckets
     .modules_bucket
     .upload(
-      version_metadata_gcs_path.into(),
-      UploadTaskBody::Bytes(content.into()),
+      version_metadata_gcs_path.as_str().into(),
+      UploadTaskBody::Bytes(content.clone().into()),
       GcsUploadOptions {
         content_type: Some("application/json".into()),
         cache_control: Some(CACHE_CONTROL_IMMUTABLE.into()),
@@ -279,6 +471,14 @@ async fn upload_version_manifest(
       },
     )
     .await?;
+  upload_metadata_signature(
+    buckets,
+    metadata_signer,
+    &version_metadata_gcs_path,
+    &content,
+    CACHE_CONTROL_IMMUTABLE,
+  )
+  .await?;
 
   Ok(())
 }
@@ -289,14 +489,19 @@ async fn create_package_version_and_npm_tarball_and_update_publishing_task(
   publishing_task: &mut PublishingTask,
   file_infos: &[crate::tarball::FileInfo],
   exports: ExportsMap,
-  dependencies: HashSet<(DependencyKind, PackageReqReference)>,
+  bin: BinMap,
+  engines: PackageVersionEngines,
+  keywords: Vec<String>,
+  license: Option<String>,
+  dependencies: HashSet<(DependencyKind, DependencyType, PackageReqReference)>,
   npm_tarball_info: &NpmTarballInfo,
   readme_path: Option<PackagePath>,
   meta: PackageVersionMeta,
+  warnings: Vec<PublishingTaskWarning>,
 ) -> Result<(), anyhow::Error> {
   let uses_npm = dependencies
     .iter()
-    .any(|(kind, _)| kind == &DependencyKind::Npm);
+    .any(|(kind, _, _)| kind == &DependencyKind::Npm);
 
   let new_package_version = NewPackageVersion {
     scope: &publishing_task.package_scope,
@@ -306,6 +511,10 @@ async fn create_package_version_and_npm_tarball_and_update_publishing_task(
     readme_path: readme_path.as_ref(),
     uses_npm,
     exports: &exports,
+    bin: &bin,
+    engines: &engines,
+    keywords: &keywords,
+    license: license.as_deref(),
     meta,
   };
 
@@ -323,11 +532,12 @@ async fn create_package_version_and_npm_tarball_and_update_publishing_task(
 
   let new_package_version_dependencies = dependencies
     .iter()
-    .map(|(kind, req)| NewPackageVersionDependency {
+    .map(|(kind, dependency_type, req)| NewPackageVersionDependency {
       package_scope: &publishing_task.package_scope,
       package_name: &publishing_task.package_name,
       package_version: &publishing_task.package_version,
       dependency_kind: *kind,
+      dependency_type: *dependency_type,
       dependency_name: &req.req.name,
       dependency_constraint: req.req.version_req.version_text(),
       dependency_path: req.sub_path.as_deref().unwrap_or(""),
@@ -351,33 +561,29 @@ async fn create_package_version_and_npm_tarball_and_update_publishing_task(
       &new_package_files,
       &new_package_version_dependencies,
       new_npm_tarball,
+      &PublishingTaskWarnings(warnings),
     )
     .await?;
 
   Ok(())
 }
 
-async fn upload_package_manifest(
+pub(crate) async fn upload_package_manifest(
   db: &Database,
   buckets: &Buckets,
-  publishing_task: &PublishingTask,
-) -> Result<(), anyhow::Error> {
-  let package_metadata_gcs_path = crate::gcs_paths::package_metadata(
-    &publishing_task.package_scope,
-    &publishing_task.package_name,
-  );
-  let package_metadata = PackageMetadata::create(
-    db,
-    &publishing_task.package_scope,
-    &publishing_task.package_name,
-  )
-  .await?;
+  metadata_signer: &MetadataSigner,
+  scope: &ScopeName,
+  name: &PackageName,
+) -> Result<Option<Version>, anyhow::Error> {
+  let package_metadata_gcs_path =
+    crate::gcs_paths::package_metadata(scope, name);
+  let package_metadata = PackageMetadata::create(db, scope, name).await?;
   let content = serde_json::to_vec_pretty(&package_metadata)?;
   buckets
     .modules_bucket
     .upload(
-      package_metadata_gcs_path.into(),
-      UploadTaskBody::Bytes(content.into()),
+      package_metadata_gcs_path.as_str().into(),
+      UploadTaskBody::Bytes(content.clone().into()),
       GcsUploadOptions {
         content_type: Some("application/json".into()),
         cache_control: Some(CACHE_CONTROL_DO_NOT_CACHE.into()),
@@ -385,8 +591,16 @@ async fn upload_package_manifest(
       },
     )
     .await?;
+  upload_metadata_signature(
+    buckets,
+    metadata_signer,
+    &package_metadata_gcs_path,
+    &content,
+    CACHE_CONTROL_DO_NOT_CACHE,
+  )
+  .await?;
 
-  Ok(())
+  Ok(package_metadata.latest)
 }
 
 async fn upload_npm_version_manifest(
@@ -394,6 +608,7 @@ async fn upload_npm_version_manifest(
   buckets: &Buckets,
   npm_url: &Url,
   publishing_task: &PublishingTask,
+  include_yanked_versions: bool,
 ) -> Result<(), anyhow::Error> {
   let npm_version_manifest_path_gcs_path =
     crate::gcs_paths::npm_version_manifest_path(
@@ -405,6 +620,7 @@ async fn upload_npm_version_manifest(
     npm_url,
     &publishing_task.package_scope,
     &publishing_task.package_name,
+    include_yanked_versions,
   )
   .await?;
   let content = serde_json::to_vec_pretty(&npm_version_manifest)?;
@@ -428,17 +644,23 @@ async fn upload_npm_version_manifest(
 pub mod tests {
   use super::*;
   use crate::api::ApiPublishingTask;
+  use crate::api::ApiPublishingTaskErrorFile;
+  use crate::api::ApiPublishingTaskExplanation;
+  use crate::api::ApiPublishingTaskSizeReport;
+  use crate::api::ApiPublishingTaskStatus;
   use crate::db::CreatePackageResult;
   use crate::db::CreatePublishingTaskResult;
   use crate::db::NewPublishingTask;
+  use crate::db::ValidationLevel;
   use crate::ids::ScopeName;
   use crate::ids::Version;
   use crate::ids::{PackageName, PackagePath};
   use crate::metadata::VersionMetadata;
+  use crate::signing::MetadataSignatures;
   use crate::tarball::gcs_tarball_path;
   use crate::tarball::ConfigFile;
-  use crate::util::test::ApiResultExt;
-  use crate::util::test::TestSetup;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
   use bytes::Bytes;
   use deno_graph::ModuleInfo;
   use flate2::write::GzEncoder;
@@ -464,6 +686,25 @@ pub mod tests {
     package_name: &PackageName,
     version: &Version,
     jsonc: bool,
+  ) -> PublishingTask {
+    process_tarball_setup_with_limits(
+      t,
+      tarball_data,
+      package_name,
+      version,
+      jsonc,
+      AnalysisLimits::default(),
+    )
+    .await
+  }
+
+  pub async fn process_tarball_setup_with_limits(
+    t: &TestSetup,
+    tarball_data: Bytes,
+    package_name: &PackageName,
+    version: &Version,
+    jsonc: bool,
+    analysis_limits: AnalysisLimits,
   ) -> PublishingTask {
     let scope_name = "scope".try_into().unwrap();
 
@@ -481,6 +722,8 @@ pub mod tests {
       .db()
       .create_publishing_task(NewPublishingTask {
         user_id: Some(t.user1.user.id),
+        github_repository_id: None,
+        github_workflow_run: None,
         package_scope: &scope_name,
         package_name,
         package_version: version,
@@ -489,6 +732,9 @@ pub mod tests {
           if jsonc { "c" } else { "" }
         ))
         .unwrap(),
+        bypass_publish_freezes: false,
+        publish_at: None,
+        idempotency_key: None,
       })
       .await
       .unwrap()
@@ -516,8 +762,11 @@ pub mod tests {
       t.buckets(),
       t.registry_url(),
       t.npm_url(),
+      false,
+      analysis_limits,
       t.db(),
       None,
+      t.metadata_signer(),
     )
     .await
     .unwrap();
@@ -724,6 +973,127 @@ pub mod tests {
     assert_eq!(dependencies[1].dependency_name, "express");
   }
 
+  #[tokio::test]
+  async fn success_peer_and_optional_dependencies() {
+    let t = TestSetup::new().await;
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("peer_dependencies")).await;
+    assert_eq!(
+      task.status,
+      PublishingTaskStatus::Success,
+      "publishing task failed {task:?}"
+    );
+
+    let dependencies = t
+      .db()
+      .list_package_version_dependencies(
+        &task.package_scope,
+        &task.package_name,
+        &task.package_version,
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(dependencies.len(), 2);
+    assert_eq!(dependencies[0].dependency_name, "chalk");
+    assert_eq!(dependencies[0].dependency_type, DependencyType::Optional);
+    assert_eq!(dependencies[1].dependency_name, "express");
+    assert_eq!(dependencies[1].dependency_type, DependencyType::Peer);
+  }
+
+  #[tokio::test]
+  async fn success_keywords() {
+    let mut t = TestSetup::new().await;
+    let task = process_tarball_setup(&t, create_mock_tarball("keywords")).await;
+    assert_eq!(
+      task.status,
+      PublishingTaskStatus::Success,
+      "publishing task failed {task:?}"
+    );
+
+    let (package, _, _) = t
+      .db()
+      .get_package(&task.package_scope, &task.package_name)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(package.keywords, vec!["testing", "cli", "left-pad"]);
+
+    let mut resp = t
+      .unauthed_http()
+      .get("/api/topics/testing/packages")
+      .call()
+      .await
+      .unwrap();
+    let packages: crate::api::ApiList<crate::api::ApiPackage> =
+      resp.expect_ok().await;
+    assert_eq!(packages.total, 1);
+    assert_eq!(packages.items[0].name, task.package_name);
+
+    // Free-form keywords can not be browsed as topics.
+    let mut resp = t
+      .unauthed_http()
+      .get("/api/topics/left-pad/packages")
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "topicNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn success_permissions() {
+    let mut t = TestSetup::new().await;
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("permissions")).await;
+    assert_eq!(
+      task.status,
+      PublishingTaskStatus::Success,
+      "publishing task failed {task:?}"
+    );
+
+    let expected = serde_json::json!({
+      "net": ["api.example.com"],
+      "env": true,
+    });
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/versions/1.2.3")
+      .call()
+      .await
+      .unwrap();
+    let version: crate::api::ApiPackageVersion = resp.expect_ok().await;
+    assert_eq!(
+      serde_json::to_value(&version.permissions).unwrap(),
+      expected
+    );
+
+    let mut resp = t
+      .http()
+      .get("/api/scopes/scope/packages/foo/score")
+      .call()
+      .await
+      .unwrap();
+    let score: crate::api::ApiPackageScore = resp.expect_ok().await;
+    assert!(score.has_permissions_declaration);
+    assert_eq!(serde_json::to_value(&score.permissions).unwrap(), expected);
+  }
+
+  #[tokio::test]
+  async fn peer_dependency_not_imported() {
+    let t = TestSetup::new().await;
+    let task = process_tarball_setup(
+      &t,
+      create_mock_tarball("peer_dependency_not_imported"),
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    let error = task.error.unwrap();
+    assert_eq!(error.code, "configFileDependenciesInvalid");
+  }
+
   #[tokio::test]
   async fn not_allowed() {
     let mut t = TestSetup::new().await;
@@ -872,6 +1242,45 @@ pub mod tests {
     assert_eq!(package_metadata.versions.len(), 1);
   }
 
+  #[tokio::test]
+  async fn signed_metadata() {
+    use base64::Engine;
+
+    let t = TestSetup::new().await;
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{:?}", task);
+
+    let public_key = base64::prelude::BASE64_STANDARD
+      .decode("bp37CigcdJIIKL9cj91XNGUa7DbKx/tyCFHxDSxHZZo=")
+      .unwrap();
+    for path in ["@scope/foo/meta.json", "@scope/foo/1.2.3_meta.json"] {
+      let content = t
+        .buckets
+        .modules_bucket
+        .download(path.into())
+        .await
+        .unwrap()
+        .unwrap();
+      let signatures: MetadataSignatures = {
+        let json = t
+          .buckets
+          .modules_bucket
+          .download(format!("{path}.sig").into())
+          .await
+          .unwrap()
+          .unwrap();
+        serde_json::from_slice(&json).unwrap()
+      };
+      assert_eq!(signatures.signatures.len(), 1);
+      let signature = &signatures.signatures[0];
+      assert_eq!(signature.keyid, "test-key");
+      assert!(
+        crate::signing::verify(&public_key, &content, &signature.sig),
+        "{path}"
+      );
+    }
+  }
+
   #[tokio::test]
   async fn module_graph() {
     let t = TestSetup::new().await;
@@ -1030,41 +1439,680 @@ pub mod tests {
   }
 
   #[tokio::test]
-  async fn invalid_exports() {
-    let t = TestSetup::new().await;
-    let task =
-      process_tarball_setup(&t, create_mock_tarball("invalid_exports")).await;
-    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
-    let error = task.error.unwrap();
-    assert_eq!(error.code, "configFileExportsInvalid");
-  }
-
-  #[tokio::test]
-  async fn exports_not_found() {
-    let t = TestSetup::new().await;
-    let task =
-      process_tarball_setup(&t, create_mock_tarball("exports_not_found")).await;
-    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
-    let error = task.error.unwrap();
-    assert_eq!(error.code, "configFileExportsInvalid");
-  }
-
-  #[tokio::test]
-  async fn invalid_path() {
-    let t = TestSetup::new().await;
-    let task =
-      process_tarball_setup(&t, create_invalid_path_mock_tarball()).await;
-    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
-    let error = task.error.unwrap();
-    assert_eq!(error.code, "invalidPath");
-  }
+  async fn scheduled_publish() {
+    let mut t = TestSetup::new().await;
+    let name = PackageName::new("foo".to_owned()).unwrap();
+    let version = Version::new("1.2.3").unwrap();
+    t.db().create_package(&t.scope.scope, &name).await.unwrap();
 
-  #[tokio::test]
-  async fn import_assertions() {
-    let t = TestSetup::new().await;
+    // publishAt must be in the future
+    let publish_at = (chrono::Utc::now() - chrono::Duration::minutes(1))
+      .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    t.http()
+      .post(format!(
+        "/api/scopes/scope/packages/foo/versions/1.2.3?config=/jsr.json&publishAt={publish_at}"
+      ))
+      .gzip()
+      .body(Body::from(create_mock_tarball("ok")))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "malformedRequest")
+      .await;
 
-    let bytes = create_mock_tarball("import_assertions");
-    let task = process_tarball_setup2(
+    let publish_at = chrono::Utc::now() + chrono::Duration::seconds(2);
+    let CreatePublishingTaskResult::Created(task) = t
+      .db()
+      .create_publishing_task(NewPublishingTask {
+        user_id: Some(t.user1.user.id),
+        github_repository_id: None,
+        github_workflow_run: None,
+        package_scope: &t.scope.scope,
+        package_name: &name,
+        package_version: &version,
+        config_file: &PackagePath::try_from("/jsr.json").unwrap(),
+        bypass_publish_freezes: false,
+        publish_at: Some(publish_at),
+        idempotency_key: None,
+      })
+      .await
+      .unwrap()
+    else {
+      unreachable!()
+    };
+    t.buckets
+      .publishing_bucket
+      .upload(
+        gcs_tarball_path(task.id).into(),
+        UploadTaskBody::Bytes(create_mock_tarball("ok")),
+        GcsUploadOptions {
+          content_type: Some("application/x-tar".into()),
+          cache_control: None,
+          gzip_encoded: true,
+        },
+      )
+      .await
+      .unwrap();
+
+    // The tarball is validated right away, but the version is not published.
+    publish_task(
+      task.id,
+      t.buckets(),
+      t.registry_url(),
+      t.npm_url(),
+      false,
+      AnalysisLimits::default(),
+      t.db(),
+      None,
+      t.metadata_signer(),
+    )
+    .await
+    .unwrap();
+    let task = t.db().get_publishing_task(task.id).await.unwrap().unwrap();
+    assert_eq!(task.status, PublishingTaskStatus::Scheduled, "{task:#?}");
+    assert!(t
+      .db()
+      .get_package_version(&t.scope.scope, &name, &version)
+      .await
+      .unwrap()
+      .is_none());
+
+    // Before the scheduled time, the task is left alone.
+    let resp = t
+      .http()
+      .post("/tasks/scheduled_publishes")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let task = t.db().get_publishing_task(task.id).await.unwrap().unwrap();
+    assert_eq!(task.status, PublishingTaskStatus::Scheduled, "{task:#?}");
+
+    tokio::time::sleep(
+      (publish_at - chrono::Utc::now())
+        .to_std()
+        .unwrap_or_default(),
+    )
+    .await;
+
+    let resp = t
+      .http()
+      .post("/tasks/scheduled_publishes")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let task = t.db().get_publishing_task(task.id).await.unwrap().unwrap();
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    assert!(t
+      .db()
+      .get_package_version(&t.scope.scope, &name, &version)
+      .await
+      .unwrap()
+      .is_some());
+  }
+
+  #[tokio::test]
+  async fn failed_publish_artifacts() {
+    let mut t = TestSetup::new().await;
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("no_exports")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    assert!(task.artifacts_expire_at.is_some());
+
+    let mut resp = t
+      .http()
+      .get(format!("/api/publishing_tasks/{}/diagnostic_log", task.id))
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = hyper::body::to_bytes(resp.body_mut()).await.unwrap();
+    let log = std::str::from_utf8(&bytes).unwrap();
+    assert!(log.starts_with("configFileExportsInvalid: "), "{log}");
+
+    let mut resp = t
+      .http()
+      .get(format!("/api/publishing_tasks/{}/tarball", task.id))
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = hyper::body::to_bytes(resp.body_mut()).await.unwrap();
+    assert!(!bytes.is_empty());
+
+    // only the submitter can download the artifacts
+    let token = t.user2.token.clone();
+    let mut resp = t
+      .http()
+      .get(format!("/api/publishing_tasks/{}/tarball", task.id))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+
+    // artifacts are deleted once they expire
+    let db = t.db();
+    db.retain_publishing_task_artifacts(
+      task.id,
+      "log",
+      chrono::Utc::now() - chrono::Duration::minutes(1),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+      db.list_expired_publishing_task_artifacts().await.unwrap(),
+      vec![task.id]
+    );
+    let mut resp = t
+      .http()
+      .get(format!("/api/publishing_tasks/{}/diagnostic_log", task.id))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::NOT_FOUND, "publishArtifactsNotFound")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn failed_publish_explanation() {
+    let mut t = TestSetup::new().await;
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("no_exports")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+
+    let explanation: ApiPublishingTaskExplanation = t
+      .http()
+      .get(format!("/api/publishing_tasks/{}/explanation", task.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(explanation.code, "configFileExportsInvalid");
+    assert!(!explanation.remediation.is_empty());
+    assert!(explanation
+      .docs_url
+      .unwrap()
+      .ends_with("/docs/troubleshooting#configfileexportsinvalid"));
+    assert_eq!(explanation.config_fields, vec!["exports"]);
+    assert_eq!(explanation.files.len(), 1);
+    assert_eq!(explanation.files[0].path, "/jsr.json");
+    assert_ne!(explanation.analysis_versions.deno_graph, "unknown");
+
+    let task = process_tarball_setup(
+      &t,
+      create_mock_tarball("global_type_augmentation1"),
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    let explanation: ApiPublishingTaskExplanation = t
+      .http()
+      .get(format!("/api/publishing_tasks/{}/explanation", task.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(explanation.code, "globalTypeAugmentation");
+    assert!(explanation.config_fields.is_empty());
+    assert_eq!(
+      explanation.files,
+      vec![ApiPublishingTaskErrorFile {
+        path: "/mod.ts".to_owned(),
+        line: Some(1),
+        column: Some(1),
+      }]
+    );
+
+    // successful publishes have nothing to explain
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    t.http()
+      .get(format!("/api/publishing_tasks/{}/explanation", task.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "publishNotFailed")
+      .await;
+  }
+
+  #[tokio::test]
+  async fn cancel_publishing_task() {
+    let mut t = TestSetup::new().await;
+    let scope = ScopeName::try_from("scope").unwrap();
+    let package = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.2.3").unwrap();
+    t.db().create_package(&scope, &package).await.unwrap();
+
+    let user_id = t.user1.user.id;
+    let config_file = PackagePath::try_from("/jsr.json").unwrap();
+    let new_task = || NewPublishingTask {
+      user_id: Some(user_id),
+      github_repository_id: None,
+      github_workflow_run: None,
+      package_scope: &scope,
+      package_name: &package,
+      package_version: &version,
+      config_file: &config_file,
+      bypass_publish_freezes: false,
+      publish_at: None,
+      idempotency_key: None,
+    };
+    let CreatePublishingTaskResult::Created(task) =
+      t.db().create_publishing_task(new_task()).await.unwrap()
+    else {
+      unreachable!()
+    };
+    t.buckets
+      .publishing_bucket
+      .upload(
+        gcs_tarball_path(task.id).into(),
+        UploadTaskBody::Bytes(create_mock_tarball("ok")),
+        GcsUploadOptions {
+          content_type: Some("application/x-tar".into()),
+          cache_control: None,
+          gzip_encoded: true,
+        },
+      )
+      .await
+      .unwrap();
+
+    // the pending task blocks publishing the version again
+    let res = t.db().create_publishing_task(new_task()).await.unwrap();
+    assert!(matches!(res, CreatePublishingTaskResult::Exists(_)));
+
+    // only the user that submitted the publish can cancel it
+    let token = t.user2.token.clone();
+    t.http()
+      .post(format!("/api/publishing_tasks/{}/cancel", task.id))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+
+    let cancelled: ApiPublishingTask = t
+      .http()
+      .post(format!("/api/publishing_tasks/{}/cancel", task.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_ok()
+      .await;
+    assert_eq!(cancelled.status, ApiPublishingTaskStatus::Failure);
+    assert_eq!(cancelled.error.unwrap().code, "cancelled");
+    assert!(t
+      .buckets
+      .publishing_bucket
+      .download(gcs_tarball_path(task.id).into())
+      .await
+      .unwrap()
+      .is_none());
+
+    // a worker that picks up the task afterwards leaves it alone
+    publish_task(
+      task.id,
+      t.buckets(),
+      t.registry_url(),
+      t.npm_url(),
+      false,
+      AnalysisLimits::default(),
+      t.db(),
+      None,
+      t.metadata_signer(),
+    )
+    .await
+    .unwrap();
+    let task = t.db().get_publishing_task(task.id).await.unwrap().unwrap();
+    assert_eq!(task.status, PublishingTaskStatus::Failure);
+
+    t.http()
+      .post(format!("/api/publishing_tasks/{}/cancel", task.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "publishNotCancellable")
+      .await;
+
+    let res = t.db().create_publishing_task(new_task()).await.unwrap();
+    assert!(matches!(res, CreatePublishingTaskResult::Created(_)));
+  }
+
+  #[tokio::test]
+  async fn retry_publishing_task() {
+    let mut t = TestSetup::new().await;
+    let scope = ScopeName::try_from("scope").unwrap();
+    let package = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.2.3").unwrap();
+    t.db().create_package(&scope, &package).await.unwrap();
+
+    let CreatePublishingTaskResult::Created(task) = t
+      .db()
+      .create_publishing_task(NewPublishingTask {
+        user_id: Some(t.user1.user.id),
+        github_repository_id: None,
+        github_workflow_run: None,
+        package_scope: &scope,
+        package_name: &package,
+        package_version: &version,
+        config_file: &PackagePath::try_from("/jsr.json").unwrap(),
+        bypass_publish_freezes: false,
+        publish_at: None,
+        idempotency_key: None,
+      })
+      .await
+      .unwrap()
+    else {
+      unreachable!()
+    };
+
+    // the task has only just been submitted
+    t.http()
+      .post(format!("/api/publishing_tasks/{}/retry", task.id))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::BAD_REQUEST, "publishNotRetryable")
+      .await;
+
+    let token = t.user2.token.clone();
+    t.http()
+      .post(format!("/api/publishing_tasks/{}/retry", task.id))
+      .token(Some(&token))
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+
+    // a retry restarts the time the task has been pending for
+    let cutoff = chrono::Utc::now() + chrono::Duration::minutes(1);
+    let retried = t
+      .db()
+      .retry_pending_publishing_task(task.id, cutoff)
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(retried.updated_at > task.updated_at);
+    let cutoff = chrono::Utc::now() - chrono::Duration::minutes(10);
+    assert!(t
+      .db()
+      .retry_pending_publishing_task(task.id, cutoff)
+      .await
+      .unwrap()
+      .is_none());
+  }
+
+  #[tokio::test]
+  async fn invalid_exports() {
+    let t = TestSetup::new().await;
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("invalid_exports")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    let error = task.error.unwrap();
+    assert_eq!(error.code, "configFileExportsInvalid");
+  }
+
+  #[tokio::test]
+  async fn exports_not_found() {
+    let t = TestSetup::new().await;
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("exports_not_found")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    let error = task.error.unwrap();
+    assert_eq!(error.code, "configFileExportsInvalid");
+  }
+
+  #[tokio::test]
+  async fn bin_not_found() {
+    let t = TestSetup::new().await;
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("bin_not_found")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    let error = task.error.unwrap();
+    assert_eq!(error.code, "configFileBinInvalid");
+  }
+
+  #[tokio::test]
+  async fn invalid_path() {
+    let t = TestSetup::new().await;
+    let task =
+      process_tarball_setup(&t, create_invalid_path_mock_tarball()).await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    let error = task.error.unwrap();
+    assert_eq!(error.code, "invalidPath");
+  }
+
+  #[tokio::test]
+  async fn size_report() {
+    let t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    let report = task.size_report.unwrap();
+    assert_eq!(report.unpacked_size, 229);
+    assert_eq!(report.file_count, 2);
+    assert!(report.npm_tarball_size > 0);
+    assert!(report.previous.is_none());
+
+    let task = process_tarball_setup2(
+      &t,
+      create_mock_tarball("size_increase"),
+      &PackageName::try_from("foo").unwrap(),
+      &Version::try_from("1.2.4").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    let report = task.size_report.unwrap();
+    assert_eq!(report.unpacked_size, 281);
+    assert_eq!(report.file_count, 3);
+    let previous = report.previous.clone().unwrap();
+    assert_eq!(previous.version.to_string(), "1.2.3");
+    assert_eq!(previous.unpacked_size, 229);
+    assert_eq!(previous.file_count, 2);
+
+    let report = ApiPublishingTaskSizeReport::from(report);
+    assert_eq!(report.unpacked_size_delta, Some(52));
+    assert_eq!(report.file_count_delta, Some(1));
+    assert!(report.npm_tarball_size_delta.is_some());
+
+    let version = t
+      .db()
+      .get_package_version(
+        &ScopeName::try_from("scope").unwrap(),
+        &PackageName::try_from("foo").unwrap(),
+        &Version::try_from("1.2.4").unwrap(),
+      )
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(version.unpacked_size, 281);
+    assert_eq!(version.file_count, 3);
+    assert!(version.npm_tarball_size.is_some());
+  }
+
+  #[tokio::test]
+  async fn archived_dependency_warning() {
+    let t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    assert!(task.warnings.0.is_empty());
+
+    let scope = ScopeName::try_from("scope").unwrap();
+    t.db()
+      .update_package_is_archived(
+        &scope,
+        &PackageName::try_from("foo").unwrap(),
+        true,
+      )
+      .await
+      .unwrap();
+
+    let package_name = PackageName::try_from("bar").unwrap();
+    let version = Version::try_from("1.2.3").unwrap();
+    let task = process_tarball_setup2(
+      &t,
+      create_mock_tarball("depends_on_ok"),
+      &package_name,
+      &version,
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    assert_eq!(task.warnings.0.len(), 1, "{task:#?}");
+    assert_eq!(task.warnings.0[0].code, "archivedDependency");
+    assert!(task.warnings.0[0].message.contains("jsr:@scope/foo"));
+
+    let archived = t
+      .db()
+      .list_archived_package_version_dependencies(
+        &scope,
+        &package_name,
+        &version,
+      )
+      .await
+      .unwrap();
+    assert_eq!(archived, vec!["@scope/foo".to_string()]);
+  }
+
+  #[tokio::test]
+  async fn analysis_memory_exceeded() {
+    let t = TestSetup::new().await;
+    let task = process_tarball_setup_with_limits(
+      &t,
+      create_mock_tarball("ok"),
+      &PackageName::try_from("foo").unwrap(),
+      &Version::try_from("1.2.3").unwrap(),
+      false,
+      AnalysisLimits {
+        memory_limit: 1024,
+        ..AnalysisLimits::default()
+      },
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    let error = task.error.unwrap();
+    assert_eq!(error.code, "analysisMemoryExceeded");
+    assert!(error.message.contains("module graph"), "{}", error.message);
+  }
+
+  #[tokio::test]
+  async fn analysis_timeout() {
+    let t = TestSetup::new().await;
+    let task = process_tarball_setup_with_limits(
+      &t,
+      create_mock_tarball("ok"),
+      &PackageName::try_from("foo").unwrap(),
+      &Version::try_from("1.2.3").unwrap(),
+      false,
+      AnalysisLimits {
+        stage_timeout: std::time::Duration::ZERO,
+        ..AnalysisLimits::default()
+      },
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    assert_eq!(task.error.unwrap().code, "analysisTimeout");
+  }
+
+  #[tokio::test]
+  async fn validation_level() {
+    let t = TestSetup::new().await;
+    let scope = ScopeName::try_from("scope").unwrap();
+    let name = PackageName::try_from("foo").unwrap();
+    t.db().create_package(&scope, &name).await.unwrap();
+    t.db()
+      .update_package_validation_level(&scope, &name, ValidationLevel::None)
+      .await
+      .unwrap();
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    let version = t
+      .db()
+      .get_package_version(&scope, &name, &Version::try_from("1.2.3").unwrap())
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(!version.meta.all_fast_check);
+    assert!(version.meta.typescript_features.is_none());
+
+    // The scope policy takes precedence over the package setting.
+    t.db()
+      .scope_set_validation_level(&scope, Some(ValidationLevel::Full))
+      .await
+      .unwrap();
+    let task = process_tarball_setup2(
+      &t,
+      create_mock_tarball("size_increase"),
+      &name,
+      &Version::try_from("1.2.4").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    let version = t
+      .db()
+      .get_package_version(&scope, &name, &Version::try_from("1.2.4").unwrap())
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(version.meta.all_fast_check);
+  }
+
+  #[tokio::test]
+  async fn docs_coverage_policy() {
+    let t = TestSetup::new().await;
+    let scope = ScopeName::try_from("scope").unwrap();
+    let name = PackageName::try_from("foo").unwrap();
+
+    // one of the two exported symbols of the package is documented
+    t.db()
+      .scope_set_docs_coverage_policy(&scope, Some(0.8), false)
+      .await
+      .unwrap();
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:#?}");
+    assert_eq!(task.warnings.0.len(), 1, "{task:#?}");
+    assert_eq!(task.warnings.0[0].code, "docsCoverageBelowMinimum");
+    let version = t
+      .db()
+      .get_package_version(&scope, &name, &Version::try_from("1.2.3").unwrap())
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(version.meta.docs_coverage.get("."), Some(&0.5));
+
+    t.db()
+      .scope_set_docs_coverage_policy(&scope, Some(0.8), true)
+      .await
+      .unwrap();
+    let task = process_tarball_setup2(
+      &t,
+      create_mock_tarball("size_increase"),
+      &name,
+      &Version::try_from("1.2.4").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Failure, "{task:#?}");
+    assert_eq!(task.error.unwrap().code, "docsCoverageBelowMinimum");
+  }
+
+  #[tokio::test]
+  async fn import_assertions() {
+    let t = TestSetup::new().await;
+
+    let bytes = create_mock_tarball("import_assertions");
+    let task = process_tarball_setup2(
       &t,
       bytes,
       &PackageName::try_from("foo").unwrap(),
@@ -1392,4 +2440,85 @@ pub mod tests {
       "application/octet-stream"
     );
   }
+
+  #[tokio::test]
+  async fn npm_manifest_yanked_versions() {
+    let t = TestSetup::new().await;
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success);
+
+    let scope = ScopeName::try_from("scope").unwrap();
+    let name = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.2.3").unwrap();
+    t.db()
+      .yank_package_version(&scope, &name, &version, true, None, None)
+      .await
+      .unwrap();
+
+    let manifest = generate_npm_version_manifest(
+      &t.db(),
+      &t.npm_url(),
+      &scope,
+      &name,
+      false,
+    )
+    .await
+    .unwrap();
+    assert!(manifest.versions.is_empty());
+
+    let manifest =
+      generate_npm_version_manifest(&t.db(), &t.npm_url(), &scope, &name, true)
+        .await
+        .unwrap();
+    let json = serde_json::to_value(&manifest).unwrap();
+    assert_eq!(
+      json["versions"]["1.2.3"]["deprecated"],
+      "This version has been yanked."
+    );
+    assert!(json["dist-tags"].get("latest").is_none());
+  }
+
+  #[tokio::test]
+  async fn concurrent_publishing_tasks() {
+    let t = TestSetup::new().await;
+    let scope = ScopeName::try_from("scope").unwrap();
+    let package = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.2.3").unwrap();
+    let config_file = PackagePath::try_from("/jsr.json").unwrap();
+    t.db().create_package(&scope, &package).await.unwrap();
+
+    let new_task = || NewPublishingTask {
+      user_id: None,
+      github_repository_id: None,
+      github_workflow_run: None,
+      package_scope: &scope,
+      package_name: &package,
+      package_version: &version,
+      config_file: &config_file,
+      bypass_publish_freezes: false,
+      publish_at: None,
+      idempotency_key: None,
+    };
+
+    // Exactly one of the publishes creates a task, and the other one gets
+    // that same task back.
+    let (db1, db2) = (t.db(), t.db());
+    let (res1, res2) = tokio::join!(
+      db1.create_publishing_task(new_task()),
+      db2.create_publishing_task(new_task()),
+    );
+    let (created, other) = match (res1.unwrap(), res2.unwrap()) {
+      (CreatePublishingTaskResult::Created(created), other)
+      | (other, CreatePublishingTaskResult::Created(created)) => {
+        (created, other)
+      }
+      res => panic!("no task was created: {res:?}"),
+    };
+    let (CreatePublishingTaskResult::Raced(other)
+    | CreatePublishingTaskResult::Exists(other)) = other
+    else {
+      panic!("unexpected result: {other:?}");
+    };
+    assert_eq!(created.id, other.id);
+  }
 }
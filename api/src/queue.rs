@@ -0,0 +1,228 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use tracing::error;
+use tracing::warn;
+use tracing::Instrument;
+
+use crate::db::Database;
+use crate::gcp;
+
+/// Processes the body of a queued task. Returning an error makes the queue
+/// retry the task later, if the backend supports retries.
+pub type TaskHandler = Arc<
+  dyn Fn(Bytes) -> BoxFuture<'static, Result<(), anyhow::Error>> + Send + Sync,
+>;
+
+/// A queue of background work, like publishing tasks and npm tarball builds.
+#[async_trait::async_trait]
+pub trait TaskQueue: Send + Sync {
+  async fn enqueue(&self, body: Bytes) -> Result<(), anyhow::Error>;
+}
+
+/// Which service backs the task queues.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QueueBackend {
+  /// Google Cloud Tasks, which delivers tasks to the `/tasks` endpoints.
+  CloudTasks,
+  /// A table in the registry database, processed by workers in the process
+  /// that serves the `/tasks` endpoints.
+  Postgres,
+  /// Tasks are run in the background of the process that enqueued them, and
+  /// are lost if it exits.
+  InProcess,
+}
+
+impl FromStr for QueueBackend {
+  type Err = anyhow::Error;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "cloud_tasks" => Ok(Self::CloudTasks),
+      "postgres" => Ok(Self::Postgres),
+      "in_process" => Ok(Self::InProcess),
+      _ => Err(anyhow::anyhow!("invalid queue backend: {s}")),
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl TaskQueue for gcp::Queue {
+  async fn enqueue(&self, body: Bytes) -> Result<(), anyhow::Error> {
+    self.task_buffer(None, Some(body)).await
+  }
+}
+
+/// Runs tasks on the Tokio runtime of the current process.
+pub struct InProcessQueue {
+  handler: TaskHandler,
+}
+
+impl InProcessQueue {
+  pub fn new(handler: TaskHandler) -> Self {
+    Self { handler }
+  }
+}
+
+#[async_trait::async_trait]
+impl TaskQueue for InProcessQueue {
+  async fn enqueue(&self, body: Bytes) -> Result<(), anyhow::Error> {
+    let fut = (self.handler)(body);
+    tokio::spawn(
+      async move {
+        if let Err(err) = fut.await {
+          error!("in-process task failed: {err:#}");
+        }
+      }
+      .in_current_span(),
+    );
+    Ok(())
+  }
+}
+
+/// How long a worker may process a task before it is handed to another
+/// worker. This is longer than the longest publish.
+const POSTGRES_QUEUE_LEASE: chrono::Duration = chrono::Duration::minutes(30);
+/// How long an idle worker waits before it checks for new tasks.
+const POSTGRES_QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// After this many failed attempts a task is dropped.
+const POSTGRES_QUEUE_MAX_ATTEMPTS: i32 = 10;
+
+/// Stores tasks in the `queued_tasks` table. Workers claim tasks with
+/// `FOR UPDATE SKIP LOCKED`, so any number of them can share a queue.
+#[derive(Clone)]
+pub struct PostgresQueue {
+  db: Database,
+  name: &'static str,
+}
+
+impl PostgresQueue {
+  pub fn new(db: Database, name: &'static str) -> Self {
+    Self { db, name }
+  }
+
+  /// Spawns `concurrency` workers that process the tasks of the queue until
+  /// the process exits.
+  pub fn spawn_workers(&self, handler: TaskHandler, concurrency: usize) {
+    for _ in 0..concurrency {
+      let queue = self.clone();
+      let handler = handler.clone();
+      tokio::spawn(async move {
+        loop {
+          match queue.process_next(&handler).await {
+            Ok(true) => {}
+            Ok(false) => tokio::time::sleep(POSTGRES_QUEUE_POLL_INTERVAL).await,
+            Err(err) => {
+              error!(queue = queue.name, "failed to claim task: {err}");
+              tokio::time::sleep(POSTGRES_QUEUE_POLL_INTERVAL).await
+            }
+          }
+        }
+      });
+    }
+  }
+
+  /// Claims and processes a single task. Returns `false` if the queue had no
+  /// task that is ready to run.
+  pub async fn process_next(
+    &self,
+    handler: &TaskHandler,
+  ) -> Result<bool, anyhow::Error> {
+    let Some(task) = self
+      .db
+      .claim_queued_task(self.name, POSTGRES_QUEUE_LEASE)
+      .await?
+    else {
+      return Ok(false);
+    };
+
+    match handler(Bytes::from(task.body)).await {
+      Ok(()) => self.db.delete_queued_task(task.id).await?,
+      Err(err) if task.attempts >= POSTGRES_QUEUE_MAX_ATTEMPTS => {
+        error!(
+          queue = self.name,
+          task_id = %task.id,
+          attempts = task.attempts,
+          "dropping task after too many failed attempts: {err:#}"
+        );
+        self.db.delete_queued_task(task.id).await?;
+      }
+      Err(err) => {
+        // Back off exponentially, up to about 17 minutes.
+        let backoff =
+          chrono::Duration::seconds(1i64 << task.attempts.clamp(1, 10));
+        warn!(
+          queue = self.name,
+          task_id = %task.id,
+          attempts = task.attempts,
+          "task failed, retrying in {backoff}: {err:#}"
+        );
+        self
+          .db
+          .reschedule_queued_task(task.id, chrono::Utc::now() + backoff)
+          .await?;
+      }
+    }
+    Ok(true)
+  }
+}
+
+#[async_trait::async_trait]
+impl TaskQueue for PostgresQueue {
+  async fn enqueue(&self, body: Bytes) -> Result<(), anyhow::Error> {
+    self.db.enqueue_task(self.name, &body).await?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TestSetup;
+  use std::sync::Mutex;
+
+  #[tokio::test]
+  async fn postgres_queue() {
+    let t = TestSetup::new().await;
+    let queue = PostgresQueue::new(t.db(), "test");
+
+    let processed = Arc::new(Mutex::new(Vec::new()));
+    let handler: TaskHandler = {
+      let processed = processed.clone();
+      Arc::new(move |body: Bytes| {
+        let processed = processed.clone();
+        Box::pin(async move {
+          if body.as_ref() == b"fail" {
+            anyhow::bail!("failed");
+          }
+          processed.lock().unwrap().push(body);
+          Ok(())
+        })
+      })
+    };
+
+    assert!(!queue.process_next(&handler).await.unwrap());
+
+    queue.enqueue(Bytes::from_static(b"one")).await.unwrap();
+    queue.enqueue(Bytes::from_static(b"fail")).await.unwrap();
+    queue.enqueue(Bytes::from_static(b"two")).await.unwrap();
+
+    assert!(queue.process_next(&handler).await.unwrap());
+    assert!(queue.process_next(&handler).await.unwrap());
+    assert!(queue.process_next(&handler).await.unwrap());
+    // The failed task is rescheduled, so it is not ready yet.
+    assert!(!queue.process_next(&handler).await.unwrap());
+
+    assert_eq!(
+      *processed.lock().unwrap(),
+      vec![Bytes::from_static(b"one"), Bytes::from_static(b"two")]
+    );
+
+    // Other queues do not see the task.
+    let other = PostgresQueue::new(t.db(), "other");
+    assert!(!other.process_next(&handler).await.unwrap());
+  }
+}
@@ -0,0 +1,475 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+//! Request quotas for the public API.
+//!
+//! Every request costs a number of units, depending on the endpoint (see
+//! [`RequestCosts`]). Anonymous requests are limited per client IP address.
+//! Requests that carry a public API key (in the `X-JSR-API-Key` header) are
+//! limited per key instead, with a higher limit, and requests that are
+//! authenticated with a token are limited per user.
+//!
+//! Some expensive endpoints are additionally limited per user, counting one
+//! unit per request.
+//!
+//! Units are counted in a sliding one minute window, in memory, so limits apply
+//! per API instance. The window is approximated from the units used in the
+//! current and the previous fixed minute, weighted by how much of the previous
+//! minute still falls into the window.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use hyper::Method;
+use serde::Deserialize;
+use uuid::Uuid;
+
+pub const WINDOW: Duration = Duration::from_secs(60);
+
+/// Once this many clients are tracked, windows that have ended are dropped.
+const MAX_TRACKED_CLIENTS: usize = 100_000;
+
+/// The maximum number of units per minute. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimits {
+  pub anonymous: Option<u32>,
+  pub api_key: Option<u32>,
+  /// Requests authenticated with a token, per user.
+  pub token: Option<u32>,
+  /// README previews rendered per user.
+  pub render_readme: Option<u32>,
+  /// Retries of stuck publishing tasks per user.
+  pub retry_publish: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+  Ip(IpAddr),
+  ApiKey(Uuid),
+  User(Uuid),
+  RenderReadme(Uuid),
+  RetryPublish(Uuid),
+}
+
+/// The cost of requests in quota units, as configured in the request costs
+/// file. Requests that match no rule cost the default cost. For example:
+///
+/// ```json
+/// {
+///   "defaultCost": 1,
+///   "rules": [
+///     { "method": "GET", "path": "/api/packages", "query": "query", "cost": 5 },
+///     { "method": "POST", "path": "/api/scopes/:scope/packages/:package/versions/:version", "cost": 50 }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RequestCosts {
+  #[serde(default = "default_cost")]
+  pub default_cost: u32,
+  #[serde(default)]
+  pub rules: Vec<RequestCostRule>,
+}
+
+fn default_cost() -> u32 {
+  1
+}
+
+impl Default for RequestCosts {
+  fn default() -> Self {
+    Self {
+      default_cost: default_cost(),
+      rules: vec![],
+    }
+  }
+}
+
+/// The first matching rule determines the cost of a request.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RequestCostRule {
+  /// The HTTP method of the request. Matches any method if not set.
+  #[serde(default)]
+  pub method: Option<String>,
+  /// The path of the request. Segments starting with `:` match any segment.
+  pub path: String,
+  /// A query parameter that must be present in the request.
+  #[serde(default)]
+  pub query: Option<String>,
+  pub cost: u32,
+}
+
+impl RequestCostRule {
+  fn matches(&self, method: &Method, path: &str, query: Option<&str>) -> bool {
+    if self
+      .method
+      .as_ref()
+      .is_some_and(|m| !m.eq_ignore_ascii_case(method.as_str()))
+    {
+      return false;
+    }
+    let mut pattern = self.path.trim_end_matches('/').split('/');
+    let mut segments = path.trim_end_matches('/').split('/');
+    loop {
+      match (pattern.next(), segments.next()) {
+        (None, None) => break,
+        (Some(pattern), Some(segment))
+          if pattern == segment
+            || (pattern.starts_with(':') && !segment.is_empty()) => {}
+        _ => return false,
+      }
+    }
+    match &self.query {
+      Some(param) => {
+        url::form_urlencoded::parse(query.unwrap_or("").as_bytes())
+          .any(|(key, value)| key == param.as_str() && !value.is_empty())
+      }
+      None => true,
+    }
+  }
+}
+
+impl RequestCosts {
+  pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+    let file = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&file)?)
+  }
+
+  pub fn cost(&self, method: &Method, path: &str, query: Option<&str>) -> u32 {
+    self
+      .rules
+      .iter()
+      .find(|rule| rule.matches(method, path, query))
+      .map(|rule| rule.cost)
+      .unwrap_or(self.default_cost)
+  }
+}
+
+/// The state of the quota of a client after a request was counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaUsage {
+  pub allowed: bool,
+  pub limit: u32,
+  pub remaining: u32,
+  pub cost: u32,
+  /// The time until the current fixed minute ends, after which units used
+  /// during it start to be released.
+  pub reset_after: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RateLimitWindow {
+  started_at: Instant,
+  used: u32,
+  previous_used: u32,
+}
+
+impl RateLimitWindow {
+  fn advance(&mut self, now: Instant) {
+    let elapsed = now - self.started_at;
+    if elapsed >= WINDOW * 2 {
+      *self = RateLimitWindow {
+        started_at: now,
+        used: 0,
+        previous_used: 0,
+      };
+    } else if elapsed >= WINDOW {
+      *self = RateLimitWindow {
+        started_at: self.started_at + WINDOW,
+        used: 0,
+        previous_used: self.used,
+      };
+    }
+  }
+
+  /// The units used in the sliding window ending now.
+  fn weighted_used(&self, now: Instant) -> u32 {
+    let elapsed = (now - self.started_at).as_secs_f64() / WINDOW.as_secs_f64();
+    let previous = self.previous_used as f64 * (1.0 - elapsed);
+    self.used + previous.ceil() as u32
+  }
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+  limits: RateLimits,
+  windows: Arc<Mutex<HashMap<RateLimitKey, RateLimitWindow>>>,
+}
+
+impl RateLimiter {
+  pub fn new(limits: RateLimits) -> Self {
+    Self {
+      limits,
+      windows: Default::default(),
+    }
+  }
+
+  pub fn limits(&self) -> RateLimits {
+    self.limits
+  }
+
+  /// Counts a request that costs one unit for the client, and returns whether
+  /// it is within the rate limit of the client.
+  pub fn check(&self, key: RateLimitKey) -> bool {
+    self
+      .consume(key, 1)
+      .map(|usage| usage.allowed)
+      .unwrap_or(true)
+  }
+
+  /// Counts a request with the given cost for the client, unless that would
+  /// exceed the quota of the client. Returns `None` if the client is not
+  /// limited.
+  pub fn consume(&self, key: RateLimitKey, cost: u32) -> Option<QuotaUsage> {
+    self.consume_at(key, cost, Instant::now())
+  }
+
+  fn consume_at(
+    &self,
+    key: RateLimitKey,
+    cost: u32,
+    now: Instant,
+  ) -> Option<QuotaUsage> {
+    let limit = match key {
+      RateLimitKey::Ip(_) => self.limits.anonymous,
+      RateLimitKey::ApiKey(_) => self.limits.api_key,
+      RateLimitKey::User(_) => self.limits.token,
+      RateLimitKey::RenderReadme(_) => self.limits.render_readme,
+      RateLimitKey::RetryPublish(_) => self.limits.retry_publish,
+    }?;
+
+    let mut windows = self.windows.lock().unwrap();
+    if windows.len() >= MAX_TRACKED_CLIENTS {
+      windows.retain(|_, window| now - window.started_at < WINDOW * 2);
+    }
+
+    let window = windows.entry(key).or_insert(RateLimitWindow {
+      started_at: now,
+      used: 0,
+      previous_used: 0,
+    });
+    window.advance(now);
+
+    let used = window.weighted_used(now);
+    let allowed = used.saturating_add(cost) <= limit;
+    if allowed {
+      window.used += cost;
+    }
+    Some(QuotaUsage {
+      allowed,
+      limit,
+      remaining: limit.saturating_sub(window.weighted_used(now)),
+      cost,
+      reset_after: WINDOW - (now - window.started_at),
+    })
+  }
+}
+
+/// How long the result of looking up an API key is cached. Deleted keys are
+/// accepted for up to this long by instances other than the one that deleted
+/// them.
+const API_KEY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Caches which API key the secrets sent in `X-JSR-API-Key` belong to, by the
+/// hash of the secret, so that the database is not queried on every request.
+/// Secrets that belong to no key are cached too.
+#[derive(Clone, Default)]
+#[allow(clippy::type_complexity)]
+pub struct ApiKeyCache {
+  entries: Arc<Mutex<HashMap<String, (Option<Uuid>, Instant)>>>,
+}
+
+impl ApiKeyCache {
+  /// Returns the ID of the API key with the given hash, `Some(None)` if there
+  /// is no such key, or `None` if the hash is not cached.
+  pub fn get(&self, hash: &str) -> Option<Option<Uuid>> {
+    self.get_at(hash, Instant::now())
+  }
+
+  fn get_at(&self, hash: &str, now: Instant) -> Option<Option<Uuid>> {
+    let entries = self.entries.lock().unwrap();
+    let (id, cached_at) = entries.get(hash)?;
+    (now - *cached_at < API_KEY_CACHE_TTL).then_some(*id)
+  }
+
+  pub fn insert(&self, hash: String, id: Option<Uuid>) {
+    self.insert_at(hash, id, Instant::now())
+  }
+
+  fn insert_at(&self, hash: String, id: Option<Uuid>, now: Instant) {
+    let mut entries = self.entries.lock().unwrap();
+    if entries.len() >= MAX_TRACKED_CLIENTS {
+      entries.retain(|_, (_, cached_at)| now - *cached_at < API_KEY_CACHE_TTL);
+      if entries.len() >= MAX_TRACKED_CLIENTS {
+        return;
+      }
+    }
+    entries.insert(hash, (id, now));
+  }
+
+  /// Removes a deleted key from the cache of this instance.
+  pub fn invalidate(&self, id: Uuid) {
+    let mut entries = self.entries.lock().unwrap();
+    entries.retain(|_, (cached_id, _)| *cached_id != Some(id));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  impl RateLimiter {
+    fn check_at(&self, key: RateLimitKey, now: Instant) -> bool {
+      self
+        .consume_at(key, 1, now)
+        .map(|usage| usage.allowed)
+        .unwrap_or(true)
+    }
+  }
+
+  #[test]
+  fn limits_per_client() {
+    let limiter = RateLimiter::new(RateLimits {
+      anonymous: Some(2),
+      api_key: Some(3),
+      token: None,
+      render_readme: Some(1),
+      retry_publish: None,
+    });
+    let now = Instant::now();
+    let ip = RateLimitKey::Ip("127.0.0.1".parse().unwrap());
+    let other_ip = RateLimitKey::Ip("127.0.0.2".parse().unwrap());
+    let api_key = RateLimitKey::ApiKey(Uuid::new_v4());
+
+    assert!(limiter.check_at(ip, now));
+    assert!(limiter.check_at(ip, now));
+    assert!(!limiter.check_at(ip, now));
+    assert!(limiter.check_at(other_ip, now));
+
+    assert!(limiter.check_at(api_key, now));
+    assert!(limiter.check_at(api_key, now));
+    assert!(limiter.check_at(api_key, now));
+    assert!(!limiter.check_at(api_key, now));
+
+    // endpoint limits are counted separately from the general limits
+    let user_id = Uuid::new_v4();
+    assert!(limiter.check_at(RateLimitKey::RenderReadme(user_id), now));
+    assert!(!limiter.check_at(RateLimitKey::RenderReadme(user_id), now));
+    assert!(limiter.check_at(RateLimitKey::ApiKey(user_id), now));
+
+    // the window slides, so units are released gradually
+    assert!(!limiter.check_at(ip, now + WINDOW));
+    assert!(limiter.check_at(ip, now + WINDOW + WINDOW / 2));
+    assert!(limiter.check_at(ip, now + WINDOW * 3));
+  }
+
+  #[test]
+  fn unlimited() {
+    let limiter = RateLimiter::new(RateLimits::default());
+    let ip = RateLimitKey::Ip("127.0.0.1".parse().unwrap());
+    for _ in 0..1000 {
+      assert!(limiter.check(ip));
+    }
+    assert_eq!(
+      limiter.consume(RateLimitKey::User(Uuid::new_v4()), 50),
+      None
+    );
+  }
+
+  #[test]
+  fn consume_costs() {
+    let limiter = RateLimiter::new(RateLimits {
+      token: Some(100),
+      ..Default::default()
+    });
+    let now = Instant::now();
+    let user = RateLimitKey::User(Uuid::new_v4());
+
+    let usage = limiter.consume_at(user, 50, now).unwrap();
+    assert!(usage.allowed);
+    assert_eq!(usage.remaining, 50);
+    assert_eq!(usage.reset_after, WINDOW);
+    assert!(limiter.consume_at(user, 50, now).unwrap().allowed);
+    // rejected requests do not use up the quota
+    let usage = limiter.consume_at(user, 5, now).unwrap();
+    assert!(!usage.allowed);
+    assert_eq!(usage.remaining, 0);
+    assert!(!limiter.consume_at(user, 1, now).unwrap().allowed);
+  }
+
+  #[test]
+  fn api_key_cache() {
+    let cache = ApiKeyCache::default();
+    let now = Instant::now();
+    let id = Uuid::new_v4();
+
+    assert_eq!(cache.get_at("a", now), None);
+    cache.insert_at("a".to_owned(), Some(id), now);
+    cache.insert_at("b".to_owned(), None, now);
+    assert_eq!(cache.get_at("a", now), Some(Some(id)));
+    assert_eq!(cache.get_at("b", now), Some(None));
+
+    // entries expire
+    assert_eq!(cache.get_at("a", now + API_KEY_CACHE_TTL), None);
+
+    cache.invalidate(id);
+    assert_eq!(cache.get_at("a", now), None);
+    assert_eq!(cache.get_at("b", now), Some(None));
+  }
+
+  #[test]
+  fn request_costs() {
+    let costs: RequestCosts = serde_json::from_value(serde_json::json!({
+      "defaultCost": 1,
+      "rules": [
+        { "method": "GET", "path": "/api/packages", "query": "query", "cost": 5 },
+        {
+          "method": "POST",
+          "path": "/api/scopes/:scope/packages/:package/versions/:version",
+          "cost": 50
+        },
+        { "path": "/api/scopes/:scope/packages/:package/versions", "cost": 2 }
+      ]
+    }))
+    .unwrap();
+
+    assert_eq!(
+      costs.cost(&Method::GET, "/api/packages", Some("query=foo")),
+      5
+    );
+    assert_eq!(costs.cost(&Method::GET, "/api/packages", Some("query=")), 1);
+    assert_eq!(costs.cost(&Method::GET, "/api/packages", None), 1);
+    assert_eq!(
+      costs.cost(
+        &Method::POST,
+        "/api/scopes/a/packages/b/versions/1.0.0",
+        None
+      ),
+      50
+    );
+    assert_eq!(
+      costs.cost(
+        &Method::GET,
+        "/api/scopes/a/packages/b/versions/1.0.0",
+        None
+      ),
+      1
+    );
+    assert_eq!(
+      costs.cost(&Method::GET, "/api/scopes/a/packages/b/versions/", None),
+      2
+    );
+    assert_eq!(
+      costs.cost(&Method::GET, "/api/scopes//packages/b/versions", None),
+      1
+    );
+    assert_eq!(
+      RequestCosts::default().cost(&Method::GET, "/api/packages", None),
+      1
+    );
+  }
+}
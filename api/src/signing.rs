@@ -0,0 +1,461 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+//! Detached signatures for the package and version metadata files that are
+//! written to the modules bucket.
+//!
+//! Every `meta.json` and `<version>_meta.json` is accompanied by a
+//! `<path>.sig` file, that contains ed25519 signatures over the exact bytes of
+//! the metadata file. The shape of the signature file and of the published
+//! keys follows the conventions of The Update Framework (TUF), so that clients
+//! can verify that metadata was not tampered with between the registry and
+//! them, for example by a CDN or a proxy.
+//!
+//! Keys are rotated by configuring a new signing key, and moving the public
+//! key of the previous signing key to the list of verification keys. All of
+//! these keys are published at [`METADATA_KEYS_PATH`], so that signatures made
+//! with the previous key can still be verified.
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::DecodingKey;
+use jsonwebtoken::EncodingKey;
+use routerify::ext::RequestExt;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::api::ApiError;
+use crate::buckets::Buckets;
+use crate::buckets::UploadTaskBody;
+use crate::gcp::GcsError;
+use crate::gcp::GcsUploadOptions;
+
+/// The path that the metadata verification keys are served at.
+pub const METADATA_KEYS_PATH: &str = "/.well-known/jsr-metadata-keys.json";
+
+/// A public key that metadata signatures can be verified with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataVerificationKey {
+  pub key_id: String,
+  /// The raw 32 byte ed25519 public key.
+  pub public_key: Vec<u8>,
+}
+
+impl FromStr for MetadataVerificationKey {
+  type Err = anyhow::Error;
+
+  /// Parses a key in the form `<key id>:<base64 public key>`.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (key_id, public_key) = s.trim().split_once(':').ok_or_else(|| {
+      anyhow::anyhow!(
+        "Invalid verification key, expected '<key id>:<public key>'"
+      )
+    })?;
+    Ok(Self {
+      key_id: parse_key_id(key_id)?,
+      public_key: parse_public_key(public_key)?,
+    })
+  }
+}
+
+/// The key that metadata files are currently signed with.
+#[derive(Clone)]
+pub struct MetadataSigningKey {
+  key_id: String,
+  encoding_key: Arc<EncodingKey>,
+  public_key: Vec<u8>,
+}
+
+impl std::fmt::Debug for MetadataSigningKey {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MetadataSigningKey")
+      .field("key_id", &self.key_id)
+      .field("encoding_key", &"***")
+      .finish()
+  }
+}
+
+impl FromStr for MetadataSigningKey {
+  type Err = anyhow::Error;
+
+  /// Parses a key in the form `<key id>:<base64 PKCS#8 private key>:<base64
+  /// public key>`. The public key is checked against the private key, so that
+  /// a misconfigured key is caught at startup.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut parts = s.trim().split(':');
+    let (Some(key_id), Some(private_key), Some(public_key), None) =
+      (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+      anyhow::bail!(
+        "Invalid signing key, expected '<key id>:<private key>:<public key>'"
+      );
+    };
+    let private_key = BASE64_STANDARD
+      .decode(private_key)
+      .map_err(|err| anyhow::anyhow!("Invalid signing key: {err}"))?;
+    let key = Self {
+      key_id: parse_key_id(key_id)?,
+      encoding_key: Arc::new(EncodingKey::from_ed_der(&private_key)),
+      public_key: parse_public_key(public_key)?,
+    };
+
+    let message = b"jsr metadata signing key check";
+    let signature = key
+      .sign(message)
+      .map_err(|err| anyhow::anyhow!("Invalid signing key: {err}"))?;
+    if !verify(&key.public_key, message, &signature) {
+      anyhow::bail!(
+        "Invalid signing key '{}', the public key does not match the private key",
+        key.key_id
+      );
+    }
+    Ok(key)
+  }
+}
+
+impl MetadataSigningKey {
+  /// Signs the message, and returns the hex encoded signature.
+  fn sign(
+    &self,
+    message: &[u8],
+  ) -> Result<String, jsonwebtoken::errors::Error> {
+    let signature = jsonwebtoken::crypto::sign(
+      message,
+      &self.encoding_key,
+      Algorithm::EdDSA,
+    )?;
+    let signature = BASE64_URL_SAFE_NO_PAD.decode(signature).unwrap();
+    Ok(to_hex(&signature))
+  }
+}
+
+fn parse_key_id(key_id: &str) -> Result<String, anyhow::Error> {
+  if key_id.is_empty()
+    || !key_id
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+  {
+    anyhow::bail!("Invalid key id '{key_id}'");
+  }
+  Ok(key_id.to_owned())
+}
+
+fn parse_public_key(public_key: &str) -> Result<Vec<u8>, anyhow::Error> {
+  let public_key = BASE64_STANDARD
+    .decode(public_key)
+    .map_err(|err| anyhow::anyhow!("Invalid public key: {err}"))?;
+  if public_key.len() != 32 {
+    anyhow::bail!("Invalid public key, expected 32 bytes");
+  }
+  Ok(public_key)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().fold(String::new(), |mut s, b| {
+    let _ = write!(s, "{b:02x}");
+    s
+  })
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+  if s.len() % 2 != 0 {
+    return None;
+  }
+  (0..s.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+    .collect()
+}
+
+/// Verifies a hex encoded ed25519 signature over the message.
+pub fn verify(public_key: &[u8], message: &[u8], signature: &str) -> bool {
+  let Some(signature) = from_hex(signature) else {
+    return false;
+  };
+  let signature = BASE64_URL_SAFE_NO_PAD.encode(signature);
+  jsonwebtoken::crypto::verify(
+    &signature,
+    message,
+    &DecodingKey::from_ed_der(public_key),
+    Algorithm::EdDSA,
+  )
+  .unwrap_or(false)
+}
+
+/// The contents of a `<path>.sig` file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataSignatures {
+  pub signatures: Vec<MetadataSignature>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataSignature {
+  pub keyid: String,
+  /// The hex encoded ed25519 signature.
+  pub sig: String,
+}
+
+/// The keys that are published at [`METADATA_KEYS_PATH`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataKeys {
+  pub keys: Vec<MetadataKey>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataKey {
+  pub keyid: String,
+  pub keytype: String,
+  pub scheme: String,
+  pub keyval: MetadataKeyValue,
+  /// Whether new metadata is signed with this key.
+  pub active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataKeyValue {
+  /// The hex encoded ed25519 public key.
+  pub public: String,
+}
+
+/// Signs metadata files with the active signing key. If no signing key is
+/// configured, metadata files are not signed.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataSigner {
+  signing_key: Option<MetadataSigningKey>,
+  verification_keys: Vec<MetadataVerificationKey>,
+}
+
+impl MetadataSigner {
+  pub fn new(
+    signing_key: Option<MetadataSigningKey>,
+    verification_keys: Vec<MetadataVerificationKey>,
+  ) -> Self {
+    Self {
+      signing_key,
+      verification_keys,
+    }
+  }
+
+  pub fn sign(&self, content: &[u8]) -> Option<MetadataSignatures> {
+    let signing_key = self.signing_key.as_ref()?;
+    // The key was checked to be able to sign when it was parsed.
+    let sig = signing_key.sign(content).unwrap();
+    Some(MetadataSignatures {
+      signatures: vec![MetadataSignature {
+        keyid: signing_key.key_id.clone(),
+        sig,
+      }],
+    })
+  }
+
+  /// Whether any of the keys of this signer is also a key of `other`.
+  pub fn shares_keys_with(&self, other: &MetadataSigner) -> bool {
+    let other_keys = other.keys().keys;
+    self.keys().keys.iter().any(|key| {
+      other_keys
+        .iter()
+        .any(|other| other.keyval.public == key.keyval.public)
+    })
+  }
+
+  pub fn keys(&self) -> MetadataKeys {
+    let active = self
+      .signing_key
+      .as_ref()
+      .map(|key| MetadataVerificationKey {
+        key_id: key.key_id.clone(),
+        public_key: key.public_key.clone(),
+      });
+    let mut keys: Vec<MetadataKey> = vec![];
+    for (key, active) in active
+      .iter()
+      .map(|key| (key, true))
+      .chain(self.verification_keys.iter().map(|key| (key, false)))
+    {
+      if keys.iter().any(|k| k.keyid == key.key_id) {
+        continue;
+      }
+      keys.push(MetadataKey {
+        keyid: key.key_id.clone(),
+        keytype: "ed25519".to_owned(),
+        scheme: "ed25519".to_owned(),
+        keyval: MetadataKeyValue {
+          public: to_hex(&key.public_key),
+        },
+        active,
+      });
+    }
+    MetadataKeys { keys }
+  }
+}
+
+/// Uploads the signatures of a metadata file next to it, at `<path>.sig`.
+pub async fn upload_metadata_signature(
+  buckets: &Buckets,
+  signer: &MetadataSigner,
+  path: &str,
+  content: &[u8],
+  cache_control: &'static str,
+) -> Result<(), GcsError> {
+  let Some(signatures) = signer.sign(content) else {
+    return Ok(());
+  };
+  let content = serde_json::to_vec_pretty(&signatures).unwrap();
+  buckets
+    .modules_bucket
+    .upload(
+      crate::gcs_paths::metadata_signature(path).into(),
+      UploadTaskBody::Bytes(content.into()),
+      GcsUploadOptions {
+        content_type: Some("application/json".into()),
+        cache_control: Some(cache_control.into()),
+        gzip_encoded: false,
+      },
+    )
+    .await
+}
+
+pub async fn metadata_keys_handler(
+  req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+  let signer = req.data::<MetadataSigner>().unwrap();
+  let body = serde_json::to_vec_pretty(&signer.keys()).unwrap();
+
+  let response = Response::builder()
+    .header("Content-Type", "application/json")
+    .header("Cache-Control", "public, max-age=300")
+    .header("Access-Control-Allow-Origin", "*")
+    .body(Body::from(body))
+    .unwrap();
+
+  Ok(response)
+}
+
+#[cfg(test)]
+pub mod tests {
+  use hyper::StatusCode;
+
+  use super::verify;
+  use super::MetadataKeys;
+  use super::MetadataSigner;
+  use super::MetadataSigningKey;
+  use super::MetadataVerificationKey;
+  use super::METADATA_KEYS_PATH;
+  use crate::testing::TestSetup;
+  use crate::testing::TEST_SIGNING_KEY;
+
+  const TEST_PUBLIC_KEY: &str =
+    "6e9dfb0a281c74920828bf5c8fdd5734651aec36cac7fb720851f10d2c47659a";
+
+  #[test]
+  fn sign_and_verify() {
+    let signing_key: MetadataSigningKey = TEST_SIGNING_KEY.parse().unwrap();
+    let signer = MetadataSigner::new(Some(signing_key), vec![]);
+
+    let content = br#"{"latest":"1.0.0"}"#;
+    let signatures = signer.sign(content).unwrap();
+    assert_eq!(signatures.signatures.len(), 1);
+    let signature = &signatures.signatures[0];
+    assert_eq!(signature.keyid, "test-key");
+    // ed25519 signatures are deterministic.
+    assert_eq!(
+      signature.sig,
+      "3559ac4313124dfec9ace8c1d9832389d8eff71cd1915e5ab280cd880936beef69571674\
+       06ef88a0f628c9dca0a0507fa2a6ba4f570877f893d57b8a6ec92706"
+    );
+
+    let public_key = super::from_hex(TEST_PUBLIC_KEY).unwrap();
+    assert!(verify(&public_key, content, &signature.sig));
+    assert!(!verify(
+      &public_key,
+      br#"{"latest":"2.0.0"}"#,
+      &signature.sig
+    ));
+    assert!(!verify(&public_key, content, "not hex"));
+
+    assert!(MetadataSigner::default().sign(content).is_none());
+  }
+
+  #[test]
+  fn parse_keys() {
+    assert!("test-key:MC4CAQAwBQYDK2VwBCIEIEwIjIzi8+fSGolja7leI02Z2S9c96ePvfDj6XrnFBsO"
+      .parse::<MetadataSigningKey>()
+      .is_err());
+    // The public key does not belong to the private key.
+    assert!("test-key:MC4CAQAwBQYDK2VwBCIEIEwIjIzi8+fSGolja7leI02Z2S9c96ePvfDj6XrnFBsO:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+      .parse::<MetadataSigningKey>()
+      .is_err());
+
+    let key: MetadataVerificationKey =
+      "old-key:bp37CigcdJIIKL9cj91XNGUa7DbKx/tyCFHxDSxHZZo="
+        .parse()
+        .unwrap();
+    assert_eq!(key.key_id, "old-key");
+    assert_eq!(key.public_key.len(), 32);
+    assert!("old key:bp37CigcdJIIKL9cj91XNGUa7DbKx/tyCFHxDSxHZZo="
+      .parse::<MetadataVerificationKey>()
+      .is_err());
+    assert!("old-key:AAAA".parse::<MetadataVerificationKey>().is_err());
+  }
+
+  #[tokio::test]
+  async fn metadata_keys() {
+    let mut t = TestSetup::new().await;
+    let mut resp = t
+      .unauthed_http()
+      .get(METADATA_KEYS_PATH)
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(resp.body_mut()).await.unwrap();
+    let keys: MetadataKeys = serde_json::from_slice(&body).unwrap();
+    assert_eq!(keys.keys.len(), 1);
+    assert_eq!(keys.keys[0].keyid, "test-key");
+    assert_eq!(keys.keys[0].keyval.public, TEST_PUBLIC_KEY);
+    assert!(keys.keys[0].active);
+
+    let signer = MetadataSigner::new(
+      Some(TEST_SIGNING_KEY.parse().unwrap()),
+      vec![
+        "old-key:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+          .parse()
+          .unwrap(),
+        "test-key:bp37CigcdJIIKL9cj91XNGUa7DbKx/tyCFHxDSxHZZo="
+          .parse()
+          .unwrap(),
+      ],
+    );
+    let keys = signer.keys();
+    assert_eq!(
+      keys
+        .keys
+        .iter()
+        .map(|key| (key.keyid.as_str(), key.active))
+        .collect::<Vec<_>>(),
+      vec![("test-key", true), ("old-key", false)]
+    );
+  }
+
+  #[test]
+  fn shares_keys_with() {
+    let signer =
+      MetadataSigner::new(Some(TEST_SIGNING_KEY.parse().unwrap()), vec![]);
+    let verifier = MetadataSigner::new(
+      None,
+      vec!["other-id:bp37CigcdJIIKL9cj91XNGUa7DbKx/tyCFHxDSxHZZo="
+        .parse()
+        .unwrap()],
+    );
+    assert!(signer.shares_keys_with(&verifier));
+    assert!(verifier.shares_keys_with(&signer));
+    assert!(!signer.shares_keys_with(&MetadataSigner::default()));
+    assert!(!MetadataSigner::default().shares_keys_with(&signer));
+  }
+}
@@ -14,6 +14,48 @@ use crate::api::ApiError;
 use crate::db::Database;
 use crate::RegistryUrl;
 
+/// Controls which parts of the registry crawlers are asked not to visit.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsConfig {
+  /// Path prefixes that no crawler may visit.
+  pub disallow: Vec<String>,
+  /// User agents that may not crawl the registry at all.
+  pub blocked_user_agents: Vec<String>,
+}
+
+impl RobotsConfig {
+  fn render(&self, registry_url: &url::Url) -> String {
+    let mut out = String::new();
+    for user_agent in &self.blocked_user_agents {
+      out.push_str(&format!("User-agent: {user_agent}\nDisallow: /\n\n"));
+    }
+    out.push_str("User-agent: *\n");
+    if self.disallow.is_empty() {
+      out.push_str("Disallow:\n");
+    }
+    for path in &self.disallow {
+      out.push_str(&format!("Disallow: {path}\n"));
+    }
+    out.push_str(&format!("\nSitemap: {registry_url}sitemap.xml\n"));
+    out
+  }
+}
+
+pub async fn robots_txt_handler(
+  req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+  let registry_url = &req.data::<RegistryUrl>().unwrap().0;
+  let robots = req.data::<RobotsConfig>().unwrap();
+
+  let response = Response::builder()
+    .header("Content-Type", "text/plain; charset=utf-8")
+    .header("Cache-Control", "public, max-age=3600")
+    .body(Body::from(robots.render(registry_url)))
+    .unwrap();
+
+  Ok(response)
+}
+
 #[allow(deprecated)] // the replacement can not be used in const positions in stable
 const TWO_DAYS: chrono::Duration = chrono::Duration::days(2);
 #[allow(deprecated)] // the replacement can not be used in const positions in stable
@@ -152,3 +194,100 @@ pub async fn packages_sitemap_handler(
 
   Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+  use hyper::StatusCode;
+
+  use super::RobotsConfig;
+  use crate::db::ExportsMap;
+  use crate::db::NewPackageVersion;
+  use crate::ids::PackageName;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
+
+  #[test]
+  fn render_robots_txt() {
+    let registry_url = "https://jsr.io/".parse().unwrap();
+    assert_eq!(
+      RobotsConfig::default().render(&registry_url),
+      "User-agent: *\nDisallow:\n\nSitemap: https://jsr.io/sitemap.xml\n"
+    );
+
+    let robots = RobotsConfig {
+      disallow: vec!["/api/".to_string(), "/login".to_string()],
+      blocked_user_agents: vec!["BadBot".to_string()],
+    };
+    assert_eq!(
+      robots.render(&registry_url),
+      "User-agent: BadBot\nDisallow: /\n\nUser-agent: *\nDisallow: /api/\nDisallow: /login\n\nSitemap: https://jsr.io/sitemap.xml\n"
+    );
+  }
+
+  #[tokio::test]
+  async fn packages_sitemap_excludes_noindex() {
+    let mut t = TestSetup::new().await;
+    let scope = t.scope.scope.clone();
+
+    for name in ["foo", "bar"] {
+      let name = PackageName::try_from(name).unwrap();
+      t.ephemeral_database
+        .create_package(&scope, &name)
+        .await
+        .unwrap();
+      t.ephemeral_database
+        .create_package_version_for_test(NewPackageVersion {
+          scope: &scope,
+          name: &name,
+          version: &"1.0.0".try_into().unwrap(),
+          user_id: None,
+          readme_path: None,
+          uses_npm: false,
+          exports: &ExportsMap::mock(),
+          bin: &Default::default(),
+          engines: &Default::default(),
+          keywords: &[],
+          license: None,
+          meta: Default::default(),
+        })
+        .await
+        .unwrap();
+    }
+
+    // Only staff can change whether a package is indexed.
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/bar")
+      .body_json(serde_json::json!({ "noindex": true }))
+      .call()
+      .await
+      .unwrap();
+    resp
+      .expect_err_code(StatusCode::FORBIDDEN, "actorNotAuthorized")
+      .await;
+
+    let staff_token = t.staff_user.token.to_string();
+    let mut resp = t
+      .http()
+      .patch("/api/scopes/scope/packages/bar")
+      .body_json(serde_json::json!({ "noindex": true }))
+      .token(Some(&staff_token))
+      .call()
+      .await
+      .unwrap();
+    let package: crate::api::ApiPackage = resp.expect_ok().await;
+    assert!(package.noindex);
+
+    let mut resp = t
+      .unauthed_http()
+      .get("/sitemap-packages.xml")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = hyper::body::to_bytes(resp.body_mut()).await.unwrap();
+    let body = std::str::from_utf8(&bytes).unwrap();
+    assert!(body.contains("/@scope/foo<"));
+    assert!(!body.contains("/@scope/bar<"));
+  }
+}
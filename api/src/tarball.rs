@@ -2,7 +2,9 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::io;
+use std::sync::Arc;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 use async_tar::EntryType;
 use bytes::Bytes;
@@ -28,13 +30,25 @@ use url::Url;
 use uuid::Uuid;
 
 use crate::analysis::analyze_package;
+use crate::analysis::AnalysisBudget;
+use crate::analysis::AnalysisLimits;
+use crate::analysis::AnalysisStage;
 use crate::analysis::PackageAnalysisData;
 use crate::analysis::PackageAnalysisOutput;
 use crate::buckets::Buckets;
 use crate::buckets::UploadTaskBody;
+use crate::db::BinMap;
 use crate::db::Database;
+use crate::db::DependencyType;
 use crate::db::ExportsMap;
+use crate::db::PackageVersionEngines;
+use crate::db::PackageVersionPermissions;
+use crate::db::PermissionTargets;
+use crate::db::PublishedSpecifier;
 use crate::db::PublishingTask;
+use crate::db::PublishingTaskErrorFile;
+use crate::db::PublishingTaskWarning;
+use crate::db::ValidationLevel;
 use crate::db::{DependencyKind, PackageVersionMeta};
 use crate::gcp::GcsError;
 use crate::gcp::GcsUploadOptions;
@@ -43,6 +57,7 @@ use crate::gcs_paths::docs_v1_path;
 use crate::gcs_paths::file_path;
 use crate::gcs_paths::npm_tarball_path;
 use crate::ids::CaseInsensitivePackagePath;
+use crate::ids::PackageName;
 use crate::ids::PackagePath;
 use crate::ids::PackagePathValidationError;
 use crate::ids::ScopedPackageName;
@@ -55,6 +70,7 @@ const MAX_TOTAL_FILE_SIZE: u64 = 20 * 1024 * 1024; // 20 MB
 const HIGH_MAX_FILE_SIZE: u64 = 20 * 1024 * 1024; // 40 MB
 const HIGH_MAX_TOTAL_FILE_SIZE: u64 = 20 * 1024 * 1024; // 40 MB
 const MAX_CONCURRENT_UPLOADS: usize = 1024;
+const MAX_CONCURRENT_HASHES: usize = 16;
 
 static MEDIA_INFER: OnceLock<infer::Infer> = OnceLock::new();
 
@@ -62,11 +78,18 @@ pub struct ProcessTarballOutput {
   pub file_infos: Vec<FileInfo>,
   pub module_graph_2: HashMap<String, deno_graph::ModuleInfo>,
   pub exports: ExportsMap,
-  pub dependencies: HashSet<(DependencyKind, PackageReqReference)>,
+  pub bin: BinMap,
+  pub engines: PackageVersionEngines,
+  pub keywords: Vec<String>,
+  pub license: Option<String>,
+  pub dependencies:
+    HashSet<(DependencyKind, DependencyType, PackageReqReference)>,
   pub npm_tarball_info: NpmTarballInfo,
   pub readme_path: Option<PackagePath>,
   pub meta: PackageVersionMeta,
   pub doc_search_json: serde_json::Value,
+  pub warnings: Vec<PublishingTaskWarning>,
+  pub specifiers: Vec<PublishedSpecifier>,
 }
 
 pub struct NpmTarballInfo {
@@ -87,6 +110,7 @@ pub async fn process_tarball(
   db: &Database,
   buckets: &Buckets,
   registry_url: Url,
+  analysis_limits: AnalysisLimits,
   publishing_task: &PublishingTask,
 ) -> Result<ProcessTarballOutput, PublishError> {
   let tarball_path = gcs_tarball_path(publishing_task.id);
@@ -104,6 +128,7 @@ pub async fn process_tarball(
     .entries()
     .map_err(PublishError::UntarError)?;
 
+  let mut entries = Vec::new();
   let mut files = HashMap::new();
   let mut case_insensitive_paths = HashSet::<CaseInsensitivePackagePath>::new();
   let mut file_infos = Vec::new();
@@ -182,10 +207,6 @@ pub async fn process_tarball(
       .await
       .map_err(PublishError::UntarError)?;
 
-    // sha256 hash the bytes
-    let hash = sha2::Sha256::digest(&bytes);
-    let hash = format!("sha256-{:x}", hash);
-
     // check for case-insensitive duplicate paths
     let case_insensitive_path = path.case_insensitive();
     if let Some(existing) = case_insensitive_paths.get(&case_insensitive_path) {
@@ -196,12 +217,15 @@ pub async fn process_tarball(
     }
     case_insensitive_paths.insert(case_insensitive_path.to_owned());
 
+    entries.push((path, bytes));
+  }
+
+  for (path, bytes, hash) in hash_files(entries).await {
+    let size = bytes.len() as u64;
     if files.insert(path.clone(), bytes).is_some() {
       unreachable!("duplicate path: {:?}", path);
     }
-
-    let file_info = FileInfo { path, hash, size };
-    file_infos.push(file_info);
+    file_infos.push(FileInfo { path, hash, size });
   }
 
   let config_file_bytes =
@@ -273,37 +297,137 @@ pub async fn process_tarball(
     });
   }
 
+  let bin = bin_map_from_json(config_file.bin, &publishing_task.package_name)
+    .map_err(|invalid_bin| PublishError::ConfigFileBinInvalid {
+    path: Box::new(publishing_task.config_file.clone()),
+    invalid_bin,
+  })?;
+
+  let engines =
+    engines_from_json(config_file.engines).map_err(|invalid_engines| {
+      PublishError::ConfigFileEnginesInvalid {
+        path: Box::new(publishing_task.config_file.clone()),
+        invalid_engines,
+      }
+    })?;
+
+  let keywords =
+    keywords_from_json(config_file.keywords).map_err(|invalid_keywords| {
+      PublishError::ConfigFileKeywordsInvalid {
+        path: Box::new(publishing_task.config_file.clone()),
+        invalid_keywords,
+      }
+    })?;
+
+  let permissions = permissions_from_json(config_file.permissions).map_err(
+    |invalid_permissions| PublishError::ConfigFilePermissionsInvalid {
+      path: Box::new(publishing_task.config_file.clone()),
+      invalid_permissions,
+    },
+  )?;
+
+  let license =
+    crate::license::detect_license(config_file.license.as_ref(), &files);
+
+  let dependency_types = dependency_types_from_json(
+    config_file.peer_dependencies,
+    config_file.optional_dependencies,
+  )
+  .map_err(|invalid_dependencies| {
+    PublishError::ConfigFileDependenciesInvalid {
+      path: Box::new(publishing_task.config_file.clone()),
+      invalid_dependencies,
+    }
+  })?;
+
+  let package = db
+    .get_package(
+      &publishing_task.package_scope,
+      &publishing_task.package_name,
+    )
+    .await?
+    .map(|(package, _, _)| package);
+  let runtime_compat = package
+    .as_ref()
+    .map(|package| package.runtime_compat.clone())
+    .unwrap_or_default();
+  let scope_info = db.get_scope(&publishing_task.package_scope).await?;
+  let validation_level = match (&scope_info, &package) {
+    (Some(scope), Some(package)) => ValidationLevel::effective(scope, package),
+    _ => ValidationLevel::default(),
+  };
+
   let span = Span::current();
   let scope = publishing_task.package_scope.clone();
   let package = publishing_task.package_name.clone();
   let version = publishing_task.package_version.clone();
   let config_file = publishing_task.config_file.clone();
-  let analysis_data = PackageAnalysisData { exports, files };
+  let analysis_data = PackageAnalysisData {
+    exports,
+    bin,
+    engines,
+    runtime_compat,
+    validation_level,
+    dependency_types,
+    files,
+  };
   let PackageAnalysisOutput {
-    data: PackageAnalysisData { exports, files },
+    data:
+      PackageAnalysisData {
+        exports,
+        bin,
+        engines,
+        runtime_compat: _,
+        validation_level: _,
+        dependency_types: _,
+        files,
+      },
     module_graph_2,
     doc_nodes_json,
     doc_search_json,
     dependencies,
     npm_tarball,
     readme_path,
-    meta,
-  } = tokio::task::spawn_blocking(|| {
-    analyze_package(
-      span,
-      registry_url,
-      scope,
-      package,
-      version,
-      config_file,
-      analysis_data,
-    )
-  })
-  .await
-  .unwrap()?;
+    mut meta,
+    specifiers,
+  } = {
+    let budget = Arc::new(AnalysisBudget::new(analysis_limits));
+    let analysis = tokio::task::spawn_blocking({
+      let budget = budget.clone();
+      move || {
+        analyze_package(
+          span,
+          registry_url,
+          scope,
+          package,
+          version,
+          config_file,
+          analysis_data,
+          budget,
+        )
+      }
+    });
+    // The analysis thread can not be cancelled, so if it does not finish in
+    // time it is left to run to completion in the background.
+    match tokio::time::timeout(analysis_limits.total_timeout(), analysis).await
+    {
+      Ok(res) => res.unwrap()?,
+      Err(_) => {
+        return Err(PublishError::AnalysisTimeout {
+          stage: budget.stage(),
+          timeout: budget.limits().stage_timeout,
+        })
+      }
+    }
+  };
+
+  meta.permissions = permissions;
 
-  // ensure all of the JSR dependencies are resolvable
-  for (kind, req) in dependencies.iter() {
+  // ensure all of the JSR dependencies are resolvable, and warn about the ones
+  // that have been archived
+  let mut warnings = Vec::new();
+  let mut checked_archived = HashSet::new();
+  for (kind, _, req) in dependencies.iter() {
     if kind == &DependencyKind::Jsr {
       let package_scope = ScopedPackageName::new(req.req.name.to_string())
         .map_err(|e| {
@@ -347,29 +471,68 @@ pub async fn process_tarball(
       if !found {
         return Err(PublishError::UnresolvableJsrDependency(req.req.clone()));
       }
+
+      if checked_archived.insert(package_scope.clone()) {
+        let is_archived = db
+          .get_package(&package_scope.scope, &package_scope.package)
+          .await?
+          .is_some_and(|(package, _, _)| package.is_archived);
+        if is_archived {
+          warnings.push(PublishingTaskWarning {
+            code: "archivedDependency".to_owned(),
+            message: format!(
+              "the dependency jsr:{} is archived and no longer maintained, consider replacing it",
+              package_scope
+            ),
+          });
+        }
+      }
+    }
+  }
+
+  // check the docs coverage of each entrypoint against the policy of the
+  // scope, which fails the publish only if the scope enforces it
+  if let Some(scope) = &scope_info {
+    if let Some(min_coverage) = scope.min_docs_coverage {
+      for (export, coverage) in &meta.docs_coverage {
+        if *coverage >= min_coverage {
+          continue;
+        }
+        if scope.enforce_min_docs_coverage {
+          return Err(PublishError::DocsCoverageBelowMinimum {
+            export: export.clone(),
+            coverage: *coverage,
+            min_coverage,
+          });
+        }
+        warnings.push(PublishingTaskWarning {
+          code: "docsCoverageBelowMinimum".to_owned(),
+          message: format!(
+            "only {:.0}% of the exported symbols of export '{export}' are documented, the scope requires {:.0}%",
+            coverage * 100.0,
+            min_coverage * 100.0
+          ),
+        });
+      }
     }
   }
 
   // TO ENSURE CONSISTENCY OF FILES IN GCS, ALL ERRORS RETURNED AFTER THIS POINT MUST BE RETRYABLE
 
-  buckets
-    .docs_bucket
-    .upload(
-      docs_v1_path(
-        &publishing_task.package_scope,
-        &publishing_task.package_name,
-        &publishing_task.package_version,
-      )
-      .into(),
-      UploadTaskBody::Bytes(doc_nodes_json),
-      GcsUploadOptions {
-        content_type: Some("application/json".into()),
-        cache_control: Some(CACHE_CONTROL_IMMUTABLE.into()),
-        gzip_encoded: false,
-      },
+  let docs_upload = buckets.docs_bucket.upload(
+    docs_v1_path(
+      &publishing_task.package_scope,
+      &publishing_task.package_name,
+      &publishing_task.package_version,
     )
-    .await
-    .map_err(PublishError::GcsUploadError)?;
+    .into(),
+    UploadTaskBody::Bytes(doc_nodes_json),
+    GcsUploadOptions {
+      content_type: Some("application/json".into()),
+      cache_control: Some(CACHE_CONTROL_IMMUTABLE.into()),
+      gzip_encoded: false,
+    },
+  );
 
   let npm_tarball_info = NpmTarballInfo {
     sha1: npm_tarball.sha1,
@@ -383,44 +546,20 @@ pub async fn process_tarball(
     &publishing_task.package_version,
     NPM_TARBALL_REVISION,
   );
-  buckets
-    .npm_bucket
-    .upload(
-      npm_tarball_path.into(),
-      UploadTaskBody::Bytes(Bytes::from(npm_tarball.tarball)),
-      GcsUploadOptions {
-        content_type: Some("application/octet-stream".into()),
-        cache_control: Some(CACHE_CONTROL_IMMUTABLE.into()),
-        gzip_encoded: false,
-      },
-    )
-    .await
-    .map_err(PublishError::GcsUploadError)?;
+  let npm_tarball_upload = buckets.npm_bucket.upload(
+    npm_tarball_path.into(),
+    UploadTaskBody::Bytes(Bytes::from(npm_tarball.tarball)),
+    GcsUploadOptions {
+      content_type: Some("application/octet-stream".into()),
+      cache_control: Some(CACHE_CONTROL_IMMUTABLE.into()),
+      gzip_encoded: false,
+    },
+  );
 
-  let mut uploads = futures::stream::iter(files)
+  let file_uploads = futures::stream::iter(files)
     .map(|(path, data)| {
       let bytes = Bytes::from(data);
-      let media_type = MediaType::from_str(&path);
-      let maybe_content_type = media_type
-        .as_content_type()
-        .map(|str| str.to_string())
-        .or_else(|| {
-          MEDIA_INFER
-            .get_or_init(|| {
-              let mut media_infer = infer::Infer::new();
-              media_infer.add("image/svg+xml", "svg", |content_bytes| {
-                (content_bytes.starts_with(b"<svg")
-                  || content_bytes.starts_with(b"<?xml"))
-                  && content_bytes.ends_with(b"</svg>")
-              });
-              media_infer
-            })
-            .get(&bytes)
-            .map(|mimetype| mimetype.mime_type().to_string())
-        });
-      (path, bytes, maybe_content_type)
-    })
-    .map(|(path, bytes, maybe_content_type)| {
+      let maybe_content_type = content_type_for_file(&path, &bytes);
       let gcs_path = file_path(
         &publishing_task.package_scope,
         &publishing_task.package_name,
@@ -428,42 +567,81 @@ pub async fn process_tarball(
         &path,
       );
 
-      async move {
-        buckets
-          .modules_bucket
-          .upload(
-            gcs_path.into(),
-            UploadTaskBody::Bytes(bytes),
-            GcsUploadOptions {
-              content_type: maybe_content_type.map(Into::into),
-              cache_control: Some(CACHE_CONTROL_IMMUTABLE.into()),
-              gzip_encoded: false,
-            },
-          )
-          .await
-          .map_err(PublishError::GcsUploadError)
-      }
+      buckets.modules_bucket.upload(
+        gcs_path.into(),
+        UploadTaskBody::Bytes(bytes),
+        GcsUploadOptions {
+          content_type: maybe_content_type.map(Into::into),
+          cache_control: Some(CACHE_CONTROL_IMMUTABLE.into()),
+          gzip_encoded: false,
+        },
+      )
     })
-    .buffer_unordered(MAX_CONCURRENT_UPLOADS);
+    .buffer_unordered(MAX_CONCURRENT_UPLOADS)
+    .try_collect::<()>();
 
-  while let Some(res) = uploads.next().await {
-    res?;
-  }
-
-  drop(uploads);
+  // The docs, the npm tarball, and the package files are independent of each
+  // other, so they are all uploaded at the same time.
+  futures::try_join!(docs_upload, npm_tarball_upload, file_uploads)
+    .map_err(PublishError::GcsUploadError)?;
 
   Ok(ProcessTarballOutput {
     file_infos,
     module_graph_2,
     exports,
+    bin,
+    engines,
+    keywords,
+    license,
     dependencies,
     npm_tarball_info,
     readme_path,
     meta,
     doc_search_json,
+    warnings,
+    specifiers,
   })
 }
 
+/// Computes the sha256 checksums of the given files on the blocking thread
+/// pool, with bounded concurrency. The results are in the same order as the
+/// input, so that the file list of a version does not depend on scheduling.
+async fn hash_files(
+  files: Vec<(PackagePath, Vec<u8>)>,
+) -> Vec<(PackagePath, Vec<u8>, String)> {
+  futures::stream::iter(files)
+    .map(|(path, bytes)| {
+      tokio::task::spawn_blocking(move || {
+        let hash = format!("sha256-{:x}", sha2::Sha256::digest(&bytes));
+        (path, bytes, hash)
+      })
+    })
+    .buffered(MAX_CONCURRENT_HASHES)
+    .map(|res| res.unwrap())
+    .collect()
+    .await
+}
+
+fn content_type_for_file(path: &PackagePath, bytes: &[u8]) -> Option<String> {
+  MediaType::from_str(path)
+    .as_content_type()
+    .map(|str| str.to_string())
+    .or_else(|| {
+      MEDIA_INFER
+        .get_or_init(|| {
+          let mut media_infer = infer::Infer::new();
+          media_infer.add("image/svg+xml", "svg", |content_bytes| {
+            (content_bytes.starts_with(b"<svg")
+              || content_bytes.starts_with(b"<?xml"))
+              && content_bytes.ends_with(b"</svg>")
+          });
+          media_infer
+        })
+        .get(bytes)
+        .map(|mimetype| mimetype.mime_type().to_string())
+    })
+}
+
 pub fn gcs_tarball_path(id: Uuid) -> String {
   format!("publishing_tasks/{}.tar.gz", id)
 }
@@ -584,6 +762,39 @@ pub enum PublishError {
     path: Box<PackagePath>,
     invalid_exports: String,
   },
+  #[error("invalid 'bin' field in config file '{path}': {invalid_bin}")]
+  ConfigFileBinInvalid {
+    path: Box<PackagePath>,
+    invalid_bin: String,
+  },
+  #[error(
+    "invalid 'engines' field in config file '{path}': {invalid_engines}"
+  )]
+  ConfigFileEnginesInvalid {
+    path: Box<PackagePath>,
+    invalid_engines: String,
+  },
+  #[error(
+    "invalid 'keywords' field in config file '{path}': {invalid_keywords}"
+  )]
+  ConfigFileKeywordsInvalid {
+    path: Box<PackagePath>,
+    invalid_keywords: String,
+  },
+  #[error(
+    "invalid 'permissions' field in config file '{path}': {invalid_permissions}"
+  )]
+  ConfigFilePermissionsInvalid {
+    path: Box<PackagePath>,
+    invalid_permissions: String,
+  },
+  #[error(
+    "invalid dependencies in config file '{path}': {invalid_dependencies}"
+  )]
+  ConfigFileDependenciesInvalid {
+    path: Box<PackagePath>,
+    invalid_dependencies: String,
+  },
 
   #[error("failed to build module graph: {}", .0.to_string_with_range())]
   GraphError(Box<ModuleGraphError>),
@@ -594,6 +805,19 @@ pub enum PublishError {
   #[error("failed to generate NPM tarball: {0}")]
   NpmTarballError(anyhow::Error),
 
+  #[error("package analysis timed out during the {stage} stage, the limit is {}s per stage", .timeout.as_secs())]
+  AnalysisTimeout {
+    stage: AnalysisStage,
+    timeout: Duration,
+  },
+
+  #[error("package analysis exceeded the memory limit during the {stage} stage, the limit is {limit} bytes, used {used} bytes")]
+  AnalysisMemoryExceeded {
+    stage: AnalysisStage,
+    limit: u64,
+    used: u64,
+  },
+
   #[error("invalid 'jsr:' specifier: {0}")]
   InvalidJsrSpecifier(PackageReqReferenceParseError),
 
@@ -621,6 +845,13 @@ pub enum PublishError {
     resolved_version: Version,
     exports_key: String,
   },
+
+  #[error("only {:.0}% of the exported symbols of export '{export}' are documented, the scope requires {:.0}%", .coverage * 100.0, .min_coverage * 100.0)]
+  DocsCoverageBelowMinimum {
+    export: String,
+    coverage: f32,
+    min_coverage: f32,
+  },
 }
 
 impl PublishError {
@@ -666,9 +897,26 @@ impl PublishError {
       PublishError::ConfigFileExportsInvalid { .. } => {
         Some("configFileExportsInvalid")
       }
+      PublishError::ConfigFileBinInvalid { .. } => Some("configFileBinInvalid"),
+      PublishError::ConfigFileEnginesInvalid { .. } => {
+        Some("configFileEnginesInvalid")
+      }
+      PublishError::ConfigFileKeywordsInvalid { .. } => {
+        Some("configFileKeywordsInvalid")
+      }
+      PublishError::ConfigFilePermissionsInvalid { .. } => {
+        Some("configFilePermissionsInvalid")
+      }
+      PublishError::ConfigFileDependenciesInvalid { .. } => {
+        Some("configFileDependenciesInvalid")
+      }
       PublishError::GraphError(_) => Some("graphError"),
       PublishError::DocError(_) => Some("docError"),
       PublishError::NpmTarballError(_) => Some("npmTarballError"),
+      PublishError::AnalysisTimeout { .. } => Some("analysisTimeout"),
+      PublishError::AnalysisMemoryExceeded { .. } => {
+        Some("analysisMemoryExceeded")
+      }
       PublishError::InvalidJsrSpecifier(_) => Some("invalidJsrSpecifier"),
       PublishError::InvalidNpmSpecifier(_) => Some("invalidNpmSpecifier"),
       PublishError::JsrMissingConstraint(_) => Some("missingConstraint"),
@@ -682,8 +930,100 @@ impl PublishError {
       PublishError::InvalidJsrDependencySubPath { .. } => {
         Some("invalidJsrDependencySubPath")
       }
+      PublishError::DocsCoverageBelowMinimum { .. } => {
+        Some("docsCoverageBelowMinimum")
+      }
+    }
+  }
+
+  /// The files of the package that caused the error. These are stored with
+  /// the error, so that the publish explanation can point at them.
+  pub fn files(&self) -> Vec<PublishingTaskErrorFile> {
+    fn file(path: impl ToString) -> PublishingTaskErrorFile {
+      PublishingTaskErrorFile {
+        path: path.to_string(),
+        line: None,
+        column: None,
+      }
+    }
+    fn location(
+      specifier: &str,
+      line: usize,
+      column: usize,
+    ) -> PublishingTaskErrorFile {
+      // Modules are analyzed at `file:///<path>`, where the path is the path
+      // of the file in the package.
+      let path = specifier.strip_prefix("file://").unwrap_or(specifier);
+      PublishingTaskErrorFile {
+        path: path.to_owned(),
+        line: (line > 0).then_some(line),
+        column: (line > 0).then_some(column),
+      }
+    }
+
+    match self {
+      PublishError::LinkInTarball { path }
+      | PublishError::InvalidEntryType { path }
+      | PublishError::InvalidPath { path, .. }
+      | PublishError::InvalidGitPath { path } => vec![file(path)],
+      PublishError::GlobalTypeAugmentation {
+        specifier,
+        line,
+        column,
+      }
+      | PublishError::CommonJs {
+        specifier,
+        line,
+        column,
+      }
+      | PublishError::BannedTripleSlashDirectives {
+        specifier,
+        line,
+        column,
+      }
+      | PublishError::BannedImportAssertion {
+        specifier,
+        line,
+        column,
+      } => vec![location(specifier, *line, *column)],
+      PublishError::FileTooLarge { path, .. }
+      | PublishError::PackageTooLarge { path, .. } => vec![file(path)],
+      PublishError::CaseInsensitiveDuplicatePath { a, b } => {
+        vec![file(a), file(b)]
+      }
+      PublishError::MissingConfigFile(path) => vec![file(path)],
+      PublishError::InvalidConfigFile { path, .. }
+      | PublishError::ConfigFileNameMismatch { path, .. }
+      | PublishError::ConfigFileVersionMismatch { path, .. }
+      | PublishError::ConfigFileExportsInvalid { path, .. }
+      | PublishError::ConfigFileBinInvalid { path, .. }
+      | PublishError::ConfigFileEnginesInvalid { path, .. }
+      | PublishError::ConfigFileKeywordsInvalid { path, .. }
+      | PublishError::ConfigFilePermissionsInvalid { path, .. }
+      | PublishError::ConfigFileDependenciesInvalid { path, .. } => {
+        vec![file(path)]
+      }
+      _ => vec![],
     }
   }
+
+  /// The fields of the config file that caused the error.
+  pub fn config_fields(&self) -> Vec<String> {
+    let fields: &[&str] = match self {
+      PublishError::ConfigFileNameMismatch { .. } => &["name"],
+      PublishError::ConfigFileVersionMismatch { .. } => &["version"],
+      PublishError::ConfigFileExportsInvalid { .. } => &["exports"],
+      PublishError::ConfigFileBinInvalid { .. } => &["bin"],
+      PublishError::ConfigFileEnginesInvalid { .. } => &["engines"],
+      PublishError::ConfigFileKeywordsInvalid { .. } => &["keywords"],
+      PublishError::ConfigFilePermissionsInvalid { .. } => &["permissions"],
+      PublishError::ConfigFileDependenciesInvalid { .. } => {
+        &["peerDependencies", "optionalDependencies"]
+      }
+      _ => &[],
+    };
+    fields.iter().map(|field| field.to_string()).collect()
+  }
 }
 
 pub struct FileInfo {
@@ -697,6 +1037,15 @@ pub struct ConfigFile {
   pub name: ScopedPackageName,
   pub version: Option<Version>,
   pub exports: Option<serde_json::Value>,
+  pub bin: Option<serde_json::Value>,
+  pub engines: Option<serde_json::Value>,
+  pub keywords: Option<serde_json::Value>,
+  pub license: Option<serde_json::Value>,
+  pub permissions: Option<serde_json::Value>,
+  #[serde(rename = "peerDependencies")]
+  pub peer_dependencies: Option<serde_json::Value>,
+  #[serde(rename = "optionalDependencies")]
+  pub optional_dependencies: Option<serde_json::Value>,
 }
 
 pub fn exports_map_from_json(
@@ -799,8 +1148,345 @@ pub fn exports_map_from_json(
   Ok(ExportsMap::new(result))
 }
 
+pub fn bin_map_from_json(
+  bin: Option<serde_json::Value>,
+  package_name: &PackageName,
+) -> Result<BinMap, String> {
+  fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+      return Err("executable names must not be empty".to_string());
+    }
+    // ban anything that is not [a-zA-Z0-9_-.]
+    if !name
+      .chars()
+      .all(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.'))
+    {
+      return Err(format!(
+        "the executable name '{name}' contains invalid characters, only [a-z][A-Z][0-9]-_. are allowed",
+      ));
+    }
+    if name.starts_with('.') {
+      return Err(format!(
+        "the executable name '{name}' must not start with a dot (.)"
+      ));
+    }
+    Ok(())
+  }
+
+  fn validate_value(name: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() {
+      return Err(format!(
+        "the path for executable '{name}' must be a non-empty relative path"
+      ));
+    }
+    if !value.starts_with("./") {
+      return Err(format!("the path '{value}' for executable '{name}' could not be resolved as a relative path from the config file, did you mean './{value}'?"));
+    }
+    let file_name = value.rsplit('/').next().unwrap();
+    if !file_name.contains('.') {
+      return Err(format!(
+        "the path '{value}' for executable '{name}' must not end in / and must have a file extension"
+      ));
+    }
+    Ok(())
+  }
+
+  let bin = match bin {
+    None => {
+      return Ok(BinMap::default());
+    }
+    Some(serde_json::Value::String(val)) => {
+      let name = package_name.to_string();
+      validate_value(&name, &val)?;
+      return Ok(BinMap::new(IndexMap::from([(name, val)])));
+    }
+    Some(serde_json::Value::Object(map)) => map,
+    Some(serde_json::Value::Array(_))
+    | Some(serde_json::Value::Bool(_))
+    | Some(serde_json::Value::Number(_))
+    | Some(serde_json::Value::Null) => {
+      return Err("'bin' field must be a string or an object".to_string());
+    }
+  };
+
+  let mut result = IndexMap::new();
+
+  for (name, value) in bin {
+    validate_name(&name)?;
+    let value = match value {
+      serde_json::Value::String(value) => value,
+      _ => {
+        return Err(format!(
+          "executable '{name}' must be a string, invalid value: '{value}'",
+        ));
+      }
+    };
+    validate_value(&name, &value)?;
+    result.insert(name, value);
+  }
+
+  Ok(BinMap::new(result))
+}
+
+/// The maximum number of keywords a package version may have.
+pub const MAX_KEYWORDS: usize = 10;
+/// The maximum length of a single keyword.
+pub const MAX_KEYWORD_LENGTH: usize = 32;
+
+/// Parses the `keywords` field of a config file. Keywords are lowercased,
+/// aliases of curated topics are replaced by the topic, and duplicates are
+/// removed.
+pub fn keywords_from_json(
+  keywords: Option<serde_json::Value>,
+) -> Result<Vec<String>, String> {
+  let keywords = match keywords {
+    None => return Ok(vec![]),
+    Some(serde_json::Value::Array(keywords)) => keywords,
+    Some(_) => return Err("'keywords' field must be an array".to_string()),
+  };
+
+  let mut result: Vec<String> = Vec::with_capacity(keywords.len());
+  for keyword in keywords {
+    let serde_json::Value::String(keyword) = keyword else {
+      return Err(format!(
+        "keyword must be a string, invalid value: '{keyword}'"
+      ));
+    };
+    let keyword = keyword.trim().to_ascii_lowercase();
+    if keyword.is_empty() || keyword.len() > MAX_KEYWORD_LENGTH {
+      return Err(format!(
+        "keyword '{keyword}' must be between 1 and {MAX_KEYWORD_LENGTH} characters long"
+      ));
+    }
+    if !keyword
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+      || keyword.starts_with('-')
+      || keyword.ends_with('-')
+    {
+      return Err(format!(
+        "keyword '{keyword}' may only contain lowercase letters, digits, and dashes, and may not start or end with a dash"
+      ));
+    }
+    let keyword = match crate::topics::topic_for_keyword(&keyword) {
+      Some(topic) => topic.to_string(),
+      None => keyword,
+    };
+    if !result.contains(&keyword) {
+      result.push(keyword);
+    }
+  }
+
+  if result.len() > MAX_KEYWORDS {
+    return Err(format!(
+      "a package may have at most {MAX_KEYWORDS} keywords"
+    ));
+  }
+
+  Ok(result)
+}
+
+/// Parses the `engines` field of a config file. It maps runtimes to the
+/// minimum version of the runtime that the package requires, in the form
+/// `>=MAJOR[.MINOR[.PATCH]]`.
+pub fn engines_from_json(
+  engines: Option<serde_json::Value>,
+) -> Result<PackageVersionEngines, String> {
+  let engines = match engines {
+    None => return Ok(PackageVersionEngines::default()),
+    Some(serde_json::Value::Object(map)) => map,
+    Some(_) => return Err("'engines' field must be an object".to_string()),
+  };
+
+  let mut result = PackageVersionEngines::default();
+  for (engine, value) in engines {
+    let slot = match engine.as_str() {
+      "deno" => &mut result.deno,
+      "node" => &mut result.node,
+      "bun" => &mut result.bun,
+      _ => {
+        return Err(format!(
+          "unknown engine '{engine}', only 'deno', 'node', and 'bun' are supported"
+        ));
+      }
+    };
+    let serde_json::Value::String(value) = value else {
+      return Err(format!(
+        "engine '{engine}' must be a string, invalid value: '{value}'"
+      ));
+    };
+
+    let invalid = || {
+      format!("the version '{value}' for engine '{engine}' must be a minimum version like '>=1.2.3'")
+    };
+    let version = value.trim().strip_prefix(">=").ok_or_else(invalid)?;
+    let mut parts = version
+      .trim()
+      .split('.')
+      .map(|part| {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+          return None;
+        }
+        part.parse::<u32>().ok()
+      })
+      .collect::<Option<Vec<_>>>()
+      .ok_or_else(invalid)?;
+    if parts.len() > 3 {
+      return Err(invalid());
+    }
+    parts.resize(3, 0);
+    *slot = Some(format!("{}.{}.{}", parts[0], parts[1], parts[2]));
+  }
+
+  Ok(result)
+}
+
+/// The maximum number of targets a single permission may list.
+pub const MAX_PERMISSION_TARGETS: usize = 32;
+/// The maximum length of a single permission target.
+pub const MAX_PERMISSION_TARGET_LENGTH: usize = 256;
+
+/// Parses the `permissions` field of a config file. Each permission maps to
+/// `true` if it is needed for any target, to a list of targets like hosts or
+/// environment variable names, or to `false` if it is not needed. Returns
+/// `None` if the package does not declare its permissions.
+pub fn permissions_from_json(
+  permissions: Option<serde_json::Value>,
+) -> Result<Option<PackageVersionPermissions>, String> {
+  let permissions = match permissions {
+    None => return Ok(None),
+    Some(serde_json::Value::Object(map)) => map,
+    Some(_) => return Err("'permissions' field must be an object".to_string()),
+  };
+
+  let mut result = PackageVersionPermissions::default();
+  for (name, value) in permissions {
+    let Some(slot) = result.get_mut(&name) else {
+      return Err(format!(
+        "unknown permission '{name}', only {} are supported",
+        PackageVersionPermissions::NAMES
+          .iter()
+          .map(|name| format!("'{name}'"))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ));
+    };
+    let invalid = || {
+      format!("permission '{name}' must be true, false, or a list of targets")
+    };
+    *slot = match value {
+      serde_json::Value::Bool(true) => Some(PermissionTargets::Any(true)),
+      serde_json::Value::Bool(false) => None,
+      serde_json::Value::Array(values) => {
+        if values.is_empty() {
+          return Err(format!(
+            "permission '{name}' must list at least one target, or be true"
+          ));
+        }
+        if values.len() > MAX_PERMISSION_TARGETS {
+          return Err(format!(
+            "permission '{name}' may list at most {MAX_PERMISSION_TARGETS} targets"
+          ));
+        }
+        let mut targets = Vec::with_capacity(values.len());
+        for value in values {
+          let serde_json::Value::String(target) = value else {
+            return Err(invalid());
+          };
+          let target = target.trim();
+          if target.is_empty()
+            || target.len() > MAX_PERMISSION_TARGET_LENGTH
+            || target.chars().any(|c| c.is_control())
+          {
+            return Err(format!(
+              "the target '{target}' of permission '{name}' must be between 1 and {MAX_PERMISSION_TARGET_LENGTH} printable characters"
+            ));
+          }
+          if !targets.iter().any(|existing| existing == target) {
+            targets.push(target.to_string());
+          }
+        }
+        Some(PermissionTargets::Only(targets))
+      }
+      _ => return Err(invalid()),
+    };
+  }
+
+  Ok(Some(result))
+}
+
+/// Parses the `peerDependencies` and `optionalDependencies` fields of a config
+/// file. Both are lists of `jsr:` or `npm:` package names without a version
+/// constraint, as the constraint is taken from the imports of the package.
+pub fn dependency_types_from_json(
+  peer_dependencies: Option<serde_json::Value>,
+  optional_dependencies: Option<serde_json::Value>,
+) -> Result<HashMap<(DependencyKind, String), DependencyType>, String> {
+  let mut result = HashMap::new();
+
+  let fields = [
+    ("peerDependencies", DependencyType::Peer, peer_dependencies),
+    (
+      "optionalDependencies",
+      DependencyType::Optional,
+      optional_dependencies,
+    ),
+  ];
+  for (field, dependency_type, value) in fields {
+    let values = match value {
+      None => continue,
+      Some(serde_json::Value::Array(values)) => values,
+      Some(_) => {
+        return Err(format!("'{field}' field must be an array of strings"));
+      }
+    };
+
+    for value in values {
+      let serde_json::Value::String(specifier) = value else {
+        return Err(format!(
+          "'{field}' must only contain strings, invalid value: '{value}'"
+        ));
+      };
+
+      let (kind, req) = if specifier.starts_with("jsr:") {
+        let req = JsrPackageReqReference::from_str(&specifier)
+          .map_err(|err| format!("invalid specifier in '{field}': {err}"))?;
+        (DependencyKind::Jsr, req.into_inner())
+      } else if specifier.starts_with("npm:") {
+        let req = NpmPackageReqReference::from_str(&specifier)
+          .map_err(|err| format!("invalid specifier in '{field}': {err}"))?;
+        (DependencyKind::Npm, req.into_inner())
+      } else {
+        return Err(format!(
+          "'{specifier}' in '{field}' must start with 'jsr:' or 'npm:'"
+        ));
+      };
+
+      if req.req.version_req.version_text() != "*" || req.sub_path.is_some() {
+        return Err(format!(
+          "'{specifier}' in '{field}' must be a package name without a version constraint or path, the constraint is taken from the imports of the package"
+        ));
+      }
+
+      let key = (kind, req.req.name.to_string());
+      if result.insert(key, dependency_type).is_some() {
+        return Err(format!(
+          "'{specifier}' is listed more than once in 'peerDependencies' and 'optionalDependencies'"
+        ));
+      }
+    }
+  }
+
+  Ok(result)
+}
+
 #[cfg(test)]
 mod tests {
+  use crate::db::DependencyKind;
+  use crate::db::DependencyType;
+  use crate::ids::PackageName;
+  use crate::ids::PackagePath;
+
   macro_rules! exports_map_from_json_error {
     ($name:ident, $json:tt, $expected:expr) => {
       #[test]
@@ -872,4 +1558,266 @@ mod tests {
     { "./foo": 1 },
     "export './foo' must be a string, invalid value: '1'"
   );
+
+  macro_rules! bin_map_from_json_error {
+    ($name:ident, $json:tt, $expected:expr) => {
+      #[test]
+      fn $name() {
+        let json = serde_json::json!($json);
+        let package_name = PackageName::try_from("foo").unwrap();
+        assert_eq!(
+          super::bin_map_from_json(Some(json), &package_name).unwrap_err(),
+          $expected
+        );
+      }
+    };
+  }
+
+  bin_map_from_json_error!(
+    bin_array,
+    [],
+    "'bin' field must be a string or an object"
+  );
+  bin_map_from_json_error!(
+    bin_invalid_root_path,
+    "cli.ts",
+    "the path 'cli.ts' for executable 'foo' could not be resolved as a relative path from the config file, did you mean './cli.ts'?"
+  );
+  bin_map_from_json_error!(
+    bin_invalid_name_1,
+    { "foo/bar": "./cli.ts" },
+    "the executable name 'foo/bar' contains invalid characters, only [a-z][A-Z][0-9]-_. are allowed"
+  );
+  bin_map_from_json_error!(
+    bin_invalid_name_2,
+    { ".foo": "./cli.ts" },
+    "the executable name '.foo' must not start with a dot (.)"
+  );
+  bin_map_from_json_error!(
+    bin_invalid_value_1,
+    { "foo": 1 },
+    "executable 'foo' must be a string, invalid value: '1'"
+  );
+  bin_map_from_json_error!(
+    bin_invalid_value_2,
+    { "foo": "./cli" },
+    "the path './cli' for executable 'foo' must not end in / and must have a file extension"
+  );
+
+  #[test]
+  fn bin_map_from_json_string() {
+    let package_name = PackageName::try_from("foo").unwrap();
+    let bin = super::bin_map_from_json(
+      Some(serde_json::json!("./cli.ts")),
+      &package_name,
+    )
+    .unwrap();
+    assert_eq!(
+      bin.iter().collect::<Vec<_>>(),
+      vec![(&"foo".to_string(), &"./cli.ts".to_string())]
+    );
+  }
+
+  macro_rules! dependency_types_from_json_error {
+    ($name:ident, $peer:tt, $optional:tt, $expected:expr) => {
+      #[test]
+      fn $name() {
+        assert_eq!(
+          super::dependency_types_from_json(
+            serde_json::from_value(serde_json::json!($peer)).unwrap(),
+            serde_json::from_value(serde_json::json!($optional)).unwrap(),
+          )
+          .unwrap_err(),
+          $expected
+        );
+      }
+    };
+  }
+
+  dependency_types_from_json_error!(
+    dependencies_not_array,
+    "npm:react",
+    null,
+    "'peerDependencies' field must be an array of strings"
+  );
+  dependency_types_from_json_error!(
+    dependencies_invalid_scheme,
+    ["react"],
+    null,
+    "'react' in 'peerDependencies' must start with 'jsr:' or 'npm:'"
+  );
+  dependency_types_from_json_error!(
+    dependencies_with_constraint,
+    null,
+    ["npm:chalk@^5"],
+    "'npm:chalk@^5' in 'optionalDependencies' must be a package name without a version constraint or path, the constraint is taken from the imports of the package"
+  );
+  dependency_types_from_json_error!(
+    dependencies_duplicate,
+    ["jsr:@std/path"],
+    ["jsr:@std/path"],
+    "'jsr:@std/path' is listed more than once in 'peerDependencies' and 'optionalDependencies'"
+  );
+
+  #[test]
+  fn dependency_types_from_json() {
+    let dependency_types = super::dependency_types_from_json(
+      Some(serde_json::json!(["npm:react", "jsr:@std/path"])),
+      Some(serde_json::json!(["npm:chalk"])),
+    )
+    .unwrap();
+    assert_eq!(dependency_types.len(), 3);
+    assert_eq!(
+      dependency_types.get(&(DependencyKind::Npm, "react".to_string())),
+      Some(&DependencyType::Peer)
+    );
+    assert_eq!(
+      dependency_types.get(&(DependencyKind::Jsr, "@std/path".to_string())),
+      Some(&DependencyType::Peer)
+    );
+    assert_eq!(
+      dependency_types.get(&(DependencyKind::Npm, "chalk".to_string())),
+      Some(&DependencyType::Optional)
+    );
+  }
+
+  macro_rules! engines_from_json_error {
+    ($name:ident, $json:tt, $expected:expr) => {
+      #[test]
+      fn $name() {
+        let json = serde_json::json!($json);
+        assert_eq!(
+          super::engines_from_json(Some(json)).unwrap_err(),
+          $expected
+        );
+      }
+    };
+  }
+
+  engines_from_json_error!(
+    engines_not_object,
+    ">=18",
+    "'engines' field must be an object"
+  );
+  engines_from_json_error!(
+    engines_unknown,
+    { "python": ">=3" },
+    "unknown engine 'python', only 'deno', 'node', and 'bun' are supported"
+  );
+  engines_from_json_error!(
+    engines_not_minimum,
+    { "node": "^18" },
+    "the version '^18' for engine 'node' must be a minimum version like '>=1.2.3'"
+  );
+  engines_from_json_error!(
+    engines_too_many_parts,
+    { "deno": ">=1.2.3.4" },
+    "the version '>=1.2.3.4' for engine 'deno' must be a minimum version like '>=1.2.3'"
+  );
+
+  #[test]
+  fn permissions_from_json() {
+    use crate::db::PermissionTargets;
+
+    assert_eq!(super::permissions_from_json(None).unwrap(), None);
+
+    let permissions = super::permissions_from_json(Some(serde_json::json!({
+      "net": ["api.example.com", " api.example.com "],
+      "env": true,
+      "read": false,
+    })))
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+      permissions.net,
+      Some(PermissionTargets::Only(vec!["api.example.com".to_string()]))
+    );
+    assert_eq!(permissions.env, Some(PermissionTargets::Any(true)));
+    assert_eq!(permissions.read, None);
+    assert_eq!(
+      serde_json::to_value(&permissions).unwrap(),
+      serde_json::json!({ "net": ["api.example.com"], "env": true })
+    );
+
+    assert_eq!(
+      super::permissions_from_json(Some(serde_json::json!(["net"])))
+        .unwrap_err(),
+      "'permissions' field must be an object"
+    );
+    assert_eq!(
+      super::permissions_from_json(Some(serde_json::json!({ "hrtime": true })))
+        .unwrap_err(),
+      "unknown permission 'hrtime', only 'net', 'env', 'read', 'write', 'run', 'sys', 'ffi' are supported"
+    );
+    assert_eq!(
+      super::permissions_from_json(Some(serde_json::json!({ "net": [] })))
+        .unwrap_err(),
+      "permission 'net' must list at least one target, or be true"
+    );
+    assert_eq!(
+      super::permissions_from_json(Some(serde_json::json!({ "env": "HOME" })))
+        .unwrap_err(),
+      "permission 'env' must be true, false, or a list of targets"
+    );
+  }
+
+  #[test]
+  fn keywords_from_json() {
+    let keywords = super::keywords_from_json(Some(serde_json::json!([
+      "HTTP", "server", "left-pad", "left-pad"
+    ])))
+    .unwrap();
+    assert_eq!(keywords, vec!["http", "left-pad"]);
+
+    assert_eq!(
+      super::keywords_from_json(Some(serde_json::json!("cli"))).unwrap_err(),
+      "'keywords' field must be an array"
+    );
+    assert_eq!(
+      super::keywords_from_json(Some(serde_json::json!(["no spaces"])))
+        .unwrap_err(),
+      "keyword 'no spaces' may only contain lowercase letters, digits, and dashes, and may not start or end with a dash"
+    );
+    let too_many = (0..11).map(|i| format!("k{i}")).collect::<Vec<_>>();
+    assert_eq!(
+      super::keywords_from_json(Some(serde_json::json!(too_many))).unwrap_err(),
+      "a package may have at most 10 keywords"
+    );
+  }
+
+  #[test]
+  fn engines_from_json() {
+    let engines = super::engines_from_json(Some(serde_json::json!({
+      "deno": ">=1.40",
+      "node": ">= 18.19.1",
+    })))
+    .unwrap();
+    assert_eq!(engines.deno.as_deref(), Some("1.40.0"));
+    assert_eq!(engines.node.as_deref(), Some("18.19.1"));
+    assert_eq!(engines.bun, None);
+  }
+
+  #[tokio::test]
+  async fn hash_files_preserves_order() {
+    let files = (0..100)
+      .map(|i| {
+        let path = PackagePath::try_from(format!("/{i}.txt")).unwrap();
+        // Make the first files the slowest to hash.
+        (path, vec![b'a'; (100 - i) * 10_000])
+      })
+      .collect::<Vec<_>>();
+    let hashed = super::hash_files(files.clone()).await;
+    assert_eq!(hashed.len(), files.len());
+    for ((path, bytes), (hashed_path, hashed_bytes, hash)) in
+      files.iter().zip(hashed.iter())
+    {
+      assert_eq!(path, hashed_path);
+      assert_eq!(bytes, hashed_bytes);
+      assert!(hash.starts_with("sha256-"));
+    }
+    assert_eq!(
+      hashed[99].2,
+      "sha256-27dd1f61b867b6a0f6e9d8a41c43231de52107e53ae424de8f847b821db4b711"
+    );
+  }
 }
@@ -1,6 +1,10 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::Arc;
 
+use anyhow::Context;
 use bytes::Bytes;
 use chrono::DateTime;
 use chrono::Utc;
@@ -13,6 +17,8 @@ use futures::stream;
 use futures::StreamExt;
 use hyper::Body;
 use hyper::Request;
+use indexmap::IndexMap;
+use indexmap::IndexSet;
 use routerify::ext::RequestExt;
 use routerify::Router;
 use routerify_query::RequestQueryExt;
@@ -21,18 +27,41 @@ use serde::Serialize;
 use serde_json::json;
 use tracing::error;
 use tracing::field;
+use tracing::info;
 use tracing::instrument;
 use tracing::Span;
+use tracing::warn;
+use url::Url;
+use uuid::Uuid;
 
 use crate::analysis::rebuild_npm_tarball;
+use crate::analysis::AnalysisLimits;
 use crate::analysis::RebuildNpmTarballData;
+use crate::analytics;
+use crate::analytics::AnalyticsSinks;
+use crate::api::dependency_freshness;
 use crate::api::ApiError;
+use crate::api::PublishQueue;
 use crate::buckets::Buckets;
 use crate::buckets::UploadTaskBody;
+use crate::db::AuditFindingSeverity;
+use crate::db::BrokenLink;
 use crate::db::Database;
+use crate::db::DownloadAnomalyKind;
+use crate::db::DownloadIngestionCheckpoint;
 use crate::db::DownloadKind;
+use crate::db::NewDownloadAnomaly;
 use crate::db::NewNpmTarball;
+use crate::db::NpmTarballBuildRequest;
+use crate::db::PublishingTaskStatus;
+use crate::db::SearchIndexUpdate;
+use crate::db::ValidationLevel;
 use crate::db::VersionDownloadCount;
+use crate::docs::DocNodesByUrl;
+use crate::emails::EmailArgs;
+use crate::emails::EmailSender;
+use crate::emails::OutdatedDependency;
+use crate::emails::OutdatedPackage;
 use crate::gcp;
 use crate::gcp::GcsUploadOptions;
 use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
@@ -41,23 +70,36 @@ use crate::gcs_paths;
 use crate::ids::PackageName;
 use crate::ids::ScopeName;
 use crate::ids::Version;
+use crate::link_check;
 use crate::npm::generate_npm_version_manifest;
 use crate::npm::NPM_TARBALL_REVISION;
+use crate::orama::OramaClient;
 use crate::publish;
+use crate::queue::TaskHandler;
+use crate::queue::TaskQueue;
+use crate::signing::MetadataSigner;
+use crate::tarball::gcs_tarball_path;
+use crate::user_export::process_user_exports;
 use crate::util;
 use crate::util::decode_json;
 use crate::util::ApiResult;
+use crate::NpmIncludeYankedVersions;
 use crate::NpmUrl;
 use crate::RegistryUrl;
 
-pub struct NpmTarballBuildQueue(pub Option<gcp::Queue>);
+pub struct NpmTarballBuildQueue(pub Option<Arc<dyn TaskQueue>>);
 pub struct LogsBigQueryTable(
   pub Option<(gcp::BigQuery, /* logs table id */ String)>,
 );
+pub struct UnmaintainedSuggestionAfter(pub chrono::Duration);
 
 pub fn tasks_router() -> Router<Body, ApiError> {
   Router::builder()
     .post("/publish", util::json(publish::publish_handler))
+    .post(
+      "/scheduled_publishes",
+      util::json(scheduled_publishes_handler),
+    )
     .post("/npm_tarball_build", util::json(npm_tarball_build_handler))
     .post(
       "/npm_tarball_enqueue",
@@ -67,6 +109,31 @@ pub fn tasks_router() -> Router<Body, ApiError> {
       "/scrape_download_counts",
       util::json(scrape_download_counts_handler),
     )
+    .post(
+      "/outdated_dependencies_email",
+      util::json(outdated_dependencies_email_handler),
+    )
+    .post(
+      "/download_anomalies",
+      util::json(download_anomalies_handler),
+    )
+    .post("/check_links", util::json(check_links_handler))
+    .post("/export_analytics", util::json(export_analytics_handler))
+    .post("/user_exports", util::json(user_exports_handler))
+    .post("/consistency_audit", util::json(consistency_audit_handler))
+    .post(
+      "/reconcile_dependent_counts",
+      util::json(reconcile_dependent_counts_handler),
+    )
+    .post(
+      "/publish_artifacts_cleanup",
+      util::json(publish_artifacts_cleanup_handler),
+    )
+    .post(
+      "/suggest_unmaintained",
+      util::json(suggest_unmaintained_handler),
+    )
+    .post("/search_index", util::json(search_index_handler))
     .build()
     .unwrap()
 }
@@ -78,43 +145,256 @@ struct NpmTarballBuildJob {
   pub version: Version,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NpmTarballBuildWorker {
+  pub batch_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum NpmTarballBuildTask {
+  /// Build the npm tarball for a single package version.
+  Job(NpmTarballBuildJob),
+  /// Repeatedly claim a batch of missing npm tarballs and build them, until
+  /// there is no work left or the time budget of the worker is exhausted.
+  Worker(NpmTarballBuildWorker),
+}
+
+/// How long a single worker task keeps claiming new batches.
+const NPM_TARBALL_BUILD_WORKER_TIME_BUDGET: std::time::Duration =
+  std::time::Duration::from_secs(5 * 60);
+/// How long a claimed version is reserved for a worker. If the worker fails to
+/// build the tarball in this time, the version is picked up by another worker.
+const NPM_TARBALL_BUILD_CLAIM_DURATION: chrono::Duration =
+  chrono::Duration::minutes(15);
+const NPM_TARBALL_BUILD_WORKER_PARALLELISM: usize = 4;
+const NPM_TARBALL_BUILD_BATCH_SIZE: i64 = 32;
+/// This matches the max concurrent dispatches of the npm tarball build queue.
+const NPM_TARBALL_BUILD_MAX_WORKERS: i64 = 30;
+
 #[instrument(
   name = "POST /tasks/npm_tarball_build",
   skip(req),
   err,
-  fields(job)
+  fields(task)
 )]
 pub async fn npm_tarball_build_handler(
   mut req: Request<Body>,
 ) -> ApiResult<()> {
-  let job: NpmTarballBuildJob = decode_json(&mut req).await?;
-  Span::current().record("job", field::debug(&job));
+  let task: NpmTarballBuildTask = decode_json(&mut req).await?;
+  Span::current().record("task", field::debug(&task));
 
   let db = req.data::<Database>().unwrap().clone();
   let buckets = req.data::<Buckets>().unwrap().clone();
   let registry_url = req.data::<RegistryUrl>().unwrap().0.clone();
   let npm_url = req.data::<NpmUrl>().unwrap().0.clone();
+  let include_yanked = req.data::<NpmIncludeYankedVersions>().unwrap().0;
 
+  run_npm_tarball_build_task(
+    task,
+    &db,
+    &buckets,
+    &registry_url,
+    &npm_url,
+    include_yanked,
+  )
+  .await
+}
+
+/// Creates the handler that processes npm tarball build tasks from a
+/// Postgres-backed or in-process task queue. Cloud Tasks delivers them to
+/// [`npm_tarball_build_handler`] instead.
+pub fn npm_tarball_build_task_handler(
+  db: Database,
+  buckets: Buckets,
+  registry_url: Url,
+  npm_url: Url,
+  include_yanked: bool,
+) -> TaskHandler {
+  Arc::new(move |body| {
+    let db = db.clone();
+    let buckets = buckets.clone();
+    let registry_url = registry_url.clone();
+    let npm_url = npm_url.clone();
+    Box::pin(async move {
+      let task: NpmTarballBuildTask = serde_json::from_slice(&body)?;
+      run_npm_tarball_build_task(
+        task,
+        &db,
+        &buckets,
+        &registry_url,
+        &npm_url,
+        include_yanked,
+      )
+      .await?;
+      Ok(())
+    })
+  })
+}
+
+async fn run_npm_tarball_build_task(
+  task: NpmTarballBuildTask,
+  db: &Database,
+  buckets: &Buckets,
+  registry_url: &Url,
+  npm_url: &Url,
+  include_yanked: bool,
+) -> Result<(), ApiError> {
+  match task {
+    NpmTarballBuildTask::Job(job) => {
+      build_npm_tarball(
+        db,
+        buckets,
+        registry_url,
+        &job.scope,
+        &job.name,
+        &job.version,
+      )
+      .await?;
+      upload_npm_version_manifest(
+        db,
+        buckets,
+        npm_url,
+        &job.scope,
+        &job.name,
+        include_yanked,
+      )
+      .await?;
+    }
+    NpmTarballBuildTask::Worker(worker) => {
+      run_npm_tarball_build_worker(
+        db,
+        buckets,
+        registry_url,
+        npm_url,
+        include_yanked,
+        worker.batch_size,
+      )
+      .await?;
+    }
+  }
+
+  Ok(())
+}
+
+async fn run_npm_tarball_build_worker(
+  db: &Database,
+  buckets: &Buckets,
+  registry_url: &Url,
+  npm_url: &Url,
+  include_yanked: bool,
+  batch_size: i64,
+) -> Result<(), ApiError> {
+  let started = std::time::Instant::now();
+
+  while started.elapsed() < NPM_TARBALL_BUILD_WORKER_TIME_BUDGET {
+    let batch = db
+      .claim_missing_npm_tarballs(
+        NPM_TARBALL_REVISION as i32,
+        batch_size,
+        NPM_TARBALL_BUILD_CLAIM_DURATION,
+      )
+      .await?;
+    if batch.is_empty() {
+      break;
+    }
+
+    let results = stream::iter(batch)
+      .map(|(scope, name, version)| async move {
+        let job = NpmTarballBuildJob {
+          scope,
+          name,
+          version,
+        };
+        let res = build_npm_tarball(
+          db,
+          buckets,
+          registry_url,
+          &job.scope,
+          &job.name,
+          &job.version,
+        )
+        .await;
+        (job, res)
+      })
+      .buffer_unordered(NPM_TARBALL_BUILD_WORKER_PARALLELISM)
+      .collect::<Vec<_>>()
+      .await;
+
+    let mut built_packages = IndexSet::new();
+    for (job, res) in results {
+      match res {
+        Ok(()) => {
+          built_packages.insert((job.scope, job.name));
+        }
+        Err(err) => {
+          // The claim is not released, so that the version is only retried
+          // (by any worker) once the claim expires.
+          error!("failed to build npm tarball for {job:?}: {err}");
+        }
+      }
+    }
+
+    for (scope, name) in &built_packages {
+      upload_npm_version_manifest(
+        db,
+        buckets,
+        npm_url,
+        scope,
+        name,
+        include_yanked,
+      )
+      .await?;
+    }
+
+    let progress = db
+      .get_npm_tarball_build_progress(NPM_TARBALL_REVISION as i32)
+      .await?;
+    info!(
+      revision = progress.revision,
+      total_versions = progress.total_versions,
+      built_versions = progress.built_versions,
+      claimed_versions = progress.claimed_versions,
+      requested_packages = progress.requested_packages,
+      "npm tarball build progress"
+    );
+  }
+
+  Ok(())
+}
+
+/// Build the npm tarball for the current revision of the given package version,
+/// unless it has already been built.
+pub(crate) async fn build_npm_tarball(
+  db: &Database,
+  buckets: &Buckets,
+  registry_url: &Url,
+  scope: &ScopeName,
+  name: &PackageName,
+  version: &Version,
+) -> Result<(), ApiError> {
   let is_already_built = db
-    .get_npm_tarball(
-      &job.scope,
-      &job.name,
-      &job.version,
-      NPM_TARBALL_REVISION as i32,
-    )
+    .get_npm_tarball(scope, name, version, NPM_TARBALL_REVISION as i32)
     .await?
     .is_some();
 
   if !is_already_built {
-    let version = db
-      .get_package_version(&job.scope, &job.name, &job.version)
+    let package_version = db
+      .get_package_version(scope, name, version)
       .await?
       .ok_or(ApiError::PackageVersionNotFound)?;
+    let (package, _, _) = db
+      .get_package(scope, name)
+      .await?
+      .ok_or(ApiError::PackageNotFound)?;
+    let package_scope =
+      db.get_scope(scope).await?.ok_or(ApiError::ScopeNotFound)?;
     let dependencies = db
-      .list_package_version_dependencies(&job.scope, &job.name, &job.version)
+      .list_package_version_dependencies(scope, name, version)
       .await?;
     let files: HashSet<_> = db
-      .list_package_files(&job.scope, &job.name, &job.version)
+      .list_package_files(scope, name, version)
       .await?
       .into_iter()
       .map(|f| f.path)
@@ -134,41 +414,47 @@ pub async fn npm_tarball_build_handler(
           name: StackString::from_string(dep.dependency_name),
           version_req,
         };
-        (dep.dependency_kind, PackageReqReference { req, sub_path })
+        (
+          dep.dependency_kind,
+          dep.dependency_type,
+          PackageReqReference { req, sub_path },
+        )
       })
       .collect();
 
     let span = Span::current();
     let data = RebuildNpmTarballData {
       files,
-      scope: version.scope,
-      name: version.name,
-      version: version.version,
+      scope: package_version.scope,
+      name: package_version.name,
+      version: package_version.version,
       dependencies,
-      exports: version.exports,
+      exports: package_version.exports,
+      bin: package_version.bin,
+      engines: package_version.engines,
+      validation_level: ValidationLevel::effective(&package_scope, &package),
+      runtime_compat: package_version.meta.runtime_compat.unwrap_or_default(),
     };
+    let registry_url = registry_url.clone();
+    let modules_bucket = buckets.modules_bucket.clone();
     let npm_tarball = tokio::task::spawn_blocking(|| {
-      rebuild_npm_tarball(span, registry_url, buckets.modules_bucket, data)
+      rebuild_npm_tarball(span, registry_url, modules_bucket, data)
     })
     .await
     .unwrap()?;
 
     let new_npm_tarball = NewNpmTarball {
-      scope: &job.scope,
-      name: &job.name,
-      version: &job.version,
+      scope,
+      name,
+      version,
       revision: NPM_TARBALL_REVISION as i32,
       size: npm_tarball.tarball.len() as i32,
       sha1: &npm_tarball.sha1,
       sha512: &npm_tarball.sha512,
     };
 
-    let npm_tarball_path = gcs_paths::npm_tarball_path(
-      &job.scope,
-      &job.name,
-      &job.version,
-      NPM_TARBALL_REVISION,
-    );
+    let npm_tarball_path =
+      gcs_paths::npm_tarball_path(scope, name, version, NPM_TARBALL_REVISION);
     buckets
       .npm_bucket
       .upload(
@@ -185,10 +471,47 @@ pub async fn npm_tarball_build_handler(
     db.create_npm_tarball(new_npm_tarball).await?;
   }
 
+  db.release_npm_tarball_build_claim(
+    scope,
+    name,
+    version,
+    NPM_TARBALL_REVISION as i32,
+  )
+  .await?;
+
+  Ok(())
+}
+
+/// Queues a build of the npm tarball of a single package version.
+pub(crate) async fn enqueue_npm_tarball_build(
+  queue: &dyn TaskQueue,
+  scope: &ScopeName,
+  name: &PackageName,
+  version: &Version,
+) -> Result<(), ApiError> {
+  let task = NpmTarballBuildTask::Job(NpmTarballBuildJob {
+    scope: scope.clone(),
+    name: name.clone(),
+    version: version.clone(),
+  });
+  let body = serde_json::to_vec(&task).unwrap();
+  queue.enqueue(body.into()).await?;
+  Ok(())
+}
+
+pub(crate) async fn upload_npm_version_manifest(
+  db: &Database,
+  buckets: &Buckets,
+  npm_url: &Url,
+  scope: &ScopeName,
+  name: &PackageName,
+  include_yanked: bool,
+) -> Result<(), ApiError> {
   let npm_version_manifest_path =
-    crate::gcs_paths::npm_version_manifest_path(&job.scope, &job.name);
+    crate::gcs_paths::npm_version_manifest_path(scope, name);
   let npm_version_manifest =
-    generate_npm_version_manifest(&db, &npm_url, &job.scope, &job.name).await?;
+    generate_npm_version_manifest(db, npm_url, scope, name, include_yanked)
+      .await?;
   let content = serde_json::to_vec_pretty(&npm_version_manifest)?;
   buckets
     .npm_bucket
@@ -212,22 +535,51 @@ const NPM_TARBALL_BUILD_ENQUEUE_PARALLELISM: usize = 32;
 pub async fn npm_tarball_enqueue_handler(req: Request<Body>) -> ApiResult<()> {
   let db = req.data::<Database>().unwrap().clone();
   let queue = req.data::<NpmTarballBuildQueue>().unwrap();
+  let bigquery = req.data::<LogsBigQueryTable>().unwrap();
+  let npm_url = req.data::<NpmUrl>().unwrap().0.clone();
 
   let queue = queue.0.as_ref().ok_or(ApiError::InternalServerError)?;
 
-  let missing_tarballs = db
-    .list_missing_npm_tarballs(NPM_TARBALL_REVISION as i32)
+  // Packages that users tried to install from the npm endpoint but got a 404
+  // for are built before all other packages.
+  if let Some((bigquery, logs_table_id)) = bigquery.0.as_ref() {
+    if let Err(err) =
+      scrape_npm_not_found_requests(&db, bigquery, logs_table_id, &npm_url)
+        .await
+    {
+      error!("failed to scrape npm not found requests: {err}");
+    }
+  }
+
+  let progress = db
+    .get_npm_tarball_build_progress(NPM_TARBALL_REVISION as i32)
     .await?;
+  let missing = progress.total_versions
+    - progress.built_versions
+    - progress.claimed_versions;
+  info!(
+    revision = progress.revision,
+    total_versions = progress.total_versions,
+    built_versions = progress.built_versions,
+    claimed_versions = progress.claimed_versions,
+    requested_packages = progress.requested_packages,
+    "npm tarball build progress"
+  );
+  if missing <= 0 {
+    return Ok(());
+  }
 
-  let mut futs = stream::iter(missing_tarballs)
-    .map(|missing_tarball| {
-      let job = NpmTarballBuildJob {
-        scope: missing_tarball.0,
-        name: missing_tarball.1,
-        version: missing_tarball.2,
-      };
-      let body = serde_json::to_vec(&job).unwrap();
-      queue.task_buffer(None, Some(body.into()))
+  let workers = ((missing + NPM_TARBALL_BUILD_BATCH_SIZE - 1)
+    / NPM_TARBALL_BUILD_BATCH_SIZE)
+    .min(NPM_TARBALL_BUILD_MAX_WORKERS);
+
+  let mut futs = stream::iter(0..workers)
+    .map(|_| {
+      let task = NpmTarballBuildTask::Worker(NpmTarballBuildWorker {
+        batch_size: NPM_TARBALL_BUILD_BATCH_SIZE,
+      });
+      let body = serde_json::to_vec(&task).unwrap();
+      queue.enqueue(body.into())
     })
     .buffer_unordered(NPM_TARBALL_BUILD_ENQUEUE_PARALLELISM);
 
@@ -238,6 +590,189 @@ pub async fn npm_tarball_enqueue_handler(req: Request<Body>) -> ApiResult<()> {
   Ok(())
 }
 
+/// How far back to look for npm requests that resulted in a 404 on the first
+/// run, which matches the interval of the enqueue cron job. Later runs continue
+/// from where the previous run stopped, so every request is counted once.
+const NPM_NOT_FOUND_SCRAPE_INITIAL_WINDOW: chrono::Duration =
+  chrono::Duration::minutes(15);
+
+/// The longest time range that is scraped at once, when catching up after runs
+/// failed.
+const NPM_NOT_FOUND_SCRAPE_MAX_RANGE: chrono::Duration =
+  chrono::Duration::days(1);
+
+/// Requests from the last few minutes are not scraped yet, as they may not have
+/// arrived in BigQuery.
+const NPM_NOT_FOUND_SCRAPE_LAG: chrono::Duration = chrono::Duration::minutes(5);
+
+/// The time after which the recorded 404 count of a package is halved, so that
+/// packages that were requested a lot in the past do not keep their priority.
+const NPM_NOT_FOUND_HALF_LIFE: chrono::Duration = chrono::Duration::days(1);
+
+async fn scrape_npm_not_found_requests(
+  db: &Database,
+  bigquery: &gcp::BigQuery,
+  logs_table_id: &str,
+  npm_url: &Url,
+) -> Result<(), ApiError> {
+  let end_timestamp = chrono::Utc::now() - NPM_NOT_FOUND_SCRAPE_LAG;
+  let start_timestamp = db
+    .get_npm_not_found_scraped_until()
+    .await?
+    .unwrap_or(end_timestamp - NPM_NOT_FOUND_SCRAPE_INITIAL_WINDOW)
+    .max(end_timestamp - NPM_NOT_FOUND_SCRAPE_MAX_RANGE);
+  if start_timestamp >= end_timestamp {
+    return Ok(());
+  }
+
+  // Both requests for package manifests (`@jsr/scope__name`) and tarballs
+  // (`~/11/@jsr/scope__name/1.0.0.tgz`) are counted. BigQuery only allows one
+  // capturing group per `REGEXP_EXTRACT`, so the scope and the name are
+  // extracted with separate patterns.
+  let npm_root = regex::escape(npm_url.as_str());
+  let pattern = |scope: &str, name: &str| {
+    format!("{npm_root}(?:~/\\d+/)?@jsr/{scope}__{name}")
+  };
+  let string_param = |name: &str, value: String| {
+    json!({
+      "name": name,
+      "parameterType": {
+        "type": "STRING"
+      },
+      "parameterValue": {
+        "value": value
+      }
+    })
+  };
+
+  let params = vec![
+    json!({
+      "name": "start_timestamp",
+      "parameterType": {
+        "type": "TIMESTAMP"
+      },
+      "parameterValue": {
+        "value": bigquery_timestamp_serialization(start_timestamp)
+      }
+    }),
+    json!({
+      "name": "end_timestamp",
+      "parameterType": {
+        "type": "TIMESTAMP"
+      },
+      "parameterValue": {
+        "value": bigquery_timestamp_serialization(end_timestamp)
+      }
+    }),
+    string_param("scope_pattern", pattern("([a-z0-9-]+)", "(?:[a-z0-9-]+)")),
+    string_param("package_pattern", pattern("(?:[a-z0-9-]+)", "([a-z0-9-]+)")),
+    string_param(
+      "request_pattern",
+      pattern("(?:[a-z0-9-]+)", "(?:[a-z0-9-]+)"),
+    ),
+  ];
+
+  let query = format!(
+    r#"
+SELECT
+  REGEXP_EXTRACT(t.http_request.request_url, @scope_pattern) AS scope,
+  REGEXP_EXTRACT(t.http_request.request_url, @package_pattern) AS package,
+  COUNT(*) AS count,
+  MAX(t.timestamp) AS last_requested_at
+FROM
+  `{logs_table_id}` AS t
+WHERE
+  t.timestamp >= @start_timestamp
+  AND t.timestamp < @end_timestamp
+  AND t.log_id = "requests"
+  AND t.http_request.status = 404
+  AND REGEXP_CONTAINS(t.http_request.request_url, @request_pattern)
+GROUP BY
+  1,
+  2"#
+  );
+  let rows = run_bigquery_query(bigquery, &query, &params).await?;
+
+  let mut requests = Vec::with_capacity(rows.len());
+  for row in rows {
+    if let Some(request) = deserialize_npm_tarball_build_request_from_bigquery(
+      &row,
+    )
+    .ok_or_else(|| {
+      error!("Failed to deserialize row: {:?}", row);
+      ApiError::InternalServerError
+    })? {
+      requests.push(request);
+    }
+  }
+
+  let decay = npm_not_found_decay(end_timestamp - start_timestamp);
+  db.record_npm_not_found_requests(requests, end_timestamp, decay)
+    .await?;
+
+  Ok(())
+}
+
+/// The factor that 404 counts are multiplied with after `elapsed` time.
+fn npm_not_found_decay(elapsed: chrono::Duration) -> f64 {
+  let half_lives =
+    elapsed.num_seconds() as f64 / NPM_NOT_FOUND_HALF_LIFE.num_seconds() as f64;
+  0.5f64.powf(half_lives)
+}
+
+// Outer option: failed to deserialize because bigquery was invalid
+// Inner option: failed to deserialize because scope / package was not formatted correctly
+fn deserialize_npm_tarball_build_request_from_bigquery(
+  row: &serde_json::Value,
+) -> Option<Option<NpmTarballBuildRequest>> {
+  let f = row.get("f")?;
+  let Ok(scope) = ScopeName::new(f.get(0)?.get("v")?.as_str()?.to_owned())
+  else {
+    return Some(None);
+  };
+  let Ok(name) = PackageName::new(f.get(1)?.get("v")?.as_str()?.to_owned())
+  else {
+    return Some(None);
+  };
+  let count = f.get(2)?.get("v")?.as_str()?.parse().ok()?;
+  let last_requested_at_micros: i64 =
+    f.get(3)?.get("v")?.as_str()?.parse().ok()?;
+  let last_requested_at =
+    DateTime::from_timestamp_micros(last_requested_at_micros)?;
+  Some(Some(NpmTarballBuildRequest {
+    scope,
+    name,
+    count,
+    last_requested_at,
+  }))
+}
+
+fn bigquery_timestamp_serialization(timestamp: DateTime<Utc>) -> String {
+  timestamp.to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+}
+
+async fn run_bigquery_query(
+  bigquery: &gcp::BigQuery,
+  query: &str,
+  params: &[serde_json::Value],
+) -> Result<Vec<serde_json::Value>, ApiError> {
+  let res = bigquery.query(query, params).await?;
+  if !res.job_complete {
+    error!("BigQuery job did not complete, errors: {:?}", res.errors);
+    return Err(ApiError::InternalServerError);
+  }
+  let mut rows = res.rows;
+  let mut page_token = res.page_token;
+  while let Some(token) = page_token {
+    let page = bigquery
+      .get_query_results(&res.job_reference.job_id, &token)
+      .await?;
+    rows.extend(page.rows);
+    page_token = page.page_token;
+  }
+  Ok(rows)
+}
+
 #[instrument(name = "POST /tasks/scrape_download_counts", skip(req), err)]
 pub async fn scrape_download_counts_handler(
   req: Request<Body>,
@@ -248,6 +783,8 @@ pub async fn scrape_download_counts_handler(
     error!("BigQuery not configured");
     return Err(ApiError::InternalServerError);
   };
+  let registry_root = req.data::<RegistryUrl>().unwrap().0.to_string();
+  let npm_root = req.data::<NpmUrl>().unwrap().0.to_string();
 
   let time_window = req
     .query("intervalHrs")
@@ -259,13 +796,74 @@ pub async fn scrape_download_counts_handler(
       msg: "intervalHrs query param must be an integer".into(),
     })?;
 
-  let current_timestamp = chrono::Utc::now();
-  let start_timestamp =
-    current_timestamp - chrono::Duration::hours(time_window);
-
-  fn bigquery_timestamp_serialization(timestamp: DateTime<Utc>) -> String {
-    timestamp.to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+  let now = chrono::Utc::now();
+  let mut start =
+    download_ingestion_window_start(now - chrono::Duration::hours(time_window));
+  // Catch up on windows that were missed, for example because previous runs
+  // failed.
+  if let Some(complete_until) =
+    db.get_download_ingestion_complete_until().await?
+  {
+    start = start.min(complete_until);
   }
+  start = start.max(download_ingestion_window_start(
+    now - MAX_DOWNLOAD_INGESTION_RANGE,
+  ));
+
+  ingest_download_counts(
+    &db,
+    bigquery,
+    logs_table_id,
+    &registry_root,
+    &npm_root,
+    start,
+    now,
+    None,
+  )
+  .await?;
+
+  Ok(())
+}
+
+/// Download counts are ingested from the request logs in windows that match
+/// the 4 hour buckets of `version_download_counts_4h`. Ingesting a window
+/// replaces the counts of its bucket, so it is always ingested from its start.
+pub const DOWNLOAD_INGESTION_WINDOW: chrono::Duration =
+  chrono::Duration::hours(4);
+
+/// The longest time range that download counts are ingested for at once.
+pub const MAX_DOWNLOAD_INGESTION_RANGE: chrono::Duration =
+  chrono::Duration::days(7);
+
+/// Rounds a time down to the start of its download ingestion window. Windows
+/// start at multiples of 4 hours since the Unix epoch, like the buckets of
+/// `TIMESTAMP_BUCKET` in BigQuery.
+pub fn download_ingestion_window_start(time: DateTime<Utc>) -> DateTime<Utc> {
+  let window = DOWNLOAD_INGESTION_WINDOW.num_seconds();
+  DateTime::from_timestamp(time.timestamp().div_euclid(window) * window, 0)
+    .unwrap()
+}
+
+/// Ingests the download counts of the windows between `start`, which must be
+/// the start of a window, and `end` from the request logs, and records a
+/// checkpoint for each window.
+#[allow(clippy::too_many_arguments)]
+#[instrument(
+  name = "ingest_download_counts",
+  skip(db, bigquery, logs_table_id, registry_root, npm_root),
+  err
+)]
+pub async fn ingest_download_counts(
+  db: &Database,
+  bigquery: &gcp::BigQuery,
+  logs_table_id: &str,
+  registry_root: &str,
+  npm_root: &str,
+  start: DateTime<Utc>,
+  end: DateTime<Utc>,
+  replayed_by: Option<Uuid>,
+) -> Result<Vec<DownloadIngestionCheckpoint>, ApiError> {
+  debug_assert_eq!(start, download_ingestion_window_start(start));
 
   let params = vec![
     json!({
@@ -274,7 +872,7 @@ pub async fn scrape_download_counts_handler(
         "type": "TIMESTAMP"
       },
       "parameterValue": {
-        "value": bigquery_timestamp_serialization(start_timestamp)
+        "value": bigquery_timestamp_serialization(start)
       }
     }),
     json!({
@@ -283,12 +881,11 @@ pub async fn scrape_download_counts_handler(
         "type": "TIMESTAMP"
       },
       "parameterValue": {
-        "value": bigquery_timestamp_serialization(current_timestamp)
+        "value": bigquery_timestamp_serialization(end)
       }
     }),
   ];
 
-  let registry_root = req.data::<RegistryUrl>().unwrap().0.to_string();
   let jsr_meta_query = format!(
     r#"
 SELECT
@@ -306,10 +903,44 @@ FROM (
   FROM
     `{logs_table_id}` AS t2
   WHERE
-    t2.timestamp BETWEEN @start_timestamp
-    AND @end_timestamp
+    t2.timestamp >= @start_timestamp
+    AND t2.timestamp < @end_timestamp
+    AND t2.log_id = "requests"
+    AND REGEXP_CONTAINS(t2.http_request.request_url, '{registry_root}@(?:[^/]*?)/(?:[^/]*?)/(?:[^/]*?)_meta.json')
+    AND NOT {BROWSER_USER_AGENT_CONDITION} ) AS t1
+GROUP BY
+  1,
+  2,
+  3,
+  4
+ORDER BY
+  time_bucket,
+  scope,
+  package,
+  version"#
+  );
+
+  let npm_tgz_query = format!(
+    r#"
+SELECT
+  t1.time_bucket,
+  t1.scope,
+  t1.package,
+  t1.version,
+  COUNT(*) AS count
+FROM (
+  SELECT
+    TIMESTAMP_BUCKET(t2.timestamp, INTERVAL 4 HOUR) AS time_bucket,
+    REGEXP_EXTRACT(t2.http_request.request_url, '{npm_root}~/\\d+/@jsr/([^/]*?)__(?:[^/]*?)/(?:[^/]*?)\\.tgz') AS scope,
+    REGEXP_EXTRACT(t2.http_request.request_url, '{npm_root}~/\\d+/@jsr/(?:[^/]*?)__([^/]*?)/(?:[^/]*?)\\.tgz') AS package,
+    REGEXP_EXTRACT(t2.http_request.request_url, '{npm_root}~/\\d+/@jsr/(?:[^/]*?)__(?:[^/]*?)/([^/]*?)\\.tgz') AS version
+  FROM
+    `{logs_table_id}` AS t2
+  WHERE
+    t2.timestamp >= @start_timestamp
+    AND t2.timestamp < @end_timestamp
     AND t2.log_id = "requests"
-    AND REGEXP_CONTAINS(t2.http_request.request_url, '{registry_root}@(?:[^/]*?)/(?:[^/]*?)/(?:[^/]*?)_meta.json') ) AS t1
+    AND REGEXP_CONTAINS(t2.http_request.request_url, '{npm_root}~/\\d+/@jsr/(?:[^/]*?)__(?:[^/]*?)/(?:[^/]*?)\\.tgz') ) AS t1
 GROUP BY
   1,
   2,
@@ -321,156 +952,877 @@ ORDER BY
   package,
   version"#
   );
-  let jsr_meta_res = bigquery.query(&jsr_meta_query, &params).await?;
-  if !jsr_meta_res.job_complete {
+
+  // Browsers request version metadata and module files when someone views a
+  // package on the website or follows a link to a file. These are counted
+  // separately, so that they do not inflate the download counts.
+  let web_query = format!(
+    r#"
+SELECT
+  t1.time_bucket,
+  t1.scope,
+  t1.package,
+  t1.version,
+  COUNT(*) AS count
+FROM (
+  SELECT
+    TIMESTAMP_BUCKET(t2.timestamp, INTERVAL 4 HOUR) AS time_bucket,
+    REGEXP_EXTRACT(t2.http_request.request_url, '{registry_root}@([^/]*?)/(?:[^/]*?)/(?:[^/]*?)(?:/|_meta.json)') AS scope,
+    REGEXP_EXTRACT(t2.http_request.request_url, '{registry_root}@(?:[^/]*?)/([^/]*?)/(?:[^/]*?)(?:/|_meta.json)') AS package,
+    REGEXP_EXTRACT(t2.http_request.request_url, '{registry_root}@(?:[^/]*?)/(?:[^/]*?)/([^/]*?)(?:/|_meta.json)') AS version
+  FROM
+    `{logs_table_id}` AS t2
+  WHERE
+    t2.timestamp >= @start_timestamp
+    AND t2.timestamp < @end_timestamp
+    AND t2.log_id = "requests"
+    AND REGEXP_CONTAINS(t2.http_request.request_url, '{registry_root}@(?:[^/]*?)/(?:[^/]*?)/(?:[^/]*?)(?:/|_meta.json)')
+    AND {BROWSER_USER_AGENT_CONDITION} ) AS t1
+GROUP BY
+  1,
+  2,
+  3,
+  4
+ORDER BY
+  time_bucket,
+  scope,
+  package,
+  version"#
+  );
+  let mut downloads = HashMap::new();
+  for (query, kind) in [
+    (jsr_meta_query, DownloadKind::JsrMeta),
+    (npm_tgz_query, DownloadKind::NpmTgz),
+    (web_query, DownloadKind::Web),
+  ] {
+    let rows = run_bigquery_query(bigquery, &query, &params).await?;
+    for (time_bucket, count) in
+      insert_bigquery_download_entries(db, rows, kind).await?
+    {
+      *downloads.entry(time_bucket).or_insert(0) += count;
+    }
+  }
+
+  let mut checkpoints = vec![];
+  let mut window_start = start;
+  while window_start < end {
+    let window_end = (window_start + DOWNLOAD_INGESTION_WINDOW).min(end);
+    let checkpoint = db
+      .upsert_download_ingestion_checkpoint(
+        window_start,
+        window_end,
+        downloads.get(&window_start).copied().unwrap_or(0),
+        replayed_by,
+      )
+      .await?;
+    checkpoints.push(checkpoint);
+    window_start += DOWNLOAD_INGESTION_WINDOW;
+  }
+
+  info!(
+    windows = checkpoints.len(),
+    "ingested download counts from {start} to {end}"
+  );
+
+  Ok(checkpoints)
+}
+
+/// Matches requests made by web browsers, in the request logs BigQuery table.
+/// All common browsers send a user agent that starts with `Mozilla/`, while
+/// Deno, npm and other package managers do not.
+const BROWSER_USER_AGENT_CONDITION: &str =
+  "REGEXP_CONTAINS(IFNULL(t2.http_request.user_agent, ''), r'^Mozilla/')";
+
+/// Exports the download counts and publish events of the last `intervalHrs`
+/// hours to the configured analytics sinks.
+#[instrument(name = "POST /tasks/export_analytics", skip(req), err)]
+pub async fn export_analytics_handler(req: Request<Body>) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+  let sinks = req.data::<AnalyticsSinks>().unwrap();
+
+  let time_window = req
+    .query("intervalHrs")
+    .ok_or_else(|| ApiError::MalformedRequest {
+      msg: "intervalHrs query param is required".into(),
+    })?
+    .parse()
+    .map_err(|_| ApiError::MalformedRequest {
+      msg: "intervalHrs query param must be an integer".into(),
+    })?;
+
+  let end = chrono::Utc::now();
+  let start = end - chrono::Duration::hours(time_window);
+
+  analytics::export(sinks, db, start, end).await?;
+
+  Ok(())
+}
+
+/// Inserts the download counts of the given rows, and returns the sum of the
+/// counts per time bucket.
+async fn insert_bigquery_download_entries(
+  db: &Database,
+  rows: Vec<serde_json::Value>,
+  kind: DownloadKind,
+) -> Result<HashMap<DateTime<Utc>, i64>, ApiError> {
+  let mut entries = Vec::with_capacity(rows.len());
+  for row in rows {
+    if let Some(entry) = deserialize_version_download_count_from_bigquery(
+      &row, kind,
+    )
+    .ok_or_else(|| {
+      error!("Failed to deserialize row: {:?}", row);
+      ApiError::InternalServerError
+    })? {
+      entries.push(entry);
+    }
+  }
+
+  let mut downloads = HashMap::new();
+  for entry in &entries {
+    *downloads.entry(entry.time_bucket).or_insert(0) += entry.count;
+  }
+
+  db.insert_download_entries(entries).await?;
+
+  Ok(downloads)
+}
+
+// Outer option: failed to deserialize because bigquery was invalid
+// Inner option: failed to deserialize because scope / package / version was not formatted correctly
+fn deserialize_version_download_count_from_bigquery(
+  row: &serde_json::Value,
+  kind: DownloadKind,
+) -> Option<Option<VersionDownloadCount>> {
+  let f = row.get("f")?;
+  let time_bucket_micros: i64 = f.get(0)?.get("v")?.as_str()?.parse().ok()?;
+  let time_bucket = DateTime::from_timestamp_micros(time_bucket_micros)?;
+  let Ok(scope) = ScopeName::new(f.get(1)?.get("v")?.as_str()?.to_owned())
+  else {
+    return Some(None);
+  };
+  let Ok(package) = PackageName::new(f.get(2)?.get("v")?.as_str()?.to_owned())
+  else {
+    return Some(None);
+  };
+  let Ok(version) = Version::new(f.get(3)?.get("v")?.as_str()?) else {
+    return Some(None);
+  };
+  let count = f.get(4)?.get("v")?.as_str()?.parse().ok()?;
+  Some(Some(VersionDownloadCount {
+    time_bucket,
+    scope,
+    package,
+    version,
+    kind,
+    count,
+  }))
+}
+
+/// Processes requested exports of user data, and deletes expired ones.
+#[instrument(name = "POST /tasks/user_exports", skip(req), err)]
+pub async fn user_exports_handler(req: Request<Body>) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+  let buckets = req.data::<Buckets>().unwrap();
+  let email_sender = req.data::<Option<EmailSender>>().unwrap();
+  let registry_url = req.data::<RegistryUrl>().unwrap();
+
+  process_user_exports(db, buckets, email_sender.as_ref(), &registry_url.0)
+    .await?;
+
+  Ok(())
+}
+
+/// Publishing tasks that have not progressed for this long are reported as
+/// stuck by the consistency audit.
+const STUCK_PUBLISHING_TASK_THRESHOLD: chrono::Duration =
+  chrono::Duration::hours(1);
+
+/// Validates cross-table invariants of the database, to catch drift caused by
+/// partial failures. The findings are shown to admins through
+/// `GET /api/admin/consistency_audit`. This runs once a day.
+#[instrument(name = "POST /tasks/consistency_audit", skip(req), err)]
+pub async fn consistency_audit_handler(req: Request<Body>) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+
+  let (audit, findings) = db
+    .run_consistency_audit(Utc::now() - STUCK_PUBLISHING_TASK_THRESHOLD)
+    .await?;
+  let errors = findings
+    .iter()
+    .filter(|finding| finding.severity == AuditFindingSeverity::Error)
+    .count();
+  if errors > 0 {
     error!(
-      "BigQuery job did not complete, errors: {:?}",
-      jsr_meta_res.errors
+      "consistency audit {} found {errors} errors and {} warnings",
+      audit.id,
+      findings.len() - errors
+    );
+  } else {
+    info!(
+      "consistency audit {} found {} warnings",
+      audit.id,
+      findings.len()
+    );
+  }
+
+  Ok(())
+}
+
+/// Corrects the dependent counts of packages that drifted from the
+/// dependencies of their dependents. This runs once a day.
+#[instrument(
+  name = "POST /tasks/reconcile_dependent_counts",
+  skip(req),
+  err
+)]
+pub async fn reconcile_dependent_counts_handler(
+  req: Request<Body>,
+) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+
+  let corrected = db.reconcile_package_dependent_counts().await?;
+  if corrected > 0 {
+    warn!("corrected {corrected} package dependent counts");
+  }
+
+  Ok(())
+}
+
+/// Publishes the versions of scheduled publishing tasks whose `publish_at` has
+/// passed, by resetting them to pending and processing them again. This runs
+/// every minute.
+#[instrument(name = "POST /tasks/scheduled_publishes", skip(req), err)]
+pub async fn scheduled_publishes_handler(req: Request<Body>) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap().clone();
+  let publish_queue = req.data::<PublishQueue>().unwrap().0.clone();
+
+  let tasks = db.list_due_scheduled_publishing_tasks().await?;
+  for task in tasks {
+    db.update_publishing_task_status(
+      task.id,
+      PublishingTaskStatus::Scheduled,
+      PublishingTaskStatus::Pending,
+      None,
+    )
+    .await?;
+
+    if let Some(queue) = &publish_queue {
+      let body = serde_json::to_vec(&task.id).unwrap();
+      queue.enqueue(body.into()).await?;
+    } else {
+      publish::publish_task(
+        task.id,
+        req.data::<Buckets>().unwrap().clone(),
+        req.data::<RegistryUrl>().unwrap().0.clone(),
+        req.data::<NpmUrl>().unwrap().0.clone(),
+        req.data::<NpmIncludeYankedVersions>().unwrap().0,
+        *req.data::<AnalysisLimits>().unwrap(),
+        db.clone(),
+        req.data::<Option<OramaClient>>().unwrap().clone(),
+        req.data::<MetadataSigner>().unwrap().clone(),
+      )
+      .await?;
+    }
+    info!("released scheduled publishing task {}", task.id);
+  }
+
+  Ok(())
+}
+
+/// Deletes the tarballs and diagnostic logs of failed publishes, and the
+/// retained tarballs of versions deleted by staff, once their retention period
+/// has ended. This runs once a day.
+#[instrument(name = "POST /tasks/publish_artifacts_cleanup", skip(req), err)]
+pub async fn publish_artifacts_cleanup_handler(
+  req: Request<Body>,
+) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+  let buckets = req.data::<Buckets>().unwrap();
+
+  let ids = db.list_expired_publishing_task_artifacts().await?;
+  for id in &ids {
+    buckets
+      .publishing_bucket
+      .bucket
+      .delete(&gcs_tarball_path(*id))
+      .await?;
+    db.clear_publishing_task_artifacts(*id).await?;
+  }
+  info!("deleted the artifacts of {} failed publishes", ids.len());
+
+  let deleted_versions = db.list_expired_deleted_package_versions().await?;
+  for deleted in &deleted_versions {
+    buckets
+      .publishing_bucket
+      .bucket
+      .delete(&gcs_paths::deleted_version_tarball_path(
+        deleted.publishing_task_id,
+      ))
+      .await?;
+    db.purge_deleted_package_version(
+      &deleted.scope,
+      &deleted.name,
+      &deleted.version,
+    )
+    .await?;
+  }
+  info!(
+    "deleted the retained tarballs of {} deleted versions",
+    deleted_versions.len()
+  );
+
+  Ok(())
+}
+
+/// How long a single search index task keeps claiming new batches. The task
+/// runs every minute, so this is a bit shorter than that.
+const SEARCH_INDEX_TIME_BUDGET: std::time::Duration =
+  std::time::Duration::from_secs(50);
+/// How long a claimed package is reserved for a task. If the task does not
+/// finish indexing the package in this time, it is picked up by another task.
+const SEARCH_INDEX_CLAIM_DURATION: chrono::Duration =
+  chrono::Duration::minutes(5);
+const SEARCH_INDEX_BATCH_SIZE: i64 = 64;
+const SEARCH_INDEX_PARALLELISM: usize = 8;
+/// The delay before retrying a package that failed to be indexed, which is
+/// doubled after every failed attempt, up to the maximum.
+const SEARCH_INDEX_RETRY_DELAY: chrono::Duration =
+  chrono::Duration::seconds(30);
+const SEARCH_INDEX_MAX_RETRY_DELAY: chrono::Duration =
+  chrono::Duration::hours(1);
+
+/// Updates the search index entries of packages that changed. Changes are
+/// recorded in the search index outbox in the same transaction as the change
+/// itself, so the search index eventually reflects every change, even if
+/// Orama is unavailable for a while. Failed packages are retried with
+/// exponential backoff. This runs every minute.
+#[instrument(name = "POST /tasks/search_index", skip(req), err)]
+pub async fn search_index_handler(req: Request<Body>) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+  let orama_client = req.data::<Option<OramaClient>>().unwrap();
+
+  let started_at = std::time::Instant::now();
+  let (mut indexed, mut failed) = (0, 0);
+  while started_at.elapsed() < SEARCH_INDEX_TIME_BUDGET {
+    let updates = db
+      .claim_search_index_updates(
+        SEARCH_INDEX_BATCH_SIZE,
+        SEARCH_INDEX_CLAIM_DURATION,
+      )
+      .await?;
+    if updates.is_empty() {
+      break;
+    }
+
+    let results = stream::iter(updates)
+      .map(|update| async move {
+        let res = index_package(db, orama_client.as_ref(), &update).await;
+        (update, res)
+      })
+      .buffer_unordered(SEARCH_INDEX_PARALLELISM)
+      .collect::<Vec<_>>()
+      .await;
+
+    for (update, res) in results {
+      match res {
+        Ok(()) => {
+          db.complete_search_index_update(
+            &update.scope,
+            &update.name,
+            update.revision,
+          )
+          .await?;
+          indexed += 1;
+        }
+        Err(err) => {
+          error!(
+            "failed to index @{}/{} (attempt {}): {err:#}",
+            update.scope,
+            update.name,
+            update.attempts + 1
+          );
+          let retry_delay = (SEARCH_INDEX_RETRY_DELAY
+            * 2i32.pow(update.attempts.clamp(0, 16) as u32))
+          .min(SEARCH_INDEX_MAX_RETRY_DELAY);
+          db.fail_search_index_update(
+            &update.scope,
+            &update.name,
+            update.revision,
+            &format!("{err:#}"),
+            Utc::now() + retry_delay,
+          )
+          .await?;
+          failed += 1;
+        }
+      }
+    }
+  }
+  info!("indexed {indexed} packages, {failed} failed");
+
+  Ok(())
+}
+
+async fn index_package(
+  db: &Database,
+  orama_client: Option<&OramaClient>,
+  update: &SearchIndexUpdate,
+) -> Result<(), anyhow::Error> {
+  // Without a search index there is nothing to keep up to date.
+  let Some(orama_client) = orama_client else {
+    return Ok(());
+  };
+  match db.get_package(&update.scope, &update.name).await? {
+    Some((package, _, meta)) => {
+      orama_client.upsert_package(&package, &meta).await
+    }
+    None => orama_client.delete_package(&update.scope, &update.name).await,
+  }
+}
+
+/// Marks packages that have not seen a new version or a maintenance status
+/// update for a long time as possibly unmaintained. Maintainers are then asked
+/// to confirm the status in the package settings, it is never set
+/// automatically. This runs once a day.
+#[instrument(name = "POST /tasks/suggest_unmaintained", skip(req), err)]
+pub async fn suggest_unmaintained_handler(req: Request<Body>) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+  let after = req.data::<UnmaintainedSuggestionAfter>().unwrap();
+
+  let packages = db
+    .suggest_unmaintained_packages(Utc::now() - after.0)
+    .await?;
+  info!("suggested {} packages as unmaintained", packages.len());
+
+  Ok(())
+}
+
+/// Sends a digest of the packages with majorly outdated or yanked dependencies
+/// to the admins of every scope that opted into it. This runs once a week.
+#[instrument(name = "POST /tasks/outdated_dependencies_email", skip(req), err)]
+pub async fn outdated_dependencies_email_handler(
+  req: Request<Body>,
+) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+  let email_sender = req.data::<Option<EmailSender>>().unwrap();
+  let registry_url = req.data::<RegistryUrl>().unwrap();
+
+  let Some(email_sender) = email_sender else {
+    return Ok(());
+  };
+
+  for scope in db.list_scopes_with_outdated_dependencies_email().await? {
+    let packages = list_outdated_packages(db, &scope).await?;
+    if packages.is_empty() {
+      continue;
+    }
+
+    for (name, email) in db.list_scope_admin_emails(&scope).await? {
+      let email_args = EmailArgs::OutdatedDependencies {
+        name: Cow::Borrowed(&name),
+        scope: Cow::Borrowed(&scope),
+        packages: packages.clone(),
+        registry_url: Cow::Borrowed(registry_url.0.as_str()),
+        registry_name: Cow::Borrowed(&email_sender.from_name),
+        support_email: Cow::Borrowed(&email_sender.from),
+      };
+      // A failure to reach one admin should not prevent the others from
+      // getting the email.
+      if let Err(err) = email_sender.send(email, email_args).await {
+        error!(
+          "failed to send outdated dependencies email for @{scope}: {err:?}"
+        );
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Lists the packages in the scope whose latest version has dependencies that
+/// are at least one major version behind, or only satisfied by yanked
+/// versions.
+async fn list_outdated_packages(
+  db: &Database,
+  scope: &ScopeName,
+) -> Result<Vec<OutdatedPackage>, ApiError> {
+  let (_, packages) =
+    db.list_packages_by_scope(scope, false, 0, i64::MAX).await?;
+
+  let mut outdated_packages = vec![];
+  for (package, _, _) in packages {
+    let Some(latest_version) = package.latest_version else {
+      continue;
+    };
+    let Ok(version) = Version::new(&latest_version) else {
+      continue;
+    };
+
+    let mut dependencies: Vec<OutdatedDependency> = vec![];
+    for dep in dependency_freshness(db, scope, &package.name, &version).await? {
+      let reason = if dep.yanked {
+        "the highest version that satisfies the constraint has been yanked"
+          .to_owned()
+      } else {
+        match (dep.majors_behind, &dep.latest_version) {
+          (Some(majors_behind), Some(latest)) if majors_behind > 0 => format!(
+            "{majors_behind} major version{} behind the latest version {latest}",
+            if majors_behind == 1 { "" } else { "s" }
+          ),
+          _ => continue,
+        }
+      };
+      // The same dependency can be imported from multiple paths.
+      if dependencies
+        .iter()
+        .any(|d| d.name == dep.name && d.constraint == dep.constraint)
+      {
+        continue;
+      }
+      dependencies.push(OutdatedDependency {
+        name: dep.name,
+        constraint: dep.constraint,
+        reason,
+      });
+    }
+
+    if !dependencies.is_empty() {
+      outdated_packages.push(OutdatedPackage {
+        name: package.name.to_string(),
+        version: version.to_string(),
+        dependencies,
+      });
+    }
+  }
+
+  Ok(outdated_packages)
+}
+
+/// Only the links of this many of the most downloaded packages are checked.
+const LINK_CHECK_POPULAR_PACKAGES: i64 = 1000;
+/// How many packages have their links checked per run of the task.
+const LINK_CHECK_BATCH_SIZE: i64 = 20;
+/// How long after a check the links of a package are checked again.
+const LINK_CHECK_INTERVAL: chrono::Duration = chrono::Duration::days(7);
+/// At most this many links are checked per package.
+const LINK_CHECK_MAX_LINKS: usize = 100;
+const LINK_CHECK_PARALLELISM: usize = 8;
+
+/// Checks the external links in the README and docs of the latest version of
+/// popular packages, and records the broken ones so that maintainers can find
+/// them. This is low priority, so each run only checks a small batch of the
+/// packages that are due. This runs every hour.
+#[instrument(name = "POST /tasks/check_links", skip(req), err)]
+pub async fn check_links_handler(req: Request<Body>) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+  let buckets = req.data::<Buckets>().unwrap();
+
+  let packages = db
+    .list_packages_due_for_link_check(
+      LINK_CHECK_POPULAR_PACKAGES,
+      Utc::now() - LINK_CHECK_INTERVAL,
+      LINK_CHECK_BATCH_SIZE,
+    )
+    .await?;
+
+  let client = link_check::link_check_client();
+  for (scope, package) in packages {
+    let Some(version) = db
+      .get_latest_unyanked_version_for_package(&scope, &package)
+      .await?
+    else {
+      continue;
+    };
+
+    let mut links = IndexMap::new();
+    if let Some(readme_path) = &version.readme_path {
+      let gcs_path =
+        gcs_paths::file_path(&scope, &package, &version.version, readme_path);
+      if let Some(readme) =
+        buckets.modules_bucket.download(gcs_path.into()).await?
+      {
+        let readme = String::from_utf8_lossy(&readme);
+        for url in link_check::extract_external_links(&readme) {
+          links.entry(url).or_insert_with(|| readme_path.to_string());
+        }
+      }
+    }
+    let docs_path = gcs_paths::docs_v1_path(&scope, &package, &version.version);
+    if let Some(docs) = buckets.docs_bucket.download(docs_path.into()).await? {
+      let doc_nodes: DocNodesByUrl =
+        serde_json::from_slice(&docs).context("failed to parse doc nodes")?;
+      for node in doc_nodes.values().flatten() {
+        let Some(doc) = node.js_doc.doc.as_deref() else {
+          continue;
+        };
+        for url in link_check::extract_external_links(doc) {
+          links.entry(url).or_insert_with(|| "docs".to_owned());
+        }
+      }
+    }
+    links.truncate(LINK_CHECK_MAX_LINKS);
+
+    let checked_link_count = links.len() as i32;
+    let broken_links = stream::iter(links)
+      .map(|(url, source)| {
+        let client = &client;
+        async move {
+          let failure = link_check::check_link(client, &url).await?;
+          Some(BrokenLink {
+            url: url.to_string(),
+            source,
+            status: failure.status.map(i32::from),
+            error: failure.error,
+          })
+        }
+      })
+      .buffer_unordered(LINK_CHECK_PARALLELISM)
+      .filter_map(|link| async move { link })
+      .collect::<Vec<_>>()
+      .await;
+
+    info!(
+      "found {} broken links out of {checked_link_count} in @{scope}/{package}@{}",
+      broken_links.len(),
+      version.version
     );
-    return Err(ApiError::InternalServerError);
+    db.record_package_link_check(
+      &scope,
+      &package,
+      &version.version,
+      checked_link_count,
+      broken_links,
+    )
+    .await?;
   }
-  let mut jsr_meta_rows = jsr_meta_res.rows;
-  let mut page_token = jsr_meta_res.page_token;
-  while let Some(token) = page_token {
-    let res = bigquery
-      .get_query_results(&jsr_meta_res.job_reference.job_id, &token)
-      .await?;
-    jsr_meta_rows.extend(res.rows);
-    page_token = res.page_token;
+
+  Ok(())
+}
+
+/// How many days before a day its downloads are compared against.
+const DOWNLOAD_ANOMALY_BASELINE_DAYS: i64 = 28;
+/// Packages need at least this many days with downloads before a day to have
+/// anomalies detected on it, so that the first days of new packages are not
+/// reported as spikes.
+const DOWNLOAD_ANOMALY_MIN_BASELINE_DAYS: usize = 7;
+/// Spikes must reach, and cliffs must fall from, at least this many daily
+/// downloads. Below it, random noise is too large to tell anything.
+const DOWNLOAD_ANOMALY_MIN_DOWNLOADS: i64 = 100;
+/// How many standard deviations a day must be away from the baseline mean.
+const DOWNLOAD_ANOMALY_Z_SCORE: f64 = 4.0;
+
+/// Detects download spikes and cliffs on the last complete day, records them,
+/// and emails the admins of scopes that opted into it. A day is anomalous if
+/// its downloads are more than [`DOWNLOAD_ANOMALY_Z_SCORE`] standard
+/// deviations away from the mean of the days before. This runs once a day.
+#[instrument(name = "POST /tasks/download_anomalies", skip(req), err)]
+pub async fn download_anomalies_handler(req: Request<Body>) -> ApiResult<()> {
+  let db = req.data::<Database>().unwrap();
+  let email_sender = req.data::<Option<EmailSender>>().unwrap();
+  let registry_url = req.data::<RegistryUrl>().unwrap();
+
+  let day = (Utc::now().date_naive() - chrono::Duration::days(1))
+    .and_time(chrono::NaiveTime::MIN)
+    .and_utc();
+  let day_end = day + chrono::Duration::days(1);
+  // A day whose downloads were not ingested completely yet would look like a
+  // cliff.
+  let complete_until = db.get_download_ingestion_complete_until().await?;
+  if !matches!(complete_until, Some(until) if until >= day_end) {
+    info!("downloads of {day} are not ingested yet, skipping");
+    return Ok(());
   }
 
-  insert_bigquery_download_entries(&db, jsr_meta_rows, DownloadKind::JsrMeta)
+  let start = day - chrono::Duration::days(DOWNLOAD_ANOMALY_BASELINE_DAYS);
+  let downloads = db
+    .list_package_daily_downloads(
+      start,
+      day_end,
+      DOWNLOAD_ANOMALY_MIN_DOWNLOADS,
+    )
     .await?;
 
-  let npm_root = req.data::<NpmUrl>().unwrap().0.to_string();
-  let npm_tgz_query = format!(
-    r#"
-SELECT
-  t1.time_bucket,
-  t1.scope,
-  t1.package,
-  t1.version,
-  COUNT(*) AS count
-FROM (
-  SELECT
-    TIMESTAMP_BUCKET(t2.timestamp, INTERVAL 4 HOUR) AS time_bucket,
-    REGEXP_EXTRACT(t2.http_request.request_url, '{npm_root}~/\\d+/@jsr/([^/]*?)__(?:[^/]*?)/(?:[^/]*?)\\.tgz') AS scope,
-    REGEXP_EXTRACT(t2.http_request.request_url, '{npm_root}~/\\d+/@jsr/(?:[^/]*?)__([^/]*?)/(?:[^/]*?)\\.tgz') AS package,
-    REGEXP_EXTRACT(t2.http_request.request_url, '{npm_root}~/\\d+/@jsr/(?:[^/]*?)__(?:[^/]*?)/([^/]*?)\\.tgz') AS version
-  FROM
-    `{logs_table_id}` AS t2
-  WHERE
-    t2.timestamp BETWEEN @start_timestamp
-    AND @end_timestamp
-    AND t2.log_id = "requests"
-    AND REGEXP_CONTAINS(t2.http_request.request_url, '{npm_root}~/\\d+/@jsr/(?:[^/]*?)__(?:[^/]*?)/(?:[^/]*?)\\.tgz') ) AS t1
-GROUP BY
-  1,
-  2,
-  3,
-  4
-ORDER BY
-  time_bucket,
-  scope,
-  package,
-  version"#
-  );
-  let npm_tgz_res = bigquery.query(&npm_tgz_query, &params).await?;
-  if !npm_tgz_res.job_complete {
-    error!(
-      "BigQuery job did not complete, errors: {:?}",
-      npm_tgz_res.errors
-    );
-    return Err(ApiError::InternalServerError);
+  let mut series: HashMap<(ScopeName, PackageName), Vec<i64>> = HashMap::new();
+  for entry in downloads {
+    let counts = series
+      .entry((entry.scope, entry.package))
+      .or_insert_with(|| vec![0; DOWNLOAD_ANOMALY_BASELINE_DAYS as usize + 1]);
+    let index = (entry.day - start).num_days() as usize;
+    if let Some(count) = counts.get_mut(index) {
+      *count = entry.count;
+    }
   }
-  let mut npm_tgz_rows = npm_tgz_res.rows;
-  let mut page_token = npm_tgz_res.page_token;
-  while let Some(token) = page_token {
-    let res = bigquery
-      .get_query_results(&npm_tgz_res.job_reference.job_id, &token)
-      .await?;
-    npm_tgz_rows.extend(res.rows);
-    page_token = res.page_token;
+
+  let mut anomalies = vec![];
+  for ((scope, package), counts) in series {
+    let (count, baseline) = counts.split_last().unwrap();
+    // The baseline starts on the first day with downloads.
+    let first_download = baseline.iter().position(|count| *count > 0);
+    let Some(baseline) = first_download.map(|i| &baseline[i..]) else {
+      continue;
+    };
+    if baseline.len() < DOWNLOAD_ANOMALY_MIN_BASELINE_DAYS {
+      continue;
+    }
+    if let Some(score) = detect_download_anomaly(baseline, *count) {
+      anomalies.push(NewDownloadAnomaly {
+        scope,
+        package,
+        day,
+        kind: score.kind,
+        count: *count,
+        mean: score.mean,
+        stddev: score.stddev,
+        z_score: score.z_score,
+      });
+    }
   }
 
-  insert_bigquery_download_entries(&db, npm_tgz_rows, DownloadKind::NpmTgz)
-    .await?;
+  let anomalies = db.insert_download_anomalies(anomalies).await?;
+  info!("found {} download anomalies on {day}", anomalies.len());
 
-  Ok(())
-}
+  let Some(email_sender) = email_sender else {
+    return Ok(());
+  };
 
-async fn insert_bigquery_download_entries(
-  db: &Database,
-  rows: Vec<serde_json::Value>,
-  kind: DownloadKind,
-) -> Result<(), ApiError> {
-  let mut entries = Vec::with_capacity(rows.len());
-  for row in rows {
-    if let Some(entry) = deserialize_version_download_count_from_bigquery(
-      &row, kind,
-    )
-    .ok_or_else(|| {
-      error!("Failed to deserialize row: {:?}", row);
-      ApiError::InternalServerError
-    })? {
-      entries.push(entry);
+  let mut scope_emails: HashMap<ScopeName, Vec<(String, String)>> =
+    HashMap::new();
+  for anomaly in anomalies {
+    if !scope_emails.contains_key(&anomaly.scope) {
+      let emails = match db.get_scope(&anomaly.scope).await? {
+        Some(scope) if scope.download_anomaly_email => {
+          db.list_scope_admin_emails(&anomaly.scope).await?
+        }
+        _ => vec![],
+      };
+      scope_emails.insert(anomaly.scope.clone(), emails);
     }
-  }
 
-  db.insert_download_entries(entries).await?;
+    let kind = match anomaly.kind {
+      DownloadAnomalyKind::Spike => "spike",
+      DownloadAnomalyKind::Cliff => "cliff",
+    };
+    let day = anomaly.day.format("%Y-%m-%d").to_string();
+    for (name, email) in &scope_emails[&anomaly.scope] {
+      let email_args = EmailArgs::DownloadAnomaly {
+        name: Cow::Borrowed(name),
+        scope: Cow::Borrowed(&anomaly.scope),
+        package: Cow::Borrowed(&anomaly.package),
+        kind: Cow::Borrowed(kind),
+        day: Cow::Borrowed(&day),
+        count: anomaly.count,
+        mean: anomaly.mean.round() as i64,
+        registry_url: Cow::Borrowed(registry_url.0.as_str()),
+        registry_name: Cow::Borrowed(&email_sender.from_name),
+        support_email: Cow::Borrowed(&email_sender.from),
+      };
+      if let Err(err) = email_sender.send(email.clone(), email_args).await {
+        error!(
+          "failed to send download anomaly email for @{}/{}: {err:?}",
+          anomaly.scope, anomaly.package
+        );
+      }
+    }
+  }
 
   Ok(())
 }
 
-// Outer option: failed to deserialize because bigquery was invalid
-// Inner option: failed to deserialize because scope / package / version was not formatted correctly
-fn deserialize_version_download_count_from_bigquery(
-  row: &serde_json::Value,
-  kind: DownloadKind,
-) -> Option<Option<VersionDownloadCount>> {
-  let f = row.get("f")?;
-  let time_bucket_micros: i64 = f.get(0)?.get("v")?.as_str()?.parse().ok()?;
-  let time_bucket = DateTime::from_timestamp_micros(time_bucket_micros)?;
-  let Ok(scope) = ScopeName::new(f.get(1)?.get("v")?.as_str()?.to_owned())
-  else {
-    return Some(None);
-  };
-  let Ok(package) = PackageName::new(f.get(2)?.get("v")?.as_str()?.to_owned())
-  else {
-    return Some(None);
-  };
-  let Ok(version) = Version::new(f.get(3)?.get("v")?.as_str()?) else {
-    return Some(None);
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DownloadAnomalyScore {
+  kind: DownloadAnomalyKind,
+  mean: f64,
+  stddev: f64,
+  z_score: f64,
+}
+
+/// Scores the downloads of a day against the daily downloads of the days
+/// before it. The standard deviation is at least the square root of the mean,
+/// the noise of a Poisson process, so that packages with very steady
+/// downloads are not flagged for small changes.
+fn detect_download_anomaly(
+  baseline: &[i64],
+  count: i64,
+) -> Option<DownloadAnomalyScore> {
+  if baseline.is_empty() {
+    return None;
+  }
+  let n = baseline.len() as f64;
+  let mean = baseline.iter().sum::<i64>() as f64 / n;
+  let variance = baseline
+    .iter()
+    .map(|count| (*count as f64 - mean).powi(2))
+    .sum::<f64>()
+    / n;
+  let stddev = variance.sqrt().max(mean.sqrt()).max(1.0);
+  let z_score = (count as f64 - mean) / stddev;
+
+  let kind = if z_score >= DOWNLOAD_ANOMALY_Z_SCORE
+    && count >= DOWNLOAD_ANOMALY_MIN_DOWNLOADS
+    && count as f64 >= 2.0 * mean
+  {
+    DownloadAnomalyKind::Spike
+  } else if z_score <= -DOWNLOAD_ANOMALY_Z_SCORE
+    && mean >= DOWNLOAD_ANOMALY_MIN_DOWNLOADS as f64
+    && count as f64 <= mean / 2.0
+  {
+    DownloadAnomalyKind::Cliff
+  } else {
+    return None;
   };
-  let count = f.get(4)?.get("v")?.as_str()?.parse().ok()?;
-  Some(Some(VersionDownloadCount {
-    time_bucket,
-    scope,
-    package,
-    version,
+
+  Some(DownloadAnomalyScore {
     kind,
-    count,
-  }))
+    mean,
+    stddev,
+    z_score,
+  })
 }
 
 #[cfg(test)]
 mod tests {
   use chrono::DateTime;
   use chrono::Utc;
+  use hyper::StatusCode;
   use serde_json::json;
   use uuid::Uuid;
 
+  use crate::api::ApiDownloadAnomaly;
+  use crate::api::ApiPackageLinkCheck;
+  use crate::db::DownloadAnomalyKind;
   use crate::db::DownloadKind;
   use crate::db::EphemeralDatabase;
   use crate::db::ExportsMap;
   use crate::db::NewPackageVersion;
   use crate::db::PackageVersionMeta;
+  use crate::db::PublishingTaskStatus;
+  use crate::db::VersionDownloadCount;
+  use crate::emails::email_content;
+  use crate::emails::EmailArgs;
   use crate::gcp::BigQueryQueryResult;
   use crate::ids::PackageName;
   use crate::ids::ScopeName;
   use crate::ids::Version;
+  use crate::npm::NPM_TARBALL_REVISION;
+  use crate::publish::tests::create_mock_tarball;
+  use crate::publish::tests::process_tarball_setup;
+  use crate::publish::tests::process_tarball_setup2;
+  use crate::testing::ApiResultExt;
+  use crate::testing::TestSetup;
 
   use super::deserialize_version_download_count_from_bigquery;
+  use super::detect_download_anomaly;
+  use super::download_ingestion_window_start;
+  use super::list_outdated_packages;
+  use super::npm_not_found_decay;
+  use super::NPM_NOT_FOUND_HALF_LIFE;
+
+  #[test]
+  fn test_npm_not_found_decay() {
+    assert_eq!(npm_not_found_decay(chrono::Duration::zero()), 1.0);
+    assert_eq!(npm_not_found_decay(NPM_NOT_FOUND_HALF_LIFE), 0.5);
+    assert_eq!(npm_not_found_decay(NPM_NOT_FOUND_HALF_LIFE * 2), 0.25);
+  }
 
   #[test]
   fn test_deserialize_version_download_count_from_bigquery() {
@@ -505,6 +1857,66 @@ mod tests {
     assert_eq!(data.count, 154);
   }
 
+  #[test]
+  fn test_download_ingestion_window_start() {
+    let start = |s: &str| {
+      download_ingestion_window_start(s.parse::<DateTime<Utc>>().unwrap())
+        .to_rfc3339()
+    };
+    assert_eq!(start("2024-07-16T12:00:00Z"), "2024-07-16T12:00:00+00:00");
+    assert_eq!(start("2024-07-16T15:59:59Z"), "2024-07-16T12:00:00+00:00");
+    assert_eq!(start("2024-07-16T01:30:00Z"), "2024-07-16T00:00:00+00:00");
+    assert_eq!(start("1969-12-31T23:00:00Z"), "1969-12-31T20:00:00+00:00");
+  }
+
+  #[test]
+  fn test_detect_download_anomaly() {
+    let baseline = [
+      200, 210, 190, 205, 195, 220, 180, 200, 215, 185, 200, 190, 210, 200,
+    ];
+    assert_eq!(detect_download_anomaly(&baseline, 230), None);
+    assert_eq!(detect_download_anomaly(&baseline, 150), None);
+
+    let spike = detect_download_anomaly(&baseline, 2000).unwrap();
+    assert_eq!(spike.kind, DownloadAnomalyKind::Spike);
+    assert_eq!(spike.mean, 200.0);
+    assert!(spike.z_score > 4.0, "{spike:?}");
+
+    let cliff = detect_download_anomaly(&baseline, 10).unwrap();
+    assert_eq!(cliff.kind, DownloadAnomalyKind::Cliff);
+    assert!(cliff.z_score < -4.0, "{cliff:?}");
+
+    // Small packages are too noisy to tell spikes apart.
+    assert_eq!(detect_download_anomaly(&[1, 2, 1, 0, 1, 2, 1], 40), None);
+    // Packages that never had downloads can not drop.
+    assert_eq!(detect_download_anomaly(&[50; 14], 0), None);
+    // A steady package is not flagged for noise, but for doubling.
+    assert_eq!(detect_download_anomaly(&[1000; 14], 1100), None);
+    assert_eq!(
+      detect_download_anomaly(&[1000; 14], 2000).unwrap().kind,
+      DownloadAnomalyKind::Spike
+    );
+
+    let scope = ScopeName::try_from("scope").unwrap();
+    let package = PackageName::try_from("foo").unwrap();
+    let content = email_content(EmailArgs::DownloadAnomaly {
+      name: "Alice".into(),
+      scope: std::borrow::Cow::Borrowed(&scope),
+      package: std::borrow::Cow::Borrowed(&package),
+      kind: "spike".into(),
+      day: "2024-11-16".into(),
+      count: 2000,
+      mean: 200,
+      registry_url: "https://jsr.io/".into(),
+      registry_name: "JSR".into(),
+      support_email: "help@jsr.io".into(),
+    })
+    .unwrap();
+    assert!(content.text.contains("@scope/foo"));
+    assert!(content.text.contains("2000"));
+    assert!(content.html.contains("https://jsr.io/@scope/foo"));
+  }
+
   #[test]
   fn test_deserialize_malformed_version_download_count_from_bigquery() {
     let value = json!({
@@ -560,6 +1972,10 @@ mod tests {
       name: &fs,
       version: &v0_215_0,
       exports: &ExportsMap::mock(),
+      bin: &Default::default(),
+      engines: &Default::default(),
+      keywords: &[],
+      license: None,
       user_id: None,
       readme_path: None,
       uses_npm: false,
@@ -572,6 +1988,10 @@ mod tests {
       name: &flag,
       version: &v1_0_0,
       exports: &ExportsMap::mock(),
+      bin: &Default::default(),
+      engines: &Default::default(),
+      keywords: &[],
+      license: None,
       user_id: None,
       readme_path: None,
       uses_npm: false,
@@ -684,5 +2104,380 @@ mod tests {
       "2024-07-16T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
     );
     assert_eq!(downloads[0].count, 238);
+
+    // web traffic is a separate series, and is not counted as downloads
+    let res: BigQueryQueryResult = serde_json::from_str(include_str!(
+      "../testdata/bigquery_query_results.json"
+    ))
+    .unwrap();
+    super::insert_bigquery_download_entries(&db, res.rows, DownloadKind::Web)
+      .await
+      .unwrap();
+
+    let downloads = db
+      .get_package_version_downloads_24h(
+        &luca,
+        &flag,
+        &v1_0_0,
+        "2024-06-01T00:00:00Z".parse().unwrap(),
+        "2024-07-31T00:00:00Z".parse().unwrap(),
+      )
+      .await
+      .unwrap();
+    assert_eq!(downloads.len(), 2, "{:?}", downloads);
+    assert!(downloads
+      .iter()
+      .any(|point| point.kind == DownloadKind::Web && point.count == 238));
+
+    let version = db
+      .get_package_version(&luca, &flag, &v1_0_0)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(version.lifetime_download_count, 238);
+  }
+
+  #[tokio::test]
+  async fn test_list_outdated_packages() {
+    let t = TestSetup::new().await;
+    let scope = ScopeName::try_from("scope").unwrap();
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+    let task = process_tarball_setup2(
+      &t,
+      create_mock_tarball("depends_on_ok"),
+      &PackageName::try_from("bar").unwrap(),
+      &Version::try_from("1.2.3").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    let packages = list_outdated_packages(&t.db(), &scope).await.unwrap();
+    assert!(packages.is_empty(), "{packages:?}");
+
+    let task = process_tarball_setup2(
+      &t,
+      create_mock_tarball("major_bump"),
+      &PackageName::try_from("foo").unwrap(),
+      &Version::try_from("2.0.0").unwrap(),
+      false,
+    )
+    .await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    let packages = list_outdated_packages(&t.db(), &scope).await.unwrap();
+    assert_eq!(packages.len(), 1, "{packages:?}");
+    assert_eq!(packages[0].name, "bar");
+    assert_eq!(packages[0].version, "1.2.3");
+    assert_eq!(packages[0].dependencies.len(), 1);
+    assert_eq!(packages[0].dependencies[0].name, "@scope/foo");
+    assert_eq!(
+      packages[0].dependencies[0].reason,
+      "1 major version behind the latest version 2.0.0"
+    );
+
+    let content = email_content(EmailArgs::OutdatedDependencies {
+      name: "Alice".into(),
+      scope: std::borrow::Cow::Borrowed(&scope),
+      packages,
+      registry_url: "https://jsr.io/".into(),
+      registry_name: "JSR".into(),
+      support_email: "help@jsr.io".into(),
+    })
+    .unwrap();
+    assert!(content.text.contains("@scope/bar@1.2.3"));
+    assert!(content.text.contains(
+      "@scope/foo@1: 1 major version behind the latest version 2.0.0"
+    ));
+    assert!(content
+      .html
+      .contains("https://jsr.io/@scope/bar@1.2.3/dependencies"));
+  }
+
+  #[tokio::test]
+  async fn search_index_outbox() {
+    let mut t = TestSetup::new().await;
+    let scope = ScopeName::try_from("scope").unwrap();
+    let name = PackageName::try_from("foo").unwrap();
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    // Publishing the package queued it for indexing.
+    let backlogs = t
+      .db()
+      .get_registry_backlogs(NPM_TARBALL_REVISION as i32)
+      .await
+      .unwrap();
+    assert_eq!(backlogs.pending_search_index_updates, 1);
+
+    let resp = t.http().post("/tasks/search_index").call().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let backlogs = t
+      .db()
+      .get_registry_backlogs(NPM_TARBALL_REVISION as i32)
+      .await
+      .unwrap();
+    assert_eq!(backlogs.pending_search_index_updates, 0);
+
+    t.db()
+      .update_package_description(&scope, &name, "first")
+      .await
+      .unwrap();
+    let updates = t
+      .db()
+      .claim_search_index_updates(10, chrono::Duration::minutes(5))
+      .await
+      .unwrap();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].name, name);
+
+    // Claimed packages are not handed out twice.
+    let claimed = t
+      .db()
+      .claim_search_index_updates(10, chrono::Duration::minutes(5))
+      .await
+      .unwrap();
+    assert!(claimed.is_empty());
+
+    // A change while the package is being indexed is not lost.
+    t.db()
+      .update_package_description(&scope, &name, "second")
+      .await
+      .unwrap();
+    t.db()
+      .complete_search_index_update(&scope, &name, updates[0].revision)
+      .await
+      .unwrap();
+    let updates = t
+      .db()
+      .claim_search_index_updates(10, chrono::Duration::minutes(5))
+      .await
+      .unwrap();
+    assert_eq!(updates.len(), 1);
+
+    // Failed packages are retried later.
+    t.db()
+      .fail_search_index_update(
+        &scope,
+        &name,
+        updates[0].revision,
+        "unavailable",
+        Utc::now() - chrono::Duration::seconds(1),
+      )
+      .await
+      .unwrap();
+    let updates = t
+      .db()
+      .claim_search_index_updates(10, chrono::Duration::minutes(5))
+      .await
+      .unwrap();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].attempts, 1);
+  }
+
+  #[tokio::test]
+  async fn download_anomalies() {
+    let mut t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    let today = Utc::now().date_naive();
+    let entry = |days_ago: i64, kind, count| VersionDownloadCount {
+      scope: ScopeName::try_from("scope").unwrap(),
+      package: PackageName::try_from("foo").unwrap(),
+      version: Version::try_from("1.2.3").unwrap(),
+      time_bucket: (today - chrono::Duration::days(days_ago))
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc(),
+      kind,
+      count,
+    };
+    let mut entries = (2..=15)
+      .map(|days_ago| entry(days_ago, DownloadKind::JsrMeta, 200))
+      .collect::<Vec<_>>();
+    entries.push(entry(1, DownloadKind::JsrMeta, 2000));
+    // Web traffic is not counted.
+    entries.push(entry(3, DownloadKind::Web, 5000));
+    t.db().insert_download_entries(entries).await.unwrap();
+
+    let path = "/api/scopes/scope/packages/foo/download_anomalies";
+
+    // Nothing is detected until the day is ingested completely.
+    let resp = t
+      .http()
+      .post("/tasks/download_anomalies")
+      .call()
+      .await
+      .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let anomalies = t
+      .http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Vec<ApiDownloadAnomaly>>()
+      .await;
+    assert!(anomalies.is_empty(), "{anomalies:?}");
+
+    let end = today.and_time(chrono::NaiveTime::MIN).and_utc();
+    t.db()
+      .upsert_download_ingestion_checkpoint(
+        end - chrono::Duration::hours(4),
+        end,
+        0,
+        None,
+      )
+      .await
+      .unwrap();
+    for _ in 0..2 {
+      let resp = t
+        .http()
+        .post("/tasks/download_anomalies")
+        .call()
+        .await
+        .unwrap();
+      assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    let anomalies = t
+      .http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<Vec<ApiDownloadAnomaly>>()
+      .await;
+    assert_eq!(anomalies.len(), 1, "{anomalies:?}");
+    assert_eq!(anomalies[0].kind, DownloadAnomalyKind::Spike);
+    assert_eq!(anomalies[0].count, 2000);
+    assert_eq!(anomalies[0].mean, 200.0);
+    assert_eq!(anomalies[0].day, end - chrono::Duration::days(1));
+  }
+
+  #[tokio::test]
+  async fn check_links() {
+    let mut t = TestSetup::new().await;
+
+    let task =
+      process_tarball_setup(&t, create_mock_tarball("broken_links")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    let path = "/api/scopes/scope/packages/foo/broken_links";
+    t.unauthed_http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::UNAUTHORIZED, "missingAuthentication")
+      .await;
+    t.http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_err_code(StatusCode::NOT_FOUND, "packageLinkCheckNotFound")
+      .await;
+
+    let resp = t.http().post("/tasks/check_links").call().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let check = t
+      .http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPackageLinkCheck>()
+      .await;
+    assert_eq!(check.version, Version::try_from("1.2.3").unwrap());
+    assert_eq!(check.checked_link_count, 3);
+    let broken_links = check
+      .broken_links
+      .iter()
+      .map(|link| (link.url.as_str(), link.source.as_str(), link.status))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      broken_links,
+      vec![
+        ("https://broken.invalid/guide", "/README.md", None),
+        ("https://also-broken.invalid/docs", "docs", None),
+      ]
+    );
+
+    // Packages that were checked recently are not checked again.
+    let resp = t.http().post("/tasks/check_links").call().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let check2 = t
+      .http()
+      .get(path)
+      .call()
+      .await
+      .unwrap()
+      .expect_ok::<ApiPackageLinkCheck>()
+      .await;
+    assert_eq!(check2.checked_at, check.checked_at);
+  }
+
+  #[tokio::test]
+  async fn npm_tarball_rebuild_uses_published_runtime_compat() {
+    let t = TestSetup::new().await;
+
+    let task = process_tarball_setup(&t, create_mock_tarball("ok")).await;
+    assert_eq!(task.status, PublishingTaskStatus::Success, "{task:?}");
+
+    let scope = ScopeName::try_from("scope").unwrap();
+    let name = PackageName::try_from("foo").unwrap();
+    let version = Version::try_from("1.2.3").unwrap();
+    let revision = NPM_TARBALL_REVISION as i32;
+    let db = t.db();
+    let package_version = db
+      .get_package_version(&scope, &name, &version)
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(package_version.meta.runtime_compat.is_some());
+    let published = db
+      .get_npm_tarball(&scope, &name, &version, revision)
+      .await
+      .unwrap()
+      .unwrap();
+
+    // Changing the runtime compatibility of the package does not change the
+    // tarballs of versions that were already published.
+    db.update_package_runtime_compat(
+      &scope,
+      &name,
+      &crate::db::RuntimeCompat {
+        browser: Some(false),
+        node: Some(true),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+    db.delete_npm_tarball(&scope, &name, &version, revision)
+      .await
+      .unwrap();
+    super::build_npm_tarball(
+      &db,
+      &t.buckets(),
+      &t.registry_url(),
+      &scope,
+      &name,
+      &version,
+    )
+    .await
+    .unwrap();
+    let rebuilt = db
+      .get_npm_tarball(&scope, &name, &version, revision)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(rebuilt.sha512, published.sha512);
   }
 }
@@ -0,0 +1,478 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+//! A harness that runs the API in process, against an ephemeral database and
+//! a fake Google Cloud Storage server. It is used by the tests of this crate.
+use crate::analysis::AnalysisLimits;
+use crate::analytics::AnalyticsSinks;
+use crate::auth::GithubOauth2Client;
+use crate::buckets::BucketWithQueue;
+use crate::buckets::Buckets;
+use crate::db::EphemeralDatabase;
+use crate::db::NewGithubIdentity;
+use crate::db::{Database, NewUser, User};
+use crate::errors_internal::ApiErrorStruct;
+use crate::gcp::FakeGcsTester;
+use crate::provenance::ProvenancePolicies;
+use crate::rate_limit::RateLimits;
+use crate::rate_limit::RequestCosts;
+use crate::signing::MetadataSigner;
+use crate::sitemap::RobotsConfig;
+use crate::trust::TrustPolicy;
+use crate::typescript::TypeScriptVersion;
+use crate::ApiError;
+use crate::MainRouterOptions;
+use hyper::http::HeaderName;
+use hyper::http::HeaderValue;
+use hyper::service::Service;
+use hyper::Body;
+use hyper::HeaderMap;
+use hyper::Response;
+use hyper::StatusCode;
+use routerify::RequestService;
+use routerify::RouteError;
+use serde::de::DeserializeOwned;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use url::Url;
+
+#[derive(Debug)]
+pub struct TestUser {
+  pub user: User,
+  pub token: String,
+  pub github_name: String,
+}
+
+pub struct TestSetup {
+  pub ephemeral_database: EphemeralDatabase,
+  #[allow(dead_code)]
+  pub gcs: FakeGcsTester,
+  pub buckets: Buckets,
+  pub metadata_signer: MetadataSigner,
+  pub user1: TestUser,
+  pub user2: TestUser,
+  pub user3: TestUser,
+  pub staff_user: TestUser,
+  #[allow(dead_code)]
+  pub scope: crate::db::Scope,
+  #[allow(dead_code)]
+  pub github_oauth2_client: GithubOauth2Client,
+  pub service: RequestService<Body, ApiError>,
+}
+
+impl TestSetup {
+  pub async fn new() -> Self {
+    let ephemeral_database = EphemeralDatabase::create().await;
+    let db = ephemeral_database.database.clone().unwrap();
+    let gcs = FakeGcsTester::new().await;
+    let publishing_bucket = gcs.create_bucket("publishing").await;
+    let modules_bucket = gcs.create_bucket("modules").await;
+    let docs_bucket = gcs.create_bucket("docs").await;
+    let npm_bucket = gcs.create_bucket("npm").await;
+    let artifacts_bucket = gcs.create_bucket("artifacts").await;
+    let buckets = Buckets {
+      publishing_bucket: BucketWithQueue::new(publishing_bucket),
+      modules_bucket: BucketWithQueue::new(modules_bucket),
+      docs_bucket: BucketWithQueue::new(docs_bucket),
+      npm_bucket: BucketWithQueue::new(npm_bucket),
+      artifacts_bucket: BucketWithQueue::new(artifacts_bucket),
+    };
+    let github_oauth2_client = GithubOauth2Client::new(
+      oauth2::ClientId::new("".to_string()),
+      Some(oauth2::ClientSecret::new("".to_string())),
+      oauth2::AuthUrl::new(
+        "https://github.com/login/oauth/authorize".to_string(),
+      )
+      .unwrap(),
+      Some(
+        oauth2::TokenUrl::new(
+          "https://github.com/login/oauth/access_token".to_string(),
+        )
+        .unwrap(),
+      ),
+    );
+
+    let user1 = Self::create_user(
+      &db,
+      NewUser {
+        name: "User 1",
+        email: None,
+        avatar_url: "https://avatars0.githubusercontent.com/u/952?v=4",
+        github_id: Some(101),
+        is_blocked: false,
+        is_staff: false,
+      },
+      "ry",
+    )
+    .await;
+
+    let user2 = Self::create_user(
+      &db,
+      NewUser {
+        name: "User 2",
+        email: None,
+        avatar_url: "",
+        github_id: Some(102),
+        is_blocked: false,
+        is_staff: false,
+      },
+      "lucacasonato",
+    )
+    .await;
+
+    let user3 = Self::create_user(
+      &db,
+      NewUser {
+        name: "User 3",
+        email: None,
+        avatar_url: "",
+        github_id: Some(103),
+        is_blocked: false,
+        is_staff: false,
+      },
+      "crowlkats",
+    )
+    .await;
+
+    let staff_user = Self::create_user(
+      &db,
+      NewUser {
+        name: "User 4",
+        email: None,
+        avatar_url: "",
+        github_id: Some(104),
+        is_blocked: false,
+        is_staff: true,
+      },
+      "bartlomieju",
+    )
+    .await;
+
+    let scope_name = "scope".try_into().unwrap();
+
+    db.create_scope(&scope_name, user1.user.id).await.unwrap();
+    let (scope, _, _) = db
+      .update_scope_limits(&scope_name, Some(250), Some(200), Some(1000))
+      .await
+      .unwrap();
+
+    db.add_bad_word_for_test("somebadword").await.unwrap();
+
+    let metadata_signer =
+      MetadataSigner::new(Some(TEST_SIGNING_KEY.parse().unwrap()), vec![]);
+
+    let router = crate::main_router(MainRouterOptions {
+      database: db,
+      buckets: buckets.clone(),
+      github_client: github_oauth2_client.clone(),
+      orama_client: None,
+      email_sender: None,
+      registry_url: "http://jsr-tests.test".parse().unwrap(),
+      npm_url: "http://npm.jsr-tests.test".parse().unwrap(),
+      npm_include_yanked_versions: false,
+      publish_queue: None,           // no queue locally
+      npm_tarball_build_queue: None, // no queue locally
+      logs_bigquery_table: None,     // no bigquery locally
+      analytics_sinks: AnalyticsSinks::default(),
+      typescript_versions: vec![
+        TypeScriptVersion::new(4, 7),
+        TypeScriptVersion::new(5, 0),
+        TypeScriptVersion::new(5, 6),
+      ],
+      analysis_limits: AnalysisLimits::default(),
+      provenance_policies: ProvenancePolicies::default(),
+      robots: RobotsConfig::default(),
+      metadata_signer: metadata_signer.clone(),
+      rate_limits: RateLimits::default(),
+      request_costs: RequestCosts::default(),
+      trust_policy: TrustPolicy::unrestricted(),
+      unmaintained_suggestion_after: chrono::Duration::days(730),
+      expose_api: true,   // api enabled
+      expose_tasks: true, // task endpoints enabled
+    });
+
+    let service = routerify::RequestServiceBuilder::new(router)
+      .unwrap()
+      .build(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8080)));
+
+    Self {
+      ephemeral_database,
+      gcs,
+      buckets,
+      metadata_signer,
+      user1,
+      user2,
+      user3,
+      staff_user,
+      scope,
+      github_oauth2_client,
+      service,
+    }
+  }
+
+  async fn create_user<'a>(
+    db: &Database,
+    new_user: NewUser<'a>,
+    github_name: &str,
+  ) -> TestUser {
+    db.upsert_github_identity(NewGithubIdentity {
+      github_id: new_user.github_id.unwrap(),
+      access_token: None,
+      access_token_expires_at: None,
+      refresh_token: None,
+      refresh_token_expires_at: None,
+    })
+    .await
+    .unwrap();
+
+    let user = db.insert_user(new_user).await.unwrap();
+
+    let token = crate::token::create_token(
+      db,
+      user.id,
+      crate::db::TokenType::Web,
+      None,
+      Some(chrono::Utc::now() + chrono::Duration::try_days(7).unwrap()),
+      None,
+    )
+    .await
+    .unwrap();
+
+    TestUser {
+      user,
+      token,
+      github_name: github_name.to_string(),
+    }
+  }
+
+  pub fn db(&self) -> Database {
+    self.ephemeral_database.database.clone().unwrap()
+  }
+
+  pub fn buckets(&self) -> Buckets {
+    self.buckets.clone()
+  }
+
+  pub fn metadata_signer(&self) -> MetadataSigner {
+    self.metadata_signer.clone()
+  }
+
+  pub fn registry_url(&self) -> Url {
+    Url::parse("http://jsr-tests.test").unwrap()
+  }
+
+  pub fn npm_url(&self) -> Url {
+    Url::parse("http://npm.jsr-tests.test").unwrap()
+  }
+
+  pub fn http(&mut self) -> TestHttpClient {
+    TestHttpClient {
+      service: &mut self.service,
+      auth: Some(&self.user1.token),
+    }
+  }
+
+  pub fn unauthed_http(&mut self) -> TestHttpClient {
+    TestHttpClient {
+      service: &mut self.service,
+      auth: None,
+    }
+  }
+}
+
+pub struct TestHttpClient<'s, 't> {
+  service: &'s mut RequestService<Body, ApiError>,
+  auth: Option<&'t str>,
+}
+
+impl<'s> TestHttpClient<'s, '_> {
+  pub fn get<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
+    TestHttpCall::new(self.service, "GET", uri.as_ref().to_string(), self.auth)
+  }
+  pub fn post<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
+    TestHttpCall::new(self.service, "POST", uri.as_ref().to_string(), self.auth)
+  }
+  pub fn delete<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
+    TestHttpCall::new(
+      self.service,
+      "DELETE",
+      uri.as_ref().to_string(),
+      self.auth,
+    )
+  }
+  #[allow(dead_code)]
+  pub fn put<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
+    TestHttpCall::new(self.service, "PUT", uri.as_ref().to_string(), self.auth)
+  }
+  pub fn patch<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
+    TestHttpCall::new(
+      self.service,
+      "PATCH",
+      uri.as_ref().to_string(),
+      self.auth,
+    )
+  }
+}
+
+pub struct TestHttpCall<'s> {
+  service: &'s mut RequestService<Body, ApiError>,
+  method: &'static str,
+  uri: String,
+  body: Body,
+  headers: HeaderMap,
+  token: Option<&'s str>,
+  sudo: bool,
+}
+
+impl<'s> TestHttpCall<'s> {
+  fn new(
+    service: &'s mut RequestService<Body, ApiError>,
+    method: &'static str,
+    uri: String,
+    token: Option<&'s str>,
+  ) -> Self {
+    Self {
+      service,
+      method,
+      uri,
+      body: Body::empty(),
+      headers: HeaderMap::default(),
+      token,
+      sudo: false,
+    }
+  }
+
+  /// overwrite the default user token for authentication
+  pub fn token(mut self, token: Option<&'s str>) -> Self {
+    self.token = token;
+    self
+  }
+
+  /// overwrite the default user token for authentication
+  pub fn sudo(mut self, sudo: bool) -> Self {
+    self.sudo = sudo;
+    self
+  }
+
+  pub fn body_json(mut self, body: serde_json::Value) -> Self {
+    self.body = body.to_string().into();
+    self
+  }
+
+  pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+    let prev = self.headers.insert(name.clone(), value);
+    assert!(prev.is_none(), "{} already present in the header map", name);
+    self
+  }
+
+  pub fn gzip(self) -> Self {
+    self.header(hyper::header::CONTENT_ENCODING, "gzip".try_into().unwrap())
+  }
+
+  pub fn body(mut self, body: Body) -> Self {
+    self.body = body;
+    self
+  }
+
+  pub async fn call(self) -> Result<hyper::Response<Body>, RouteError> {
+    let mut req = hyper::Request::builder().method(self.method).uri(self.uri);
+
+    for (key, value) in self.headers.into_iter() {
+      req = req.header(key.unwrap(), value)
+    }
+    if let Some(token) = self.token {
+      req =
+        req.header(hyper::header::AUTHORIZATION, &format!("Bearer {}", token));
+    }
+
+    if self.sudo {
+      req = req
+        .header(hyper::header::HeaderName::from_static("x-jsr-sudo"), "true");
+    }
+
+    let req = req.body(self.body).unwrap();
+    self.service.call(req).await
+  }
+}
+
+#[async_trait::async_trait]
+pub trait ApiResultExt {
+  #[track_caller]
+  async fn expect_json<T: DeserializeOwned>(&mut self, status: StatusCode)
+    -> T;
+
+  #[track_caller]
+  async fn expect_ok<T: DeserializeOwned>(&mut self) -> T {
+    self.expect_json(StatusCode::OK).await
+  }
+
+  #[track_caller]
+  async fn expect_ok_no_content(&mut self);
+
+  #[track_caller]
+  async fn expect_err(&mut self, status: StatusCode) -> ApiErrorStruct {
+    self.expect_json(status).await
+  }
+
+  #[track_caller]
+  async fn expect_err_code(
+    &mut self,
+    status: StatusCode,
+    code: &str,
+  ) -> ApiErrorStruct {
+    let err: ApiErrorStruct = self.expect_json(status).await;
+    assert_eq!(err.code, code, "{}", err.message);
+    err
+  }
+}
+
+#[async_trait::async_trait]
+impl ApiResultExt for Response<Body> {
+  #[track_caller]
+  async fn expect_json<T: DeserializeOwned>(
+    &mut self,
+    status: StatusCode,
+  ) -> T {
+    let bytes = hyper::body::to_bytes(self.body_mut()).await.unwrap();
+    let body = std::str::from_utf8(&bytes).expect("invalid utf8");
+    assert_eq!(
+      self.status(),
+      status,
+      "expected {} response, got {} with body: {}",
+      status,
+      self.status(),
+      body
+    );
+    serde_json::from_str(body).unwrap()
+  }
+
+  #[track_caller]
+  async fn expect_ok_no_content(&mut self) {
+    let bytes = hyper::body::to_bytes(self.body_mut()).await.unwrap();
+    let body = std::str::from_utf8(&bytes).expect("invalid utf8");
+    assert_eq!(
+      self.status(),
+      StatusCode::NO_CONTENT,
+      "expected {} response, got {} with body: {}",
+      StatusCode::NO_CONTENT,
+      self.status(),
+      body
+    );
+  }
+}
+
+/// The metadata signing key of the [`TestSetup`].
+pub const TEST_SIGNING_KEY: &str = "test-key:MC4CAQAwBQYDK2VwBCIEIEwIjIzi8+fSGolja7leI02Z2S9c96ePvfDj6XrnFBsO:bp37CigcdJIIKL9cj91XNGUa7DbKx/tyCFHxDSxHZZo=";
+
+#[cfg(test)]
+mod tests {
+  use super::TestSetup;
+
+  #[tokio::test]
+  async fn harness_expectations() {
+    let t = TestSetup::new().await;
+    // This permissions set up is important - the scope member management tests
+    // rely on it.
+    assert!(!t.user1.user.is_staff);
+    assert!(!t.user2.user.is_staff);
+    assert!(!t.user3.user.is_staff);
+    assert!(t.staff_user.user.is_staff);
+  }
+}
@@ -1,6 +1,7 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 // Copyright Deno Land Inc. All Rights Reserved. Proprietary and confidential.
 
+use crate::api::ApiError;
 use crate::db::*;
 use chrono::DateTime;
 use chrono::Utc;
@@ -36,12 +37,78 @@ pub async fn create_token(
   Ok(token_string)
 }
 
+/// Trims a token description to a single line, and checks that it is not
+/// empty or too long.
+pub fn normalize_description(description: &str) -> Result<String, ApiError> {
+  let description = description.trim().replace('\n', " ").replace('\r', "");
+  if description.is_empty() {
+    return Err(ApiError::MalformedRequest {
+      msg: "description must not be empty".into(),
+    });
+  }
+  if description.len() > 250 {
+    return Err(ApiError::MalformedRequest {
+      msg: "description must not be longer than 250 characters".into(),
+    });
+  }
+  if description.contains(|c: char| c.is_control()) {
+    return Err(ApiError::MalformedRequest {
+      msg: "description must not contain control characters".into(),
+    });
+  }
+  Ok(description)
+}
+
+/// Checks that permissions requested for a personal or device token can be
+/// granted to it. Tokens for a list of packages are scope tokens, which are
+/// created and validated by scope admins through the scope tokens endpoint.
+pub fn check_user_token_permissions(
+  permissions: Option<&Permissions>,
+) -> Result<(), ApiError> {
+  let Some(permissions) = permissions else {
+    return Ok(());
+  };
+  let has_packages = permissions.0.iter().any(|permission| {
+    matches!(
+      permission,
+      Permission::PackagePublish(PackagePublishPermission::Packages { .. })
+    )
+  });
+  if has_packages {
+    return Err(ApiError::MalformedRequest {
+      msg:
+        "permissions for a list of packages are only supported for scope tokens"
+          .into(),
+    });
+  }
+  Ok(())
+}
+
 const MAX_DECODED_LEN: usize = 111;
 const BASE62: &[u8] =
   b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
 
 pub fn generate_token(token_type: TokenType) -> String {
-  let prefix = token_type.prefix();
+  generate_prefixed(token_type.prefix())
+}
+
+/// Generate a public API key. API keys look like tokens, but use their own
+/// prefix so they can not be confused with them.
+pub fn generate_api_key() -> String {
+  generate_prefixed(API_KEY_PREFIX)
+}
+
+const API_KEY_PREFIX: &str = "jsrk";
+
+/// Generate a short-lived publish token for a GitHub Actions workflow that
+/// exchanged its OIDC token.
+pub fn generate_oidc_publish_token() -> String {
+  generate_prefixed(OIDC_PUBLISH_TOKEN_PREFIX)
+}
+
+pub const OIDC_PUBLISH_TOKEN_PREFIX: &str = "jsro";
+
+fn generate_prefixed(prefix: &str) -> String {
   let mut random_string = String::new();
   for _ in 0..29 {
     random_string.push(BASE62[rand::random::<usize>() % 62] as char);
@@ -80,6 +147,8 @@ pub fn encode_base62(mut num: u32) -> String {
 #[cfg(test)]
 mod tests {
   use super::encode_base62;
+  use super::generate_api_key;
+  use super::generate_oidc_publish_token;
   use super::generate_token;
   use super::TokenType;
 
@@ -99,4 +168,18 @@ mod tests {
     assert!(token.starts_with("jsrw_"));
     assert!(!token.contains(' '));
   }
+
+  #[test]
+  fn test_generate_api_key() {
+    let key = generate_api_key();
+    assert_eq!(key.len(), 40);
+    assert!(key.starts_with("jsrk_"));
+  }
+
+  #[test]
+  fn test_generate_oidc_publish_token() {
+    let token = generate_oidc_publish_token();
+    assert_eq!(token.len(), 40);
+    assert!(token.starts_with("jsro_"));
+  }
 }
@@ -0,0 +1,67 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+
+/// The curated topics that packages can be browsed by. Each topic has a list
+/// of aliases, which are keywords that are normalized to the topic when a
+/// package is published.
+const TOPICS: &[(&str, &[&str])] = &[
+  ("ai", &["llm", "machine-learning", "ml"]),
+  ("auth", &["authentication", "authorization", "oauth"]),
+  ("cli", &["command-line", "terminal"]),
+  ("crypto", &["cryptography", "encryption"]),
+  ("database", &["db", "sql", "orm"]),
+  ("date", &["time", "datetime"]),
+  ("encoding", &["base64", "hex"]),
+  ("fs", &["filesystem", "file-system"]),
+  ("http", &["server", "fetch", "web-server"]),
+  ("json", &[]),
+  ("logging", &["log", "logger"]),
+  ("markdown", &["md"]),
+  ("math", &["mathematics"]),
+  ("parser", &["parsing"]),
+  ("react", &["preact"]),
+  ("streams", &["stream"]),
+  ("testing", &["test", "tests", "assert", "mock"]),
+  ("types", &["typescript-types"]),
+  ("utils", &["util", "utilities", "utility"]),
+  ("validation", &["schema", "validator"]),
+  ("web-framework", &["framework"]),
+  ("websocket", &["websockets", "ws"]),
+];
+
+/// Returns the curated topic for a keyword, if the keyword is a topic or one
+/// of its aliases.
+pub fn topic_for_keyword(keyword: &str) -> Option<&'static str> {
+  TOPICS.iter().find_map(|(topic, aliases)| {
+    (*topic == keyword || aliases.contains(&keyword)).then_some(*topic)
+  })
+}
+
+pub fn is_topic(topic: &str) -> bool {
+  TOPICS.iter().any(|(t, _)| *t == topic)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::is_topic;
+  use super::topic_for_keyword;
+  use super::TOPICS;
+
+  #[test]
+  fn topics() {
+    assert_eq!(topic_for_keyword("testing"), Some("testing"));
+    assert_eq!(topic_for_keyword("test"), Some("testing"));
+    assert_eq!(topic_for_keyword("sql"), Some("database"));
+    assert_eq!(topic_for_keyword("foo"), None);
+    assert!(is_topic("cli"));
+    assert!(!is_topic("terminal"));
+
+    // Aliases must not be ambiguous.
+    let mut seen = std::collections::HashSet::new();
+    for (topic, aliases) in TOPICS {
+      assert!(seen.insert(*topic), "duplicate topic {topic}");
+      for alias in *aliases {
+        assert!(seen.insert(*alias), "duplicate alias {alias}");
+      }
+    }
+  }
+}
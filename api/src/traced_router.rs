@@ -14,12 +14,14 @@ use std::future::Future;
 use std::future::Ready;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Context;
 use std::task::Poll;
 
 use futures::FutureExt;
 use hyper::body::HttpBody;
 use hyper::header::HeaderValue;
+use hyper::header::HOST;
 use hyper::server::conn::AddrStream;
 use hyper::service::Service;
 use hyper::Request;
@@ -59,6 +61,23 @@ impl<
       is_internal,
     })
   }
+
+  /// Serves requests for the given hostnames with a different router, for
+  /// example one that is backed by a separate namespace.
+  pub fn with_host_router(
+    mut self,
+    hosts: Vec<String>,
+    router: Router<B, E>,
+  ) -> routerify::Result<TracedRouterService<B, E>> {
+    self.builder.host_builders.push((
+      hosts
+        .into_iter()
+        .map(|host| host.to_ascii_lowercase())
+        .collect(),
+      RequestServiceBuilder::new(router)?,
+    ));
+    Ok(self)
+  }
 }
 
 impl<
@@ -84,11 +103,27 @@ impl<
   }
 }
 
+#[allow(clippy::type_complexity)]
 pub struct TracedRequestService<B, E> {
   request_service: RequestService<B, E>,
+  host_services: Vec<(Arc<[String]>, RequestService<B, E>)>,
   is_internal: bool,
 }
 
+/// Returns the hostname that a request was sent to, without the port.
+fn request_host<B>(req: &Request<B>) -> Option<String> {
+  let host = req
+    .headers()
+    .get(HOST)
+    .and_then(|host| host.to_str().ok())
+    .or_else(|| req.uri().host())?;
+  let host = match host.rsplit_once(':') {
+    Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+    _ => host,
+  };
+  Some(host.to_ascii_lowercase())
+}
+
 // Here to please clippy.
 type PinBox<T> = Pin<Box<T>>;
 
@@ -139,7 +174,18 @@ impl<
       });
     }
 
-    let fut = self.request_service.call(req).then(|res| async move {
+    let host = request_host(&req);
+    let request_service = host
+      .and_then(|host| {
+        self
+          .host_services
+          .iter_mut()
+          .find(|(hosts, _)| hosts.contains(&host))
+          .map(|(_, service)| service)
+      })
+      .unwrap_or(&mut self.request_service);
+
+    let fut = request_service.call(req).then(|res| async move {
       match res {
         Ok(mut resp) => {
           let status = resp.status();
@@ -166,8 +212,10 @@ impl<
 }
 
 #[derive(Debug)]
+#[allow(clippy::type_complexity)]
 pub struct TracedRequestServiceBuilder<B, E> {
   builder: RequestServiceBuilder<B, E>,
+  host_builders: Vec<(Arc<[String]>, RequestServiceBuilder<B, E>)>,
 }
 
 impl<
@@ -178,7 +226,10 @@ impl<
   pub fn new(router: Router<B, E>) -> routerify::Result<Self> {
     let builder = RequestServiceBuilder::new(router)?;
 
-    Ok(Self { builder })
+    Ok(Self {
+      builder,
+      host_builders: vec![],
+    })
   }
 
   pub fn build(
@@ -187,8 +238,14 @@ impl<
     is_internal: bool,
   ) -> TracedRequestService<B, E> {
     let request_service = self.builder.build(remote_addr);
+    let host_services = self
+      .host_builders
+      .iter_mut()
+      .map(|(hosts, builder)| (hosts.clone(), builder.build(remote_addr)))
+      .collect();
     TracedRequestService {
       request_service,
+      host_services,
       is_internal,
     }
   }
@@ -0,0 +1,245 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+//! Progressive trust for new accounts.
+//!
+//! Every account has a trust level, which is derived from the age of the
+//! account, whether it has an email address (GitHub only shares verified
+//! ones), whether it published a version with provenance, and whether staff
+//! flagged it. Accounts at lower levels can create fewer scopes and packages,
+//! and publish less often. The limits relax as the account gains trust, so a
+//! campaign of freshly created accounts can not flood the registry.
+//!
+//! The limits of each level are configured in the trust policy file. For
+//! example:
+//!
+//! ```json
+//! {
+//!   "establishedAfterDays": 7,
+//!   "trustedAfterDays": 30,
+//!   "new": { "scopes": 1, "newPackagesPerDay": 3, "publishAttemptsPerDay": 20 },
+//!   "established": { "scopes": 2, "newPackagesPerDay": 10, "publishAttemptsPerDay": 100 }
+//! }
+//! ```
+
+use std::path::Path;
+
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::db::UserTrustSignals;
+
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum TrustLevel {
+  New,
+  Established,
+  Trusted,
+}
+
+/// The limits of a trust level. `None` means that only the limits that apply
+/// to every account are enforced.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TrustLimits {
+  /// The most scopes the account can create. This never raises the scope
+  /// limit of the account.
+  #[serde(default)]
+  pub scopes: Option<u32>,
+  /// New packages per day, in scopes that the account created.
+  #[serde(default)]
+  pub new_packages_per_day: Option<u32>,
+  #[serde(default)]
+  pub publish_attempts_per_day: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TrustPolicy {
+  /// Accounts that are at least this old, and have an email address, become
+  /// established. Publishing with provenance makes an account established
+  /// regardless of its age.
+  pub established_after_days: u32,
+  /// Accounts that are at least this old, and have an email address or
+  /// published with provenance, become trusted.
+  pub trusted_after_days: u32,
+  #[serde(default)]
+  pub new: TrustLimits,
+  #[serde(default)]
+  pub established: TrustLimits,
+}
+
+impl Default for TrustPolicy {
+  fn default() -> Self {
+    Self {
+      established_after_days: 7,
+      trusted_after_days: 30,
+      new: TrustLimits {
+        scopes: Some(1),
+        new_packages_per_day: Some(3),
+        publish_attempts_per_day: Some(20),
+      },
+      established: TrustLimits {
+        scopes: Some(2),
+        new_packages_per_day: Some(10),
+        publish_attempts_per_day: Some(100),
+      },
+    }
+  }
+}
+
+impl TrustPolicy {
+  /// A policy without any limits. Trust levels are still evaluated.
+  #[cfg(test)]
+  pub fn unrestricted() -> Self {
+    Self {
+      established_after_days: 0,
+      trusted_after_days: 0,
+      new: TrustLimits::default(),
+      established: TrustLimits::default(),
+    }
+  }
+
+  pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+    let file = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&file)?)
+  }
+
+  pub fn level(&self, signals: &UserTrustSignals) -> TrustLevel {
+    self.level_at(signals, Utc::now())
+  }
+
+  fn level_at(
+    &self,
+    signals: &UserTrustSignals,
+    now: DateTime<Utc>,
+  ) -> TrustLevel {
+    if signals.is_staff {
+      return TrustLevel::Trusted;
+    }
+    if signals.moderation_flags > 0 {
+      return TrustLevel::New;
+    }
+    let age_days = (now - signals.created_at).num_days();
+    if age_days >= self.trusted_after_days as i64
+      && (signals.has_email || signals.has_provenance_publish)
+    {
+      TrustLevel::Trusted
+    } else if signals.has_provenance_publish
+      || (age_days >= self.established_after_days as i64 && signals.has_email)
+    {
+      TrustLevel::Established
+    } else {
+      TrustLevel::New
+    }
+  }
+
+  pub fn limits(&self, level: TrustLevel) -> TrustLimits {
+    match level {
+      TrustLevel::New => self.new,
+      TrustLevel::Established => self.established,
+      TrustLevel::Trusted => TrustLimits::default(),
+    }
+  }
+
+  /// The most scopes that the account can create, given the scope limit that
+  /// staff set for it.
+  pub fn scope_limit(&self, level: TrustLevel, scope_limit: i32) -> i32 {
+    match self.limits(level).scopes {
+      Some(scopes) => scope_limit.min(scopes.try_into().unwrap_or(i32::MAX)),
+      None => scope_limit,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn signals(now: DateTime<Utc>, age_days: i64) -> UserTrustSignals {
+    UserTrustSignals {
+      created_at: now - chrono::Duration::days(age_days),
+      is_staff: false,
+      has_email: true,
+      has_provenance_publish: false,
+      moderation_flags: 0,
+      new_packages_per_day: 0,
+      publish_attempts_per_day: 0,
+    }
+  }
+
+  #[test]
+  fn trust_levels() {
+    let policy = TrustPolicy::default();
+    let now = Utc::now();
+
+    assert_eq!(policy.level_at(&signals(now, 0), now), TrustLevel::New);
+    assert_eq!(
+      policy.level_at(&signals(now, 7), now),
+      TrustLevel::Established
+    );
+    assert_eq!(policy.level_at(&signals(now, 30), now), TrustLevel::Trusted);
+
+    // without an email address, accounts only gain trust with provenance
+    let no_email = UserTrustSignals {
+      has_email: false,
+      ..signals(now, 365)
+    };
+    assert_eq!(policy.level_at(&no_email, now), TrustLevel::New);
+    let provenance = UserTrustSignals {
+      has_provenance_publish: true,
+      ..no_email.clone()
+    };
+    assert_eq!(policy.level_at(&provenance, now), TrustLevel::Trusted);
+    let new_provenance = UserTrustSignals {
+      has_provenance_publish: true,
+      ..signals(now, 0)
+    };
+    assert_eq!(
+      policy.level_at(&new_provenance, now),
+      TrustLevel::Established
+    );
+
+    // flagged accounts are treated as new
+    let flagged = UserTrustSignals {
+      moderation_flags: 1,
+      ..signals(now, 365)
+    };
+    assert_eq!(policy.level_at(&flagged, now), TrustLevel::New);
+
+    let staff = UserTrustSignals {
+      is_staff: true,
+      ..signals(now, 0)
+    };
+    assert_eq!(policy.level_at(&staff, now), TrustLevel::Trusted);
+  }
+
+  #[test]
+  fn trust_scope_limit() {
+    let policy = TrustPolicy::default();
+    assert_eq!(policy.scope_limit(TrustLevel::New, 3), 1);
+    assert_eq!(policy.scope_limit(TrustLevel::Established, 3), 2);
+    assert_eq!(policy.scope_limit(TrustLevel::Trusted, 3), 3);
+    assert_eq!(policy.scope_limit(TrustLevel::Established, 1), 1);
+
+    let policy = TrustPolicy::unrestricted();
+    assert_eq!(policy.scope_limit(TrustLevel::New, 3), 3);
+  }
+
+  #[test]
+  fn trust_policy_from_json() {
+    let policy: TrustPolicy = serde_json::from_str(
+      r#"{
+        "establishedAfterDays": 3,
+        "trustedAfterDays": 14,
+        "new": { "scopes": 1 }
+      }"#,
+    )
+    .unwrap();
+    assert_eq!(policy.limits(TrustLevel::New).scopes, Some(1));
+    assert_eq!(policy.limits(TrustLevel::New).new_packages_per_day, None);
+    assert_eq!(policy.limits(TrustLevel::Established).scopes, None);
+  }
+}
@@ -0,0 +1,270 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use deno_ast::swc::ast;
+use deno_ast::swc::visit::Visit;
+use deno_ast::swc::visit::VisitWith;
+use deno_graph::ModuleGraph;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A TypeScript release, identified by its major and minor version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TypeScriptVersion {
+  pub major: u32,
+  pub minor: u32,
+}
+
+impl TypeScriptVersion {
+  pub const fn new(major: u32, minor: u32) -> Self {
+    Self { major, minor }
+  }
+}
+
+impl std::fmt::Display for TypeScriptVersion {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}.{}", self.major, self.minor)
+  }
+}
+
+impl FromStr for TypeScriptVersion {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid =
+      || anyhow::anyhow!("Invalid TypeScript version '{s}', expected 'X.Y'");
+    let (major, minor) = s.trim().split_once('.').ok_or_else(invalid)?;
+    Ok(Self {
+      major: major.parse().map_err(|_| invalid())?,
+      minor: minor.parse().map_err(|_| invalid())?,
+    })
+  }
+}
+
+impl serde::Serialize for TypeScriptVersion {
+  fn serialize<S: serde::Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.to_string())
+  }
+}
+
+impl<'de> serde::Deserialize<'de> for TypeScriptVersion {
+  fn deserialize<D: serde::Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+  }
+}
+
+/// The TypeScript versions that the declaration output of packages is checked
+/// against.
+#[derive(Debug, Clone)]
+pub struct TypeScriptVersions(pub Vec<TypeScriptVersion>);
+
+/// A syntax feature used in the declaration output of a package that is only
+/// understood by TypeScript starting at a certain version.
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum TypeScriptFeature {
+  /// `` type A = `prefix-${string}` ``
+  TemplateLiteralTypes,
+  /// `{ [K in keyof T as Uppercase<K>]: T[K] }`
+  MappedTypeKeyRemapping,
+  /// `abstract new () => T`
+  AbstractConstructorTypes,
+  /// `import { type A } from "./a.ts"`
+  TypeModifiersOnImportNames,
+  /// `interface A<in T, out U> {}`
+  VarianceAnnotations,
+  /// `T extends [infer U extends string] ? U : never`
+  InferTypeConstraints,
+  /// `class A { accessor a: string; }`
+  AutoAccessors,
+  /// `function a<const T>(t: T): T`
+  ConstTypeParameters,
+  /// `export type * from "./a.ts"`
+  ExportTypeStar,
+  /// `import data from "./data.json" with { type: "json" }`
+  ImportAttributes,
+}
+
+impl TypeScriptFeature {
+  /// The first TypeScript version that supports the feature.
+  pub fn min_version(&self) -> TypeScriptVersion {
+    match self {
+      Self::TemplateLiteralTypes => TypeScriptVersion::new(4, 1),
+      Self::MappedTypeKeyRemapping => TypeScriptVersion::new(4, 1),
+      Self::AbstractConstructorTypes => TypeScriptVersion::new(4, 2),
+      Self::TypeModifiersOnImportNames => TypeScriptVersion::new(4, 5),
+      Self::VarianceAnnotations => TypeScriptVersion::new(4, 7),
+      Self::InferTypeConstraints => TypeScriptVersion::new(4, 7),
+      Self::AutoAccessors => TypeScriptVersion::new(4, 9),
+      Self::ConstTypeParameters => TypeScriptVersion::new(5, 0),
+      Self::ExportTypeStar => TypeScriptVersion::new(5, 0),
+      Self::ImportAttributes => TypeScriptVersion::new(5, 3),
+    }
+  }
+}
+
+/// Collects the TypeScript features used in the declaration output of all
+/// local modules in the graph. Returns `None` if declarations could not be
+/// generated for some module, because it has slow types.
+pub fn collect_declaration_features(
+  graph: &ModuleGraph,
+) -> Option<BTreeSet<TypeScriptFeature>> {
+  let mut visitor = FeatureVisitor {
+    features: BTreeSet::new(),
+  };
+  for module in graph.modules() {
+    if module.specifier().scheme() != "file" {
+      continue;
+    }
+    let Some(js) = module.js() else {
+      continue;
+    };
+    if js.media_type.is_declaration() {
+      continue;
+    }
+    let dts = js.fast_check_module()?.dts.as_ref()?;
+    dts.program.visit_with(&mut visitor);
+  }
+  Some(visitor.features)
+}
+
+struct FeatureVisitor {
+  features: BTreeSet<TypeScriptFeature>,
+}
+
+impl Visit for FeatureVisitor {
+  fn visit_ts_tpl_lit_type(&mut self, n: &ast::TsTplLitType) {
+    self
+      .features
+      .insert(TypeScriptFeature::TemplateLiteralTypes);
+    n.visit_children_with(self);
+  }
+
+  fn visit_ts_mapped_type(&mut self, n: &ast::TsMappedType) {
+    if n.name_type.is_some() {
+      self
+        .features
+        .insert(TypeScriptFeature::MappedTypeKeyRemapping);
+    }
+    n.visit_children_with(self);
+  }
+
+  fn visit_ts_constructor_type(&mut self, n: &ast::TsConstructorType) {
+    if n.is_abstract {
+      self
+        .features
+        .insert(TypeScriptFeature::AbstractConstructorTypes);
+    }
+    n.visit_children_with(self);
+  }
+
+  fn visit_import_named_specifier(&mut self, n: &ast::ImportNamedSpecifier) {
+    if n.is_type_only {
+      self
+        .features
+        .insert(TypeScriptFeature::TypeModifiersOnImportNames);
+    }
+    n.visit_children_with(self);
+  }
+
+  fn visit_export_named_specifier(&mut self, n: &ast::ExportNamedSpecifier) {
+    if n.is_type_only {
+      self
+        .features
+        .insert(TypeScriptFeature::TypeModifiersOnImportNames);
+    }
+    n.visit_children_with(self);
+  }
+
+  fn visit_ts_type_param(&mut self, n: &ast::TsTypeParam) {
+    if n.is_in || n.is_out {
+      self.features.insert(TypeScriptFeature::VarianceAnnotations);
+    }
+    if n.is_const {
+      self.features.insert(TypeScriptFeature::ConstTypeParameters);
+    }
+    n.visit_children_with(self);
+  }
+
+  fn visit_ts_infer_type(&mut self, n: &ast::TsInferType) {
+    if n.type_param.constraint.is_some() {
+      self
+        .features
+        .insert(TypeScriptFeature::InferTypeConstraints);
+    }
+    n.visit_children_with(self);
+  }
+
+  fn visit_auto_accessor(&mut self, n: &ast::AutoAccessor) {
+    self.features.insert(TypeScriptFeature::AutoAccessors);
+    n.visit_children_with(self);
+  }
+
+  fn visit_export_all(&mut self, n: &ast::ExportAll) {
+    if n.type_only {
+      self.features.insert(TypeScriptFeature::ExportTypeStar);
+    }
+    if n.with.is_some() {
+      self.features.insert(TypeScriptFeature::ImportAttributes);
+    }
+    n.visit_children_with(self);
+  }
+
+  fn visit_import_decl(&mut self, n: &ast::ImportDecl) {
+    if n.with.is_some() {
+      self.features.insert(TypeScriptFeature::ImportAttributes);
+    }
+    n.visit_children_with(self);
+  }
+
+  fn visit_named_export(&mut self, n: &ast::NamedExport) {
+    if n.with.is_some() {
+      self.features.insert(TypeScriptFeature::ImportAttributes);
+    }
+    n.visit_children_with(self);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::TypeScriptFeature;
+  use super::TypeScriptVersion;
+
+  #[test]
+  fn parse_typescript_version() {
+    assert_eq!(
+      "5.0".parse::<TypeScriptVersion>().unwrap(),
+      TypeScriptVersion::new(5, 0)
+    );
+    assert_eq!(
+      " 4.9 ".parse::<TypeScriptVersion>().unwrap(),
+      TypeScriptVersion::new(4, 9)
+    );
+    assert!("5".parse::<TypeScriptVersion>().is_err());
+    assert!("5.x".parse::<TypeScriptVersion>().is_err());
+    assert!(TypeScriptVersion::new(4, 10) > TypeScriptVersion::new(4, 9));
+  }
+
+  #[test]
+  fn feature_min_version() {
+    assert_eq!(
+      TypeScriptFeature::ConstTypeParameters
+        .min_version()
+        .to_string(),
+      "5.0"
+    );
+    assert_eq!(
+      serde_json::to_value(TypeScriptFeature::ExportTypeStar).unwrap(),
+      serde_json::json!("exportTypeStar")
+    );
+  }
+}
@@ -0,0 +1,193 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+//! Exports of all personal data that the registry stores about a user.
+//!
+//! Users request an export through the API, after which the export task
+//! collects the data, uploads it to the publishing bucket, and emails the user
+//! a download link. The link contains a secret of which only the hash is
+//! stored, and expires after [`USER_EXPORT_EXPIRY`], at which point the export
+//! is deleted again.
+
+use std::borrow::Cow;
+
+use anyhow::Context;
+use bytes::Bytes;
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::error;
+use tracing::instrument;
+use tracing::warn;
+use url::Url;
+use uuid::Uuid;
+
+use crate::api::ApiApiKey;
+use crate::api::ApiFullUser;
+use crate::api::ApiPublishingTask;
+use crate::api::ApiScopeInvite;
+use crate::api::ApiToken;
+use crate::buckets::Buckets;
+use crate::buckets::UploadTaskBody;
+use crate::db::Database;
+use crate::db::UserExport;
+use crate::emails::EmailArgs;
+use crate::emails::EmailSender;
+use crate::gcp::GcsUploadOptions;
+use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
+use crate::gcs_paths;
+use crate::ids::ScopeName;
+
+/// How long the download link of an export is valid for.
+pub const USER_EXPORT_EXPIRY: chrono::Duration = chrono::Duration::days(7);
+
+/// The maximum number of exports that are processed in a single task run.
+const MAX_EXPORTS_PER_RUN: i64 = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserExportData {
+  pub exported_at: DateTime<Utc>,
+  pub user: ApiFullUser,
+  pub scope_memberships: Vec<UserExportScopeMembership>,
+  pub scope_invites: Vec<ApiScopeInvite>,
+  /// The metadata of all tokens of the user. The token secrets are never
+  /// stored, so they can not be exported.
+  pub tokens: Vec<ApiToken>,
+  pub api_keys: Vec<ApiApiKey>,
+  /// The publishing tasks that were started by the user.
+  pub publishing_tasks: Vec<ApiPublishingTask>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserExportScopeMembership {
+  pub scope: ScopeName,
+  pub is_admin: bool,
+  pub created_at: DateTime<Utc>,
+}
+
+#[instrument(name = "collect_user_export_data", skip(db), err)]
+pub async fn collect_user_export_data(
+  db: &Database,
+  user_id: Uuid,
+) -> Result<UserExportData, anyhow::Error> {
+  let user = db.get_user(user_id).await?.context("user not found")?;
+  let scope_memberships = db.list_scope_memberships_by_user(user_id).await?;
+  let scope_invites = db.get_scope_invites_by_user(&user_id).await?;
+  let tokens = db.list_all_tokens(user_id).await?;
+  let api_keys = db.list_api_keys(user_id).await?;
+  let publishing_tasks = db.list_publishing_tasks_by_user(user_id).await?;
+
+  Ok(UserExportData {
+    exported_at: Utc::now(),
+    user: user.into(),
+    scope_memberships: scope_memberships
+      .into_iter()
+      .map(|member| UserExportScopeMembership {
+        scope: member.scope,
+        is_admin: member.is_admin,
+        created_at: member.created_at,
+      })
+      .collect(),
+    scope_invites: scope_invites.into_iter().map(Into::into).collect(),
+    tokens: tokens.into_iter().map(Into::into).collect(),
+    api_keys: api_keys.into_iter().map(Into::into).collect(),
+    publishing_tasks: publishing_tasks.into_iter().map(Into::into).collect(),
+  })
+}
+
+/// Processes pending exports, and deletes exports whose download link has
+/// expired.
+pub async fn process_user_exports(
+  db: &Database,
+  buckets: &Buckets,
+  email_sender: Option<&EmailSender>,
+  registry_url: &Url,
+) -> Result<(), anyhow::Error> {
+  for export in db.claim_pending_user_exports(MAX_EXPORTS_PER_RUN).await? {
+    let res =
+      process_user_export(db, buckets, email_sender, registry_url, &export)
+        .await;
+    if let Err(err) = res {
+      error!("failed to process user export {}: {err:?}", export.id);
+      db.fail_user_export(export.id).await?;
+    }
+  }
+
+  for export in db.list_expired_user_exports().await? {
+    let path = gcs_paths::user_export_path(export.id);
+    buckets.publishing_bucket.bucket.delete(&path).await?;
+    db.delete_user_export(export.id).await?;
+  }
+
+  Ok(())
+}
+
+/// Uploads the export and emails the download link to the user. Returns the
+/// download link.
+#[instrument(
+  name = "process_user_export",
+  skip(db, buckets, email_sender, registry_url, export),
+  err,
+  fields(export.id = %export.id, export.user_id = %export.user_id)
+)]
+pub async fn process_user_export(
+  db: &Database,
+  buckets: &Buckets,
+  email_sender: Option<&EmailSender>,
+  registry_url: &Url,
+  export: &UserExport,
+) -> Result<Url, anyhow::Error> {
+  let data = collect_user_export_data(db, export.user_id).await?;
+  let email = data
+    .user
+    .email
+    .clone()
+    .context("user has no email address")?;
+  let content = serde_json::to_vec_pretty(&data)?;
+
+  buckets
+    .publishing_bucket
+    .upload(
+      gcs_paths::user_export_path(export.id).into(),
+      UploadTaskBody::Bytes(Bytes::from(content)),
+      GcsUploadOptions {
+        content_type: Some("application/json".into()),
+        cache_control: Some(CACHE_CONTROL_DO_NOT_CACHE.into()),
+        gzip_encoded: false,
+      },
+    )
+    .await?;
+
+  let secret = format!(
+    "{:032x}{:032x}",
+    rand::random::<u128>(),
+    rand::random::<u128>()
+  );
+  let expires_at = Utc::now() + USER_EXPORT_EXPIRY;
+  db.complete_user_export(export.id, &crate::token::hash(&secret), expires_at)
+    .await?;
+
+  let mut download_url =
+    registry_url.join(&format!("api/user/export/{}/download", export.id))?;
+  download_url.query_pairs_mut().append_pair("token", &secret);
+
+  match email_sender {
+    Some(email_sender) => {
+      let email_args = EmailArgs::UserExport {
+        name: Cow::Borrowed(&data.user.name),
+        download_url: Cow::Borrowed(download_url.as_str()),
+        expires_at: Cow::Owned(expires_at.to_string()),
+        registry_url: Cow::Borrowed(registry_url.as_str()),
+        registry_name: Cow::Borrowed(&email_sender.from_name),
+        support_email: Cow::Borrowed(&email_sender.from),
+      };
+      email_sender.send(email, email_args).await?;
+    }
+    None => {
+      warn!("no email sender configured, not sending user export email");
+    }
+  }
+
+  Ok(download_url)
+}
@@ -1,4 +1,6 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
+use chrono::DateTime;
+use chrono::Utc;
 use futures::FutureExt;
 use hyper::body;
 use hyper::header;
@@ -9,10 +11,12 @@ use hyper::Response;
 use hyper::StatusCode;
 use oauth2::http::HeaderName;
 use routerify::prelude::RequestExt;
+use routerify::RequestInfo;
 use routerify_query::RequestQueryExt;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::future::Future;
+use std::net::IpAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use tracing::error;
@@ -23,14 +27,26 @@ use url::Url;
 use uuid::Uuid;
 
 use crate::api::ApiError;
+use crate::api::ApiList;
+use crate::counters::BufferedCounters;
 use crate::db::Database;
+use crate::db::DeprecatedRouteUsageKey;
 use crate::db::Permissions;
+use crate::gcp::CACHE_CONTROL_DO_NOT_CACHE;
 use crate::github::verify_oidc_token;
 use crate::iam::IamInfo;
+use crate::iam::Principal;
 use crate::iam::ReqIamExt as _;
 use crate::ids::PackageName;
 use crate::ids::ScopeName;
 use crate::ids::Version;
+use crate::rate_limit::ApiKeyCache;
+use crate::rate_limit::QuotaUsage;
+use crate::rate_limit::RateLimitKey;
+use crate::rate_limit::RateLimiter;
+use crate::rate_limit::RequestCosts;
+use crate::rate_limit::WINDOW;
+use crate::token::OIDC_PUBLISH_TOKEN_PREFIX;
 
 pub const USER_AGENT: &str = "JSR";
 
@@ -60,6 +76,77 @@ where
   }
 }
 
+/// Wrap an endpoint handler that returns one page of a list, selected with the
+/// `page` and `limit` query parameters (see [`pagination`]).
+///
+/// The response includes a `hasMore` flag, and a `Link` header with `next` and
+/// `prev` links to the neighbouring pages.
+pub fn paginated_json<T, H, HF>(
+  handler: H,
+) -> impl Fn(Request<Body>) -> ApiHandlerFuture<Response<Body>>
+where
+  T: Serialize,
+  H: Fn(Request<Body>) -> HF,
+  HF: Future<Output = ApiResult<ApiList<T>>> + Send + 'static,
+{
+  move |req: Request<Body>| {
+    let (start, limit) = pagination(&req);
+    let uri = req.uri().clone();
+    let fut = handler(req);
+    async move {
+      let list = fut.await?;
+      let has_more =
+        (start as usize).saturating_add(list.items.len()) < list.total;
+
+      #[derive(Serialize)]
+      #[serde(rename_all = "camelCase")]
+      struct Page<'a, T> {
+        #[serde(flatten)]
+        list: &'a ApiList<T>,
+        has_more: bool,
+      }
+      let mut resp = respond_json(
+        &Page {
+          list: &list,
+          has_more,
+        },
+        StatusCode::OK,
+      );
+
+      let page = start / limit + 1;
+      let mut links = vec![];
+      if has_more {
+        links.push(pagination_link(&uri, page + 1, "next"));
+      }
+      if page > 1 {
+        links.push(pagination_link(&uri, page - 1, "prev"));
+      }
+      if !links.is_empty() {
+        resp
+          .headers_mut()
+          .insert(header::LINK, links.join(", ").try_into().unwrap());
+      }
+      Ok(resp)
+    }
+    .boxed()
+  }
+}
+
+/// Formats a link to another page of the same list, keeping all other query
+/// parameters.
+fn pagination_link(uri: &hyper::Uri, page: i64, rel: &str) -> String {
+  let mut query = url::form_urlencoded::Serializer::new(String::new());
+  for (key, value) in
+    url::form_urlencoded::parse(uri.query().unwrap_or("").as_bytes())
+  {
+    if key != "page" {
+      query.append_pair(&key, &value);
+    }
+  }
+  query.append_pair("page", &page.to_string());
+  format!("<{}?{}>; rel=\"{rel}\"", uri.path(), query.finish())
+}
+
 pub fn respond_json<V: ?Sized + Serialize>(
   value: &V,
   status_code: StatusCode,
@@ -68,6 +155,17 @@ pub fn respond_json<V: ?Sized + Serialize>(
   create_response(status_code, "application/json", body)
 }
 
+/// Responds with a `301 Moved Permanently` to the given location. Package
+/// redirects expire, so the redirect itself must not be cached.
+pub fn moved_permanently(location: &str) -> Response<Body> {
+  Response::builder()
+    .status(StatusCode::MOVED_PERMANENTLY)
+    .header(header::LOCATION, location)
+    .header(header::CACHE_CONTROL, CACHE_CONTROL_DO_NOT_CACHE)
+    .body(Body::empty())
+    .unwrap()
+}
+
 pub fn create_response<B>(
   status: StatusCode,
   mime: &str, // TODO mime::Mime,
@@ -147,6 +245,144 @@ where
   }
 }
 
+/// A route that is going to be removed, and the dates it was deprecated on and
+/// will stop working on.
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+  /// The path of the route, as it is recorded in the usage report.
+  pub route: &'static str,
+  pub deprecated_at: DateTime<Utc>,
+  pub sunset: DateTime<Utc>,
+}
+
+/// User agents are truncated to this many bytes before they are counted.
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+/// The requests to deprecated routes that were not written to the database
+/// yet. See [`crate::counters`].
+#[derive(Clone, Default)]
+pub struct DeprecatedRouteUsages(BufferedCounters<DeprecatedRouteUsageKey>);
+
+impl DeprecatedRouteUsages {
+  pub async fn flush(&self, db: &Database) -> Result<(), sqlx::Error> {
+    let counts = self.0.take();
+    if counts.is_empty() {
+      return Ok(());
+    }
+    let usages = counts
+      .iter()
+      .map(|(key, count)| (key, *count as i64))
+      .collect::<Vec<_>>();
+    if let Err(err) = db.record_deprecated_route_usages(&usages).await {
+      self.0.restore(counts);
+      return Err(err);
+    }
+    Ok(())
+  }
+}
+
+/// The number of requests made with each API key, buffered in memory so that
+/// requests do not write to the database.
+#[derive(Clone, Default)]
+pub struct ApiKeyUsages(BufferedCounters<Uuid>);
+
+impl ApiKeyUsages {
+  pub async fn flush(&self, db: &Database) -> Result<(), sqlx::Error> {
+    let counts = self.0.take();
+    if counts.is_empty() {
+      return Ok(());
+    }
+    let usages = counts
+      .iter()
+      .map(|(id, count)| (*id, *count as i64))
+      .collect::<Vec<_>>();
+    if let Err(err) = db.record_api_key_usages(&usages).await {
+      self.0.restore(counts);
+      return Err(err);
+    }
+    Ok(())
+  }
+}
+
+static DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+static SUNSET: HeaderName = HeaderName::from_static("sunset");
+const DEPRECATION_DOCS: &str =
+  "<https://jsr.io/docs/api#deprecated-routes>; rel=\"deprecation\"";
+
+/// Wrap a deprecated endpoint handler, adding the `Deprecation`, `Sunset` and
+/// `Link` headers to successful responses, and counting the request against
+/// the token and user agent that made it, so that remaining consumers can be
+/// found before the route is removed. Counts are buffered in memory and written
+/// to the database periodically.
+pub fn deprecated<H, HF>(
+  deprecation: Deprecation,
+  handler: H,
+) -> impl Fn(Request<Body>) -> ApiHandlerFuture<Response<Body>>
+where
+  H: Send + Sync + Fn(Request<Body>) -> HF + Send + 'static,
+  HF: Future<Output = ApiResult<Response<Body>>> + Send + 'static,
+{
+  let deprecation_value = header::HeaderValue::from_str(&format!(
+    "@{}",
+    deprecation.deprecated_at.timestamp()
+  ))
+  .unwrap();
+  let sunset_value = header::HeaderValue::from_str(
+    &deprecation
+      .sunset
+      .format("%a, %d %b %Y %H:%M:%S GMT")
+      .to_string(),
+  )
+  .unwrap();
+  let handler = Arc::new(handler);
+  move |req: Request<Body>| {
+    let handler = handler.clone();
+    let deprecation_value = deprecation_value.clone();
+    let sunset_value = sunset_value.clone();
+    async move {
+      let usages = req.data::<DeprecatedRouteUsages>().unwrap();
+      let iam_info = req.context::<IamInfo>().unwrap();
+      let user_id = match &iam_info.principal {
+        Principal::User(user)
+        | Principal::GitHubActions {
+          user: Some(user), ..
+        } => Some(user.id),
+        _ => None,
+      };
+      let mut user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+      if user_agent.len() > MAX_USER_AGENT_LENGTH {
+        let mut end = MAX_USER_AGENT_LENGTH;
+        while !user_agent.is_char_boundary(end) {
+          end -= 1;
+        }
+        user_agent.truncate(end);
+      }
+      usages.0.increment(DeprecatedRouteUsageKey {
+        route: deprecation.route,
+        token_id: iam_info.token_id,
+        user_id,
+        user_agent,
+      });
+
+      let mut res = handler(req).await?;
+      let headers = res.headers_mut();
+      headers.insert(DEPRECATION.clone(), deprecation_value);
+      headers.insert(SUNSET.clone(), sunset_value);
+      headers.append(
+        header::LINK,
+        header::HeaderValue::from_static(DEPRECATION_DOCS),
+      );
+      Ok(res)
+    }
+    .boxed()
+  }
+}
+
 #[instrument(name = "auth", skip(req), err, fields(token.kind, user.id, repo.id))]
 pub async fn auth_middleware(req: Request<Body>) -> ApiResult<Request<Body>> {
   let db = req.data::<Database>().unwrap();
@@ -156,6 +392,29 @@ pub async fn auth_middleware(req: Request<Body>) -> ApiResult<Request<Body>> {
 
   let iam_info =
     match token {
+      Some((AuthorizationToken::Bearer(token), _))
+        if token.starts_with(OIDC_PUBLISH_TOKEN_PREFIX) =>
+      {
+        span.record("token.kind", field::display("oidcpublish"));
+        let token = db
+          .get_oidc_publish_token_by_hash(&crate::token::hash(token))
+          .await?
+          .ok_or(ApiError::InvalidBearerToken)?;
+        if token.expires_at < chrono::Utc::now() {
+          return Err(ApiError::InvalidBearerToken);
+        }
+        span.record("repo.id", field::display(token.repository_id));
+
+        let user = match token.user_id {
+          Some(user_id) => db.get_user(user_id).await?,
+          None => None,
+        };
+        if let Some(user) = &user {
+          span.record("user.id", field::display(user.id));
+        }
+
+        IamInfo::from((token, user))
+      }
       Some((AuthorizationToken::Bearer(token), sudo)) => {
         span.record("token.kind", field::display("bearer"));
         if let Some(token) =
@@ -195,7 +454,7 @@ pub async fn auth_middleware(req: Request<Body>) -> ApiResult<Request<Body>> {
           span.record("user.id", field::display(user.id));
         }
 
-        IamInfo::from((claims.repository_id, aud, user))
+        IamInfo::from((claims, aud, user))
       }
       None => IamInfo::anonymous(),
     };
@@ -253,6 +512,146 @@ fn extract_token_and_sudo(
   None
 }
 
+static X_JSR_API_KEY: HeaderName =
+  header::HeaderName::from_static("x-jsr-api-key");
+
+/// Counts the cost of a request against the quota of the client: per user for
+/// requests authenticated with a token, per API key if the request carries
+/// one, and per client IP address otherwise. Must run after
+/// [`auth_middleware`].
+///
+/// API keys are looked up through the [`ApiKeyCache`]. Keys that are not
+/// cached are looked up in the database only if the client IP address is
+/// within its quota, so that guessing keys is rate limited too. Requests made
+/// with a key are counted in memory once they are allowed.
+#[instrument(
+  name = "rate_limit",
+  skip(req),
+  err,
+  fields(api_key.id, quota.cost)
+)]
+pub async fn rate_limit_middleware(
+  req: Request<Body>,
+) -> ApiResult<Request<Body>> {
+  let iam_info = req.context::<IamInfo>().unwrap();
+  let rate_limiter = req.data::<RateLimiter>().unwrap();
+  let key = if let Principal::User(user)
+  | Principal::GitHubActions {
+    user: Some(user), ..
+  } = &iam_info.principal
+  {
+    RateLimitKey::User(user.id)
+  } else if !matches!(iam_info.principal, Principal::Anonymous) {
+    return Ok(req);
+  } else if let Some(api_key) = req.headers().get(&X_JSR_API_KEY) {
+    let api_key = api_key.to_str().map_err(|_| ApiError::InvalidApiKey)?;
+    let hash = crate::token::hash(api_key);
+    let api_key_cache = req.data::<ApiKeyCache>().unwrap();
+    let id = match api_key_cache.get(&hash) {
+      Some(id) => id,
+      None => {
+        if let Some(usage) = client_ip(&req)
+          .and_then(|ip| rate_limiter.consume(RateLimitKey::Ip(ip), 1))
+        {
+          if !usage.allowed {
+            req.set_context(usage);
+            return Err(ApiError::RateLimitExceeded);
+          }
+        }
+        let db = req.data::<Database>().unwrap();
+        let id = db.get_api_key_by_hash(&hash).await?.map(|key| key.id);
+        api_key_cache.insert(hash, id);
+        id
+      }
+    };
+    let id = id.ok_or(ApiError::InvalidApiKey)?;
+    Span::current().record("api_key.id", field::display(id));
+    RateLimitKey::ApiKey(id)
+  } else {
+    match client_ip(&req) {
+      Some(ip) => RateLimitKey::Ip(ip),
+      None => return Ok(req),
+    }
+  };
+
+  let request_costs = req.data::<RequestCosts>().unwrap();
+  let cost =
+    request_costs.cost(req.method(), req.uri().path(), req.uri().query());
+  Span::current().record("quota.cost", cost);
+
+  if let Some(usage) = rate_limiter.consume(key, cost) {
+    req.set_context(usage);
+    if !usage.allowed {
+      return Err(ApiError::RateLimitExceeded);
+    }
+  }
+
+  if let RateLimitKey::ApiKey(id) = key {
+    req.data::<ApiKeyUsages>().unwrap().0.increment(id);
+  }
+
+  Ok(req)
+}
+
+static X_RATELIMIT_LIMIT: HeaderName =
+  HeaderName::from_static("x-ratelimit-limit");
+static X_RATELIMIT_REMAINING: HeaderName =
+  HeaderName::from_static("x-ratelimit-remaining");
+static X_RATELIMIT_RESET: HeaderName =
+  HeaderName::from_static("x-ratelimit-reset");
+static X_RATELIMIT_COST: HeaderName =
+  HeaderName::from_static("x-ratelimit-cost");
+// The fields of the IETF draft "RateLimit header fields for HTTP", which
+// are sent in addition to the `X-RateLimit-*` headers.
+static RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+static RATELIMIT_REMAINING: HeaderName =
+  HeaderName::from_static("ratelimit-remaining");
+static RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+static RATELIMIT_POLICY: HeaderName =
+  HeaderName::from_static("ratelimit-policy");
+
+/// Adds the quota of the client, as counted by [`rate_limit_middleware`], to
+/// the response headers.
+pub async fn quota_headers_middleware(
+  mut resp: Response<Body>,
+  req_info: RequestInfo,
+) -> ApiResult<Response<Body>> {
+  let Some(usage) = req_info.context::<QuotaUsage>() else {
+    return Ok(resp);
+  };
+  let reset_after = usage.reset_after.as_secs_f64().ceil() as u64;
+  let headers = resp.headers_mut();
+  headers.insert(X_RATELIMIT_LIMIT.clone(), usage.limit.into());
+  headers.insert(X_RATELIMIT_REMAINING.clone(), usage.remaining.into());
+  headers.insert(X_RATELIMIT_RESET.clone(), reset_after.into());
+  headers.insert(X_RATELIMIT_COST.clone(), usage.cost.into());
+  headers.insert(RATELIMIT_LIMIT.clone(), usage.limit.into());
+  headers.insert(RATELIMIT_REMAINING.clone(), usage.remaining.into());
+  headers.insert(RATELIMIT_RESET.clone(), reset_after.into());
+  let policy = format!("{};w={}", usage.limit, WINDOW.as_secs());
+  headers.insert(RATELIMIT_POLICY.clone(), policy.parse().unwrap());
+  if !usage.allowed {
+    headers.insert(header::RETRY_AFTER, reset_after.into());
+  }
+  Ok(resp)
+}
+
+/// The IP address of the client. The API is served behind a load balancer,
+/// which appends the address of the client it received the request from to
+/// `X-Forwarded-For`, followed by its own address. Entries before that are
+/// supplied by the client and can not be trusted.
+pub fn client_ip(req: &Request<Body>) -> Option<IpAddr> {
+  let forwarded_for = req
+    .headers()
+    .get("x-forwarded-for")
+    .and_then(|value| value.to_str().ok())
+    .map(|value| value.split(',').map(str::trim).collect::<Vec<_>>());
+  match forwarded_for.as_deref() {
+    Some([.., client, _]) | Some([client]) => client.parse().ok(),
+    _ => Some(req.remote_addr().ip()),
+  }
+}
+
 #[derive(Clone, Debug, serde::Deserialize)]
 pub struct GithubOidcTokenAud {
   pub permissions: Permissions,
@@ -412,457 +811,8 @@ impl RequestIdExt for Request<Body> {
 }
 
 #[cfg(test)]
-pub mod test {
-  use crate::auth::GithubOauth2Client;
-  use crate::buckets::BucketWithQueue;
-  use crate::buckets::Buckets;
-  use crate::db::EphemeralDatabase;
-  use crate::db::NewGithubIdentity;
-  use crate::db::{Database, NewUser, User};
-  use crate::errors_internal::ApiErrorStruct;
-  use crate::gcp::FakeGcsTester;
-  use crate::util::sanitize_redirect_url;
-  use crate::ApiError;
-  use crate::MainRouterOptions;
-  use hyper::http::HeaderName;
-  use hyper::http::HeaderValue;
-  use hyper::service::Service;
-  use hyper::Body;
-  use hyper::HeaderMap;
-  use hyper::Response;
-  use hyper::StatusCode;
-  use routerify::RequestService;
-  use routerify::RouteError;
-  use serde::de::DeserializeOwned;
-  use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
-  use url::Url;
-
-  #[derive(Debug)]
-  pub struct TestUser {
-    pub user: User,
-    pub token: String,
-    pub github_name: String,
-  }
-
-  pub struct TestSetup {
-    pub ephemeral_database: EphemeralDatabase,
-    #[allow(dead_code)]
-    pub gcs: FakeGcsTester,
-    pub buckets: Buckets,
-    pub user1: TestUser,
-    pub user2: TestUser,
-    pub user3: TestUser,
-    pub staff_user: TestUser,
-    #[allow(dead_code)]
-    pub scope: crate::db::Scope,
-    #[allow(dead_code)]
-    pub github_oauth2_client: GithubOauth2Client,
-    pub service: RequestService<Body, ApiError>,
-  }
-
-  impl TestSetup {
-    pub async fn new() -> Self {
-      let ephemeral_database = EphemeralDatabase::create().await;
-      let db = ephemeral_database.database.clone().unwrap();
-      let gcs = FakeGcsTester::new().await;
-      let publishing_bucket = gcs.create_bucket("publishing").await;
-      let modules_bucket = gcs.create_bucket("modules").await;
-      let docs_bucket = gcs.create_bucket("docs").await;
-      let npm_bucket = gcs.create_bucket("npm").await;
-      let buckets = Buckets {
-        publishing_bucket: BucketWithQueue::new(publishing_bucket),
-        modules_bucket: BucketWithQueue::new(modules_bucket),
-        docs_bucket: BucketWithQueue::new(docs_bucket),
-        npm_bucket: BucketWithQueue::new(npm_bucket),
-      };
-      let github_oauth2_client = GithubOauth2Client::new(
-        oauth2::ClientId::new("".to_string()),
-        Some(oauth2::ClientSecret::new("".to_string())),
-        oauth2::AuthUrl::new(
-          "https://github.com/login/oauth/authorize".to_string(),
-        )
-        .unwrap(),
-        Some(
-          oauth2::TokenUrl::new(
-            "https://github.com/login/oauth/access_token".to_string(),
-          )
-          .unwrap(),
-        ),
-      );
-
-      let user1 = Self::create_user(
-        &db,
-        NewUser {
-          name: "User 1",
-          email: None,
-          avatar_url: "https://avatars0.githubusercontent.com/u/952?v=4",
-          github_id: Some(101),
-          is_blocked: false,
-          is_staff: false,
-        },
-        "ry",
-      )
-      .await;
-
-      let user2 = Self::create_user(
-        &db,
-        NewUser {
-          name: "User 2",
-          email: None,
-          avatar_url: "",
-          github_id: Some(102),
-          is_blocked: false,
-          is_staff: false,
-        },
-        "lucacasonato",
-      )
-      .await;
-
-      let user3 = Self::create_user(
-        &db,
-        NewUser {
-          name: "User 3",
-          email: None,
-          avatar_url: "",
-          github_id: Some(103),
-          is_blocked: false,
-          is_staff: false,
-        },
-        "crowlkats",
-      )
-      .await;
-
-      let staff_user = Self::create_user(
-        &db,
-        NewUser {
-          name: "User 4",
-          email: None,
-          avatar_url: "",
-          github_id: Some(104),
-          is_blocked: false,
-          is_staff: true,
-        },
-        "bartlomieju",
-      )
-      .await;
-
-      let scope_name = "scope".try_into().unwrap();
-
-      db.create_scope(&scope_name, user1.user.id).await.unwrap();
-      let (scope, _, _) = db
-        .update_scope_limits(&scope_name, Some(250), Some(200), Some(1000))
-        .await
-        .unwrap();
-
-      db.add_bad_word_for_test("somebadword").await.unwrap();
-
-      let router = crate::main_router(MainRouterOptions {
-        database: db,
-        buckets: buckets.clone(),
-        github_client: github_oauth2_client.clone(),
-        orama_client: None,
-        email_sender: None,
-        registry_url: "http://jsr-tests.test".parse().unwrap(),
-        npm_url: "http://npm.jsr-tests.test".parse().unwrap(),
-        publish_queue: None,           // no queue locally
-        npm_tarball_build_queue: None, // no queue locally
-        logs_bigquery_table: None,     // no bigquery locally
-        expose_api: true,              // api enabled
-        expose_tasks: true,            // task endpoints enabled
-      });
-
-      let service = routerify::RequestServiceBuilder::new(router)
-        .unwrap()
-        .build(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8080)));
-
-      Self {
-        ephemeral_database,
-        gcs,
-        buckets,
-        user1,
-        user2,
-        user3,
-        staff_user,
-        scope,
-        github_oauth2_client,
-        service,
-      }
-    }
-
-    async fn create_user<'a>(
-      db: &Database,
-      new_user: NewUser<'a>,
-      github_name: &str,
-    ) -> TestUser {
-      db.upsert_github_identity(NewGithubIdentity {
-        github_id: new_user.github_id.unwrap(),
-        access_token: None,
-        access_token_expires_at: None,
-        refresh_token: None,
-        refresh_token_expires_at: None,
-      })
-      .await
-      .unwrap();
-
-      let user = db.insert_user(new_user).await.unwrap();
-
-      let token = crate::token::create_token(
-        db,
-        user.id,
-        crate::db::TokenType::Web,
-        None,
-        Some(chrono::Utc::now() + chrono::Duration::try_days(7).unwrap()),
-        None,
-      )
-      .await
-      .unwrap();
-
-      TestUser {
-        user,
-        token,
-        github_name: github_name.to_string(),
-      }
-    }
-
-    pub fn db(&self) -> Database {
-      self.ephemeral_database.database.clone().unwrap()
-    }
-
-    pub fn buckets(&self) -> Buckets {
-      self.buckets.clone()
-    }
-
-    pub fn registry_url(&self) -> Url {
-      Url::parse("http://jsr-tests.test").unwrap()
-    }
-
-    pub fn npm_url(&self) -> Url {
-      Url::parse("http://npm.jsr-tests.test").unwrap()
-    }
-
-    pub fn http(&mut self) -> TestHttpClient {
-      TestHttpClient {
-        service: &mut self.service,
-        auth: Some(&self.user1.token),
-      }
-    }
-
-    pub fn unauthed_http(&mut self) -> TestHttpClient {
-      TestHttpClient {
-        service: &mut self.service,
-        auth: None,
-      }
-    }
-  }
-
-  pub struct TestHttpClient<'s, 't> {
-    service: &'s mut RequestService<Body, ApiError>,
-    auth: Option<&'t str>,
-  }
-
-  impl<'s> TestHttpClient<'s, '_> {
-    pub fn get<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
-      TestHttpCall::new(
-        self.service,
-        "GET",
-        uri.as_ref().to_string(),
-        self.auth,
-      )
-    }
-    pub fn post<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
-      TestHttpCall::new(
-        self.service,
-        "POST",
-        uri.as_ref().to_string(),
-        self.auth,
-      )
-    }
-    pub fn delete<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
-      TestHttpCall::new(
-        self.service,
-        "DELETE",
-        uri.as_ref().to_string(),
-        self.auth,
-      )
-    }
-    #[allow(dead_code)]
-    pub fn put<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
-      TestHttpCall::new(
-        self.service,
-        "PUT",
-        uri.as_ref().to_string(),
-        self.auth,
-      )
-    }
-    pub fn patch<U: AsRef<str>>(&'s mut self, uri: U) -> TestHttpCall<'s> {
-      TestHttpCall::new(
-        self.service,
-        "PATCH",
-        uri.as_ref().to_string(),
-        self.auth,
-      )
-    }
-  }
-
-  pub struct TestHttpCall<'s> {
-    service: &'s mut RequestService<Body, ApiError>,
-    method: &'static str,
-    uri: String,
-    body: Body,
-    headers: HeaderMap,
-    token: Option<&'s str>,
-    sudo: bool,
-  }
-
-  impl<'s> TestHttpCall<'s> {
-    fn new(
-      service: &'s mut RequestService<Body, ApiError>,
-      method: &'static str,
-      uri: String,
-      token: Option<&'s str>,
-    ) -> Self {
-      Self {
-        service,
-        method,
-        uri,
-        body: Body::empty(),
-        headers: HeaderMap::default(),
-        token,
-        sudo: false,
-      }
-    }
-
-    /// overwrite the default user token for authentication
-    pub fn token(mut self, token: Option<&'s str>) -> Self {
-      self.token = token;
-      self
-    }
-
-    /// overwrite the default user token for authentication
-    pub fn sudo(mut self, sudo: bool) -> Self {
-      self.sudo = sudo;
-      self
-    }
-
-    pub fn body_json(mut self, body: serde_json::Value) -> Self {
-      self.body = body.to_string().into();
-      self
-    }
-
-    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
-      let prev = self.headers.insert(name.clone(), value);
-      assert!(prev.is_none(), "{} already present in the header map", name);
-      self
-    }
-
-    pub fn gzip(self) -> Self {
-      self.header(hyper::header::CONTENT_ENCODING, "gzip".try_into().unwrap())
-    }
-
-    pub fn body(mut self, body: Body) -> Self {
-      self.body = body;
-      self
-    }
-
-    pub async fn call(self) -> Result<hyper::Response<Body>, RouteError> {
-      let mut req = hyper::Request::builder().method(self.method).uri(self.uri);
-
-      for (key, value) in self.headers.into_iter() {
-        req = req.header(key.unwrap(), value)
-      }
-      if let Some(token) = self.token {
-        req = req
-          .header(hyper::header::AUTHORIZATION, &format!("Bearer {}", token));
-      }
-
-      if self.sudo {
-        req = req
-          .header(hyper::header::HeaderName::from_static("x-jsr-sudo"), "true");
-      }
-
-      let req = req.body(self.body).unwrap();
-      self.service.call(req).await
-    }
-  }
-
-  #[async_trait::async_trait]
-  pub trait ApiResultExt {
-    #[track_caller]
-    async fn expect_json<T: DeserializeOwned>(
-      &mut self,
-      status: StatusCode,
-    ) -> T;
-
-    #[track_caller]
-    async fn expect_ok<T: DeserializeOwned>(&mut self) -> T {
-      self.expect_json(StatusCode::OK).await
-    }
-
-    #[track_caller]
-    async fn expect_ok_no_content(&mut self);
-
-    #[track_caller]
-    async fn expect_err(&mut self, status: StatusCode) -> ApiErrorStruct {
-      self.expect_json(status).await
-    }
-
-    #[track_caller]
-    async fn expect_err_code(
-      &mut self,
-      status: StatusCode,
-      code: &str,
-    ) -> ApiErrorStruct {
-      let err: ApiErrorStruct = self.expect_json(status).await;
-      assert_eq!(err.code, code, "{}", err.message);
-      err
-    }
-  }
-
-  #[async_trait::async_trait]
-  impl ApiResultExt for Response<Body> {
-    #[track_caller]
-    async fn expect_json<T: DeserializeOwned>(
-      &mut self,
-      status: StatusCode,
-    ) -> T {
-      let bytes = hyper::body::to_bytes(self.body_mut()).await.unwrap();
-      let body = std::str::from_utf8(&bytes).expect("invalid utf8");
-      assert_eq!(
-        self.status(),
-        status,
-        "expected {} response, got {} with body: {}",
-        status,
-        self.status(),
-        body
-      );
-      serde_json::from_str(body).unwrap()
-    }
-
-    #[track_caller]
-    async fn expect_ok_no_content(&mut self) {
-      let bytes = hyper::body::to_bytes(self.body_mut()).await.unwrap();
-      let body = std::str::from_utf8(&bytes).expect("invalid utf8");
-      assert_eq!(
-        self.status(),
-        StatusCode::NO_CONTENT,
-        "expected {} response, got {} with body: {}",
-        StatusCode::NO_CONTENT,
-        self.status(),
-        body
-      );
-    }
-  }
-
-  #[tokio::test]
-  async fn harness_expectations() {
-    let t = TestSetup::new().await;
-    // This permissions set up is important - the scope member management tests
-    // rely on it.
-    assert!(!t.user1.user.is_staff);
-    assert!(!t.user2.user.is_staff);
-    assert!(!t.user3.user.is_staff);
-    assert!(t.staff_user.user.is_staff);
-  }
+mod tests {
+  use super::sanitize_redirect_url;
 
   #[test]
   fn sanitize_url_test() {
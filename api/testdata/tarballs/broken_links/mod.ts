@@ -0,0 +1,10 @@
+/**
+ * This is a test module. See https://also-broken.invalid/docs for more.
+ *
+ * @module
+ */
+
+/**
+ * This is a test constant.
+ */
+export const hello = "Hello, world!";
@@ -0,0 +1,18 @@
+/**
+ * This is a test module.
+ *
+ * @module
+ */
+
+/**
+ * This is a test constant.
+ *
+ * @deprecated Use {@linkcode greeting} instead.
+ */
+export const hello = "Hello, world!";
+
+/**
+ * This is a test constant.
+ */
+export const greeting = "Hello, world!";
+export const 读取多键1 = 1;
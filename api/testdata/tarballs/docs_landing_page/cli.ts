@@ -0,0 +1,2 @@
+/** Runs the command line interface. */
+export function runCli(): void {}
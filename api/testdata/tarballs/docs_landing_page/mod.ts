@@ -0,0 +1,2 @@
+/** Says hello. */
+export const hello = "Hello, world!";
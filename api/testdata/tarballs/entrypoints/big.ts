@@ -0,0 +1 @@
+export const big: string = "this module is only loaded dynamically";
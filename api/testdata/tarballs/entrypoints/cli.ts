@@ -0,0 +1,5 @@
+import chalk from "npm:chalk@5";
+import { hello } from "./mod.ts";
+
+const message = await Promise.resolve(hello);
+console.log(chalk.green(message));
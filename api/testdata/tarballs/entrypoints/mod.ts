@@ -0,0 +1,7 @@
+import { greet } from "./util.ts";
+
+export const hello: string = greet("world");
+
+export function loadBig(): Promise<unknown> {
+  return import("./big.ts");
+}
@@ -0,0 +1,2 @@
+import "npm:express@4";
+await import("npm:chalk@5");
@@ -0,0 +1,5 @@
+export type Greeting = `Hello, ${string}!`;
+
+export function identity<const T>(value: T): T {
+  return value;
+}
@@ -4,7 +4,11 @@ import { ComponentChildren } from "preact";
 import { define } from "../../../util.ts";
 import { ScopeHeader } from "../(_components)/ScopeHeader.tsx";
 import { ScopeNav } from "../(_components)/ScopeNav.tsx";
-import { FullScope, User } from "../../../utils/api_types.ts";
+import {
+  FullScope,
+  ScopePublishFreeze,
+  User,
+} from "../../../utils/api_types.ts";
 import { scopeDataWithMember } from "../../../utils/data.ts";
 import { path } from "../../../utils/api.ts";
 import { QuotaCard } from "../../../components/QuotaCard.tsx";
@@ -21,6 +25,9 @@ export default define.page<typeof handler>(function ScopeSettingsPage(
       <ScopeQuotas scope={data.scope} user={state.user!} />
       <GitHubActionsSecurity scope={data.scope} />
       <RequirePublishingFromCI scope={data.scope} />
+      <OutdatedDependenciesEmail scope={data.scope} />
+      <DownloadAnomalyEmail scope={data.scope} />
+      <PublishFreezes freezes={data.publishFreezes} />
       <DeleteScope scope={data.scope} />
     </div>
   );
@@ -184,6 +191,207 @@ function RequirePublishingFromCI({ scope }: { scope: FullScope }) {
   );
 }
 
+function OutdatedDependenciesEmail({ scope }: { scope: FullScope }) {
+  return (
+    <div class="mb-12 mt-12">
+      <h2 class="text-lg sm:text-xl font-semibold">
+        Outdated Dependencies Email
+      </h2>
+      <p class="mt-2 text-jsr-gray-600 max-w-2xl">
+        Once a week, all admins of this scope receive an email listing the
+        packages in the scope whose latest version depends on a package that is
+        at least one major version behind, or whose constraint is only
+        satisfied by yanked versions. Admins without an email address on their
+        account do not receive the email.
+      </p>
+
+      <p class="mt-4 text-jsr-gray-600 max-w-2xl">
+        This setting is currently{" "}
+        <span class="font-semibold">
+          {scope.outdatedDependenciesEmail ? "enabled" : "disabled"}
+        </span>.
+      </p>
+      <form
+        class="mt-8 max-w-4xl"
+        method="POST"
+      >
+        <input
+          type="hidden"
+          name="value"
+          value={String(!scope.outdatedDependenciesEmail)}
+        />
+        <button
+          name="action"
+          value="outdatedDependenciesEmail"
+          class={scope.outdatedDependenciesEmail
+            ? "button-danger"
+            : "button-primary"}
+          type="submit"
+        >
+          {scope.outdatedDependenciesEmail ? "Disable" : "Enable"}{" "}
+          outdated dependencies email
+        </button>
+      </form>
+    </div>
+  );
+}
+
+function DownloadAnomalyEmail({ scope }: { scope: FullScope }) {
+  return (
+    <div class="mb-12 mt-12">
+      <h2 class="text-lg sm:text-xl font-semibold">
+        Download Anomaly Email
+      </h2>
+      <p class="mt-2 text-jsr-gray-600 max-w-2xl">
+        Once a day, the downloads of every package in this scope are compared to
+        its downloads over the previous four weeks. If a package was downloaded
+        far more or far less than usual, all admins of this scope receive an
+        email about it. Admins without an email address on their account do
+        not receive the email.
+      </p>
+
+      <p class="mt-4 text-jsr-gray-600 max-w-2xl">
+        This setting is currently{" "}
+        <span class="font-semibold">
+          {scope.downloadAnomalyEmail ? "enabled" : "disabled"}
+        </span>.
+      </p>
+      <form
+        class="mt-8 max-w-4xl"
+        method="POST"
+      >
+        <input
+          type="hidden"
+          name="value"
+          value={String(!scope.downloadAnomalyEmail)}
+        />
+        <button
+          name="action"
+          value="downloadAnomalyEmail"
+          class={scope.downloadAnomalyEmail
+            ? "button-danger"
+            : "button-primary"}
+          type="submit"
+        >
+          {scope.downloadAnomalyEmail ? "Disable" : "Enable"}{" "}
+          download anomaly email
+        </button>
+      </form>
+    </div>
+  );
+}
+
+const WEEKDAY_FORMAT = new Intl.DateTimeFormat("en-US", {
+  weekday: "long",
+  hour: "2-digit",
+  minute: "2-digit",
+  timeZone: "UTC",
+  timeZoneName: "short",
+});
+
+function formatFreezeWindow(freeze: ScopePublishFreeze) {
+  const startsAt = new Date(freeze.startsAt);
+  const endsAt = new Date(freeze.endsAt);
+  if (freeze.recursWeekly) {
+    return `Every week from ${WEEKDAY_FORMAT.format(startsAt)} to ${
+      WEEKDAY_FORMAT.format(endsAt)
+    }`;
+  }
+  return `From ${startsAt.toUTCString()} to ${endsAt.toUTCString()}`;
+}
+
+function PublishFreezes({ freezes }: { freezes: ScopePublishFreeze[] }) {
+  return (
+    <div class="mb-12 mt-12">
+      <h2 class="text-lg sm:text-xl font-semibold">Publish Freezes</h2>
+      <p class="mt-2 text-jsr-gray-600 max-w-2xl">
+        During a publish freeze, no new versions can be published to any package
+        in this scope. Freezes can be one-off, for example during a release
+        freeze, or repeat every week, for example to prevent publishing on
+        weekends. In an emergency, scope admins can publish during a freeze with
+        a personal access token that is allowed to bypass publish freezes.
+      </p>
+
+      {freezes.length > 0 && (
+        <ul class="mt-4 max-w-2xl divide-y divide-jsr-gray-200 border-y border-jsr-gray-200">
+          {freezes.map((freeze) => (
+            <li class="py-3 flex justify-between items-center gap-4">
+              <div>
+                <p class="text-jsr-gray-900 font-semibold">{freeze.reason}</p>
+                <p class="text-sm text-jsr-gray-600">
+                  {formatFreezeWindow(freeze)}
+                </p>
+              </div>
+              <form method="POST">
+                <input type="hidden" name="freezeId" value={freeze.id} />
+                <button
+                  name="action"
+                  value="deletePublishFreeze"
+                  class="button-danger"
+                  type="submit"
+                >
+                  Remove
+                </button>
+              </form>
+            </li>
+          ))}
+        </ul>
+      )}
+
+      <form class="mt-8 max-w-2xl grid gap-4" method="POST">
+        <label class="block">
+          <span class="text-jsr-gray-600 font-semibold block">Reason</span>
+          <input
+            type="text"
+            name="reason"
+            class="mt-1 p-1.5 input-container input w-88 bg-white"
+            placeholder="Release freeze"
+            required
+          />
+        </label>
+        <div class="flex flex-wrap gap-4">
+          <label class="block">
+            <span class="text-jsr-gray-600 font-semibold block">
+              Starts at (UTC)
+            </span>
+            <input
+              type="datetime-local"
+              name="startsAt"
+              class="mt-1 p-1.5 input-container input bg-white"
+              required
+            />
+          </label>
+          <label class="block">
+            <span class="text-jsr-gray-600 font-semibold block">
+              Ends at (UTC)
+            </span>
+            <input
+              type="datetime-local"
+              name="endsAt"
+              class="mt-1 p-1.5 input-container input bg-white"
+              required
+            />
+          </label>
+        </div>
+        <label class="flex items-center gap-2 text-jsr-gray-600">
+          <input type="checkbox" name="recursWeekly" value="true" />
+          Repeat every week
+        </label>
+        <div>
+          <button
+            name="action"
+            value="createPublishFreeze"
+            class="button-primary"
+            type="submit"
+          >
+            Add publish freeze
+          </button>
+        </div>
+      </form>
+    </div>
+  );
+}
+
 interface CardButtonProps {
   title: ComponentChildren;
   description: ComponentChildren;
@@ -252,9 +460,12 @@ function DeleteScope({ scope }: { scope: FullScope }) {
 
 export const handler = define.handlers({
   async GET(ctx) {
-    const [user, data] = await Promise.all([
+    const [user, data, publishFreezesResp] = await Promise.all([
       ctx.state.userPromise,
       scopeDataWithMember(ctx.state, ctx.params.scope),
+      ctx.state.api.get<ScopePublishFreeze[]>(
+        path`/scopes/${ctx.params.scope}/publish_freezes`,
+      ),
     ]);
     if (user instanceof Response) return user;
     if (data === null) throw new HttpError(404, "The scope was not found.");
@@ -262,11 +473,19 @@ export const handler = define.handlers({
     const iam = scopeIAM(ctx.state, data?.scopeMember, user);
     if (!iam.canAdmin) throw new HttpError(404, "The scope was not found.");
 
+    if (!publishFreezesResp.ok) {
+      if (publishFreezesResp.code === "scopeNotFound") {
+        throw new HttpError(404, "The scope was not found.");
+      }
+      throw publishFreezesResp; // graceful handle errors
+    }
+
     ctx.state.meta = { title: `Settings - @${data.scope.scope} - JSR` };
     return {
       data: {
         scope: data.scope as FullScope,
         iam,
+        publishFreezes: publishFreezesResp.data,
       },
     };
   },
@@ -313,6 +532,78 @@ export const handler = define.handlers({
           headers: { Location: `/@${scope}/~/settings` },
         });
       }
+      case "outdatedDependenciesEmail": {
+        const value = form.get("value") === "true";
+        const res = await ctx.state.api.patch(
+          path`/scopes/${scope}`,
+          { outdatedDependenciesEmail: value },
+        );
+        if (!res.ok) {
+          if (res.code === "scopeNotFound") {
+            throw new HttpError(404, "The scope was not found.");
+          }
+          throw res; // graceful handle errors
+        }
+        return new Response(null, {
+          status: 303,
+          headers: { Location: `/@${scope}/~/settings` },
+        });
+      }
+      case "downloadAnomalyEmail": {
+        const value = form.get("value") === "true";
+        const res = await ctx.state.api.patch(
+          path`/scopes/${scope}`,
+          { downloadAnomalyEmail: value },
+        );
+        if (!res.ok) {
+          if (res.code === "scopeNotFound") {
+            throw new HttpError(404, "The scope was not found.");
+          }
+          throw res; // graceful handle errors
+        }
+        return new Response(null, {
+          status: 303,
+          headers: { Location: `/@${scope}/~/settings` },
+        });
+      }
+      case "createPublishFreeze": {
+        const res = await ctx.state.api.post(
+          path`/scopes/${scope}/publish_freezes`,
+          {
+            // datetime-local inputs have no time zone, they are entered as UTC.
+            startsAt: new Date(`${form.get("startsAt")}Z`).toISOString(),
+            endsAt: new Date(`${form.get("endsAt")}Z`).toISOString(),
+            recursWeekly: form.get("recursWeekly") === "true",
+            reason: String(form.get("reason")),
+          },
+        );
+        if (!res.ok) {
+          if (res.code === "scopeNotFound") {
+            throw new HttpError(404, "The scope was not found.");
+          }
+          throw res; // graceful handle errors
+        }
+        return new Response(null, {
+          status: 303,
+          headers: { Location: `/@${scope}/~/settings` },
+        });
+      }
+      case "deletePublishFreeze": {
+        const freezeId = String(form.get("freezeId"));
+        const res = await ctx.state.api.delete(
+          path`/scopes/${scope}/publish_freezes/${freezeId}`,
+        );
+        if (!res.ok) {
+          if (res.code === "scopeNotFound") {
+            throw new HttpError(404, "The scope was not found.");
+          }
+          throw res; // graceful handle errors
+        }
+        return new Response(null, {
+          status: 303,
+          headers: { Location: `/@${scope}/~/settings` },
+        });
+      }
       case "deleteScope": {
         const res = await ctx.state.api.delete(path`/scopes/${scope}`);
         if (!res.ok) {
@@ -3,9 +3,12 @@ import { HttpError } from "fresh";
 import { AccountLayout } from "../account/(_components)/AccountLayout.tsx";
 import { QuotaCard } from "../../components/QuotaCard.tsx";
 import { define } from "../../util.ts";
+import { path } from "../../utils/api.ts";
+import { UserExport } from "../../utils/api_types.ts";
 
 export default define.page<typeof handler>(function AccountInvitesPage({
   data,
+  url,
 }) {
   const requestLimitIncreaseBody = `Hello JSR team,
 I would like to request a scope quota increase for my account.
@@ -50,6 +53,36 @@ Reason: `;
             </div>
           </div>
         </div>
+        <div>
+          <h2 class="text-xl mb-2 font-bold">Export your data</h2>
+          <p class="mt-2 text-jsr-gray-600 max-w-xl">
+            You can request an export of all personal data that JSR stores about
+            your account. Once the export is ready, a download link is emailed
+            to {data.user.email ?? "your email address"}. The link is valid for
+            7 days.
+          </p>
+          {url.searchParams.has("exportRequested")
+            ? (
+              <p class="mt-4 text-jsr-gray-600 max-w-xl">
+                Your export has been requested. You will receive an email in a
+                few minutes.
+              </p>
+            )
+            : data.user.email
+            ? (
+              <form method="POST" class="mt-4">
+                <button type="submit" class="button-primary">
+                  Request data export
+                </button>
+              </form>
+            )
+            : (
+              <p class="mt-4 text-jsr-gray-600 max-w-xl">
+                Add a public or primary email address to your GitHub account
+                and log in again to request an export.
+              </p>
+            )}
+        </div>
         <div>
           <h2 class="text-xl mb-2 font-bold">Delete account</h2>
           <p class="mt-2 text-jsr-gray-600 max-w-xl">
@@ -82,4 +115,9 @@ export const handler = define.handlers({
       },
     };
   },
+  async POST(ctx) {
+    const res = await ctx.state.api.get<UserExport>(path`/user/export`);
+    if (!res.ok) throw res; // graceful handle errors
+    return ctx.redirect("/account/settings?exportRequested", 303);
+  },
 });
@@ -0,0 +1,73 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+import { useSignal } from "@preact/signals";
+import { CopyButton } from "../../../../islands/CopyButton.tsx";
+import { api, APIResponseError, path } from "../../../../utils/api.ts";
+import { CreatedApiKey } from "../../../../utils/api_types.ts";
+import { ErrorDisplay } from "../../../../components/ErrorDisplay.tsx";
+
+export function CreateApiKey() {
+  const name = useSignal("");
+  const submitting = useSignal(false);
+  const error = useSignal<APIResponseError | null>(null);
+  const secret = useSignal<string | null>(null);
+
+  function onSubmit(e: Event) {
+    e.preventDefault();
+    submitting.value = true;
+    api.post<CreatedApiKey>(path`/user/api_keys`, { name: name.value })
+      .then((response) => {
+        submitting.value = false;
+        if (response.ok) {
+          secret.value = response.data.secret;
+        } else {
+          error.value = response;
+        }
+      });
+  }
+
+  if (secret.value !== null) {
+    return (
+      <div class="mt-4 max-w-2xl bg-blue-50 border border-blue-500 p-4 rounded-lg">
+        <p class="text-jsr-gray-600">
+          Your API key has been created. Copy it now, as you will not be able to
+          see it again.
+        </p>
+        <code class="mt-4 p-2 pr-4 bg-blue-100 rounded-md text-sm w-full relative flex items-center justify-between">
+          <div>{secret.value}</div>
+          <CopyButton text={secret.value} title="Copy API key" />
+        </code>
+        <button
+          class="link mt-4"
+          type="button"
+          onClick={() => location.reload()}
+        >
+          Done
+        </button>
+      </div>
+    );
+  }
+
+  return (
+    <form class="mt-4 max-w-2xl" onSubmit={onSubmit}>
+      <div class="flex gap-4 items-center">
+        <input
+          type="text"
+          class="p-1.5 input-container input w-72 bg-white"
+          placeholder="My search site"
+          required
+          maxLength={100}
+          value={name}
+          onInput={(e) => name.value = (e.target as HTMLInputElement).value}
+        />
+        <button class="button-primary" disabled={submitting}>
+          Create API key
+        </button>
+      </div>
+      {error.value !== null && (
+        <div class="mt-4">
+          <ErrorDisplay error={error.value} />
+        </div>
+      )}
+    </form>
+  );
+}
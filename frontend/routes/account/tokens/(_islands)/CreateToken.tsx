@@ -264,6 +264,7 @@ function CreateTokenForm() {
   const permission = useSignal<"package" | "scope" | "full" | null>(null);
   const scope = useSignal<string>("");
   const name = useSignal<string>("");
+  const bypassPublishFreezes = useSignal(false);
 
   const submitting = useSignal(false);
   const error = useSignal<APIResponseError | null>(null);
@@ -299,7 +300,11 @@ function CreateTokenForm() {
         }];
         break;
       case "scope":
-        permissions = [{ permission: "package/publish", scope: scope.value }];
+        permissions = [{
+          permission: "package/publish",
+          scope: scope.value,
+          bypassPublishFreezes: bypassPublishFreezes.value,
+        }];
         break;
       case "full":
         permissions = null;
@@ -331,7 +336,12 @@ function CreateTokenForm() {
     <form class="max-w-2xl mt-12" onSubmit={onSubmit}>
       <DescriptionInput description={description} />
       <ExpiryInput expiry={expiry} />
-      <PermissionsInput selected={permission} scope={scope} name={name} />
+      <PermissionsInput
+        selected={permission}
+        scope={scope}
+        name={name}
+        bypassPublishFreezes={bypassPublishFreezes}
+      />
       <button class="button-primary mt-10" disabled={disabled}>
         Create token
       </button>
@@ -391,10 +401,11 @@ function ExpiryInput({ expiry }: { expiry: Signal<number> }) {
 }
 
 function PermissionsInput(
-  { selected, scope, name }: {
+  { selected, scope, name, bypassPublishFreezes }: {
     selected: Signal<"package" | "scope" | "full" | null>;
     scope: Signal<string>;
     name: Signal<string>;
+    bypassPublishFreezes: Signal<boolean>;
   },
 ) {
   const onInput = useCallback((e: Event) => {
@@ -505,6 +516,21 @@ function PermissionsInput(
                 scope.value = (e.target as HTMLInputElement).value}
             />
           </div>
+          <label class="flex items-baseline ml-5 mb-2 text-sm">
+            <input
+              type="checkbox"
+              class="mr-2"
+              checked={bypassPublishFreezes}
+              disabled={selected.value !== "scope"}
+              onInput={(e) =>
+                bypassPublishFreezes.value =
+                  (e.target as HTMLInputElement).checked}
+            />
+            <span>
+              Allow publishing during publish freezes (emergency override, only
+              works if you are an admin of the scope)
+            </span>
+          </label>
         </div>
 
         <div class="border border-t-0 bg-jsr-gray-50 border-jsr-gray-200 -mx-3 px-3 -mb-1 py-1 rounded-b-lg flex flex-col sm:flex-row justify-between">
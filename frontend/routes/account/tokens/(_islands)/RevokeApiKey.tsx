@@ -0,0 +1,26 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+import { api, path } from "../../../../utils/api.ts";
+
+export function RevokeApiKey(props: { id: string }) {
+  function onClick(e: Event) {
+    e.preventDefault();
+    if (!confirm("Are you sure you want to revoke this API key?")) {
+      return;
+    }
+
+    api.delete(path`/user/api_keys/${props.id}`).then((res) => {
+      if (res.ok) {
+        location.reload();
+      } else {
+        console.error(res);
+        alert("Failed to revoke API key");
+      }
+    });
+  }
+
+  return (
+    <button class="text-red-600 underline hover:text-red-700" onClick={onClick}>
+      Revoke API key
+    </button>
+  );
+}
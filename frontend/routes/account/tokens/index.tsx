@@ -3,10 +3,12 @@
 import { HttpError } from "fresh";
 import { define } from "../../../util.ts";
 import { path } from "../../../utils/api.ts";
-import { Token } from "../../../utils/api_types.ts";
+import { ApiKey, Token } from "../../../utils/api_types.ts";
 import { AccountLayout } from "../(_components)/AccountLayout.tsx";
 import twas from "twas";
 import { RevokeToken } from "./(_islands)/RevokeToken.tsx";
+import { RevokeApiKey } from "./(_islands)/RevokeApiKey.tsx";
+import { CreateApiKey } from "./(_islands)/CreateApiKey.tsx";
 import { Plus } from "../../../components/icons/Plus.tsx";
 
 export default define.page<typeof handler>(function AccountTokensPage({
@@ -56,6 +58,24 @@ export default define.page<typeof handler>(function AccountTokensPage({
             </div>
           )}
       </div>
+      <div class="mt-8">
+        <h2 class="text-xl mt-4 mb-2 font-bold">API keys</h2>
+        <p class="text-jsr-gray-600 max-w-2xl">
+          API keys identify tools that read a lot of data from the JSR API, like
+          search sites and analytics services. Requests that send an API key in
+          the <code>X-JSR-API-Key</code>{" "}
+          header have a higher rate limit than anonymous requests. API keys can
+          only read public data, and can not act on behalf of your account.
+        </p>
+
+        {data.apiKeys.length > 0 && (
+          <ul class="max-w-2xl divide-slate-200 divide-y border-t border-b border-slate-200 mt-4">
+            {data.apiKeys.map((apiKey) => <ApiKeyRow apiKey={apiKey} />)}
+          </ul>
+        )}
+
+        <CreateApiKey />
+      </div>
       <div class="mt-8">
         <h2 class="text-xl mt-4 mb-2 font-bold">Sessions</h2>
         <p class="text-jsr-gray-600 max-w-2xl">
@@ -128,7 +148,17 @@ function PersonalTokenRow({ token }: { token: Token }) {
               return `Can publish ${
                 "package" in perm
                   ? `new versions of @${perm.scope}/${perm.package}`
-                  : `new versions of any package in @${perm.scope}`
+                  : "packages" in perm
+                  ? `new versions of ${
+                    perm.packages.map((pkg) => `@${perm.scope}/${pkg}`).join(
+                      ", ",
+                    )
+                  }`
+                  : `new versions of any package in @${perm.scope}${
+                    "bypassPublishFreezes" in perm && perm.bypassPublishFreezes
+                      ? ", including during publish freezes"
+                      : ""
+                  }`
               }`;
             }
             return `has unknown permission: ${perm.permission}`;
@@ -138,6 +168,29 @@ function PersonalTokenRow({ token }: { token: Token }) {
   );
 }
 
+function ApiKeyRow({ apiKey }: { apiKey: ApiKey }) {
+  return (
+    <li class="py-2">
+      <div class="flex justify-between">
+        <p class="text-jsr-gray-600">{apiKey.name}</p>
+        <p class="text-sm text-right">
+          <RevokeApiKey id={apiKey.id} />
+        </p>
+      </div>
+      <div class="grid sm:grid-cols-2">
+        <p class="text-sm">
+          {apiKey.requestCount.toLocaleString()} requests
+          {apiKey.lastUsedAt &&
+            `, last used ${twas(new Date(apiKey.lastUsedAt).getTime())}`}
+        </p>
+        <p class="text-sm sm:text-right">
+          Created {twas(new Date(apiKey.createdAt).getTime())}
+        </p>
+      </div>
+    </li>
+  );
+}
+
 function SessionRow({ token }: { token: Token }) {
   const expiresAt = token.expiresAt ? new Date(token.expiresAt) : null;
   const isActive = expiresAt === null || expiresAt.getTime() > Date.now();
@@ -186,20 +239,23 @@ function SessionRow({ token }: { token: Token }) {
 
 export const handler = define.handlers({
   async GET(ctx) {
-    const [currentUser, tokensRes] = await Promise.all([
+    const [currentUser, tokensRes, apiKeysRes] = await Promise.all([
       ctx.state.userPromise,
       ctx.state.api.get<Token[]>(path`/user/tokens`),
+      ctx.state.api.get<ApiKey[]>(path`/user/api_keys`),
     ]);
     if (currentUser instanceof Response) return currentUser;
     if (!currentUser) throw new HttpError(404, "No signed in user found.");
 
     if (!tokensRes.ok) throw tokensRes; // gracefully handle errors
+    if (!apiKeysRes.ok) throw apiKeysRes;
 
     ctx.state.meta = { title: "Your tokens - JSR" };
     return {
       data: {
         user: currentUser,
         tokens: tokensRes.data,
+        apiKeys: apiKeysRes.data,
       },
     };
   },
@@ -0,0 +1,26 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+import type { RouteConfig } from "fresh";
+import { define } from "../../util.ts";
+
+// The SVG badges used to be rendered here, at
+// `/badges/<kind>/@<scope>/<package>.svg`. They are now served by the API at
+// `/badges/<scope>/<package>/<kind>.svg`, and the old URLs, which are embedded
+// in many READMEs, redirect there.
+export const handler = define.handlers({
+  GET(ctx) {
+    const { kind, scope, package: name } = ctx.params;
+    const location = `/badges/${scope}/${name}/${kind}.svg${ctx.url.search}`;
+    return new Response(null, {
+      status: 301, // Moved Permanently
+      headers: {
+        Location: location,
+        "access-control-allow-origin": "*",
+        "cache-control": "public, max-age=86400",
+      },
+    });
+  },
+});
+
+export const config: RouteConfig = {
+  routeOverride: "/badges/:kind(version|downloads|score)/@:scope/:package.svg",
+};
@@ -1,5 +1,8 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
-import type { PackageVersionWithUser } from "../../../utils/api_types.ts";
+import type {
+  PackageVersionPublishInfo,
+  PackageVersionWithUser,
+} from "../../../utils/api_types.ts";
 import { LocalSymbolSearch } from "../(_islands)/LocalSymbolSearch.tsx";
 import { Docs } from "../../../util.ts";
 import { Params } from "./PackageNav.tsx";
@@ -9,6 +12,7 @@ interface DocsProps {
   docs: Docs;
   params: Params;
   selectedVersion: PackageVersionWithUser;
+  publishInfo?: PackageVersionPublishInfo | null;
   showProvenanceBadge?: boolean;
 }
 
@@ -33,8 +37,11 @@ export function DocsView({
   docs,
   params,
   selectedVersion,
+  publishInfo,
   showProvenanceBadge,
 }: DocsProps) {
+  const workflowRun = publishInfo?.githubWorkflowRun;
+
   return (
     <div class="pt-6 space-y-8">
       <style hidden dangerouslySetInnerHTML={{ __html: docs.css }} />
@@ -102,6 +109,25 @@ export function DocsView({
                   View transparency log
                 </a>
               </div>
+
+              {workflowRun && (
+                <div class="mt-2 text-sm text-jsr-gray-500">
+                  <span class="font-mono">{workflowRun.workflowPath}</span> in
+                  {" "}
+                  <span class="font-mono">{workflowRun.repository}</span> on
+                  {" "}
+                  <span class="font-mono">{workflowRun.ref}</span>
+                  {" · "}
+                  <a
+                    href={workflowRun.runUrl}
+                    target="_blank"
+                    rel="noopener noreferrer"
+                    class="link"
+                  >
+                    View workflow run
+                  </a>
+                </div>
+              )}
             </div>
           )}
         </div>
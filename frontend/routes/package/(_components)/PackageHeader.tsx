@@ -9,6 +9,8 @@ import { WarningTriangle } from "../../../components/icons/WarningTriangle.tsx";
 import { Tooltip } from "../../../components/Tooltip.tsx";
 import twas from "twas";
 import { greaterThan, parse } from "@std/semver";
+import { YANK_REASON_CATEGORIES } from "../../../utils/yank_reason.ts";
+import { MAINTENANCE_STATUSES } from "../../../utils/maintenance_status.ts";
 
 interface PackageHeaderProps {
   package: Package;
@@ -39,6 +41,23 @@ export function PackageHeader({
         </div>
       )}
 
+      {pkg.successor && (
+        <div class="border border-jsr-yellow-500 bg-jsr-yellow-50 rounded py-3 px-4 md:text-center">
+          <div class="text-sm md:text-base flex items-center justify-center gap-4 md:gap-2">
+            <WarningTriangle class="text-jsr-yellow-400 flex-none" />
+            <span class="font-medium">
+              This package has moved to{" "}
+              <a
+                class="link"
+                href={`/@${pkg.successor.scope}/${pkg.successor.name}`}
+              >
+                @{pkg.successor.scope}/{pkg.successor.name}
+              </a>.
+            </span>
+          </div>
+        </div>
+      )}
+
       {selectedVersion && pkg.latestVersion &&
         pkg.latestVersion !== selectedVersion.version && (
         <div class="border border-jsr-yellow-500 bg-jsr-yellow-50 rounded py-3 px-4 md:text-center">
@@ -48,8 +67,16 @@ export function PackageHeader({
               This release {selectedVersion.yanked
                 ? (
                   <>
-                    was yanked — the latest version of @{pkg
-                      .scope}/{pkg.name} is {pkg.latestVersion}.
+                    was yanked
+                    {selectedVersion.yankReasonCategory &&
+                      ` (${
+                        YANK_REASON_CATEGORIES[
+                          selectedVersion.yankReasonCategory
+                        ].toLowerCase()
+                      })`}
+                    {selectedVersion.yankReason &&
+                      `: ${selectedVersion.yankReason}`} — the latest version
+                    of @{pkg.scope}/{pkg.name} is {pkg.latestVersion}.
                   </>
                 )
                 : isNewerPrerelease
@@ -137,10 +164,29 @@ export function PackageHeader({
                 </div>
               )}
 
+              {pkg.maintenanceStatus && (
+                <div
+                  class={`chip sm:big-chip select-none ${
+                    pkg.maintenanceStatus === "unmaintained"
+                      ? "bg-red-500 text-white"
+                      : pkg.maintenanceStatus === "actively_maintained"
+                      ? "bg-green-100 text-green-800"
+                      : "bg-jsr-gray-100"
+                  }`}
+                  title="Maintenance status"
+                >
+                  {MAINTENANCE_STATUSES[pkg.maintenanceStatus].toLowerCase()}
+                </div>
+              )}
+
               {pkg.githubRepository && (
                 <a
                   class="chip sm:big-chip bg-jsr-gray-100 !inline-flex items-center gap-1 select-none"
-                  href={`https://github.com/${pkg.githubRepository.owner}/${pkg.githubRepository.name}`}
+                  href={`https://github.com/${pkg.githubRepository.owner}/${pkg.githubRepository.name}${
+                    pkg.githubRepository.subdirectory
+                      ? `/tree/HEAD/${pkg.githubRepository.subdirectory}`
+                      : ""
+                  }`}
                   target="_blank"
                   rel="noopener noreferrer"
                   aria-label="GitHub repository"
@@ -148,6 +194,8 @@ export function PackageHeader({
                   <GitHub class="text-black !size-4" aria-hidden={true} />
                   <span>
                     {pkg.githubRepository.owner}/{pkg.githubRepository.name}
+                    {pkg.githubRepository.subdirectory &&
+                      `/${pkg.githubRepository.subdirectory}`}
                   </span>
                   <ExternalLink strokeWidth="2.25" />
                 </a>
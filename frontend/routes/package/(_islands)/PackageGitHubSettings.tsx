@@ -18,8 +18,10 @@ export function PackageGitHubSettings(
 
   const originalOwner = props.repo?.owner ?? "";
   const originalRepo = props.repo?.name ?? "";
+  const originalSubdirectory = props.repo?.subdirectory ?? "";
   const owner = useSignal<string>(originalOwner);
   const repo = useSignal<string>(originalRepo);
+  const subdirectory = useSignal<string>(originalSubdirectory);
   const error = useSignal<string>("");
 
   async function onSubmit(e: JSX.TargetedEvent<HTMLFormElement>) {
@@ -29,13 +31,21 @@ export function PackageGitHubSettings(
 
     const rawOwner = data.get("owner");
     const rawRepo = data.get("repo");
+    const rawSubdirectory = data.get("subdirectory");
     owner.value = rawOwner ? String(rawOwner) : "";
     repo.value = rawRepo ? String(rawRepo) : "";
+    subdirectory.value = rawSubdirectory ? String(rawSubdirectory) : "";
 
     processing.value = true;
     const res = await api.patch(
       path`/scopes/${props.scope}/packages/${props.package}`,
-      { githubRepository: { owner: owner.value, name: repo.value } },
+      {
+        githubRepository: {
+          owner: owner.value,
+          name: repo.value,
+          subdirectory: subdirectory.value || null,
+        },
+      },
     );
     processing.value = false;
     if (!res.ok) {
@@ -59,13 +69,28 @@ export function PackageGitHubSettings(
           disabled={processing}
         />
 
+        <input
+          class="input-container input w-full md:w-64 block"
+          type="text"
+          name="subdirectory"
+          placeholder="Subdirectory (optional)"
+          title="The directory of the repository that contains the package, for monorepos"
+          value={subdirectory}
+          onInput={(e) => {
+            subdirectory.value = e.currentTarget.value;
+            error.value = "";
+          }}
+          disabled={processing}
+        />
+
         <button
           class="button-primary"
           type="submit"
           name="action"
           value="updateRepo"
           disabled={processing.value ||
-            !(owner.value !== originalOwner || repo.value !== originalRepo)}
+            !(owner.value !== originalOwner || repo.value !== originalRepo ||
+              subdirectory.value !== originalSubdirectory)}
         >
           Save
         </button>
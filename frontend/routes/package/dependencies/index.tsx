@@ -32,9 +32,11 @@ export default define.page<typeof handler>(function Deps(
     string,
     {
       link: string;
+      type: Dependency["type"];
       constraints: Set<string>;
       modules: Record<string, string | undefined>;
       defaultModule: boolean;
+      isArchived: boolean;
     }
   > = {};
 
@@ -42,9 +44,11 @@ export default define.page<typeof handler>(function Deps(
     const key = `${dep.kind}:${dep.name}`;
     deps[key] ??= {
       link: getDependencyLink(dep),
+      type: dep.type,
       constraints: new Set(),
       modules: {},
       defaultModule: false,
+      isArchived: dep.isArchived,
     };
     deps[key].constraints.add(dep.constraint);
     if (dep.path) {
@@ -95,9 +99,11 @@ export default define.page<typeof handler>(function Deps(
                   <Dependency
                     name={name}
                     link={info.link}
+                    type={info.type}
                     constraints={[...info.constraints]}
                     modules={Object.entries(info.modules)}
                     defaultModule={info.defaultModule}
+                    isArchived={info.isArchived}
                   />
                 ))}
               </Table>
@@ -116,12 +122,14 @@ export default define.page<typeof handler>(function Deps(
 });
 
 function Dependency(
-  { name, link, constraints, modules, defaultModule }: {
+  { name, link, type, constraints, modules, defaultModule, isArchived }: {
     name: string;
     link: string;
+    type: Dependency["type"];
     constraints: string[];
     modules: [path: string, link?: string][];
     defaultModule: boolean;
+    isArchived: boolean;
   },
 ) {
   return (
@@ -130,6 +138,12 @@ function Dependency(
         <a href={link} class="link">
           {name}
         </a>
+        {type !== "regular" && (
+          <span class="ml-2 text-sm text-jsr-gray-500">({type})</span>
+        )}
+        {isArchived && (
+          <span class="ml-2 text-sm text-jsr-yellow-700">(archived)</span>
+        )}
       </TableData>
       <TableData class="space-x-4">
         {constraints.map((constraint) => <span>{constraint}</span>)}
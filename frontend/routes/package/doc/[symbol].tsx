@@ -102,7 +102,11 @@ export const handler = define.handlers({
         docs,
         member: scopeMember,
       },
-      headers: { ...(ctx.params.version ? { "X-Robots-Tag": "noindex" } : {}) },
+      headers: {
+        ...(ctx.params.version || pkg.noindex
+          ? { "X-Robots-Tag": "noindex" }
+          : {}),
+      },
     };
   },
 });
@@ -6,6 +6,8 @@ import { PackageNav, Params } from "./(_components)/PackageNav.tsx";
 import { PackageHeader } from "./(_components)/PackageHeader.tsx";
 import { DocsView } from "./(_components)/Docs.tsx";
 import { scopeIAM } from "../../utils/iam.ts";
+import { path } from "../../utils/api.ts";
+import type { PackageVersionPublishInfo } from "../../utils/api_types.ts";
 
 const FRONTEND_ROOT = Deno.env.get("FRONTEND_ROOT") ?? "http://jsr.test";
 
@@ -34,6 +36,7 @@ export default define.page<typeof handler>(function PackagePage(
             docs={data.docs}
             params={params as unknown as Params}
             selectedVersion={data.selectedVersion}
+            publishInfo={data.publishInfo}
             showProvenanceBadge
           />
         )
@@ -84,6 +87,14 @@ export const handler = define.handlers({
       });
     }
 
+    let publishInfo: PackageVersionPublishInfo | null = null;
+    if (selectedVersion?.rekorLogId) {
+      const resp = await ctx.state.api.get<PackageVersionPublishInfo>(
+        path`/scopes/${pkg.scope}/packages/${pkg.name}/versions/${selectedVersion.version}/publish_info`,
+      );
+      if (resp.ok) publishInfo = resp.data;
+    }
+
     ctx.state.meta = {
       title: `@${pkg.scope}/${pkg.name} - JSR`,
       description: `@${pkg.scope}/${pkg.name} on JSR${
@@ -96,9 +107,14 @@ export const handler = define.handlers({
         package: pkg,
         selectedVersion,
         docs,
+        publishInfo,
         member: scopeMember,
       },
-      headers: { ...(ctx.params.version ? { "X-Robots-Tag": "noindex" } : {}) },
+      headers: {
+        ...(ctx.params.version || pkg.noindex
+          ? { "X-Robots-Tag": "noindex" }
+          : {}),
+      },
     };
   },
 });
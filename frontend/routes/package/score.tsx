@@ -201,6 +201,32 @@ function ScoreInfo(props: {
             public transparency log entry
           </a>.
         </ScoreItem>
+        <ScoreItem
+          value={score.hasPermissionsDeclaration}
+          scoreValue={1}
+          title="Declares its permissions"
+        >
+          The package should declare the Deno permissions it needs in the{" "}
+          <a class="link" href="/docs/package-configuration#permissions">
+            <code>permissions</code> field
+          </a>{" "}
+          of its config file.{" "}
+          {score.permissions && (
+            <>
+              {Object.keys(score.permissions).length === 0
+                ? "It declares that it needs no permissions."
+                : `It declares that it needs: ${
+                  Object.entries(score.permissions)
+                    .map(([name, targets]) =>
+                      targets === true
+                        ? `${name} (all)`
+                        : `${name} (${targets.join(", ")})`
+                    )
+                    .join("; ")
+                }.`}
+            </>
+          )}
+        </ScoreItem>
       </ul>
     </div>
   );
@@ -1,6 +1,11 @@
 // Copyright 2024 the JSR authors. All rights reserved. MIT license.
 import { HttpError, RouteConfig } from "fresh";
-import type { Package, RuntimeCompat } from "../../utils/api_types.ts";
+import type {
+  MaintenanceStatus as MaintenanceStatusType,
+  Package,
+  PackageSuccessor,
+  RuntimeCompat,
+} from "../../utils/api_types.ts";
 import { path } from "../../utils/api.ts";
 import { define } from "../../util.ts";
 import { PackageGitHubSettings } from "./(_islands)/PackageGitHubSettings.tsx";
@@ -10,6 +15,7 @@ import { PackageNav, Params } from "./(_components)/PackageNav.tsx";
 import { PackageDescriptionEditor } from "./(_islands)/PackageDescriptionEditor.tsx";
 import { RUNTIME_COMPAT_KEYS } from "../../components/RuntimeCompatIndicator.tsx";
 import { scopeIAM } from "../../utils/iam.ts";
+import { MAINTENANCE_STATUSES } from "../../utils/maintenance_status.ts";
 
 export default define.page<typeof handler>(function Settings({ data, params }) {
   return (
@@ -30,6 +36,12 @@ export default define.page<typeof handler>(function Settings({ data, params }) {
 
       <GitHubRepository package={data.package} />
 
+      <DocsLandingPage docsLandingPage={data.package.docsLandingPage} />
+
+      <Successor successor={data.package.successor} />
+
+      <MaintenanceStatus package={data.package} />
+
       <ArchivePackage isArchived={data.package.isArchived} />
 
       <DeletePackage hasVersions={data.package.versionCount > 0} />
@@ -45,6 +57,9 @@ export default define.page<typeof handler>(function Settings({ data, params }) {
           <form method="POST">
             <FeaturePackage package={data.package} />
           </form>
+          <form method="POST">
+            <NoindexPackage package={data.package} />
+          </form>
         </div>
       )}
     </div>
@@ -159,6 +174,143 @@ function RuntimeCompatEditorItem({ name, id, value }: {
   );
 }
 
+function DocsLandingPage(props: { docsLandingPage: string | null }) {
+  return (
+    <form class="border-t pt-8 mt-12" method="POST">
+      <h2 class="text-xl font-sans font-bold">Documentation landing page</h2>
+
+      <p class="mt-2 text-jsr-gray-600 max-w-3xl">
+        The page shown on the documentation tab of the package. This can be an
+        export (for example <code>./cli</code>) or a markdown file in the
+        package (for example <code>/docs/guide.md</code>). Leave empty to show
+        the README.
+      </p>
+
+      <div class="flex gap-4 items-center mt-4">
+        <input
+          class="input-container input w-full max-w-sm block"
+          type="text"
+          name="docsLandingPage"
+          placeholder="README"
+          value={props.docsLandingPage ?? ""}
+        />
+        <button
+          class="button-primary"
+          type="submit"
+          name="action"
+          value="updateDocsLandingPage"
+        >
+          Save
+        </button>
+      </div>
+    </form>
+  );
+}
+
+function Successor(props: { successor: PackageSuccessor | null }) {
+  return (
+    <form class="border-t pt-8 mt-12" method="POST">
+      <h2 class="text-xl font-sans font-bold">Successor</h2>
+
+      <p class="mt-2 text-jsr-gray-600 max-w-3xl">
+        If this package has moved, set the package that replaces it (for
+        example <code>@scope/package</code>). Users of this package will be
+        pointed to the successor, and this package will be ranked lower in
+        search results. Leave empty if the package has not moved.
+      </p>
+
+      <div class="flex gap-4 items-center mt-4">
+        <input
+          class="input-container input w-full max-w-sm block"
+          type="text"
+          name="successor"
+          placeholder="@scope/package"
+          value={props.successor
+            ? `@${props.successor.scope}/${props.successor.name}`
+            : ""}
+        />
+        <button
+          class="button-primary"
+          type="submit"
+          name="action"
+          value="updateSuccessor"
+        >
+          Save
+        </button>
+      </div>
+    </form>
+  );
+}
+
+function MaintenanceStatus(props: { package: Package }) {
+  const { maintenanceStatus, unmaintainedSuggestedAt } = props.package;
+  return (
+    <form class="border-t pt-8 mt-12" method="POST">
+      <h2 class="text-xl font-sans font-bold">Maintenance status</h2>
+
+      <p class="mt-2 text-jsr-gray-600 max-w-3xl">
+        Let users know whether this package is still maintained. The status is
+        shown on the package page, and can be used to filter search results.
+      </p>
+
+      {unmaintainedSuggestedAt && (
+        <div class="mt-4 max-w-3xl rounded border border-jsr-yellow-500 bg-jsr-yellow-50 py-3 px-4">
+          <p>
+            This package has not seen a new version in a long time. Is it still
+            maintained? Mark it as unmaintained, or keep the current status to
+            dismiss this suggestion.
+          </p>
+          <div class="flex gap-4 mt-3">
+            <button
+              class="button-danger"
+              type="submit"
+              name="maintenanceStatus"
+              value="unmaintained"
+            >
+              Mark as unmaintained
+            </button>
+            <button
+              class="button-primary"
+              type="submit"
+              name="maintenanceStatus"
+              value={maintenanceStatus ?? ""}
+            >
+              Keep current status
+            </button>
+          </div>
+          <input type="hidden" name="action" value="updateMaintenanceStatus" />
+        </div>
+      )}
+
+      {!unmaintainedSuggestedAt && (
+        <div class="flex gap-4 items-center mt-4">
+          <select
+            name="maintenanceStatus"
+            class="input-container select w-full max-w-sm block"
+          >
+            <option value="" selected={maintenanceStatus === null}>
+              Not set
+            </option>
+            {Object.entries(MAINTENANCE_STATUSES).map(([value, label]) => (
+              <option value={value} selected={maintenanceStatus === value}>
+                {label}
+              </option>
+            ))}
+          </select>
+          <button
+            class="button-primary"
+            type="submit"
+            name="action"
+            value="updateMaintenanceStatus"
+          >
+            Save
+          </button>
+        </div>
+      )}
+    </form>
+  );
+}
+
 function ArchivePackage(props: { isArchived: boolean }) {
   if (!props.isArchived) {
     return (
@@ -265,6 +417,32 @@ function FeaturePackage(props: { package: Package }) {
   );
 }
 
+function NoindexPackage(props: { package: Package }) {
+  if (props.package.noindex) {
+    return (
+      <button
+        class="button-primary mt-4"
+        type="submit"
+        name="action"
+        value="isIndexed"
+      >
+        Allow search engine indexing
+      </button>
+    );
+  }
+
+  return (
+    <button
+      class="button-danger mt-4"
+      type="submit"
+      name="action"
+      value="isNoindex"
+    >
+      Prevent search engine indexing
+    </button>
+  );
+}
+
 export const handler = define.handlers({
   async GET(ctx) {
     const [user, data] = await Promise.all([
@@ -347,12 +525,64 @@ export const handler = define.handlers({
           headers: { Location: `/@${scope}/${packageName}/settings` },
         });
       }
+      case "updateDocsLandingPage": {
+        const docsLandingPage = String(data.get("docsLandingPage") ?? "")
+          .trim();
+        const repoRes = await api.patch(
+          path`/scopes/${scope}/packages/${packageName}`,
+          { docsLandingPage: docsLandingPage || null },
+        );
+        if (!repoRes.ok) throw repoRes;
+        return new Response(null, {
+          status: 303,
+          headers: { Location: `/@${scope}/${packageName}/settings` },
+        });
+      }
+      case "updateSuccessor": {
+        const successor = String(data.get("successor") ?? "").trim();
+        let body: PackageSuccessor | null = null;
+        if (successor) {
+          const match = successor.match(/^@?([^/]+)\/([^/]+)$/);
+          if (!match) {
+            throw new HttpError(
+              400,
+              "The successor must be a package name like @scope/package.",
+            );
+          }
+          body = { scope: match[1], name: match[2] };
+        }
+        const repoRes = await api.patch(
+          path`/scopes/${scope}/packages/${packageName}`,
+          { successor: body },
+        );
+        if (!repoRes.ok) throw repoRes;
+        return new Response(null, {
+          status: 303,
+          headers: { Location: `/@${scope}/${packageName}/settings` },
+        });
+      }
+      case "updateMaintenanceStatus": {
+        const maintenanceStatus = String(data.get("maintenanceStatus") ?? "");
+        const repoRes = await api.patch(
+          path`/scopes/${scope}/packages/${packageName}`,
+          {
+            maintenanceStatus:
+              (maintenanceStatus || null) as MaintenanceStatusType | null,
+          },
+        );
+        if (!repoRes.ok) throw repoRes;
+        return new Response(null, {
+          status: 303,
+          headers: { Location: `/@${scope}/${packageName}/settings` },
+        });
+      }
       case "updateRepo": {
         const owner = String(data.get("owner"));
         const name = String(data.get("repo"));
+        const subdirectory = String(data.get("subdirectory") ?? "") || null;
         const repoRes = await api.patch(
           path`/scopes/${scope}/packages/${packageName}`,
-          { githubRepository: { owner, name } },
+          { githubRepository: { owner, name, subdirectory } },
         );
         if (!repoRes.ok) throw repoRes;
         return new Response(null, {
@@ -413,6 +643,18 @@ export const handler = define.handlers({
           headers: { Location: `/@${scope}/${packageName}/settings` },
         });
       }
+      case "isNoindex":
+      case "isIndexed": {
+        const repoRes = await api.patch(
+          path`/scopes/${scope}/packages/${packageName}`,
+          { noindex: action === "isNoindex" },
+        );
+        if (!repoRes.ok) throw repoRes;
+        return new Response(null, {
+          status: 303,
+          headers: { Location: `/@${scope}/${packageName}/settings` },
+        });
+      }
       default: {
         throw new Error("Invalid action " + action);
       }
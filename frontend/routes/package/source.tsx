@@ -79,6 +79,17 @@ export default define.page<typeof handler>(function PackagePage(
           })}
         </div>
 
+        {data.source?.repositoryUrl && (
+          <a
+            class="link text-sm pl-3 inline-flex items-center gap-1"
+            href={data.source.repositoryUrl}
+            target="_blank"
+            rel="noopener noreferrer"
+          >
+            View on GitHub at the commit this version was built from
+          </a>
+        )}
+
         {data.source
           ? (
             data.source.source.kind == "dir"
@@ -199,7 +210,11 @@ export const handler = define.handlers({
         sourcePath,
         member: scopeMember,
       },
-      headers: { ...(ctx.params.version ? { "X-Robots-Tag": "noindex" } : {}) },
+      headers: {
+        ...(ctx.params.version || pkg.noindex
+          ? { "X-Robots-Tag": "noindex" }
+          : {}),
+      },
     };
   },
 });
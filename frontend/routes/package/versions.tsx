@@ -9,7 +9,7 @@ import { define } from "../../util.ts";
 import { compare, equals, format, lessThan, parse, SemVer } from "@std/semver";
 import twas from "twas";
 import IconTrashX from "$tabler_icons/trash-x.tsx";
-import { packageData } from "../../utils/data.ts";
+import { listPackageVersions, packageData } from "../../utils/data.ts";
 import { PackageHeader } from "./(_components)/PackageHeader.tsx";
 import { PackageNav, Params } from "./(_components)/PackageNav.tsx";
 import { path } from "../../utils/api.ts";
@@ -17,6 +17,7 @@ import { ErrorIcon } from "../../components/icons/Error.tsx";
 import { Check } from "../../components/icons/Check.tsx";
 import { Pending } from "../../components/icons/Pending.tsx";
 import { ScopeIAM, scopeIAM } from "../../utils/iam.ts";
+import { YANK_REASON_CATEGORIES } from "../../utils/yank_reason.ts";
 
 export default define.page<typeof handler>(function Versions({
   data,
@@ -118,6 +119,8 @@ export default define.page<typeof handler>(function Versions({
                 tasks={version.tasks}
                 releaseTrack={version.releaseTrack}
                 isLatestInReleaseTrack={isLatestInReleaseTrack}
+                isLatest={version.version?.version ===
+                  data.package.latestVersion}
                 iam={iam}
               />
             );
@@ -142,6 +145,7 @@ function ordinalNumber(number: number): string {
 
 const statusVerb: Record<PublishingTaskStatus, string> = {
   "pending": "is queued",
+  "scheduled": "is scheduled",
   "success": "succeeded",
   "failure": "failed",
   "processed": "is processing",
@@ -154,6 +158,7 @@ function Version({
   tasks,
   releaseTrack,
   isLatestInReleaseTrack,
+  isLatest,
   iam,
 }: {
   semver: SemVer;
@@ -161,6 +166,7 @@ function Version({
   tasks: PublishingTask[];
   releaseTrack: string;
   isLatestInReleaseTrack: boolean;
+  isLatest: boolean;
   iam: ScopeIAM;
 }) {
   const isPublished = version !== null;
@@ -240,11 +246,50 @@ function Version({
                 {twas(new Date(version.createdAt).getTime())}
               </div>
             )}
+            {version?.yanked &&
+              (version.yankReasonCategory || version.yankReason) && (
+              <div class="text-sm text-red-700 z-0">
+                Yanked
+                {version.yankReasonCategory &&
+                  ` (${YANK_REASON_CATEGORIES[version.yankReasonCategory]})`}
+                {version.yankReason && `: ${version.yankReason}`}
+              </div>
+            )}
           </div>
         </div>
         {isPublished && iam.canAdmin && (
-          <form method="POST" class="z-20">
+          <form method="POST" class="z-20 flex items-center gap-2">
             <input type="hidden" name="version" value={version.version} />
+            {!version.yanked && (
+              <>
+                <select
+                  name="yankReasonCategory"
+                  class="input-container select text-sm"
+                  title="Why is this version yanked?"
+                >
+                  <option value="">No reason</option>
+                  {Object.entries(YANK_REASON_CATEGORIES).map((
+                    [value, label],
+                  ) => <option value={value}>{label}</option>)}
+                </select>
+                <input
+                  type="text"
+                  name="yankReason"
+                  class="input-container input text-sm hidden md:block"
+                  placeholder="Details (optional)"
+                  maxLength={1000}
+                />
+                {isLatest && (
+                  <label
+                    class="flex items-center gap-1 text-sm"
+                    title="This is the latest version of the package. Yanking it moves the latest tag to an older version."
+                  >
+                    <input type="checkbox" name="force" required />
+                    Yank latest
+                  </label>
+                )}
+              </>
+            )}
             <button
               class="button-danger"
               name="action"
@@ -280,20 +325,19 @@ function Version({
 
 export const handler = define.handlers({
   async GET(ctx) {
-    const [res, versionsResp, tasksResp] = await Promise.all([
+    const [res, versions, tasksResp] = await Promise.all([
       packageData(ctx.state, ctx.params.scope, ctx.params.package),
-      ctx.state.api.get<PackageVersionWithUser[]>(
-        path`/scopes/${ctx.params.scope}/packages/${ctx.params.package}/versions`,
-      ),
+      listPackageVersions(ctx.state, ctx.params.scope, ctx.params.package),
       ctx.state.api.hasToken()
         ? ctx.state.api.get<PublishingTask[]>(
           path`/scopes/${ctx.params.scope}/packages/${ctx.params.package}/publishing_tasks`,
         )
         : Promise.resolve(null),
     ]);
-    if (res === null) throw new HttpError(404, "This package was not found.");
+    if (res === null || versions === null) {
+      throw new HttpError(404, "This package was not found.");
+    }
 
-    if (!versionsResp.ok) throw versionsResp; // TODO: handle errors gracefully
     let publishingTasks;
     if (tasksResp) {
       if (!tasksResp.ok) {
@@ -314,10 +358,11 @@ export const handler = define.handlers({
     return {
       data: {
         package: res.pkg,
-        versions: versionsResp.data,
+        versions,
         publishingTasks,
         member: res.scopeMember,
       },
+      headers: res.pkg.noindex ? { "X-Robots-Tag": "noindex" } : {},
     };
   },
 
@@ -337,7 +382,13 @@ export const handler = define.handlers({
         const version = String(data.get("version"));
         const res = await api.patch(
           path`/scopes/${scope}/packages/${packageName}/versions/${version}`,
-          { yanked: true },
+          {
+            yanked: true,
+            yankReasonCategory: data.get("yankReasonCategory") || undefined,
+            yankReason: String(data.get("yankReason") ?? "").trim() ||
+              undefined,
+            force: data.get("force") === "on",
+          },
         );
         if (!res.ok) throw res;
         return new Response(null, {
@@ -17,6 +17,7 @@ export interface VersionPublishStatus {
 
 const statusVerb: Record<PublishingTaskStatus, string> = {
   "pending": "is queued",
+  "scheduled": "is scheduled",
   "success": "succeeded",
   "failure": "failed",
   "processed": "is processing",
@@ -89,12 +90,34 @@ export function PackagePublishStatus(props: {
           )}
         Publish {statusVerb[task.status]}
       </p>
+      {task.status === "scheduled" && task.publishAt && (
+        <p class="text-jsr-gray-600 max-w-3xl ml-6 text-sm">
+          The package was validated successfully. The version will be published
+          at {new Date(task.publishAt).toLocaleString()}.
+        </p>
+      )}
       {task.error && (
         <p class="text-red-700 max-w-3xl ml-6 text-sm">
           <span class="font-mono font-semibold">{task.error.code}</span>:{" "}
           {task.error.message}
         </p>
       )}
+      {task.artifactsExpireAt && (
+        <p class="text-jsr-gray-600 max-w-3xl ml-6 text-sm">
+          To debug this failure, download the{" "}
+          <a class="link" href={`/api/publishing_tasks/${task.id}/tarball`}>
+            uploaded tarball
+          </a>{" "}
+          or the{" "}
+          <a
+            class="link"
+            href={`/api/publishing_tasks/${task.id}/diagnostic_log`}
+          >
+            diagnostic log
+          </a>. They are available until{" "}
+          {new Date(task.artifactsExpireAt).toLocaleString()}.
+        </p>
+      )}
     </>
   );
 }
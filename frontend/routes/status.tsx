@@ -3,6 +3,7 @@ import { HttpError, RouteConfig } from "fresh";
 import { define } from "../util.ts";
 import type {
   PublishingTask,
+  PublishingTaskSizeReport,
   PublishingTaskStatus,
 } from "../utils/api_types.ts";
 import { path } from "../utils/api.ts";
@@ -83,6 +84,23 @@ export default define.page<typeof handler>(function PackageListPage({
             </div>
           )}
 
+          {data.publishingTask.warnings.map((warning) => (
+            <div class="bg-yellow-100 rounded border-2 border-yellow-200 py-1.5 px-3 flex justify-between gap-3">
+              <div class="space-y-1.5">
+                <div class="font-bold text-xl">
+                  {warning.code}
+                </div>
+                <div>
+                  {warning.message}
+                </div>
+              </div>
+            </div>
+          ))}
+
+          {data.publishingTask.sizeReport && (
+            <SizeReport report={data.publishingTask.sizeReport} />
+          )}
+
           {data.publishingTask.status === "success" && (
             <p>
               <a
@@ -103,9 +121,61 @@ export default define.page<typeof handler>(function PackageListPage({
   );
 });
 
+function SizeReport({ report }: { report: PublishingTaskSizeReport }) {
+  return (
+    <div>
+      <p>
+        <span class="font-semibold">Unpacked size:</span>{" "}
+        {bytesToSize(report.unpackedSize)}
+        {report.unpackedSizeDelta !== null && (
+          <Delta value={report.unpackedSizeDelta} format={bytesToSize} />
+        )}
+      </p>
+      <p>
+        <span class="font-semibold">Files:</span> {report.fileCount}
+        {report.fileCountDelta !== null && (
+          <Delta value={report.fileCountDelta} format={String} />
+        )}
+      </p>
+      <p>
+        <span class="font-semibold">npm tarball size:</span>{" "}
+        {bytesToSize(report.npmTarballSize)}
+        {report.npmTarballSizeDelta !== null && (
+          <Delta value={report.npmTarballSizeDelta} format={bytesToSize} />
+        )}
+      </p>
+      {report.previousVersion && (
+        <p class="text-sm text-jsr-gray-500">
+          Compared to version {report.previousVersion}.
+        </p>
+      )}
+    </div>
+  );
+}
+
+function Delta(
+  { value, format }: { value: number; format: (value: number) => string },
+) {
+  if (value === 0) return null;
+  return (
+    <span class={value > 0 ? "text-red-600" : "text-green-600"}>
+      {" "}({value > 0 ? "+" : "-"}
+      {format(Math.abs(value))})
+    </span>
+  );
+}
+
+function bytesToSize(bytes: number) {
+  const sizes = ["B", "KB", "MB", "GB", "TB"];
+  if (bytes == 0) return "0 B";
+  const i = Math.floor(Math.log(bytes) / Math.log(1024));
+  return (bytes / Math.pow(1024, i)).toFixed(0) + " " + sizes[i];
+}
+
 export function StatusToIcon(status: PublishingTaskStatus) {
   switch (status) {
     case "pending":
+    case "scheduled":
     case "processing":
     case "processed":
       return <Pending class="size-6 stroke-blue-500 stroke-2" />;
@@ -17,10 +17,27 @@ export interface FullUser extends User {
   inviteCount: number;
 }
 
+export type TrustLevel = "new" | "established" | "trusted";
+
+export interface UserTrust {
+  level: TrustLevel;
+  scopeLimit: number;
+  newPackagesPerDayLimit: number | null;
+  newPackagesPerDayUsage: number;
+  publishAttemptsPerDayLimit: number | null;
+  publishAttemptsPerDayUsage: number;
+}
+
 export interface Scope {
   scope: string;
   updatedAt: string;
   createdAt: string;
+  profile?: ScopeProfile;
+}
+
+export interface ScopeProfile {
+  readme: string | null;
+  pinnedPackages: string[];
 }
 
 export interface FullScope extends Scope {
@@ -28,6 +45,15 @@ export interface FullScope extends Scope {
   quotas: ScopeQuota;
   ghActionsVerifyActor: boolean;
   requirePublishingFromCI: boolean;
+  validationLevel: ValidationLevel | null;
+  outdatedDependenciesEmail: boolean;
+  downloadAnomalyEmail: boolean;
+  docsCoveragePolicy: DocsCoveragePolicy;
+}
+
+export interface DocsCoveragePolicy {
+  minCoverage: number | null;
+  enforce: boolean;
 }
 
 export interface ScopeQuota {
@@ -47,6 +73,22 @@ export interface ScopeMember {
   createdAt: string;
 }
 
+export interface ScopeMetrics {
+  publishesPerWeek: { week: string; count: number }[];
+  packageDownloads: {
+    package: string;
+    downloads30d: number;
+    downloadsTotal: number;
+  }[];
+  activeTokens: number;
+  members: {
+    user: User;
+    isAdmin: boolean;
+    publishes30d: number;
+    lastPublishedAt: string | null;
+  }[];
+}
+
 export interface ScopeInvite {
   scope: string;
   targetUser: User;
@@ -55,8 +97,27 @@ export interface ScopeInvite {
   createdAt: string;
 }
 
+export interface ScopePublishFreeze {
+  id: string;
+  scope: string;
+  startsAt: string;
+  endsAt: string;
+  recursWeekly: boolean;
+  reason: string;
+  createdAt: string;
+}
+
+export interface ScopePublishIpRange {
+  id: string;
+  scope: string;
+  cidr: string;
+  description: string;
+  createdAt: string;
+}
+
 export type PublishingTaskStatus =
   | "pending"
+  | "scheduled"
   | "processing"
   | "processed"
   | "success"
@@ -70,14 +131,44 @@ export interface PublishingTask {
   packageScope: string;
   packageName: string;
   packageVersion: string;
+  sizeReport: PublishingTaskSizeReport | null;
+  warnings: { code: string; message: string }[];
+  artifactsExpireAt: string | null;
+  publishAt: string | null;
   createdAt: string;
   updatedAt: string;
 }
 
+export interface PublishingTaskSizeReport {
+  unpackedSize: number;
+  fileCount: number;
+  npmTarballSize: number;
+  previousVersion: string | null;
+  unpackedSizeDelta: number | null;
+  fileCountDelta: number | null;
+  npmTarballSizeDelta: number | null;
+}
+
+export interface PublishingTaskExplanation {
+  code: string;
+  message: string;
+  remediation: string[];
+  docsUrl: string | null;
+  files: { path: string; line: number | null; column: number | null }[];
+  configFile: string;
+  configFields: string[];
+  analysisVersions: {
+    denoAst: string;
+    denoGraph: string;
+    denoDoc: string;
+  };
+}
+
 export interface GithubRepository {
   id: number;
   owner: string;
   name: string;
+  subdirectory: string | null;
   updatedAt: string;
   createdAt: string;
 }
@@ -95,8 +186,11 @@ export interface PackageScore {
   hasReadmeExamples: boolean;
   allEntrypointsDocs: boolean;
   percentageDocumentedSymbols: number;
+  docsCoverage: Record<string, number>;
   allFastCheck: boolean;
   hasProvenance: boolean;
+  hasPermissionsDeclaration: boolean;
+  permissions: PackageVersionPermissions | null;
 
   // package specific
   hasDescription: boolean;
@@ -112,32 +206,116 @@ export interface Package {
   description: string;
   githubRepository: GithubRepository | null;
   runtimeCompat: RuntimeCompat;
+  validationLevel: ValidationLevel;
   updatedAt: string;
   createdAt: string;
   versionCount: number;
+  starCount: number;
   score: number | null;
   latestVersion: string | null;
   whenFeatured: string | null;
   isArchived: boolean;
+  noindex: boolean;
+  keywords: string[];
+  docsLandingPage: string | null;
+  successor: PackageSuccessor | null;
+  maintenanceStatus: MaintenanceStatus | null;
+  unmaintainedSuggestedAt: string | null;
+}
+
+export type MaintenanceStatus =
+  | "actively_maintained"
+  | "maintenance_only"
+  | "seeking_maintainers"
+  | "unmaintained";
+
+export interface PackageSuccessor {
+  scope: string;
+  name: string;
 }
 
+export type ValidationLevel = "full" | "lint_only" | "none";
+
+export type YankReasonCategory = "security" | "broken" | "accidental";
+
 export interface PackageVersion {
   scope: string;
   package: string;
   version: string;
   yanked: boolean;
+  yankReasonCategory: YankReasonCategory | null;
+  yankReason: string | null;
   usesNpm: boolean;
   newerVersionsCount: number;
   rekorLogId: string | null;
   readmePath: string;
+  engines: PackageVersionEngines;
+  keywords: string[];
+  permissions: PackageVersionPermissions | null;
+  unpackedSize: number;
+  fileCount: number;
+  npmTarballSize: number | null;
   updatedAt: string;
   createdAt: string;
 }
 
+export interface PackageVersionEngines {
+  deno?: string;
+  node?: string;
+  bun?: string;
+}
+
+export interface PackageVersionPermissions {
+  net?: true | string[];
+  env?: true | string[];
+  read?: true | string[];
+  write?: true | string[];
+  run?: true | string[];
+  sys?: true | string[];
+  ffi?: true | string[];
+}
+
 export interface PackageVersionWithUser extends PackageVersion {
   user?: User;
 }
 
+export interface PackageVersionPublishInfo {
+  scope: string;
+  package: string;
+  version: string;
+  publishingTaskId: string;
+  user: User | null;
+  githubRepository: GithubRepository | null;
+  githubWorkflowRun: GithubWorkflowRun | null;
+  provenance: PublishProvenance | null;
+  metaUrl: string;
+  queuedAt: string;
+  publishAt: string | null;
+  completedAt: string;
+  durationMs: number;
+}
+
+export interface GithubWorkflowRun {
+  repository: string;
+  workflowPath: string;
+  ref: string;
+  runId: number;
+  runUrl: string;
+}
+
+export interface PublishProvenance {
+  rekorLogId: string;
+  transparencyLogUrl: string;
+  sourceRepository: string | null;
+  sourceCommit: string | null;
+}
+
+export interface PackageVersionSummary {
+  version: string;
+  yanked: boolean;
+  createdAt: string;
+}
+
 export interface PackageVersionDocsContent {
   kind: "content";
   version: PackageVersionWithUser;
@@ -181,6 +359,7 @@ export interface PackageVersionSource {
   comrakCss: string;
   script: string;
   source: SourceDir | SourceFile;
+  repositoryUrl: string | null;
 }
 
 export interface Alias {
@@ -200,6 +379,7 @@ export interface Authorization {
 export type PermissionPackagePublishScope = {
   permission: "package/publish";
   scope: string;
+  bypassPublishFreezes?: boolean;
 };
 
 export type PermissionPackagePublishPackage = {
@@ -208,6 +388,12 @@ export type PermissionPackagePublishPackage = {
   package: string;
 };
 
+export type PermissionPackagePublishPackages = {
+  permission: "package/publish";
+  scope: string;
+  packages: string[];
+};
+
 export type PermissionPackagePublishVersion = {
   permission: "package/publish";
   scope: string;
@@ -219,13 +405,92 @@ export type PermissionPackagePublishVersion = {
 export type Permission =
   | PermissionPackagePublishScope
   | PermissionPackagePublishPackage
+  | PermissionPackagePublishPackages
   | PermissionPackagePublishVersion;
 
 export interface Dependency {
   kind: "jsr" | "npm";
+  type: "regular" | "peer" | "optional";
+  name: string;
+  constraint: string;
+  path: string;
+  isArchived: boolean;
+}
+
+export interface PublishedSpecifier {
+  referrer: string;
+  specifier: string;
+  resolved: string;
+  kind: "jsr" | "npm" | "node" | "bun";
+  importedNames: string[];
+}
+
+export interface DeprecatedImport {
+  scope: string;
+  package: string;
+  version: string;
+  export: string;
+  name: string;
+  message: string | null;
+  referrers: string[];
+}
+
+export interface PackageVersionArtifact {
+  filename: string;
+  size: number;
+  sha256: string;
+  uploadedAt: string | null;
+  createdAt: string;
+}
+
+export interface DependencyFreshness {
+  kind: "jsr" | "npm";
+  type: "regular" | "peer" | "optional";
   name: string;
   constraint: string;
   path: string;
+  latestVersion: string | null;
+  matchesLatest: boolean | null;
+  majorsBehind: number | null;
+  yanked: boolean;
+}
+
+export type LicenseClassification =
+  | "permissive"
+  | "weak_copyleft"
+  | "copyleft"
+  | "unknown";
+
+export interface LicenseReport {
+  packages: {
+    scope: string;
+    package: string;
+    version: string;
+    license: string | null;
+    classification: LicenseClassification;
+    flagged: boolean;
+    depth: number;
+  }[];
+  unresolved: {
+    kind: "jsr" | "npm";
+    name: string;
+    constraint: string;
+  }[];
+  truncated: boolean;
+}
+
+export interface Entrypoint {
+  name: string;
+  moduleCount: number;
+  totalBytes: number;
+  externalDependencyCount: number;
+  topLevelAwait: boolean;
+}
+
+export interface TypeScriptCompatibility {
+  version: string;
+  status: "compatible" | "incompatible" | "unknown";
+  unsupportedFeatures: string[];
 }
 
 export interface PackageVersionReference {
@@ -240,9 +505,24 @@ export interface Stats {
   featured: Package[];
 }
 
+export type ComponentStatus = "operational" | "degraded" | "majorOutage";
+
+export interface StatusComponent {
+  name: "publishing" | "docsGeneration" | "searchIndexing" | "npmBridge";
+  status: ComponentStatus;
+  backlog: number;
+  lagSeconds: number;
+}
+
+export interface RegistryStatus {
+  status: ComponentStatus;
+  components: StatusComponent[];
+}
+
 export interface List<T> {
   items: T[];
   total: number;
+  hasMore: boolean;
 }
 
 export interface Dependent {
@@ -255,18 +535,50 @@ export interface Dependent {
 export interface Token {
   id: string;
   description: string | null;
-  type: "web" | "device" | "personal";
+  type: "web" | "device" | "personal" | "scope";
   expiresAt: string | null;
   permissions: Permission[] | null;
   updatedAt: string;
   createdAt: string;
 }
 
+export interface UserExport {
+  id: string;
+  status: "pending" | "processing" | "success" | "failure";
+  expiresAt: string | null;
+  updatedAt: string;
+  createdAt: string;
+}
+
 export interface CreatedToken {
   token: Token;
   secret: string;
 }
 
+export interface ApiKey {
+  id: string;
+  name: string;
+  requestCount: number;
+  lastUsedAt: string | null;
+  updatedAt: string;
+  createdAt: string;
+}
+
+export interface CreatedApiKey {
+  apiKey: ApiKey;
+  secret: string;
+}
+
+export interface PackageCollection {
+  id: string;
+  userId: string;
+  name: string;
+  description: string;
+  packages: { scope: string; name: string }[];
+  updatedAt: string;
+  createdAt: string;
+}
+
 export interface DependencyGraphJsrEntrypoint {
   type: "entrypoint" | "path";
   value: string;
@@ -308,3 +620,10 @@ export interface DependencyGraphItem {
   size: number | undefined;
   mediaType: string | undefined;
 }
+
+export interface RenderedReadme {
+  html: string;
+  css: string;
+  comrakCss: string;
+  script: string;
+}
@@ -3,6 +3,7 @@ import { Docs, Source, State } from "../util.ts";
 import { APIResponse, path } from "./api.ts";
 import {
   FullScope,
+  List,
   Package,
   PackageVersionDocs,
   PackageVersionDocsRedirect,
@@ -197,6 +198,27 @@ export async function packageDataWithSource(
   };
 }
 
+/** Lists all versions of a package, fetching every page of the list. */
+export async function listPackageVersions(
+  state: State,
+  scope: string,
+  pkg: string,
+): Promise<PackageVersionWithUser[] | null> {
+  const versions: PackageVersionWithUser[] = [];
+  for (let page = 1;; page++) {
+    const resp = await state.api.get<List<PackageVersionWithUser>>(
+      path`/scopes/${scope}/packages/${pkg}/versions`,
+      { page, limit: 100 },
+    );
+    if (!resp.ok) {
+      if (resp.code === "packageNotFound") return null;
+      throw resp;
+    }
+    versions.push(...resp.data.items);
+    if (!resp.data.hasMore) return versions;
+  }
+}
+
 export async function scopeData(
   state: State,
   scope: string,
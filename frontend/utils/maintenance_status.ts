@@ -0,0 +1,9 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+import type { MaintenanceStatus } from "./api_types.ts";
+
+export const MAINTENANCE_STATUSES: Record<MaintenanceStatus, string> = {
+  actively_maintained: "Actively maintained",
+  maintenance_only: "Maintenance only",
+  seeking_maintainers: "Seeking maintainers",
+  unmaintained: "Unmaintained",
+};
@@ -0,0 +1,8 @@
+// Copyright 2024 the JSR authors. All rights reserved. MIT license.
+import type { YankReasonCategory } from "./api_types.ts";
+
+export const YANK_REASON_CATEGORIES: Record<YankReasonCategory, string> = {
+  security: "Security issue",
+  broken: "Broken",
+  accidental: "Published by accident",
+};
@@ -21,6 +21,42 @@ resource "google_storage_bucket" "publishing" {
   name          = "${var.gcp_project}-publishing"
   location      = "US"
   force_destroy = true
+
+  # User data exports are deleted by the user_exports task once their download
+  # link expires. This catches any that the task missed.
+  lifecycle_rule {
+    condition {
+      age            = 14
+      matches_prefix = ["user_exports/"]
+    }
+    action {
+      type = "Delete"
+    }
+  }
+
+  # The tarballs of versions deleted by staff are only read if the version is
+  # restored, so they are moved to nearline storage. They are deleted by the
+  # publish_artifacts_cleanup task after 30 days, which is the minimum storage
+  # duration of nearline storage, and this catches any that the task missed.
+  lifecycle_rule {
+    condition {
+      age            = 1
+      matches_prefix = ["deleted_versions/"]
+    }
+    action {
+      type          = "SetStorageClass"
+      storage_class = "NEARLINE"
+    }
+  }
+  lifecycle_rule {
+    condition {
+      age            = 45
+      matches_prefix = ["deleted_versions/"]
+    }
+    action {
+      type = "Delete"
+    }
+  }
 }
 
 resource "google_storage_bucket" "docs" {
@@ -29,6 +65,14 @@ resource "google_storage_bucket" "docs" {
   force_destroy = true
 }
 
+# Artifacts that maintainers attach to package versions. They are served by the
+# API, so the bucket is not public.
+resource "google_storage_bucket" "artifacts" {
+  name          = "${var.gcp_project}-artifacts"
+  location      = "US"
+  force_destroy = true
+}
+
 resource "google_storage_bucket" "npm" {
   name          = "${var.gcp_project}-npm"
   location      = "US"
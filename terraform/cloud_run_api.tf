@@ -7,6 +7,7 @@ locals {
     "MODULES_BUCKET"    = google_storage_bucket.modules.name
     "DOCS_BUCKET"       = google_storage_bucket.docs.name
     "NPM_BUCKET"        = google_storage_bucket.npm.name
+    "ARTIFACTS_BUCKET"  = google_storage_bucket.artifacts.name
 
     "METADATA_STRATEGY" = "instance_metadata"
 
@@ -304,6 +305,12 @@ resource "google_storage_bucket_iam_member" "npm_bucket_access" {
   member = "serviceAccount:${google_service_account.registry_api.email}"
 }
 
+resource "google_storage_bucket_iam_member" "artifacts_bucket_access" {
+  bucket = google_storage_bucket.artifacts.name
+  role   = "roles/storage.objectAdmin"
+  member = "serviceAccount:${google_service_account.registry_api.email}"
+}
+
 resource "google_secret_manager_secret_iam_member" "github_client_secret" {
   secret_id = google_secret_manager_secret.github_client_secret.id
   role      = "roles/secretmanager.secretAccessor"
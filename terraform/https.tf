@@ -91,6 +91,47 @@ resource "google_compute_url_map" "frontend_https" {
       service = google_compute_backend_bucket.npm.self_link
     }
 
+    # Version manifests and tarballs are served by the API. It redirects the
+    # manifests of the old names of renamed or moved packages to their new
+    # names, builds tarballs of the current revision that were not built yet on
+    # demand, and falls back to previous revisions. Built tarballs are
+    # immutable, so the CDN of the API backend serves repeated downloads.
+    path_rule {
+      paths = ["/@jsr/*"]
+      route_action {
+        url_rewrite {
+          path_prefix_rewrite = "/api/npm/@jsr/"
+        }
+        cors_policy {
+          allow_methods     = ["HEAD", "GET"]
+          allow_credentials = false
+          expose_headers    = ["*"]
+          allow_origins     = ["*"]
+          allow_headers     = ["Authorization", "X-Cloud-Trace-Context"]
+          max_age           = 3600
+        }
+      }
+      service = google_compute_backend_service.registry_api.self_link
+    }
+
+    path_rule {
+      paths = ["/~/*"]
+      route_action {
+        url_rewrite {
+          path_prefix_rewrite = "/api/npm/~/"
+        }
+        cors_policy {
+          allow_methods     = ["HEAD", "GET"]
+          allow_credentials = false
+          expose_headers    = ["*"]
+          allow_origins     = ["*"]
+          allow_headers     = ["Authorization", "X-Cloud-Trace-Context"]
+          max_age           = 3600
+        }
+      }
+      service = google_compute_backend_service.registry_api.self_link
+    }
+
     default_service = google_compute_backend_bucket.npm.self_link
     default_route_action {
       cors_policy {
@@ -150,6 +191,12 @@ resource "google_compute_url_map" "frontend_https" {
       match_rules {
         prefix_match = "/api/"
       }
+      match_rules {
+        full_path_match = "/robots.txt"
+      }
+      match_rules {
+        full_path_match = "/.well-known/jsr-metadata-keys.json"
+      }
       match_rules {
         full_path_match = "/sitemap.xml"
       }
@@ -159,6 +206,9 @@ resource "google_compute_url_map" "frontend_https" {
       match_rules {
         full_path_match = "/sitemap-packages.xml"
       }
+      match_rules {
+        regex_match = "^/badges/[a-z0-9-]+/[a-z0-9-]+/(version|downloads|score)\\.svg$"
+      }
       match_rules {
         full_path_match = "/login"
       }
@@ -214,8 +264,35 @@ resource "google_compute_url_map" "frontend_https" {
       }
     }
 
+    # The metadata files of packages are served by the API, which serves them
+    # from the modules bucket, and redirects the old names of renamed or moved
+    # packages to their new names.
     route_rules {
       priority = 3
+      service  = google_compute_backend_service.registry_api.self_link
+      route_action {
+        cors_policy {
+          allow_methods     = ["HEAD", "GET"]
+          allow_credentials = false
+          expose_headers    = ["*"]
+          allow_origins     = ["*"]
+          allow_headers     = ["Authorization", "X-Cloud-Trace-Context"]
+          max_age           = 3600
+        }
+      }
+
+      match_rules {
+        regex_match = "^/@[a-z0-9-]+/[a-z0-9-]+/(meta|[^/]+_meta)\\.json(\\.sig)?$"
+        header_matches {
+          header_name  = "Accept"
+          invert_match = true
+          prefix_match = "text/html"
+        }
+      }
+    }
+
+    route_rules {
+      priority = 4
       service  = google_compute_backend_bucket.modules.self_link
       route_action {
         cors_policy {
@@ -29,6 +29,171 @@ resource "google_cloud_scheduler_job" "scrape_download_counts" {
   }
 }
 
+resource "google_cloud_scheduler_job" "export_analytics" {
+  name        = "export-analytics"
+  description = "Export download counts and publish events to the configured analytics sinks."
+  schedule    = "45 * * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/export_analytics?intervalHrs=12"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "user_exports" {
+  name        = "user-exports"
+  description = "Process requested exports of user data, and delete expired ones."
+  schedule    = "*/5 * * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/user_exports"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "scheduled_publishes" {
+  name        = "scheduled-publishes"
+  description = "Publish the versions of scheduled publishes whose time has come."
+  schedule    = "* * * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/scheduled_publishes"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "search_index" {
+  name        = "search-index"
+  description = "Send pending package changes to the Orama package index."
+  schedule    = "* * * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/search_index"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "consistency_audit" {
+  name        = "consistency-audit"
+  description = "Validate cross-table invariants of the database, and report findings to admins."
+  schedule    = "0 2 * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/consistency_audit"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "reconcile_dependent_counts" {
+  name        = "reconcile-dependent-counts"
+  description = "Correct package dependent counts that drifted because of concurrent publishes."
+  schedule    = "30 4 * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/reconcile_dependent_counts"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "publish_artifacts_cleanup" {
+  name        = "publish-artifacts-cleanup"
+  description = "Delete the retained tarballs and diagnostic logs of failed publishes after 7 days, and the retained tarballs of deleted versions after 30 days."
+  schedule    = "30 3 * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/publish_artifacts_cleanup"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "suggest_unmaintained" {
+  name        = "suggest-unmaintained"
+  description = "Ask maintainers of long inactive packages to confirm whether they are unmaintained."
+  schedule    = "0 4 * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/suggest_unmaintained"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "outdated_dependencies_email" {
+  name        = "outdated-dependencies-email"
+  description = "Email scope admins that opted in about packages with outdated dependencies."
+  schedule    = "0 14 * * 1"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/outdated_dependencies_email"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "download_anomalies" {
+  name        = "download-anomalies"
+  description = "Detect download spikes and cliffs of the previous day, and email scope admins that opted in."
+  schedule    = "0 6 * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/download_anomalies"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
+resource "google_cloud_scheduler_job" "check_links" {
+  name        = "check-links"
+  description = "Check the external links in the READMEs and docs of a small batch of popular packages"
+  schedule    = "15 * * * *"
+  region      = "us-central1"
+
+  http_target {
+    http_method = "POST"
+    uri         = "${google_cloud_run_v2_service.registry_api_tasks.uri}/tasks/check_links"
+    oidc_token {
+      service_account_email = google_service_account.task_dispatcher.email
+    }
+  }
+}
+
 resource "google_cloud_scheduler_job" "orama_package_deploy" {
   name        = "orama-package-deploy"
   description = "Deploy the package Orama index with any new changes"
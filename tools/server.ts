@@ -29,7 +29,14 @@ async function createBucket(name: string) {
 
 const bucketCreationInterval = setInterval(async () => {
   let allBucketsCreated = true;
-  for (const bucket of [MODULES_BUCKET, "docs", "publishing", NPM_BUCKET]) {
+  const buckets = [
+    MODULES_BUCKET,
+    "docs",
+    "publishing",
+    NPM_BUCKET,
+    "artifacts",
+  ];
+  for (const bucket of buckets) {
     allBucketsCreated &&= await createBucket(bucket);
   }
   if (allBucketsCreated) {